@@ -1,8 +1,11 @@
 //! CLI argument parsing for GBA CLI.
 
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use crate::output::OutputMode;
+
 /// GBA CLI - GeekTime Bootcamp Agent
 ///
 /// A CLI tool that wraps the Claude Agent SDK for adding functionality around repositories.
@@ -17,9 +20,27 @@ pub struct Args {
     #[arg(short, long, default_value = ".")]
     pub path: PathBuf,
 
+    /// Member project of the workspace containing the path to run the
+    /// command in, as listed in `.gba/workspace.yml`.
+    #[arg(long)]
+    pub project: Option<String>,
+
     /// Verbose output.
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "quiet")]
     pub verbose: bool,
+
+    /// Only print results, warnings and errors.
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Output format: `json` writes a JSON object per line to stdout.
+    #[arg(long, value_enum, global = true, default_value_t = OutputMode::Text)]
+    pub output: OutputMode,
+
+    /// Never prompt for input, e.g. in CI: template variables left unset
+    /// take their default or fail if required.
+    #[arg(long, global = true)]
+    pub no_interactive: bool,
 }
 
 /// Available subcommands.
@@ -36,6 +57,48 @@ pub enum Command {
 
     /// Execute a single prompt.
     Prompt(PromptArgs),
+
+    /// Run two prompt templates against the same context and compare them.
+    Compare(CompareArgs),
+
+    /// Show the status of the features of the project, or of every member
+    /// of the workspace.
+    Status(StatusArgs),
+
+    /// Break down the spend recorded in the cost ledger.
+    #[command(visible_alias = "costs")]
+    Cost(CostArgs),
+
+    /// Show the changes of a feature against the main branch.
+    Diff(DiffArgs),
+
+    /// Manage feature worktrees.
+    #[command(subcommand)]
+    Worktree(WorktreeCommand),
+
+    /// Manage feature state files.
+    #[command(subcommand)]
+    State(StateCommand),
+
+    /// Inspect prompt templates.
+    #[command(subcommand)]
+    Templates(TemplatesCommand),
+
+    /// Check the configuration and the templates, reporting every problem.
+    Validate,
+
+    /// Bring a feature branch up to date with the main branch.
+    Merge(MergeArgs),
+
+    /// Re-render a recorded run from its transcript.
+    Replay(ReplayArgs),
+
+    /// Review a feature with the configured reviewer personas.
+    Review(ReviewArgs),
+
+    /// Serve a Slack bot that plans features and reports runs.
+    #[cfg(feature = "slack")]
+    Serve(ServeArgs),
 }
 
 /// Arguments for the init subcommand.
@@ -52,15 +115,50 @@ pub struct InitArgs {
     /// Repository URL.
     #[arg(short, long)]
     pub repo_url: Option<String>,
+
+    /// Write an agent guidance file from the project's configuration and
+    /// layout, `CLAUDE.md` by default (e.g. `AGENTS.md`).
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        default_missing_value = "CLAUDE.md"
+    )]
+    pub write_agent_docs: Option<PathBuf>,
+
+    /// Import the settings of an existing Claude Code setup (`.claude/`,
+    /// `.mcp.json`) into the configuration, and report what was migrated.
+    #[arg(long)]
+    pub from_existing: bool,
 }
 
 /// Arguments for the run subcommand.
-#[derive(Debug, clap::Args)]
+#[derive(Debug, Clone, clap::Args)]
 pub struct RunArgs {
     /// Feature name to work on.
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        required_unless_present = "features",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub feature: String,
 
+    /// Features to work on concurrently, comma-separated, each in its own
+    /// worktree and agent session.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "FEATURES",
+        conflicts_with_all = ["feature", "tui", "dry_run", "auto_stash", "events"]
+    )]
+    pub features: Vec<String>,
+
+    /// Maximum number of features running at once with `--features`.
+    #[arg(short, long, default_value_t = 4)]
+    pub jobs: usize,
+
     /// Task kind.
     #[arg(short, long)]
     pub kind: TaskKind,
@@ -76,10 +174,41 @@ pub struct RunArgs {
     /// Resume from previous state.
     #[arg(long)]
     pub resume: bool,
+
+    /// Run even if the working tree has uncommitted changes.
+    #[arg(long, conflicts_with = "auto_stash")]
+    pub force: bool,
+
+    /// Stash uncommitted changes before the run and restore them afterwards.
+    #[arg(long)]
+    pub auto_stash: bool,
+
+    /// Commit the worktree's changes after a successful implementation.
+    #[arg(long)]
+    pub commit: bool,
+
+    /// Start the run even if a project quota has been reached.
+    #[arg(long)]
+    pub override_quota: bool,
+
+    /// Tag the run for cost attribution, e.g. `sprint-42` or
+    /// `team:payments`, in addition to the project's tags. Repeatable.
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Write the run's events to stdout as JSON lines, for IDE plugins and
+    /// wrappers; everything else goes to stderr.
+    #[arg(long, conflicts_with_all = ["tui", "dry_run"])]
+    pub events: bool,
+
+    /// Show the rendered prompt, the template's settings and the estimated
+    /// tokens, then exit without contacting the API.
+    #[arg(long, conflicts_with_all = ["tui", "commit"])]
+    pub dry_run: bool,
 }
 
 /// Task kind for execution.
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum TaskKind {
     /// Create an implementation plan.
     Planning,
@@ -89,6 +218,9 @@ pub enum TaskKind {
 
     /// Verify the implementation.
     Verification,
+
+    /// Plan, implement and verify, one phase after the other.
+    All,
 }
 
 impl std::fmt::Display for TaskKind {
@@ -97,20 +229,35 @@ impl std::fmt::Display for TaskKind {
             Self::Planning => write!(f, "planning"),
             Self::Implementation => write!(f, "implementation"),
             Self::Verification => write!(f, "verification"),
+            Self::All => write!(f, "all"),
         }
     }
 }
 
 impl TaskKind {
     /// Get the template name for this task kind.
+    ///
+    /// `All` runs each phase with its own template; it starts with the
+    /// planning template.
     #[must_use]
     pub const fn template_name(&self) -> &str {
         match self {
-            Self::Planning => "plan",
+            Self::Planning | Self::All => "plan",
             Self::Implementation => "implement",
             Self::Verification => "verify",
         }
     }
+
+    /// Get the phases a run of this task kind goes through, in order.
+    #[must_use]
+    pub const fn phases(&self) -> &'static [Self] {
+        match self {
+            Self::Planning => &[Self::Planning],
+            Self::Implementation => &[Self::Implementation],
+            Self::Verification => &[Self::Verification],
+            Self::All => &[Self::Planning, Self::Implementation, Self::Verification],
+        }
+    }
 }
 
 /// Arguments for the list-prompts subcommand.
@@ -131,6 +278,328 @@ pub struct PromptArgs {
     /// User message.
     #[arg(short, long)]
     pub message: String,
+
+    /// Run the prompt even if a project quota has been reached.
+    #[arg(long)]
+    pub override_quota: bool,
+}
+
+/// Arguments for the compare subcommand.
+#[derive(Debug, clap::Args)]
+pub struct CompareArgs {
+    /// First template, e.g. `plan`.
+    #[arg(long)]
+    pub template_a: String,
+
+    /// Second template, e.g. `plan_v2`.
+    #[arg(long)]
+    pub template_b: String,
+
+    /// Feature name to render the templates for.
+    #[arg(short, long)]
+    pub feature: String,
+
+    /// Feature description.
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Model to run both prompts with instead of the configured one, e.g. a
+    /// cheaper model.
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// Start the comparison even if a project quota has been reached.
+    #[arg(long)]
+    pub override_quota: bool,
+}
+
+/// Arguments for the cost subcommand.
+#[derive(Debug, clap::Args)]
+pub struct CostArgs {
+    /// Group the spend by feature, task kind, model, tag or day.
+    #[arg(long, value_enum, default_value_t = CostGroupBy::Feature)]
+    pub group_by: CostGroupBy,
+
+    /// Only count runs since a date, e.g. `2026-02-01`, or a time ago, e.g.
+    /// `7d`, `12h` or `2w`.
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Print the breakdown as a JSON document.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Parse the start of a `--since` period: a UTC date, or a number of hours,
+/// days or weeks before now.
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    let invalid = || format!("invalid time `{value}`, expected e.g. 2026-02-01 or 7d");
+    let (count, unit) = value.split_at(value.len().saturating_sub(1));
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let ago = match unit {
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        "w" => Duration::try_weeks(count),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+    Ok(Utc::now() - ago)
+}
+
+/// Key the spend is grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CostGroupBy {
+    /// Feature name.
+    Feature,
+
+    /// Task kind.
+    Kind,
+
+    /// Model.
+    Model,
+
+    /// Cost attribution tag; a run with several tags counts towards each.
+    Tag,
+
+    /// Day the run finished, in UTC.
+    Day,
+}
+
+/// Arguments for the status subcommand.
+#[derive(Debug, clap::Args)]
+pub struct StatusArgs {
+    /// Only show this feature, by name or identifier.
+    pub feature: Option<String>,
+
+    /// Print the status as a JSON document.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the diff subcommand.
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// Feature name.
+    #[arg(short, long)]
+    pub feature: String,
+
+    /// Only include these paths.
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Exclude these paths.
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Maximum diff size in bytes (0 for unlimited).
+    #[arg(long, default_value_t = 0)]
+    pub max_bytes: usize,
+
+    /// Show binary files as git's notice instead of leaving them out.
+    #[arg(long)]
+    pub binary: bool,
+}
+
+/// Worktree subcommands.
+#[derive(Debug, Subcommand)]
+pub enum WorktreeCommand {
+    /// List feature worktrees.
+    List,
+
+    /// Remove merged or abandoned feature worktrees.
+    Prune(PruneArgs),
+}
+
+/// Arguments for the worktree prune subcommand.
+#[derive(Debug, clap::Args)]
+pub struct PruneArgs {
+    /// Only show what would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Also delete the unmerged branches of abandoned worktrees, losing the
+    /// work committed to them.
+    #[arg(long)]
+    pub delete_unmerged: bool,
+}
+
+/// State subcommands.
+#[derive(Debug, Subcommand)]
+pub enum StateCommand {
+    /// Validate every feature state against the current schema and the
+    /// feature's artifacts.
+    Check(StateCheckArgs),
+}
+
+/// Arguments for the state check subcommand.
+#[derive(Debug, clap::Args)]
+pub struct StateCheckArgs {
+    /// Repair recoverable issues, e.g. re-derive usage totals from the cost
+    /// ledger.
+    #[arg(long)]
+    pub fix: bool,
+}
+
+/// Templates subcommands.
+#[derive(Debug, Subcommand)]
+pub enum TemplatesCommand {
+    /// List the variables a template references and whether the standard
+    /// context provides them.
+    Vars(TemplateVarsArgs),
+
+    /// Create a template in the templates directory with a valid front
+    /// matter.
+    New(TemplateNewArgs),
+
+    /// Show the template a name resolves to, local or bundled, with its
+    /// configuration and the variables it references.
+    Show(TemplateShowArgs),
+}
+
+/// Arguments for the templates vars subcommand.
+#[derive(Debug, clap::Args)]
+pub struct TemplateVarsArgs {
+    /// Template name, e.g. `plan`.
+    pub name: String,
+}
+
+/// Arguments for the templates new subcommand.
+#[derive(Debug, clap::Args)]
+pub struct TemplateNewArgs {
+    /// Template name, e.g. `security-audit`.
+    pub name: String,
+
+    /// Start from a copy of a bundled template, e.g. `plan`, instead of an
+    /// empty one.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub from: Option<String>,
+
+    /// Overwrite an existing template.
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Arguments for the templates show subcommand.
+#[derive(Debug, clap::Args)]
+pub struct TemplateShowArgs {
+    /// Template name, e.g. `plan`.
+    pub name: String,
+}
+
+/// Arguments for the merge subcommand.
+#[derive(Debug, clap::Args)]
+pub struct MergeArgs {
+    /// Feature name.
+    pub feature: String,
+
+    /// How to integrate the main branch.
+    #[arg(long, value_enum, default_value_t = MergeStrategy::Rebase)]
+    pub strategy: MergeStrategy,
+
+    /// Continue after resolving conflicts.
+    #[arg(long = "continue", conflicts_with = "abort")]
+    pub continue_merge: bool,
+
+    /// Abort a rebase or merge stopped on conflicts.
+    #[arg(long)]
+    pub abort: bool,
+
+    /// Skip the verification run after conflicts are resolved.
+    #[arg(long)]
+    pub no_verify: bool,
+}
+
+/// Arguments for the replay subcommand.
+#[derive(Debug, clap::Args)]
+pub struct ReplayArgs {
+    /// Transcript file, e.g. `.gba/features/0001/transcripts/<run-id>.jsonl`.
+    pub transcript: PathBuf,
+
+    /// Replay in the TUI.
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Print agent text as raw markdown instead of rendering it.
+    #[arg(long, conflicts_with = "tui")]
+    pub raw: bool,
+}
+
+/// Arguments for the review subcommand.
+///
+/// Posting to a pull request needs the `github` feature and reads the token
+/// from `GITHUB_TOKEN`.
+#[derive(Debug, clap::Args)]
+pub struct ReviewArgs {
+    /// Feature name to review.
+    #[arg(short, long)]
+    pub feature: String,
+
+    /// Post the findings as review comments on this GitHub pull request.
+    #[cfg(feature = "github")]
+    #[arg(long, value_name = "NUMBER")]
+    pub post_to_pr: Option<u64>,
+
+    /// Repository of the pull request, `owner/name` (defaults to the
+    /// `origin` remote).
+    #[cfg(feature = "github")]
+    #[arg(long, requires = "post_to_pr")]
+    pub repo: Option<String>,
+
+    /// Show the comments that would be posted without posting them.
+    #[cfg(feature = "github")]
+    #[arg(long, requires = "post_to_pr")]
+    pub dry_run: bool,
+
+    /// Fail when the review has findings of this severity or a more severe
+    /// one, or a persona's review failed.
+    #[arg(long, value_enum, value_name = "SEVERITY")]
+    pub fail_on: Option<ReviewSeverity>,
+
+    /// Start the review even if a project quota has been reached.
+    #[arg(long)]
+    pub override_quota: bool,
+}
+
+/// Severity of review findings failing `gba review --fail-on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReviewSeverity {
+    /// Any finding.
+    Minor,
+
+    /// Important and critical findings.
+    Important,
+
+    /// Critical findings.
+    Critical,
+}
+
+/// Arguments for the serve subcommand.
+///
+/// The Slack app's credentials are read from `SLACK_SIGNING_SECRET` and
+/// `SLACK_BOT_TOKEN`.
+#[cfg(feature = "slack")]
+#[derive(Debug, clap::Args)]
+pub struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:3000")]
+    pub bind: std::net::SocketAddr,
+
+    /// Seconds between progress updates posted while a run is going.
+    #[arg(long, default_value_t = 60)]
+    pub progress_interval: u64,
+}
+
+/// Strategy for integrating the main branch into a feature branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MergeStrategy {
+    /// Rebase the feature branch onto the main branch.
+    Rebase,
+
+    /// Merge the main branch into the feature branch.
+    Merge,
 }
 
 #[cfg(test)]
@@ -153,11 +622,337 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_init_agent_docs_args_parsing() {
+        let args = Args::try_parse_from(["gba", "init", "--write-agent-docs"]).unwrap();
+        let Command::Init(init) = args.command else {
+            panic!("expected init command");
+        };
+        assert_eq!(init.write_agent_docs, Some(PathBuf::from("CLAUDE.md")));
+
+        let args =
+            Args::try_parse_from(["gba", "init", "--write-agent-docs", "AGENTS.md"]).unwrap();
+        let Command::Init(init) = args.command else {
+            panic!("expected init command");
+        };
+        assert_eq!(init.write_agent_docs, Some(PathBuf::from("AGENTS.md")));
+        assert!(!init.from_existing);
+
+        let args = Args::try_parse_from(["gba", "init", "--from-existing"]).unwrap();
+        let Command::Init(init) = args.command else {
+            panic!("expected init command");
+        };
+        assert!(init.from_existing);
+    }
+
+    #[test]
+    fn test_project_args_parsing() {
+        let args = Args::try_parse_from(["gba", "--project", "web", "status"]).unwrap();
+        assert_eq!(args.project.as_deref(), Some("web"));
+        assert!(matches!(args.command, Command::Status(_)));
+
+        let args = Args::try_parse_from(["gba", "status"]).unwrap();
+        assert!(args.project.is_none());
+        let Command::Status(status) = args.command else {
+            panic!("expected status command");
+        };
+        assert!(status.feature.is_none());
+        assert!(!status.json);
+
+        let args = Args::try_parse_from(["gba", "status", "add-auth", "--json"]).unwrap();
+        let Command::Status(status) = args.command else {
+            panic!("expected status command");
+        };
+        assert_eq!(status.feature.as_deref(), Some("add-auth"));
+        assert!(status.json);
+    }
+
+    #[test]
+    fn test_output_args_parsing() {
+        let args = Args::try_parse_from(["gba", "status"]).unwrap();
+        assert_eq!(args.output, OutputMode::Text);
+
+        // The flag is accepted after the subcommand too
+        let args = Args::try_parse_from(["gba", "status", "--output", "json"]).unwrap();
+        assert_eq!(args.output, OutputMode::Json);
+        assert!(Args::try_parse_from(["gba", "--output", "yaml", "status"]).is_err());
+    }
+
+    #[test]
+    fn test_no_interactive_args_parsing() {
+        let args = Args::try_parse_from(["gba", "status"]).unwrap();
+        assert!(!args.no_interactive);
+
+        let args = Args::try_parse_from([
+            "gba",
+            "run",
+            "-f",
+            "add-auth",
+            "-k",
+            "planning",
+            "--no-interactive",
+        ])
+        .unwrap();
+        assert!(args.no_interactive);
+    }
+
+    #[test]
+    fn test_state_check_args_parsing() {
+        let args = Args::try_parse_from(["gba", "state", "check", "--fix"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Command::State(StateCommand::Check(StateCheckArgs { fix: true }))
+        ));
+    }
+
+    #[test]
+    fn test_templates_vars_args_parsing() {
+        let args = Args::try_parse_from(["gba", "templates", "vars", "plan"]).unwrap();
+        match args.command {
+            Command::Templates(TemplatesCommand::Vars(args)) => assert_eq!(args.name, "plan"),
+            _ => panic!("Expected templates vars command"),
+        }
+        assert!(Args::try_parse_from(["gba", "templates", "vars"]).is_err());
+    }
+
+    #[test]
+    fn test_templates_new_args_parsing() {
+        let args =
+            Args::try_parse_from(["gba", "templates", "new", "audit", "--from", "review"]).unwrap();
+        match args.command {
+            Command::Templates(TemplatesCommand::New(args)) => {
+                assert_eq!(args.name, "audit");
+                assert_eq!(args.from.as_deref(), Some("review"));
+                assert!(!args.force);
+            }
+            _ => panic!("Expected templates new command"),
+        }
+        let args = Args::try_parse_from(["gba", "templates", "show", "plan"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Command::Templates(TemplatesCommand::Show(TemplateShowArgs { name })) if name == "plan"
+        ));
+    }
+
+    #[test]
+    fn test_review_args_parsing() {
+        let args = Args::try_parse_from(["gba", "review", "-f", "auth", "--fail-on", "important"])
+            .unwrap();
+        let Command::Review(review) = args.command else {
+            panic!("expected review command");
+        };
+        assert_eq!(review.feature, "auth");
+        assert_eq!(review.fail_on, Some(ReviewSeverity::Important));
+        assert!(!review.override_quota);
+
+        let args =
+            Args::try_parse_from(["gba", "review", "-f", "auth", "--override-quota"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Command::Review(ReviewArgs {
+                override_quota: true,
+                ..
+            })
+        ));
+
+        let posted = Args::try_parse_from(["gba", "review", "-f", "auth", "--post-to-pr", "7"]);
+        #[cfg(feature = "github")]
+        assert!(matches!(
+            posted.unwrap().command,
+            Command::Review(ReviewArgs {
+                post_to_pr: Some(7),
+                ..
+            })
+        ));
+        #[cfg(not(feature = "github"))]
+        assert!(posted.is_err());
+    }
+
+    #[test]
+    fn test_worktree_prune_args_parsing() {
+        let args = Args::try_parse_from(["gba", "worktree", "prune", "--dry-run"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Command::Worktree(WorktreeCommand::Prune(PruneArgs {
+                dry_run: true,
+                delete_unmerged: false
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_run_dry_run_args_parsing() {
+        let args =
+            Args::try_parse_from(["gba", "run", "-f", "auth", "-k", "planning", "--dry-run"])
+                .unwrap();
+        let Command::Run(run) = args.command else {
+            panic!("expected run command");
+        };
+        assert!(run.dry_run);
+
+        assert!(
+            Args::try_parse_from([
+                "gba",
+                "run",
+                "-f",
+                "auth",
+                "-k",
+                "planning",
+                "--dry-run",
+                "--tui",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_tag_and_cost_args_parsing() {
+        let args = Args::try_parse_from([
+            "gba",
+            "run",
+            "-f",
+            "add-auth",
+            "-k",
+            "planning",
+            "--tag",
+            "sprint-42",
+            "--tag",
+            "team:payments",
+        ])
+        .unwrap();
+        let Command::Run(run) = args.command else {
+            panic!("expected run command");
+        };
+        assert_eq!(run.tags, ["sprint-42", "team:payments"]);
+
+        let args = Args::try_parse_from(["gba", "cost", "--group-by", "tag"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Command::Cost(CostArgs {
+                group_by: CostGroupBy::Tag,
+                since: None,
+                json: false,
+            })
+        ));
+
+        let args = Args::try_parse_from([
+            "gba",
+            "costs",
+            "--group-by",
+            "day",
+            "--since",
+            "2026-02-01",
+            "--json",
+        ])
+        .unwrap();
+        let Command::Cost(cost) = args.command else {
+            panic!("expected cost command");
+        };
+        assert_eq!(cost.group_by, CostGroupBy::Day);
+        assert_eq!(
+            cost.since.unwrap().to_rfc3339(),
+            "2026-02-01T00:00:00+00:00"
+        );
+        assert!(cost.json);
+
+        let week_ago = parse_since("1w").unwrap();
+        assert!(
+            (Utc::now() - week_ago - Duration::weeks(1))
+                .num_seconds()
+                .abs()
+                < 5
+        );
+        assert!(parse_since("7x").is_err());
+        assert!(parse_since("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_run_features_args_parsing() {
+        let args = Args::try_parse_from([
+            "gba",
+            "run",
+            "--features",
+            "add-auth,add-billing",
+            "-j",
+            "2",
+            "-k",
+            "implementation",
+        ])
+        .unwrap();
+        let Command::Run(run) = args.command else {
+            panic!("expected run command");
+        };
+        assert_eq!(run.features, ["add-auth", "add-billing"]);
+        assert_eq!(run.jobs, 2);
+        assert!(run.feature.is_empty());
+
+        assert!(Args::try_parse_from(["gba", "run", "-k", "planning"]).is_err());
+        for conflict in [&["-f", "add-auth"][..], &["--tui"], &["--auto-stash"]] {
+            let mut argv = vec!["gba", "run", "--features", "a,b", "-k", "planning"];
+            argv.extend(conflict);
+            assert!(Args::try_parse_from(argv).is_err());
+        }
+    }
+
+    #[test]
+    fn test_run_events_args_parsing() {
+        let args =
+            Args::try_parse_from(["gba", "run", "-f", "add-auth", "-k", "planning", "--events"])
+                .unwrap();
+        assert!(matches!(
+            args.command,
+            Command::Run(RunArgs { events: true, .. })
+        ));
+
+        for conflict in ["--tui", "--dry-run"] {
+            assert!(
+                Args::try_parse_from([
+                    "gba", "run", "-f", "add-auth", "-k", "planning", "--events", conflict
+                ])
+                .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_args_parsing() {
+        let args = Args::try_parse_from(["gba", "merge", "add-auth"]).unwrap();
+        let Command::Merge(merge) = args.command else {
+            panic!("expected merge command");
+        };
+        assert_eq!(merge.feature, "add-auth");
+        assert_eq!(merge.strategy, MergeStrategy::Rebase);
+        assert!(!merge.continue_merge);
+
+        let args = Args::try_parse_from([
+            "gba",
+            "merge",
+            "add-auth",
+            "--strategy",
+            "merge",
+            "--continue",
+        ])
+        .unwrap();
+        assert!(matches!(
+            args.command,
+            Command::Merge(MergeArgs {
+                strategy: MergeStrategy::Merge,
+                continue_merge: true,
+                ..
+            })
+        ));
+
+        assert!(
+            Args::try_parse_from(["gba", "merge", "add-auth", "--continue", "--abort"]).is_err()
+        );
+    }
+
     #[test]
     fn test_task_kind_display() {
         assert_eq!(TaskKind::Planning.to_string(), "planning");
         assert_eq!(TaskKind::Implementation.to_string(), "implementation");
         assert_eq!(TaskKind::Verification.to_string(), "verification");
+        assert_eq!(TaskKind::All.to_string(), "all");
     }
 
     #[test]
@@ -166,4 +961,56 @@ mod tests {
         assert_eq!(TaskKind::Implementation.template_name(), "implement");
         assert_eq!(TaskKind::Verification.template_name(), "verify");
     }
+
+    #[test]
+    fn test_task_kind_phases() {
+        assert_eq!(TaskKind::Planning.phases(), &[TaskKind::Planning]);
+        assert_eq!(
+            TaskKind::All.phases(),
+            &[
+                TaskKind::Planning,
+                TaskKind::Implementation,
+                TaskKind::Verification
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_args_parsing() {
+        let args = Args::try_parse_from([
+            "gba",
+            "compare",
+            "--template-a",
+            "plan",
+            "--template-b",
+            "plan_v2",
+            "--feature",
+            "add-auth",
+            "--model",
+            "haiku",
+        ])
+        .unwrap();
+        let Command::Compare(compare) = args.command else {
+            panic!("expected compare command");
+        };
+        assert_eq!(compare.template_a, "plan");
+        assert_eq!(compare.template_b, "plan_v2");
+        assert_eq!(compare.model.as_deref(), Some("haiku"));
+        assert!(compare.description.is_none());
+
+        assert!(Args::try_parse_from(["gba", "compare", "--template-a", "plan"]).is_err());
+    }
+
+    #[test]
+    fn test_replay_args_parsing() {
+        let args = Args::try_parse_from(["gba", "replay", "run.jsonl", "--tui"]).unwrap();
+        let Command::Replay(replay) = args.command else {
+            panic!("expected replay command");
+        };
+        assert_eq!(replay.transcript, PathBuf::from("run.jsonl"));
+        assert!(replay.tui);
+        assert!(!replay.raw);
+
+        assert!(Args::try_parse_from(["gba", "replay", "run.jsonl", "--tui", "--raw"]).is_err());
+    }
 }
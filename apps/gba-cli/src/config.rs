@@ -3,6 +3,8 @@
 //! This module handles loading and managing GBA project configuration.
 
 use gba_core::config::ProjectConfig;
+use gba_core::vcs::Vcs;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use tracing::{debug, instrument};
@@ -28,10 +30,32 @@ pub enum ConfigLoadError {
     /// Not a GBA project (no .gba directory).
     #[error("Not a GBA project: {0} (missing .gba directory)")]
     NotGbaProject(PathBuf),
+
+    /// No workspace file in a directory or its ancestors.
+    #[error("No GBA workspace found from {0} (missing .gba/workspace.yml)")]
+    NoWorkspace(PathBuf),
+
+    /// Workspace file that cannot be read or parsed.
+    #[error("Invalid workspace file {path}: {reason}")]
+    InvalidWorkspace {
+        /// Path of the workspace file.
+        path: PathBuf,
+        /// Why the file is invalid.
+        reason: String,
+    },
+
+    /// Project that isn't a member of the workspace.
+    #[error("Unknown project '{name}'; workspace members: {members}")]
+    UnknownProject {
+        /// Requested project name.
+        name: String,
+        /// Names of the workspace members, comma-separated.
+        members: String,
+    },
 }
 
 /// Configuration manager for GBA CLI.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConfigManager {
     /// Project path.
     project_path: PathBuf,
@@ -167,6 +191,174 @@ impl ConfigManager {
     pub fn feature_state_path(&self, feature_id: &str) -> PathBuf {
         self.features_dir().join(feature_id).join("state.yml")
     }
+
+    /// Get the transcript file path of a feature's run.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature_id` - The feature identifier.
+    /// * `run_id` - The run identifier.
+    #[must_use]
+    pub fn feature_transcript_path(&self, feature_id: &str, run_id: &str) -> PathBuf {
+        self.features_dir()
+            .join(feature_id)
+            .join("transcripts")
+            .join(format!("{run_id}.jsonl"))
+    }
+
+    /// Get the path of a feature's last verification report.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature_id` - The feature identifier.
+    #[must_use]
+    #[cfg_attr(not(feature = "github"), allow(dead_code))]
+    pub fn feature_verification_path(&self, feature_id: &str) -> PathBuf {
+        self.features_dir()
+            .join(feature_id)
+            .join("verification.json")
+    }
+
+    /// Open the version control backend of a directory of the project, as
+    /// configured under `repository.vcs` or detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The project directory or a worktree.
+    #[must_use]
+    pub fn vcs(&self, path: &Path) -> Box<dyn Vcs> {
+        gba_core::vcs::open(path, self.config.repository.vcs)
+    }
+
+    /// Get the path of the cost ledger.
+    #[must_use]
+    pub fn ledger_path(&self) -> PathBuf {
+        self.project_path.join(".gba").join("ledger.jsonl")
+    }
+}
+
+/// Workspace file listing the member projects of a multi-project workspace,
+/// in `.gba/workspace.yml` of the workspace root.
+///
+/// ```yaml
+/// members:
+///   - name: api
+///     path: services/api
+///   - name: web
+///     path: ../web
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceFile {
+    /// Member projects.
+    #[serde(default)]
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// A member project of a workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    /// Name used with `--project`.
+    pub name: String,
+
+    /// Project directory, relative to the workspace root or absolute.
+    pub path: PathBuf,
+}
+
+/// A workspace file with the directory it was found in.
+#[derive(Debug)]
+pub struct ProjectWorkspace {
+    /// Workspace root.
+    root: PathBuf,
+    /// Parsed workspace file.
+    file: WorkspaceFile,
+}
+
+impl ProjectWorkspace {
+    /// Get the workspace file path of a workspace root.
+    #[must_use]
+    pub fn file_path(root: &Path) -> PathBuf {
+        root.join(".gba").join("workspace.yml")
+    }
+
+    /// Find the workspace a directory belongs to: the closest of the
+    /// directory and its ancestors with a workspace file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no workspace file is found or it is invalid.
+    pub fn find(start: &Path) -> Result<Self> {
+        let root = start
+            .ancestors()
+            .find(|dir| Self::file_path(dir).is_file())
+            .ok_or_else(|| ConfigLoadError::NoWorkspace(start.to_path_buf()))?;
+        Self::load(root)
+    }
+
+    /// Load the workspace file of a workspace root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    #[instrument]
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = Self::file_path(root);
+        let invalid = |reason: String| ConfigLoadError::InvalidWorkspace {
+            path: path.clone(),
+            reason,
+        };
+        let content = std::fs::read_to_string(&path).map_err(|e| invalid(e.to_string()))?;
+        let file: WorkspaceFile =
+            serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        debug!(
+            "Loaded workspace {} with {} members",
+            root.display(),
+            file.members.len()
+        );
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Get the workspace root.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the member projects with their resolved directories, in file
+    /// order.
+    #[must_use]
+    pub fn members(&self) -> Vec<(&str, PathBuf)> {
+        self.file
+            .members
+            .iter()
+            .map(|member| (member.name.as_str(), self.root.join(&member.path)))
+            .collect()
+    }
+
+    /// Get the directory of a member project.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no member has the name.
+    pub fn member_path(&self, name: &str) -> Result<PathBuf> {
+        self.members()
+            .into_iter()
+            .find(|(member, _)| *member == name)
+            .map(|(_, path)| path)
+            .ok_or_else(|| ConfigLoadError::UnknownProject {
+                name: name.to_string(),
+                members: self
+                    .file
+                    .members
+                    .iter()
+                    .map(|member| member.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
 }
 
 #[cfg(test)]
@@ -180,6 +372,41 @@ mod tests {
         assert_eq!(config_path, PathBuf::from("/test/project/.gba/config.yml"));
     }
 
+    #[test]
+    fn test_project_workspace() {
+        let root = std::env::temp_dir().join(format!("gba-test-workspace-{}", std::process::id()));
+        let nested = root.join("services").join("api").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(root.join(".gba")).unwrap();
+        std::fs::write(
+            ProjectWorkspace::file_path(&root),
+            "members:\n  - name: api\n    path: services/api\n  - name: web\n    path: /srv/web\n",
+        )
+        .unwrap();
+
+        let workspace = ProjectWorkspace::find(&nested).unwrap();
+        assert_eq!(workspace.root(), root.as_path());
+        assert_eq!(
+            workspace.member_path("api").unwrap(),
+            root.join("services/api")
+        );
+        assert_eq!(
+            workspace.member_path("web").unwrap(),
+            PathBuf::from("/srv/web")
+        );
+        let err = workspace.member_path("docs").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown project 'docs'; workspace members: api, web"
+        );
+
+        std::fs::remove_dir_all(&root).ok();
+        assert!(matches!(
+            ProjectWorkspace::find(&root),
+            Err(ConfigLoadError::NoWorkspace(_))
+        ));
+    }
+
     #[test]
     fn test_is_gba_project_false() {
         let temp_dir = std::env::temp_dir().join("gba-test-no-gba");
@@ -0,0 +1,125 @@
+//! `gba run --dry-run`: previewing a run without contacting the API.
+
+use gba::{RunPreview, Workspace};
+use gba_core::context_budget::ContextStage;
+use gba_core::feature;
+use gba_core::state::FeatureState;
+use gba_pm::Context as PromptContext;
+
+use crate::cli::{RunArgs, TaskKind};
+use crate::config::ConfigManager;
+use crate::error::Result as CliResult;
+use crate::output::output;
+use crate::run::{build_resume_context, fill_template_variables};
+
+/// Show what a run would send to the agent, without contacting the API,
+/// changing the feature state or creating its worktree.
+///
+/// Verification commands are not run: their results are left out of the
+/// prompt.
+///
+/// # Arguments
+///
+/// * `workspace` - Workspace of the run.
+/// * `config` - Configuration manager.
+/// * `args` - Run command arguments.
+/// * `template_name` - Template of the run.
+/// * `context` - Context to render the template with.
+/// * `resumed` - Feature state of the run being resumed, if any.
+///
+/// # Errors
+///
+/// Returns an error if the template cannot be rendered or the context
+/// cannot be built.
+pub async fn dry_run(
+    workspace: &Workspace,
+    config: &ConfigManager,
+    args: &RunArgs,
+    template_name: &str,
+    mut context: PromptContext,
+    resumed: Option<&FeatureState>,
+) -> CliResult<()> {
+    let feature_id = feature::feature_id(&args.feature);
+    let state = FeatureState::load_or_new(
+        &config.feature_state_path(&feature_id),
+        &args.feature,
+        &feature_id,
+    )
+    .map_err(gba_core::CoreError::from)?;
+    let prompt_manager = workspace.prompts();
+    let tools = prompt_manager.get_config(args.kind.template_name())?.tools;
+    if let Some(state) = resumed {
+        context = build_resume_context(config, state, tools);
+    } else if let Some(worktree) = &state.context.worktree {
+        context.worktree_path = worktree.path.display().to_string();
+        context.worktree_branch = worktree.branch.clone();
+    }
+
+    fill_template_variables(prompt_manager, template_name, &mut context)?;
+    let prompt = prompt_manager.get_prompt(template_name, &context)?;
+    let RunPreview {
+        working_dir,
+        task,
+        preview,
+        report,
+    } = workspace
+        .preview(&args.feature, &args.kind.to_string(), template_name, prompt)
+        .await?;
+
+    let out = output();
+    out.prompt_output(&format!("Prompt ({template_name})"), &preview.prompt);
+    out.prompt_output("System Prompt", &preview.system_prompt);
+    out.section("Dry Run");
+    out.list_item("Template:", template_name);
+    out.list_item("Working directory:", &working_dir.display().to_string());
+    out.list_item("Model:", &preview.generation.model);
+    let tools = match preview.tools.allowed() {
+        Some(allowed) => allowed.join(", "),
+        None => "all".to_string(),
+    };
+    out.list_item("Tools:", &tools);
+    if !preview.tools.denied().is_empty() {
+        out.list_item("Denied tools:", &preview.tools.denied().join(", "));
+    }
+    out.list_item("Permission mode:", &preview.permission_mode.to_string());
+    if let Some(max_turns) = preview.generation.max_turns {
+        out.list_item("Max turns:", &max_turns.to_string());
+    }
+    out.list_item(
+        "Max output tokens:",
+        &preview.generation.max_tokens.to_string(),
+    );
+    out.list_item(
+        "Context:",
+        &format!(
+            "{} files, {} bytes",
+            task.context.files.len(),
+            preview.context_bytes
+        ),
+    );
+    if report.stage != ContextStage::Full {
+        out.list_item("Context stage:", &report.stage.to_string());
+    }
+    out.list_item(
+        "Estimated input tokens:",
+        &preview.estimated_tokens.to_string(),
+    );
+
+    let budget = config
+        .config()
+        .context
+        .input_budget(&config.config().agent, &config.config().model_registry());
+    if let Some(budget) = budget
+        && preview.estimated_tokens > budget as usize
+    {
+        out.warning(&format!(
+            "The prompt exceeds the input budget of {budget} tokens even with the context as {}",
+            report.stage
+        ));
+    }
+    if args.kind == TaskKind::Verification {
+        out.info("Verification commands were not run; their results are left out of the prompt");
+    }
+
+    Ok(())
+}
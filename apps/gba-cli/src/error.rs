@@ -17,6 +17,10 @@ pub enum CliError {
     #[error("Prompt manager error: {0}")]
     Prompt(#[from] gba_pm::PromptError),
 
+    /// Error from the GBA workspace.
+    #[error("{0}")]
+    Workspace(gba::GbaError),
+
     /// Error from configuration operations.
     #[error("Configuration error: {0}")]
     Config(String),
@@ -27,7 +31,6 @@ pub enum CliError {
 
     /// Error from argument parsing.
     #[error("Invalid arguments: {0}")]
-    #[allow(dead_code)]
     InvalidArgs(String),
 
     /// User canceled operation.
@@ -46,7 +49,6 @@ pub enum CliError {
 
     /// Invalid template name.
     #[error("Invalid template name: {0}")]
-    #[allow(dead_code)]
     InvalidTemplateName(String),
 
     /// Not a GBA project.
@@ -58,6 +60,77 @@ pub enum CliError {
     #[allow(dead_code)]
     FeatureStateNotFound(String),
 
+    /// A project quota has been reached.
+    #[error("{0}; rerun with --override-quota to start the run anyway")]
+    QuotaExceeded(String),
+
+    /// Feature has no worktree.
+    #[error("Feature '{0}' has no worktree; run an implementation task first")]
+    NoWorktree(String),
+
+    /// Working tree has uncommitted changes.
+    #[error(
+        "{count} uncommitted change(s) in {path}; commit or stash them, or rerun with --force or --auto-stash"
+    )]
+    DirtyWorkingTree {
+        /// Path of the working tree.
+        path: PathBuf,
+        /// Number of changed paths.
+        count: usize,
+    },
+
+    /// The working tree could not be checked for uncommitted changes.
+    #[error(
+        "Could not check {path} for uncommitted changes: {reason}; rerun with --force to skip the check"
+    )]
+    WorkingTreeCheckFailed {
+        /// Path of the working tree.
+        path: PathBuf,
+        /// Why the check failed.
+        reason: String,
+    },
+
+    /// Stashed changes could not be restored after a run.
+    #[error(
+        "Could not restore stashed changes in {path}: {reason}; they are kept in the stash, restore them with `git stash apply {stash}`"
+    )]
+    StashRestoreFailed {
+        /// Path of the working tree.
+        path: PathBuf,
+        /// Commit SHA of the stash entry.
+        stash: String,
+        /// Why restoring failed.
+        reason: String,
+    },
+
+    /// Feature states have issues left after a check.
+    #[error(
+        "{count} feature state issue(s) found, {fixable} of them fixable with `gba state check --fix`"
+    )]
+    StateIssues {
+        /// Number of issues left.
+        count: usize,
+        /// Number of those `--fix` repairs.
+        fixable: usize,
+    },
+
+    /// Features of a concurrent run failed.
+    #[error("{failed} of {total} feature(s) failed")]
+    FeaturesFailed {
+        /// Number of features that failed.
+        failed: usize,
+        /// Number of features run.
+        total: usize,
+    },
+
+    /// The configuration or templates have problems.
+    #[error("{0} problem(s) found in the configuration and templates")]
+    ValidationProblems(usize),
+
+    /// Clipboard access failed.
+    #[error("Clipboard error: {0}")]
+    Clipboard(String),
+
     /// Agent execution failed.
     #[error("Agent execution failed: {0}")]
     #[allow(dead_code)]
@@ -74,7 +147,6 @@ impl CliError {
 
     /// Create an invalid arguments error.
     #[must_use]
-    #[allow(dead_code)]
     pub const fn invalid_args(message: String) -> Self {
         Self::InvalidArgs(message)
     }
@@ -111,6 +183,38 @@ impl From<crate::config::ConfigLoadError> for CliError {
                 Self::Config(format!("Invalid project path: {}", path.display()))
             }
             crate::config::ConfigLoadError::NotGbaProject(path) => Self::NotGbaProject(path),
+            e @ (crate::config::ConfigLoadError::NoWorkspace(_)
+            | crate::config::ConfigLoadError::InvalidWorkspace { .. }
+            | crate::config::ConfigLoadError::UnknownProject { .. }) => Self::Config(e.to_string()),
+        }
+    }
+}
+
+impl From<gba::GbaError> for CliError {
+    fn from(err: gba::GbaError) -> Self {
+        use gba::GbaError;
+
+        // Errors the user fixes with a flag of the command name the flag
+        match err {
+            GbaError::Core(gba_core::CoreError::Quota(e)) => Self::QuotaExceeded(e.to_string()),
+            GbaError::Core(e) => Self::Core(e),
+            GbaError::Prompt(e) => Self::Prompt(e),
+            GbaError::NotGbaProject(path) => Self::NotGbaProject(path),
+            GbaError::NoWorktree(feature) => Self::NoWorktree(feature),
+            GbaError::DirtyWorkingTree { path, count } => Self::DirtyWorkingTree { path, count },
+            GbaError::WorkingTreeCheckFailed { path, reason } => {
+                Self::WorkingTreeCheckFailed { path, reason }
+            }
+            GbaError::StashRestoreFailed {
+                path,
+                stash,
+                reason,
+            } => Self::StashRestoreFailed {
+                path,
+                stash,
+                reason,
+            },
+            e => Self::Workspace(e),
         }
     }
 }
@@ -126,5 +230,11 @@ mod tests {
 
         let err = CliError::TemplateNotFound("test".to_string());
         assert_eq!(err.to_string(), "Template 'test' not found");
+
+        let err = CliError::QuotaExceeded("Daily runs quota reached: 5 of 5 used".to_string());
+        assert_eq!(
+            err.to_string(),
+            "Daily runs quota reached: 5 of 5 used; rerun with --override-quota to start the run anyway"
+        );
     }
 }
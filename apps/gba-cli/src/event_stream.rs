@@ -0,0 +1,316 @@
+//! Headless event stream of `gba run --events`.
+//!
+//! Each event is written to stdout as a JSON object on its own line, with a
+//! `type` field naming it and a `timestamp`, as the run happens:
+//!
+//! - `started`: the run started a phase, with the stream's `version`.
+//! - `chunk`: the agent wrote text, summarized by its length and first line.
+//! - `toolCall`: the agent called a tool.
+//! - `usage`: the tokens and cost spent so far.
+//! - `result`: the run finished, successfully or not; always the last event
+//!   of a run that started.
+//!
+//! Everything else the CLI prints goes to stderr, so stdout carries nothing
+//! but events; a run failing before it starts, e.g. on an invalid template,
+//! writes no events and only exits with an error. Fields are only ever
+//! added to the events, and a breaking change bumps [`VERSION`].
+
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use chrono::{SecondsFormat, Utc};
+use gba_core::Response;
+use gba_core::events::{EventKind, EventSink, TaskEvent};
+use gba_core::stream::Chunk;
+use gba_core::task::Usage;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::output::Writer;
+
+/// Version of the event schema.
+pub const VERSION: u32 = 1;
+
+/// Maximum number of characters of a chunk's preview.
+const PREVIEW_CHARS: usize = 80;
+
+/// An event of the stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum StreamEvent<'a> {
+    /// The run started a phase.
+    Started {
+        /// Version of the event schema.
+        version: u32,
+        /// Feature name.
+        feature: &'a str,
+        /// Feature ID.
+        feature_id: &'a str,
+        /// Run identifier.
+        run_id: &'a str,
+        /// Phase of the run, e.g. `"implementation"`.
+        phase: &'a str,
+        /// Directory the agent works in.
+        working_dir: &'a str,
+    },
+
+    /// The agent wrote text.
+    Chunk {
+        /// Number of characters of the text.
+        chars: usize,
+        /// First line of the text, shortened.
+        preview: String,
+    },
+
+    /// The agent called a tool.
+    ToolCall {
+        /// Tool name.
+        tool: &'a str,
+        /// Summary of the call, e.g. the file read.
+        summary: &'a str,
+    },
+
+    /// Tokens and cost spent so far.
+    Usage {
+        /// Tokens and cost.
+        #[serde(flatten)]
+        usage: &'a Usage,
+    },
+
+    /// The run finished.
+    Result {
+        /// Whether the run succeeded.
+        success: bool,
+        /// Number of turns, if the run succeeded.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        turns: Option<u32>,
+        /// Tokens and cost of the run; of a failed run, what it spent until
+        /// it stopped.
+        usage: &'a Usage,
+        /// Error message of a failed run.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+        /// Response of the agent, if the run succeeded.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content: Option<&'a str>,
+    },
+}
+
+impl StreamEvent<'_> {
+    /// Summarize a text chunk of the agent.
+    #[must_use]
+    pub fn chunk(text: &str) -> StreamEvent<'static> {
+        let first_line = text.trim_start().lines().next().unwrap_or_default();
+        let mut preview: String = first_line.chars().take(PREVIEW_CHARS).collect();
+        if first_line.chars().count() > PREVIEW_CHARS {
+            preview.push('…');
+        }
+        StreamEvent::Chunk {
+            chars: text.chars().count(),
+            preview,
+        }
+    }
+
+    /// Build the result event of a run.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - Response of the run, or the error it failed with.
+    /// * `partial` - Usage spent by a failed run until it stopped.
+    #[must_use]
+    pub fn result<'a>(
+        result: std::result::Result<&'a Response, &'a str>,
+        partial: &'a Usage,
+    ) -> StreamEvent<'a> {
+        match result {
+            Ok(response) => StreamEvent::Result {
+                success: true,
+                turns: Some(response.turns),
+                usage: &response.usage,
+                error: None,
+                content: Some(&response.content),
+            },
+            Err(error) => StreamEvent::Result {
+                success: false,
+                turns: None,
+                usage: partial,
+                error: Some(error),
+                content: None,
+            },
+        }
+    }
+}
+
+/// Event with its timestamp, as written.
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    event: &'a StreamEvent<'a>,
+}
+
+/// Writes events as JSON lines.
+///
+/// Also a sink for the tool call events of a run.
+pub struct EventStream {
+    /// Writer of the events.
+    writer: Mutex<Writer>,
+}
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream").finish_non_exhaustive()
+    }
+}
+
+impl EventStream {
+    /// Create a stream writing to stdout.
+    #[must_use]
+    pub fn stdout() -> Self {
+        Self::with_writer(Box::new(io::stdout()))
+    }
+
+    /// Create a stream writing to a writer.
+    #[must_use]
+    pub fn with_writer(writer: Writer) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Write an event, flushed so consumers see it right away.
+    pub fn emit(&self, event: &StreamEvent<'_>) {
+        let line = Line {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            event,
+        };
+        let Ok(mut json) = serde_json::to_string(&line) else {
+            return;
+        };
+        json.push('\n');
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        // A closed stdout only means the consumer went away
+        let _ = writer.write_all(json.as_bytes());
+        let _ = writer.flush();
+    }
+
+    /// Write the chunks of a task as events until it is done.
+    ///
+    /// Tool calls are written through [`EventSink`] with their summary.
+    pub async fn forward_chunks(&self, mut chunks: mpsc::UnboundedReceiver<Chunk>) {
+        while let Some(chunk) = chunks.recv().await {
+            match chunk {
+                Chunk::Text(text) => self.emit(&StreamEvent::chunk(&text)),
+                Chunk::Usage(usage) => self.emit(&StreamEvent::Usage { usage: &usage }),
+                Chunk::ToolUse { .. } | Chunk::Error { .. } => {}
+                Chunk::Done { .. } => break,
+            }
+        }
+    }
+}
+
+impl EventSink for EventStream {
+    fn send(&self, event: &TaskEvent) {
+        if let EventKind::ToolCall { tool, summary } = &event.kind {
+            self.emit(&StreamEvent::ToolCall { tool, summary });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Writer appending to a shared buffer.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<serde_json::Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_chunk_summary() {
+        let StreamEvent::Chunk { chars, preview } =
+            StreamEvent::chunk("\nReading the plan.\nThen…")
+        else {
+            panic!("expected a chunk event");
+        };
+        assert_eq!(chars, 24);
+        assert_eq!(preview, "Reading the plan.");
+
+        let StreamEvent::Chunk { preview, .. } = StreamEvent::chunk(&"a".repeat(100)) else {
+            panic!("expected a chunk event");
+        };
+        assert_eq!(preview.chars().count(), PREVIEW_CHARS + 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_lines() {
+        let buffer = Buffer::default();
+        let stream = EventStream::with_writer(Box::new(buffer.clone()));
+        stream.emit(&StreamEvent::Started {
+            version: VERSION,
+            feature: "add-auth",
+            feature_id: "0001",
+            run_id: "run-1",
+            phase: "planning",
+            working_dir: "/repo",
+        });
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let usage = Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            total_cost_usd: 0.01,
+        };
+        tx.send(Chunk::Text("Planning.".to_string())).unwrap();
+        tx.send(Chunk::Usage(usage.clone())).unwrap();
+        tx.send(Chunk::Done {
+            usage: usage.clone(),
+            partial: false,
+        })
+        .unwrap();
+        stream.forward_chunks(rx).await;
+        stream.emit(&StreamEvent::result(Err("Timed out"), &usage));
+
+        let lines = buffer.lines();
+        let types = lines
+            .iter()
+            .map(|line| line["type"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["started", "chunk", "usage", "result"]);
+        assert_eq!(lines[0]["version"], VERSION);
+        assert_eq!(lines[0]["featureId"], "0001");
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(lines[2]["outputTokens"], 5);
+        assert_eq!(lines[3]["success"], false);
+        assert_eq!(lines[3]["error"], "Timed out");
+        assert!(lines[3].get("content").is_none());
+    }
+}
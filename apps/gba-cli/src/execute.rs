@@ -0,0 +1,353 @@
+//! Executing the agent of a run on the console, in the TUI or as an event
+//! stream.
+
+use gba::Run;
+use gba_core::diff::{self, DiffOptions};
+use gba_core::events::{EventKind, EventSink, TaskEvent};
+use gba_core::stream::Chunk;
+use gba_core::{Agent, Response, Task};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::cli::RunArgs;
+use crate::config::ConfigManager;
+use crate::error::Result as CliResult;
+use crate::event_stream::{self, EventStream, StreamEvent};
+use crate::keymap::KeyMap;
+use crate::markdown::MarkdownStream;
+use crate::output::{Event, output};
+use crate::ui::{AppEvent, Tui};
+
+/// Execute the rendered task, in the TUI or on the console.
+///
+/// On the console, tool calls are listed as the agent makes them and the
+/// response is printed when it finishes.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `args` - Run command arguments.
+/// * `run` - Run of the task.
+/// * `agent` - Agent to execute the task with.
+/// * `task` - Task to execute.
+///
+/// # Returns
+///
+/// The response of the agent.
+///
+/// # Errors
+///
+/// Returns an error if execution fails or is canceled by quitting the TUI.
+pub async fn execute(
+    config: &ConfigManager,
+    args: &RunArgs,
+    run: &Run,
+    agent: Agent,
+    task: &Task,
+) -> CliResult<Response> {
+    let state = run.state();
+    let run_id = run.run_id();
+    let run_events = |tx: Option<mpsc::UnboundedSender<AppEvent>>| {
+        run.events().clone().with_sink(Arc::new(ToolCallSink(tx)))
+    };
+
+    // In TUI mode, run execution in the background and feed the TUI
+    if args.tui {
+        debug!("Starting TUI mode");
+        let keymap = KeyMap::from_config(&config.config().tui.keys)?;
+        let mut tui = Tui::new(keymap)?;
+        // Show the worktree's changes against the main branch in the diff pane
+        let diff_base = state
+            .context
+            .worktree
+            .as_ref()
+            .map(|_| config.config().project.repository.main_branch.clone());
+        let execution = tokio::spawn(execute_in_background(
+            tui.sender(),
+            args.kind.to_string(),
+            agent.with_events(run_events(Some(tui.sender()))),
+            task.clone(),
+            diff_base,
+        ));
+        tui.set_task(execution.abort_handle());
+
+        let result = tui.run().await;
+        tui.exit()?;
+
+        // Quitting the TUI before the agent finishes cancels the task
+        execution.abort();
+        let response = match execution.await {
+            Ok(response) => response,
+            Err(e) if e.is_cancelled() => Err(gba_core::CoreError::Canceled),
+            Err(e) => Err(gba_core::CoreError::ClaudeAgent(format!(
+                "Background execution task failed: {e}"
+            ))),
+        };
+        result?;
+        debug!("TUI completed");
+        return Ok(response?);
+    }
+
+    debug!(
+        "Executing task (non-TUI mode) in {}",
+        agent.working_dir().display()
+    );
+    let out = output();
+    out.detail(&format!(
+        "Working directory: {}",
+        agent.working_dir().display()
+    ));
+
+    // Headless runs stream their events on stdout
+    if args.events {
+        let stream = Arc::new(EventStream::stdout());
+        stream.emit(&StreamEvent::Started {
+            version: event_stream::VERSION,
+            feature: &state.feature.name,
+            feature_id: &state.feature.id,
+            run_id,
+            phase: &args.kind.to_string(),
+            working_dir: &agent.working_dir().display().to_string(),
+        });
+        let (chunks_tx, chunks_rx) = mpsc::unbounded_channel();
+        let forward = {
+            let stream = stream.clone();
+            tokio::spawn(async move { stream.forward_chunks(chunks_rx).await })
+        };
+        let events = run.events().clone().with_sink(stream.clone());
+        let result = agent
+            .with_events(events)
+            .with_chunks(chunks_tx)
+            .execute_task(task)
+            .await;
+        let _ = forward.await;
+        match &result {
+            Ok(response) => stream.emit(&StreamEvent::result(Ok(response), &response.usage)),
+            Err(e) => {
+                let partial = e
+                    .partial_response()
+                    .map(|partial| partial.usage.clone())
+                    .unwrap_or_default();
+                stream.emit(&StreamEvent::result(Err(&e.to_string()), &partial));
+            }
+        }
+        return Ok(result?);
+    }
+
+    out.event(&Event::TaskStarted {
+        feature: &state.feature.name,
+        feature_id: &state.feature.id,
+        kind: &args.kind.to_string(),
+        run_id,
+        working_dir: &agent.working_dir().display().to_string(),
+    });
+
+    // JSON output streams the agent's text and usage as events
+    if out.is_json() {
+        let (chunks_tx, chunks_rx) = mpsc::unbounded_channel();
+        let events = tokio::spawn(write_chunk_events(chunks_rx));
+        let result = agent
+            .with_events(run_events(None))
+            .with_chunks(chunks_tx)
+            .execute_task(task)
+            .await;
+        let _ = events.await;
+        return Ok(result?);
+    }
+
+    let response = agent
+        .with_events(run_events(None))
+        .execute_task(task)
+        .await?;
+
+    out.separator();
+    if out.is_colors_enabled() {
+        let mut markdown = MarkdownStream::new();
+        out.text(&markdown.push(&response.content));
+        out.text(&markdown.finish());
+    } else {
+        out.print(&response.content);
+    }
+    out.separator();
+    out.info(&format!(
+        "{} turns, {} input / {} output tokens, ${:.4}",
+        response.turns,
+        response.usage.input_tokens,
+        response.usage.output_tokens,
+        response.usage.total_cost_usd
+    ));
+
+    Ok(response)
+}
+
+/// Shows the tool calls of a run as the agent makes them: in the TUI if it
+/// has a sender, and on the console otherwise.
+struct ToolCallSink(Option<mpsc::UnboundedSender<AppEvent>>);
+
+impl EventSink for ToolCallSink {
+    fn send(&self, event: &TaskEvent) {
+        let EventKind::ToolCall { tool, summary } = &event.kind else {
+            return;
+        };
+        match &self.0 {
+            // Send errors only mean the TUI has already shut down
+            Some(tx) => {
+                let _ = tx.send(AppEvent::ToolCall(format!("{tool} {summary}")));
+            }
+            None if output().is_json() => output().event(&Event::ToolCall { tool, summary }),
+            None => output().list_item("→", &format!("{tool} {summary}")),
+        }
+    }
+}
+
+/// Write the chunks of a task as JSON events until it is done.
+///
+/// Tool calls are written by [`ToolCallSink`] with their summary, failures
+/// as the command's error, and the usage once the task is done.
+async fn write_chunk_events(mut chunks: mpsc::UnboundedReceiver<Chunk>) {
+    while let Some(chunk) = chunks.recv().await {
+        match chunk {
+            Chunk::Text(text) => output().event(&Event::Chunk { text: &text }),
+            Chunk::ToolUse { .. } | Chunk::Usage(_) | Chunk::Error { .. } => {}
+            Chunk::Done { usage, partial } => {
+                output().event(&Event::Usage {
+                    usage: &usage,
+                    partial,
+                });
+                break;
+            }
+        }
+    }
+}
+
+/// Execute a task in the background, reporting progress as TUI events.
+///
+/// # Arguments
+///
+/// * `tx` - Sender for TUI events.
+/// * `phase` - Name of the execution phase.
+/// * `agent` - Agent to execute the task with.
+/// * `task` - Task to execute.
+/// * `diff_base` - Base ref to diff the working directory against, if any.
+///
+/// # Returns
+///
+/// The response of the agent.
+async fn execute_in_background(
+    tx: mpsc::UnboundedSender<AppEvent>,
+    phase: String,
+    agent: Agent,
+    task: Task,
+    diff_base: Option<String>,
+) -> gba_core::Result<Response> {
+    // Send errors only mean the TUI has already shut down
+    let _ = tx.send(AppEvent::PhaseChange(phase));
+    let (chunk_tx, chunks) = mpsc::unbounded_channel();
+    let agent = agent.with_chunks(chunk_tx);
+    let (result, failure) = tokio::join!(agent.execute_task(&task), forward_chunks(chunks, &tx));
+
+    if let Some(base) = diff_base {
+        let working_dir = agent.working_dir().clone();
+        let diff = tokio::task::spawn_blocking(move || {
+            diff::diff_worktree(&working_dir, &base, &DiffOptions::default())
+        })
+        .await;
+        match diff {
+            Ok(Ok(diff)) => {
+                let _ = tx.send(AppEvent::DiffUpdate(diff.content));
+            }
+            Ok(Err(e)) => warn!("Failed to generate diff: {}", e),
+            Err(e) => warn!("Diff task failed: {}", e),
+        }
+    }
+
+    let _ = tx.send(AppEvent::Finished(
+        failure.or_else(|| result.as_ref().err().map(ToString::to_string)),
+    ));
+    result
+}
+
+/// Forward the chunks of a task to the TUI until its final chunk.
+///
+/// Tool calls are reported by the run's events, with their summary.
+///
+/// # Returns
+///
+/// The failure of the task, if any, with a hint when it may be retried.
+async fn forward_chunks(
+    mut chunks: mpsc::UnboundedReceiver<Chunk>,
+    tx: &mpsc::UnboundedSender<AppEvent>,
+) -> Option<String> {
+    let mut failure = None;
+    while let Some(chunk) = chunks.recv().await {
+        match chunk {
+            Chunk::Text(text) => {
+                let _ = tx.send(AppEvent::AgentChunk(text));
+            }
+            Chunk::ToolUse { .. } => {}
+            Chunk::Usage(usage) => {
+                let _ = tx.send(AppEvent::UsageUpdate(usage));
+            }
+            Chunk::Error { message, retryable } => {
+                failure = Some(if retryable {
+                    format!("{message} (retry with --resume)")
+                } else {
+                    message
+                });
+            }
+            Chunk::Done { usage, .. } => {
+                let _ = tx.send(AppEvent::UsageUpdate(usage));
+                break;
+            }
+        }
+    }
+    failure
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gba_core::task::Usage;
+
+    #[tokio::test]
+    async fn test_forward_chunks_until_done() {
+        let (chunk_tx, chunks) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for chunk in [
+            Chunk::Text("Reading.".to_string()),
+            Chunk::Usage(Usage {
+                output_tokens: 2,
+                ..Usage::default()
+            }),
+            Chunk::Error {
+                message: "Timed out after 300s".to_string(),
+                retryable: true,
+            },
+            Chunk::Done {
+                usage: Usage {
+                    output_tokens: 5,
+                    ..Usage::default()
+                },
+                partial: true,
+            },
+            Chunk::Text("Next task.".to_string()),
+        ] {
+            chunk_tx.send(chunk).unwrap();
+        }
+
+        let failure = forward_chunks(chunks, &tx).await;
+        assert_eq!(
+            failure.as_deref(),
+            Some("Timed out after 300s (retry with --resume)")
+        );
+        assert!(matches!(rx.recv().await, Some(AppEvent::AgentChunk(text)) if text == "Reading."));
+        assert!(
+            matches!(rx.recv().await, Some(AppEvent::UsageUpdate(usage)) if usage.output_tokens == 2)
+        );
+        assert!(
+            matches!(rx.recv().await, Some(AppEvent::UsageUpdate(usage)) if usage.output_tokens == 5)
+        );
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,534 @@
+//! `gba init`: setting up a project for GBA.
+
+use gba_core::atomic;
+use gba_core::config::ProjectConfig;
+use gba_core::verify;
+use gba_pm::Context as PromptContext;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info, instrument, warn};
+
+use crate::config::ConfigManager;
+use crate::error::{CliError, Result as CliResult};
+use crate::migrate;
+use crate::output::output;
+use crate::templates::init_prompt_manager;
+
+/// Line opening the block of `.gitignore` managed by `gba init`.
+const GITIGNORE_BEGIN: &str = "# >>> gba >>>";
+
+/// Line closing the block of `.gitignore` managed by `gba init`.
+const GITIGNORE_END: &str = "# <<< gba <<<";
+
+/// Paths written by GBA that must not be committed: feature state, logs,
+/// caches, scratch directories, agent sessions, the repository index, backups
+/// and worktrees.
+const GITIGNORE_ENTRIES: &[&str] = &[
+    ".gba/features/",
+    ".gba/logs/",
+    ".gba/cache/",
+    ".gba/tmp/",
+    ".gba/sessions/",
+    ".gba/index/",
+    ".gba/*.bak",
+    ".trees/",
+];
+
+/// Initialize a GBA project.
+///
+/// # Arguments
+///
+/// * `project_path` - Path to the project directory.
+/// * `main_branch` - Name of the main branch.
+/// * `repo_url` - Optional repository URL.
+///
+/// # Errors
+///
+/// Returns an error if initialization fails.
+#[instrument(skip(project_path))]
+pub async fn init(project_path: &Path, main_branch: &str, repo_url: Option<&str>) -> CliResult<()> {
+    info!("Initializing GBA project at {}", project_path.display());
+
+    // Also brings the ignored paths of an initialized project up to date
+    if update_gitignore(project_path)? {
+        debug!("Updated {}", project_path.join(".gitignore").display());
+    }
+
+    // Check if .gba directory already exists
+    let gba_dir = project_path.join(".gba");
+    if gba_dir.exists() {
+        debug!(
+            "GBA project already initialized at {}",
+            project_path.display()
+        );
+        return Ok(());
+    }
+
+    let templates_dir = gba_dir.join("templates");
+    let features_dir = gba_dir.join("features");
+
+    fs::create_dir_all(&templates_dir)?;
+    fs::create_dir_all(&features_dir)?;
+
+    // Create features README
+    let readme_path = features_dir.join("README.md");
+    let readme_content = "# Features Directory\n\n\
+        This directory contains state files for each feature being developed.\n\n\
+        State files track the progress of task execution and are excluded from git.\n";
+    atomic::write(&readme_path, readme_content)?;
+
+    // Detect repository name from path
+    let repo_name = project_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("project");
+
+    // Detect repository URL from git if not provided
+    let detected_url = if repo_url.is_none() {
+        detect_repo_url(project_path)
+    } else {
+        None
+    };
+
+    let final_repo_url = repo_url.or(detected_url.as_deref()).unwrap_or("unknown");
+
+    // Create default configuration
+    debug!("Creating default configuration file");
+
+    let config = ProjectConfig {
+        version: "1.0".to_string(),
+        project: Default::default(),
+        agent: Default::default(),
+        prompts: Default::default(),
+        repository: Default::default(),
+        logging: Default::default(),
+        worktree: Default::default(),
+        limits: Default::default(),
+        quota: Default::default(),
+        tui: Default::default(),
+        review: Default::default(),
+        index: Default::default(),
+        post_process: Default::default(),
+        constraints: Default::default(),
+        verification: Default::default(),
+        fix_loop: Default::default(),
+        context: Default::default(),
+        scratch: Default::default(),
+        storage: Default::default(),
+        github: Default::default(),
+        models: Vec::new(),
+        webhooks: Vec::new(),
+    };
+
+    // Update project metadata
+    let config_yaml = format!(
+        r#"# GBA Project Configuration
+version: "{}"
+
+# Project metadata
+project:
+  name: "{}"
+  repository:
+    url: "{}"
+    mainBranch: "{}"
+
+# Agent defaults
+agent:
+  model: "{}"
+  maxTokens: {}
+  temperature: {}
+  timeout: {}
+
+# Prompt templates configuration
+prompts:
+  directory: "./.gba/templates"
+  useBundled: true
+
+# Repository scanning settings
+repository:
+  excludePatterns: {}
+  maxFileSize: {}
+
+# Logging configuration
+logging:
+  level: "{}"
+  format: "{}"
+
+# Worktree configuration
+worktree:
+  directory: "./.trees"
+  branchPrefix: "{}"
+
+# Execution limits
+limits:
+  maxTurns: {}
+  maxCostUsd: {}
+"#,
+        config.version,
+        repo_name,
+        final_repo_url,
+        main_branch,
+        config.agent.model,
+        config.agent.max_tokens,
+        config.agent.temperature,
+        config.agent.timeout,
+        serde_yaml::to_string(&config.repository.exclude_patterns).unwrap(),
+        config.repository.max_file_size,
+        config.logging.level,
+        config.logging.format,
+        config.worktree.branch_prefix,
+        config.limits.max_turns,
+        config.limits.max_cost_usd
+    );
+
+    let config_path = ConfigManager::config_file_path(project_path);
+    atomic::write_with_backup(&config_path, config_yaml)?;
+
+    info!(
+        "GBA project initialized successfully at {}",
+        project_path.display()
+    );
+    debug!("Configuration file: {}", config_path.display());
+
+    Ok(())
+}
+
+/// Import an existing Claude Code setup into the project's configuration
+/// and report what was migrated.
+///
+/// See [`migrate`] for the settings imported. The configuration is only
+/// rewritten when something was imported, keeping the previous one as a
+/// backup.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved, or a
+/// settings file cannot be parsed.
+#[instrument]
+pub fn migrate_existing(project_path: &Path) -> CliResult<()> {
+    let mut config = ConfigManager::load(project_path)?.config().clone();
+    let migration = migrate::migrate(project_path, &mut config)?;
+
+    let out = output();
+    if !migration.found() {
+        out.info("No existing Claude Code setup found to migrate");
+        return Ok(());
+    }
+
+    let config_path = ConfigManager::config_file_path(project_path);
+    if !migration.imported.is_empty() {
+        config
+            .save_to_file(&config_path)
+            .map_err(|e| CliError::Config(e.to_string()))?;
+    }
+
+    out.section("Migrated from");
+    for source in &migration.sources {
+        out.bullet(source);
+    }
+    if !migration.imported.is_empty() {
+        out.section("Imported");
+        for imported in &migration.imported {
+            out.list_item(&format!("{}:", imported.setting), &imported.value);
+        }
+    }
+    if !migration.skipped.is_empty() {
+        out.section("Skipped");
+        for skipped in &migration.skipped {
+            out.list_item(&format!("{}:", skipped.value), &skipped.reason);
+        }
+    }
+    out.success(&format!(
+        "Imported {} setting(s) into {}",
+        migration.imported.len(),
+        config_path.display()
+    ));
+    Ok(())
+}
+
+/// Add the paths GBA writes to the project's `.gitignore`.
+///
+/// The paths are kept in a block between marker lines, created at the end of
+/// the file, or of a new file, and rewritten in place when it already
+/// exists; the rest of the file is left untouched.
+///
+/// # Returns
+///
+/// Whether the file changed.
+fn update_gitignore(project_path: &Path) -> std::io::Result<bool> {
+    let path = project_path.join(".gitignore");
+    let existing = match fs::read_to_string(&path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut block = format!("{GITIGNORE_BEGIN}\n");
+    for entry in GITIGNORE_ENTRIES {
+        block.push_str(entry);
+        block.push('\n');
+    }
+    block.push_str(GITIGNORE_END);
+    block.push('\n');
+
+    let begin = existing.find(GITIGNORE_BEGIN);
+    let end = begin.and_then(|begin| {
+        existing[begin..]
+            .find(GITIGNORE_END)
+            .map(|end| begin + end + GITIGNORE_END.len())
+    });
+    let updated = match (begin, end) {
+        (Some(begin), Some(end)) => {
+            let rest = existing[end..]
+                .strip_prefix('\n')
+                .unwrap_or(&existing[end..]);
+            format!("{}{block}{rest}", &existing[..begin])
+        }
+        _ if existing.is_empty() => block,
+        _ => {
+            let separator = if existing.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            };
+            format!("{existing}{separator}{block}")
+        }
+    };
+
+    if updated == existing {
+        return Ok(false);
+    }
+    atomic::write(&path, updated)?;
+    Ok(true)
+}
+
+/// Detect the repository URL from version control, if any.
+fn detect_repo_url(project_path: &Path) -> Option<String> {
+    gba_core::vcs::open(project_path, None)
+        .info()
+        .inspect_err(|e| debug!("Failed to detect repository URL: {}", e))
+        .ok()
+        .and_then(|info| info.remote_url)
+}
+
+/// Files marking the languages of a repository.
+const LANGUAGE_MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust"),
+    ("go.mod", "Go"),
+    ("package.json", "JavaScript"),
+    ("tsconfig.json", "TypeScript"),
+    ("pyproject.toml", "Python"),
+    ("requirements.txt", "Python"),
+    ("pom.xml", "Java"),
+    ("build.gradle", "Java"),
+    ("build.gradle.kts", "Kotlin"),
+    ("Gemfile", "Ruby"),
+    ("composer.json", "PHP"),
+    ("mix.exs", "Elixir"),
+    ("CMakeLists.txt", "C/C++"),
+];
+
+/// Write an agent guidance file, e.g. `CLAUDE.md`, from the `agent-docs`
+/// template.
+///
+/// The template is rendered with the project's configuration (verification
+/// commands, protected paths, denied tools, branch and worktree settings)
+/// and what a scan of the repository finds (languages and top-level
+/// directories). An existing file is left untouched.
+///
+/// # Arguments
+///
+/// * `project_path` - Path to an initialized project directory.
+/// * `file` - Guidance file, relative to the project directory.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded, the template
+/// cannot be rendered or the file cannot be written.
+#[instrument]
+pub fn write_agent_docs(project_path: &Path, file: &Path) -> CliResult<()> {
+    let path = project_path.join(file);
+    if path.exists() {
+        output().warning(&format!(
+            "{} already exists; remove it to regenerate it",
+            path.display()
+        ));
+        return Ok(());
+    }
+
+    let config = ConfigManager::load(project_path)?;
+    let project = config.config();
+    let prompt_manager = init_prompt_manager(&config)?;
+
+    let main_branch = &project.project.repository.main_branch;
+    let mut context = PromptContext::new(project_path.display().to_string(), main_branch, "");
+    let name = if project.project.name.is_empty() {
+        project_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    } else {
+        project.project.name.clone()
+    };
+    context.add_extra("project_name", serde_json::json!(name));
+    context.add_extra("main_branch", serde_json::json!(main_branch));
+    context.add_extra(
+        "languages",
+        serde_json::json!(detect_languages(project_path)),
+    );
+    context.add_extra(
+        "directories",
+        serde_json::json!(top_level_directories(
+            project_path,
+            &project.repository.exclude_patterns
+        )),
+    );
+    context.add_extra(
+        "commands",
+        serde_json::json!(verify::resolve_commands(
+            project_path,
+            &project.verification
+        )),
+    );
+    context.add_extra(
+        "protected_paths",
+        serde_json::json!(project.agent.protected_paths),
+    );
+    context.add_extra(
+        "denied_tools",
+        serde_json::json!(project.agent.denied_tools),
+    );
+    context.add_extra(
+        "excluded",
+        serde_json::json!(project.repository.exclude_patterns),
+    );
+    context.add_extra(
+        "branch_prefix",
+        serde_json::json!(project.worktree.branch_prefix),
+    );
+    context.add_extra(
+        "worktree_dir",
+        serde_json::json!(project.worktree.directory),
+    );
+
+    let content = prompt_manager.get_prompt("agent-docs", &context)?;
+    atomic::write(&path, format!("{}\n", content.trim_end()))?;
+    output().success(&format!("Wrote {}", path.display()));
+
+    Ok(())
+}
+
+/// Detect the languages of a repository from marker files at its root.
+fn detect_languages(project_path: &Path) -> Vec<&'static str> {
+    let mut languages = Vec::new();
+    for (marker, language) in LANGUAGE_MARKERS {
+        if project_path.join(marker).is_file() && !languages.contains(language) {
+            languages.push(*language);
+        }
+    }
+    languages
+}
+
+/// List the top-level directories of a repository, without hidden and
+/// excluded ones, sorted.
+fn top_level_directories(project_path: &Path, exclude_patterns: &[String]) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(project_path) else {
+        return Vec::new();
+    };
+    let mut directories = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .filter(|name| {
+            !exclude_patterns
+                .iter()
+                .any(|pattern| pattern.trim_matches('/') == name)
+        })
+        .collect::<Vec<_>>();
+    directories.sort();
+    directories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_gitignore() {
+        let temp_dir = std::env::temp_dir().join("gba-test-update-gitignore");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let gitignore = temp_dir.join(".gitignore");
+
+        assert!(update_gitignore(&temp_dir).unwrap());
+        let created = fs::read_to_string(&gitignore).unwrap();
+        assert!(created.starts_with(GITIGNORE_BEGIN));
+        assert!(created.contains(
+            "\n.gba/features/\n.gba/logs/\n.gba/cache/\n.gba/tmp/\n.gba/sessions/\n.gba/index/\n.gba/*.bak\n.trees/\n"
+        ));
+
+        // Existing content is kept, and a second update changes nothing
+        fs::write(&gitignore, "target/").unwrap();
+        assert!(update_gitignore(&temp_dir).unwrap());
+        assert!(!update_gitignore(&temp_dir).unwrap());
+        let appended = fs::read_to_string(&gitignore).unwrap();
+        assert!(appended.starts_with("target/\n\n# >>> gba >>>\n"));
+        assert_eq!(appended.matches(GITIGNORE_BEGIN).count(), 1);
+
+        // An outdated block is rewritten in place
+        fs::write(
+            &gitignore,
+            format!("a\n{GITIGNORE_BEGIN}\n.trees/\n{GITIGNORE_END}\nb\n"),
+        )
+        .unwrap();
+        assert!(update_gitignore(&temp_dir).unwrap());
+        let rewritten = fs::read_to_string(&gitignore).unwrap();
+        assert!(rewritten.starts_with("a\n# >>> gba >>>\n.gba/features/\n"));
+        assert!(rewritten.ends_with("# <<< gba <<<\nb\n"));
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+
+    #[test]
+    fn test_write_agent_docs() {
+        let temp_dir = std::env::temp_dir().join("gba-test-agent-docs");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join(".gba")).unwrap();
+        fs::create_dir_all(temp_dir.join("src")).unwrap();
+        fs::create_dir_all(temp_dir.join("target")).unwrap();
+        fs::write(temp_dir.join("Cargo.toml"), "[package]\n").unwrap();
+
+        let mut config = ProjectConfig::default_config();
+        config.verification.commands =
+            vec![gba_core::VerificationCommand::new("test", "cargo test")];
+        config.agent.protected_paths = vec!["Cargo.lock".to_string()];
+        config.prompts.use_bundled = true;
+        config.repository.exclude_patterns = vec!["target/".to_string()];
+        fs::write(
+            temp_dir.join(".gba").join("config.yml"),
+            serde_yaml::to_string(&config).unwrap(),
+        )
+        .unwrap();
+
+        write_agent_docs(&temp_dir, Path::new("AGENTS.md")).unwrap();
+        let docs = fs::read_to_string(temp_dir.join("AGENTS.md")).unwrap();
+        assert!(docs.contains("- Main branch: `main`"));
+        assert!(docs.contains("- Languages: Rust\n"));
+        assert!(docs.contains("- Top-level directories: `src/`\n"));
+        assert!(docs.contains("- test: `cargo test`"));
+        assert!(docs.contains("- `Cargo.lock`"));
+        assert!(!docs.contains("disabled in this project"));
+        assert!(docs.contains("generated and vendored paths: `target/`."));
+
+        // An existing file is kept
+        fs::write(temp_dir.join("AGENTS.md"), "custom").unwrap();
+        write_agent_docs(&temp_dir, Path::new("AGENTS.md")).unwrap();
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("AGENTS.md")).unwrap(),
+            "custom"
+        );
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+}
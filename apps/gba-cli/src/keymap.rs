@@ -0,0 +1,251 @@
+//! Key bindings for the GBA TUI.
+//!
+//! This module resolves the `tui.keys` configuration section into a lookup
+//! table from terminal key events to TUI actions.
+
+use gba_core::config::TuiKeyBindings;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::error::{CliError, Result};
+
+/// Action triggered by a key press in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Scroll down one line.
+    ScrollDown,
+    /// Scroll up one line.
+    ScrollUp,
+    /// Scroll down half a page.
+    HalfPageDown,
+    /// Scroll up half a page.
+    HalfPageUp,
+    /// Jump to the top.
+    Top,
+    /// Jump to the bottom.
+    Bottom,
+    /// Switch to the next pane.
+    NextPane,
+    /// Switch to the previous pane.
+    PrevPane,
+    /// Select a pane by its zero-based index.
+    SelectPane(usize),
+    /// Copy the active pane to the clipboard.
+    Copy,
+    /// Pause or resume the output of the running task.
+    Pause,
+    /// Abort the running task.
+    Abort,
+    /// Quit the TUI.
+    Quit,
+}
+
+/// A single key combination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyBinding {
+    /// Key code.
+    code: KeyCode,
+    /// Required modifiers (only Ctrl and Alt are significant).
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parse a key description such as `"j"`, `"G"`, `"ctrl-d"`, or `"tab"`.
+    fn parse(spec: &str) -> Result<Self> {
+        let invalid = || CliError::Config(format!("Invalid key binding: '{spec}'"));
+
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if let Some(stripped) = lower.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest[rest.len() - stripped.len()..];
+            } else if let Some(stripped) = lower.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest[rest.len() - stripped.len()..];
+            } else {
+                break;
+            }
+        }
+
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    // Key events with Ctrl are matched lowercased, so
+                    // `ctrl-D` is spelled like `ctrl-d`
+                    (Some(c), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+                        KeyCode::Char(c.to_ascii_lowercase())
+                    }
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+
+        Ok(Self { code, modifiers })
+    }
+
+    /// Check whether a key event matches this binding.
+    fn matches(&self, key: &KeyEvent) -> bool {
+        let significant = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        let code = match (key.code, key.modifiers.contains(KeyModifiers::CONTROL)) {
+            // Terminals report Ctrl+letter in either case; normalize to lowercase
+            (KeyCode::Char(c), true) => KeyCode::Char(c.to_ascii_lowercase()),
+            (code, _) => code,
+        };
+        code == self.code && key.modifiers & significant == self.modifiers
+    }
+}
+
+/// Lookup table from key events to TUI actions.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    /// Bindings in priority order.
+    bindings: Vec<(KeyBinding, Action)>,
+    /// Whether number keys select panes.
+    number_keys: bool,
+}
+
+impl KeyMap {
+    /// Build a key map from the configured bindings.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - Key bindings from the `tui.keys` configuration section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a key description cannot be parsed.
+    pub fn from_config(keys: &TuiKeyBindings) -> Result<Self> {
+        let groups = [
+            (&keys.quit, Action::Quit),
+            (&keys.copy, Action::Copy),
+            (&keys.pause, Action::Pause),
+            (&keys.abort, Action::Abort),
+            (&keys.scroll_down, Action::ScrollDown),
+            (&keys.scroll_up, Action::ScrollUp),
+            (&keys.half_page_down, Action::HalfPageDown),
+            (&keys.half_page_up, Action::HalfPageUp),
+            (&keys.top, Action::Top),
+            (&keys.bottom, Action::Bottom),
+            (&keys.next_pane, Action::NextPane),
+            (&keys.prev_pane, Action::PrevPane),
+        ];
+
+        let mut bindings = Vec::new();
+        for (specs, action) in groups {
+            for spec in specs {
+                bindings.push((KeyBinding::parse(spec)?, action));
+            }
+        }
+
+        Ok(Self {
+            bindings,
+            number_keys: keys.number_keys,
+        })
+    }
+
+    /// Resolve a key event to an action.
+    #[must_use]
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        if let Some((_, action)) = self.bindings.iter().find(|(b, _)| b.matches(key)) {
+            return Some(*action);
+        }
+
+        match key.code {
+            KeyCode::Char(c @ '1'..='9') if self.number_keys && key.modifiers.is_empty() => {
+                Some(Action::SelectPane(c as usize - '1' as usize))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_default_bindings() {
+        let keymap = KeyMap::from_config(&TuiKeyBindings::default()).unwrap();
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::ScrollDown)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(Action::HalfPageDown)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('d'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('2'), KeyModifiers::NONE)),
+            Some(Action::SelectPane(1))
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char(' '), KeyModifiers::NONE)),
+            Some(Action::Pause)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('x'), KeyModifiers::NONE)),
+            Some(Action::Abort)
+        );
+    }
+
+    #[test]
+    fn test_keymap_custom_and_invalid_bindings() {
+        let mut keys = TuiKeyBindings {
+            quit: vec!["ctrl-c".to_string()],
+            number_keys: false,
+            ..TuiKeyBindings::default()
+        };
+        let keymap = KeyMap::from_config(&keys).unwrap();
+
+        assert_eq!(
+            keymap.action(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(keymap.action(&KeyEvent::from(KeyCode::Char('q'))), None);
+        assert_eq!(keymap.action(&KeyEvent::from(KeyCode::Char('1'))), None);
+
+        // Ctrl bindings match whichever case they are spelled in
+        keys.quit = vec!["Ctrl-D".to_string()];
+        let keymap = KeyMap::from_config(&keys).unwrap();
+        assert_eq!(
+            keymap.action(&KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(&KeyEvent::new(
+                KeyCode::Char('D'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT
+            )),
+            Some(Action::Quit)
+        );
+
+        keys.copy = vec!["ctrl-yy".to_string()];
+        assert!(KeyMap::from_config(&keys).is_err());
+    }
+}
@@ -10,45 +10,123 @@ use tracing_subscriber::{EnvFilter, prelude::*};
 
 mod cli;
 mod config;
+mod dry_run;
 mod error;
+mod event_stream;
+mod execute;
+mod init;
+mod keymap;
+mod markdown;
+mod merge;
+mod migrate;
 mod output;
+mod prompt;
+mod replay;
+mod report;
 mod run;
+mod status;
+mod templates;
 mod ui;
+mod worktrees;
 
 use cli::{Args, Command};
-use config::ConfigManager;
+use config::{ConfigManager, ProjectWorkspace};
+use error::CliError;
+use output::{Event, OutputFormatter, Verbosity, output};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    // Initialize tracing
-    init_tracing(&args)?;
-
-    debug!("GBA CLI starting with command: {:?}", args.command);
-
-    // Resolve the project path
-    let project_path = if args.path.as_os_str() == "." {
+    // Resolve the project path, through the workspace with --project
+    let path = if args.path.as_os_str() == "." {
         std::env::current_dir().context("Failed to get current directory")?
     } else {
-        args.path
+        args.path.clone()
+    };
+    let project_path = match &args.project {
+        Some(project) => ProjectWorkspace::find(&path)
+            .and_then(|workspace| workspace.member_path(project))
+            .map_err(CliError::from)?,
+        None => path,
     };
 
+    // Initialize tracing and output
+    init_tracing(&args, &project_path)?;
+    let verbosity = if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    let mut formatter = OutputFormatter::new()
+        .with_verbosity(verbosity)
+        .with_mode(args.output);
+    let headless = matches!(&args.command, Command::Run(run) if run.events);
+    formatter = formatter.with_interactive(!args.no_interactive && !headless);
+    // Headless runs keep stdout for their event stream
+    if headless {
+        formatter =
+            formatter.with_writers(Box::new(std::io::stderr()), Box::new(std::io::stderr()));
+    }
+    output::init(formatter);
+
+    debug!("GBA CLI starting with command: {:?}", args.command);
     debug!("Project path: {}", project_path.display());
 
-    // Execute command
-    match args.command {
+    let project_selected = args.project.is_some();
+    match execute_command(args.command, project_path, project_selected).await {
+        Ok(()) => Ok(()),
+        // JSON output reports the failure as its last event
+        Err(e) if output().is_json() => {
+            let retryable = matches!(
+                e.downcast_ref::<CliError>(),
+                Some(CliError::Core(core)) if core.is_retryable()
+            );
+            output().event(&Event::Error {
+                message: &format!("{e:#}"),
+                retryable,
+            });
+            std::process::exit(1);
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Execute a subcommand.
+async fn execute_command(
+    command: Command,
+    project_path: PathBuf,
+    project_selected: bool,
+) -> Result<()> {
+    match command {
         Command::Init(init_args) => execute_init(init_args).await?,
         Command::Run(run_args) => execute_run(project_path, run_args).await?,
         Command::ListPrompts(list_args) => execute_list_prompts(project_path, list_args).await?,
         Command::Prompt(prompt_args) => execute_prompt(project_path, prompt_args).await?,
+        Command::Compare(compare_args) => execute_compare(project_path, compare_args).await?,
+        Command::Status(args) => execute_status(&project_path, project_selected, &args)?,
+        Command::Cost(cost_args) => execute_cost(project_path, cost_args)?,
+        Command::Diff(diff_args) => execute_diff(project_path, diff_args)?,
+        Command::Worktree(worktree_command) => execute_worktree(project_path, worktree_command)?,
+        Command::State(state_command) => execute_state(project_path, state_command)?,
+        Command::Templates(templates_command) => {
+            execute_templates(project_path, templates_command)?
+        }
+        Command::Validate => templates::validate(&project_path)?,
+        Command::Merge(merge_args) => execute_merge(project_path, merge_args).await?,
+        Command::Replay(replay_args) => execute_replay(&project_path, replay_args).await?,
+        Command::Review(review_args) => execute_review(project_path, review_args).await?,
+        #[cfg(feature = "slack")]
+        Command::Serve(serve_args) => execute_serve(project_path, serve_args).await?,
     }
 
     Ok(())
 }
 
 /// Initialize tracing subscriber.
-fn init_tracing(args: &Args) -> Result<()> {
+fn init_tracing(args: &Args, project_path: &Path) -> Result<()> {
     let log_level = if args.verbose {
         Level::DEBUG
     } else {
@@ -61,18 +139,11 @@ fn init_tracing(args: &Args) -> Result<()> {
         .add_directive("gba_pm=info".parse()?)
         .add_directive("gba_cli=info".parse()?);
 
-    // Console filter - only warnings and errors to stdout
+    // Console filter - only warnings and errors to stderr
     let console_filter = EnvFilter::new("warn,gba_cli=warn");
 
-    // Check if we have a config with log file settings
-    let project_path = if args.path.as_os_str() == "." {
-        std::env::current_dir().context("Failed to get current directory")?
-    } else {
-        args.path.clone()
-    };
-
     // Try to load config for log file settings
-    let log_file = if let Some(config) = ConfigManager::try_load(&project_path) {
+    let log_file = if let Some(config) = ConfigManager::try_load(project_path) {
         let cfg = config.config();
         if !cfg.logging.file.is_empty() {
             Some(Path::new(&cfg.logging.file).to_path_buf())
@@ -109,7 +180,7 @@ fn init_tracing(args: &Args) -> Result<()> {
             .with(filter.clone())
             .with(
                 tracing_subscriber::fmt::layer()
-                    .with_writer(std::io::stdout)
+                    .with_writer(std::io::stderr)
                     .with_ansi(true)
                     .with_target(false)
                     .with_thread_ids(false)
@@ -133,7 +204,7 @@ fn init_tracing(args: &Args) -> Result<()> {
             .with(filter)
             .with(
                 tracing_subscriber::fmt::layer()
-                    .with_writer(std::io::stdout)
+                    .with_writer(std::io::stderr)
                     .with_ansi(true)
                     .with_target(false)
                     .with_thread_ids(false)
@@ -152,7 +223,13 @@ async fn execute_init(args: cli::InitArgs) -> Result<()> {
         .path
         .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
 
-    run::init(&project_path, &args.main_branch, args.repo_url.as_deref()).await?;
+    init::init(&project_path, &args.main_branch, args.repo_url.as_deref()).await?;
+    if args.from_existing {
+        init::migrate_existing(&project_path)?;
+    }
+    if let Some(file) = &args.write_agent_docs {
+        init::write_agent_docs(&project_path, file)?;
+    }
 
     Ok(())
 }
@@ -175,8 +252,60 @@ async fn execute_run(project_path: PathBuf, args: cli::RunArgs) -> Result<()> {
         )
     })?;
 
-    run::run(config, args).await?;
+    // A pull request is opened once the whole pipeline of a feature passed
+    let open_pull_request = args.kind == cli::TaskKind::All
+        && args.features.is_empty()
+        && !args.dry_run
+        && config.config().github.create_pull_request;
+    let feature = args.feature.clone();
+    run::run(config.clone(), args).await?;
+    if open_pull_request {
+        open_feature_pull_request(&project_path, &config, &feature).await?;
+    }
+
+    Ok(())
+}
+
+/// Open a pull request for a feature whose verification passed.
+#[cfg(feature = "github")]
+async fn open_feature_pull_request(
+    project_path: &Path,
+    config: &ConfigManager,
+    feature: &str,
+) -> Result<()> {
+    use gba_core::verify::VerificationReport;
+
+    let feature_id = gba_core::feature::feature_id(feature);
+    let report_path = config.feature_verification_path(&feature_id);
+    let report: VerificationReport = serde_json::from_str(
+        &std::fs::read_to_string(&report_path)
+            .with_context(|| format!("Failed to read {}", report_path.display()))?,
+    )?;
+    if !report.passed() {
+        output().warning("Verification didn't pass; not opening a pull request");
+        return Ok(());
+    }
+
+    let state = gba_core::FeatureState::load(&config.feature_state_path(&feature_id))?;
+    let summary = state
+        .result
+        .and_then(|result| result.summary)
+        .unwrap_or_default();
+    let workspace = gba::Workspace::open(project_path)
+        .with_context(|| format!("Failed to open GBA project at {}", project_path.display()))?;
+    let url = workspace.open_pull_request(feature, &summary).await?;
+    output().success(&format!("Opened pull request: {url}"));
+    Ok(())
+}
 
+/// Open a pull request for a feature whose verification passed.
+#[cfg(not(feature = "github"))]
+async fn open_feature_pull_request(
+    _project_path: &Path,
+    _config: &ConfigManager,
+    _feature: &str,
+) -> Result<()> {
+    output().warning("Not opening a pull request: gba is built without the github feature");
     Ok(())
 }
 
@@ -189,7 +318,7 @@ async fn execute_list_prompts(project_path: PathBuf, args: cli::ListPromptsArgs)
         )
     })?;
 
-    run::list_prompts(config, args.verbose)?;
+    templates::list_prompts(config, args.verbose)?;
 
     Ok(())
 }
@@ -205,7 +334,290 @@ async fn execute_prompt(project_path: PathBuf, args: cli::PromptArgs) -> Result<
         )
     })?;
 
-    run::execute_prompt(config, &args.template, &args.message).await?;
+    prompt::execute_prompt(config, &args.template, &args.message, args.override_quota).await?;
+
+    Ok(())
+}
+
+/// Execute compare command.
+async fn execute_compare(project_path: PathBuf, args: cli::CompareArgs) -> Result<()> {
+    info!(
+        "Comparing templates: {} and {}",
+        args.template_a, args.template_b
+    );
+
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    prompt::compare(config, args).await?;
+
+    Ok(())
+}
+
+/// Execute status command.
+///
+/// In a workspace root, the status of every member project is shown, unless
+/// `--project` selects one.
+fn execute_status(
+    project_path: &Path,
+    project_selected: bool,
+    args: &cli::StatusArgs,
+) -> Result<()> {
+    if !project_selected && ProjectWorkspace::file_path(project_path).is_file() {
+        let workspace = ProjectWorkspace::load(project_path).map_err(CliError::from)?;
+        status::show_workspace_status(&workspace, args)?;
+        return Ok(());
+    }
+
+    let config = ConfigManager::load(project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    status::show_status(&config, args)?;
+
+    Ok(())
+}
+
+/// Execute diff command.
+fn execute_diff(project_path: PathBuf, args: cli::DiffArgs) -> Result<()> {
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    report::show_diff(&config, &args)?;
+
+    Ok(())
+}
+
+/// Execute cost command.
+fn execute_cost(project_path: PathBuf, args: cli::CostArgs) -> Result<()> {
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    report::show_cost(&config, &args)?;
+
+    Ok(())
+}
+
+/// Execute worktree command.
+fn execute_worktree(project_path: PathBuf, command: cli::WorktreeCommand) -> Result<()> {
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    match command {
+        cli::WorktreeCommand::List => worktrees::list_worktrees(&config)?,
+        cli::WorktreeCommand::Prune(args) => {
+            worktrees::prune_worktrees(&config, args.dry_run, args.delete_unmerged)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute state command.
+fn execute_state(project_path: PathBuf, command: cli::StateCommand) -> Result<()> {
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    match command {
+        cli::StateCommand::Check(args) => status::check_states(&config, args.fix)?,
+    }
+
+    Ok(())
+}
+
+/// Execute templates command.
+fn execute_templates(project_path: PathBuf, command: cli::TemplatesCommand) -> Result<()> {
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    match command {
+        cli::TemplatesCommand::Vars(args) => templates::template_variables(&config, &args.name)?,
+        cli::TemplatesCommand::New(args) => {
+            templates::new_template(&config, &args.name, args.from.as_deref(), args.force)?;
+        }
+        cli::TemplatesCommand::Show(args) => templates::show_template(&config, &args.name)?,
+    }
+
+    Ok(())
+}
+
+/// Execute merge command.
+async fn execute_merge(project_path: PathBuf, args: cli::MergeArgs) -> Result<()> {
+    let config = ConfigManager::load(&project_path).with_context(|| {
+        format!(
+            "Failed to load configuration from {}",
+            project_path.display()
+        )
+    })?;
+
+    merge::merge(config, args).await?;
+
+    Ok(())
+}
+
+/// Execute replay command.
+///
+/// Replaying doesn't require a GBA project; the project's TUI key bindings
+/// are used when there is one.
+async fn execute_replay(project_path: &Path, args: cli::ReplayArgs) -> Result<()> {
+    let keys = ConfigManager::try_load(project_path)
+        .map(|config| config.config().tui.keys.clone())
+        .unwrap_or_default();
+
+    replay::replay(&args.transcript, args.tui, args.raw, &keys).await?;
+
+    Ok(())
+}
+
+/// Execute review command.
+///
+/// Runs the fan-out review of the feature and prints it. With `--post-to-pr`
+/// the findings are mapped onto the pull request's diff and posted as a
+/// review, or only shown with `--dry-run`. With `--fail-on` it fails when the
+/// review has findings of that severity or above.
+async fn execute_review(project_path: PathBuf, args: cli::ReviewArgs) -> Result<()> {
+    let workspace = gba::Workspace::open(&project_path)
+        .with_context(|| format!("Failed to open GBA project at {}", project_path.display()))?
+        .with_quota_override(args.override_quota);
+    let review = workspace.review_fanout(&args.feature).await?;
+    output().text(&review.to_markdown());
+
+    #[cfg(feature = "github")]
+    if let Some(number) = args.post_to_pr {
+        post_review(&project_path, &review, number, args.repo, args.dry_run).await?;
+    }
+    if let Some(fail_on) = args.fail_on {
+        check_review_gate(&review, fail_on)?;
+    }
+
+    Ok(())
+}
+
+/// Fail when a review has findings at or above a severity, or when a
+/// persona's review failed and its findings are unknown.
+fn check_review_gate(
+    review: &gba::core::review::MergedReview,
+    fail_on: cli::ReviewSeverity,
+) -> Result<()> {
+    use gba::core::review::Severity;
+
+    let severity = match fail_on {
+        cli::ReviewSeverity::Minor => Severity::Minor,
+        cli::ReviewSeverity::Important => Severity::Important,
+        cli::ReviewSeverity::Critical => Severity::Critical,
+    };
+    let count = review.count_at_least(severity);
+    if count > 0 {
+        anyhow::bail!("{count} review finding(s) at {severity} severity or above");
+    }
+    if !review.failures.is_empty() {
+        anyhow::bail!(
+            "{} persona review(s) failed; findings unknown",
+            review.failures.len()
+        );
+    }
+    Ok(())
+}
+
+/// Post a review to a GitHub pull request, or show what would be posted.
+#[cfg(feature = "github")]
+async fn post_review(
+    project_path: &Path,
+    review: &gba::core::review::MergedReview,
+    number: u64,
+    repo: Option<String>,
+    dry_run: bool,
+) -> Result<()> {
+    use gba::core::pull_request::PullRequestReview;
+    use gba::github::{self, GitHubClient};
+
+    let repo = match repo {
+        Some(repo) => repo,
+        None => gba_core::git::remote_url(project_path, "origin")?
+            .as_deref()
+            .and_then(github::repo_from_url)
+            .context("The origin remote is not a GitHub repository; pass --repo owner/name")?,
+    };
+
+    let client = GitHubClient::from_env();
+    let diff = client.pull_request_diff(&repo, number).await?;
+    let pr_review = PullRequestReview::from_review(review, &diff);
+
+    let out = output();
+    if dry_run {
+        out.section(&format!("Review for {repo}#{number} (dry run)"));
+        out.text(&pr_review.body);
+        for comment in &pr_review.comments {
+            out.subsection(&format!("{}:{}", comment.path, comment.line));
+            out.text(&comment.body);
+        }
+        out.info(&format!(
+            "{} comments on the diff, {} findings in the review body",
+            pr_review.comments.len(),
+            pr_review.unplaced.len()
+        ));
+        return Ok(());
+    }
+
+    let url = client.create_review(&repo, number, &pr_review).await?;
+    out.success(&format!(
+        "Posted review with {} comments: {url}",
+        pr_review.comments.len()
+    ));
+
+    Ok(())
+}
+
+/// Execute serve command.
+///
+/// Serves the Slack bot until interrupted with Ctrl-C.
+#[cfg(feature = "slack")]
+async fn execute_serve(project_path: PathBuf, args: cli::ServeArgs) -> Result<()> {
+    use gba::slack::{SlackBot, SlackConfig};
+
+    let workspace = gba::Workspace::open(&project_path)
+        .with_context(|| format!("Failed to open GBA project at {}", project_path.display()))?;
+    let config = SlackConfig::from_env()?
+        .with_progress_interval(std::time::Duration::from_secs(args.progress_interval));
+    let bot = SlackBot::new(workspace, config);
+
+    let listener = tokio::net::TcpListener::bind(args.bind)
+        .await
+        .with_context(|| format!("Failed to listen on {}", args.bind))?;
+    info!("Serving the Slack bot on http://{}", args.bind);
+    axum::serve(listener, bot.router())
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("Slack bot server failed")?;
 
     Ok(())
 }
@@ -0,0 +1,329 @@
+//! Streaming markdown rendering for console output.
+//!
+//! Agent responses arrive in chunks that may end anywhere, even in the middle
+//! of a word. A [`MarkdownStream`] buffers the chunks and renders each line
+//! as soon as it is complete, so output appears progressively: headings,
+//! bold, italics and inline code are styled, list bullets are replaced, and
+//! fenced code blocks are highlighted.
+
+/// ANSI reset.
+const RESET: &str = "\x1b[0m";
+/// ANSI bold.
+const BOLD: &str = "\x1b[1m";
+/// ANSI italic.
+const ITALIC: &str = "\x1b[3m";
+/// ANSI underline.
+const UNDERLINE: &str = "\x1b[4m";
+/// ANSI gray, for comments and decorations.
+const GRAY: &str = "\x1b[90m";
+/// ANSI green, for strings.
+const GREEN: &str = "\x1b[32m";
+/// ANSI yellow, for inline code and numbers.
+const YELLOW: &str = "\x1b[33m";
+/// ANSI magenta, for keywords.
+const MAGENTA: &str = "\x1b[35m";
+/// ANSI cyan, for headings.
+const CYAN: &str = "\x1b[36m";
+
+/// Keywords highlighted in code blocks, across common languages.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "do",
+    "else",
+    "elif",
+    "enum",
+    "export",
+    "false",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "None",
+    "null",
+    "pub",
+    "return",
+    "self",
+    "Self",
+    "static",
+    "struct",
+    "switch",
+    "trait",
+    "true",
+    "True",
+    "False",
+    "type",
+    "use",
+    "var",
+    "where",
+    "while",
+    "yield",
+];
+
+/// Incremental markdown renderer.
+///
+/// Chunks are added with [`MarkdownStream::push`], and the incomplete last
+/// line is rendered by [`MarkdownStream::finish`] at the end of the stream.
+#[derive(Debug, Default)]
+pub struct MarkdownStream {
+    /// Text of the incomplete last line.
+    pending: String,
+    /// Language of the fenced code block being rendered, if in one.
+    fence: Option<String>,
+}
+
+impl MarkdownStream {
+    /// Create a renderer.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk of markdown.
+    ///
+    /// Returns the rendered complete lines, each ending with a newline.
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let Some(end) = self.pending.rfind('\n') else {
+            return String::new();
+        };
+
+        let complete = self.pending[..end].to_string();
+        self.pending.drain(..=end);
+        complete
+            .split('\n')
+            .map(|line| self.render_line(line.trim_end_matches('\r')) + "\n")
+            .collect()
+    }
+
+    /// Render the remaining incomplete line, if any.
+    pub fn finish(&mut self) -> String {
+        if self.pending.is_empty() {
+            return String::new();
+        }
+        let line = std::mem::take(&mut self.pending);
+        self.render_line(&line) + "\n"
+    }
+
+    /// Render a line of markdown.
+    fn render_line(&mut self, line: &str) -> String {
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            return match self.fence.take() {
+                Some(_) => format!("{GRAY}```{RESET}"),
+                None => {
+                    let language = info.trim().to_string();
+                    let label = format!("{GRAY}```{language}{RESET}");
+                    self.fence = Some(language);
+                    label
+                }
+            };
+        }
+        if let Some(language) = &self.fence {
+            return highlight(line, language);
+        }
+
+        let indent = &line[..line.len() - trimmed.len()];
+        if let Some((level, text)) = heading(trimmed) {
+            let style = if level == 1 {
+                format!("{BOLD}{UNDERLINE}{CYAN}")
+            } else {
+                format!("{BOLD}{CYAN}")
+            };
+            return format!("{style}{}{RESET}", strip_inline(text));
+        }
+        if !trimmed.is_empty() && trimmed.chars().all(|c| matches!(c, '-' | '*' | '_' | ' ')) {
+            let count = trimmed.chars().filter(|c| !c.is_whitespace()).count();
+            if count >= 3 {
+                return format!("{GRAY}{}{RESET}", "─".repeat(40));
+            }
+        }
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            return format!(
+                "{indent}{GRAY}│{RESET} {ITALIC}{}{RESET}",
+                inline(quote.trim())
+            );
+        }
+        for bullet in ["- ", "* ", "+ "] {
+            if let Some(item) = trimmed.strip_prefix(bullet) {
+                return format!("{indent}{CYAN}•{RESET} {}", inline(item));
+            }
+        }
+        if let Some((number, item)) = trimmed.split_once(". ")
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+        {
+            return format!("{indent}{CYAN}{number}.{RESET} {}", inline(item));
+        }
+
+        format!("{indent}{}", inline(trimmed))
+    }
+}
+
+/// Get the level and text of a heading line.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = &line[level..];
+    ((1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')))
+        .then(|| (level, text.trim()))
+}
+
+/// Render inline markup: `**bold**`, `*italic*` and `` `code` ``.
+///
+/// Unclosed markers are kept as they are.
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('`')
+            && let Some(end) = after.find('`')
+        {
+            out.push_str(&format!("{YELLOW}{}{RESET}", &after[..end]));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("**")
+            && let Some(end) = after.find("**")
+            && end > 0
+        {
+            out.push_str(&format!("{BOLD}{}{RESET}", inline(&after[..end])));
+            rest = &after[end + 2..];
+        } else if let Some(after) = rest.strip_prefix('*')
+            && !after.starts_with([' ', '*'])
+            && let Some(end) = after.find('*')
+            && end > 0
+        {
+            out.push_str(&format!("{ITALIC}{}{RESET}", inline(&after[..end])));
+            rest = &after[end + 1..];
+        } else {
+            let next = rest.chars().next().map_or(1, char::len_utf8);
+            out.push_str(&rest[..next]);
+            rest = &rest[next..];
+        }
+    }
+    out
+}
+
+/// Remove inline markers from text styled as a whole, e.g. a heading.
+fn strip_inline(text: &str) -> String {
+    text.replace("**", "").replace('`', "")
+}
+
+/// Highlight a line of code: comments, strings, numbers and keywords.
+fn highlight(line: &str, language: &str) -> String {
+    let comment = match language {
+        "sh" | "bash" | "shell" | "zsh" | "python" | "py" | "yaml" | "yml" | "toml" | "ruby"
+        | "rb" => "#",
+        "sql" | "lua" | "haskell" => "--",
+        _ => "//",
+    };
+
+    let mut out = String::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        if rest.starts_with(comment) {
+            out.push_str(&format!("{GRAY}{rest}{RESET}"));
+            break;
+        }
+        let first = rest.chars().next().unwrap_or_default();
+        // In Rust, a single quote is more often a lifetime than a character
+        if first == '"' || (first == '\'' && !matches!(language, "rust" | "rs")) {
+            let end = string_end(rest, first);
+            out.push_str(&format!("{GREEN}{}{RESET}", &rest[..end]));
+            rest = &rest[end..];
+        } else if first.is_alphanumeric() || first == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            if KEYWORDS.contains(&word) {
+                out.push_str(&format!("{MAGENTA}{word}{RESET}"));
+            } else if first.is_ascii_digit() {
+                out.push_str(&format!("{YELLOW}{word}{RESET}"));
+            } else {
+                out.push_str(word);
+            }
+            rest = &rest[end..];
+        } else {
+            out.push(first);
+            rest = &rest[first.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// Get the byte length of the string literal at the start of `text`, up to
+/// the end of the line when unterminated.
+fn string_end(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return i + c.len_utf8(),
+            _ => escaped = false,
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_renders_complete_lines() {
+        let mut stream = MarkdownStream::new();
+        assert_eq!(stream.push("# Pl"), "");
+        assert_eq!(
+            stream.push("an\n- add **login**"),
+            format!("{BOLD}{UNDERLINE}{CYAN}Plan{RESET}\n")
+        );
+        assert_eq!(
+            stream.finish(),
+            format!("{CYAN}•{RESET} add {BOLD}login{RESET}\n")
+        );
+        assert_eq!(stream.finish(), "");
+    }
+
+    #[test]
+    fn test_inline_markup() {
+        assert_eq!(
+            inline("run `cargo test` *now*"),
+            format!("run {YELLOW}cargo test{RESET} {ITALIC}now{RESET}")
+        );
+        assert_eq!(inline("a * b and **open"), "a * b and **open");
+        assert_eq!(inline("café"), "café");
+    }
+
+    #[test]
+    fn test_fenced_code_is_highlighted() {
+        let mut stream = MarkdownStream::new();
+        let rendered = stream.push("```rust\nlet x = \"# not\"; // note\n```\n**after**\n");
+        let lines = rendered.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], format!("{GRAY}```rust{RESET}"));
+        assert_eq!(
+            lines[1],
+            format!("{MAGENTA}let{RESET} x = {GREEN}\"# not\"{RESET}; {GRAY}// note{RESET}")
+        );
+        assert_eq!(lines[2], format!("{GRAY}```{RESET}"));
+        assert_eq!(lines[3], format!("{BOLD}after{RESET}"));
+    }
+}
@@ -0,0 +1,128 @@
+//! `gba merge`: integrating a feature's branch into the main branch.
+
+use gba_core::feature;
+use gba_core::git::{Integration, IntegrationOutcome};
+use gba_core::state::FeatureState;
+use tracing::instrument;
+
+use crate::cli::{MergeArgs, MergeStrategy, RunArgs, TaskKind};
+use crate::config::ConfigManager;
+use crate::error::{CliError, Result as CliResult};
+use crate::output::output;
+use crate::run::run;
+
+/// Bring a feature branch up to date with the main branch.
+///
+/// Rebases the feature branch onto the main branch, or merges the main branch
+/// into it, inside the feature's worktree. When the integration stops on
+/// conflicts it is left in progress; after they are resolved, `--continue`
+/// finishes it and runs the verification task on the result.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `args` - Merge command arguments.
+///
+/// # Errors
+///
+/// Returns an error if the feature has no worktree, git fails, or the
+/// verification run fails.
+#[instrument(skip(config))]
+pub async fn merge(config: ConfigManager, args: MergeArgs) -> CliResult<()> {
+    let feature_id = feature::feature_id(&args.feature);
+    let state_path = config.feature_state_path(&feature_id);
+    if !state_path.exists() {
+        return Err(CliError::FeatureStateNotFound(args.feature.clone()));
+    }
+    let mut state = FeatureState::load(&state_path).map_err(gba_core::CoreError::from)?;
+    let Some(worktree) = state.context.worktree.clone() else {
+        return Err(CliError::NoWorktree(args.feature.clone()));
+    };
+    let main_branch = config.config().project.repository.main_branch.clone();
+
+    let out = output();
+    if args.abort {
+        gba_core::git::abort_integration(&worktree.path).map_err(gba_core::CoreError::from)?;
+        out.info(&format!(
+            "Aborted integration of {main_branch} into {}",
+            worktree.branch
+        ));
+        state.status.current_step = None;
+        state.status.message = Some("Merge aborted".to_string());
+        state.save(&state_path).map_err(gba_core::CoreError::from)?;
+        return Ok(());
+    }
+
+    let outcome = if args.continue_merge {
+        gba_core::git::continue_integration(&worktree.path)
+    } else {
+        let integration = match args.strategy {
+            MergeStrategy::Rebase => Integration::Rebase,
+            MergeStrategy::Merge => Integration::Merge,
+        };
+        gba_core::git::integrate(&worktree.path, &main_branch, integration)
+    }
+    .map_err(gba_core::CoreError::from)?;
+
+    let message = match &outcome {
+        IntegrationOutcome::UpToDate => {
+            format!("{} is up to date with {main_branch}", worktree.branch)
+        }
+        IntegrationOutcome::Completed => {
+            let (ahead, _) = gba_core::git::ahead_behind(&worktree.path, &main_branch, "HEAD")
+                .map_err(gba_core::CoreError::from)?;
+            format!(
+                "{} is up to date with {main_branch} and {ahead} commit(s) ahead",
+                worktree.branch
+            )
+        }
+        IntegrationOutcome::Conflicts(paths) => {
+            format!(
+                "Conflicts in {} file(s) integrating {main_branch}",
+                paths.len()
+            )
+        }
+    };
+    state.status.current_step = Some("merge".to_string());
+    state.status.message = Some(message.clone());
+    state.save(&state_path).map_err(gba_core::CoreError::from)?;
+
+    match outcome {
+        IntegrationOutcome::Conflicts(paths) => {
+            out.warning(&message);
+            out.section("Conflicted Files");
+            for path in &paths {
+                out.list_item(&path.display().to_string(), "unresolved");
+            }
+            out.print(&format!(
+                "\nResolve the conflicts in {}, then run `gba merge {} --continue` (or `--abort`)",
+                worktree.path.display(),
+                args.feature
+            ));
+        }
+        IntegrationOutcome::Completed if args.continue_merge && !args.no_verify => {
+            out.info(&message);
+            out.info("Verifying the conflict resolution");
+            let verify_args = RunArgs {
+                feature: args.feature.clone(),
+                features: Vec::new(),
+                jobs: 1,
+                kind: TaskKind::Verification,
+                description: None,
+                tui: false,
+                resume: false,
+                force: false,
+                auto_stash: false,
+                commit: false,
+                override_quota: false,
+                tags: Vec::new(),
+                events: false,
+                dry_run: false,
+            };
+            run(config, verify_args).await?;
+        }
+        _ => out.info(&message),
+    }
+
+    Ok(())
+}
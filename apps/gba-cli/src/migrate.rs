@@ -0,0 +1,512 @@
+//! Migration of an existing Claude Code setup into a GBA configuration.
+//!
+//! Repositories often already configure the agent for interactive use:
+//! `.claude/settings.json` (and its `.local` variant) hold a model,
+//! permission rules and ignore patterns, `.mcp.json` the MCP servers, and
+//! `CLAUDE.md` the project's guidance. [`migrate`] imports what has a GBA
+//! equivalent into a [`ProjectConfig`] and records what it skipped and why:
+//!
+//! | Claude Code setting                 | GBA setting                     |
+//! |-------------------------------------|---------------------------------|
+//! | `model`                             | `agent.model`                   |
+//! | `permissions.defaultMode`           | `agent.permissionMode`          |
+//! | `permissions.allow`: `Bash(cmd:*)`  | `agent.sandbox.allowedCommands` |
+//! | `permissions.deny`: `Tool`          | `agent.deniedTools`             |
+//! | `permissions.deny`: `Bash(cmd)`     | `agent.sandbox.blockedPatterns` |
+//! | `permissions.deny`: `Edit(path)`    | `agent.protectedPaths`          |
+//! | `permissions.deny`: `Read(path)`    | `repository.excludePatterns`    |
+//! | `permissions.additionalDirectories` | `agent.sandbox.allowedPaths`    |
+//! | `ignorePatterns`                    | `repository.excludePatterns`    |
+//! | `.mcp.json` `mcpServers`            | `agent.mcpServers`              |
+//!
+//! The agent still reads the project's Claude Code settings and `CLAUDE.md`
+//! itself; importing them lets GBA's own guards enforce them too.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use gba_core::config::{McpServer, PermissionMode, ProjectConfig};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::{CliError, Result};
+
+/// Claude Code settings files, from the lowest precedence to the highest.
+const SETTINGS_FILES: &[&str] = &[".claude/settings.json", ".claude/settings.local.json"];
+
+/// MCP servers file of a project.
+const MCP_FILE: &str = ".mcp.json";
+
+/// Guidance files the agent reads by itself.
+const GUIDANCE_FILES: &[&str] = &["CLAUDE.md", ".claude/CLAUDE.md"];
+
+/// Tools editing files, whose denied paths become protected paths.
+const EDIT_TOOLS: &[&str] = &["Edit", "Write", "MultiEdit", "NotebookEdit"];
+
+/// The outcome of a migration.
+#[derive(Debug, Default)]
+pub struct Migration {
+    /// Files found, relative to the project.
+    pub sources: Vec<String>,
+    /// Settings imported.
+    pub imported: Vec<Imported>,
+    /// Settings left out.
+    pub skipped: Vec<Skipped>,
+}
+
+impl Migration {
+    /// Whether an existing setup was found.
+    #[must_use]
+    pub fn found(&self) -> bool {
+        !self.sources.is_empty()
+    }
+
+    /// Record an imported setting.
+    fn import(&mut self, setting: &str, value: impl Into<String>) {
+        self.imported.push(Imported {
+            setting: setting.to_string(),
+            value: value.into(),
+        });
+    }
+
+    /// Record a skipped setting.
+    fn skip(&mut self, value: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(Skipped {
+            value: value.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// A setting imported into the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported {
+    /// GBA setting, e.g. `agent.deniedTools`.
+    pub setting: String,
+    /// Value added or set.
+    pub value: String,
+}
+
+/// A setting left out of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    /// Claude Code setting, e.g. `permissions.allow: Read`.
+    pub value: String,
+    /// Why it was left out.
+    pub reason: String,
+}
+
+/// The parts of a Claude Code settings file GBA can use.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClaudeSettings {
+    /// Model of the agent.
+    #[serde(default)]
+    model: Option<String>,
+    /// Permission rules.
+    #[serde(default)]
+    permissions: Permissions,
+    /// Paths hidden from the agent; superseded by `Read` deny rules.
+    #[serde(default)]
+    ignore_patterns: Vec<String>,
+}
+
+/// Permission rules of a Claude Code settings file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Permissions {
+    /// Rules allowing tool use without asking, e.g. `Bash(npm test:*)`.
+    #[serde(default)]
+    allow: Vec<String>,
+    /// Rules denying tool use, e.g. `WebFetch` or `Read(./.env)`.
+    #[serde(default)]
+    deny: Vec<String>,
+    /// Permission mode, e.g. `acceptEdits`.
+    #[serde(default)]
+    default_mode: Option<String>,
+    /// Directories the agent may access beyond the working directory.
+    #[serde(default)]
+    additional_directories: Vec<String>,
+}
+
+/// The MCP servers file of a project.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpFile {
+    /// Servers by name.
+    #[serde(default)]
+    mcp_servers: BTreeMap<String, serde_json::Value>,
+}
+
+/// Import a project's Claude Code setup into its configuration.
+///
+/// Values already in the configuration are kept, and list settings only
+/// gain the values they lack, so migrating twice changes nothing.
+///
+/// # Arguments
+///
+/// * `project_path` - Root of the project.
+/// * `config` - Configuration to update.
+///
+/// # Errors
+///
+/// Returns an error if a settings file exists but cannot be read or parsed.
+pub fn migrate(project_path: &Path, config: &mut ProjectConfig) -> Result<Migration> {
+    let mut migration = Migration::default();
+
+    for file in SETTINGS_FILES {
+        let Some(settings) = read_json::<ClaudeSettings>(project_path, file)? else {
+            continue;
+        };
+        migration.sources.push((*file).to_string());
+        import_settings(settings, config, &mut migration);
+    }
+
+    if let Some(mcp) = read_json::<McpFile>(project_path, MCP_FILE)? {
+        migration.sources.push(MCP_FILE.to_string());
+        for (name, server) in mcp.mcp_servers {
+            import_mcp_server(&name, server, config, &mut migration);
+        }
+    }
+
+    for file in GUIDANCE_FILES {
+        if project_path.join(file).is_file() {
+            migration.sources.push((*file).to_string());
+            migration.skip(*file, "kept as is: the agent reads it");
+        }
+    }
+
+    Ok(migration)
+}
+
+/// Read a JSON file of the project, if it exists.
+fn read_json<T: serde::de::DeserializeOwned>(project_path: &Path, file: &str) -> Result<Option<T>> {
+    let path = project_path.join(file);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(CliError::Config(format!(
+                "Failed to read {}: {e}",
+                path.display()
+            )));
+        }
+    };
+    debug!("Migrating {}", path.display());
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| CliError::Config(format!("Failed to parse {}: {e}", path.display())))
+}
+
+/// Import the settings of a Claude Code settings file.
+fn import_settings(
+    settings: ClaudeSettings,
+    config: &mut ProjectConfig,
+    migration: &mut Migration,
+) {
+    if let Some(model) = settings.model
+        && model != config.agent.model
+    {
+        let previous = std::mem::replace(&mut config.agent.model, model.clone());
+        match config.validate_model() {
+            Ok(()) => migration.import("agent.model", model),
+            Err(e) => {
+                config.agent.model = previous;
+                migration.skip(format!("model: {model}"), e.to_string());
+            }
+        }
+    }
+
+    let permissions = settings.permissions;
+    if let Some(mode) = permissions.default_mode {
+        match serde_json::from_value::<PermissionMode>(serde_json::json!(mode)) {
+            Ok(mode) if mode == config.agent.permission_mode => {}
+            Ok(mode) => {
+                config.agent.permission_mode = mode;
+                migration.import("agent.permissionMode", mode.to_string());
+            }
+            Err(_) => migration.skip(
+                format!("permissions.defaultMode: {mode}"),
+                "no GBA permission mode",
+            ),
+        }
+    }
+
+    for rule in permissions.allow {
+        match parse_rule(&rule) {
+            ("Bash", Some(command)) => match command_prefix(command) {
+                Some(command) => add(
+                    &mut config.agent.sandbox.allowed_commands,
+                    "agent.sandbox.allowedCommands",
+                    command,
+                    migration,
+                ),
+                None => migration.skip(format!("permissions.allow: {rule}"), "allows any command"),
+            },
+            _ => migration.skip(
+                format!("permissions.allow: {rule}"),
+                "tools are enabled by each template",
+            ),
+        }
+    }
+
+    for rule in permissions.deny {
+        let skipped = format!("permissions.deny: {rule}");
+        match parse_rule(&rule) {
+            (tool, None) => add(
+                &mut config.agent.denied_tools,
+                "agent.deniedTools",
+                tool,
+                migration,
+            ),
+            ("Bash", Some(command)) => match command_prefix(command) {
+                Some(command) => add(
+                    &mut config.agent.sandbox.blocked_patterns,
+                    "agent.sandbox.blockedPatterns",
+                    command,
+                    migration,
+                ),
+                None => add(
+                    &mut config.agent.denied_tools,
+                    "agent.deniedTools",
+                    "Bash",
+                    migration,
+                ),
+            },
+            (tool, Some(path)) if EDIT_TOOLS.contains(&tool) => match project_pattern(path) {
+                Some(pattern) => add(
+                    &mut config.agent.protected_paths,
+                    "agent.protectedPaths",
+                    &pattern,
+                    migration,
+                ),
+                None => migration.skip(skipped, "outside the project"),
+            },
+            ("Read", Some(path)) => match exclude_pattern(path) {
+                Ok(pattern) => add(
+                    &mut config.repository.exclude_patterns,
+                    "repository.excludePatterns",
+                    &pattern,
+                    migration,
+                ),
+                Err(reason) => migration.skip(skipped, reason),
+            },
+            _ => migration.skip(skipped, "no GBA equivalent"),
+        }
+    }
+
+    for directory in permissions.additional_directories {
+        add(
+            &mut config.agent.sandbox.allowed_paths,
+            "agent.sandbox.allowedPaths",
+            &directory,
+            migration,
+        );
+    }
+
+    for pattern in settings.ignore_patterns {
+        match exclude_pattern(&pattern) {
+            Ok(exclude) => add(
+                &mut config.repository.exclude_patterns,
+                "repository.excludePatterns",
+                &exclude,
+                migration,
+            ),
+            Err(reason) => migration.skip(format!("ignorePatterns: {pattern}"), reason),
+        }
+    }
+}
+
+/// Import an MCP server, unless the configuration has one of that name.
+fn import_mcp_server(
+    name: &str,
+    server: serde_json::Value,
+    config: &mut ProjectConfig,
+    migration: &mut Migration,
+) {
+    if config.agent.mcp_servers.contains_key(name) {
+        return;
+    }
+    match serde_json::from_value::<McpServer>(server) {
+        Ok(server) if !server.command.is_empty() => {
+            config.agent.mcp_servers.insert(name.to_string(), server);
+            migration.import("agent.mcpServers", name);
+        }
+        _ => migration.skip(
+            format!("mcpServers: {name}"),
+            "only stdio servers are supported",
+        ),
+    }
+}
+
+/// Add a value to a list setting, unless it is there already.
+fn add(list: &mut Vec<String>, setting: &str, value: &str, migration: &mut Migration) {
+    if !list.iter().any(|existing| existing == value) {
+        list.push(value.to_string());
+        migration.import(setting, value);
+    }
+}
+
+/// Split a permission rule into its tool and specifier, e.g. `Bash(ls:*)`
+/// into `Bash` and `ls:*`.
+fn parse_rule(rule: &str) -> (&str, Option<&str>) {
+    let rule = rule.trim();
+    match rule.split_once('(') {
+        Some((tool, rest)) => (tool.trim(), rest.strip_suffix(')').map(str::trim)),
+        None => (rule, None),
+    }
+}
+
+/// Get the command prefix of a Bash rule specifier, e.g. `npm test` for
+/// `npm test:*`; `None` if it matches any command.
+fn command_prefix(specifier: &str) -> Option<&str> {
+    let prefix = specifier
+        .strip_suffix(":*")
+        .or_else(|| specifier.strip_suffix('*'))
+        .unwrap_or(specifier)
+        .trim();
+    (!prefix.is_empty()).then_some(prefix)
+}
+
+/// Get a project-relative pattern from a Claude Code path rule: `./x` and
+/// `/x` are relative to the project, while `//x` and `~/x` point outside it.
+fn project_pattern(path: &str) -> Option<String> {
+    if path.starts_with("//") || path.starts_with('~') {
+        return None;
+    }
+    let pattern = path
+        .strip_prefix("./")
+        .or_else(|| path.strip_prefix('/'))
+        .unwrap_or(path);
+    (!pattern.is_empty()).then(|| pattern.to_string())
+}
+
+/// Get an exclude pattern from a Claude Code path rule, e.g. `secrets/` for
+/// `./secrets/**`. Exclude patterns match whole path components, so only a
+/// trailing `**` wildcard can be kept.
+fn exclude_pattern(path: &str) -> std::result::Result<String, &'static str> {
+    let pattern = project_pattern(path).ok_or("outside the project")?;
+    let pattern = match pattern.strip_suffix("/**") {
+        Some(dir) => format!("{dir}/"),
+        None => pattern,
+    };
+    if pattern.contains(['*', '?', '[']) {
+        return Err("wildcards are not supported in exclude patterns");
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_claude_settings() {
+        let dir = std::env::temp_dir().join("gba-test-migrate");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".claude")).unwrap();
+        std::fs::write(
+            dir.join(".claude/settings.json"),
+            r#"{
+                "model": "claude-sonnet-4-5",
+                "permissions": {
+                    "allow": ["Bash(cargo test:*)", "Read", "Bash(*)"],
+                    "deny": [
+                        "WebFetch",
+                        "Bash(git push:*)",
+                        "Edit(./.github/workflows/**)",
+                        "Read(./secrets/**)",
+                        "Read(./*.pem)",
+                        "Write(//etc/hosts)"
+                    ],
+                    "defaultMode": "acceptEdits",
+                    "additionalDirectories": ["../shared"]
+                }
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".claude/settings.local.json"),
+            r#"{"model": "gpt-4o", "ignorePatterns": [".env"]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".mcp.json"),
+            r#"{"mcpServers": {
+                "db": {"command": "npx", "args": ["db-mcp"]},
+                "docs": {"type": "http", "url": "https://example.com/mcp"}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("CLAUDE.md"), "# Guidance\n").unwrap();
+
+        let mut config = ProjectConfig::default();
+        let migration = migrate(&dir, &mut config).unwrap();
+        assert_eq!(
+            migration.sources,
+            [
+                ".claude/settings.json",
+                ".claude/settings.local.json",
+                ".mcp.json",
+                "CLAUDE.md"
+            ]
+        );
+
+        assert_eq!(config.agent.model, "claude-sonnet-4-5");
+        assert_eq!(config.agent.permission_mode, PermissionMode::AcceptEdits);
+        assert_eq!(config.agent.sandbox.allowed_commands, ["cargo test"]);
+        assert_eq!(config.agent.denied_tools, ["WebFetch"]);
+        assert!(
+            config
+                .agent
+                .sandbox
+                .blocked_patterns
+                .contains(&"git push".to_string())
+        );
+        assert_eq!(config.agent.protected_paths, [".github/workflows/**"]);
+        assert!(
+            config
+                .repository
+                .exclude_patterns
+                .ends_with(&["secrets/".to_string(), ".env".to_string()])
+        );
+        assert!(
+            config
+                .agent
+                .sandbox
+                .allowed_paths
+                .contains(&"../shared".to_string())
+        );
+        assert_eq!(config.agent.mcp_servers["db"].command, "npx");
+        assert!(!config.agent.mcp_servers.contains_key("docs"));
+
+        let skipped: Vec<&str> = migration
+            .skipped
+            .iter()
+            .map(|skipped| skipped.value.as_str())
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                "permissions.allow: Read",
+                "permissions.allow: Bash(*)",
+                "permissions.deny: Read(./*.pem)",
+                "permissions.deny: Write(//etc/hosts)",
+                "model: gpt-4o",
+                "mcpServers: docs",
+                "CLAUDE.md"
+            ]
+        );
+
+        // Migrating again finds nothing new
+        let again = migrate(&dir, &mut config).unwrap();
+        assert!(again.imported.is_empty());
+
+        // A project without a setup has nothing to migrate
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(!migrate(&dir, &mut config).unwrap().found());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,14 +1,199 @@
 //! Output formatting and display for GBA CLI.
 //!
-//! This module provides utilities for formatted output to stdout.
+//! Results, such as listings and agent responses, are written to stdout, and
+//! diagnostics, such as status messages and progress, to stderr, so stdout
+//! stays clean for machine-readable output. The [`Verbosity`] decides which
+//! diagnostics are shown. Writers can be injected, e.g. to capture output in
+//! tests.
+//!
+//! Each message is written whole under a lock, so messages printed from
+//! concurrent tasks don't interleave.
+//!
+//! In [`OutputMode::Json`], every message is instead written to stdout as a
+//! JSON object on its own line, with an `event` field naming it, e.g.
+//! `{"event":"message","level":"info","message":"..."}`; runs also report
+//! their progress as [`Event`]s. Tracing logs stay on stderr.
 
+use std::fmt;
 use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+
+use gba_core::compare::DiffLine;
+use gba_core::task::Usage;
+use serde::Serialize;
+
+/// Writer of an output stream.
+pub type Writer = Box<dyn Write + Send>;
+
+/// Which diagnostics are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only warnings and errors.
+    Quiet,
+    /// Status messages too.
+    #[default]
+    Normal,
+    /// Details too.
+    Verbose,
+}
+
+/// Format of the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputMode {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// A JSON object per line, for scripts and CI pipelines.
+    Json,
+}
+
+/// Level of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Status message.
+    Info,
+    /// Something succeeded.
+    Success,
+    /// Warning.
+    Warning,
+    /// Error.
+    Error,
+    /// Detail, shown only in verbose mode.
+    Detail,
+}
+
+/// An event written as a JSON object in [`OutputMode::Json`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "event",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum Event<'a> {
+    /// A message, e.g. printed with [`OutputFormatter::info`].
+    Message {
+        /// Level of the message.
+        level: Level,
+        /// The message.
+        message: &'a str,
+    },
+
+    /// Text of a result.
+    Text {
+        /// The text.
+        text: &'a str,
+    },
+
+    /// Section header of a result.
+    Section {
+        /// Title of the section.
+        title: &'a str,
+    },
+
+    /// Labeled item of a result.
+    Item {
+        /// Label, e.g. `"Name:"`.
+        label: &'a str,
+        /// Value of the item.
+        value: &'a str,
+    },
+
+    /// A prompt template was rendered.
+    PromptRendered {
+        /// Template name.
+        template: &'a str,
+        /// Rendered prompt.
+        prompt: &'a str,
+    },
+
+    /// A task started.
+    TaskStarted {
+        /// Feature name.
+        feature: &'a str,
+        /// Feature ID.
+        feature_id: &'a str,
+        /// Task kind, e.g. `"implementation"`.
+        kind: &'a str,
+        /// Run identifier.
+        run_id: &'a str,
+        /// Directory the agent works in.
+        working_dir: &'a str,
+    },
+
+    /// Text of the agent, streamed as it arrives.
+    Chunk {
+        /// The text.
+        text: &'a str,
+    },
+
+    /// The agent called a tool.
+    ToolCall {
+        /// Tool name.
+        tool: &'a str,
+        /// Summary of the call, e.g. the file read.
+        summary: &'a str,
+    },
+
+    /// Usage of a finished task.
+    Usage {
+        /// Tokens and cost.
+        #[serde(flatten)]
+        usage: &'a Usage,
+        /// Whether the task failed, its usage then being what it spent until
+        /// it stopped.
+        partial: bool,
+    },
+
+    /// The command failed.
+    Error {
+        /// Error message.
+        message: &'a str,
+        /// Whether running the command again may succeed.
+        retryable: bool,
+    },
+}
+
+/// Get the output formatter, created with the defaults unless [`init`] set
+/// it first.
+pub fn output() -> &'static OutputFormatter {
+    OUTPUT.get_or_init(OutputFormatter::new)
+}
+
+/// Set the output formatter, before any output.
+///
+/// Returns `false` if output was already written with another formatter.
+pub fn init(formatter: OutputFormatter) -> bool {
+    OUTPUT.set(formatter).is_ok()
+}
+
+/// Output formatter of the process.
+static OUTPUT: OnceLock<OutputFormatter> = OnceLock::new();
 
 /// Output formatter for CLI messages.
-#[derive(Debug)]
 pub struct OutputFormatter {
     /// Use colors in output.
     colors_enabled: bool,
+    /// Diagnostics shown.
+    verbosity: Verbosity,
+    /// Format of the output.
+    mode: OutputMode,
+    /// Whether the user can be asked for input.
+    interactive: bool,
+    /// Writer of results.
+    stdout: Mutex<Writer>,
+    /// Writer of diagnostics.
+    stderr: Mutex<Writer>,
+}
+
+impl fmt::Debug for OutputFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputFormatter")
+            .field("colors_enabled", &self.colors_enabled)
+            .field("verbosity", &self.verbosity)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
 }
 
 impl OutputFormatter {
@@ -21,73 +206,218 @@ impl OutputFormatter {
     /// Create a new output formatter with color control.
     #[must_use]
     #[allow(dead_code)]
-    pub const fn with_colors(mut self, colors_enabled: bool) -> Self {
+    pub fn with_colors(mut self, colors_enabled: bool) -> Self {
         self.colors_enabled = colors_enabled;
         self
     }
 
-    /// Print a success message.
+    /// Set which diagnostics are shown.
+    #[must_use]
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Set the format of the output; JSON output has no colors.
+    #[must_use]
+    pub fn with_mode(mut self, mode: OutputMode) -> Self {
+        self.mode = mode;
+        if mode == OutputMode::Json {
+            self.colors_enabled = false;
+        }
+        self
+    }
+
+    /// Set whether the user can be asked for input.
+    #[must_use]
+    pub fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Write results and diagnostics to other writers than stdout and
+    /// stderr.
+    ///
+    /// # Arguments
+    ///
+    /// * `stdout` - Writer of results.
+    /// * `stderr` - Writer of diagnostics.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_writers(mut self, stdout: Writer, stderr: Writer) -> Self {
+        self.stdout = Mutex::new(stdout);
+        self.stderr = Mutex::new(stderr);
+        self
+    }
+
+    /// Get which diagnostics are shown.
+    #[must_use]
     #[allow(dead_code)]
+    pub const fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Check if the output is JSON.
+    #[must_use]
+    pub fn is_json(&self) -> bool {
+        self.mode == OutputMode::Json
+    }
+
+    /// Check if the user can be asked for input; never with JSON output.
+    #[must_use]
+    pub fn is_interactive(&self) -> bool {
+        self.interactive && !self.is_json()
+    }
+
+    /// Ask the user a question on stderr and read the answer from stdin.
+    ///
+    /// # Returns
+    ///
+    /// The answer without its line ending, or `None` at the end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if stdin cannot be read.
+    pub fn ask(&self, question: &str) -> io::Result<Option<String>> {
+        self.diagnostic(question);
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(answer.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    /// Write an event to stdout as a JSON line; text output skips events.
+    pub fn event(&self, event: &Event<'_>) {
+        if !self.is_json() {
+            return;
+        }
+        // Events hold only strings, numbers and flags: they always serialize
+        if let Ok(mut line) = serde_json::to_string(event) {
+            line.push('\n');
+            Self::write(&self.stdout, &line);
+        }
+    }
+
+    /// Print text to stdout as is.
+    pub fn text(&self, text: &str) {
+        if self.is_json() {
+            self.event(&Event::Text { text });
+        } else {
+            Self::write(&self.stdout, text);
+        }
+    }
+
+    /// Print a line to stdout.
+    pub fn print(&self, message: &str) {
+        if self.is_json() {
+            self.event(&Event::Text { text: message });
+        } else {
+            self.text(&format!("{message}\n"));
+        }
+    }
+
+    /// Print a detail, shown only in verbose mode.
+    pub fn detail(&self, message: &str) {
+        if self.verbosity < Verbosity::Verbose || self.message(Level::Detail, message) {
+            return;
+        }
+        self.diagnostic(&format!("  {message}\n"));
+    }
+
+    /// Print a success message, unless quiet.
     pub fn success(&self, message: &str) {
+        if self.verbosity == Verbosity::Quiet || self.message(Level::Success, message) {
+            return;
+        }
         let prefix = if self.colors_enabled {
             "\x1b[32m✓\x1b[0m"
         } else {
             "✓"
         };
-        println!("{} {}", prefix, message);
+        self.diagnostic(&format!("{prefix} {message}\n"));
     }
 
     /// Print an error message.
     #[allow(dead_code)]
     pub fn error(&self, message: &str) {
+        if self.message(Level::Error, message) {
+            return;
+        }
         let prefix = if self.colors_enabled {
             "\x1b[31m✗\x1b[0m"
         } else {
             "✗"
         };
-        eprintln!("{} {}", prefix, message);
+        self.diagnostic(&format!("{prefix} {message}\n"));
     }
 
     /// Print a warning message.
     #[allow(dead_code)]
     pub fn warning(&self, message: &str) {
+        if self.message(Level::Warning, message) {
+            return;
+        }
         let prefix = if self.colors_enabled {
             "\x1b[33m⚠\x1b[0m"
         } else {
             "⚠"
         };
-        println!("{} {}", prefix, message);
+        self.diagnostic(&format!("{prefix} {message}\n"));
     }
 
-    /// Print an info message.
+    /// Print an info message, unless quiet.
     pub fn info(&self, message: &str) {
+        if self.verbosity == Verbosity::Quiet || self.message(Level::Info, message) {
+            return;
+        }
         let prefix = if self.colors_enabled {
             "\x1b[36mℹ\x1b[0m"
         } else {
             "ℹ"
         };
-        println!("{} {}", prefix, message);
+        self.diagnostic(&format!("{prefix} {message}\n"));
     }
 
     /// Print a section header.
     pub fn section(&self, title: &str) {
-        println!("\n{}", Self::bold(title, self.colors_enabled));
-        println!("{}", Self::repeat_char("=", title.len()));
+        if self.is_json() {
+            self.event(&Event::Section { title });
+            return;
+        }
+        self.text(&format!(
+            "\n{}\n{}\n",
+            Self::bold(title, self.colors_enabled),
+            Self::repeat_char("=", title.len())
+        ));
     }
 
     /// Print a subsection header.
     #[allow(dead_code)]
     pub fn subsection(&self, title: &str) {
-        println!("\n{}", Self::underline(title, self.colors_enabled));
+        if self.is_json() {
+            self.event(&Event::Section { title });
+            return;
+        }
+        self.text(&format!(
+            "\n{}\n",
+            Self::underline(title, self.colors_enabled)
+        ));
     }
 
     /// Print a list item.
     pub fn list_item(&self, prefix: &str, content: &str) {
-        println!("  {} {}", prefix, content);
+        if self.is_json() {
+            self.event(&Event::Item {
+                label: prefix,
+                value: content,
+            });
+            return;
+        }
+        self.print(&format!("  {prefix} {content}"));
     }
 
     /// Print a bullet list item.
-    #[allow(dead_code)]
     pub fn bullet(&self, content: &str) {
         self.list_item("•", content);
     }
@@ -99,18 +429,63 @@ impl OutputFormatter {
 
     /// Print a separator line.
     pub fn separator(&self) {
-        println!();
-        println!("{}", Self::repeat_char("-", 80));
-        println!();
+        if self.is_json() {
+            return;
+        }
+        self.text(&format!("\n{}\n\n", Self::repeat_char("-", 80)));
     }
 
     /// Print prompt output with formatting.
     pub fn prompt_output(&self, template: &str, content: &str) {
         self.section(template);
-        println!("{}", content);
+        self.print(content);
         self.separator();
     }
 
+    /// Print a line diff of two texts side by side.
+    ///
+    /// The gutter marks changed lines with `|`, lines only on the left with
+    /// `<` and lines only on the right with `>`. Lines longer than their
+    /// column are cut.
+    ///
+    /// # Arguments
+    ///
+    /// * `titles` - Titles of the left and right columns.
+    /// * `lines` - Diff lines.
+    /// * `width` - Total width of the output in characters.
+    pub fn side_by_side(&self, titles: (&str, &str), lines: &[DiffLine], width: usize) {
+        let column = width.saturating_sub(3).max(20) / 2;
+        let mut output = format!(
+            "{} | {}\n{}\n",
+            Self::fit(titles.0, column),
+            titles.1,
+            Self::repeat_char("-", column * 2 + 3)
+        );
+
+        for line in lines {
+            let (left, right) = line.sides();
+            let (gutter, color) = match line {
+                DiffLine::Same(_) => (" ", None),
+                DiffLine::Changed(..) => ("|", Some("33")),
+                DiffLine::Removed(_) => ("<", Some("31")),
+                DiffLine::Added(_) => (">", Some("32")),
+            };
+            let row = format!(
+                "{} {} {}",
+                Self::fit(left.unwrap_or_default(), column),
+                gutter,
+                Self::fit(right.unwrap_or_default(), column).trim_end()
+            );
+            match color {
+                Some(color) if self.colors_enabled => {
+                    output.push_str(&format!("\x1b[{color}m{row}\x1b[0m\n"));
+                }
+                _ => output.push_str(&format!("{row}\n")),
+            }
+        }
+        self.text(&output);
+    }
+
     /// Print prompt list.
     pub fn prompt_list(&self, prompts: &[String], verbose: bool) {
         self.section("Available Prompts");
@@ -123,7 +498,7 @@ impl OutputFormatter {
             }
         }
 
-        println!("\nTotal: {} prompts", prompts.len());
+        self.print(&format!("\nTotal: {} prompts", prompts.len()));
     }
 
     /// Print feature information.
@@ -157,12 +532,15 @@ impl OutputFormatter {
             icon
         };
 
-        println!("{} {}", prefix, text);
+        self.print(&format!("{prefix} {text}"));
     }
 
-    /// Print a progress bar.
+    /// Print a progress bar, unless quiet.
     #[allow(dead_code)]
     pub fn progress(&self, current: usize, total: usize, message: &str) {
+        if self.verbosity == Verbosity::Quiet || self.is_json() {
+            return;
+        }
         let percentage = if total > 0 {
             (current * 100) / total
         } else {
@@ -185,40 +563,60 @@ impl OutputFormatter {
             )
         };
 
-        print!(
-            "\r{} [{}{}] {}/{} ({})",
-            message, bar, percentage, current, total, percentage
-        );
-        io::stdout().flush().unwrap();
+        self.diagnostic(&format!(
+            "\r{message} [{bar}{percentage}] {current}/{total} ({percentage})"
+        ));
     }
 
     /// Clear the progress line.
     #[allow(dead_code)]
     pub fn clear_progress(&self, width: usize) {
-        print!("\r{}\r", Self::repeat_char(" ", width));
-        io::stdout().flush().unwrap();
+        if self.verbosity == Verbosity::Quiet || self.is_json() {
+            return;
+        }
+        self.diagnostic(&format!("\r{}\r", Self::repeat_char(" ", width)));
     }
 
     /// Print formatted code block.
     #[allow(dead_code)]
     pub fn code_block(&self, language: Option<&str>, code: &str) {
-        println!();
-        if let Some(lang) = language {
-            println!("```{}", lang);
-        } else {
-            println!("```");
-        }
-        println!("{}", code);
-        println!("```");
+        self.text(&format!(
+            "\n```{}\n{code}\n```\n",
+            language.unwrap_or_default()
+        ));
     }
 
     /// Check if colors are enabled.
     #[must_use]
-    #[allow(dead_code)]
     pub fn is_colors_enabled(&self) -> bool {
         self.colors_enabled
     }
 
+    /// Write a message as an event in JSON output.
+    ///
+    /// Returns whether it was written, leaving text output to the caller.
+    fn message(&self, level: Level, message: &str) -> bool {
+        if self.is_json() {
+            self.event(&Event::Message { level, message });
+        }
+        self.is_json()
+    }
+
+    /// Write a diagnostic to stderr.
+    fn diagnostic(&self, text: &str) {
+        Self::write(&self.stderr, text);
+    }
+
+    /// Write text whole to a stream.
+    ///
+    /// Write errors, e.g. a closed pipe, are ignored: there is nowhere left
+    /// to report them.
+    fn write(stream: &Mutex<Writer>, text: &str) {
+        let mut writer = stream.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writer.write_all(text.as_bytes());
+        let _ = writer.flush();
+    }
+
     /// Helper function to create bold text.
     fn bold(text: &str, colors_enabled: bool) -> String {
         if colors_enabled {
@@ -238,6 +636,20 @@ impl OutputFormatter {
         }
     }
 
+    /// Helper function to cut or pad text to a number of characters.
+    fn fit(text: &str, width: usize) -> String {
+        let text = text.replace('\t', "    ");
+        if text.chars().count() > width {
+            let cut = text
+                .chars()
+                .take(width.saturating_sub(1))
+                .collect::<String>();
+            format!("{cut}…")
+        } else {
+            format!("{text:<width$}")
+        }
+    }
+
     /// Helper function to repeat a character.
     fn repeat_char(c: &str, count: usize) -> String {
         c.repeat(count)
@@ -248,7 +660,14 @@ impl Default for OutputFormatter {
     fn default() -> Self {
         // Check if we should use colors based on terminal support
         let colors_enabled = atty::is(atty::Stream::Stdout);
-        Self { colors_enabled }
+        Self {
+            colors_enabled,
+            verbosity: Verbosity::Normal,
+            mode: OutputMode::Text,
+            interactive: atty::is(atty::Stream::Stdin),
+            stdout: Mutex::new(Box::new(io::stdout())),
+            stderr: Mutex::new(Box::new(io::stderr())),
+        }
     }
 }
 
@@ -273,36 +692,132 @@ pub fn terminal_supports_colors() -> bool {
     atty::is(atty::Stream::Stdout)
 }
 
-/// Print a simple message without formatting.
-#[allow(dead_code)]
-pub fn print(message: &str) {
-    println!("{}", message);
-}
-
-/// Print a message with a prefix.
-#[allow(dead_code)]
-pub fn print_with_prefix(prefix: &str, message: &str) {
-    println!("{} {}", prefix, message);
-}
-
-/// Print an error message to stderr.
-#[allow(dead_code)]
-pub fn print_error(message: &str) {
-    eprintln!("Error: {}", message);
-}
-
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
 
+    /// Writer capturing output in memory.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Captured {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn captured(verbosity: Verbosity) -> (OutputFormatter, Captured, Captured) {
+        let (stdout, stderr) = (Captured::default(), Captured::default());
+        let formatter = OutputFormatter::new()
+            .with_colors(false)
+            .with_verbosity(verbosity)
+            .with_writers(Box::new(stdout.clone()), Box::new(stderr.clone()));
+        (formatter, stdout, stderr)
+    }
+
     #[test]
     fn test_output_formatter() {
-        let formatter = OutputFormatter::new().with_colors(false);
-        // Just test that it doesn't panic
+        let (formatter, stdout, stderr) = captured(Verbosity::Normal);
         formatter.success("Test success");
         formatter.error("Test error");
         formatter.warning("Test warning");
         formatter.info("Test info");
+        formatter.detail("Test detail");
+        formatter.list_item("Name:", "login");
+
+        // Diagnostics go to stderr, keeping stdout for results
+        assert_eq!(stdout.text(), "  Name: login\n");
+        assert_eq!(
+            stderr.text(),
+            "✓ Test success\n✗ Test error\n⚠ Test warning\nℹ Test info\n"
+        );
+    }
+
+    #[test]
+    fn test_verbosity() {
+        let (formatter, stdout, stderr) = captured(Verbosity::Quiet);
+        formatter.info("Test info");
+        formatter.success("Test success");
+        formatter.warning("Test warning");
+        formatter.print("result");
+        assert_eq!(stdout.text(), "result\n");
+        assert_eq!(stderr.text(), "⚠ Test warning\n");
+
+        let (formatter, _, stderr) = captured(Verbosity::Verbose);
+        formatter.detail("Test detail");
+        assert_eq!(stderr.text(), "  Test detail\n");
+    }
+
+    #[test]
+    fn test_json_output() {
+        let (formatter, stdout, stderr) = captured(Verbosity::Normal);
+        let formatter = formatter.with_mode(OutputMode::Json);
+        formatter.info("Rendering");
+        formatter.detail("Hidden unless verbose");
+        formatter.section("Usage");
+        formatter.list_item("Model:", "sonnet");
+        formatter.separator();
+        formatter.event(&Event::Usage {
+            usage: &Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+                total_cost_usd: 0.5,
+            },
+            partial: false,
+        });
+        formatter.event(&Event::Error {
+            message: "Timed out after 300s",
+            retryable: true,
+        });
+
+        // Every event is a JSON line on stdout
+        assert_eq!(stderr.text(), "");
+        let events = stdout
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                serde_json::json!({"event": "message", "level": "info", "message": "Rendering"}),
+                serde_json::json!({"event": "section", "title": "Usage"}),
+                serde_json::json!({"event": "item", "label": "Model:", "value": "sonnet"}),
+                serde_json::json!({
+                    "event": "usage",
+                    "inputTokens": 10,
+                    "outputTokens": 5,
+                    "totalCostUsd": 0.5,
+                    "partial": false,
+                }),
+                serde_json::json!({"event": "error", "message": "Timed out after 300s", "retryable": true}),
+            ]
+        );
+
+        // Text output skips events
+        let (formatter, stdout, _) = captured(Verbosity::Normal);
+        formatter.event(&Event::Chunk { text: "Hello" });
+        assert_eq!(stdout.text(), "");
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(OutputFormatter::fit("abc", 5), "abc  ");
+        assert_eq!(OutputFormatter::fit("abcdef", 4), "abc…");
+        assert_eq!(OutputFormatter::fit("\tx", 6), "    x ");
     }
 
     #[test]
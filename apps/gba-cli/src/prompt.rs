@@ -0,0 +1,227 @@
+//! `gba prompt` and `gba compare`: running a template outside a feature.
+
+use gba::Workspace;
+use gba_core::compare::{DiffLine, diff_lines};
+use gba_core::context_builder::ContextBuilderConfig;
+use gba_core::feature;
+use gba_core::ledger::{self, Ledger, LedgerEntry};
+use gba_core::pool::{AgentPool, PoolTask};
+use gba_core::{Agent, Task};
+use gba_pm::Context as PromptContext;
+use tracing::{debug, info, instrument};
+
+use crate::cli::CompareArgs;
+use crate::config::ConfigManager;
+use crate::error::{CliError, Result as CliResult};
+use crate::output::output;
+use crate::run::{build_feature_context, fill_template_variables};
+use crate::templates::init_prompt_manager;
+
+/// Execute a single prompt.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `template` - Template name to use.
+/// * `message` - User message to include.
+/// * `override_quota` - Run even if a project quota has been reached.
+///
+/// # Errors
+///
+/// Returns an error if a quota has been reached or execution fails.
+#[instrument(skip(config))]
+pub async fn execute_prompt(
+    config: ConfigManager,
+    template: &str,
+    message: &str,
+    override_quota: bool,
+) -> CliResult<()> {
+    info!("Executing prompt: {}", template);
+    Workspace::open(config.project_path())?
+        .with_quota_override(override_quota)
+        .check_quota()?;
+
+    // Initialize prompt manager
+    let prompt_manager = init_prompt_manager(&config)?;
+
+    // Verify template exists
+    if !prompt_manager.has_prompt(template) {
+        return Err(CliError::template_not_found(template.to_string()));
+    }
+
+    // Build basic context
+    let repo_path = config.project_path().to_str().unwrap_or(".");
+    let mut context = PromptContext::new(repo_path, "main", message);
+    fill_template_variables(&prompt_manager, template, &mut context)?;
+
+    // Get the prompt
+    debug!("Rendering prompt template: {}", template);
+    let prompt = prompt_manager.get_prompt(template, &context)?;
+
+    // Still need to output to console for user-visible command
+    let out = output();
+    out.prompt_output(template, &prompt);
+
+    debug!("Prompt would be sent to agent for execution");
+
+    Ok(())
+}
+
+/// Compare two prompt templates on a feature.
+///
+/// Both templates are rendered with the same prompt context and run
+/// concurrently against the same repository context, each recording its own
+/// transcript. The outputs are printed side by side, followed by the usage of
+/// each run, which is also recorded in the cost ledger.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `args` - Compare command arguments.
+///
+/// # Errors
+///
+/// Returns an error if a template or the model is unknown, a quota has been
+/// reached, or either run fails.
+#[instrument(skip(config))]
+pub async fn compare(config: ConfigManager, args: CompareArgs) -> CliResult<()> {
+    let prompt_manager = init_prompt_manager(&config)?;
+    for template in [&args.template_a, &args.template_b] {
+        if !prompt_manager.has_prompt(template) {
+            return Err(CliError::template_not_found(template.clone()));
+        }
+    }
+
+    let mut agent_config = config.config().agent.clone();
+    if let Some(model) = &args.model {
+        if config.config().model_registry().get(model).is_none() {
+            return Err(CliError::invalid_args(format!(
+                "Unknown model '{model}'; add it under 'models' to use it"
+            )));
+        }
+        agent_config.model = model.clone();
+    }
+    Workspace::open(config.project_path())?
+        .with_quota_override(args.override_quota)
+        .check_quota()?;
+
+    let user_message = args
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("Work on feature: {}", args.feature));
+    let mut context = build_feature_context(
+        &config,
+        &args.feature,
+        args.description.as_deref(),
+        &user_message,
+    );
+    for template in [&args.template_a, &args.template_b] {
+        fill_template_variables(&prompt_manager, template, &mut context)?;
+    }
+    let prompts = [&args.template_a, &args.template_b]
+        .into_iter()
+        .map(|template| {
+            Ok::<_, gba_pm::PromptError>((
+                prompt_manager.get_prompt(template, &context)?,
+                prompt_manager.get_config(template)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let main_branch = &config.config().project.repository.main_branch;
+    let repo_context = gba_core::context_builder::build_context(
+        config.project_path(),
+        main_branch,
+        &ContextBuilderConfig::default()
+            .with_vcs(config.config().repository.vcs)
+            .with_injection_policy(config.config().repository.prompt_injection)
+            .with_binary_placeholders(config.config().repository.binary_placeholders)
+            .with_exclude_vendored(config.config().repository.exclude_vendored)
+            .with_keep_vendored(config.config().repository.keep_vendored.clone()),
+    )
+    .await?;
+
+    let feature_id = feature::feature_id(&args.feature);
+    let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+    let runs = [("a", &args.template_a), ("b", &args.template_b)]
+        .map(|(side, template)| format!("compare-{timestamp}-{side}-{template}"));
+    let tasks = runs
+        .iter()
+        .zip(prompts)
+        .map(|(run_id, (prompt, template))| {
+            let task = Task::from_template(prompt, repo_context.clone(), &template);
+            let agent = Agent::new(agent_config.clone())
+                .with_working_dir(config.project_path())
+                .with_phase("compare")
+                .with_allowed_tools(template.tools)
+                .with_post_processing(config.config().post_process.pipeline("compare"))
+                .with_constraints(template.constraints)
+                .with_model_registry(config.config().model_registry())
+                .with_transcript(config.feature_transcript_path(&feature_id, run_id));
+            PoolTask::new(agent, task)
+        })
+        .collect();
+
+    info!(
+        "Running templates {} and {}",
+        args.template_a, args.template_b
+    );
+    let mut responses = Vec::new();
+    for (run_id, result) in runs.iter().zip(AgentPool::new(2).run(tasks).await) {
+        let response = result?;
+        let entry = LedgerEntry::new(
+            &feature_id,
+            &args.feature,
+            "compare",
+            &agent_config.model,
+            &response.usage,
+        )
+        .with_run_id(Some(run_id.clone()))
+        .with_tags(ledger::merge_tags(&config.config().project.tags, &[]));
+        if let Err(e) = Ledger::new(config.ledger_path()).append(&entry) {
+            output().warning(&format!("Failed to record usage in the cost ledger: {e}"));
+        }
+        responses.push(response);
+    }
+
+    let out = output();
+    out.section(&format!(
+        "{} vs {} ({})",
+        args.template_a, args.template_b, agent_config.model
+    ));
+    let lines = diff_lines(&responses[0].content, &responses[1].content);
+    if lines.iter().any(DiffLine::is_change) {
+        let width = ratatui::crossterm::terminal::size()
+            .map(|(columns, _)| usize::from(columns))
+            .unwrap_or(120);
+        out.side_by_side((&args.template_a, &args.template_b), &lines, width);
+    } else {
+        out.info("Both templates produced the same output");
+    }
+
+    out.section("Usage");
+    for ((template, run_id), response) in [&args.template_a, &args.template_b]
+        .into_iter()
+        .zip(&runs)
+        .zip(&responses)
+    {
+        out.list_item(
+            &format!("{template}:"),
+            &format!(
+                "{} input / {} output tokens, ${:.4}",
+                response.usage.input_tokens,
+                response.usage.output_tokens,
+                response.usage.total_cost_usd
+            ),
+        );
+        out.list_item(
+            "  Transcript:",
+            &config
+                .feature_transcript_path(&feature_id, run_id)
+                .display()
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! `gba replay`: replaying the transcript of a run.
+
+use gba_core::config::TuiKeyBindings;
+use gba_core::task::Usage;
+use gba_core::transcript::{Transcript, TranscriptEvent};
+use std::path::Path;
+use tracing::instrument;
+
+use crate::error::Result as CliResult;
+use crate::keymap::KeyMap;
+use crate::markdown::MarkdownStream;
+use crate::output::output;
+use crate::ui::{AppEvent, Tui};
+
+/// Replay a recorded run from its transcript.
+///
+/// # Arguments
+///
+/// * `path` - Transcript file.
+/// * `tui` - Replay in the TUI instead of on the console.
+/// * `raw` - Print agent text as raw markdown on the console. Text is also
+///   printed raw when stdout is not a terminal.
+/// * `keys` - TUI key bindings.
+///
+/// # Errors
+///
+/// Returns an error if the transcript cannot be loaded or the TUI fails.
+#[instrument(skip(keys))]
+pub async fn replay(path: &Path, tui: bool, raw: bool, keys: &TuiKeyBindings) -> CliResult<()> {
+    let transcript = Transcript::load(path).map_err(gba_core::CoreError::from)?;
+
+    if tui {
+        let mut tui = Tui::new(KeyMap::from_config(keys)?)?;
+        let tx = tui.sender();
+        // Send errors only mean the TUI has already shut down
+        for event in replay_events(&transcript) {
+            let _ = tx.send(event);
+        }
+        let result = tui.run().await;
+        tui.exit()?;
+        return result;
+    }
+
+    let out = output();
+    let mut markdown = (!raw && out.is_colors_enabled()).then(MarkdownStream::new);
+    for event in transcript.events() {
+        match event {
+            TranscriptEvent::Header {
+                version,
+                model,
+                started_at,
+                prompt,
+            } => {
+                out.section("Replay");
+                out.list_item("Model:", model);
+                out.list_item("Started:", &started_at.to_rfc3339());
+                out.list_item("Schema:", &format!("v{version}"));
+                out.prompt_output("Prompt", prompt);
+            }
+            TranscriptEvent::Text { text } => match &mut markdown {
+                Some(markdown) => out.text(&markdown.push(&format!("{text}\n"))),
+                None => out.print(text),
+            },
+            TranscriptEvent::ToolCall { name, input, .. } => {
+                out.list_item("→", &format!("{name} {input}"));
+            }
+            TranscriptEvent::ToolResult {
+                content, is_error, ..
+            } => {
+                if *is_error {
+                    out.warning(&format!("Tool failed: {content}"));
+                }
+            }
+            TranscriptEvent::Usage {
+                input_tokens,
+                output_tokens,
+                total_cost_usd,
+                turns,
+                duration_ms,
+            } => {
+                out.separator();
+                out.info(&format!(
+                    "{turns} turns in {:.1}s, {input_tokens} input / {output_tokens} output tokens, ${total_cost_usd:.4}",
+                    *duration_ms as f64 / 1000.0
+                ));
+            }
+        }
+    }
+
+    if let Some(markdown) = &mut markdown {
+        out.text(&markdown.finish());
+    }
+
+    Ok(())
+}
+
+/// Convert a transcript into the TUI events of the recorded run.
+fn replay_events(transcript: &Transcript) -> Vec<AppEvent> {
+    let mut events = vec![AppEvent::PhaseChange("replay".to_string())];
+    for event in transcript.events() {
+        match event {
+            TranscriptEvent::Text { text } => events.push(AppEvent::AgentChunk(text.clone())),
+            TranscriptEvent::ToolCall { name, .. } => events.push(AppEvent::ToolCall(name.clone())),
+            TranscriptEvent::Usage {
+                input_tokens,
+                output_tokens,
+                total_cost_usd,
+                ..
+            } => events.push(AppEvent::UsageUpdate(Usage {
+                input_tokens: u32::try_from(*input_tokens).unwrap_or(u32::MAX),
+                output_tokens: u32::try_from(*output_tokens).unwrap_or(u32::MAX),
+                total_cost_usd: *total_cost_usd,
+            })),
+            TranscriptEvent::Header { .. } | TranscriptEvent::ToolResult { .. } => {}
+        }
+    }
+    events.push(AppEvent::Finished(None));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_events() {
+        let mut transcript = Transcript::new("sonnet", "Add a login endpoint");
+        transcript.push(TranscriptEvent::Text {
+            text: "Done.".to_string(),
+        });
+        transcript.push(TranscriptEvent::ToolCall {
+            id: "toolu_1".to_string(),
+            name: "Edit".to_string(),
+            input: serde_json::json!({}),
+        });
+        transcript.push(TranscriptEvent::Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            total_cost_usd: 0.01,
+            turns: 1,
+            duration_ms: 1500,
+        });
+
+        let events = replay_events(&transcript);
+        assert_eq!(events.len(), 5);
+        assert!(matches!(&events[0], AppEvent::PhaseChange(phase) if phase == "replay"));
+        assert!(matches!(&events[1], AppEvent::AgentChunk(text) if text == "Done."));
+        assert!(matches!(&events[2], AppEvent::ToolCall(name) if name == "Edit"));
+        assert!(matches!(&events[3], AppEvent::UsageUpdate(usage) if usage.output_tokens == 5));
+        assert!(matches!(events[4], AppEvent::Finished(None)));
+    }
+}
@@ -0,0 +1,155 @@
+//! `gba diff` and `gba cost`: reports on features' changes and spending.
+
+use gba_core::diff::{self, DiffOptions};
+use gba_core::feature;
+use gba_core::ledger::{self, CostBreakdown, CostGroup, Ledger};
+use gba_core::state::FeatureState;
+use serde::Serialize;
+use tracing::{instrument, warn};
+
+use crate::cli::{CostArgs, CostGroupBy, DiffArgs};
+use crate::config::ConfigManager;
+use crate::error::Result as CliResult;
+use crate::output::output;
+use crate::worktrees::worktree_manager;
+
+/// Show the diff of a feature branch against the main branch.
+///
+/// Uses the feature's worktree (including uncommitted changes) when one is
+/// recorded in the feature state, and the feature branch otherwise.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `args` - Diff command arguments.
+///
+/// # Errors
+///
+/// Returns an error if the diff cannot be generated.
+#[instrument(skip(config))]
+pub fn show_diff(config: &ConfigManager, args: &DiffArgs) -> CliResult<()> {
+    let main_branch = &config.config().project.repository.main_branch;
+    let feature_id = feature::feature_id(&args.feature);
+    let options = DiffOptions::new()
+        .with_include(args.include.clone())
+        .with_exclude(args.exclude.clone())
+        .with_max_bytes(args.max_bytes)
+        .with_binary(args.binary);
+
+    let state_path = config.feature_state_path(&feature_id);
+    let worktree = if state_path.exists() {
+        FeatureState::load(&state_path)
+            .map_err(gba_core::CoreError::from)?
+            .context
+            .worktree
+            .filter(|w| w.path.is_dir())
+    } else {
+        None
+    };
+
+    let diff = match worktree {
+        Some(worktree) => diff::diff_worktree(&worktree.path, main_branch, &options),
+        None => {
+            let branch =
+                worktree_manager(config).branch_name(&feature::worktree_name(&args.feature));
+            diff::diff_refs(config.project_path(), main_branch, &branch, &options)
+        }
+    }
+    .map_err(gba_core::CoreError::from)?;
+
+    if diff.is_empty() {
+        output().info(&format!("No changes for feature '{}'", args.feature));
+    } else {
+        output().text(&diff.content);
+    }
+
+    Ok(())
+}
+
+/// Breakdown of the spend printed by `gba cost --json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CostReport {
+    /// Key the spend is grouped by.
+    group_by: CostGroup,
+    /// Start of the period counted, if limited.
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Spend of each group.
+    groups: Vec<CostBreakdown>,
+    /// Spend of every run counted.
+    total: CostBreakdown,
+}
+
+/// Show the spend recorded in the cost ledger, grouped by a key.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `args` - Cost command arguments.
+///
+/// # Errors
+///
+/// Returns an error if the ledger cannot be read.
+pub fn show_cost(config: &ConfigManager, args: &CostArgs) -> CliResult<()> {
+    let group = match args.group_by {
+        CostGroupBy::Feature => CostGroup::Feature,
+        CostGroupBy::Kind => CostGroup::Kind,
+        CostGroupBy::Model => CostGroup::Model,
+        CostGroupBy::Tag => CostGroup::Tag,
+        CostGroupBy::Day => CostGroup::Day,
+    };
+    let mut entries = Ledger::new(config.ledger_path())
+        .entries()
+        .map_err(gba_core::CoreError::from)?;
+    if let Some(since) = args.since {
+        entries.retain(|entry| entry.timestamp >= since);
+    }
+
+    let mut total = CostBreakdown {
+        key: "total".to_string(),
+        runs: entries.len(),
+        ..CostBreakdown::default()
+    };
+    for entry in &entries {
+        total.input_tokens += entry.input_tokens;
+        total.output_tokens += entry.output_tokens;
+        total.total_cost_usd += entry.total_cost_usd;
+    }
+    let groups = ledger::breakdown(&entries, group);
+
+    let out = output();
+    if args.json {
+        let report = CostReport {
+            group_by: group,
+            since: args.since,
+            groups,
+            total,
+        };
+        out.text(&format!(
+            "{}\n",
+            serde_json::to_string_pretty(&report).map_err(gba_core::CoreError::from)?
+        ));
+        return Ok(());
+    }
+
+    out.section(&format!("Cost by {group}"));
+    if entries.is_empty() {
+        out.info("No runs recorded in the cost ledger");
+        return Ok(());
+    }
+    for row in groups {
+        out.list_item(
+            &format!("{}:", row.key),
+            &format!(
+                "${:.4} ({} runs, {} input / {} output tokens)",
+                row.total_cost_usd, row.runs, row.input_tokens, row.output_tokens
+            ),
+        );
+    }
+    out.list_item(
+        "Total:",
+        &format!("${:.4} ({} runs)", total.total_cost_usd, total.runs),
+    );
+
+    Ok(())
+}
@@ -1,181 +1,33 @@
-//! Command execution logic for GBA CLI.
+//! `gba run`: running a feature's phases with an agent.
 //!
-//! This module contains the main command handlers for the CLI.
-
-use gba_core::config::ProjectConfig;
-use gba_pm::{Context as PromptContext, PromptManager};
-use std::fs;
-use std::path::Path;
+//! A run goes through the project's [`gba::Workspace`], which records the
+//! feature state, the cost ledger and the context report like any embedder.
+
+use futures::{StreamExt, stream};
+use gba::Workspace;
+use gba_core::feature;
+use gba_core::state::FeatureState;
+use gba_core::verify::{self, VerificationReport};
+use gba_pm::{Context as PromptContext, DeclaredVariable, PromptManager, ResumeContext};
+use std::time::Instant;
 use tracing::{debug, info, instrument, warn};
 
-use crate::cli::RunArgs;
+use crate::cli::{RunArgs, TaskKind};
 use crate::config::ConfigManager;
+use crate::dry_run::dry_run;
 use crate::error::{CliError, Result as CliResult};
-use crate::output::OutputFormatter;
-use crate::ui::Tui;
-
-/// Get the output formatter.
-fn output() -> &'static OutputFormatter {
-    static OUTPUT: std::sync::OnceLock<OutputFormatter> = std::sync::OnceLock::new();
-    OUTPUT.get_or_init(OutputFormatter::new)
-}
-
-/// Initialize a GBA project.
-///
-/// # Arguments
-///
-/// * `project_path` - Path to the project directory.
-/// * `main_branch` - Name of the main branch.
-/// * `repo_url` - Optional repository URL.
-///
-/// # Errors
-///
-/// Returns an error if initialization fails.
-#[instrument(skip(project_path))]
-pub async fn init(project_path: &Path, main_branch: &str, repo_url: Option<&str>) -> CliResult<()> {
-    info!("Initializing GBA project at {}", project_path.display());
-
-    // Check if .gba directory already exists
-    let gba_dir = project_path.join(".gba");
-    if gba_dir.exists() {
-        debug!(
-            "GBA project already initialized at {}",
-            project_path.display()
-        );
-        return Ok(());
-    }
-
-    let templates_dir = gba_dir.join("templates");
-    let features_dir = gba_dir.join("features");
-
-    fs::create_dir_all(&templates_dir)?;
-    fs::create_dir_all(&features_dir)?;
+use crate::execute::execute;
+use crate::output::{Event, output};
+use crate::templates::init_prompt_manager;
 
-    // Create features README
-    let readme_path = features_dir.join("README.md");
-    let readme_content = "# Features Directory\n\n\
-        This directory contains state files for each feature being developed.\n\n\
-        State files track the progress of task execution and are excluded from git.\n";
-    fs::write(&readme_path, readme_content)?;
-
-    // Detect repository name from path
-    let repo_name = project_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("project");
-
-    // Detect repository URL from git if not provided
-    let detected_url = if repo_url.is_none() {
-        detect_repo_url(project_path)
-    } else {
-        None
-    };
-
-    let final_repo_url = repo_url.or(detected_url.as_deref()).unwrap_or("unknown");
-
-    // Create default configuration
-    debug!("Creating default configuration file");
-
-    let config = ProjectConfig {
-        version: "1.0".to_string(),
-        project: Default::default(),
-        agent: Default::default(),
-        prompts: Default::default(),
-        repository: Default::default(),
-        logging: Default::default(),
-        worktree: Default::default(),
-        limits: Default::default(),
-    };
-
-    // Update project metadata
-    let config_yaml = format!(
-        r#"# GBA Project Configuration
-version: "{}"
-
-# Project metadata
-project:
-  name: "{}"
-  repository:
-    url: "{}"
-    mainBranch: "{}"
-
-# Agent defaults
-agent:
-  model: "{}"
-  maxTokens: {}
-  temperature: {}
-  timeout: {}
-
-# Prompt templates configuration
-prompts:
-  directory: "./.gba/templates"
-  useBundled: true
-
-# Repository scanning settings
-repository:
-  excludePatterns: {}
-  maxFileSize: {}
-
-# Logging configuration
-logging:
-  level: "{}"
-  format: "{}"
-
-# Worktree configuration
-worktree:
-  directory: "./.trees"
-  branchPrefix: "{}"
-
-# Execution limits
-limits:
-  maxTurns: {}
-  maxCostUsd: {}
-"#,
-        config.version,
-        repo_name,
-        final_repo_url,
-        main_branch,
-        config.agent.model,
-        config.agent.max_tokens,
-        config.agent.temperature,
-        config.agent.timeout,
-        serde_yaml::to_string(&config.repository.exclude_patterns).unwrap(),
-        config.repository.max_file_size,
-        config.logging.level,
-        config.logging.format,
-        config.worktree.branch_prefix,
-        config.limits.max_turns,
-        config.limits.max_cost_usd
-    );
-
-    let config_path = ConfigManager::config_file_path(project_path);
-    fs::write(&config_path, config_yaml)?;
-
-    info!(
-        "GBA project initialized successfully at {}",
-        project_path.display()
-    );
-    debug!("Configuration file: {}", config_path.display());
-
-    Ok(())
-}
-
-/// Detect repository URL from git.
-fn detect_repo_url(project_path: &Path) -> Option<String> {
-    let output = std::process::Command::new("git")
-        .args(["-C", project_path.to_str()?, "remote", "get-url", "origin"])
-        .output()
-        .ok()?;
-
-    if output.status.success() {
-        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        None
-    }
-}
+/// Template continuing an interrupted task with `--resume`.
+const RESUME_TEMPLATE: &str = "resume";
 
 /// Execute the run command.
 ///
+/// `--kind all` runs the whole lifecycle of the feature, see
+/// [`run_pipeline`].
+///
 /// # Arguments
 ///
 /// * `config` - Configuration manager.
@@ -191,182 +43,550 @@ pub async fn run(config: ConfigManager, args: RunArgs) -> CliResult<()> {
         kind = %args.kind,
         tui = args.tui,
         resume = args.resume,
+        dry_run = args.dry_run,
         "Starting run command"
     );
-
-    // Check if resuming or starting fresh
-    if args.resume {
-        check_feature_state(&config, &args.feature)?;
+    if !args.features.is_empty() {
+        return run_many(config, args).await;
+    }
+    if args.kind == TaskKind::All {
+        return run_pipeline(config, args).await;
     }
 
-    // Initialize prompt manager
-    let prompt_manager = init_prompt_manager(&config)?;
+    // A fresh runner picks the feature up where another one left it
+    let workspace = open_workspace(&config, &args)?;
+    restore_feature(&workspace, &args.feature)?;
+
+    // Check if resuming or starting fresh
+    let resumed = if args.resume {
+        check_feature_state(&config, &args.feature)?
+    } else {
+        None
+    };
 
     // Get template name
-    let template_name = args.kind.template_name();
+    let template_name = if resumed.is_some() {
+        RESUME_TEMPLATE
+    } else {
+        args.kind.template_name()
+    };
 
     // Verify template exists
+    let prompt_manager = workspace.prompts();
     if !prompt_manager.has_prompt(template_name) {
         return Err(CliError::template_not_found(template_name.to_string()));
     }
 
     // Build context for rendering
-    let context = build_run_context(&config, &args)?;
+    let mut context = build_run_context(&config, &args)?;
+    if args.dry_run {
+        return dry_run(
+            &workspace,
+            &config,
+            &args,
+            template_name,
+            context,
+            resumed.as_ref(),
+        )
+        .await;
+    }
+
+    // Lock the feature, record the task in its state and set the user's
+    // changes aside, creating the worktree on the way into implementation
+    let mut run = workspace
+        .start_run(
+            &args.feature,
+            &args.kind.to_string(),
+            args.description.as_deref(),
+        )
+        .await?;
+    if let Some(stash) = run.stash() {
+        output().info(&format!(
+            "Stashed uncommitted changes in {} as {}",
+            run.working_dir().display(),
+            &stash[..stash.len().min(12)]
+        ));
+    }
+    let tools = prompt_manager.get_config(args.kind.template_name())?.tools;
+    if resumed.is_some() {
+        context = build_resume_context(&config, run.state(), tools);
+    } else if let Some(worktree) = &run.state().context.worktree {
+        context.worktree_path = worktree.path.display().to_string();
+        context.worktree_branch = worktree.branch.clone();
+    }
+
+    // Give the agent the real results of the project's checks to triage
+    let verification = if args.kind == TaskKind::Verification {
+        let report = VerificationReport::new(
+            verify::run_commands(run.working_dir(), &config.config().verification).await,
+        );
+        show_command_results(&report);
+        context.add_extra(verify::COMMANDS_KEY, report.commands_metadata());
+        run.context_mut()
+            .metadata
+            .insert(verify::COMMANDS_KEY.to_string(), report.commands_metadata());
+        Some(report)
+    } else {
+        None
+    };
+    // The metadata the kind prepared is available as template variables
+    if resumed.is_none() {
+        for (name, value) in &run.context().metadata {
+            context.add_extra(name, value.clone());
+        }
+    }
 
     // Get the prompt
+    fill_template_variables(prompt_manager, template_name, &mut context)?;
     debug!("Rendering prompt template: {}", template_name);
-    let _prompt = prompt_manager.get_prompt(template_name, &context)?;
+    let prompt = prompt_manager.get_prompt(template_name, &context)?;
     debug!("Prompt rendered successfully");
-
-    // In TUI mode, start the TUI
-    if args.tui {
-        debug!("Starting TUI mode");
-        let mut tui = Tui::new()?;
-        tui.draw()?;
-        tui.exit()?;
-        debug!("TUI completed");
-    } else {
-        debug!("Executing task (non-TUI mode)");
-        // TODO: Integrate with gba-core Agent for actual execution
-        debug!("Task would be executed here");
+    output().event(&Event::PromptRendered {
+        template: template_name,
+        prompt: &prompt,
+    });
+    let task = workspace.task(&mut run, template_name, prompt)?;
+
+    let mut agent = workspace.agent(&run);
+    if let Some(session_id) = resumed.and_then(|state| state.execution.session_id) {
+        debug!("Resuming agent session {}", session_id);
+        agent = agent.with_resume(session_id);
     }
+    let result = execute(&config, &args, &run, agent, &task)
+        .await
+        .map(|response| run.kind().post_process(response));
+
+    let stashed = run.stash().is_some();
+    let finished = workspace.finish_run(&mut run, result.as_ref());
+    workspace.flush_events().await;
+    if let (Some(sha), Some(worktree)) = (run.commit(), &run.state().context.worktree) {
+        output().info(&format!(
+            "Committed {} on {}",
+            &sha[..sha.len().min(12)],
+            worktree.branch
+        ));
+    }
+    if stashed && run.stash().is_none() {
+        output().info(&format!(
+            "Restored stashed changes in {}",
+            run.working_dir().display()
+        ));
+    }
+    finished?;
+    let response = result?;
 
+    if let Some(report) = verification {
+        workspace.save_verification(&run, &report.with_response(&response))?;
+    }
     Ok(())
 }
 
-/// List available prompts.
+/// Open the workspace of a run with the options of the command.
+///
+/// # Errors
+///
+/// Returns an error if the configuration or templates cannot be loaded.
+fn open_workspace(config: &ConfigManager, args: &RunArgs) -> CliResult<Workspace> {
+    Ok(Workspace::open(config.project_path())?
+        .with_quota_override(args.override_quota)
+        .with_tags(args.tags.clone())
+        .with_force(args.force)
+        .with_auto_stash(args.auto_stash)
+        .with_commit(args.commit))
+}
+
+/// Run a feature through planning, implementation and verification, one
+/// phase after the other, stopping at the first that fails.
+///
+/// Each phase runs like `gba run --kind <phase>`: the plan is saved to the
+/// feature's `plan.md` and implementation creates the worktree. Every phase
+/// is recorded in the feature state under `pipeline`. With `--resume`, the
+/// pipeline picks up at the phase of the interrupted task, keeping the steps
+/// that succeeded before it.
 ///
 /// # Arguments
 ///
 /// * `config` - Configuration manager.
-/// * `verbose` - Whether to show verbose output.
+/// * `args` - Run command arguments.
 ///
 /// # Errors
 ///
-/// Returns an error if listing fails.
-pub fn list_prompts(config: ConfigManager, verbose: bool) -> CliResult<()> {
-    info!("Listing available prompts");
+/// Returns an error if `--dry-run` is set, the feature state cannot be saved
+/// or a phase fails.
+async fn run_pipeline(config: ConfigManager, args: RunArgs) -> CliResult<()> {
+    if args.dry_run {
+        return Err(CliError::invalid_args(
+            "--dry-run previews a single phase; pass --kind planning, implementation or verification"
+                .to_string(),
+        ));
+    }
 
-    // Initialize prompt manager
-    let prompt_manager = init_prompt_manager(&config)?;
+    let workspace = open_workspace(&config, &args)?;
+    restore_feature(&workspace, &args.feature)?;
+    let feature_id = feature::feature_id(&args.feature);
+    let state_path = config.feature_state_path(&feature_id);
+    let mut state = FeatureState::load_or_new(&state_path, &args.feature, &feature_id)
+        .map_err(gba_core::CoreError::from)?;
+    let start = if args.resume {
+        pipeline_resume_index(&state)
+    } else {
+        0
+    };
+    if start == 0 {
+        state.pipeline.clear();
+    } else {
+        state.pipeline.retain(|step| step.success);
+    }
+    state.save(&state_path).map_err(gba_core::CoreError::from)?;
+
+    let phases = TaskKind::All.phases();
+    for (index, kind) in phases.iter().enumerate().skip(start) {
+        output().section(&format!("Phase {}/{}: {kind}", index + 1, phases.len()));
+        let phase_args = RunArgs {
+            kind: *kind,
+            resume: args.resume && index == start,
+            ..args.clone()
+        };
+        let before = FeatureState::load(&state_path).map_err(gba_core::CoreError::from)?;
+        let result = Box::pin(run(config.clone(), phase_args)).await;
+
+        let mut state = FeatureState::load(&state_path).map_err(gba_core::CoreError::from)?;
+        state.record_pipeline_step(&kind.to_string(), &before, result.is_ok());
+        state.save(&state_path).map_err(gba_core::CoreError::from)?;
+        workspace.persist_feature(&args.feature);
+        result?;
+    }
 
-    // Get available templates
-    let templates = prompt_manager.list_prompts();
+    Ok(())
+}
 
-    if templates.is_empty() {
-        debug!("No templates found");
-        return Ok(());
+/// Get how many features can run at once.
+///
+/// Without worktrees, features other than planning run in the project
+/// directory itself, where concurrent runs would overwrite each other's
+/// changes, so they run one at a time.
+fn concurrent_jobs(config: &ConfigManager, args: &RunArgs) -> usize {
+    let jobs = args.jobs.max(1);
+    if jobs > 1
+        && args.kind != TaskKind::Planning
+        && !config.vcs(config.project_path()).supports_worktrees()
+    {
+        return 1;
     }
+    jobs
+}
 
-    debug!("Found {} templates", templates.len());
-    // Still need to output to console for user-visible command
-    let out = output();
-    out.prompt_list(&templates, verbose);
+/// Run several features concurrently, at most `--jobs` at a time.
+///
+/// Each feature runs like `gba run --feature <feature>`, in its own worktree
+/// and agent session. A failing feature doesn't stop the others; the outcome
+/// of each is summarized at the end.
+///
+/// # Errors
+///
+/// Returns an error if verification is asked for a feature without a
+/// worktree, or if any feature failed.
+async fn run_many(config: ConfigManager, args: RunArgs) -> CliResult<()> {
+    let mut features = Vec::new();
+    for feature in &args.features {
+        if !feature.is_empty() && !features.contains(feature) {
+            features.push(feature.clone());
+        }
+    }
+
+    // Verification in the primary checkout stashes the user's changes, which
+    // concurrent runs would race on
+    if args.kind == TaskKind::Verification {
+        for feature in &features {
+            let state_path = config.feature_state_path(&feature::feature_id(feature));
+            let has_worktree =
+                FeatureState::load(&state_path).is_ok_and(|state| state.context.worktree.is_some());
+            if !has_worktree {
+                return Err(CliError::NoWorktree(feature.clone()));
+            }
+        }
+    }
 
+    let out = output();
+    let jobs = concurrent_jobs(&config, &args);
+    if jobs < args.jobs {
+        out.warning("The project has no worktrees; running features one at a time");
+    }
+    out.section(&format!(
+        "Running {} features, {jobs} at a time",
+        features.len()
+    ));
+    let results = stream::iter(&features)
+        .map(|feature| {
+            let feature_args = RunArgs {
+                feature: feature.clone(),
+                features: Vec::new(),
+                ..args.clone()
+            };
+            let config = config.clone();
+            async move {
+                out.info(&format!("{feature}: started"));
+                let started = Instant::now();
+                let result = Box::pin(run(config, feature_args)).await;
+                let elapsed = started.elapsed().as_secs_f64();
+                match &result {
+                    Ok(()) => out.success(&format!("{feature}: finished in {elapsed:.0}s")),
+                    Err(e) => out.error(&format!("{feature}: failed after {elapsed:.0}s: {e}")),
+                }
+                (feature, result)
+            }
+        })
+        .buffered(jobs)
+        .collect::<Vec<_>>()
+        .await;
+
+    out.section("Summary");
+    for (feature, result) in &results {
+        let cost = FeatureState::load(&config.feature_state_path(&feature::feature_id(feature)))
+            .map(|state| state.execution.cost.total_cost_usd)
+            .unwrap_or_default();
+        match result {
+            Ok(()) => out.list_item(&format!("{feature}:"), &format!("done, ${cost:.4}")),
+            Err(e) => out.list_item(&format!("{feature}:"), &format!("failed, ${cost:.4}: {e}")),
+        }
+    }
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        return Err(CliError::FeaturesFailed {
+            failed,
+            total: results.len(),
+        });
+    }
     Ok(())
 }
 
-/// Execute a single prompt.
+/// Get the index of the pipeline phase to resume: the phase of the feature's
+/// interrupted or failed task, or the first phase without one.
+fn pipeline_resume_index(state: &FeatureState) -> usize {
+    if !state.is_resumable() {
+        return 0;
+    }
+    TaskKind::All
+        .phases()
+        .iter()
+        .position(|kind| kind.to_string() == state.task.kind)
+        .unwrap_or(0)
+}
+
+/// Print the results of verification commands.
+fn show_command_results(report: &VerificationReport) {
+    let out = output();
+    for command in &report.commands {
+        let message = format!(
+            "{} ({}): {:.1}s",
+            command.name,
+            command.command,
+            command.duration_ms as f64 / 1000.0
+        );
+        if command.success {
+            out.success(&message);
+        } else if command.timed_out {
+            out.error(&format!("{message}, timed out"));
+        } else {
+            out.error(&format!("{message}, failed"));
+        }
+    }
+}
+
+/// Build context for run command.
 ///
 /// # Arguments
 ///
 /// * `config` - Configuration manager.
-/// * `template` - Template name to use.
-/// * `message` - User message to include.
+/// * `args` - Run command arguments.
 ///
 /// # Errors
 ///
-/// Returns an error if execution fails.
-#[instrument(skip(config))]
-pub async fn execute_prompt(config: ConfigManager, template: &str, message: &str) -> CliResult<()> {
-    info!("Executing prompt: {}", template);
-
-    // Initialize prompt manager
-    let prompt_manager = init_prompt_manager(&config)?;
+/// Returns an error if context building fails.
+fn build_run_context(config: &ConfigManager, args: &RunArgs) -> Result<PromptContext, CliError> {
+    let user_message = args
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("{} for feature: {}", args.kind, args.feature));
 
-    // Verify template exists
-    if !prompt_manager.has_prompt(template) {
-        return Err(CliError::template_not_found(template.to_string()));
+    let mut context = build_feature_context(
+        config,
+        &args.feature,
+        args.description.as_deref(),
+        &user_message,
+    );
+    // Implementation follows the plan stored by the planning run
+    if args.kind == TaskKind::Implementation {
+        match load_plan(config, &feature::feature_id(&args.feature))? {
+            Some(plan) => context.implementation_plan = plan,
+            None => warn!("Implementing {} without a stored plan", args.feature),
+        }
     }
+    Ok(context)
+}
 
-    // Build basic context
-    let repo_path = config.project_path().to_str().unwrap_or(".");
-    let context = PromptContext::new(repo_path, "main", message);
-
-    // Get the prompt
-    debug!("Rendering prompt template: {}", template);
-    let prompt = prompt_manager.get_prompt(template, &context)?;
+/// Fill in the variables a template declares that the context lacks.
+///
+/// In an interactive session the user is asked for each value, showing its
+/// description, choices and default, until the value is valid. Otherwise,
+/// e.g. with `--no-interactive`, variables take their default and a
+/// required variable without one fails the command.
+///
+/// # Arguments
+///
+/// * `prompt_manager` - Prompt manager holding the template.
+/// * `template_name` - Name of the template to render.
+/// * `context` - Context to add the values to.
+///
+/// # Errors
+///
+/// Returns an error if the template is unknown, stdin cannot be read, or a
+/// value is missing or invalid without a user to correct it.
+pub fn fill_template_variables(
+    prompt_manager: &PromptManager,
+    template_name: &str,
+    context: &mut PromptContext,
+) -> CliResult<()> {
+    let declared = prompt_manager.get_config(template_name)?.variables;
+    let missing = context.missing_variables(&declared);
+    if missing.is_empty() {
+        return Ok(());
+    }
 
-    // Still need to output to console for user-visible command
     let out = output();
-    out.prompt_output(template, &prompt);
-
-    debug!("Prompt would be sent to agent for execution");
-
+    let interactive = out.is_interactive();
+    if interactive {
+        out.info(&format!("Template '{template_name}' needs a few values"));
+    }
+    let mut values = Vec::with_capacity(missing.len());
+    for variable in missing {
+        let value = if interactive {
+            loop {
+                let Some(answer) = out.ask(&variable_question(variable))? else {
+                    // Out of input: fall back to the default or fail
+                    break variable.parse("")?;
+                };
+                match variable.parse(&answer) {
+                    Ok(value) => break value,
+                    Err(e) => out.warning(&e.to_string()),
+                }
+            }
+        } else {
+            variable.parse("")?
+        };
+        values.push((variable.name.clone(), value));
+    }
+    for (name, value) in values {
+        context.add_extra(&name, value);
+    }
     Ok(())
 }
 
-/// Initialize the prompt manager.
+/// Build the question asking for a template variable, e.g.
+/// `tone (Tone of the reply) [formal, casual] (formal): `.
 ///
 /// # Arguments
 ///
-/// * `config` - Configuration manager.
+/// * `variable` - Variable to ask for.
+fn variable_question(variable: &DeclaredVariable) -> String {
+    format!("{}{}: ", variable.name, variable_hint(variable))
+}
+
+/// Describe a template variable: its description, choices and default, or
+/// whether it is required.
 ///
-/// # Errors
+/// # Arguments
 ///
-/// Returns an error if initialization fails.
-fn init_prompt_manager(config: &ConfigManager) -> Result<PromptManager, CliError> {
-    let templates_dir = config.templates_dir();
-    let use_bundled = config.config().prompts.use_bundled;
-
-    debug!(
-        "Initializing prompt manager with templates dir: {}",
-        templates_dir.display()
-    );
+/// * `variable` - Variable to describe.
+pub fn variable_hint(variable: &DeclaredVariable) -> String {
+    let mut hint = String::new();
+    if !variable.description.is_empty() {
+        hint.push_str(&format!(" ({})", variable.description));
+    }
+    if !variable.choices.is_empty() {
+        hint.push_str(&format!(" [{}]", variable.choices.join(", ")));
+    }
+    match &variable.default {
+        Some(default) => hint.push_str(&format!(" ({default})")),
+        None if variable.required => hint.push_str(" (required)"),
+        None => {}
+    }
+    hint
+}
 
-    PromptManager::with_local_dir(templates_dir, use_bundled)
-        .map_err(|e| CliError::Config(format!("Failed to initialize prompt manager: {e}")))
+/// Restore a feature from the configured store if the project has no state
+/// for it, telling the user.
+///
+/// # Errors
+///
+/// Returns an error if the feature cannot be downloaded.
+fn restore_feature(workspace: &Workspace, feature: &str) -> CliResult<()> {
+    let count = workspace.restore_feature(feature)?;
+    if count > 0 {
+        output().info(&format!(
+            "Restored {count} files of the feature from the {} store",
+            workspace.config().storage.backend
+        ));
+    }
+    Ok(())
 }
 
-/// Build context for run command.
+/// Load the plan stored for a feature, if any.
 ///
 /// # Arguments
 ///
 /// * `config` - Configuration manager.
-/// * `args` - Run command arguments.
+/// * `feature_id` - Feature identifier.
+fn load_plan(config: &ConfigManager, feature_id: &str) -> std::io::Result<Option<String>> {
+    gba_core::plan::load(&config.features_dir().join(feature_id))
+}
+
+/// Extra variables [`build_feature_context`] adds to the prompt context.
+pub const FEATURE_VARIABLES: &[&str] = &[
+    "feature_name",
+    "feature_id",
+    "feature_description",
+    "main_branch",
+    "current_branch",
+    "head_sha",
+];
+
+/// Build the prompt context of a feature.
 ///
-/// # Errors
+/// # Arguments
 ///
-/// Returns an error if context building fails.
-fn build_run_context(config: &ConfigManager, args: &RunArgs) -> Result<PromptContext, CliError> {
+/// * `config` - Configuration manager.
+/// * `feature_name` - Feature name.
+/// * `description` - Feature description, if any.
+/// * `user_message` - User message of the prompt.
+pub fn build_feature_context(
+    config: &ConfigManager,
+    feature_name: &str,
+    description: Option<&str>,
+    user_message: &str,
+) -> PromptContext {
     let repo_path = config.project_path().to_str().unwrap_or(".");
     let main_branch = config.config().project.repository.main_branch.clone();
-    let feature_id = format!("{:04}", feature_id_from_name(&args.feature));
+    let feature_id = feature::feature_id(feature_name);
 
-    let user_message = args
-        .description
-        .clone()
-        .unwrap_or_else(|| format!("{} for feature: {}", args.kind, args.feature));
-
-    let mut context = PromptContext::new(repo_path, &main_branch, &user_message);
+    let mut context = PromptContext::new(repo_path, &main_branch, user_message);
 
     // Add feature context
-    context.add_extra("feature_name", serde_json::json!(args.feature));
+    context.add_extra("feature_name", serde_json::json!(feature_name));
     context.add_extra("feature_id", serde_json::json!(feature_id));
-    context.add_extra("feature_description", serde_json::json!(args.description));
+    context.add_extra("feature_description", serde_json::json!(description));
     context.add_extra("main_branch", serde_json::json!(main_branch));
 
-    Ok(context)
-}
-
-/// Generate a feature ID from a feature name.
-fn feature_id_from_name(name: &str) -> u32 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+    // Repository metadata is best-effort; GBA projects needn't be git repos
+    match config.vcs(config.project_path()).info() {
+        Ok(repo) => {
+            context.add_extra("current_branch", serde_json::json!(repo.branch));
+            context.add_extra("head_sha", serde_json::json!(repo.head));
+        }
+        Err(e) => debug!("No repository metadata: {}", e),
+    }
 
-    let mut hasher = DefaultHasher::new();
-    name.hash(&mut hasher);
-    (hasher.finish() % 10000) as u32
+    context
 }
 
 /// Check feature state for resumption.
@@ -376,24 +596,69 @@ fn feature_id_from_name(name: &str) -> u32 {
 /// * `config` - Configuration manager.
 /// * `feature` - Feature name.
 ///
+/// # Returns
+///
+/// The state of the task to resume, or `None` to start fresh when the
+/// feature has no state or its task is neither in progress nor failed.
+///
 /// # Errors
 ///
-/// Returns an error if state check fails.
-fn check_feature_state(config: &ConfigManager, feature: &str) -> Result<(), CliError> {
-    let feature_id = format!("{:04}", feature_id_from_name(feature));
+/// Returns an error if the state file cannot be read or parsed.
+fn check_feature_state(
+    config: &ConfigManager,
+    feature: &str,
+) -> Result<Option<FeatureState>, CliError> {
+    let feature_id = feature::feature_id(feature);
     let state_path = config.feature_state_path(&feature_id);
 
     if !state_path.exists() {
         warn!("No previous state found, starting fresh");
-        return Ok(());
+        return Ok(None);
     }
 
     info!("Found previous state at {}", state_path.display());
+    let state = FeatureState::load(&state_path).map_err(gba_core::CoreError::from)?;
+    if !state.is_resumable() {
+        output().warning(&format!(
+            "Feature {feature} has no interrupted task to resume, starting fresh"
+        ));
+        return Ok(None);
+    }
 
-    // TODO: Load and validate state file
-    let _state_content = fs::read_to_string(&state_path)?;
+    Ok(Some(state))
+}
 
-    Ok(())
+/// Build the prompt context resuming the task of a feature from its state.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `state` - Feature state of the run.
+/// * `tools` - Tools of the task's template.
+pub fn build_resume_context(
+    config: &ConfigManager,
+    state: &FeatureState,
+    tools: Vec<String>,
+) -> PromptContext {
+    let main_branch = config.config().project.repository.main_branch.clone();
+    let plan = load_plan(config, &state.feature.id)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let mut context = PromptContext::for_resume(ResumeContext {
+        implementation_plan: plan,
+        tools,
+        ..ResumeContext::from(state)
+    });
+    // Without a worktree the feature is developed in the project itself
+    if state.context.worktree.is_none() {
+        context.worktree_path = config.project_path().display().to_string();
+        context.worktree_branch = main_branch.clone();
+    }
+    context.repo_path = config.project_path().display().to_string();
+    context.main_branch = main_branch;
+    context
 }
 
 /// Create implementation plan.
@@ -430,7 +695,7 @@ pub async fn create_plan(
     // Build context
     let repo_path = config.project_path().to_str().unwrap_or(".");
     let main_branch = config.config().project.repository.main_branch.clone();
-    let feature_id = format!("{:04}", feature_id_from_name(feature_name));
+    let feature_id = feature::feature_id(feature_name);
 
     let mut context = PromptContext::new(
         repo_path,
@@ -502,21 +767,14 @@ pub async fn verify_implementation(_config: &ConfigManager, feature_name: &str)
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cli::TaskKind;
-
-    #[test]
-    fn test_feature_id_from_name() {
-        let id1 = feature_id_from_name("test-feature");
-        let id2 = feature_id_from_name("test-feature");
-        assert_eq!(id1, id2);
-
-        let id3 = feature_id_from_name("different-feature");
-        assert_ne!(id1, id3);
-    }
+    use gba_core::config::ProjectConfig;
+    use gba_core::state::TaskStatus;
+    use std::fs;
 
     #[test]
     fn test_build_run_context() {
         let temp_dir = std::env::temp_dir().join("gba-test-build-context");
+        fs::remove_dir_all(&temp_dir).ok();
         fs::create_dir_all(&temp_dir).unwrap();
         let gba_dir = temp_dir.join(".gba");
         fs::create_dir_all(&gba_dir).unwrap();
@@ -530,15 +788,159 @@ mod tests {
 
         let args = RunArgs {
             feature: "test".to_string(),
+            features: Vec::new(),
+            jobs: 1,
             kind: TaskKind::Planning,
             description: Some("Test feature".to_string()),
             tui: false,
             resume: false,
+            force: false,
+            auto_stash: false,
+            commit: false,
+            override_quota: false,
+            tags: Vec::new(),
+            events: false,
+            dry_run: false,
         };
 
         let result = build_run_context(&config_manager, &args);
         assert!(result.is_ok());
 
+        // Implementation picks up the stored plan
+        let args = RunArgs {
+            kind: TaskKind::Implementation,
+            ..args
+        };
+        let context = build_run_context(&config_manager, &args).unwrap();
+        assert!(context.implementation_plan.is_empty());
+        let feature_dir = config_manager
+            .features_dir()
+            .join(feature::feature_id("test"));
+        gba_core::plan::save(&feature_dir, "1. Add login").unwrap();
+        let context = build_run_context(&config_manager, &args).unwrap();
+        assert_eq!(context.implementation_plan, "1. Add login");
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+
+    #[test]
+    fn test_check_feature_state_for_resume() {
+        let temp_dir = std::env::temp_dir().join("gba-test-resume-state");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join(".gba")).unwrap();
+        let config_yaml = serde_yaml::to_string(&ProjectConfig::default_config()).unwrap();
+        fs::write(temp_dir.join(".gba").join("config.yml"), config_yaml).unwrap();
+        let config_manager = ConfigManager::load(&temp_dir).unwrap();
+
+        // Without a state, the run starts fresh
+        assert!(
+            check_feature_state(&config_manager, "Add Auth")
+                .unwrap()
+                .is_none()
+        );
+
+        let id = feature::feature_id("Add Auth");
+        let state_path = config_manager.feature_state_path(&id);
+        let mut state = FeatureState::new("Add Auth", &id);
+        state.task.kind = "implementation".to_string();
+        state.status.state = TaskStatus::Completed;
+        state.save(&state_path).unwrap();
+        assert!(
+            check_feature_state(&config_manager, "Add Auth")
+                .unwrap()
+                .is_none()
+        );
+
+        state.status.state = TaskStatus::InProgress;
+        state.status.current_phase = Some("implementation".to_string());
+        state.status.current_step = Some("Edit src/auth.rs".to_string());
+        state.execution.turns = 7;
+        state.execution.session_id = Some("session-1".to_string());
+        state.save(&state_path).unwrap();
+        gba_core::plan::save(&config_manager.features_dir().join(&id), "1. Add login").unwrap();
+
+        let resumed = check_feature_state(&config_manager, "Add Auth")
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.execution.session_id.as_deref(), Some("session-1"));
+        let context = build_resume_context(&config_manager, &resumed, vec!["Read".to_string()]);
+        assert_eq!(context.task_kind, "implementation");
+        assert_eq!(context.current_step, "Edit src/auth.rs");
+        assert_eq!(context.turns_so_far, 7);
+        assert_eq!(context.implementation_plan, "1. Add login");
+        assert_eq!(context.worktree_branch, "main");
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+
+    #[test]
+    fn test_pipeline_resume_index() {
+        let mut state = FeatureState::new("Add Auth", "0001");
+        assert_eq!(pipeline_resume_index(&state), 0);
+
+        // The pipeline picks up at the phase that failed
+        state.task.kind = "implementation".to_string();
+        state.status.state = TaskStatus::Failed;
+        assert_eq!(pipeline_resume_index(&state), 1);
+        state.task.kind = "verification".to_string();
+        state.status.state = TaskStatus::InProgress;
+        assert_eq!(pipeline_resume_index(&state), 2);
+
+        state.status.state = TaskStatus::Completed;
+        assert_eq!(pipeline_resume_index(&state), 0);
+    }
+
+    #[test]
+    fn test_variable_question() {
+        let mut variable = DeclaredVariable {
+            name: "tone".to_string(),
+            ..DeclaredVariable::default()
+        };
+        assert_eq!(variable_question(&variable), "tone: ");
+
+        variable.required = true;
+        assert_eq!(variable_question(&variable), "tone (required): ");
+
+        variable.description = "Tone of the reply".to_string();
+        variable.choices = vec!["formal".to_string(), "casual".to_string()];
+        variable.default = Some("formal".to_string());
+        assert_eq!(
+            variable_question(&variable),
+            "tone (Tone of the reply) [formal, casual] (formal): "
+        );
+    }
+
+    #[test]
+    fn test_concurrent_jobs_without_worktrees() {
+        let temp_dir = std::env::temp_dir().join("gba-test-concurrent-jobs");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join(".gba")).unwrap();
+        let config_yaml = serde_yaml::to_string(&ProjectConfig::default_config()).unwrap();
+        fs::write(temp_dir.join(".gba").join("config.yml"), config_yaml).unwrap();
+        let config_manager = ConfigManager::load(&temp_dir).unwrap();
+
+        let mut args = RunArgs {
+            feature: String::new(),
+            features: vec!["add-auth".to_string(), "add-billing".to_string()],
+            jobs: 4,
+            kind: TaskKind::Planning,
+            description: None,
+            tui: false,
+            resume: false,
+            force: false,
+            auto_stash: false,
+            commit: false,
+            override_quota: false,
+            tags: Vec::new(),
+            events: false,
+            dry_run: false,
+        };
+        assert_eq!(concurrent_jobs(&config_manager, &args), 4);
+        args.kind = TaskKind::Implementation;
+        assert_eq!(concurrent_jobs(&config_manager, &args), 1);
+        args.kind = TaskKind::All;
+        assert_eq!(concurrent_jobs(&config_manager, &args), 1);
+
         fs::remove_dir_all(temp_dir).ok();
     }
 }
@@ -0,0 +1,430 @@
+//! `gba status` and `gba state check`: the state of the project's features.
+
+use gba_core::feature;
+use gba_core::ledger::Ledger;
+use gba_core::state::{FeatureState, TaskStatus};
+use gba_core::state_check;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{instrument, warn};
+
+use crate::cli::StatusArgs;
+use crate::config::{ConfigManager, ProjectWorkspace};
+use crate::error::{CliError, Result as CliResult};
+use crate::output::output;
+
+/// Characters of a step shown in the `gba status` table.
+const STATUS_STEP_WIDTH: usize = 40;
+
+/// Status of a feature printed by `gba status`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FeatureStatus {
+    /// Feature identifier.
+    id: String,
+    /// Feature name.
+    name: String,
+    /// Task kind of the last run, e.g. `implementation`.
+    kind: String,
+    /// Status of the last run.
+    status: TaskStatus,
+    /// Phase the run is in.
+    phase: Option<String>,
+    /// Step the run is at.
+    step: Option<String>,
+    /// Agent turns of the last run.
+    turns: u32,
+    /// Cost of the feature so far in USD.
+    cost_usd: f64,
+    /// Worktree of the feature, if any.
+    worktree: Option<PathBuf>,
+    /// When the state was last saved.
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<&FeatureState> for FeatureStatus {
+    fn from(state: &FeatureState) -> Self {
+        Self {
+            id: state.feature.id.clone(),
+            name: state.feature.name.clone(),
+            kind: state.task.kind.clone(),
+            status: state.status.state,
+            phase: state.status.current_phase.clone(),
+            step: state.status.current_step.clone(),
+            turns: state.execution.turns,
+            cost_usd: state.execution.cost.total_cost_usd,
+            worktree: state
+                .context
+                .worktree
+                .as_ref()
+                .map(|worktree| worktree.path.clone()),
+            updated_at: state.timestamps.updated_at,
+        }
+    }
+}
+
+/// Features of a project printed by `gba status --json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectStatus {
+    /// Member name, in a workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    /// Status of each feature.
+    features: Vec<FeatureStatus>,
+    /// Cost of the features in USD.
+    total_cost_usd: f64,
+}
+
+impl ProjectStatus {
+    /// Summarize the states of features.
+    fn new(project: Option<String>, features: &[FeatureState]) -> Self {
+        let features = features.iter().map(FeatureStatus::from).collect::<Vec<_>>();
+        // An empty f64 sum is -0.0
+        let total_cost_usd = features
+            .iter()
+            .fold(0.0, |total, feature| total + feature.cost_usd);
+        Self {
+            project,
+            features,
+            total_cost_usd,
+        }
+    }
+}
+
+/// Show the status of the features of a project.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `args` - Status command arguments.
+///
+/// # Errors
+///
+/// Returns an error if the features directory cannot be read, or the
+/// feature asked for has no state.
+#[instrument(skip(config))]
+pub fn show_status(config: &ConfigManager, args: &StatusArgs) -> CliResult<()> {
+    let mut features = load_feature_states(config)?;
+    if let Some(feature) = &args.feature {
+        features.retain(|state| is_feature(state, feature));
+        if features.is_empty() {
+            return Err(CliError::FeatureStateNotFound(feature.clone()));
+        }
+    }
+    let status = ProjectStatus::new(None, &features);
+
+    let out = output();
+    if args.json {
+        out.text(&format!(
+            "{}\n",
+            serde_json::to_string_pretty(&status).map_err(gba_core::CoreError::from)?
+        ));
+        return Ok(());
+    }
+
+    out.section("Features");
+    show_features(&status.features);
+    out.print(&format!(
+        "\nTotal: {} features, ${:.2}",
+        status.features.len(),
+        status.total_cost_usd
+    ));
+
+    Ok(())
+}
+
+/// Show the status of the features of every member of a workspace.
+///
+/// Members whose configuration cannot be loaded are reported and skipped.
+///
+/// # Arguments
+///
+/// * `workspace` - The workspace.
+/// * `args` - Status command arguments; a feature is looked up in every
+///   member.
+///
+/// # Errors
+///
+/// Returns an error if the features directory of a member cannot be read.
+#[instrument(skip(workspace), fields(root = %workspace.root().display()))]
+pub fn show_workspace_status(workspace: &ProjectWorkspace, args: &StatusArgs) -> CliResult<()> {
+    let out = output();
+    let mut projects = Vec::new();
+
+    for (name, path) in workspace.members() {
+        let config = match ConfigManager::load(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                out.warning(&format!("Skipping {name}: {e}"));
+                continue;
+            }
+        };
+        let mut features = load_feature_states(&config)?;
+        if let Some(feature) = &args.feature {
+            features.retain(|state| is_feature(state, feature));
+        }
+        projects.push((path, ProjectStatus::new(Some(name.to_string()), &features)));
+    }
+
+    if args.json {
+        let projects = projects
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect::<Vec<_>>();
+        out.text(&format!(
+            "{}\n",
+            serde_json::to_string_pretty(&projects).map_err(gba_core::CoreError::from)?
+        ));
+        return Ok(());
+    }
+
+    let mut count = 0;
+    let mut cost = 0.0;
+    for (path, status) in &projects {
+        out.section(&format!(
+            "{} ({})",
+            status.project.as_deref().unwrap_or_default(),
+            path.display()
+        ));
+        show_features(&status.features);
+        count += status.features.len();
+        cost += status.total_cost_usd;
+    }
+    out.print(&format!(
+        "\nTotal: {count} features in {} projects, ${cost:.2}",
+        workspace.members().len()
+    ));
+
+    Ok(())
+}
+
+/// Check whether a state is of a feature given by name or identifier.
+fn is_feature(state: &FeatureState, feature: &str) -> bool {
+    state.feature.id == feature
+        || state.feature.name == feature
+        || state.feature.id == feature::feature_id(feature)
+}
+
+/// Load the states of the features of a project, by feature identifier.
+///
+/// Features without a readable state file are skipped with a warning.
+fn load_feature_states(config: &ConfigManager) -> CliResult<Vec<FeatureState>> {
+    let features_dir = config.features_dir();
+    if !features_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut ids = fs::read_dir(&features_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    ids.sort();
+
+    let mut states = Vec::new();
+    for id in ids {
+        let path = config.feature_state_path(&id);
+        if !path.exists() {
+            continue;
+        }
+        match FeatureState::load(&path) {
+            Ok(state) => states.push(state),
+            Err(e) => warn!("Skipping feature {id}: {e}"),
+        }
+    }
+    Ok(states)
+}
+
+/// Print a table of features: phase, step, turns, cost, worktree and when
+/// each was last updated.
+fn show_features(features: &[FeatureStatus]) {
+    let out = output();
+    if features.is_empty() {
+        out.info("No features");
+        return;
+    }
+    let rows = features.iter().map(status_row).collect::<Vec<_>>();
+    let header = [
+        "FEATURE", "PHASE", "STATUS", "STEP", "TURNS", "COST", "WORKTREE", "UPDATED",
+    ]
+    .map(String::from);
+    let mut widths = header.each_ref().map(|cell| cell.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.print(line.trim_end());
+    }
+}
+
+/// Format the cells of a feature in the `gba status` table.
+fn status_row(feature: &FeatureStatus) -> [String; 8] {
+    let status = match feature.status {
+        TaskStatus::Pending => "pending",
+        TaskStatus::InProgress => "in progress",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Failed => "failed",
+    };
+    let phase = feature
+        .phase
+        .as_deref()
+        .or((!feature.kind.is_empty()).then_some(feature.kind.as_str()))
+        .unwrap_or("-");
+    let step = feature.step.as_deref().unwrap_or("-");
+    let step = match step.char_indices().nth(STATUS_STEP_WIDTH) {
+        Some((end, _)) => format!("{}...", &step[..end]),
+        None => step.to_string(),
+    };
+    [
+        format!("{} {}", feature.id, feature.name),
+        phase.to_string(),
+        status.to_string(),
+        step,
+        feature.turns.to_string(),
+        format!("${:.2}", feature.cost_usd),
+        feature
+            .worktree
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string()),
+        feature
+            .updated_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+    ]
+}
+
+/// Validate the feature states of the project, repairing recoverable issues
+/// with `fix`.
+///
+/// An unreadable cost ledger is reported, and usage totals are then not
+/// compared.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `fix` - Repair recoverable issues.
+///
+/// # Errors
+///
+/// Returns an error if issues are left, or the states cannot be read or
+/// repaired.
+#[instrument(skip(config))]
+pub fn check_states(config: &ConfigManager, fix: bool) -> CliResult<()> {
+    let out = output();
+    let ledger = Ledger::new(config.ledger_path())
+        .entries()
+        .unwrap_or_else(|e| {
+            out.warning(&format!("Not comparing usage with the cost ledger: {e}"));
+            Vec::new()
+        });
+    let mut checks = state_check::check_features(&config.features_dir(), &ledger)
+        .map_err(gba_core::CoreError::from)?;
+
+    out.section("Feature States");
+    for check in &mut checks {
+        if fix {
+            for issue in check.fix().map_err(gba_core::CoreError::from)? {
+                out.list_item(&check.id, &format!("{issue} (fixed)"));
+            }
+        }
+        for issue in &check.issues {
+            out.list_item(&check.id, &issue.to_string());
+        }
+    }
+
+    let count = checks.iter().map(|check| check.issues.len()).sum::<usize>();
+    out.print(&format!(
+        "
+Total: {} features, {count} issues left",
+        checks.len()
+    ));
+    if count == 0 {
+        return Ok(());
+    }
+    let fixable = checks
+        .iter()
+        .flat_map(|check| &check.issues)
+        .filter(|issue| issue.is_fixable())
+        .count();
+    Err(CliError::StateIssues { count, fixable })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gba_core::config::ProjectConfig;
+
+    #[test]
+    fn test_check_states() {
+        let temp_dir = std::env::temp_dir().join("gba-test-check-states");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join(".gba")).unwrap();
+        let config_yaml = serde_yaml::to_string(&ProjectConfig::default_config()).unwrap();
+        fs::write(temp_dir.join(".gba").join("config.yml"), config_yaml).unwrap();
+        let config_manager = ConfigManager::load(&temp_dir).unwrap();
+        assert!(check_states(&config_manager, false).is_ok());
+
+        // A run interrupted by a crash leaves its state in progress
+        let id = feature::feature_id("Add Auth");
+        let mut state = FeatureState::new("Add Auth", &id);
+        state.status.state = TaskStatus::InProgress;
+        state.save(&config_manager.feature_state_path(&id)).unwrap();
+        assert!(matches!(
+            check_states(&config_manager, false),
+            Err(CliError::StateIssues {
+                count: 1,
+                fixable: 1
+            })
+        ));
+
+        assert!(check_states(&config_manager, true).is_ok());
+        let state = FeatureState::load(&config_manager.feature_state_path(&id)).unwrap();
+        assert_eq!(state.status.state, TaskStatus::Failed);
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+
+    #[test]
+    fn test_feature_status() {
+        let mut state = FeatureState::new("add-auth", "0001");
+        state.task.kind = "implementation".to_string();
+        state.status.state = TaskStatus::InProgress;
+        state.status.current_step = Some("x".repeat(STATUS_STEP_WIDTH + 5));
+        state.execution.turns = 7;
+        state.execution.cost.total_cost_usd = 0.42;
+        let other = FeatureState::new("add-billing", "0002");
+
+        assert!(is_feature(&state, "add-auth"));
+        assert!(is_feature(&state, "0001"));
+        assert!(!is_feature(&other, "add-auth"));
+
+        let status = ProjectStatus::new(None, &[state, other]);
+        assert!((status.total_cost_usd - 0.42).abs() < f64::EPSILON);
+        let row = status_row(&status.features[0]);
+        assert_eq!(row[0], "0001 add-auth");
+        assert_eq!(row[1], "implementation");
+        assert_eq!(row[2], "in progress");
+        assert_eq!(row[3], format!("{}...", "x".repeat(STATUS_STEP_WIDTH)));
+        assert_eq!(row[4..7], ["7", "$0.42", "-"]);
+        assert_eq!(status_row(&status.features[1])[1], "-");
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["features"][0]["turns"], 7);
+        assert!(json.get("project").is_none());
+        assert_eq!(
+            ProjectStatus::new(None, &[]).total_cost_usd.to_string(),
+            "0"
+        );
+    }
+}
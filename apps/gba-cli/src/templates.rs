@@ -0,0 +1,463 @@
+//! `gba list-prompts`, `gba templates` and `gba validate`: managing the
+//! project's prompt templates.
+
+use gba_core::atomic;
+use gba_core::config::ProjectConfig;
+use gba_pm::{BundledTemplate, PromptManager, PromptTemplate, TemplateEngine};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, instrument, warn};
+
+use crate::config::ConfigManager;
+use crate::error::{CliError, Result as CliResult};
+use crate::output::output;
+use crate::run::{FEATURE_VARIABLES, variable_hint};
+
+/// List available prompts.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `verbose` - Whether to show verbose output.
+///
+/// # Errors
+///
+/// Returns an error if listing fails.
+pub fn list_prompts(config: ConfigManager, verbose: bool) -> CliResult<()> {
+    info!("Listing available prompts");
+
+    // Initialize prompt manager
+    let prompt_manager = init_prompt_manager(&config)?;
+
+    // Get available templates
+    let templates = prompt_manager.list_prompts();
+
+    if templates.is_empty() {
+        debug!("No templates found");
+        return Ok(());
+    }
+
+    debug!("Found {} templates", templates.len());
+    // Still need to output to console for user-visible command
+    let out = output();
+    out.prompt_list(&templates, verbose);
+
+    Ok(())
+}
+
+/// List the variables a template references, and whether the standard
+/// context provides each or it must be supplied as an extra variable.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `name` - Template name.
+///
+/// # Errors
+///
+/// Returns an error if the template is not found.
+pub fn template_variables(config: &ConfigManager, name: &str) -> CliResult<()> {
+    let prompt_manager = init_prompt_manager(config)?;
+    if !prompt_manager.has_prompt(name) {
+        return Err(CliError::template_not_found(name.to_string()));
+    }
+    let variables = prompt_manager.template_variables(name)?;
+
+    let out = output();
+    out.section(&format!("Variables of {name}"));
+    if variables.is_empty() {
+        out.info("The template references no variables");
+    }
+    for variable in &variables {
+        let source = if variable.provided {
+            "standard context"
+        } else {
+            "extra (add_extra)"
+        };
+        out.list_item(&format!("{}:", variable.name), source);
+    }
+
+    let declared = prompt_manager.get_config(name)?.variables;
+    if !declared.is_empty() {
+        out.section("Declared");
+        for variable in &declared {
+            out.list_item(
+                &format!("{}:", variable.name),
+                variable_hint(variable).trim(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Source of a new template without `--from`: a front matter with the
+/// agent settings and a body using the standard context.
+const TEMPLATE_SCAFFOLD: &str = r#"---
+# Settings of the agent running this template
+systemPrompt: ""
+usePreset: true
+tools:
+  - Read
+  - Glob
+  - Grep
+maxTurns: 20
+# Variables the standard context doesn't provide, asked for when missing
+# variables:
+#   - name: audience
+#     description: Who the answer is for
+#     default: developers
+---
+{# Rendered with the feature's context; `gba templates vars <name>` lists it #}
+# {{ feature_name }}
+
+{{ feature_description }}
+
+{{ user_message }}
+"#;
+
+/// Create a template in the templates directory.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `name` - Template name, letters, digits, `-` and `_` only.
+/// * `from` - Bundled template to copy, if any.
+/// * `force` - Whether to overwrite an existing template.
+///
+/// # Returns
+///
+/// The path of the new template.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid, the bundled template doesn't
+/// exist, the template already exists without `force`, or it cannot be
+/// written.
+pub fn new_template(
+    config: &ConfigManager,
+    name: &str,
+    from: Option<&str>,
+    force: bool,
+) -> CliResult<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(CliError::InvalidTemplateName(format!(
+            "{name}, use letters, digits, '-' and '_'"
+        )));
+    }
+    let source = match from {
+        Some(from) => BundledTemplate::from_name(from)
+            .ok_or_else(|| CliError::template_not_found(from.to_string()))?
+            .source(),
+        None => TEMPLATE_SCAFFOLD,
+    };
+
+    let path = config.templates_dir().join(format!("{name}.jinja2"));
+    if path.exists() && !force {
+        return Err(CliError::invalid_args(format!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        )));
+    }
+    // A template that doesn't parse would fail every run using it
+    PromptTemplate::parse(source)?;
+    fs::create_dir_all(config.templates_dir())?;
+    atomic::write(&path, source).map_err(gba_core::CoreError::from)?;
+
+    let out = output();
+    out.success(&format!("Created {}", path.display()));
+    if BundledTemplate::from_name(name).is_some() {
+        out.info(&format!("It overrides the bundled {name} template"));
+    }
+    Ok(path)
+}
+
+/// Show the template a name resolves to: whether it is local or bundled,
+/// its configuration, its source and the variables it references.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `name` - Template name.
+///
+/// # Errors
+///
+/// Returns an error if the template is not found.
+pub fn show_template(config: &ConfigManager, name: &str) -> CliResult<()> {
+    let prompt_manager = init_prompt_manager(config)?;
+    if !prompt_manager.has_prompt(name) {
+        return Err(CliError::template_not_found(name.to_string()));
+    }
+
+    let path = config.templates_dir().join(format!("{name}.jinja2"));
+    let bundled = BundledTemplate::from_name(name);
+    let (origin, source) = if path.is_file() {
+        let origin = match bundled {
+            Some(_) => format!("local {}, overriding the bundled template", path.display()),
+            None => format!("local {}", path.display()),
+        };
+        (origin, fs::read_to_string(&path)?)
+    } else if let Some(bundled) = bundled {
+        (
+            format!("bundled {}", bundled.file_name()),
+            bundled.source().to_string(),
+        )
+    } else {
+        return Err(CliError::template_not_found(name.to_string()));
+    };
+
+    let out = output();
+    out.section(&format!("Template {name}"));
+    out.list_item("Source:", &origin);
+    match prompt_manager.get_config(name) {
+        Ok(template) => {
+            out.subsection("Configuration");
+            out.text(
+                serde_yaml::to_string(&template)
+                    .unwrap_or_default()
+                    .trim_end(),
+            );
+        }
+        // The front matter of e.g. `resume` is itself templated
+        Err(_) => out.info("The front matter is resolved when the template is rendered"),
+    }
+    out.subsection("Source");
+    out.text(source.trim_end());
+
+    template_variables(config, name)
+}
+
+/// Check the configuration and the templates of a project, reporting every
+/// problem with its file and line.
+///
+/// The configuration is checked against the schema and the model table; each
+/// template of the templates directory, partials in subdirectories included,
+/// for its front matter, its syntax and the variables it references.
+///
+/// # Errors
+///
+/// Returns an error if problems are found or the files cannot be read.
+#[instrument]
+pub fn validate(project_path: &Path) -> CliResult<()> {
+    if !ConfigManager::is_gba_project(project_path) {
+        return Err(CliError::NotGbaProject(project_path.to_path_buf()));
+    }
+    let out = output();
+    let mut count = 0;
+
+    let config_path = ConfigManager::config_file_path(project_path);
+    let content = fs::read_to_string(&config_path)?;
+    out.section("Configuration");
+    for problem in ProjectConfig::check(&content) {
+        out.list_item(&location(&config_path, problem.line), &problem.message);
+        count += 1;
+    }
+
+    // Templates are checked even if the configuration is invalid
+    let templates_dir = serde_yaml::from_str::<ProjectConfig>(&content).map_or_else(
+        |_| project_path.join(".gba").join("templates"),
+        |config| project_path.join(&config.prompts.directory),
+    );
+    // Templates with syntax errors can't be loaded into it, so the engine
+    // only checks them
+    let engine = TemplateEngine::new()?;
+    let extra = FEATURE_VARIABLES
+        .iter()
+        .map(ToString::to_string)
+        .collect::<BTreeSet<_>>();
+    let files = template_files(&templates_dir)?;
+    out.section("Templates");
+    for path in &files {
+        let source = fs::read_to_string(path)?;
+        let name = path
+            .strip_prefix(&templates_dir)
+            .unwrap_or(path)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        for problem in gba_pm::check_template(&engine, &name, &source, &extra) {
+            out.list_item(&location(path, problem.line), &problem.message);
+            count += 1;
+        }
+    }
+
+    out.print(&format!(
+        "\nTotal: {} templates, {count} problems",
+        files.len()
+    ));
+    if count > 0 {
+        return Err(CliError::ValidationProblems(count));
+    }
+    Ok(())
+}
+
+/// Format the location of a problem, e.g. `.gba/config.yml:4`.
+fn location(path: &Path, line: Option<usize>) -> String {
+    match line {
+        Some(line) => format!("{}:{line}", path.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// List the `.jinja2` files of a directory and its subdirectories, sorted.
+fn template_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(template_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "jinja2") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Initialize the prompt manager.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+///
+/// # Errors
+///
+/// Returns an error if initialization fails.
+pub fn init_prompt_manager(config: &ConfigManager) -> Result<PromptManager, CliError> {
+    let templates_dir = config.templates_dir();
+    let use_bundled = config.config().prompts.use_bundled;
+
+    debug!(
+        "Initializing prompt manager with templates dir: {}",
+        templates_dir.display()
+    );
+
+    PromptManager::with_local_dir(templates_dir, use_bundled)
+        .map_err(|e| CliError::Config(format!("Failed to initialize prompt manager: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let temp_dir = std::env::temp_dir().join("gba-test-validate");
+        fs::remove_dir_all(&temp_dir).ok();
+        let templates_dir = temp_dir.join(".gba").join("templates");
+        fs::create_dir_all(templates_dir.join("partials")).unwrap();
+        let mut config = ProjectConfig::default_config();
+        config.prompts.directory = "./.gba/templates".to_string();
+        fs::write(
+            ConfigManager::config_file_path(&temp_dir),
+            serde_yaml::to_string(&config).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            templates_dir.join("plan.jinja2"),
+            "---\ntools: []\n---\n{% include \"bundled/plan\" %}\n{{ feature_name }}",
+        )
+        .unwrap();
+        fs::write(
+            templates_dir.join("partials").join("rules.jinja2"),
+            "Never push to {{ main_branch }}.",
+        )
+        .unwrap();
+        assert!(validate(&temp_dir).is_ok());
+
+        fs::write(
+            templates_dir.join("custom.jinja2"),
+            "{{ ticket }}\n{% if %}",
+        )
+        .unwrap();
+        config.agent.temperature = 5.0;
+        fs::write(
+            ConfigManager::config_file_path(&temp_dir),
+            serde_yaml::to_string(&config).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            validate(&temp_dir),
+            Err(CliError::ValidationProblems(2))
+        ));
+        assert_eq!(
+            template_files(&templates_dir).unwrap(),
+            [
+                templates_dir.join("custom.jinja2"),
+                templates_dir.join("partials").join("rules.jinja2"),
+                templates_dir.join("plan.jinja2"),
+            ]
+        );
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+
+    #[test]
+    fn test_template_variables() {
+        let temp_dir = std::env::temp_dir().join("gba-test-template-variables");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join(".gba")).unwrap();
+        let mut config = ProjectConfig::default_config();
+        config.prompts.use_bundled = true;
+        let config_yaml = serde_yaml::to_string(&config).unwrap();
+        fs::write(temp_dir.join(".gba").join("config.yml"), config_yaml).unwrap();
+        let config_manager = ConfigManager::load(&temp_dir).unwrap();
+
+        assert!(template_variables(&config_manager, "plan").is_ok());
+        assert!(matches!(
+            template_variables(&config_manager, "missing"),
+            Err(CliError::TemplateNotFound(_))
+        ));
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+
+    #[test]
+    fn test_new_and_show_template() {
+        let temp_dir = std::env::temp_dir().join("gba-test-new-template");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join(".gba")).unwrap();
+        let mut config = ProjectConfig::default_config();
+        config.prompts.use_bundled = true;
+        let config_yaml = serde_yaml::to_string(&config).unwrap();
+        fs::write(temp_dir.join(".gba").join("config.yml"), config_yaml).unwrap();
+        let config_manager = ConfigManager::load(&temp_dir).unwrap();
+
+        let path = new_template(&config_manager, "audit", None, false).unwrap();
+        assert!(PromptTemplate::parse(&fs::read_to_string(&path).unwrap()).is_ok());
+        assert!(matches!(
+            new_template(&config_manager, "audit", None, false),
+            Err(CliError::InvalidArgs(_))
+        ));
+        let path = new_template(&config_manager, "audit", Some("review"), true).unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            BundledTemplate::Review.source()
+        );
+        assert!(matches!(
+            new_template(&config_manager, "../audit", None, false),
+            Err(CliError::InvalidTemplateName(_))
+        ));
+        assert!(matches!(
+            new_template(&config_manager, "other", Some("missing"), false),
+            Err(CliError::TemplateNotFound(_))
+        ));
+
+        assert!(show_template(&config_manager, "audit").is_ok());
+        assert!(show_template(&config_manager, "plan").is_ok());
+        assert!(matches!(
+            show_template(&config_manager, "missing"),
+            Err(CliError::TemplateNotFound(_))
+        ));
+
+        fs::remove_dir_all(temp_dir).ok();
+    }
+}
@@ -1,12 +1,29 @@
 //! UI/TUI implementation for GBA CLI.
 //!
 //! This module provides terminal user interface functionality using ratatui.
+//!
+//! The TUI is event driven: keyboard input, ticks, and agent output are all
+//! delivered as [`AppEvent`]s over a single channel. Task execution runs in a
+//! spawned tokio task that feeds the channel through a [`Tui::sender`] handle,
+//! while the TUI loop only applies events to the [`App`] state and redraws.
+//!
+//! The response pane follows the agent's output as it streams in until the
+//! user scrolls up, and again once they jump back to the bottom. Pausing
+//! holds the output back, while the agent keeps running, so it can be read
+//! without moving; aborting cancels the task and keeps the TUI open.
 
+use std::io::{self, Stdout};
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use gba_core::task::Usage;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
     crossterm::{
-        event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+        event::{self, Event, KeyEvent, KeyEventKind},
         execute,
         terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
     },
@@ -14,10 +31,21 @@ use ratatui::{
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, Paragraph, Wrap},
 };
-use std::io::{self, Stdout};
-use tracing::debug;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::{debug, warn};
 
-use crate::error::Result;
+use crate::error::{CliError, Result};
+use crate::keymap::{Action, KeyMap};
+
+/// Interval between tick events.
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Frames of the activity spinner, advanced on every tick.
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Interval at which the input thread polls the terminal for events.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// TUI state machine.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,121 +54,415 @@ enum TuiState {
     Initial,
     /// Running state.
     Running,
-    /// Paused state.
-    #[allow(dead_code)]
+    /// Running, with the output held back until resumed.
     Paused,
     /// Completed state.
-    #[allow(dead_code)]
     Completed,
     /// Error state.
-    #[allow(dead_code)]
     Error,
 }
 
-/// TUI state.
-#[allow(dead_code)]
-pub struct Tui {
-    /// Terminal instance.
-    terminal: Terminal<CrosstermBackend<Stdout>>,
+/// Event delivered to the TUI loop.
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    /// Key pressed by the user.
+    Key(KeyEvent),
+    /// Periodic tick used for redraws and animations.
+    Tick,
+    /// Chunk of agent output text.
+    AgentChunk(String),
+    /// Tool invoked by the agent.
+    ToolCall(String),
+    /// Diff of the changes made so far.
+    DiffUpdate(String),
+    /// Cumulative usage reported by the agent.
+    UsageUpdate(Usage),
+    /// Execution moved to a new phase.
+    PhaseChange(String),
+    /// Execution finished, with an error message on failure.
+    Finished(Option<String>),
+}
+
+/// Content pane shown in the main area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    /// Last agent response.
+    Response,
+    /// Diff of the selected file.
+    Diff,
+}
+
+impl Pane {
+    /// All panes in display order.
+    pub const ALL: [Self; 2] = [Self::Response, Self::Diff];
+
+    /// Get the pane title.
+    #[must_use]
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Response => "Response",
+            Self::Diff => "Diff",
+        }
+    }
+
+    /// Get the pane that follows this one.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Response => Self::Diff,
+            Self::Diff => Self::Response,
+        }
+    }
+
+    /// Get the pane that precedes this one.
+    #[must_use]
+    pub const fn prev(self) -> Self {
+        match self {
+            Self::Response => Self::Diff,
+            Self::Diff => Self::Response,
+        }
+    }
+}
+
+/// Content of the main area panes.
+#[derive(Debug, Clone)]
+pub struct Panes {
+    /// Currently active pane.
+    active: Pane,
+    /// Last agent response.
+    response: String,
+    /// Diff of the selected file.
+    diff: String,
+}
+
+impl Panes {
+    /// Create empty panes with the response pane active.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            active: Pane::Response,
+            response: String::new(),
+            diff: String::new(),
+        }
+    }
+
+    /// Get the active pane.
+    #[must_use]
+    pub const fn active(&self) -> Pane {
+        self.active
+    }
+
+    /// Switch to the next pane.
+    pub fn next(&mut self) {
+        self.active = self.active.next();
+    }
+
+    /// Switch to the previous pane.
+    pub fn prev(&mut self) {
+        self.active = self.active.prev();
+    }
+
+    /// Switch to the pane at the given index, ignoring out-of-range indices.
+    pub fn select(&mut self, index: usize) {
+        if let Some(pane) = Pane::ALL.get(index) {
+            self.active = *pane;
+        }
+    }
+
+    /// Get the content of the active pane.
+    #[must_use]
+    pub fn active_content(&self) -> &str {
+        match self.active {
+            Pane::Response => &self.response,
+            Pane::Diff => &self.diff,
+        }
+    }
+
+    /// Set the last response.
+    #[allow(dead_code)]
+    pub fn set_response(&mut self, response: impl Into<String>) {
+        self.response = response.into();
+    }
+
+    /// Set the diff of the selected file.
+    pub fn set_diff(&mut self, diff: impl Into<String>) {
+        self.diff = diff.into();
+    }
+}
+
+impl Default for Panes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// TUI application state.
+///
+/// Holds everything the TUI renders and is updated exclusively through
+/// [`App::update`], which keeps it independent of the terminal.
+#[derive(Debug)]
+pub struct App {
     /// Current state.
     state: TuiState,
+    /// Main area panes.
+    panes: Panes,
+    /// Transient status message shown in the footer.
+    status_message: Option<String>,
+    /// Current execution phase.
+    phase: String,
+    /// Tool currently being executed by the agent.
+    current_tool: Option<String>,
+    /// Latest usage reported by the agent.
+    usage: Usage,
+    /// Whether the user asked to quit.
+    should_quit: bool,
+    /// Key bindings.
+    keymap: KeyMap,
+    /// Scroll offset of the active pane, in lines.
+    scroll: u16,
+    /// Height of the main content viewport from the last render.
+    viewport_height: u16,
+    /// Width of the main content viewport from the last render.
+    viewport_width: u16,
+    /// Current spinner frame.
+    spinner_frame: usize,
+    /// When the current wait (for the model or a tool) started.
+    waiting_since: Instant,
+    /// Whether the active pane scrolls to its bottom as output streams in.
+    follow: bool,
+    /// Output received while paused.
+    held: String,
+    /// Whether the user asked to abort the running task.
+    abort_requested: bool,
 }
 
-impl Tui {
-    /// Create a new TUI.
+impl App {
+    /// Create a new application state.
     ///
-    /// # Errors
+    /// # Arguments
     ///
-    /// Returns an error if the terminal cannot be initialized.
-    #[allow(dead_code)]
-    pub fn new() -> Result<Self> {
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+    /// * `keymap` - Key bindings used to interpret key presses.
+    #[must_use]
+    pub fn new(keymap: KeyMap) -> Self {
+        Self {
+            state: TuiState::Initial,
+            panes: Panes::new(),
+            status_message: None,
+            phase: String::new(),
+            current_tool: None,
+            usage: Usage::default(),
+            should_quit: false,
+            keymap,
+            scroll: 0,
+            viewport_height: 0,
+            viewport_width: 0,
+            spinner_frame: 0,
+            waiting_since: Instant::now(),
+            follow: true,
+            held: String::new(),
+            abort_requested: false,
+        }
+    }
 
-        let backend = CrosstermBackend::new(stdout);
-        let terminal = Terminal::new(backend)?;
+    /// Apply an event to the application state.
+    pub fn update(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Key(key) => self.handle_key(key),
+            AppEvent::Tick => {
+                self.spinner_frame = (self.spinner_frame + 1) % SPINNER_FRAMES.len();
+            }
+            AppEvent::AgentChunk(text) => {
+                self.mark_running();
+                self.current_tool = None;
+                self.waiting_since = Instant::now();
+                if self.state == TuiState::Paused {
+                    self.held.push_str(&text);
+                } else {
+                    self.panes.response.push_str(&text);
+                }
+            }
+            AppEvent::ToolCall(name) => {
+                self.mark_running();
+                self.current_tool = Some(name);
+                self.waiting_since = Instant::now();
+            }
+            AppEvent::DiffUpdate(diff) => self.panes.set_diff(diff),
+            AppEvent::UsageUpdate(usage) => self.usage = usage,
+            AppEvent::PhaseChange(phase) => {
+                self.mark_running();
+                self.waiting_since = Instant::now();
+                self.phase = phase;
+            }
+            AppEvent::Finished(error) => {
+                self.release_held();
+                self.current_tool = None;
+                match error {
+                    Some(message) => {
+                        self.state = TuiState::Error;
+                        self.status_message = Some(format!("Failed: {message}"));
+                    }
+                    None => self.state = TuiState::Completed,
+                }
+            }
+        }
+    }
 
-        debug!("TUI initialized");
+    /// Mark the task as running, unless its output is paused.
+    fn mark_running(&mut self) {
+        if self.state != TuiState::Paused {
+            self.state = TuiState::Running;
+        }
+    }
 
-        Ok(Self {
-            terminal,
-            state: TuiState::Initial,
-        })
+    /// Show the output held back while paused.
+    fn release_held(&mut self) {
+        let held = mem::take(&mut self.held);
+        self.panes.response.push_str(&held);
     }
 
-    /// Draw the UI frame.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if drawing fails.
-    #[allow(dead_code)]
-    pub fn draw(&mut self) -> Result<()> {
-        let state = self.state;
-        self.terminal.draw(|f| {
-            let size = f.area();
-
-            // Create main layout
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Length(3), // Header
-                        Constraint::Min(0),    // Main content
-                        Constraint::Length(3), // Footer
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
-
-            // Render header
-            Self::render_header(f, chunks[0]);
-
-            // Render main content
-            Self::render_main_content(f, chunks[1], state);
-
-            // Render footer
-            Self::render_footer(f, chunks[2]);
-        })?;
-        Ok(())
+    /// Pause or resume the output of the running task.
+    fn toggle_pause(&mut self) {
+        match self.state {
+            TuiState::Running => {
+                self.state = TuiState::Paused;
+                self.status_message =
+                    Some("Paused: the agent keeps running, its output is held".to_string());
+            }
+            TuiState::Paused => {
+                self.state = TuiState::Running;
+                self.status_message = None;
+                self.release_held();
+            }
+            TuiState::Initial | TuiState::Completed | TuiState::Error => {}
+        }
     }
 
-    /// Run the main TUI loop.
-    ///
-    /// # Errors
+    /// Whether the user asked to abort the running task, clearing the
+    /// request.
+    pub fn take_abort_request(&mut self) -> bool {
+        mem::take(&mut self.abort_requested)
+    }
+
+    /// Describe what execution is currently waiting on.
     ///
-    /// Returns an error if the TUI loop fails.
+    /// Returns `None` unless a task is running.
+    fn activity(&self, now: Instant) -> Option<String> {
+        if self.state != TuiState::Running {
+            return None;
+        }
+
+        let spinner = SPINNER_FRAMES[self.spinner_frame];
+        let elapsed = now.saturating_duration_since(self.waiting_since).as_secs();
+        Some(match &self.current_tool {
+            Some(tool) => format!("{spinner} running tool {tool}… {elapsed}s"),
+            None => format!("{spinner} waiting for model… {elapsed}s"),
+        })
+    }
+
+    /// Whether the TUI loop should exit.
+    #[must_use]
+    pub const fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Get a mutable reference to the main area panes.
     #[allow(dead_code)]
-    pub fn run(&mut self) -> Result<()> {
-        debug!("Starting TUI loop");
+    pub fn panes_mut(&mut self) -> &mut Panes {
+        &mut self.panes
+    }
 
-        self.state = TuiState::Running;
+    /// Handle a key press.
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
 
-        loop {
-            self.draw()?;
+        let Some(action) = self.keymap.action(&key) else {
+            return;
+        };
 
-            // Check for events
-            if event::poll(std::time::Duration::from_millis(100))?
-                && let Event::Key(key) = event::read()?
-                && key == KeyEvent::new(KeyCode::Char('q'), KeyModifiers::empty())
-            {
-                break;
+        let half_page = (self.viewport_height / 2).max(1);
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::Copy => self.copy_active_pane(),
+            Action::Pause => self.toggle_pause(),
+            Action::Abort => {
+                self.abort_requested = matches!(self.state, TuiState::Running | TuiState::Paused);
             }
+            Action::ScrollDown => self.scroll_to(self.scroll.saturating_add(1)),
+            Action::ScrollUp => self.scroll_to(self.scroll.saturating_sub(1)),
+            Action::HalfPageDown => self.scroll_to(self.scroll.saturating_add(half_page)),
+            Action::HalfPageUp => self.scroll_to(self.scroll.saturating_sub(half_page)),
+            Action::Top => self.scroll_to(0),
+            Action::Bottom => self.scroll_to(u16::MAX),
+            Action::NextPane => self.switch_pane(Panes::next),
+            Action::PrevPane => self.switch_pane(Panes::prev),
+            Action::SelectPane(index) => self.switch_pane(|panes| panes.select(index)),
+        }
+    }
 
-            // Check if we should exit
-            if matches!(self.state, TuiState::Completed | TuiState::Error) {
-                break;
+    /// Switch panes, resetting the scroll position and status message.
+    fn switch_pane(&mut self, switch: impl FnOnce(&mut Panes)) {
+        switch(&mut self.panes);
+        self.scroll = 0;
+        self.follow = false;
+        self.status_message = None;
+    }
+
+    /// Scroll the active pane, clamped to its content, following its output
+    /// if scrolled to the bottom.
+    fn scroll_to(&mut self, offset: u16) {
+        self.scroll = offset.min(self.max_scroll());
+        self.follow = self.scroll == self.max_scroll();
+    }
+
+    /// Get the maximum scroll offset for the active pane.
+    ///
+    /// Long lines wrap in the pane, so they are counted at its width once it
+    /// has been rendered.
+    fn max_scroll(&self) -> u16 {
+        let content = self.panes.active_content();
+        let lines = if self.viewport_width == 0 {
+            content.lines().count()
+        } else {
+            Paragraph::new(content)
+                .wrap(Wrap { trim: false })
+                .line_count(self.viewport_width)
+        };
+        u16::try_from(lines)
+            .unwrap_or(u16::MAX)
+            .saturating_sub(self.viewport_height)
+    }
+
+    /// Copy the content of the active pane to the system clipboard.
+    fn copy_active_pane(&mut self) {
+        let pane = self.panes.active();
+        let content = self.panes.active_content();
+
+        let message = if content.is_empty() {
+            format!("{} pane is empty, nothing copied", pane.title())
+        } else {
+            match copy_to_clipboard(content) {
+                Ok(()) => {
+                    debug!("Copied {} pane to clipboard", pane.title());
+                    format!(
+                        "Copied {} pane to clipboard ({} chars)",
+                        pane.title(),
+                        content.chars().count()
+                    )
+                }
+                Err(e) => {
+                    warn!("Failed to copy to clipboard: {}", e);
+                    e.to_string()
+                }
             }
-        }
+        };
 
-        debug!("TUI loop completed");
-        Ok(())
+        self.status_message = Some(message);
     }
 
-    /// Render a single frame.
-    #[allow(dead_code)]
-    fn render_frame(&self, f: &mut Frame, state: TuiState) {
+    /// Render the application state into a frame.
+    fn render(&mut self, f: &mut Frame) {
         let size = f.area();
 
         // Create main layout
@@ -158,18 +480,27 @@ impl Tui {
             .split(size);
 
         // Render header
-        Self::render_header(f, chunks[0]);
+        self.render_header(f, chunks[0]);
 
         // Render main content
-        Self::render_main_content(f, chunks[1], state);
+        self.render_main_content(f, chunks[1]);
 
         // Render footer
-        Self::render_footer(f, chunks[2]);
+        self.render_footer(f, chunks[2]);
     }
 
     /// Render the header section.
-    fn render_header(f: &mut Frame, area: Rect) {
-        let title = Paragraph::new("GBA - GeekTime Bootcamp Agent")
+    fn render_header(&self, f: &mut Frame, area: Rect) {
+        let mut text = "GBA - GeekTime Bootcamp Agent".to_string();
+        if !self.phase.is_empty() {
+            text.push_str(&format!(" | {}", self.phase));
+        }
+        text.push_str(&format!(
+            " | tokens: {}/{} | ${:.4}",
+            self.usage.input_tokens, self.usage.output_tokens, self.usage.total_cost_usd
+        ));
+
+        let title = Paragraph::new(text)
             .style(
                 Style::default()
                     .fg(Color::Cyan)
@@ -186,22 +517,39 @@ impl Tui {
     }
 
     /// Render the main content section.
-    fn render_main_content(f: &mut Frame, area: Rect, state: TuiState) {
-        let content = match state {
+    fn render_main_content(&mut self, f: &mut Frame, area: Rect) {
+        let status = match self.state {
             TuiState::Initial => "Initializing...",
             TuiState::Running => "Running task...",
-            TuiState::Paused => "Paused. Press 'r' to resume or 'q' to quit.",
+            TuiState::Paused => "Paused. Output is held until resumed.",
             TuiState::Completed => "Task completed successfully!",
             TuiState::Error => "An error occurred.",
         };
 
+        let content = match self.panes.active_content() {
+            "" => status,
+            content => content,
+        };
+
+        // Account for the block borders
+        self.viewport_height = area.height.saturating_sub(2);
+        self.viewport_width = area.width.saturating_sub(2);
+        self.scroll = if self.follow {
+            self.max_scroll()
+        } else {
+            self.scroll.min(self.max_scroll())
+        };
+
+        let title = format!("{} [Tab: switch]", self.panes.active().title());
+
         let paragraph = Paragraph::new(content)
             .style(Style::default().fg(Color::White))
             .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Status")
+                    .title(title)
                     .title_style(Style::default().fg(Color::Yellow)),
             );
 
@@ -209,8 +557,13 @@ impl Tui {
     }
 
     /// Render the footer section.
-    fn render_footer(f: &mut Frame, area: Rect) {
-        let help_text = "Press 'q' to quit";
+    fn render_footer(&self, f: &mut Frame, area: Rect) {
+        let activity = self.activity(Instant::now());
+        let help_text = self
+            .status_message
+            .as_deref()
+            .or(activity.as_deref())
+            .unwrap_or("Press 'q' to quit, 'p' to pause, 'x' to abort, 'y' to copy pane");
 
         let paragraph = Paragraph::new(help_text)
             .style(Style::default().fg(Color::Gray))
@@ -219,15 +572,131 @@ impl Tui {
 
         f.render_widget(paragraph, area);
     }
+}
+
+/// Terminal user interface.
+pub struct Tui {
+    /// Terminal instance.
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    /// Application state.
+    app: App,
+    /// Sender side of the event channel, cloned for producers.
+    events_tx: mpsc::UnboundedSender<AppEvent>,
+    /// Receiver side of the event channel.
+    events_rx: mpsc::UnboundedReceiver<AppEvent>,
+    /// Shutdown signal for the input thread.
+    shutdown: Arc<AtomicBool>,
+    /// Task running in the background, canceled on abort.
+    task: Option<AbortHandle>,
+}
+
+impl Tui {
+    /// Create a new TUI.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terminal cannot be initialized.
+    pub fn new(keymap: KeyMap) -> Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let backend = CrosstermBackend::new(stdout);
+        let terminal = Terminal::new(backend)?;
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+
+        debug!("TUI initialized");
+
+        Ok(Self {
+            terminal,
+            app: App::new(keymap),
+            events_tx,
+            events_rx,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            task: None,
+        })
+    }
+
+    /// Get a sender for delivering events to the TUI loop.
+    ///
+    /// Background tasks use this to push agent output into the TUI.
+    #[must_use]
+    pub fn sender(&self) -> mpsc::UnboundedSender<AppEvent> {
+        self.events_tx.clone()
+    }
+
+    /// Set the task running in the background, canceled when the user
+    /// aborts it.
+    pub fn set_task(&mut self, task: AbortHandle) {
+        self.task = Some(task);
+    }
+
+    /// Get a mutable reference to the application state.
+    #[allow(dead_code)]
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Draw the UI frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if drawing fails.
+    pub fn draw(&mut self) -> Result<()> {
+        let app = &mut self.app;
+        self.terminal.draw(|f| app.render(f))?;
+        Ok(())
+    }
+
+    /// Run the main TUI loop until the user quits.
+    ///
+    /// Spawns the input reader and ticker, then applies every received event
+    /// to the application state and redraws.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TUI loop fails.
+    pub async fn run(&mut self) -> Result<()> {
+        debug!("Starting TUI loop");
+
+        let input = spawn_input_reader(self.sender(), Arc::clone(&self.shutdown));
+        let ticker = spawn_ticker(self.sender());
+
+        self.draw()?;
+        while let Some(event) = self.events_rx.recv().await {
+            self.app.update(event);
+            if self.app.take_abort_request()
+                && let Some(task) = self.task.take()
+            {
+                task.abort();
+                debug!("Aborted the running task");
+                self.app
+                    .update(AppEvent::Finished(Some("Aborted".to_string())));
+            }
+            if self.app.should_quit() {
+                break;
+            }
+            self.draw()?;
+        }
+
+        self.shutdown.store(true, Ordering::Relaxed);
+        ticker.abort();
+        if let Err(e) = input.await {
+            warn!("Input reader task failed: {}", e);
+        }
+
+        debug!("TUI loop completed");
+        Ok(())
+    }
 
     /// Exit the TUI.
     ///
     /// # Errors
     ///
     /// Returns an error if cleanup fails.
-    #[allow(dead_code)]
     pub fn exit(&mut self) -> Result<()> {
         debug!("Exiting TUI");
+        self.shutdown.store(true, Ordering::Relaxed);
         disable_raw_mode()?;
         execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
         Ok(())
@@ -240,10 +709,62 @@ impl Drop for Tui {
     }
 }
 
-impl Default for Tui {
-    fn default() -> Self {
-        Self::new().expect("Failed to initialize TUI")
-    }
+/// Spawn a blocking task that forwards terminal key events to the channel.
+///
+/// The task polls with a short timeout so it notices the shutdown signal.
+fn spawn_input_reader(
+    tx: mpsc::UnboundedSender<AppEvent>,
+    shutdown: Arc<AtomicBool>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        while !shutdown.load(Ordering::Relaxed) {
+            match event::poll(INPUT_POLL_INTERVAL) {
+                Ok(true) => match event::read() {
+                    Ok(Event::Key(key)) => {
+                        if tx.send(AppEvent::Key(key)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Failed to read terminal event: {}", e);
+                        break;
+                    }
+                },
+                Ok(false) => {}
+                Err(e) => {
+                    warn!("Failed to poll terminal events: {}", e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Spawn a task that sends tick events at a fixed interval.
+fn spawn_ticker(tx: mpsc::UnboundedSender<AppEvent>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if tx.send(AppEvent::Tick).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Copy text to the system clipboard.
+///
+/// # Errors
+///
+/// Returns an error if the clipboard is unavailable or cannot be written.
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| CliError::Clipboard(format!("Clipboard unavailable: {e}")))?;
+    clipboard
+        .set_text(text)
+        .map_err(|e| CliError::Clipboard(format!("Failed to copy to clipboard: {e}")))
 }
 
 /// Draw a simple message in the terminal.
@@ -304,10 +825,200 @@ pub fn draw_progress(f: &mut Frame, area: Rect, message: &str, progress: f32) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gba_core::config::TuiKeyBindings;
+    use ratatui::crossterm::event::{KeyCode, KeyModifiers};
 
     #[test]
     fn test_tui_state() {
         assert_eq!(TuiState::Initial, TuiState::Initial);
         assert_ne!(TuiState::Initial, TuiState::Running);
     }
+
+    fn app() -> App {
+        App::new(KeyMap::from_config(&TuiKeyBindings::default()).unwrap())
+    }
+
+    fn key(code: KeyCode) -> AppEvent {
+        AppEvent::Key(KeyEvent::from(code))
+    }
+
+    #[test]
+    fn test_app_update_agent_events() {
+        let mut app = app();
+        app.update(AppEvent::PhaseChange("planning".to_string()));
+        app.update(AppEvent::ToolCall("Read".to_string()));
+        assert_eq!(app.current_tool.as_deref(), Some("Read"));
+
+        app.update(AppEvent::AgentChunk("Hello ".to_string()));
+        app.update(AppEvent::AgentChunk("world".to_string()));
+        assert_eq!(app.panes.active_content(), "Hello world");
+        assert!(app.current_tool.is_none());
+        assert_eq!(app.phase, "planning");
+        assert_eq!(app.state, TuiState::Running);
+
+        app.update(AppEvent::UsageUpdate(Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            total_cost_usd: 0.01,
+        }));
+        assert_eq!(app.usage.output_tokens, 5);
+
+        app.update(AppEvent::Finished(None));
+        assert_eq!(app.state, TuiState::Completed);
+        assert!(!app.should_quit());
+    }
+
+    #[test]
+    fn test_app_update_keys() {
+        let mut app = app();
+        app.update(AppEvent::Finished(Some("boom".to_string())));
+        assert_eq!(app.state, TuiState::Error);
+
+        app.update(key(KeyCode::Tab));
+        assert_eq!(app.panes.active(), Pane::Diff);
+
+        app.update(key(KeyCode::Char('q')));
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn test_app_activity_spinner() {
+        let mut app = app();
+        assert!(app.activity(Instant::now()).is_none());
+
+        app.update(AppEvent::PhaseChange("implementation".to_string()));
+        let later = app.waiting_since + Duration::from_secs(12);
+        assert_eq!(
+            app.activity(later).as_deref(),
+            Some("⠋ waiting for model… 12s")
+        );
+
+        app.update(AppEvent::Tick);
+        app.update(AppEvent::ToolCall("Bash".to_string()));
+        let later = app.waiting_since + Duration::from_secs(3);
+        assert_eq!(
+            app.activity(later).as_deref(),
+            Some("⠙ running tool Bash… 3s")
+        );
+
+        app.update(AppEvent::Finished(None));
+        assert!(app.activity(later).is_none());
+    }
+
+    #[test]
+    fn test_app_scroll_keys() {
+        let mut app = app();
+        app.viewport_height = 10;
+        app.update(AppEvent::AgentChunk("line\n".repeat(30)));
+
+        app.update(key(KeyCode::Char('j')));
+        app.update(key(KeyCode::Char('j')));
+        assert_eq!(app.scroll, 2);
+        app.update(key(KeyCode::Char('k')));
+        assert_eq!(app.scroll, 1);
+        app.update(AppEvent::Key(KeyEvent::new(
+            KeyCode::Char('d'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.scroll, 6);
+        app.update(AppEvent::Key(KeyEvent::new(
+            KeyCode::Char('G'),
+            KeyModifiers::SHIFT,
+        )));
+        assert_eq!(app.scroll, 20);
+        app.update(key(KeyCode::Char('g')));
+        assert_eq!(app.scroll, 0);
+
+        app.update(key(KeyCode::Char('G')));
+        app.update(key(KeyCode::Char('2')));
+        assert_eq!(app.panes.active(), Pane::Diff);
+        assert_eq!(app.scroll, 0);
+        app.update(key(KeyCode::Char('h')));
+        assert_eq!(app.panes.active(), Pane::Response);
+    }
+
+    #[test]
+    fn test_app_pause_holds_output() {
+        let mut app = app();
+        app.update(AppEvent::AgentChunk("Reading. ".to_string()));
+        app.update(key(KeyCode::Char('p')));
+        assert_eq!(app.state, TuiState::Paused);
+
+        // Output is held, and the task stays paused, until resumed
+        app.update(AppEvent::ToolCall("Read".to_string()));
+        app.update(AppEvent::AgentChunk("Editing.".to_string()));
+        assert_eq!(app.state, TuiState::Paused);
+        assert_eq!(app.panes.active_content(), "Reading. ");
+
+        app.update(key(KeyCode::Char(' ')));
+        assert_eq!(app.state, TuiState::Running);
+        assert_eq!(app.panes.active_content(), "Reading. Editing.");
+
+        // Finishing releases the held output
+        app.update(key(KeyCode::Char('p')));
+        app.update(AppEvent::AgentChunk(" Done.".to_string()));
+        app.update(AppEvent::Finished(None));
+        assert_eq!(app.state, TuiState::Completed);
+        assert_eq!(app.panes.active_content(), "Reading. Editing. Done.");
+        app.update(key(KeyCode::Char('p')));
+        assert_eq!(app.state, TuiState::Completed);
+    }
+
+    #[test]
+    fn test_app_abort_request() {
+        let mut app = app();
+        app.update(key(KeyCode::Char('x')));
+        assert!(!app.take_abort_request());
+
+        app.update(AppEvent::PhaseChange("implementation".to_string()));
+        app.update(key(KeyCode::Char('x')));
+        assert!(app.take_abort_request());
+        assert!(!app.take_abort_request());
+        assert!(!app.should_quit());
+    }
+
+    #[test]
+    fn test_app_scrolls_wrapped_lines() {
+        let mut app = app();
+        app.viewport_height = 10;
+        app.viewport_width = 20;
+        app.update(AppEvent::AgentChunk(
+            format!("{}\n", "word ".repeat(8)).repeat(10),
+        ));
+
+        // Each 40-column line wraps onto two rows of the pane
+        app.update(key(KeyCode::Char('G')));
+        assert_eq!(app.scroll, 10);
+    }
+
+    #[test]
+    fn test_app_follows_output() {
+        let mut app = app();
+        app.viewport_height = 10;
+        app.update(AppEvent::AgentChunk("line\n".repeat(30)));
+        assert!(app.follow);
+
+        app.update(key(KeyCode::Char('k')));
+        assert!(!app.follow);
+        app.update(key(KeyCode::Char('G')));
+        assert!(app.follow);
+        assert_eq!(app.scroll, 20);
+    }
+
+    #[test]
+    fn test_panes_active_content() {
+        let mut panes = Panes::new();
+        panes.set_response("response text");
+        panes.set_diff("diff text");
+
+        assert_eq!(panes.active(), Pane::Response);
+        assert_eq!(panes.active_content(), "response text");
+
+        panes.next();
+        assert_eq!(panes.active(), Pane::Diff);
+        assert_eq!(panes.active_content(), "diff text");
+
+        panes.next();
+        assert_eq!(panes.active(), Pane::Response);
+    }
 }
@@ -0,0 +1,117 @@
+//! `gba worktree`: listing and pruning feature worktrees.
+
+use gba_core::state::FeatureState;
+use gba_core::worktree::{StaleWorktree, WorktreeManager};
+use tracing::{debug, instrument, warn};
+
+use crate::config::ConfigManager;
+use crate::error::Result as CliResult;
+use crate::output::output;
+
+/// List feature worktrees.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+///
+/// # Errors
+///
+/// Returns an error if the project is not a git repository or the worktrees
+/// cannot be listed.
+pub fn list_worktrees(config: &ConfigManager) -> CliResult<()> {
+    config
+        .vcs(config.project_path())
+        .require_worktrees()
+        .map_err(gba_core::CoreError::from)?;
+    let worktrees = worktree_manager(config)
+        .list()
+        .map_err(gba_core::CoreError::from)?;
+
+    let out = output();
+    out.section("Feature Worktrees");
+    for worktree in &worktrees {
+        let branch = worktree.branch.as_deref().unwrap_or("(detached)");
+        out.list_item(&worktree.path.display().to_string(), branch);
+    }
+    out.print(&format!("\nTotal: {} worktrees", worktrees.len()));
+
+    Ok(())
+}
+
+/// Remove stale feature worktrees according to the configured prune policy.
+///
+/// Feature state of removed worktrees is updated so that a later
+/// implementation run creates a fresh worktree.
+///
+/// # Arguments
+///
+/// * `config` - Configuration manager.
+/// * `dry_run` - Only report what would be removed.
+/// * `delete_unmerged` - Also delete the unmerged branches of abandoned
+///   worktrees.
+///
+/// # Errors
+///
+/// Returns an error if stale worktrees cannot be detected or state cannot be
+/// updated.
+#[instrument(skip(config))]
+pub fn prune_worktrees(
+    config: &ConfigManager,
+    dry_run: bool,
+    delete_unmerged: bool,
+) -> CliResult<()> {
+    let manager = worktree_manager(config);
+    let mut policy = config.config().worktree.prune.clone();
+    policy.delete_unmerged |= delete_unmerged;
+    let policy = &policy;
+
+    let stale = if dry_run {
+        manager.find_stale(policy)
+    } else {
+        manager.cleanup(policy)
+    }
+    .map_err(gba_core::CoreError::from)?;
+
+    let out = output();
+    out.section(if dry_run {
+        "Worktrees To Remove"
+    } else {
+        "Removed Worktrees"
+    });
+    for entry in &stale {
+        out.list_item(&entry.feature, &entry.reason.to_string());
+        if !dry_run {
+            detach_worktree_from_state(config, entry)?;
+        }
+    }
+    out.print(&format!("\nTotal: {} worktrees", stale.len()));
+
+    Ok(())
+}
+
+/// Create a worktree manager from the project configuration.
+pub fn worktree_manager(config: &ConfigManager) -> WorktreeManager {
+    WorktreeManager::new(config.project_path(), config.config().worktree.clone())
+        .with_base(config.config().project.repository.main_branch.clone())
+}
+
+/// Clear a removed worktree from its feature state.
+///
+/// Worktree directories are named `<id>-<slug>`, which locates the state file.
+fn detach_worktree_from_state(config: &ConfigManager, stale: &StaleWorktree) -> CliResult<()> {
+    let Some((feature_id, _)) = stale.feature.split_once('-') else {
+        return Ok(());
+    };
+    let state_path = config.feature_state_path(feature_id);
+    if !state_path.exists() {
+        return Ok(());
+    }
+
+    let mut state = FeatureState::load(&state_path).map_err(gba_core::CoreError::from)?;
+    state.context.worktree = None;
+    state.status.message = Some(format!("Worktree removed ({})", stale.reason));
+    state.save(&state_path).map_err(gba_core::CoreError::from)?;
+
+    debug!("Cleared worktree from state {}", state_path.display());
+    Ok(())
+}
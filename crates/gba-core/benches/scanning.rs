@@ -0,0 +1,95 @@
+//! Benchmarks for repository scanning on synthetic trees.
+//!
+//! Run with `cargo bench -p gba-core --bench scanning`. Set `GBA_BENCH_FILES`
+//! to change the size of the large tree, e.g. `GBA_BENCH_FILES=100000`.
+
+use std::path::{Path, PathBuf};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gba_core::context_builder::{ContextBuilderConfig, scan_repository, walk_directory};
+
+/// Files per directory in the synthetic trees.
+const FILES_PER_DIR: usize = 50;
+
+/// Create a synthetic repository with `files` source files, plus a `target/`
+/// directory of the same size that scans must skip.
+fn synthetic_tree(files: usize) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("gba-bench-scan-{files}"));
+    if root.join(".complete").exists() {
+        return root;
+    }
+    let _ = std::fs::remove_dir_all(&root);
+
+    for top in ["src", "target"] {
+        for i in 0..files {
+            let dir = root
+                .join(top)
+                .join(format!("m{:03}", i / (FILES_PER_DIR * FILES_PER_DIR)))
+                .join(format!("d{:03}", (i / FILES_PER_DIR) % FILES_PER_DIR));
+            if i % FILES_PER_DIR == 0 {
+                std::fs::create_dir_all(&dir).unwrap();
+            }
+            std::fs::write(dir.join(format!("f{i:06}.rs")), "pub fn f() {}\n").unwrap();
+        }
+    }
+    std::fs::write(root.join(".complete"), "").unwrap();
+    root
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread().build().unwrap()
+}
+
+fn bench_walk(c: &mut Criterion, sizes: &[usize]) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("walk_directory");
+    group.sample_size(10);
+    for &files in sizes {
+        let root = synthetic_tree(files);
+        group.bench_with_input(BenchmarkId::from_parameter(files), &root, |b, root| {
+            b.iter(|| rt.block_on(walk_directory(root)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn bench_scan(c: &mut Criterion, sizes: &[usize]) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("scan_repository");
+    group.sample_size(10);
+    for &files in sizes {
+        let root = synthetic_tree(files);
+        for max_files in [100, usize::MAX] {
+            let config = ContextBuilderConfig::default().with_max_files(max_files);
+            let id = BenchmarkId::new(files.to_string(), max_label(max_files));
+            group.bench_with_input(id, &root, |b, root: &PathBuf| {
+                b.iter(|| {
+                    rt.block_on(scan_repository(Path::new(root), &config))
+                        .unwrap()
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+fn max_label(max_files: usize) -> String {
+    if max_files == usize::MAX {
+        "all".to_string()
+    } else {
+        format!("max-{max_files}")
+    }
+}
+
+fn benches(c: &mut Criterion) {
+    let large = std::env::var("GBA_BENCH_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(20_000);
+    let sizes = [1_000, large];
+    bench_walk(c, &sizes);
+    bench_scan(c, &sizes);
+}
+
+criterion_group!(scanning, benches);
+criterion_main!(scanning);
@@ -1,16 +1,50 @@
 //! Agent implementation for interacting with Claude Agent SDK.
 
+use std::collections::HashMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use claude_agent_sdk_rs::types::mcp::McpStdioServerConfig;
 use claude_agent_sdk_rs::{
-    ClaudeAgentOptions, ContentBlock, Message, PermissionMode, SettingSource, SystemPrompt, query,
+    ClaudeAgentOptions, ClaudeClient, ClaudeError, ContentBlock, HookEvent, HookMatcher,
+    McpServerConfig, McpServers, Message, PermissionMode, ResultMessage, SettingSource,
+    SystemPrompt, query,
 };
+use futures::{FutureExt, Stream, StreamExt};
+use gba_pm::constraints;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
-use crate::config::AgentConfig;
-use crate::context_builder::{ContextBuilderConfig, build_context};
+use crate::audit::AuditLog;
+use crate::config::{AgentConfig, LimitsConfig, OutputConstraint};
+use crate::context_builder::{ContextBuilderConfig, build_context, estimate_tokens};
 use crate::error::{CoreError, Result};
-use crate::task::{Context as TaskContext, Response, Task};
+use crate::events::{self, RunEvents};
+use crate::layout::{LayoutRenderer, PromptLayout};
+use crate::metrics::Metrics;
+use crate::models::ModelRegistry;
+use crate::postprocess::PostProcessPipeline;
+use crate::protect::{PathGuard, Violation};
+use crate::sandbox::{SandboxPolicy, ToolPolicy};
+use crate::session::{self, Session};
+use crate::state::StateTracker;
+use crate::stream::{self, Chunk};
+use crate::task::{Context as TaskContext, GenerationConfig, Response, Task, ToolCall, Usage};
+use crate::task_kind::TaskKindPlugin;
+use crate::transcript::{self, Transcript};
+
+/// Environment variable capping the output tokens of each response of the
+/// Claude Code CLI the SDK runs.
+const MAX_OUTPUT_TOKENS_ENV: &str = "CLAUDE_CODE_MAX_OUTPUT_TOKENS";
+
+/// System prompt of tasks that don't set their own.
+const DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful coding assistant.";
+
+/// Prompt continuing a resumed session.
+const RESUME_PROMPT: &str = "Continue the task from where you left off.";
 
 /// Agent for interacting with Claude Agent SDK.
 ///
@@ -43,6 +77,42 @@ pub struct Agent {
     config: AgentConfig,
     /// Working directory for the agent.
     working_dir: PathBuf,
+    /// Metrics handle tasks are recorded in.
+    metrics: Option<Metrics>,
+    /// Phase tasks are recorded under, e.g. `"implementation"`.
+    phase: String,
+    /// File the transcript of each task is written to.
+    transcript_path: Option<PathBuf>,
+    /// Audit log tool calls are recorded in.
+    audit: Option<AuditLog>,
+    /// Tools the agent may use; empty allows all tools.
+    allowed_tools: Vec<String>,
+    /// Model table used to estimate costs.
+    models: ModelRegistry,
+    /// Post-processors applied to response content.
+    post_processing: PostProcessPipeline,
+    /// Requirements on responses, see [`Self::with_constraints`].
+    constraints: Vec<OutputConstraint>,
+    /// Publisher of the run's tool call events.
+    events: Option<RunEvents>,
+    /// Tracker saving the progress of each turn to the feature state.
+    state: Option<StateTracker>,
+    /// Session to resume instead of starting a new one.
+    resume: Option<String>,
+    /// Turn and cost limits of each task.
+    limits: Option<LimitsConfig>,
+    /// Token canceling the running task.
+    cancel: Option<CancellationToken>,
+    /// Renderer laying out the full prompt instead of the built-in format.
+    layout: Option<Arc<dyn LayoutRenderer>>,
+    /// Whether files in prompts are prefixed with line numbers.
+    line_numbers: bool,
+    /// Directory each task's session is recorded in.
+    sessions: Option<PathBuf>,
+    /// Stream the output of each task is sent to in chunks.
+    chunks: Option<ChunkStream>,
+    /// Scratch directory of the run.
+    scratch_dir: Option<PathBuf>,
 }
 
 impl fmt::Debug for Agent {
@@ -50,6 +120,23 @@ impl fmt::Debug for Agent {
         f.debug_struct("Agent")
             .field("working_dir", &self.working_dir)
             .field("config", &self.config)
+            .field("phase", &self.phase)
+            .field("metrics", &self.metrics.is_some())
+            .field("transcript_path", &self.transcript_path)
+            .field("audit", &self.audit.as_ref().map(AuditLog::path))
+            .field("allowed_tools", &self.allowed_tools)
+            .field("post_processing", &self.post_processing)
+            .field("constraints", &self.constraints)
+            .field("events", &self.events.is_some())
+            .field("state", &self.state.as_ref().map(StateTracker::path))
+            .field("resume", &self.resume)
+            .field("limits", &self.limits)
+            .field("cancel", &self.cancel.is_some())
+            .field("layout", &self.layout)
+            .field("line_numbers", &self.line_numbers)
+            .field("sessions", &self.sessions)
+            .field("chunks", &self.chunks.is_some())
+            .field("scratch_dir", &self.scratch_dir)
             .finish()
     }
 }
@@ -83,7 +170,277 @@ impl Agent {
 
         tracing::info!("Created agent with model: {}", config.model);
 
-        Self { config, working_dir }
+        Self {
+            config,
+            working_dir,
+            metrics: None,
+            phase: "task".to_string(),
+            transcript_path: None,
+            audit: None,
+            allowed_tools: Vec::new(),
+            models: ModelRegistry::builtin(),
+            post_processing: PostProcessPipeline::default(),
+            constraints: Vec::new(),
+            events: None,
+            state: None,
+            resume: None,
+            limits: None,
+            cancel: None,
+            layout: None,
+            line_numbers: false,
+            sessions: None,
+            chunks: None,
+            scratch_dir: None,
+        }
+    }
+
+    /// Set the working directory the agent operates in.
+    ///
+    /// # Arguments
+    ///
+    /// * `working_dir` - Directory the agent runs tools in, e.g. a feature worktree.
+    #[must_use]
+    pub fn with_working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = working_dir.into();
+        self
+    }
+
+    /// Record executed tasks in a metrics handle.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Handle shared with the embedder, see [`Metrics`].
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Set the phase executed tasks are recorded under in the metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - Phase name, e.g. `"implementation"`. Defaults to `"task"`.
+    #[must_use]
+    pub fn with_phase(mut self, phase: impl Into<String>) -> Self {
+        self.phase = phase.into();
+        self
+    }
+
+    /// Write a JSONL transcript of each executed task.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Transcript file, overwritten by every task. See
+    ///   [`crate::transcript`] for the format.
+    #[must_use]
+    pub fn with_transcript(mut self, path: impl Into<PathBuf>) -> Self {
+        self.transcript_path = Some(path.into());
+        self
+    }
+
+    /// Record every tool call of executed tasks in an audit log.
+    ///
+    /// # Arguments
+    ///
+    /// * `audit` - Audit log, e.g. the feature's `audit.jsonl`. See
+    ///   [`crate::audit`] for the format.
+    #[must_use]
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Restrict the tools the agent may use.
+    ///
+    /// The tools are further restricted by the `allowedTools` and
+    /// `deniedTools` of the configuration, see [`ToolPolicy`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tools` - Tool names, e.g. `"Read"`. Empty allows all tools.
+    #[must_use]
+    pub fn with_allowed_tools(mut self, tools: Vec<String>) -> Self {
+        self.allowed_tools = tools;
+        self
+    }
+
+    /// Set the model table, e.g. with the project's overrides.
+    ///
+    /// Used to estimate the cost of tasks when the SDK doesn't report it.
+    ///
+    /// # Arguments
+    ///
+    /// * `models` - Model table, see [`ModelRegistry`].
+    #[must_use]
+    pub fn with_model_registry(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Post-process the content of responses, e.g. to strip the prose before
+    /// a plan's first heading.
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - Post-processors, see [`crate::postprocess`]. Empty by
+    ///   default.
+    #[must_use]
+    pub fn with_post_processing(mut self, pipeline: PostProcessPipeline) -> Self {
+        self.post_processing = pipeline;
+        self
+    }
+
+    /// Require responses to meet output constraints, e.g. a plan with
+    /// numbered phases.
+    ///
+    /// Their instructions are appended to the prompt. A response that breaks
+    /// one, checked once post-processed, is sent back once to be fixed in the
+    /// same conversation, and the task fails with
+    /// [`CoreError::OutputConstraints`] if the fixed response still does.
+    ///
+    /// # Arguments
+    ///
+    /// * `constraints` - Constraints, see [`gba_pm::constraints`]. None by
+    ///   default.
+    #[must_use]
+    pub fn with_constraints(mut self, constraints: Vec<OutputConstraint>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Publish an event for every tool call of the agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - Publisher of the run, see [`crate::events`].
+    #[must_use]
+    pub fn with_events(mut self, events: RunEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Set the tracker saving the progress of each turn to the feature state.
+    ///
+    /// # Arguments
+    ///
+    /// * `tracker` - Tracker of the feature's `state.yml`.
+    #[must_use]
+    pub fn with_state_tracker(mut self, tracker: StateTracker) -> Self {
+        self.state = Some(tracker);
+        self
+    }
+
+    /// Resume a session of an earlier run instead of starting a new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - Session recorded in the feature state.
+    #[must_use]
+    pub fn with_resume(mut self, session_id: impl Into<String>) -> Self {
+        self.resume = Some(session_id.into());
+        self
+    }
+
+    /// Limit the turns and cost of each task.
+    ///
+    /// The turns and estimated cost of each task are tracked as its messages
+    /// stream in, and a task exceeding a limit is interrupted. It fails with
+    /// [`CoreError::TurnLimitReached`] or [`CoreError::BudgetExceeded`],
+    /// holding what it produced until then and its actual usage. The turns of a [`Task`] override the limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - Execution limits of the project.
+    #[must_use]
+    pub fn with_limits(mut self, limits: LimitsConfig) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Cancel the running task when a token is canceled.
+    ///
+    /// The task fails with [`CoreError::Canceled`].
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Token canceling the task.
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Lay out the full prompt of each task with a renderer instead of the
+    /// built-in format.
+    ///
+    /// # Arguments
+    ///
+    /// * `renderer` - Renderer of the prompt layout.
+    #[must_use]
+    pub fn with_layout_renderer(mut self, renderer: Arc<dyn LayoutRenderer>) -> Self {
+        self.layout = Some(renderer);
+        self
+    }
+
+    /// Prefix the lines of files in prompts with their line numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to number lines.
+    #[must_use]
+    pub const fn with_line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// Record the full message history of each task as a session in a
+    /// directory, to resume it with [`Self::resume_session`].
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory of the sessions, e.g. `.gba/sessions`.
+    #[must_use]
+    pub fn with_sessions_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.sessions = Some(dir.into());
+        self
+    }
+
+    /// Send the output of each task in chunks as it streams in, ending with
+    /// [`Chunk::Done`] even if the task fails, see [`crate::stream`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - Sender of the chunks.
+    #[must_use]
+    pub fn with_chunks(mut self, tx: mpsc::UnboundedSender<Chunk>) -> Self {
+        self.chunks = Some(ChunkStream::new(tx));
+        self
+    }
+
+    /// Point the agent to a scratch directory for its intermediate files.
+    ///
+    /// The prompt names the directory, and the sandbox allows it even when
+    /// paths are restricted to the working directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Scratch directory of the run, see [`crate::scratch`].
+    #[must_use]
+    pub fn with_scratch_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.scratch_dir = Some(dir.into());
+        self
+    }
+
+    /// Configure the agent for a task kind: its phase and default tools.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Task kind, see [`crate::task_kind`].
+    #[must_use]
+    pub fn with_task_kind(self, kind: &dyn TaskKindPlugin) -> Self {
+        self.with_phase(kind.name())
+            .with_allowed_tools(kind.default_tools())
     }
 
     /// Execute a task with the given prompt and context.
@@ -131,56 +488,130 @@ impl Agent {
     /// ```
     #[tracing::instrument(skip(self, prompt, context))]
     pub async fn execute(&self, prompt: &str, context: &TaskContext) -> Result<Response> {
+        self.measured(
+            self.query_prompt(prompt, context),
+            None,
+            self.chunks.as_ref(),
+        )
+        .await
+    }
+
+    /// Query with a prompt and context, collecting the response.
+    async fn query_prompt(&self, prompt: &str, context: &TaskContext) -> Result<Response> {
         tracing::info!("Executing task with prompt: {}", prompt);
 
         // Build the full prompt with context
-        let full_prompt = self.build_prompt(prompt, context);
+        let full_prompt = self.build_prompt(prompt, context)?;
 
         // Build options
-        let options = Self::build_options(&self.config)?;
+        let TaskOptions {
+            options,
+            tools,
+            generation,
+            ..
+        } = self.task_options(DEFAULT_SYSTEM_PROMPT, &[], None, None, None);
 
-        // Send the query using the simple query API
-        let messages = query(&full_prompt, Some(options))
-            .await
-            .map_err(|e| CoreError::ClaudeAgent(format!("Failed to send query: {e}")))?;
+        // Send the query
+        let mut recorder = self.session_recorder(&full_prompt);
+        let exchange = self
+            .send_query(
+                &full_prompt,
+                options.clone(),
+                &tools,
+                &mut recorder,
+                self.chunks.as_ref(),
+            )
+            .await?;
+        self.record_transcript(&full_prompt, &exchange.messages)
+            .await;
+        let response = self.collect_response(exchange, generation.clone())?;
+        self.enforce_constraints(
+            response,
+            options,
+            &tools,
+            generation,
+            &mut recorder,
+            self.chunks.as_ref(),
+        )
+        .await
+    }
 
-        // Collect all messages
-        let mut response = Response::default();
+    /// Collect the response of a query from its messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::TurnLimitReached`] or [`CoreError::BudgetExceeded`]
+    /// if the task stopped on a limit.
+    fn collect_response(
+        &self,
+        exchange: Exchange,
+        generation: GenerationConfig,
+    ) -> Result<Response> {
+        let max_turns = generation.max_turns;
+        let mut response = Response {
+            violations: exchange.violations,
+            generation: Some(generation),
+            ..Response::default()
+        };
+        let mut limit = exchange.limit;
+        let mut finished = false;
+        // Tool calls awaiting their result, with when they were received
+        let mut calls = HashMap::new();
 
-        for message in &messages {
-            match message {
-                Message::User(user_msg) => {
-                    // Track user messages if needed
-                    if let Some(ref content) = user_msg.content {
-                        for block in content {
-                            if let ContentBlock::Text(text) = block {
-                                tracing::debug!("User message: {}", text.text);
-                            }
-                        }
+        for (index, message) in exchange.messages.iter().enumerate() {
+            let received = exchange.received.get(index).copied();
+            let blocks = match message {
+                Message::User(user_msg) => user_msg.content.as_deref().unwrap_or_default(),
+                Message::Assistant(msg) => &msg.message.content,
+                _ => &[],
+            };
+            for block in blocks {
+                match block {
+                    ContentBlock::Text(text) if matches!(message, Message::User(_)) => {
+                        tracing::debug!("User message: {}", text.text);
                     }
-                }
-                Message::Assistant(msg) => {
-                    for block in &msg.message.content {
-                        match block {
-                            ContentBlock::Text(text) => {
-                                response.content.push_str(&text.text);
-                            }
-                            ContentBlock::ToolUse(tool) => {
-                                tracing::debug!("Tool used: {} ({})", tool.name, tool.id);
-                            }
-                            ContentBlock::ToolResult(result) => {
-                                tracing::debug!("Tool result: {}", result.tool_use_id);
-                            }
-                            _ => {}
-                        }
+                    ContentBlock::Text(text) => response.content.push_str(&text.text),
+                    ContentBlock::ToolUse(tool) => {
+                        tracing::debug!("Tool used: {} ({})", tool.name, tool.id);
+                        calls.insert(tool.id.clone(), (response.tool_calls.len(), received));
+                        response.tool_calls.push(ToolCall {
+                            id: tool.id.clone(),
+                            name: tool.name.clone(),
+                            arguments: tool.input.clone(),
+                            result: None,
+                            is_error: false,
+                            duration_ms: None,
+                        });
                     }
+                    ContentBlock::ToolResult(result) => {
+                        tracing::debug!("Tool result: {}", result.tool_use_id);
+                        let Some((call, called)) = calls.remove(&result.tool_use_id) else {
+                            continue;
+                        };
+                        let call = &mut response.tool_calls[call];
+                        call.result = Some(transcript::tool_result_text(result));
+                        call.is_error = result.is_error.unwrap_or(false);
+                        call.duration_ms = called.zip(received).map(|(called, received)| {
+                            u64::try_from(received.duration_since(called).as_millis())
+                                .unwrap_or(u64::MAX)
+                        });
+                    }
+                    _ => {}
                 }
+            }
+
+            match message {
+                Message::User(_) | Message::Assistant(_) => {}
                 Message::Result(result) => {
                     tracing::info!(
                         "Query completed. Turns: {}, Duration: {}ms",
                         result.num_turns,
                         result.duration_ms
                     );
+                    response.turns = result.num_turns;
+                    response.session_id = Some(result.session_id.clone());
+                    limit = limit.or_else(|| self.result_limit(result, max_turns));
+                    finished = true;
 
                     if let Some(ref usage) = result.usage {
                         // Parse usage from JSON value
@@ -195,9 +626,9 @@ impl Agent {
                             response.usage.output_tokens = output_tokens as u32;
                         }
                     }
-                    if let Some(cost) = result.total_cost_usd {
-                        response.usage.total_cost_usd = cost;
-                    }
+                    response.usage.total_cost_usd = result
+                        .total_cost_usd
+                        .unwrap_or_else(|| self.estimate_cost(&response.usage));
                     tracing::info!(
                         "Usage: Input tokens: {}, Output tokens: {}, Cost: ${:.4}",
                         response.usage.input_tokens,
@@ -211,7 +642,7 @@ impl Agent {
             }
         }
 
-        Ok(response)
+        self.enforce_limit(response, limit, &exchange.messages, finished)
     }
 
     /// Execute a task with a [`Task`] object.
@@ -249,6 +680,107 @@ impl Agent {
     /// ```
     #[tracing::instrument(skip(self, task))]
     pub async fn execute_task(&self, task: &Task) -> Result<Response> {
+        let chunks = self.chunks.as_ref();
+        self.measured(self.query_task(task, chunks), task.timeout, chunks)
+            .await
+    }
+
+    /// Execute a task, streaming its output in chunks as it runs.
+    ///
+    /// The task runs like [`Self::execute_task`], with its system prompt,
+    /// tools, limits and timeout, and its chunks follow the semantics of
+    /// [`crate::stream`]: the stream ends after the task's [`Chunk::Done`].
+    /// The chunks go to the returned stream only, not to the sender set
+    /// with [`Self::with_chunks`].
+    ///
+    /// The task runs while the stream is polled; dropping the stream cancels
+    /// it.
+    ///
+    /// # Arguments
+    ///
+    /// * `task` - The task to execute.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use gba_core::stream::Chunk;
+    /// use gba_core::{Agent, AgentConfig, Context, Task};
+    ///
+    /// # async fn run() {
+    /// let agent = Agent::new(AgentConfig::default());
+    /// let task = Task::with_defaults("Implement feature X", Context::default());
+    ///
+    /// let mut chunks = std::pin::pin!(agent.execute_task_stream(&task));
+    /// while let Some(chunk) = chunks.next().await {
+    ///     if let Chunk::Text(text) = chunk {
+    ///         print!("{text}");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn execute_task_stream<'a>(
+        &'a self,
+        task: &'a Task,
+    ) -> impl Stream<Item = Chunk> + Send + 'a {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let run = async move {
+            let chunks = ChunkStream::new(tx);
+            // The outcome reaches the stream as its final chunks
+            let _ = self
+                .measured(
+                    self.query_task(task, Some(&chunks)),
+                    task.timeout,
+                    Some(&chunks),
+                )
+                .await;
+        };
+        drive(rx, run)
+    }
+
+    /// Show what executing a task would send to the agent, without
+    /// contacting it: the full prompt, and the settings of the task.
+    ///
+    /// # Arguments
+    ///
+    /// * `task` - Task to preview.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt cannot be laid out.
+    pub fn preview(&self, task: &Task) -> Result<TaskPreview> {
+        let TaskOptions {
+            tools,
+            generation,
+            permission_mode,
+            ..
+        } = self.task_options(
+            &task.system_prompt,
+            &task.tools,
+            Some(task.max_turns),
+            task.max_tokens,
+            task.permission_mode,
+        );
+        let prompt = self.build_prompt(&task.prompt, &task.context)?;
+        Ok(TaskPreview {
+            estimated_tokens: estimate_tokens(&task.system_prompt) + estimate_tokens(&prompt),
+            context_bytes: task
+                .context
+                .files
+                .iter()
+                .map(|file| file.content.len())
+                .sum(),
+            prompt,
+            system_prompt: task.system_prompt.clone(),
+            tools,
+            generation,
+            permission_mode,
+        })
+    }
+
+    /// Query with a task's settings, collecting the response and sending
+    /// its output to `chunks`.
+    async fn query_task(&self, task: &Task, chunks: Option<&ChunkStream>) -> Result<Response> {
         tracing::info!(
             "Executing task with system prompt: {} ({} turns)",
             task.system_prompt,
@@ -256,66 +788,113 @@ impl Agent {
         );
 
         // Build options with task-specific settings
-        let system_prompt: SystemPrompt = task.system_prompt.clone().into();
-        let options = ClaudeAgentOptions::builder()
-            .model(self.config.model.clone())
-            .system_prompt(system_prompt)
-            .permission_mode(PermissionMode::BypassPermissions)
-            .setting_sources(vec![SettingSource::User, SettingSource::Project])
-            .max_turns(task.max_turns)
-            .build();
+        let TaskOptions {
+            options,
+            tools,
+            generation,
+            ..
+        } = self.task_options(
+            &task.system_prompt,
+            &task.tools,
+            Some(task.max_turns),
+            task.max_tokens,
+            task.permission_mode,
+        );
 
         // Build the full prompt with context
-        let full_prompt = self.build_prompt(&task.prompt, &task.context);
+        let full_prompt = self.build_prompt(&task.prompt, &task.context)?;
 
         // Send the query
-        let messages = query(&full_prompt, Some(options))
+        let mut recorder = self.session_recorder(&full_prompt);
+        let exchange = self
+            .send_query(&full_prompt, options.clone(), &tools, &mut recorder, chunks)
+            .await?;
+        self.record_transcript(&full_prompt, &exchange.messages)
+            .await;
+        let response = self.collect_response(exchange, generation.clone())?;
+        self.enforce_constraints(response, options, &tools, generation, &mut recorder, chunks)
             .await
-            .map_err(|e| CoreError::ClaudeAgent(format!("Failed to send query: {e}")))?;
-
-        // Collect all messages
-        let mut response = Response::default();
+    }
 
-        for message in &messages {
-            match message {
-                Message::Assistant(msg) => {
-                    for block in &msg.message.content {
-                        if let ContentBlock::Text(text) = block {
-                            response.content.push_str(&text.text);
-                        }
-                    }
-                }
-                Message::Result(result) => {
-                    if let Some(ref usage) = result.usage {
-                        if let Some(input_tokens) =
-                            usage.get("input_tokens").and_then(|v| v.as_u64())
-                        {
-                            response.usage.input_tokens = input_tokens as u32;
-                        }
-                        if let Some(output_tokens) =
-                            usage.get("output_tokens").and_then(|v| v.as_u64())
-                        {
-                            response.usage.output_tokens = output_tokens as u32;
-                        }
-                    }
-                    if let Some(cost) = result.total_cost_usd {
-                        response.usage.total_cost_usd = cost;
-                    }
-                }
-                Message::User(_) | Message::System(_) | Message::StreamEvent(_) | Message::ControlCancelRequest(_) => {
-                    // Ignore other message types
-                }
-            }
+    /// Send a response that breaks the agent's output constraints back once
+    /// to be fixed, in the same conversation.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - Response of the task.
+    /// * `options` - Options the task was queried with.
+    /// * `tools` - Tools of the task.
+    /// * `generation` - Generation settings of the task.
+    /// * `recorder` - Recorder of the task's session.
+    /// * `chunks` - Receiver of the task's output.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreError::OutputConstraints`] if the response can't be
+    /// sent back, having no session, or the fixed response still breaks a
+    /// constraint.
+    async fn enforce_constraints(
+        &self,
+        response: Response,
+        options: ClaudeAgentOptions,
+        tools: &ToolPolicy,
+        generation: GenerationConfig,
+        recorder: &mut SessionRecorder,
+        chunks: Option<&ChunkStream>,
+    ) -> Result<Response> {
+        let problems = self.constraint_violations(&response);
+        if problems.is_empty() {
+            return Ok(response);
         }
+        let Some(session_id) = response.session_id.clone() else {
+            return Err(CoreError::OutputConstraints {
+                problems,
+                partial: Box::new(response),
+            });
+        };
 
-        tracing::info!(
-            "Task completed. Input tokens: {}, Output tokens: {}, Cost: ${:.4}",
-            response.usage.input_tokens,
-            response.usage.output_tokens,
-            response.usage.total_cost_usd,
+        tracing::warn!(
+            "Response doesn't meet {} output requirement(s), asking for a fix",
+            problems.len()
         );
+        if let Some(metrics) = &self.metrics {
+            metrics.retry(&self.phase);
+        }
+        let repair = constraints::repair_prompt(&self.constraints, &problems);
+        if let Some(session) = &recorder.session
+            && let Err(e) = session.record_resume(&repair)
+        {
+            tracing::warn!("Failed to record session {}: {}", session.id(), e);
+        }
+        let options = ClaudeAgentOptions {
+            resume: Some(session_id),
+            ..options
+        };
+        let exchange = self
+            .send_query(&repair, options, tools, recorder, chunks)
+            .await?;
+        let repaired = merge_attempts(response, self.collect_response(exchange, generation)?);
+        let problems = self.constraint_violations(&repaired);
+        if problems.is_empty() {
+            Ok(repaired)
+        } else {
+            Err(CoreError::OutputConstraints {
+                problems,
+                partial: Box::new(repaired),
+            })
+        }
+    }
 
-        Ok(response)
+    /// Check a response against the agent's output constraints, as it reads
+    /// once post-processed.
+    fn constraint_violations(&self, response: &Response) -> Vec<String> {
+        if self.constraints.is_empty() {
+            return Vec::new();
+        }
+        constraints::violations(
+            &self.constraints,
+            &self.post_processing.apply(&response.content),
+        )
     }
 
     /// Execute a task with context building.
@@ -371,6 +950,62 @@ impl Agent {
         self.execute(prompt, &context).await
     }
 
+    /// Resume a recorded session, continuing its task from where it left
+    /// off, e.g. after a crash or reboot.
+    ///
+    /// The conversation is resumed from the latest SDK session the session
+    /// recorded, and the new messages are appended to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Session id, the [`Response::session_id`] of the task.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no sessions directory is set, the session cannot
+    /// be opened, or the query fails.
+    #[tracing::instrument(skip(self))]
+    pub async fn resume_session(&self, id: &str) -> Result<Response> {
+        self.measured(self.query_session(id), None, self.chunks.as_ref())
+            .await
+    }
+
+    /// Query to continue a recorded session, collecting the response.
+    async fn query_session(&self, id: &str) -> Result<Response> {
+        let dir = self
+            .sessions
+            .as_deref()
+            .ok_or_else(|| CoreError::Config("No sessions directory set".to_string()))?;
+        let session = Session::open(dir, id)?;
+        let resume = session.latest_session_id()?;
+        tracing::info!("Resuming session {} from {}", id, resume);
+        session.record_resume(RESUME_PROMPT)?;
+
+        let TaskOptions {
+            options,
+            tools,
+            generation,
+            ..
+        } = self.task_options(DEFAULT_SYSTEM_PROMPT, &[], None, None, None);
+        let options = ClaudeAgentOptions {
+            resume: Some(resume),
+            ..options
+        };
+        let mut recorder = SessionRecorder::resumed(session);
+        let exchange = self
+            .send_query(
+                RESUME_PROMPT,
+                options,
+                &tools,
+                &mut recorder,
+                self.chunks.as_ref(),
+            )
+            .await?;
+        self.record_transcript(RESUME_PROMPT, &exchange.messages)
+            .await;
+        self.collect_response(exchange, generation)
+    }
+
     /// Get the agent configuration.
     #[must_use]
     pub const fn config(&self) -> &AgentConfig {
@@ -383,66 +1018,799 @@ impl Agent {
         &self.working_dir
     }
 
-    /// Build the full prompt with context.
-    fn build_prompt(&self, prompt: &str, context: &TaskContext) -> String {
-        let mut full_prompt = String::new();
+    /// Run a task, post-processing its response, finishing its chunks and
+    /// recording it in the metrics if a handle is set.
+    ///
+    /// `timeout` overrides the agent's timeout, in seconds, and `chunks`
+    /// receives the task's final chunks.
+    async fn measured(
+        &self,
+        task: impl Future<Output = Result<Response>>,
+        timeout: Option<u64>,
+        chunks: Option<&ChunkStream>,
+    ) -> Result<Response> {
+        let task = async {
+            if let Some(chunks) = chunks {
+                chunks.start();
+            }
+            let result = self
+                .bounded(task, timeout.unwrap_or(self.config.timeout))
+                .await
+                .map(|response| self.post_processing.process(response));
+            if let Some(chunks) = chunks {
+                chunks.finish(&result, |spend| self.spend_usage(spend));
+            }
+            result
+        };
+        let Some(metrics) = &self.metrics else {
+            return task.await;
+        };
 
-        // Add context information
-        full_prompt.push_str("\n## Repository Context\n\n");
-        full_prompt.push_str(&format!(
-            "Repository path: {}\n",
-            context.repository_path.display()
-        ));
-        full_prompt.push_str(&format!("Branch: {}\n", context.branch));
-        if !context.files.is_empty() {
-            full_prompt.push_str(&format!("Files: {}\n\n", context.files.len()));
-
-            for file in &context.files {
-                full_prompt.push_str(&format!(
-                    "### {}\n\n```\n{}\n```\n\n",
-                    file.path.display(),
-                    file.content
-                ));
+        metrics.task_started(&self.phase);
+        let started = Instant::now();
+        let result = task.await;
+        match &result {
+            Ok(response) => {
+                metrics.task_succeeded(&self.phase, &response.usage, started.elapsed());
             }
-        } else {
-            full_prompt.push('\n');
+            Err(_) => metrics.task_failed(&self.phase, started.elapsed()),
         }
+        result
+    }
 
-        // Add metadata
-        if !context.metadata.is_empty() {
-            full_prompt.push_str("\n## Metadata\n\n");
-            for (key, value) in &context.metadata {
-                full_prompt.push_str(&format!("{}: {}\n", key, value));
+    /// Run a task within a timeout in seconds, unless it is canceled first.
+    ///
+    /// A timeout of 0 disables it.
+    async fn bounded(
+        &self,
+        task: impl Future<Output = Result<Response>>,
+        seconds: u64,
+    ) -> Result<Response> {
+        let task = async {
+            if seconds == 0 {
+                return task.await;
             }
-            full_prompt.push('\n');
+            tokio::time::timeout(Duration::from_secs(seconds), task)
+                .await
+                .map_err(|_| CoreError::Timeout { seconds })?
+        };
+        match &self.cancel {
+            Some(token) => tokio::select! {
+                result = task => result,
+                () = token.cancelled() => Err(CoreError::Canceled),
+            },
+            None => task.await,
         }
-
-        // Add the main prompt
-        full_prompt.push_str("\n## Task\n\n");
-        full_prompt.push_str(prompt);
-
-        full_prompt
     }
 
-    /// Build Claude Agent Options from AgentConfig.
-    fn build_options(config: &AgentConfig) -> Result<ClaudeAgentOptions> {
-        let system_prompt_text = "You are a helpful coding assistant.";
-        let system_prompt: SystemPrompt = system_prompt_text.into();
+    /// Get the limit a task's result reports it stopped on, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - Result message of the task.
+    /// * `max_turns` - Maximum number of turns of the task.
+    fn result_limit(&self, result: &ResultMessage, max_turns: Option<u32>) -> Option<Limit> {
+        match result.subtype.as_str() {
+            "error_max_turns" => Some(Limit::Turns {
+                taken: result.num_turns,
+                max: max_turns.unwrap_or(result.num_turns),
+            }),
+            "error_max_budget_usd" => {
+                let spent = result.total_cost_usd.unwrap_or_default();
+                Some(Limit::Cost {
+                    spent,
+                    max: self
+                        .limits
+                        .as_ref()
+                        .map_or(spent, |limits| limits.max_cost_usd),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the limit the tracked turns and estimated cost of a running task
+    /// exceed, if any.
+    fn spend_limit(
+        &self,
+        spend: &Spend,
+        max_turns: Option<u32>,
+        max_cost: Option<f64>,
+    ) -> Option<Limit> {
+        if let Some(max) = max_turns.filter(|max| spend.turns > *max) {
+            return Some(Limit::Turns {
+                taken: spend.turns,
+                max,
+            });
+        }
+        let spent = self.spend_usage(spend).total_cost_usd;
+        max_cost
+            .filter(|max| spent > *max)
+            .map(|max| Limit::Cost { spent, max })
+    }
+
+    /// Get the usage of a task from its tracked spend, with its estimated
+    /// cost.
+    fn spend_usage(&self, spend: &Spend) -> Usage {
+        let mut usage = Usage {
+            input_tokens: u32::try_from(spend.input_tokens).unwrap_or(u32::MAX),
+            output_tokens: u32::try_from(spend.output_tokens).unwrap_or(u32::MAX),
+            ..Usage::default()
+        };
+        usage.total_cost_usd = self.estimate_cost(&usage);
+        usage
+    }
+
+    /// Fail a task that stopped on a limit, with what it produced until then.
+    ///
+    /// A task interrupted while streaming has no result message: its turns
+    /// and usage are then tracked from its assistant messages.
+    fn enforce_limit(
+        &self,
+        mut response: Response,
+        limit: Option<Limit>,
+        messages: &[Message],
+        finished: bool,
+    ) -> Result<Response> {
+        let Some(limit) = limit else {
+            return Ok(response);
+        };
+        if !finished {
+            let mut spend = Spend::default();
+            for message in messages {
+                spend.observe(message);
+            }
+            response.turns = spend.turns;
+            response.usage = self.spend_usage(&spend);
+        }
+        let error = limit.into_error(response);
+        tracing::warn!("Task stopped: {}", error);
+        Err(error)
+    }
+
+    /// Send a query and collect all messages, with the tool calls denied for
+    /// modifying protected paths.
+    ///
+    /// Uses the simple query API, unless hooks are needed to enforce the
+    /// sandbox or path protection, or record the audit log, or turns are
+    /// tracked in the feature state: those are only supported by the
+    /// bidirectional client.
+    async fn send_query(
+        &self,
+        prompt: &str,
+        mut options: ClaudeAgentOptions,
+        tools: &ToolPolicy,
+        recorder: &mut SessionRecorder,
+        chunks: Option<&ChunkStream>,
+    ) -> Result<Exchange> {
+        let guard = (!self.config.protected_paths.is_empty())
+            .then(|| PathGuard::new(&self.config.protected_paths, &self.working_dir));
+        options.hooks = self.hooks(tools, guard.as_ref());
+        if options.hooks.is_none()
+            && self.state.is_none()
+            && self.limits.is_none()
+            && chunks.is_none()
+        {
+            let messages = query(prompt, Some(options))
+                .await
+                .map_err(|e| CoreError::ClaudeAgent(format!("Failed to send query: {e}")))?;
+            for message in &messages {
+                recorder.record(message);
+            }
+            return Ok(Exchange {
+                messages,
+                received: Vec::new(),
+                violations: Vec::new(),
+                limit: None,
+            });
+        }
+
+        let max_turns = options.max_turns;
+        let max_cost = options.max_budget_usd;
+
+        let mut client = ClaudeClient::new(options);
+        client
+            .connect()
+            .await
+            .map_err(|e| CoreError::ClaudeAgent(format!("Failed to connect: {e}")))?;
+
+        let result = async {
+            client.query(prompt).await?;
+            let mut messages = Vec::new();
+            let mut received = Vec::new();
+            let mut turn = None;
+            let mut spend = Spend::default();
+            let mut limit = None;
+            let mut stream = client.receive_response();
+            while let Some(message) = stream.next().await {
+                let message = message?;
+                recorder.record(&message);
+                if let Some(chunks) = chunks {
+                    chunks.send(&message, |spend| self.spend_usage(spend));
+                }
+                self.track_turn(&message, &mut turn);
+                spend.observe(&message);
+                messages.push(message);
+                received.push(Instant::now());
+                limit = self.spend_limit(&spend, max_turns, max_cost);
+                if limit.is_some() {
+                    break;
+                }
+            }
+            drop(stream);
+            if limit.is_some() {
+                client.interrupt().await?;
+            }
+            Ok((messages, received, limit))
+        }
+        .await
+        .map_err(|e: ClaudeError| CoreError::ClaudeAgent(format!("Failed to send query: {e}")));
+
+        if let Err(e) = client.disconnect().await {
+            tracing::warn!("Failed to disconnect from Claude: {}", e);
+        }
+        if let Some(audit) = &self.audit {
+            audit.finish();
+        }
+        let violations = guard.map(|guard| guard.violations()).unwrap_or_default();
+        result.map(|(messages, received, limit)| Exchange {
+            messages,
+            received,
+            violations,
+            limit,
+        })
+    }
+
+    /// Record a turn in the feature state when an assistant message starts
+    /// one.
+    ///
+    /// A turn may be streamed as several messages of the same API message,
+    /// tracked by `turn`. The turn's step is its last tool call.
+    fn track_turn(&self, message: &Message, turn: &mut Option<String>) {
+        let (Some(state), Message::Assistant(msg)) = (&self.state, message) else {
+            return;
+        };
+        let step = msg
+            .message
+            .content
+            .iter()
+            .rev()
+            .find_map(|block| match block {
+                ContentBlock::ToolUse(tool) => Some(format!(
+                    "{} {}",
+                    tool.name,
+                    events::tool_summary(&tool.name, &tool.input)
+                )),
+                _ => None,
+            });
+        let new_turn = msg.message.id.is_none() || msg.message.id != *turn;
+        if new_turn {
+            turn.clone_from(&msg.message.id);
+            state.record_turn(msg.session_id.as_deref(), step.as_deref());
+        } else if let Some(step) = step {
+            state.record_step(&step);
+        }
+    }
+
+    /// Start recording a task's session, if a sessions directory is set.
+    fn session_recorder(&self, prompt: &str) -> SessionRecorder {
+        SessionRecorder {
+            dir: self.sessions.clone(),
+            model: self.config.model.clone(),
+            prompt: prompt.to_string(),
+            session: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Estimate the cost of a task from its token usage.
+    ///
+    /// Unknown models are estimated at no cost.
+    fn estimate_cost(&self, usage: &Usage) -> f64 {
+        self.models
+            .estimate_cost(
+                &self.config.model,
+                u64::from(usage.input_tokens),
+                u64::from(usage.output_tokens),
+            )
+            .unwrap_or_default()
+    }
+
+    /// Build the hooks for the agent's tool use, if any are configured.
+    ///
+    /// The audit hooks run first, so calls denied by the sandbox or the path
+    /// guard are audited too.
+    fn hooks(
+        &self,
+        tools: &ToolPolicy,
+        guard: Option<&PathGuard>,
+    ) -> Option<HashMap<HookEvent, Vec<HookMatcher>>> {
+        let mut hooks: HashMap<HookEvent, Vec<HookMatcher>> = HashMap::new();
+        if let Some(audit) = &self.audit {
+            hooks = audit.hooks();
+        }
+        if let Some(events) = &self.events {
+            for (event, matchers) in events.hooks() {
+                hooks.entry(event).or_default().extend(matchers);
+            }
+        }
+        if self.config.sandbox.enabled {
+            let mut sandbox = self.config.sandbox.clone();
+            if let Some(dir) = &self.scratch_dir {
+                sandbox.allowed_paths.push(dir.display().to_string());
+            }
+            let policy = SandboxPolicy::new(sandbox, &self.working_dir);
+            for (event, matchers) in policy.into_hooks() {
+                hooks.entry(event).or_default().extend(matchers);
+            }
+        }
+        if tools.is_restricted() {
+            for (event, matchers) in tools.hooks() {
+                hooks.entry(event).or_default().extend(matchers);
+            }
+        }
+        if let Some(guard) = guard {
+            for (event, matchers) in guard.hooks() {
+                hooks.entry(event).or_default().extend(matchers);
+            }
+        }
+        (!hooks.is_empty()).then_some(hooks)
+    }
+
+    /// Write the transcript of a task, if a transcript file is set.
+    ///
+    /// Failures are logged, since the task itself has completed.
+    async fn record_transcript(&self, prompt: &str, messages: &[Message]) {
+        let Some(path) = &self.transcript_path else {
+            return;
+        };
+
+        let mut transcript = Transcript::new(self.config.model.clone(), prompt);
+        for message in messages {
+            transcript.push_message(message);
+        }
+        let path = path.clone();
+        match tokio::task::spawn_blocking(move || transcript.save(&path)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to write transcript: {}", e),
+            Err(e) => tracing::warn!("Transcript task failed: {}", e),
+        }
+    }
+
+    /// Build the full prompt with context, laid out by the agent's layout
+    /// renderer if it has one, and the instructions of the agent's output
+    /// constraints.
+    fn build_prompt(&self, prompt: &str, context: &TaskContext) -> Result<String> {
+        let prompt = constraints::with_instructions(prompt, &self.constraints);
+        let mut layout = PromptLayout::new(&prompt, context).with_line_numbers(self.line_numbers);
+        if let Some(dir) = &self.scratch_dir {
+            layout = layout.with_scratch_dir(dir);
+        }
+        match &self.layout {
+            Some(renderer) => renderer.render(&layout),
+            None => Ok(layout.render()),
+        }
+    }
+
+    /// Resolve the generation settings of a task from the agent's
+    /// configuration and limits.
+    ///
+    /// The temperature is not applied: the Claude Code CLI the SDK runs
+    /// takes none, so the model samples at its default.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_turns` - Turns of the task, overriding the agent's limit.
+    /// * `max_tokens` - Output tokens of the task, overriding the agent's.
+    fn generation(&self, max_turns: Option<u32>, max_tokens: Option<u32>) -> GenerationConfig {
+        tracing::debug!(
+            "Temperature {} is not applied by the Claude Code CLI",
+            self.config.temperature
+        );
+        GenerationConfig {
+            model: self.config.model.clone(),
+            max_tokens: max_tokens.unwrap_or(self.config.max_tokens),
+            max_turns: max_turns.or_else(|| self.limits.as_ref().map(|limits| limits.max_turns)),
+            max_budget_usd: self.limits.as_ref().map(|limits| limits.max_cost_usd),
+            temperature: None,
+        }
+    }
+
+    /// Get the tools a task may use: its own tools, or else the template's,
+    /// combined with the project's tool lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `task_tools` - Tools of the task; empty keeps the template's.
+    fn tool_policy(&self, task_tools: &[String]) -> ToolPolicy {
+        let tools = if task_tools.is_empty() {
+            &self.allowed_tools
+        } else {
+            task_tools
+        };
+        ToolPolicy::new(tools, &self.config)
+    }
+
+    /// Get the configured MCP servers, started by the agent for each task.
+    fn mcp_servers(&self) -> McpServers {
+        if self.config.mcp_servers.is_empty() {
+            return McpServers::Empty;
+        }
+        let servers = self
+            .config
+            .mcp_servers
+            .iter()
+            .map(|(name, server)| {
+                let config = McpStdioServerConfig {
+                    command: server.command.clone(),
+                    args: (!server.args.is_empty()).then(|| server.args.clone()),
+                    env: (!server.env.is_empty()).then(|| server.env.clone().into_iter().collect()),
+                };
+                (name.clone(), McpServerConfig::Stdio(config))
+            })
+            .collect();
+        McpServers::Dict(servers)
+    }
+
+    /// Build the Claude Agent Options of a task, each query spawning its
+    /// own client with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `system_prompt` - System prompt of the task.
+    /// * `task_tools` - Tools of the task; empty keeps the template's.
+    /// * `max_turns` - Turns of the task, overriding the agent's limit.
+    /// * `max_tokens` - Output tokens of the task, overriding the agent's.
+    /// * `permission_mode` - Permission mode of the task, overriding the
+    ///   agent's.
+    fn task_options(
+        &self,
+        system_prompt: &str,
+        task_tools: &[String],
+        max_turns: Option<u32>,
+        max_tokens: Option<u32>,
+        permission_mode: Option<crate::config::PermissionMode>,
+    ) -> TaskOptions {
+        let generation = self.generation(max_turns, max_tokens);
+        let tools = self.tool_policy(task_tools);
+        let system_prompt: SystemPrompt = system_prompt.to_string().into();
+        let permission_mode = permission_mode.unwrap_or(self.config.permission_mode);
 
         let options = ClaudeAgentOptions::builder()
-            .model(config.model.clone())
+            .model(generation.model.clone())
             .system_prompt(system_prompt)
-            .permission_mode(PermissionMode::BypassPermissions)
+            .permission_mode(sdk_permission_mode(permission_mode))
             .setting_sources(vec![SettingSource::User, SettingSource::Project])
+            .cwd(self.working_dir.clone())
+            .allowed_tools(tools.allowed().unwrap_or_default().to_vec())
+            .disallowed_tools(tools.denied().to_vec())
+            .mcp_servers(self.mcp_servers())
             .build();
 
-        Ok(options)
+        TaskOptions {
+            options: ClaudeAgentOptions {
+                resume: self.resume.clone(),
+                env: output_env(generation.max_tokens),
+                max_turns: generation.max_turns,
+                max_budget_usd: generation.max_budget_usd,
+                ..options
+            },
+            tools,
+            generation,
+            permission_mode,
+        }
+    }
+}
+
+/// Get the SDK's permission mode of a configured one.
+const fn sdk_permission_mode(mode: crate::config::PermissionMode) -> PermissionMode {
+    match mode {
+        crate::config::PermissionMode::Default => PermissionMode::Default,
+        crate::config::PermissionMode::AcceptEdits => PermissionMode::AcceptEdits,
+        crate::config::PermissionMode::Plan => PermissionMode::Plan,
+        crate::config::PermissionMode::BypassPermissions => PermissionMode::BypassPermissions,
+    }
+}
+
+/// What executing a task would send to the agent, see [`Agent::preview`].
+#[derive(Debug, Clone)]
+pub struct TaskPreview {
+    /// Full prompt, with the task's context.
+    pub prompt: String,
+
+    /// System prompt.
+    pub system_prompt: String,
+
+    /// Tools the task may use.
+    pub tools: ToolPolicy,
+
+    /// Generation settings.
+    pub generation: GenerationConfig,
+
+    /// Permission mode of the agent.
+    pub permission_mode: crate::config::PermissionMode,
+
+    /// Estimated input tokens of the system prompt and prompt.
+    pub estimated_tokens: usize,
+
+    /// Size of the context's file contents in bytes.
+    pub context_bytes: usize,
+}
+
+/// Options of a task's query, with the settings they were built from.
+struct TaskOptions {
+    /// Options of the client.
+    options: ClaudeAgentOptions,
+    /// Tools the task may use, enforced by hooks too.
+    tools: ToolPolicy,
+    /// Generation settings of the task.
+    generation: GenerationConfig,
+    /// Permission mode of the task.
+    permission_mode: crate::config::PermissionMode,
+}
+
+/// Combine a response with the fixed response of its repair: the fixed
+/// content, with the tool calls, turns, usage and violations of both.
+fn merge_attempts(first: Response, mut repaired: Response) -> Response {
+    let mut tool_calls = first.tool_calls;
+    tool_calls.append(&mut repaired.tool_calls);
+    let mut violations = first.violations;
+    violations.append(&mut repaired.violations);
+    Response {
+        tool_calls,
+        violations,
+        turns: first.turns + repaired.turns,
+        usage: Usage {
+            input_tokens: first.usage.input_tokens + repaired.usage.input_tokens,
+            output_tokens: first.usage.output_tokens + repaired.usage.output_tokens,
+            total_cost_usd: first.usage.total_cost_usd + repaired.usage.total_cost_usd,
+        },
+        session_id: repaired.session_id.or(first.session_id),
+        ..repaired
+    }
+}
+
+/// Get the environment capping the output tokens of each response.
+fn output_env(max_tokens: u32) -> HashMap<String, String> {
+    HashMap::from([(MAX_OUTPUT_TOKENS_ENV.to_string(), max_tokens.to_string())])
+}
+
+/// Messages of a query, with what the agent observed while they streamed in.
+struct Exchange {
+    /// Messages received.
+    messages: Vec<Message>,
+    /// When each message was received, if it streamed in; empty when all
+    /// were received at once.
+    received: Vec<Instant>,
+    /// Tool calls denied for modifying protected paths.
+    violations: Vec<Violation>,
+    /// Limit the task was interrupted on.
+    limit: Option<Limit>,
+}
+
+/// Records the messages of a task in its session, started once the SDK
+/// reports the session's id.
+///
+/// Failures are logged and stop the recording, since the task itself can
+/// go on.
+struct SessionRecorder {
+    /// Directory of the sessions; `None` records nothing.
+    dir: Option<PathBuf>,
+    /// Model used for the task.
+    model: String,
+    /// Full prompt of the task.
+    prompt: String,
+    /// Session recorded to.
+    session: Option<Session>,
+    /// Messages received before the session's id.
+    pending: Vec<Message>,
+}
+
+impl SessionRecorder {
+    /// Record the messages of a resumed session.
+    fn resumed(session: Session) -> Self {
+        Self {
+            dir: session.path().parent().map(Path::to_path_buf),
+            model: String::new(),
+            prompt: String::new(),
+            session: Some(session),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Record a message.
+    fn record(&mut self, message: &Message) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        if self.session.is_none() {
+            let Some(id) = session::message_session_id(message) else {
+                self.pending.push(message.clone());
+                return;
+            };
+            match Session::create(dir, id, &self.model, &self.prompt) {
+                Ok(session) => self.session = Some(session),
+                Err(e) => {
+                    tracing::warn!("Failed to record session {}: {}", id, e);
+                    self.dir = None;
+                    return;
+                }
+            }
+        }
+
+        let Some(session) = &self.session else {
+            return;
+        };
+        let result = std::mem::take(&mut self.pending)
+            .iter()
+            .chain([message])
+            .try_for_each(|message| session.record(message));
+        if let Err(e) = result {
+            tracing::warn!("Failed to record session {}: {}", session.id(), e);
+            self.dir = None;
+        }
+    }
+}
+
+/// Sends the output of tasks in chunks, tracking what each task spent for
+/// the final chunk of a failed task.
+struct ChunkStream {
+    /// Sender of the chunks.
+    tx: mpsc::UnboundedSender<Chunk>,
+    /// Spend of the running task.
+    spend: Mutex<Spend>,
+}
+
+impl ChunkStream {
+    /// Create a stream sending chunks to a channel.
+    fn new(tx: mpsc::UnboundedSender<Chunk>) -> Self {
+        Self {
+            tx,
+            spend: Mutex::default(),
+        }
+    }
+
+    /// Start streaming a task.
+    fn start(&self) {
+        *self.spend.lock().unwrap_or_else(|e| e.into_inner()) = Spend::default();
+    }
+
+    /// Send the chunks of a message, followed by the usage spent so far if
+    /// the message started a turn.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - Message of the agent.
+    /// * `usage` - Usage of the task's spend.
+    fn send(&self, message: &Message, usage: impl FnOnce(&Spend) -> Usage) {
+        let mut spend = self.spend.lock().unwrap_or_else(|e| e.into_inner());
+        let turns = spend.turns;
+        spend.observe(message);
+        for chunk in stream::message_chunks(message) {
+            // Send errors only mean the consumer is gone
+            let _ = self.tx.send(chunk);
+        }
+        if spend.turns > turns {
+            let _ = self.tx.send(Chunk::Usage(usage(&spend)));
+        }
+    }
+
+    /// Send the final chunks of a task.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - Result of the task.
+    /// * `usage` - Usage of the task's spend, for failures without a
+    ///   partial response.
+    fn finish(&self, result: &Result<Response>, usage: impl FnOnce(&Spend) -> Usage) {
+        let spent = usage(&self.spend.lock().unwrap_or_else(|e| e.into_inner()));
+        for chunk in stream::final_chunks(result, spent) {
+            let _ = self.tx.send(chunk);
+        }
+    }
+}
+
+/// Stream the chunks a task sends to a channel, running the task while the
+/// stream is polled.
+///
+/// The stream ends once the task has finished, dropping its sender, and its
+/// chunks are drained.
+fn drive<'a>(
+    rx: mpsc::UnboundedReceiver<Chunk>,
+    run: impl Future<Output = ()> + Send + 'a,
+) -> impl Stream<Item = Chunk> + Send + 'a {
+    let chunks = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let run = run
+        .into_stream()
+        .filter_map(|()| std::future::ready(None::<Chunk>));
+    futures::stream::select(chunks, run)
+}
+
+/// Turns and token usage of a running task, tracked from its assistant
+/// messages.
+#[derive(Debug, Default)]
+struct Spend {
+    /// Turns taken.
+    turns: u32,
+    /// Input tokens of the turns.
+    input_tokens: u64,
+    /// Output tokens of the turns.
+    output_tokens: u64,
+    /// API message of the current turn.
+    turn: Option<String>,
+}
+
+impl Spend {
+    /// Track an assistant message.
+    ///
+    /// A turn may be streamed as several messages of the same API message,
+    /// each with the usage of the whole turn: only the first is counted.
+    fn observe(&mut self, message: &Message) {
+        let Message::Assistant(msg) = message else {
+            return;
+        };
+        if msg.message.id.is_some() && msg.message.id == self.turn {
+            return;
+        }
+        self.turn.clone_from(&msg.message.id);
+        self.turns += 1;
+        if let Some(usage) = &msg.message.usage {
+            let tokens = |key| usage.get(key).and_then(|v| v.as_u64()).unwrap_or_default();
+            self.input_tokens += tokens("input_tokens");
+            self.output_tokens += tokens("output_tokens");
+        }
+    }
+}
+
+/// A limit a task stopped on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Limit {
+    /// The agent used its maximum number of turns.
+    Turns {
+        /// Turns taken by the agent.
+        taken: u32,
+        /// Maximum number of turns.
+        max: u32,
+    },
+
+    /// The task's cost reached its budget.
+    Cost {
+        /// Cost of the task in USD.
+        spent: f64,
+        /// Budget of the task in USD.
+        max: f64,
+    },
+}
+
+impl Limit {
+    /// Get the error of a task stopped on this limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `partial` - What the task produced until it stopped.
+    fn into_error(self, partial: Response) -> CoreError {
+        let partial = Box::new(partial);
+        match self {
+            Self::Turns { taken, .. } => CoreError::TurnLimitReached {
+                turns: taken,
+                partial,
+            },
+            Self::Cost { spent, max } => CoreError::BudgetExceeded {
+                spent,
+                limit: max,
+                partial,
+            },
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context_budget::{self, ContextStage};
+    use crate::session::SessionEntry;
     use crate::task::Context;
 
     #[test]
@@ -457,10 +1825,60 @@ mod tests {
             metadata: Default::default(),
         };
 
-        let prompt = agent.build_prompt("Hello", &context);
+        let prompt = agent.build_prompt("Hello", &context).unwrap();
         assert!(prompt.contains("Hello"));
         assert!(prompt.contains("/repo"));
         assert!(prompt.contains("main"));
+        assert!(!prompt.contains("## Scratch Directory"));
+
+        let agent = agent.with_scratch_dir("/repo/.gba/tmp/run-1");
+        let prompt = agent.build_prompt("Hello", &context).unwrap();
+        assert!(prompt.contains("## Scratch Directory"));
+        assert!(prompt.contains("to /repo/.gba/tmp/run-1 instead of the repository"));
+    }
+
+    #[test]
+    fn test_build_prompt_with_degraded_context() {
+        let agent = Agent::new(AgentConfig::default());
+        let mut context = Context {
+            repository_path: PathBuf::from("/repo"),
+            branch: "main".to_string(),
+            files: vec![crate::task::File {
+                path: PathBuf::from("src/lib.rs"),
+                content: "pub fn run() {}\n".repeat(1_000),
+                language: "rust".to_string(),
+            }],
+            metadata: Default::default(),
+        };
+        context_budget::fit_context(&mut context, 0, 10, &ContextStage::ALL);
+
+        let prompt = agent.build_prompt("Hello", &context).unwrap();
+        assert!(prompt.contains("## Repository Overview\n\nFile contents were left out"));
+        assert!(prompt.contains("```\nsrc/\n  lib.rs (rust, 1000 lines)\n```"));
+        assert!(!prompt.contains("repositoryOverview"));
+        assert!(prompt.contains("contextStage: \"tree\""));
+    }
+
+    #[test]
+    fn test_build_prompt_with_layout_renderer() {
+        #[derive(Debug)]
+        struct TaskOnly;
+
+        impl LayoutRenderer for TaskOnly {
+            fn render(&self, layout: &PromptLayout) -> Result<String> {
+                Ok(format!("{} on {}", layout.task, layout.branch))
+            }
+        }
+
+        let agent = Agent::new(AgentConfig::default()).with_layout_renderer(Arc::new(TaskOnly));
+        let context = Context {
+            branch: "main".to_string(),
+            ..Context::default()
+        };
+        assert_eq!(
+            agent.build_prompt("Hello", &context).unwrap(),
+            "Hello on main"
+        );
     }
 
     #[test]
@@ -471,4 +1889,547 @@ mod tests {
         assert!(!agent.working_dir().as_os_str().is_empty());
         assert_eq!(agent.config().model, "claude-sonnet-4-20250514");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generation_applied_to_options() {
+        let agent = Agent::new(AgentConfig {
+            max_tokens: 8192,
+            temperature: 0.2,
+            ..AgentConfig::default()
+        })
+        .with_limits(LimitsConfig {
+            max_turns: 40,
+            max_cost_usd: 5.0,
+        });
+
+        let TaskOptions {
+            options,
+            generation,
+            ..
+        } = agent.task_options(DEFAULT_SYSTEM_PROMPT, &[], None, None, None);
+        assert_eq!(generation.max_tokens, 8192);
+        assert_eq!(generation.max_turns, Some(40));
+        assert_eq!(generation.temperature, None);
+        assert_eq!(
+            options.env.get(MAX_OUTPUT_TOKENS_ENV).map(String::as_str),
+            Some("8192")
+        );
+        assert_eq!(options.max_turns, Some(40));
+        assert_eq!(options.max_budget_usd, Some(5.0));
+
+        // A task's settings override the agent's
+        let generation = agent.generation(Some(10), Some(1024));
+        assert_eq!(generation.max_turns, Some(10));
+        assert_eq!(generation.max_tokens, 1024);
+        assert_eq!(
+            output_env(generation.max_tokens)[MAX_OUTPUT_TOKENS_ENV],
+            "1024"
+        );
+    }
+
+    #[test]
+    fn test_task_options_apply_task_settings() {
+        let agent = Agent::new(AgentConfig {
+            denied_tools: vec!["Bash".to_string()],
+            ..AgentConfig::default()
+        })
+        .with_allowed_tools(vec!["Read".to_string(), "Write".to_string()]);
+        let task =
+            Task::with_defaults("Review the login page", TaskContext::default()).with_tools(vec![
+                "Read".to_string(),
+                "Grep".to_string(),
+                "Bash".to_string(),
+            ]);
+
+        let TaskOptions { options, tools, .. } = agent.task_options(
+            &task.system_prompt,
+            &task.tools,
+            Some(task.max_turns),
+            task.max_tokens,
+            task.permission_mode,
+        );
+        assert!(matches!(
+            &options.system_prompt,
+            Some(SystemPrompt::Text(text)) if *text == task.system_prompt
+        ));
+        assert_eq!(options.allowed_tools, vec!["Read", "Grep"]);
+        assert_eq!(options.disallowed_tools, vec!["Bash"]);
+        assert_eq!(options.max_turns, Some(task.max_turns));
+        assert!(!tools.is_allowed("Write"));
+
+        // Without tools of its own, a task keeps the template's
+        let TaskOptions { options, .. } =
+            agent.task_options(DEFAULT_SYSTEM_PROMPT, &[], None, None, None);
+        assert_eq!(options.allowed_tools, vec!["Read", "Write"]);
+    }
+
+    #[test]
+    fn test_permission_mode_applied_to_options() {
+        let TaskOptions { options, .. } = Agent::new(AgentConfig::default()).task_options(
+            DEFAULT_SYSTEM_PROMPT,
+            &[],
+            None,
+            None,
+            None,
+        );
+        assert!(matches!(
+            options.permission_mode,
+            Some(PermissionMode::BypassPermissions)
+        ));
+
+        let agent = Agent::new(AgentConfig {
+            permission_mode: crate::config::PermissionMode::AcceptEdits,
+            ..AgentConfig::default()
+        });
+        let TaskOptions { options, .. } =
+            agent.task_options(DEFAULT_SYSTEM_PROMPT, &[], None, None, None);
+        assert!(matches!(
+            options.permission_mode,
+            Some(PermissionMode::AcceptEdits)
+        ));
+
+        // A task's mode overrides the agent's
+        let task = Task::with_defaults("Plan the login page", TaskContext::default())
+            .with_permission_mode(crate::config::PermissionMode::Plan);
+        assert_eq!(
+            agent.preview(&task).unwrap().permission_mode,
+            crate::config::PermissionMode::Plan
+        );
+    }
+
+    #[test]
+    fn test_mcp_servers_applied_to_options() {
+        let TaskOptions { options, .. } = Agent::new(AgentConfig::default()).task_options(
+            DEFAULT_SYSTEM_PROMPT,
+            &[],
+            None,
+            None,
+            None,
+        );
+        assert!(matches!(options.mcp_servers, McpServers::Empty));
+
+        let server = crate::config::McpServer {
+            command: "npx".to_string(),
+            args: vec!["-y".to_string(), "@acme/issues-mcp".to_string()],
+            env: [("ISSUES_TOKEN".to_string(), "secret".to_string())].into(),
+        };
+        let agent = Agent::new(AgentConfig {
+            mcp_servers: [("issues".to_string(), server)].into(),
+            ..AgentConfig::default()
+        });
+        let TaskOptions { options, .. } =
+            agent.task_options(DEFAULT_SYSTEM_PROMPT, &[], None, None, None);
+        let McpServers::Dict(servers) = options.mcp_servers else {
+            panic!("expected the configured servers");
+        };
+        let Some(McpServerConfig::Stdio(issues)) = servers.get("issues") else {
+            panic!("expected a stdio server");
+        };
+        assert_eq!(issues.command, "npx");
+        assert_eq!(
+            issues.args.as_deref(),
+            Some(&["-y".to_string(), "@acme/issues-mcp".to_string()][..])
+        );
+        assert_eq!(
+            issues
+                .env
+                .as_ref()
+                .and_then(|env| env.get("ISSUES_TOKEN"))
+                .map(String::as_str),
+            Some("secret")
+        );
+    }
+
+    #[test]
+    fn test_preview() {
+        let task = Task::with_defaults(
+            "Add a login page",
+            Context {
+                files: vec![crate::task::File {
+                    path: PathBuf::from("src/lib.rs"),
+                    content: "pub fn login() {}".to_string(),
+                    language: "rust".to_string(),
+                }],
+                ..Context::default()
+            },
+        )
+        .with_max_tokens(2048);
+        let agent = Agent::new(AgentConfig::default());
+
+        let preview = agent.preview(&task).unwrap();
+        assert_eq!(
+            preview.prompt,
+            agent.build_prompt(&task.prompt, &task.context).unwrap()
+        );
+        assert_eq!(preview.system_prompt, task.system_prompt);
+        assert_eq!(preview.generation.max_turns, Some(task.max_turns));
+        assert_eq!(preview.generation.max_tokens, 2048);
+        assert_eq!(preview.context_bytes, 17);
+        assert!(preview.estimated_tokens > estimate_tokens(&preview.prompt));
+    }
+
+    #[test]
+    fn test_constraints_instruct_and_merge_repair() {
+        let agent = Agent::new(AgentConfig::default())
+            .with_post_processing(PostProcessPipeline::new(vec![
+                crate::postprocess::PostProcessor::StripPreamble,
+            ]))
+            .with_constraints(vec![OutputConstraint::RequiredSections {
+                headings: vec!["Phases".to_string()],
+            }]);
+        let task = Task::with_defaults("Plan the feature", Context::default());
+        let preview = agent.preview(&task).unwrap();
+        assert!(preview.prompt.contains("## Output Requirements"));
+        assert!(preview.prompt.contains("Phases"));
+
+        // Constraints are checked on the post-processed content
+        let first = Response {
+            content: "Sure!\n\n# Plan\n\nNo phases yet".to_string(),
+            turns: 2,
+            usage: Usage {
+                input_tokens: 100,
+                output_tokens: 10,
+                total_cost_usd: 0.5,
+            },
+            session_id: Some("s1".to_string()),
+            ..Response::default()
+        };
+        assert_eq!(
+            agent.constraint_violations(&first),
+            ["missing the 'Phases' section"]
+        );
+
+        let repaired = Response {
+            content: "Here it is:\n\n## Phases\n".to_string(),
+            turns: 1,
+            usage: Usage {
+                input_tokens: 50,
+                output_tokens: 5,
+                total_cost_usd: 0.25,
+            },
+            ..Response::default()
+        };
+        let merged = merge_attempts(first, repaired);
+        assert!(agent.constraint_violations(&merged).is_empty());
+        assert_eq!(merged.turns, 3);
+        assert_eq!(merged.usage.input_tokens, 150);
+        assert!((merged.usage.total_cost_usd - 0.75).abs() < f64::EPSILON);
+        assert_eq!(merged.session_id.as_deref(), Some("s1"));
+    }
+
+    #[test]
+    fn test_session_recorder_starts_on_session_id() {
+        let dir = std::env::temp_dir().join(format!("gba-test-recorder-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let agent = Agent::new(AgentConfig::default()).with_sessions_dir(&dir);
+        let message = |value: serde_json::Value| serde_json::from_value::<Message>(value).unwrap();
+
+        let mut recorder = agent.session_recorder("Add a login page");
+        recorder.record(&message(serde_json::json!({
+            "type": "user",
+            "content": [{"type": "text", "text": "Add a login page"}],
+        })));
+        assert!(!dir.exists());
+        recorder.record(&message(serde_json::json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "session-1",
+        })));
+
+        let session = Session::open(&dir, "session-1").unwrap();
+        assert_eq!(session.messages().unwrap().len(), 2);
+        let SessionEntry::Start { prompt, .. } = &session.entries().unwrap()[0] else {
+            panic!("expected a start entry");
+        };
+        assert_eq!(prompt, "Add a login page");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_result_limit() {
+        let result = |subtype: &str| ResultMessage {
+            subtype: subtype.to_string(),
+            duration_ms: 1000,
+            duration_api_ms: 800,
+            is_error: subtype != "success",
+            num_turns: 12,
+            session_id: "session-1".to_string(),
+            total_cost_usd: Some(2.5),
+            usage: None,
+            result: None,
+            structured_output: None,
+        };
+        let agent = Agent::new(AgentConfig::default()).with_limits(LimitsConfig {
+            max_turns: 12,
+            max_cost_usd: 2.0,
+        });
+
+        assert_eq!(agent.result_limit(&result("success"), Some(12)), None);
+        assert_eq!(
+            agent.result_limit(&result("error_max_turns"), Some(12)),
+            Some(Limit::Turns { taken: 12, max: 12 })
+        );
+        assert_eq!(
+            agent.result_limit(&result("error_max_budget_usd"), Some(12)),
+            Some(Limit::Cost {
+                spent: 2.5,
+                max: 2.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_spend_limit_and_partial_response() {
+        let assistant = |id: &str, text: &str| {
+            serde_json::from_value::<Message>(serde_json::json!({
+                "type": "assistant",
+                "message": {
+                    "id": id,
+                    "content": [{"type": "text", "text": text}],
+                    "usage": {"input_tokens": 1_000_000, "output_tokens": 0},
+                },
+            }))
+            .unwrap()
+        };
+        // A turn streamed as two messages counts once
+        let messages = [
+            assistant("msg-1", "Reading "),
+            assistant("msg-1", "the code."),
+            assistant("msg-2", "Editing."),
+        ];
+        let mut spend = Spend::default();
+        for message in &messages {
+            spend.observe(message);
+        }
+        assert_eq!(spend.turns, 2);
+        assert_eq!(spend.input_tokens, 2_000_000);
+
+        let agent = Agent::new(AgentConfig::default());
+        let cost = agent.spend_usage(&spend).total_cost_usd;
+        assert!(cost > 0.0);
+        assert_eq!(agent.spend_limit(&spend, Some(2), Some(cost)), None);
+        assert_eq!(
+            agent.spend_limit(&spend, Some(1), None),
+            Some(Limit::Turns { taken: 2, max: 1 })
+        );
+        assert!(matches!(
+            agent.spend_limit(&spend, None, Some(cost / 2.0)),
+            Some(Limit::Cost { .. })
+        ));
+
+        let response = Response {
+            content: "Reading the code.".to_string(),
+            ..Response::default()
+        };
+        let limit = Limit::Turns { taken: 2, max: 1 };
+        let error = agent
+            .enforce_limit(response, Some(limit), &messages, false)
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            CoreError::TurnLimitReached { turns: 2, .. }
+        ));
+        assert_eq!(error.to_string(), "Turn limit reached after 2 turns");
+        let partial = error.partial_response().unwrap();
+        assert_eq!(partial.content, "Reading the code.");
+        assert_eq!(partial.turns, 2);
+        assert_eq!(partial.usage.input_tokens, 2_000_000);
+        assert!((partial.usage.total_cost_usd - cost).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bounded_timeout_and_cancellation() {
+        let config = AgentConfig {
+            timeout: 5,
+            ..AgentConfig::default()
+        };
+        let pending = std::future::pending::<Result<Response>>;
+
+        let agent = Agent::new(config.clone());
+        assert!(matches!(
+            agent.bounded(pending(), 5).await,
+            Err(CoreError::Timeout { seconds: 5 })
+        ));
+        // A task's timeout overrides the agent's
+        let task = Task::with_defaults("Add a login page", Context::default()).with_timeout(1);
+        assert!(matches!(
+            agent.measured(pending(), task.timeout, None).await,
+            Err(CoreError::Timeout { seconds: 1 })
+        ));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let agent = Agent::new(config).with_cancellation(token);
+        assert!(matches!(
+            agent.bounded(pending(), 5).await,
+            Err(CoreError::Canceled)
+        ));
+    }
+
+    #[test]
+    fn test_collect_response_captures_tool_calls() {
+        let message =
+            |value: serde_json::Value| -> Message { serde_json::from_value(value).unwrap() };
+        let messages = vec![
+            message(serde_json::json!({
+                "type": "assistant",
+                "message": {"content": [
+                    {"type": "text", "text": "Reading the router."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "Read", "input": {"file_path": "src/main.rs"}},
+                    {"type": "tool_use", "id": "toolu_2", "name": "Bash", "input": {"command": "cargo test"}},
+                ]},
+            })),
+            message(serde_json::json!({
+                "type": "user",
+                "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "fn main() {}"},
+                    {"type": "tool_result", "tool_use_id": "toolu_2", "content": [{"type": "text", "text": "1 failed"}], "is_error": true},
+                ],
+            })),
+            message(serde_json::json!({
+                "type": "result",
+                "subtype": "success",
+                "duration_ms": 1000,
+                "duration_api_ms": 800,
+                "is_error": false,
+                "num_turns": 2,
+                "session_id": "session-1",
+            })),
+        ];
+        let start = Instant::now();
+        let exchange = Exchange {
+            messages,
+            received: vec![
+                start,
+                start + Duration::from_millis(250),
+                start + Duration::from_millis(300),
+            ],
+            violations: Vec::new(),
+            limit: None,
+        };
+
+        let agent = Agent::new(AgentConfig::default());
+        let response = agent
+            .collect_response(exchange, agent.generation(None, None))
+            .unwrap();
+        assert_eq!(response.content, "Reading the router.");
+        let [read, bash] = &response.tool_calls[..] else {
+            panic!("expected two tool calls");
+        };
+        assert_eq!(read.name, "Read");
+        assert_eq!(read.arguments["file_path"], "src/main.rs");
+        assert_eq!(read.result.as_deref(), Some("fn main() {}"));
+        assert!(!read.is_error);
+        assert_eq!(read.duration_ms, Some(250));
+        assert_eq!(bash.result.as_deref(), Some("1 failed"));
+        assert!(bash.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_chunks_end_with_done_after_mid_stream_error() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let agent = Agent::new(AgentConfig::default()).with_chunks(tx);
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "type": "assistant",
+            "message": {
+                "id": "msg-1",
+                "content": [{"type": "text", "text": "Reading the router."}],
+                "usage": {"input_tokens": 1000, "output_tokens": 200},
+            },
+        }))
+        .unwrap();
+
+        let result = agent
+            .measured(
+                async {
+                    agent
+                        .chunks
+                        .as_ref()
+                        .unwrap()
+                        .send(&message, |spend| agent.spend_usage(spend));
+                    Err(CoreError::ClaudeAgent("connection reset".to_string()))
+                },
+                None,
+                agent.chunks.as_ref(),
+            )
+            .await;
+        assert!(result.is_err());
+
+        assert_eq!(
+            rx.recv().await,
+            Some(Chunk::Text("Reading the router.".to_string()))
+        );
+        // The turn's usage follows its first message
+        let Some(Chunk::Usage(usage)) = rx.recv().await else {
+            panic!("expected the usage of the turn");
+        };
+        assert_eq!(usage.input_tokens, 1000);
+        assert!(matches!(
+            rx.recv().await,
+            Some(Chunk::Error {
+                retryable: true,
+                ..
+            })
+        ));
+        let Some(Chunk::Done { usage, partial }) = rx.recv().await else {
+            panic!("expected the final chunk");
+        };
+        assert!(partial);
+        assert_eq!(usage.output_tokens, 200);
+        assert!(rx.try_recv().is_err());
+
+        // The next task streams its own spend
+        let result = agent
+            .measured(
+                async { Ok(Response::default()) },
+                None,
+                agent.chunks.as_ref(),
+            )
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(
+            rx.recv().await,
+            Some(Chunk::Done {
+                usage: Usage::default(),
+                partial: false
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drive_streams_chunks_until_task_finishes() {
+        let agent = Agent::new(AgentConfig::default());
+        let (tx, rx) = mpsc::unbounded_channel();
+        let run = async {
+            let chunks = ChunkStream::new(tx);
+            let _ = agent
+                .measured(
+                    async {
+                        let _ = chunks.tx.send(Chunk::Text("Reading".to_string()));
+                        tokio::task::yield_now().await;
+                        let _ = chunks.tx.send(Chunk::Text(" the router.".to_string()));
+                        Ok(Response::default())
+                    },
+                    None,
+                    Some(&chunks),
+                )
+                .await;
+        };
+
+        let chunks: Vec<Chunk> = drive(rx, run).collect().await;
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::Text("Reading".to_string()),
+                Chunk::Text(" the router.".to_string()),
+                Chunk::Done {
+                    usage: Usage::default(),
+                    partial: false
+                },
+            ]
+        );
+        // A stream of its own leaves the agent's sender alone
+        assert!(agent.chunks.is_none());
+    }
+}
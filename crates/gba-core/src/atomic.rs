@@ -0,0 +1,198 @@
+//! Atomic writes of gba-managed files.
+//!
+//! A plain `fs::write` truncates the file before writing it, so a crash or
+//! a full disk in between leaves a half-written state or configuration that
+//! no longer parses. [`write`] writes to a temporary file next to the
+//! target, syncs it and renames it over the target: readers see either the
+//! previous content or the new one, never a mix.
+//!
+//! [`write_with_backup`] also keeps the previous content in a `.bak` file,
+//! see [`backup_path`], to recover from a save that was complete but wrong.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::atomic;
+//! use std::path::Path;
+//!
+//! atomic::write_with_backup(Path::new(".gba/config.yml"), "version: \"1.0\"\n")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::debug;
+
+/// Extension appended to the name of a backup file.
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// Counter keeping the temporary files of concurrent writes apart.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write a file atomically, replacing it if it exists.
+///
+/// The parent directory must exist, as with `fs::write`.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file.
+/// * `contents` - New content.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file cannot be written or renamed; the
+/// target is left untouched then.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp = temp_path(path);
+    if let Err(e) = write_synced(&tmp, contents.as_ref()) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    sync_parent(path);
+    debug!("Wrote {} atomically", path.display());
+    Ok(())
+}
+
+/// Create a file atomically, failing if it exists.
+///
+/// The content is written to a temporary file first and then hard-linked
+/// to the target, so a reader never sees the file empty or half-written.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file.
+/// * `contents` - Content of the file.
+///
+/// # Errors
+///
+/// Returns an error of kind [`io::ErrorKind::AlreadyExists`] if the file
+/// exists, or another error if the temporary file cannot be written or
+/// linked.
+pub fn create_new(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp = temp_path(path);
+    let result =
+        write_synced(&tmp, contents.as_ref()).and_then(|()| std::fs::hard_link(&tmp, path));
+    let _ = std::fs::remove_file(&tmp);
+    result?;
+    sync_parent(path);
+    debug!("Created {} atomically", path.display());
+    Ok(())
+}
+
+/// Write a file atomically, keeping its previous content in a backup file.
+///
+/// The backup is only refreshed when the content changes, so saving the
+/// same content twice keeps the older version.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file.
+/// * `contents` - New content.
+///
+/// # Errors
+///
+/// Returns an error if the backup or the file cannot be written.
+pub fn write_with_backup(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let contents = contents.as_ref();
+    match std::fs::read(path) {
+        Ok(previous) if previous != contents => write(&backup_path(path), previous)?,
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    write(path, contents)
+}
+
+/// Get the path of the backup file of a file, e.g. `state.yml.bak`.
+#[must_use]
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, &format!(".{BACKUP_EXTENSION}"))
+}
+
+/// Get a unique temporary path next to a file, so the rename stays on the
+/// same filesystem.
+fn temp_path(path: &Path) -> PathBuf {
+    let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    with_suffix(path, &format!(".{}.{count}.tmp", std::process::id()))
+}
+
+/// Append a suffix to the file name of a path.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Write a new file and flush it to disk.
+fn write_synced(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Flush the rename to disk; best-effort, as not every platform can open a
+/// directory.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_replaces_file_atomically() {
+        let dir = std::env::temp_dir().join("gba-test-atomic-write");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.yml");
+
+        write(&path, "a: 1\n").unwrap();
+        write(&path, "a: 2\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a: 2\n");
+        assert!(!backup_path(&path).exists());
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(write(&dir.join("missing").join("state.yml"), "a: 1\n").is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_with_backup_keeps_previous_version() {
+        let dir = std::env::temp_dir().join("gba-test-atomic-backup");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.yml");
+
+        write_with_backup(&path, "v: 1\n").unwrap();
+        assert!(!backup_path(&path).exists());
+        write_with_backup(&path, "v: 2\n").unwrap();
+        write_with_backup(&path, "v: 2\n").unwrap();
+        assert_eq!(backup_path(&path), dir.join("config.yml.bak"));
+        assert_eq!(
+            std::fs::read_to_string(backup_path(&path)).unwrap(),
+            "v: 1\n"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v: 2\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
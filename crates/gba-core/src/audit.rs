@@ -0,0 +1,400 @@
+//! Audit log of the agent's tool calls.
+//!
+//! Every tool call is recorded as one JSON line in the feature's
+//! `audit.jsonl`: the tool, its arguments, exit status, duration and the
+//! files it touched. Entries are written from the tool-use hooks and only
+//! ever appended, so the log shows what automation did to the codebase.
+//!
+//! Calls that start but never finish, because a hook denied them or the tool
+//! failed, are recorded as [`AuditStatus::Incomplete`] when the query ends.
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use claude_agent_sdk_rs::{
+    HookCallback, HookEvent, HookInput, HookJsonOutput, HookMatcher, SyncHookJsonOutput,
+};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::sandbox::PATH_FIELDS;
+
+/// Result type alias for audit operations.
+pub type Result<T> = std::result::Result<T, AuditError>;
+
+/// Error types for audit operations.
+#[derive(Debug, Error)]
+pub enum AuditError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// An audit line could not be serialized or parsed.
+    #[error("Invalid audit entry at line {line}: {source}")]
+    Entry {
+        /// Line number, starting at 1 (0 when serializing).
+        line: usize,
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+}
+
+/// Outcome of a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditStatus {
+    /// The tool completed successfully.
+    Success,
+    /// The tool completed with an error or a non-zero exit code.
+    Error,
+    /// The tool never completed: it was denied by a hook or failed.
+    Incomplete,
+}
+
+/// A single tool call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// When the tool call started.
+    pub timestamp: DateTime<Utc>,
+
+    /// Run identifier, e.g. `"20260224T103000Z-1a2b"`.
+    #[serde(default)]
+    pub run_id: Option<String>,
+
+    /// Tool use identifier.
+    #[serde(default)]
+    pub tool_use_id: Option<String>,
+
+    /// Tool name, e.g. `"Bash"`.
+    pub tool: String,
+
+    /// Tool arguments.
+    pub input: Value,
+
+    /// Outcome of the call.
+    pub status: AuditStatus,
+
+    /// Exit code reported by the tool, if any.
+    #[serde(default)]
+    pub exit_code: Option<i64>,
+
+    /// Duration of the call in milliseconds, if it completed.
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+
+    /// Files the call read or modified, as given in its arguments.
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// A tool call that has started but not completed yet.
+#[derive(Debug)]
+struct PendingCall {
+    started: Instant,
+    timestamp: DateTime<Utc>,
+    tool: String,
+    input: Value,
+}
+
+/// Append-only audit log file.
+///
+/// Clones share the calls in flight, so the hooks built by
+/// [`AuditLog::hooks`] and the log they came from see the same calls.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba_core::audit::AuditLog;
+///
+/// let audit = AuditLog::new(".gba/features/0003/audit.jsonl");
+/// for entry in audit.entries()? {
+///     println!("{} {} {:?}", entry.timestamp, entry.tool, entry.status);
+/// }
+/// # Ok::<(), gba_core::audit::AuditError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    /// Path of the audit file.
+    path: PathBuf,
+    /// Run the calls belong to.
+    run_id: Option<String>,
+    /// Calls in flight, keyed by tool use identifier.
+    pending: Arc<DashMap<Option<String>, PendingCall>>,
+}
+
+impl AuditLog {
+    /// Create an audit log backed by the given file.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            run_id: None,
+            pending: Arc::default(),
+        }
+    }
+
+    /// Set the run identifier recorded with each entry.
+    #[must_use]
+    pub fn with_run_id(mut self, run_id: Option<String>) -> Self {
+        self.run_id = run_id;
+        self
+    }
+
+    /// Get the path of the audit file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry, creating the file if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be written.
+    pub fn append(&self, entry: &AuditEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut line =
+            serde_json::to_string(entry).map_err(|source| AuditError::Entry { line: 0, source })?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Read all entries, oldest first.
+    ///
+    /// A missing audit file has no entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or a line is invalid.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| AuditError::Entry {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// Record the start of a tool call.
+    pub fn start(&self, tool_use_id: Option<String>, tool: &str, input: &Value) {
+        let call = PendingCall {
+            started: Instant::now(),
+            timestamp: Utc::now(),
+            tool: tool.to_string(),
+            input: input.clone(),
+        };
+        self.pending.insert(tool_use_id, call);
+    }
+
+    /// Record the completion of a tool call.
+    ///
+    /// Calls that were never started are recorded without a duration.
+    pub fn complete(
+        &self,
+        tool_use_id: Option<String>,
+        tool: &str,
+        input: &Value,
+        response: &Value,
+    ) {
+        let call = self.pending.remove(&tool_use_id).map(|(_, call)| call);
+        let exit_code = exit_code(response);
+        let failed = exit_code.is_some_and(|code| code != 0)
+            || ["is_error", "isError", "interrupted"]
+                .iter()
+                .any(|key| response.get(*key).and_then(Value::as_bool) == Some(true));
+
+        let entry = AuditEntry {
+            timestamp: call.as_ref().map_or_else(Utc::now, |call| call.timestamp),
+            run_id: self.run_id.clone(),
+            tool_use_id,
+            tool: tool.to_string(),
+            input: input.clone(),
+            status: if failed {
+                AuditStatus::Error
+            } else {
+                AuditStatus::Success
+            },
+            exit_code,
+            duration_ms: call
+                .map(|call| u64::try_from(call.started.elapsed().as_millis()).unwrap_or(u64::MAX)),
+            files: files_touched(input),
+        };
+        self.write(&entry);
+    }
+
+    /// Record all calls still in flight as incomplete.
+    ///
+    /// Called when a query ends: calls that were denied or failed never
+    /// complete.
+    pub fn finish(&self) {
+        let keys = self
+            .pending
+            .iter()
+            .map(|call| call.key().clone())
+            .collect::<Vec<_>>();
+        let mut calls = keys
+            .into_iter()
+            .filter_map(|key| self.pending.remove(&key))
+            .collect::<Vec<_>>();
+        calls.sort_by_key(|(_, call)| call.started);
+
+        for (tool_use_id, call) in calls {
+            let entry = AuditEntry {
+                timestamp: call.timestamp,
+                run_id: self.run_id.clone(),
+                tool_use_id,
+                files: files_touched(&call.input),
+                tool: call.tool,
+                input: call.input,
+                status: AuditStatus::Incomplete,
+                exit_code: None,
+                duration_ms: None,
+            };
+            self.write(&entry);
+        }
+    }
+
+    /// Build the hooks that record every tool call.
+    #[must_use]
+    pub fn hooks(&self) -> HashMap<HookEvent, Vec<HookMatcher>> {
+        let audit = self.clone();
+        let callback: HookCallback = Arc::new(move |input, tool_use_id, _context| {
+            let audit = audit.clone();
+            Box::pin(async move {
+                match input {
+                    HookInput::PreToolUse(input) => {
+                        audit.start(tool_use_id, &input.tool_name, &input.tool_input);
+                    }
+                    HookInput::PostToolUse(input) => audit.complete(
+                        tool_use_id,
+                        &input.tool_name,
+                        &input.tool_input,
+                        &input.tool_response,
+                    ),
+                    _ => {}
+                }
+                HookJsonOutput::Sync(SyncHookJsonOutput::default())
+            })
+        });
+
+        [HookEvent::PreToolUse, HookEvent::PostToolUse]
+            .into_iter()
+            .map(|event| {
+                let matcher = HookMatcher::builder().hooks(vec![callback.clone()]).build();
+                (event, vec![matcher])
+            })
+            .collect()
+    }
+
+    /// Append an entry, logging failures so they never interrupt the agent.
+    fn write(&self, entry: &AuditEntry) {
+        if let Err(e) = self.append(entry) {
+            tracing::warn!("Failed to write audit entry: {}", e);
+        }
+    }
+}
+
+/// Get the exit code reported in a tool response.
+fn exit_code(response: &Value) -> Option<i64> {
+    ["exitCode", "exit_code", "returnCode"]
+        .iter()
+        .find_map(|key| response.get(*key).and_then(Value::as_i64))
+}
+
+/// Get the files named in a tool call's arguments.
+fn files_touched(input: &Value) -> Vec<String> {
+    let mut files = PATH_FIELDS
+        .iter()
+        .filter_map(|field| input.get(*field).and_then(Value::as_str))
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    // MultiEdit and similar tools list their edits per file
+    if let Some(edits) = input.get("edits").and_then(Value::as_array) {
+        for path in edits
+            .iter()
+            .filter_map(|edit| edit.get("file_path").and_then(Value::as_str))
+        {
+            if !files.iter().any(|file| file == path) {
+                files.push(path.to_string());
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_audit_records_calls() {
+        let dir = std::env::temp_dir().join(format!("gba-test-audit-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let audit = AuditLog::new(dir.join("audit.jsonl")).with_run_id(Some("run-1".to_string()));
+
+        let edit = json!({ "file_path": "src/lib.rs", "old_string": "a", "new_string": "b" });
+        audit.start(Some("toolu_1".to_string()), "Edit", &edit);
+        audit.complete(Some("toolu_1".to_string()), "Edit", &edit, &json!({}));
+
+        let bash = json!({ "command": "cargo test" });
+        audit.start(Some("toolu_2".to_string()), "Bash", &bash);
+        audit.complete(
+            Some("toolu_2".to_string()),
+            "Bash",
+            &bash,
+            &json!({ "stdout": "", "exitCode": 101 }),
+        );
+
+        audit.start(
+            Some("toolu_3".to_string()),
+            "Bash",
+            &json!({ "command": "rm -rf /" }),
+        );
+        audit.finish();
+
+        let entries = audit.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].status, AuditStatus::Success);
+        assert_eq!(entries[0].files, vec!["src/lib.rs".to_string()]);
+        assert_eq!(entries[0].run_id.as_deref(), Some("run-1"));
+        assert!(entries[0].duration_ms.is_some());
+        assert_eq!(entries[1].status, AuditStatus::Error);
+        assert_eq!(entries[1].exit_code, Some(101));
+        assert_eq!(entries[2].status, AuditStatus::Incomplete);
+        assert_eq!(entries[2].tool_use_id.as_deref(), Some("toolu_3"));
+        assert!(entries[2].duration_ms.is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,332 @@
+//! Crate maps of Cargo workspaces.
+//!
+//! In a multi-crate repository, the agent works better knowing which crates
+//! there are and how they depend on each other. A [`CrateMap`] is read with
+//! `cargo metadata`, so workspace membership globs, exclusions and inherited
+//! settings resolve the way Cargo resolves them, and is added to the
+//! context's metadata under [`CRATES_KEY`].
+//!
+//! The map also scopes a context to the crates a feature touches: see
+//! [`CrateMap::touched`] and
+//! [`ContextBuilderConfig::with_crates`](crate::context_builder::ContextBuilderConfig::with_crates).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::cargo::CrateMap;
+//! use std::path::Path;
+//!
+//! if let Some(map) = CrateMap::load(Path::new("."))? {
+//!     let touched = map.touched(&["crates/gba-core/src/agent.rs".into()]);
+//!     println!("Scoped to {:?}", map.with_dependencies(&touched));
+//! }
+//! # Ok::<(), gba_core::cargo::CargoError>(())
+//! ```
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+/// Context metadata key the crate map is added under.
+pub const CRATES_KEY: &str = "crates";
+
+/// Context metadata key the crates a scoped context holds are added under.
+pub const CRATE_SCOPE_KEY: &str = "crateScope";
+
+/// Result type alias for Cargo operations.
+pub type Result<T> = std::result::Result<T, CargoError>;
+
+/// Error types for Cargo operations.
+#[derive(Debug, Error)]
+pub enum CargoError {
+    /// `cargo metadata` failed, e.g. on an invalid manifest.
+    #[error("cargo metadata failed: {0}")]
+    Metadata(String),
+
+    /// The output of `cargo metadata` could not be parsed.
+    #[error("Invalid cargo metadata: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    /// IO error, e.g. cargo is not installed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A crate of a Cargo workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateNode {
+    /// Package name, e.g. `"gba-core"`.
+    pub name: String,
+
+    /// Directory of the crate relative to the workspace root, e.g.
+    /// `"crates/gba-core"`; empty for a root package.
+    pub path: String,
+
+    /// Workspace crates it depends on, including build dependencies but not
+    /// dev-dependencies, sorted.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+/// Crates of a Cargo workspace and their dependencies on each other.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateMap {
+    /// Crates, sorted by path.
+    pub crates: Vec<CrateNode>,
+}
+
+/// Output of `cargo metadata`, as far as it's read.
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    workspace_root: PathBuf,
+}
+
+/// Package in the output of `cargo metadata`.
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    id: String,
+    manifest_path: PathBuf,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+/// Dependency of a package in the output of `cargo metadata`.
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    name: String,
+    kind: Option<String>,
+}
+
+impl CrateMap {
+    /// Read the crate map of the Cargo project at a directory.
+    ///
+    /// Runs `cargo metadata --no-deps`, which reads the manifests without
+    /// resolving or fetching dependencies.
+    ///
+    /// # Returns
+    ///
+    /// The crate map, or `None` if the directory has no `Cargo.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if cargo cannot be run, fails or its output cannot
+    /// be parsed.
+    #[instrument]
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        if !root.join("Cargo.toml").is_file() {
+            return Ok(None);
+        }
+        let output = Command::new("cargo")
+            .args(["metadata", "--no-deps", "--format-version", "1"])
+            .current_dir(root)
+            .output()?;
+        if !output.status.success() {
+            return Err(CargoError::Metadata(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        let map = Self::parse(&output.stdout)?;
+        debug!("Found {} crates in {}", map.crates.len(), root.display());
+        Ok(Some(map))
+    }
+
+    /// Parse the output of `cargo metadata`.
+    fn parse(json: &[u8]) -> Result<Self> {
+        let metadata: Metadata = serde_json::from_slice(json)?;
+        let members = metadata
+            .packages
+            .iter()
+            .filter(|package| metadata.workspace_members.contains(&package.id))
+            .collect::<Vec<_>>();
+        let names = members
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect::<BTreeSet<_>>();
+
+        let mut crates = members
+            .iter()
+            .map(|package| {
+                let dir = package.manifest_path.parent().unwrap_or(Path::new(""));
+                let path = dir
+                    .strip_prefix(&metadata.workspace_root)
+                    .unwrap_or(dir)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let dependencies = package
+                    .dependencies
+                    .iter()
+                    .filter(|dependency| dependency.kind.as_deref() != Some("dev"))
+                    .filter(|dependency| names.contains(dependency.name.as_str()))
+                    .map(|dependency| dependency.name.clone())
+                    .collect::<BTreeSet<_>>();
+                CrateNode {
+                    name: package.name.clone(),
+                    path,
+                    dependencies: dependencies.into_iter().collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+        crates.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self { crates })
+    }
+
+    /// Get a crate by name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&CrateNode> {
+        self.crates.iter().find(|node| node.name == name)
+    }
+
+    /// Get the crate a path belongs to: the crate with the deepest directory
+    /// containing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path relative to the workspace root.
+    #[must_use]
+    pub fn crate_of(&self, path: &Path) -> Option<&CrateNode> {
+        let path = crate::context_builder::normalize_path(path);
+        self.crates
+            .iter()
+            .filter(|node| {
+                node.path.is_empty()
+                    || path == node.path
+                    || path.starts_with(&format!("{}/", node.path))
+            })
+            .max_by_key(|node| node.path.len())
+    }
+
+    /// Get the names of the crates changed paths belong to, sorted.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Changed paths relative to the workspace root, e.g. the
+    ///   files of a feature's diff.
+    #[must_use]
+    pub fn touched(&self, paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .filter_map(|path| self.crate_of(path))
+            .map(|node| node.name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Add the workspace crates some crates depend on, directly or not.
+    ///
+    /// # Returns
+    ///
+    /// The crates and their dependencies, sorted.
+    #[must_use]
+    pub fn with_dependencies(&self, names: &[String]) -> Vec<String> {
+        let mut scope = BTreeSet::new();
+        let mut pending = names.to_vec();
+        while let Some(name) = pending.pop() {
+            if !scope.insert(name.clone()) {
+                continue;
+            }
+            if let Some(node) = self.get(&name) {
+                pending.extend(node.dependencies.iter().cloned());
+            }
+        }
+        scope.into_iter().collect()
+    }
+
+    /// Get the directories of the crates outside a scope, to leave out of a
+    /// context scoped to it.
+    ///
+    /// A crate nested in a scoped crate's directory is left out too, but the
+    /// scoped crate's own files are kept. A root package is never left out.
+    #[must_use]
+    pub fn excluded_dirs(&self, scope: &[String]) -> Vec<String> {
+        self.crates
+            .iter()
+            .filter(|node| !node.path.is_empty() && !scope.contains(&node.name))
+            .map(|node| format!("{}/", node.path))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> CrateMap {
+        let json = serde_json::json!({
+            "workspace_root": "/repo",
+            "workspace_members": ["app 0.1.0", "core 0.1.0", "pm 0.1.0", "xtask 0.1.0"],
+            "packages": [
+                {
+                    "name": "app",
+                    "id": "app 0.1.0",
+                    "manifest_path": "/repo/apps/app/Cargo.toml",
+                    "dependencies": [
+                        {"name": "core", "kind": null},
+                        {"name": "clap", "kind": null},
+                        {"name": "xtask", "kind": "dev"}
+                    ]
+                },
+                {
+                    "name": "core",
+                    "id": "core 0.1.0",
+                    "manifest_path": "/repo/crates/core/Cargo.toml",
+                    "dependencies": [{"name": "pm", "kind": "build"}]
+                },
+                {
+                    "name": "pm",
+                    "id": "pm 0.1.0",
+                    "manifest_path": "/repo/crates/pm/Cargo.toml",
+                    "dependencies": []
+                },
+                {
+                    "name": "xtask",
+                    "id": "xtask 0.1.0",
+                    "manifest_path": "/repo/Cargo.toml"
+                }
+            ]
+        });
+        CrateMap::parse(json.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_crate_map() {
+        let map = map();
+        let paths = map
+            .crates
+            .iter()
+            .map(|node| node.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["", "apps/app", "crates/core", "crates/pm"]);
+        assert_eq!(map.get("app").unwrap().dependencies, ["core"]);
+        assert_eq!(map.get("core").unwrap().dependencies, ["pm"]);
+    }
+
+    #[test]
+    fn test_scope_to_touched_crates() {
+        let map = map();
+        assert_eq!(
+            map.crate_of(Path::new("crates/core/src/lib.rs"))
+                .unwrap()
+                .name,
+            "core"
+        );
+        assert_eq!(map.crate_of(Path::new("README.md")).unwrap().name, "xtask");
+
+        let touched = map.touched(&[
+            PathBuf::from("crates/core/src/lib.rs"),
+            PathBuf::from("crates/core/Cargo.toml"),
+        ]);
+        assert_eq!(touched, ["core"]);
+        let scope = map.with_dependencies(&touched);
+        assert_eq!(scope, ["core", "pm"]);
+        assert_eq!(map.excluded_dirs(&scope), ["apps/app/"]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Line diffs of two texts, e.g. the outputs of two prompt variants.
+//!
+//! [`diff_lines`] aligns the lines of two texts on their longest common
+//! subsequence, pairing removed and added lines so they can be shown side by
+//! side.
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_core::compare::{DiffLine, diff_lines};
+//!
+//! let lines = diff_lines("# Plan\n1. Add login\n", "# Plan\n1. Add sessions\n2. Add login\n");
+//! assert_eq!(lines[0], DiffLine::Same("# Plan".to_string()));
+//! assert_eq!(
+//!     lines[1],
+//!     DiffLine::Changed("1. Add login".to_string(), "1. Add sessions".to_string())
+//! );
+//! assert_eq!(lines[2], DiffLine::Added("2. Add login".to_string()));
+//! ```
+
+/// A line of a diff between a left and a right text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// A line present in both texts.
+    Same(String),
+    /// A left line replaced by a right line.
+    Changed(String, String),
+    /// A line only in the left text.
+    Removed(String),
+    /// A line only in the right text.
+    Added(String),
+}
+
+impl DiffLine {
+    /// Get the left and right sides of the line, if present.
+    #[must_use]
+    pub fn sides(&self) -> (Option<&str>, Option<&str>) {
+        match self {
+            Self::Same(line) => (Some(line), Some(line)),
+            Self::Changed(left, right) => (Some(left), Some(right)),
+            Self::Removed(left) => (Some(left), None),
+            Self::Added(right) => (None, Some(right)),
+        }
+    }
+
+    /// Check whether the line differs between the texts.
+    #[must_use]
+    pub const fn is_change(&self) -> bool {
+        !matches!(self, Self::Same(_))
+    }
+}
+
+/// Diff two texts line by line.
+///
+/// Runs of removed and added lines between common lines are paired into
+/// [`DiffLine::Changed`] lines, the surplus of the longer run being reported
+/// as removed or added.
+///
+/// # Arguments
+///
+/// * `left` - Left text, e.g. the output of the first prompt.
+/// * `right` - Right text.
+#[must_use]
+pub fn diff_lines(left: &str, right: &str) -> Vec<DiffLine> {
+    let left = left.lines().collect::<Vec<_>>();
+    let right = right.lines().collect::<Vec<_>>();
+
+    // lcs[i][j]: length of the longest common subsequence of left[i..] and right[j..]
+    let mut lcs = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            flush(&mut lines, &mut removed, &mut added);
+            lines.push(DiffLine::Same(left[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < right.len() && (i == left.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(right[j]);
+            j += 1;
+        } else {
+            removed.push(left[i]);
+            i += 1;
+        }
+    }
+    flush(&mut lines, &mut removed, &mut added);
+    lines
+}
+
+/// Pair pending removed and added lines into diff lines.
+fn flush(lines: &mut Vec<DiffLine>, removed: &mut Vec<&str>, added: &mut Vec<&str>) {
+    let mut removed_lines = removed.drain(..);
+    let mut added_lines = added.drain(..);
+    loop {
+        match (removed_lines.next(), added_lines.next()) {
+            (Some(left), Some(right)) => {
+                lines.push(DiffLine::Changed(left.to_string(), right.to_string()));
+            }
+            (Some(left), None) => lines.push(DiffLine::Removed(left.to_string())),
+            (None, Some(right)) => lines.push(DiffLine::Added(right.to_string())),
+            (None, None) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        assert!(diff_lines("", "").is_empty());
+        assert_eq!(
+            diff_lines("a\nb\n", "a\nb"),
+            vec![DiffLine::Same("a".into()), DiffLine::Same("b".into())]
+        );
+
+        let lines = diff_lines("a\nb\nc\nd", "a\nx\nc\ny\nz");
+        assert_eq!(
+            lines,
+            vec![
+                DiffLine::Same("a".into()),
+                DiffLine::Changed("b".into(), "x".into()),
+                DiffLine::Same("c".into()),
+                DiffLine::Changed("d".into(), "y".into()),
+                DiffLine::Added("z".into()),
+            ]
+        );
+        assert_eq!(lines.iter().filter(|line| line.is_change()).count(), 3);
+        assert_eq!(lines[4].sides(), (None, Some("z")));
+
+        assert_eq!(
+            diff_lines("a\nb", ""),
+            vec![DiffLine::Removed("a".into()), DiffLine::Removed("b".into())]
+        );
+    }
+}
@@ -1,9 +1,19 @@
 //! Configuration types for GBA Core.
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use validator::Validate;
 
+pub use gba_pm::{OutputConstraint, PermissionMode};
+
+use crate::context_budget::ContextStage;
+use crate::injection::InjectionPolicy;
+use crate::models::{ModelInfo, ModelRegistry};
+use crate::postprocess::{PostProcessPipeline, PostProcessor};
+use crate::store::StoreKind;
+use crate::vcs::VcsKind;
+
 /// Result type alias for configuration operations.
 pub type Result<T> = std::result::Result<T, ConfigError>;
 
@@ -47,31 +57,103 @@ pub struct ProjectConfig {
 
     /// Project metadata.
     #[serde(default)]
+    #[validate(nested)]
     pub project: ProjectMetadata,
 
     /// Agent defaults.
     #[serde(default)]
+    #[validate(nested)]
     pub agent: AgentConfig,
 
     /// Prompt templates configuration.
     #[serde(default)]
+    #[validate(nested)]
     pub prompts: PromptsConfig,
 
     /// Repository scanning settings.
     #[serde(default)]
+    #[validate(nested)]
     pub repository: RepositoryConfig,
 
     /// Logging configuration.
     #[serde(default)]
+    #[validate(nested)]
     pub logging: LoggingConfig,
 
     /// Worktree configuration.
     #[serde(default)]
+    #[validate(nested)]
     pub worktree: WorktreeConfig,
 
     /// Execution limits.
     #[serde(default)]
+    #[validate(nested)]
     pub limits: LimitsConfig,
+
+    /// Project-level usage quotas.
+    #[serde(default)]
+    #[validate(nested)]
+    pub quota: QuotaConfig,
+
+    /// Terminal UI settings.
+    #[serde(default)]
+    #[validate(nested)]
+    pub tui: TuiConfig,
+
+    /// Fan-out review settings.
+    #[serde(default)]
+    #[validate(nested)]
+    pub review: ReviewConfig,
+
+    /// Long-term repository memory.
+    #[serde(default)]
+    #[validate(nested)]
+    pub index: IndexConfig,
+
+    /// Post-processing of agent responses.
+    #[serde(default)]
+    #[validate(nested)]
+    pub post_process: PostProcessConfig,
+
+    /// Requirements on the responses of task kinds.
+    #[serde(default)]
+    pub constraints: ConstraintsConfig,
+
+    /// Commands run by the verification phase.
+    #[serde(default)]
+    #[validate(nested)]
+    pub verification: VerificationConfig,
+
+    /// Fix loop between verification and implementation.
+    #[serde(default)]
+    #[validate(nested)]
+    pub fix_loop: FixLoopConfig,
+
+    /// Fitting the context into the model's input budget.
+    #[serde(default)]
+    #[validate(nested)]
+    pub context: ContextConfig,
+
+    /// Scratch directories of runs.
+    #[serde(default)]
+    #[validate(nested)]
+    pub scratch: ScratchConfig,
+
+    /// Store features are persisted to beyond the project.
+    #[serde(default)]
+    pub storage: StorageConfig,
+
+    /// Pull requests opened for implemented features.
+    #[serde(default)]
+    pub github: GitHubConfig,
+
+    /// Models overriding or extending the built-in model table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<ModelInfo>,
+
+    /// Endpoints task events are posted to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
 }
 
 fn default_config_version() -> String {
@@ -89,10 +171,14 @@ pub struct ProjectMetadata {
     /// Repository information.
     #[serde(default)]
     pub repository: RepositoryMetadata,
+
+    /// Cost attribution tags recorded on every run, e.g. `team:payments`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 /// Repository metadata.
-#[derive(Debug, Clone, Serialize, Deserialize, Validate, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct RepositoryMetadata {
     /// Repository URL.
@@ -104,6 +190,15 @@ pub struct RepositoryMetadata {
     pub main_branch: String,
 }
 
+impl Default for RepositoryMetadata {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            main_branch: default_main_branch(),
+        }
+    }
+}
+
 fn default_main_branch() -> String {
     "main".to_string()
 }
@@ -116,18 +211,68 @@ pub struct AgentConfig {
     #[serde(default = "default_model")]
     pub model: String,
 
-    /// Maximum tokens for responses.
+    /// Maximum output tokens of each response, passed to the agent; a
+    /// [`crate::Task`] may override it.
     #[serde(default = "default_max_tokens")]
     pub max_tokens: u32,
 
-    /// Temperature for generation.
+    /// Temperature for generation. Not applied by the Claude Code CLI, see
+    /// [`crate::task::GenerationConfig::temperature`].
     #[serde(default = "default_temperature")]
     #[validate(range(min = 0.0, max = 2.0))]
     pub temperature: f32,
 
-    /// Timeout in seconds.
+    /// Timeout of a task in seconds; 0 disables it.
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+
+    /// Guardrails for the agent's tool use.
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+
+    /// Paths the agent may read but never modify, e.g. `Cargo.lock` or
+    /// `.github/workflows/**`. See [`crate::protect`] for the pattern syntax.
+    #[serde(default)]
+    pub protected_paths: Vec<String>,
+
+    /// Tools the agent may use, e.g. `Read` or `Edit`. Combined with the
+    /// tools of each template: a tool must be enabled by both. Empty enables
+    /// the template's tools.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+
+    /// Tools the agent may never use, e.g. `Bash` or `WebFetch`, whatever
+    /// the template enables.
+    #[serde(default)]
+    pub denied_tools: Vec<String>,
+
+    /// How the agent asks for permission to use tools: `default`,
+    /// `acceptEdits`, `plan` or `bypassPermissions`. A template may override
+    /// it with `permissionMode` in its front matter.
+    #[serde(default)]
+    pub permission_mode: PermissionMode,
+
+    /// MCP servers started for the agent's runs, by name, providing
+    /// project-specific tools. A template restricting its tools must enable
+    /// them, e.g. `mcp__<name>` for all the tools of a server.
+    #[serde(default)]
+    pub mcp_servers: BTreeMap<String, McpServer>,
+}
+
+/// An MCP server the agent starts over stdio.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServer {
+    /// Command starting the server, e.g. `npx`.
+    pub command: String,
+
+    /// Arguments of the command.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables of the server.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
 }
 
 impl Default for AgentConfig {
@@ -137,10 +282,93 @@ impl Default for AgentConfig {
             max_tokens: default_max_tokens(),
             temperature: default_temperature(),
             timeout: default_timeout(),
+            sandbox: SandboxConfig::default(),
+            protected_paths: Vec::new(),
+            allowed_tools: Vec::new(),
+            denied_tools: Vec::new(),
+            permission_mode: PermissionMode::default(),
+            mcp_servers: BTreeMap::new(),
         }
     }
 }
 
+/// Sandboxing policy for the agent's tool use.
+///
+/// Enforced through a pre-tool-use hook, so automation running with
+/// bypassed permissions still can't run arbitrary commands or touch files
+/// outside its working directory.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxConfig {
+    /// Enforce the policy.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Command prefixes the Bash tool may run, e.g. `cargo` or `git status`.
+    /// Empty allows any command not otherwise blocked.
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+
+    /// Patterns that reject a Bash command wherever they appear.
+    #[serde(default = "default_blocked_patterns")]
+    pub blocked_patterns: Vec<String>,
+
+    /// Allow network access: web tools and commands such as `curl`.
+    #[serde(default = "default_allow_network")]
+    pub allow_network: bool,
+
+    /// Restrict file access to the working directory and `allowedPaths`.
+    #[serde(default = "default_restrict_paths")]
+    pub restrict_paths: bool,
+
+    /// Additional paths the agent may access, relative to the working
+    /// directory or absolute.
+    #[serde(default = "default_allowed_paths")]
+    pub allowed_paths: Vec<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_commands: Vec::new(),
+            blocked_patterns: default_blocked_patterns(),
+            allow_network: default_allow_network(),
+            restrict_paths: default_restrict_paths(),
+            allowed_paths: default_allowed_paths(),
+        }
+    }
+}
+
+fn default_blocked_patterns() -> Vec<String> {
+    [
+        "rm -rf /",
+        "rm -rf ~",
+        "rm -fr /",
+        "mkfs",
+        "dd if=",
+        ":(){",
+        "chmod -R 777 /",
+        "git push --force",
+        "git push -f",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_allow_network() -> bool {
+    true
+}
+
+fn default_restrict_paths() -> bool {
+    true
+}
+
+fn default_allowed_paths() -> Vec<String> {
+    vec!["/tmp".to_string(), "/dev/null".to_string()]
+}
+
 fn default_model() -> String {
     "claude-sonnet-4-20250514".to_string()
 }
@@ -179,7 +407,7 @@ fn default_use_bundled() -> bool {
 }
 
 /// Repository scanning configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, Validate, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct RepositoryConfig {
     /// Patterns to exclude when scanning files.
@@ -189,6 +417,44 @@ pub struct RepositoryConfig {
     /// Maximum file size to include in context (bytes).
     #[serde(default = "default_max_file_size")]
     pub max_file_size: usize,
+
+    /// Version control of the project, `git` or `plain`; detected if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcs: Option<VcsKind>,
+
+    /// What to do with scanned files containing a possible prompt
+    /// injection: `flag` (the default), `strip`, `exclude` or `off`.
+    #[serde(default)]
+    pub prompt_injection: InjectionPolicy,
+
+    /// List binary files in contexts with a one-line placeholder, e.g.
+    /// `binary, 2.3MB`, instead of leaving them out.
+    #[serde(default)]
+    pub binary_placeholders: bool,
+
+    /// Leave lockfiles, vendored code and build output out of contexts, see
+    /// [`crate::vendored`].
+    #[serde(default = "default_exclude_vendored")]
+    pub exclude_vendored: bool,
+
+    /// Paths kept in contexts even though they look vendored, e.g.
+    /// `Cargo.lock` or `vendor/`, matched like `excludePatterns`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keep_vendored: Vec<String>,
+}
+
+impl Default for RepositoryConfig {
+    fn default() -> Self {
+        Self {
+            exclude_patterns: default_exclude_patterns(),
+            max_file_size: default_max_file_size(),
+            vcs: None,
+            prompt_injection: InjectionPolicy::default(),
+            binary_placeholders: false,
+            exclude_vendored: default_exclude_vendored(),
+            keep_vendored: Vec::new(),
+        }
+    }
 }
 
 fn default_exclude_patterns() -> Vec<String> {
@@ -199,6 +465,10 @@ fn default_exclude_patterns() -> Vec<String> {
     ]
 }
 
+fn default_exclude_vendored() -> bool {
+    true
+}
+
 fn default_max_file_size() -> usize {
     1_048_576 // 1MB
 }
@@ -237,7 +507,7 @@ fn default_log_to_console() -> bool {
 }
 
 /// Worktree configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, Validate, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct WorktreeConfig {
     /// Base directory for git worktrees.
@@ -247,6 +517,145 @@ pub struct WorktreeConfig {
     /// Branch prefix for feature worktrees.
     #[serde(default = "default_branch_prefix")]
     pub branch_prefix: String,
+
+    /// Commit the worktree's changes after a successful implementation run.
+    #[serde(default)]
+    pub auto_commit: bool,
+
+    /// Pre-commit hook installed in feature worktrees.
+    #[serde(default)]
+    pub pre_commit: PreCommitConfig,
+
+    /// Sparse checkout of feature worktrees.
+    #[serde(default)]
+    pub sparse_checkout: SparseCheckoutConfig,
+
+    /// Policy for cleaning up stale worktrees.
+    #[serde(default)]
+    pub prune: PrunePolicy,
+}
+
+impl Default for WorktreeConfig {
+    fn default() -> Self {
+        Self {
+            directory: default_worktree_dir(),
+            branch_prefix: default_branch_prefix(),
+            auto_commit: false,
+            pre_commit: PreCommitConfig::default(),
+            sparse_checkout: SparseCheckoutConfig::default(),
+            prune: PrunePolicy::default(),
+        }
+    }
+}
+
+/// Pre-commit hook run on commits in feature worktrees.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PreCommitConfig {
+    /// Install the hook in feature worktrees.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Shell commands run from the worktree root, e.g. `cargo fmt --check`.
+    /// The commit is rejected if any of them fails.
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
+
+/// Sparse checkout of feature worktrees, for large repositories.
+///
+/// Worktrees check out only the listed directories (plus top-level files),
+/// which keeps them and the agent's context small.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SparseCheckoutConfig {
+    /// Check out feature worktrees sparsely.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Directories checked out in every feature worktree.
+    #[serde(default)]
+    pub directories: Vec<String>,
+
+    /// Additional directories per feature name.
+    #[serde(default)]
+    pub features: HashMap<String, Vec<String>>,
+
+    /// Also check out the directories of the plan's affected files.
+    #[serde(default = "default_sparse_from_plan")]
+    pub from_plan: bool,
+}
+
+impl Default for SparseCheckoutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directories: Vec::new(),
+            features: HashMap::new(),
+            from_plan: default_sparse_from_plan(),
+        }
+    }
+}
+
+impl SparseCheckoutConfig {
+    /// Get the configured directories for a feature.
+    #[must_use]
+    pub fn directories_for(&self, feature: &str) -> Vec<String> {
+        let mut directories = self.directories.clone();
+        if let Some(extra) = self.features.get(feature) {
+            directories.extend(extra.iter().cloned());
+        }
+        directories
+    }
+}
+
+fn default_sparse_from_plan() -> bool {
+    true
+}
+
+/// Policy for cleaning up stale feature worktrees.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunePolicy {
+    /// Remove worktrees whose branch has been merged into the main branch.
+    #[serde(default = "default_prune_merged")]
+    pub merged: bool,
+
+    /// Remove worktrees with no activity for this many days (0 disables).
+    #[serde(default = "default_abandoned_after_days")]
+    pub abandoned_after_days: u32,
+
+    /// Also delete the local branch of removed worktrees, if it is merged.
+    #[serde(default = "default_prune_delete_branches")]
+    pub delete_branches: bool,
+
+    /// Also delete the unmerged branches of abandoned worktrees, losing the
+    /// work committed to them.
+    #[serde(default)]
+    pub delete_unmerged: bool,
+}
+
+impl Default for PrunePolicy {
+    fn default() -> Self {
+        Self {
+            merged: default_prune_merged(),
+            abandoned_after_days: default_abandoned_after_days(),
+            delete_branches: default_prune_delete_branches(),
+            delete_unmerged: false,
+        }
+    }
+}
+
+fn default_prune_merged() -> bool {
+    true
+}
+
+fn default_abandoned_after_days() -> u32 {
+    30
+}
+
+fn default_prune_delete_branches() -> bool {
+    true
 }
 
 fn default_worktree_dir() -> String {
@@ -258,7 +667,9 @@ fn default_branch_prefix() -> String {
 }
 
 /// Execution limits.
-#[derive(Debug, Clone, Serialize, Deserialize, Validate, Default)]
+///
+/// Agents enforce them on every task, see [`crate::Agent::with_limits`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct LimitsConfig {
     /// Maximum number of agent turns per task.
@@ -270,6 +681,15 @@ pub struct LimitsConfig {
     pub max_cost_usd: f64,
 }
 
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_turns: default_max_turns(),
+            max_cost_usd: default_max_cost(),
+        }
+    }
+}
+
 fn default_max_turns() -> u32 {
     100
 }
@@ -278,6 +698,654 @@ fn default_max_cost() -> f64 {
     10.0
 }
 
+/// Project-level usage quotas.
+///
+/// Quotas are checked against the cost ledger before a run starts, across
+/// all features. Unset quotas are not enforced. See [`crate::quota`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaConfig {
+    /// Maximum total cost in USD over the last 24 hours.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_max_usd: Option<f64>,
+
+    /// Maximum total cost in USD over the last 7 days.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly_max_usd: Option<f64>,
+
+    /// Maximum number of runs over the last 24 hours.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_runs_per_day: Option<u32>,
+}
+
+/// Post-processing of agent responses.
+///
+/// The common post-processors run first, then those of the task kind. See
+/// [`crate::postprocess`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PostProcessConfig {
+    /// Post-processors applied to the responses of every task kind.
+    #[serde(default = "default_common_post_processors")]
+    pub common: Vec<PostProcessor>,
+
+    /// Post-processors by task kind, e.g. `planning`.
+    #[serde(default = "default_kind_post_processors")]
+    pub kinds: HashMap<String, Vec<PostProcessor>>,
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            common: default_common_post_processors(),
+            kinds: default_kind_post_processors(),
+        }
+    }
+}
+
+impl PostProcessConfig {
+    /// Get the pipeline applied to the responses of a task kind.
+    #[must_use]
+    pub fn pipeline(&self, kind: &str) -> PostProcessPipeline {
+        let kind = self.kinds.get(kind).map(Vec::as_slice).unwrap_or_default();
+        PostProcessPipeline::new(self.common.iter().chain(kind).cloned().collect())
+    }
+}
+
+fn default_common_post_processors() -> Vec<PostProcessor> {
+    vec![PostProcessor::NormalizeLineEndings]
+}
+
+fn default_kind_post_processors() -> HashMap<String, Vec<PostProcessor>> {
+    ["planning", "review"]
+        .into_iter()
+        .map(|kind| (kind.to_string(), vec![PostProcessor::StripPreamble]))
+        .collect()
+}
+
+/// Requirements on the responses of task kinds, added to those their
+/// templates declare. See [`gba_pm::constraints`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstraintsConfig {
+    /// Constraints by task kind, e.g. `planning`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub kinds: HashMap<String, Vec<OutputConstraint>>,
+}
+
+impl ConstraintsConfig {
+    /// Get the constraints on the responses of a task kind.
+    #[must_use]
+    pub fn for_kind(&self, kind: &str) -> &[OutputConstraint] {
+        self.kinds.get(kind).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Verification configuration.
+///
+/// The verification phase runs these commands in the feature's worktree and
+/// passes their results to the agent. See [`crate::verify`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationConfig {
+    /// Commands to run, in order, e.g. `cargo test`.
+    #[serde(default)]
+    pub commands: Vec<VerificationCommand>,
+
+    /// Default timeout of a command in seconds.
+    #[serde(default = "default_verification_timeout")]
+    #[validate(range(min = 1))]
+    pub timeout_secs: u64,
+
+    /// Maximum bytes of output kept per command; the end is kept.
+    #[serde(default = "default_verification_max_output")]
+    pub max_output_bytes: usize,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            timeout_secs: default_verification_timeout(),
+            max_output_bytes: default_verification_max_output(),
+        }
+    }
+}
+
+fn default_verification_timeout() -> u64 {
+    600
+}
+
+fn default_verification_max_output() -> usize {
+    20_000
+}
+
+/// Fix loop configuration.
+///
+/// A fix loop verifies a feature and, while verification fails, runs an
+/// implementation task with the failures attached, up to a number of
+/// iterations and a budget.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct FixLoopConfig {
+    /// Maximum number of verifications.
+    #[serde(default = "default_fix_loop_iterations")]
+    #[validate(range(min = 1))]
+    pub max_iterations: u32,
+
+    /// Maximum cost of the loop in USD. Unset, the loop is only bounded by
+    /// its iterations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+}
+
+impl Default for FixLoopConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: default_fix_loop_iterations(),
+            max_cost_usd: None,
+        }
+    }
+}
+
+fn default_fix_loop_iterations() -> u32 {
+    3
+}
+
+/// Context budget configuration.
+///
+/// When a prompt would exceed the input budget, its context is degraded
+/// through the stages in order, see [`crate::context_budget`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextConfig {
+    /// Stages tried in order; the first that fits is used.
+    #[serde(default = "default_context_stages")]
+    pub stages: Vec<ContextStage>,
+
+    /// Maximum input tokens. Unset, the model's context window less
+    /// `agent.maxTokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_input_tokens: Option<u32>,
+
+    /// Prefix the lines of files in prompts with their line numbers.
+    #[serde(default)]
+    pub line_numbers: bool,
+
+    /// In a Cargo workspace, scope the context of a feature with a worktree
+    /// to the crates its changes touch and the crates they depend on.
+    #[serde(default)]
+    pub scope_to_crates: bool,
+
+    /// Build the context of an implementation from the files its plan lists
+    /// and their immediate dependents, instead of scanning the repository.
+    /// A plan listing no files falls back to the scan.
+    #[serde(default = "default_preload_plan_files")]
+    pub preload_plan_files: bool,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            stages: default_context_stages(),
+            max_input_tokens: None,
+            line_numbers: false,
+            scope_to_crates: false,
+            preload_plan_files: true,
+        }
+    }
+}
+
+impl ContextConfig {
+    /// Get the input budget for an agent configuration: the configured
+    /// maximum, capped by the model's context window less the output tokens.
+    #[must_use]
+    pub fn input_budget(&self, agent: &AgentConfig, models: &ModelRegistry) -> Option<u32> {
+        let model = models
+            .get(&agent.model)
+            .map(|model| model.input_budget(agent.max_tokens));
+        match (self.max_input_tokens, model) {
+            (Some(max), Some(model)) => Some(max.min(model)),
+            (max, model) => max.or(model),
+        }
+    }
+}
+
+fn default_preload_plan_files() -> bool {
+    true
+}
+
+fn default_context_stages() -> Vec<ContextStage> {
+    ContextStage::ALL.to_vec()
+}
+
+/// An endpoint task events are posted to as JSON, see [`crate::events`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    /// URL events are posted to.
+    pub url: String,
+
+    /// Environment variable holding the secret bodies are signed with.
+    /// Unset, events are posted unsigned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_env: Option<String>,
+
+    /// Event types posted, e.g. `"finished"`; empty posts all events.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+
+    /// Retries of a failed delivery.
+    #[serde(default = "default_webhook_retries")]
+    pub max_retries: u32,
+}
+
+impl WebhookConfig {
+    /// Check whether an event type is posted to this endpoint.
+    #[must_use]
+    pub fn accepts(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+/// A command run by the verification phase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationCommand {
+    /// Short name shown in reports, e.g. `"test"`.
+    pub name: String,
+
+    /// Command line, run through the platform shell.
+    pub command: String,
+
+    /// Timeout in seconds, overriding the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl VerificationCommand {
+    /// Create a command with the default timeout.
+    #[must_use]
+    pub fn new(name: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            command: command.into(),
+            timeout_secs: None,
+        }
+    }
+
+    /// Set the timeout in seconds.
+    #[must_use]
+    pub const fn with_timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = Some(timeout_secs);
+        self
+    }
+}
+
+/// Fan-out review configuration.
+///
+/// A fan-out review runs one review template per persona concurrently over
+/// the same diff and merges their findings.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewConfig {
+    /// Review templates to run, one per persona, e.g. `review-security`.
+    #[serde(default = "default_review_personas")]
+    pub personas: Vec<String>,
+
+    /// Maximum number of reviews running at once.
+    #[serde(default = "default_review_concurrency")]
+    #[validate(range(min = 1))]
+    pub max_concurrency: usize,
+
+    /// Maximum size in bytes of the diff reviewed (0 for unlimited); a
+    /// larger diff is cut at a line boundary.
+    #[serde(default = "default_review_max_diff_bytes")]
+    pub max_diff_bytes: usize,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self {
+            personas: default_review_personas(),
+            max_concurrency: default_review_concurrency(),
+            max_diff_bytes: default_review_max_diff_bytes(),
+        }
+    }
+}
+
+fn default_review_personas() -> Vec<String> {
+    vec![
+        "review-security".to_string(),
+        "review-performance".to_string(),
+        "review-style".to_string(),
+    ]
+}
+
+fn default_review_concurrency() -> usize {
+    3
+}
+
+fn default_review_max_diff_bytes() -> usize {
+    200_000
+}
+
+/// Scratch directories of runs, see [`crate::scratch`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ScratchConfig {
+    /// What happens to a run's scratch directory when the run finishes.
+    #[serde(default)]
+    pub retention: ScratchRetention,
+
+    /// Remove scratch directories older than this many days when a run
+    /// starts (0 disables).
+    #[serde(default = "default_scratch_max_age_days")]
+    pub max_age_days: u32,
+}
+
+impl Default for ScratchConfig {
+    fn default() -> Self {
+        Self {
+            retention: ScratchRetention::default(),
+            max_age_days: default_scratch_max_age_days(),
+        }
+    }
+}
+
+/// Store features are persisted to beyond the project, see
+/// [`crate::store`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageConfig {
+    /// Backend of the store.
+    #[serde(default)]
+    pub backend: StoreKind,
+
+    /// Directory of the filesystem backend, relative to the project; without
+    /// it, features are kept in the project only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// Bucket of the S3 backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<String>,
+
+    /// Key prefix of the features in the bucket, e.g. `gba/my-repo`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+
+    /// Endpoint URL of an S3-compatible service, e.g. MinIO or R2.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+/// Pull requests opened for implemented features.
+///
+/// With `createPullRequest`, a feature whose implementation passed
+/// verification has its worktree branch pushed and a pull request opened
+/// for it, with the plan and a summary of the implementation in its body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHubConfig {
+    /// Open a pull request after a verified implementation.
+    #[serde(default)]
+    pub create_pull_request: bool,
+
+    /// Repository, e.g. `acme/app`. Unset, it is read from the URL of the
+    /// remote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+
+    /// Remote the branch is pushed to.
+    #[serde(default = "default_github_remote")]
+    pub remote: String,
+
+    /// Branch the pull request targets. Unset, the main branch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+
+    /// Open pull requests as drafts.
+    #[serde(default)]
+    pub draft: bool,
+
+    /// Base URL of the REST API, e.g. for GitHub Enterprise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            create_pull_request: false,
+            repo: None,
+            remote: default_github_remote(),
+            base: None,
+            draft: false,
+            api_url: None,
+        }
+    }
+}
+
+fn default_github_remote() -> String {
+    "origin".to_string()
+}
+
+/// What happens to a run's scratch directory when the run finishes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScratchRetention {
+    /// Remove it.
+    Delete,
+    /// Keep it after a failed run, for debugging, and remove it otherwise.
+    #[default]
+    KeepFailed,
+    /// Keep it until it is pruned.
+    Keep,
+}
+
+fn default_scratch_max_age_days() -> u32 {
+    7
+}
+
+/// Repository index configuration.
+///
+/// When enabled, plans, review findings and the files runs look at are
+/// embedded into `.gba/index`, and each run recalls the closest entries
+/// into its context. See [`crate::index`].
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexConfig {
+    /// Maintain and query the index.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Maximum number of entries recalled into a run's context.
+    #[serde(default = "default_index_max_results")]
+    pub max_results: usize,
+
+    /// Dimensions of the embeddings.
+    #[serde(default = "default_index_dimensions")]
+    #[validate(range(min = 1))]
+    pub dimensions: usize,
+
+    /// Index the files runs look at, besides plans and review findings.
+    #[serde(default = "default_index_files")]
+    pub index_files: bool,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_results: default_index_max_results(),
+            dimensions: default_index_dimensions(),
+            index_files: default_index_files(),
+        }
+    }
+}
+
+fn default_index_max_results() -> usize {
+    5
+}
+
+fn default_index_dimensions() -> usize {
+    256
+}
+
+fn default_index_files() -> bool {
+    true
+}
+
+/// Terminal UI configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TuiConfig {
+    /// Key bindings for TUI actions.
+    #[serde(default)]
+    pub keys: TuiKeyBindings,
+}
+
+/// Key bindings for TUI actions.
+///
+/// Each action accepts a list of keys such as `"j"`, `"G"`, `"ctrl-d"`,
+/// `"down"`, or `"tab"`.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct TuiKeyBindings {
+    /// Scroll down one line.
+    #[serde(default = "default_keys_scroll_down")]
+    pub scroll_down: Vec<String>,
+
+    /// Scroll up one line.
+    #[serde(default = "default_keys_scroll_up")]
+    pub scroll_up: Vec<String>,
+
+    /// Scroll down half a page.
+    #[serde(default = "default_keys_half_page_down")]
+    pub half_page_down: Vec<String>,
+
+    /// Scroll up half a page.
+    #[serde(default = "default_keys_half_page_up")]
+    pub half_page_up: Vec<String>,
+
+    /// Jump to the top.
+    #[serde(default = "default_keys_top")]
+    pub top: Vec<String>,
+
+    /// Jump to the bottom.
+    #[serde(default = "default_keys_bottom")]
+    pub bottom: Vec<String>,
+
+    /// Switch to the next pane.
+    #[serde(default = "default_keys_next_pane")]
+    pub next_pane: Vec<String>,
+
+    /// Switch to the previous pane.
+    #[serde(default = "default_keys_prev_pane")]
+    pub prev_pane: Vec<String>,
+
+    /// Select a pane by its number.
+    #[serde(default = "default_number_keys")]
+    pub number_keys: bool,
+
+    /// Copy the active pane to the clipboard.
+    #[serde(default = "default_keys_copy")]
+    pub copy: Vec<String>,
+
+    /// Pause or resume the output of the running task.
+    #[serde(default = "default_keys_pause")]
+    pub pause: Vec<String>,
+
+    /// Abort the running task, keeping the TUI open.
+    #[serde(default = "default_keys_abort")]
+    pub abort: Vec<String>,
+
+    /// Quit the TUI.
+    #[serde(default = "default_keys_quit")]
+    pub quit: Vec<String>,
+}
+
+impl Default for TuiKeyBindings {
+    fn default() -> Self {
+        Self {
+            scroll_down: default_keys_scroll_down(),
+            scroll_up: default_keys_scroll_up(),
+            half_page_down: default_keys_half_page_down(),
+            half_page_up: default_keys_half_page_up(),
+            top: default_keys_top(),
+            bottom: default_keys_bottom(),
+            next_pane: default_keys_next_pane(),
+            prev_pane: default_keys_prev_pane(),
+            number_keys: default_number_keys(),
+            copy: default_keys_copy(),
+            pause: default_keys_pause(),
+            abort: default_keys_abort(),
+            quit: default_keys_quit(),
+        }
+    }
+}
+
+fn keys(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|k| (*k).to_string()).collect()
+}
+
+fn default_keys_scroll_down() -> Vec<String> {
+    keys(&["j", "down"])
+}
+
+fn default_keys_scroll_up() -> Vec<String> {
+    keys(&["k", "up"])
+}
+
+fn default_keys_half_page_down() -> Vec<String> {
+    keys(&["ctrl-d", "pagedown"])
+}
+
+fn default_keys_half_page_up() -> Vec<String> {
+    keys(&["ctrl-u", "pageup"])
+}
+
+fn default_keys_top() -> Vec<String> {
+    keys(&["g", "home"])
+}
+
+fn default_keys_bottom() -> Vec<String> {
+    keys(&["G", "end"])
+}
+
+fn default_keys_next_pane() -> Vec<String> {
+    keys(&["tab", "l"])
+}
+
+fn default_keys_prev_pane() -> Vec<String> {
+    keys(&["backtab", "h"])
+}
+
+fn default_number_keys() -> bool {
+    true
+}
+
+fn default_keys_copy() -> Vec<String> {
+    keys(&["y"])
+}
+
+fn default_keys_pause() -> Vec<String> {
+    keys(&["p", "space"])
+}
+
+fn default_keys_abort() -> Vec<String> {
+    keys(&["x"])
+}
+
+fn default_keys_quit() -> Vec<String> {
+    keys(&["q"])
+}
+
 impl ProjectConfig {
     /// Load configuration from a file.
     ///
@@ -292,25 +1360,69 @@ impl ProjectConfig {
         config.validate().map_err(|e| {
             ConfigError::ValidationError(format!("Configuration validation failed: {e}"))
         })?;
+        config.validate_model()?;
 
         tracing::debug!("Loaded configuration from {}", path.display());
         Ok(config)
     }
 
+    /// Check the content of a configuration file, collecting every problem
+    /// instead of stopping at the first.
+    ///
+    /// A file that doesn't parse has a single problem; otherwise each field
+    /// out of its range and an unknown model or too large `maxTokens` is
+    /// reported, located on the line of the field where it can be found.
+    #[must_use]
+    pub fn check(content: &str) -> Vec<ConfigProblem> {
+        let config: Self = match serde_yaml::from_str(content) {
+            Ok(config) => config,
+            Err(e) => {
+                return vec![ConfigProblem {
+                    line: e.location().map(|location| location.line()),
+                    message: e.to_string(),
+                }];
+            }
+        };
+
+        let mut problems = Vec::new();
+        if let Err(errors) = config.validate() {
+            let mut fields = Vec::new();
+            collect_validation_errors(&errors, &mut Vec::new(), &mut fields);
+            for (path, message) in fields {
+                problems.push(ConfigProblem {
+                    line: field_line(content, &path),
+                    message: format!("{}: {message}", path.join(".")),
+                });
+            }
+        }
+        if let Err(ConfigError::ValidationError(message)) = config.validate_model() {
+            let field = if config.model_registry().get(&config.agent.model).is_some() {
+                "maxTokens"
+            } else {
+                "model"
+            };
+            problems.push(ConfigProblem {
+                line: field_line(content, &["agent".to_string(), field.to_string()]),
+                message,
+            });
+        }
+        problems
+    }
+
     /// Save configuration to a file.
     ///
     /// # Errors
     ///
     /// Returns an error if the configuration cannot be written.
     #[tracing::instrument(skip(self, path))]
-    pub fn save_to_file(&self, path: &PathBuf) -> Result<()> {
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
         let content = serde_yaml::to_string(self)?;
-        std::fs::write(path, content)?;
+        crate::atomic::write_with_backup(path, content)?;
 
         tracing::debug!("Saved configuration to {}", path.display());
         Ok(())
@@ -328,7 +1440,50 @@ impl ProjectConfig {
             logging: LoggingConfig::default(),
             worktree: WorktreeConfig::default(),
             limits: LimitsConfig::default(),
+            quota: QuotaConfig::default(),
+            tui: TuiConfig::default(),
+            review: ReviewConfig::default(),
+            index: IndexConfig::default(),
+            post_process: PostProcessConfig::default(),
+            constraints: ConstraintsConfig::default(),
+            verification: VerificationConfig::default(),
+            fix_loop: FixLoopConfig::default(),
+            context: ContextConfig::default(),
+            scratch: ScratchConfig::default(),
+            storage: StorageConfig::default(),
+            github: GitHubConfig::default(),
+            models: Vec::new(),
+            webhooks: Vec::new(),
+        }
+    }
+
+    /// Get the model table: the built-in models with the project's overrides.
+    #[must_use]
+    pub fn model_registry(&self) -> ModelRegistry {
+        ModelRegistry::builtin().with_overrides(self.models.clone())
+    }
+
+    /// Check that `agent.model` is a known model and `agent.maxTokens` fits
+    /// its output limit.
+    ///
+    /// # Errors
+    ///
+    /// Returns a validation error otherwise.
+    pub fn validate_model(&self) -> Result<()> {
+        let registry = self.model_registry();
+        let Some(model) = registry.get(&self.agent.model) else {
+            return Err(ConfigError::ValidationError(format!(
+                "Unknown model '{}'; add it under 'models' to use it",
+                self.agent.model
+            )));
+        };
+        if self.agent.max_tokens > model.max_output_tokens {
+            return Err(ConfigError::ValidationError(format!(
+                "maxTokens {} exceeds the {} output token limit of {}",
+                self.agent.max_tokens, model.max_output_tokens, model.id
+            )));
         }
+        Ok(())
     }
 }
 
@@ -338,6 +1493,117 @@ impl Default for ProjectConfig {
     }
 }
 
+/// A problem found in a configuration file by [`ProjectConfig::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Line of the problem, starting at 1, if known.
+    pub line: Option<usize>,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Flatten validation errors into the path of each invalid field, with
+/// camelCase keys as in the file, and its messages.
+fn collect_validation_errors(
+    errors: &validator::ValidationErrors,
+    path: &mut Vec<String>,
+    fields: &mut Vec<(Vec<String>, String)>,
+) {
+    let mut errors = errors.errors().iter().collect::<Vec<_>>();
+    errors.sort_by_key(|(field, _)| *field);
+    for (field, kind) in errors {
+        path.push(camel_case(field));
+        match kind {
+            validator::ValidationErrorsKind::Field(errors) => {
+                let message = errors
+                    .iter()
+                    .map(|error| {
+                        error.message.as_ref().map_or_else(
+                            || validation_message(error),
+                            std::string::ToString::to_string,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                fields.push((path.clone(), message));
+            }
+            validator::ValidationErrorsKind::Struct(errors) => {
+                collect_validation_errors(errors, path, fields);
+            }
+            validator::ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    path.push(index.to_string());
+                    collect_validation_errors(errors, path, fields);
+                    path.pop();
+                }
+            }
+        }
+        path.pop();
+    }
+}
+
+/// Describe a validation error without a message, e.g. `must be at least 1`.
+fn validation_message(error: &validator::ValidationError) -> String {
+    let bound = |name: &str| error.params.get(name).map(ToString::to_string);
+    match (error.code.as_ref(), bound("min"), bound("max")) {
+        ("range", Some(min), Some(max)) => format!("must be between {min} and {max}"),
+        ("range", Some(min), None) => format!("must be at least {min}"),
+        ("range", None, Some(max)) => format!("must be at most {max}"),
+        (code, _, _) => format!("invalid ({code})"),
+    }
+}
+
+/// Convert a field name to camelCase, e.g. `max_turns` to `maxTurns`.
+fn camel_case(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut upper = false;
+    for c in field.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Find the line, starting at 1, of a field of a YAML file by the keys of
+/// its path, each looked up after the line of the previous one.
+///
+/// Returns the line of the deepest key found, or `None` if the first isn't.
+fn field_line(content: &str, path: &[String]) -> Option<usize> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let mut found = None;
+    let mut from = 0;
+    for key in path.iter().filter(|key| key.parse::<usize>().is_err()) {
+        let prefix = format!("{key}:");
+        let Some(offset) = lines[from..].iter().position(|line| {
+            line.trim_start()
+                .trim_start_matches("- ")
+                .starts_with(&prefix)
+        }) else {
+            break;
+        };
+        from += offset;
+        found = Some(from + 1);
+        from += 1;
+    }
+    found
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -356,6 +1622,29 @@ mod tests {
         assert!(config.validate().is_ok());
     }
 
+    #[test]
+    fn test_config_validate_model() {
+        let mut config = ProjectConfig::default();
+        assert!(config.validate_model().is_ok());
+
+        config.agent.model = "claude-next".to_string();
+        assert!(config.validate_model().is_err());
+
+        config.models.push(ModelInfo {
+            id: "claude-next".to_string(),
+            aliases: vec![],
+            context_window: 500_000,
+            max_output_tokens: 2048,
+            input_cost_per_mtok: 2.0,
+            output_cost_per_mtok: 10.0,
+        });
+        let err = config.validate_model().unwrap_err();
+        assert!(err.to_string().contains("maxTokens 4096"));
+
+        config.agent.max_tokens = 2048;
+        assert!(config.validate_model().is_ok());
+    }
+
     #[test]
     fn test_config_invalid_temperature() {
         let mut config = ProjectConfig::default();
@@ -371,6 +1660,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_config_check() {
+        assert!(ProjectConfig::check("version: \"1.0\"\n").is_empty());
+
+        let problems = ProjectConfig::check("agent:\n  model: [\n");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].line.is_some());
+
+        let content = "\
+version: \"1.0\"
+agent:
+  model: claude-next
+  temperature: 3.0
+fixLoop:
+  maxIterations: 0
+";
+        let problems = ProjectConfig::check(content);
+        let found = problems
+            .iter()
+            .map(|problem| (problem.line, problem.message.split(':').next().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (Some(4), "agent.temperature"),
+                (Some(6), "fixLoop.maxIterations"),
+                (
+                    Some(3),
+                    "Unknown model 'claude-next'; add it under 'models' to use it"
+                ),
+            ]
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "line 4: agent.temperature: must be between 0.0 and 2.0"
+        );
+    }
+
+    #[test]
+    fn test_tui_key_bindings_partial_override() {
+        let yaml = "tui:\n  keys:\n    quit: [\"ctrl-c\"]\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.tui.keys.quit, vec!["ctrl-c".to_string()]);
+        assert_eq!(config.tui.keys.scroll_down, vec!["j", "down"]);
+        assert!(config.tui.keys.number_keys);
+    }
+
+    #[test]
+    fn test_webhooks_config() {
+        let yaml = "webhooks:\n  - url: https://example.com/gba\n    events: [finished]\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        let webhook = &config.webhooks[0];
+        assert_eq!(webhook.max_retries, 3);
+        assert!(webhook.secret_env.is_none());
+        assert!(webhook.accepts("finished"));
+        assert!(!webhook.accepts("toolCall"));
+    }
+
+    #[test]
+    fn test_storage_config() {
+        let config: ProjectConfig = serde_yaml::from_str("version: \"1.0\"\n").unwrap();
+        assert_eq!(config.storage.backend, StoreKind::Filesystem);
+        assert!(config.storage.path.is_none());
+
+        let yaml = "storage:\n  backend: s3\n  bucket: ci\n  prefix: gba/repo\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.storage.backend, StoreKind::S3);
+        assert_eq!(config.storage.bucket.as_deref(), Some("ci"));
+        assert_eq!(config.storage.prefix, "gba/repo");
+    }
+
+    #[test]
+    fn test_github_config() {
+        let config: ProjectConfig = serde_yaml::from_str("version: \"1.0\"\n").unwrap();
+        assert!(!config.github.create_pull_request);
+        assert_eq!(config.github.remote, "origin");
+
+        let yaml = "github:\n  createPullRequest: true\n  repo: acme/app\n  draft: true\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.github.create_pull_request);
+        assert_eq!(config.github.repo.as_deref(), Some("acme/app"));
+        assert!(config.github.draft);
+        assert!(config.github.base.is_none());
+    }
+
+    #[test]
+    fn test_mcp_servers_config() {
+        let yaml = "agent:\n  mcpServers:\n    issues:\n      command: npx\n      args: [\"-y\", \"@acme/issues-mcp\"]\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        let server = &config.agent.mcp_servers["issues"];
+        assert_eq!(server.command, "npx");
+        assert_eq!(server.args, vec!["-y", "@acme/issues-mcp"]);
+        assert!(server.env.is_empty());
+    }
+
     #[test]
     fn test_config_serialize_deserialize() {
         let config = ProjectConfig::default();
@@ -0,0 +1,409 @@
+//! Fitting a context into the model's input budget.
+//!
+//! A prompt carries the content of every file in its [`Context`]. When the
+//! prompt would exceed the tokens the model accepts, the context is degraded
+//! in stages instead of sending a prompt that fails:
+//!
+//! 1. [`ContextStage::Full`] - file contents as scanned
+//! 2. [`ContextStage::Outlines`] - the declarations of each file: functions,
+//!    types, headings
+//! 3. [`ContextStage::Tree`] - a directory tree of the files with their
+//!    language and length
+//! 4. [`ContextStage::Paths`] - the paths of the files only
+//!
+//! The first configured stage that fits is used, and the agent reads the
+//! files it needs with its tools. Tree and path summaries replace the files
+//! with an overview under [`OVERVIEW_KEY`] in the context metadata.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::path::PathBuf;
+//!
+//! use gba_core::context_budget::{ContextStage, fit_context};
+//! use gba_core::task::{Context, File};
+//!
+//! let mut context = Context {
+//!     repository_path: PathBuf::from("/repo"),
+//!     branch: "main".to_string(),
+//!     files: vec![File {
+//!         path: PathBuf::from("src/lib.rs"),
+//!         content: "pub fn run() {\n    let x = 1;\n}\n".repeat(50),
+//!         language: "rust".to_string(),
+//!     }],
+//!     metadata: Default::default(),
+//! };
+//!
+//! let stage = fit_context(&mut context, 100, 400, &ContextStage::ALL);
+//! assert_eq!(stage, ContextStage::Outlines);
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::context_builder::estimate_tokens;
+use crate::task::{Context, File};
+
+/// Metadata key of the repository overview replacing the files in the tree
+/// and path stages.
+pub const OVERVIEW_KEY: &str = "repositoryOverview";
+
+/// Metadata key of the stage a degraded context was built at.
+pub const STAGE_KEY: &str = "contextStage";
+
+/// Prefixes of lines kept in outlines, after indentation.
+const DECLARATION_PREFIXES: &[&str] = &[
+    "pub ",
+    "pub(",
+    "fn ",
+    "async fn ",
+    "struct ",
+    "enum ",
+    "trait ",
+    "impl ",
+    "impl<",
+    "mod ",
+    "type ",
+    "const ",
+    "static ",
+    "macro_rules!",
+    "class ",
+    "def ",
+    "async def ",
+    "function ",
+    "async function ",
+    "export ",
+    "interface ",
+    "func ",
+    "package ",
+    "module ",
+    "public ",
+    "protected ",
+    "private ",
+    "CREATE ",
+];
+
+/// How much of the files a context carries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContextStage {
+    /// File contents as scanned.
+    #[default]
+    Full,
+
+    /// The declarations of each file.
+    Outlines,
+
+    /// A directory tree of the files.
+    Tree,
+
+    /// The paths of the files.
+    Paths,
+}
+
+impl fmt::Display for ContextStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Outlines => write!(f, "outlines"),
+            Self::Tree => write!(f, "tree"),
+            Self::Paths => write!(f, "paths"),
+        }
+    }
+}
+
+impl ContextStage {
+    /// All stages, from the most to the least detailed.
+    pub const ALL: [Self; 4] = [Self::Full, Self::Outlines, Self::Tree, Self::Paths];
+
+    /// Degrade files to this stage.
+    ///
+    /// Returns the files to keep and the overview replacing them, if any.
+    #[must_use]
+    pub fn apply(&self, files: &[File]) -> (Vec<File>, Option<String>) {
+        match self {
+            Self::Full => (files.to_vec(), None),
+            Self::Outlines => {
+                let files = files
+                    .iter()
+                    .map(|file| File {
+                        content: outline(&file.content, &file.language),
+                        ..file.clone()
+                    })
+                    .collect();
+                (files, None)
+            }
+            Self::Tree => (Vec::new(), Some(tree_summary(files))),
+            Self::Paths => {
+                let paths = files
+                    .iter()
+                    .map(|file| file.path.display().to_string())
+                    .collect::<Vec<_>>();
+                (Vec::new(), Some(paths.join("\n")))
+            }
+        }
+    }
+}
+
+/// Degrade a context until it fits a budget.
+///
+/// Tries the stages in order and keeps the first one whose files fit in the
+/// tokens left by the rest of the prompt. If none fits, the last stage is
+/// used anyway, with a warning. Without stages, the context is kept in full.
+///
+/// # Arguments
+///
+/// * `context` - Context to degrade in place.
+/// * `reserved_tokens` - Tokens of the prompt besides the files.
+/// * `budget` - Input tokens the model accepts.
+/// * `stages` - Stages to try, normally starting with
+///   [`ContextStage::Full`].
+///
+/// Returns the stage used.
+pub fn fit_context(
+    context: &mut Context,
+    reserved_tokens: usize,
+    budget: usize,
+    stages: &[ContextStage],
+) -> ContextStage {
+    let available = budget.saturating_sub(reserved_tokens);
+    let Some(last) = stages.last() else {
+        let tokens = files_tokens(&context.files);
+        if tokens > available {
+            warn!(
+                "Context of {} tokens exceeds the {} tokens available",
+                tokens, available
+            );
+        }
+        return ContextStage::Full;
+    };
+
+    let (stage, files, overview, tokens) = stages
+        .iter()
+        .map(|stage| {
+            let (files, overview) = stage.apply(&context.files);
+            let tokens = files_tokens(&files) + overview.as_deref().map_or(0, estimate_tokens);
+            (*stage, files, overview, tokens)
+        })
+        .find(|(.., tokens)| *tokens <= available)
+        .unwrap_or_else(|| {
+            let (files, overview) = last.apply(&context.files);
+            let tokens = files_tokens(&files) + overview.as_deref().map_or(0, estimate_tokens);
+            warn!(
+                "Context of {} tokens exceeds the {} tokens available even as {}",
+                tokens, available, last
+            );
+            (*last, files, overview, tokens)
+        });
+
+    if stage != ContextStage::Full {
+        info!(
+            "Degraded context to {} ({} tokens of {} available)",
+            stage, tokens, available
+        );
+        context
+            .metadata
+            .insert(STAGE_KEY.to_string(), stage.to_string().into());
+    }
+    if let Some(overview) = overview {
+        context
+            .metadata
+            .insert(OVERVIEW_KEY.to_string(), overview.into());
+    }
+    context.files = files;
+    stage
+}
+
+/// Estimate the tokens the files take in a prompt.
+#[must_use]
+pub fn files_tokens(files: &[File]) -> usize {
+    files.iter().map(file_tokens).sum()
+}
+
+/// Estimate the tokens a file takes in a prompt: its path and its content.
+#[must_use]
+pub fn file_tokens(file: &File) -> usize {
+    estimate_tokens(&file.path.to_string_lossy()) + estimate_tokens(&file.content)
+}
+
+/// Outline a file: the lines declaring functions, types and modules, or the
+/// headings of markdown.
+///
+/// A file without declarations is summarized by its length.
+#[must_use]
+pub fn outline(content: &str, language: &str) -> String {
+    let lines = content
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim_start();
+            if language == "markdown" {
+                trimmed.starts_with('#')
+            } else {
+                DECLARATION_PREFIXES
+                    .iter()
+                    .any(|prefix| trimmed.starts_with(prefix))
+            }
+        })
+        .map(str::trim_end)
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        format!("... ({} lines)", content.lines().count())
+    } else {
+        lines.join("\n")
+    }
+}
+
+/// Summarize files as a directory tree, each file with its language and
+/// number of lines.
+#[must_use]
+pub fn tree_summary(files: &[File]) -> String {
+    let mut files = files.iter().collect::<Vec<_>>();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut lines = Vec::new();
+    let mut open: Vec<String> = Vec::new();
+    for file in files {
+        let dirs = file
+            .path
+            .parent()
+            .map(Path::components)
+            .into_iter()
+            .flatten()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let common = open.iter().zip(&dirs).take_while(|(a, b)| a == b).count();
+        open.truncate(common);
+        for dir in &dirs[common..] {
+            lines.push(format!("{}{}/", "  ".repeat(open.len()), dir));
+            open.push(dir.clone());
+        }
+
+        let name = file
+            .path
+            .file_name()
+            .map_or_else(|| file.path.to_string_lossy(), |n| n.to_string_lossy());
+        lines.push(format!(
+            "{}{} ({}, {} lines)",
+            "  ".repeat(open.len()),
+            name,
+            file.language,
+            file.content.lines().count()
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(path: &str, language: &str, content: &str) -> File {
+        File {
+            path: PathBuf::from(path),
+            content: content.to_string(),
+            language: language.to_string(),
+        }
+    }
+
+    fn context(files: Vec<File>) -> Context {
+        Context {
+            repository_path: PathBuf::from("/repo"),
+            branch: "main".to_string(),
+            files,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_outline() {
+        let source = "use std::fmt;\n\npub struct A {\n    x: u32,\n}\n\nimpl A {\n    pub fn new() -> Self {\n        Self { x: 0 }\n    }\n}\n";
+        assert_eq!(
+            outline(source, "rust"),
+            "pub struct A {\nimpl A {\n    pub fn new() -> Self {"
+        );
+        assert_eq!(
+            outline("# Title\ntext\n## Usage\n", "markdown"),
+            "# Title\n## Usage"
+        );
+        assert_eq!(outline("a = 1\nb = 2\n", "toml"), "... (2 lines)");
+    }
+
+    #[test]
+    fn test_tree_summary() {
+        let files = [
+            file("src/lib.rs", "rust", "a\nb\n"),
+            file("README.md", "markdown", "# A\n"),
+            file("src/context/mod.rs", "rust", "a\n"),
+            file("tests/it.rs", "rust", ""),
+        ];
+        assert_eq!(
+            tree_summary(&files),
+            "README.md (markdown, 1 lines)\nsrc/\n  context/\n    mod.rs (rust, 1 lines)\n  lib.rs (rust, 2 lines)\ntests/\n  it.rs (rust, 0 lines)"
+        );
+    }
+
+    #[test]
+    fn test_fit_context_degrades_in_stages() {
+        let body = "pub fn f() {\n    body();\n}\n".repeat(100);
+        let files = vec![
+            file("src/a.rs", "rust", &body),
+            file("src/b.rs", "rust", &body),
+        ];
+
+        let mut full = context(files.clone());
+        assert_eq!(
+            fit_context(&mut full, 0, 100_000, &ContextStage::ALL),
+            ContextStage::Full
+        );
+        assert_eq!(full.files[0].content, body);
+        assert!(full.metadata.is_empty());
+
+        let mut outlines = context(files.clone());
+        assert_eq!(
+            fit_context(&mut outlines, 0, 1_000, &ContextStage::ALL),
+            ContextStage::Outlines
+        );
+        assert!(
+            outlines.files[0]
+                .content
+                .lines()
+                .all(|l| l == "pub fn f() {")
+        );
+        assert_eq!(outlines.metadata[STAGE_KEY], "outlines");
+
+        let mut tree = context(files.clone());
+        assert_eq!(
+            fit_context(&mut tree, 0, 30, &ContextStage::ALL),
+            ContextStage::Tree
+        );
+        assert!(tree.files.is_empty());
+        assert_eq!(
+            tree.metadata[OVERVIEW_KEY],
+            "src/\n  a.rs (rust, 300 lines)\n  b.rs (rust, 300 lines)"
+        );
+
+        let mut paths = context(files.clone());
+        assert_eq!(
+            fit_context(&mut paths, 0, 10, &ContextStage::ALL),
+            ContextStage::Paths
+        );
+        assert_eq!(paths.metadata[OVERVIEW_KEY], "src/a.rs\nsrc/b.rs");
+
+        // The last configured stage is used when nothing fits
+        let mut capped = context(files);
+        let stages = [ContextStage::Full, ContextStage::Outlines];
+        assert_eq!(
+            fit_context(&mut capped, 50, 10, &stages),
+            ContextStage::Outlines
+        );
+        assert_eq!(
+            fit_context(&mut context(vec![]), 50, 10, &[]),
+            ContextStage::Full
+        );
+    }
+}
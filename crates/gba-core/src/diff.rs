@@ -0,0 +1,364 @@
+//! Unified diff generation.
+//!
+//! Produces unified diffs between two refs, or between a worktree and its
+//! base branch, by shelling out to the `git` CLI. Diffs can be restricted to
+//! a set of paths and are truncated to a maximum size so they fit in prompts
+//! and terminal panes. Binary files are left out of the content unless
+//! [`DiffOptions::include_binary`] is set, and listed in
+//! [`Diff::binary_files`].
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+use crate::git::GitError;
+
+/// Result type alias for diff operations.
+pub type Result<T> = std::result::Result<T, DiffError>;
+
+/// Error types for diff operations.
+#[derive(Debug, Error)]
+pub enum DiffError {
+    /// A git command failed.
+    #[error("git {command} failed: {stderr}")]
+    Git {
+        /// The git subcommand that failed.
+        command: String,
+        /// Standard error output from git.
+        stderr: String,
+    },
+
+    /// IO error, e.g. git is not installed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<GitError> for DiffError {
+    fn from(e: GitError) -> Self {
+        match e {
+            GitError::Git { command, stderr } => Self::Git { command, stderr },
+            GitError::Io(e) => Self::Io(e),
+        }
+    }
+}
+
+/// Options controlling diff generation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffOptions {
+    /// Paths (git pathspecs) to include; empty means all files.
+    pub include: Vec<String>,
+
+    /// Paths (git pathspecs) to exclude.
+    pub exclude: Vec<String>,
+
+    /// Maximum size of the diff content in bytes (0 for unlimited).
+    pub max_bytes: usize,
+
+    /// Number of context lines around changes.
+    pub context_lines: u32,
+
+    /// Keep binary files in the content, as git's one-line notice.
+    pub include_binary: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_bytes: 200_000,
+            context_lines: 3,
+            include_binary: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Create default diff options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the paths to include.
+    #[must_use]
+    pub fn with_include(mut self, include: Vec<String>) -> Self {
+        self.include = include;
+        self
+    }
+
+    /// Set the paths to exclude.
+    #[must_use]
+    pub fn with_exclude(mut self, exclude: Vec<String>) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// Set the maximum diff size in bytes.
+    #[must_use]
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Set the number of context lines.
+    #[must_use]
+    pub fn with_context_lines(mut self, context_lines: u32) -> Self {
+        self.context_lines = context_lines;
+        self
+    }
+
+    /// Set whether binary files are kept in the content.
+    #[must_use]
+    pub fn with_binary(mut self, include_binary: bool) -> Self {
+        self.include_binary = include_binary;
+        self
+    }
+
+    /// Build the pathspec arguments for git.
+    fn pathspecs(&self) -> Vec<String> {
+        let mut specs: Vec<String> = self.include.clone();
+        if specs.is_empty() && !self.exclude.is_empty() {
+            specs.push(".".to_string());
+        }
+        specs.extend(self.exclude.iter().map(|p| format!(":(exclude){p}")));
+        specs
+    }
+}
+
+/// A generated unified diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diff {
+    /// Unified diff content, possibly truncated.
+    pub content: String,
+
+    /// Files changed in the diff.
+    pub files: Vec<PathBuf>,
+
+    /// Whether the content was truncated.
+    pub truncated: bool,
+
+    /// Binary files changed, left out of the content unless
+    /// [`DiffOptions::include_binary`] is set.
+    #[serde(default)]
+    pub binary_files: Vec<PathBuf>,
+}
+
+impl Diff {
+    /// Check whether the diff has no changes.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Generate a diff between two refs.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository.
+/// * `from` - Base ref.
+/// * `to` - Target ref.
+/// * `options` - Diff options.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when a ref doesn't exist.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba_core::diff::{self, DiffOptions};
+/// use std::path::Path;
+///
+/// let options = DiffOptions::new().with_include(vec!["src/".to_string()]);
+/// let diff = diff::diff_refs(Path::new("."), "main", "gba/0001-add-auth", &options)?;
+/// println!("{}", diff.content);
+/// # Ok::<(), gba_core::diff::DiffError>(())
+/// ```
+#[instrument(skip(options))]
+pub fn diff_refs(repo_path: &Path, from: &str, to: &str, options: &DiffOptions) -> Result<Diff> {
+    let range = format!("{from}...{to}");
+    generate(repo_path, &[range.as_str()], options)
+}
+
+/// Generate a diff of a worktree, including uncommitted changes, against the
+/// point where it diverged from a base ref.
+///
+/// Untracked files are not included.
+///
+/// # Arguments
+///
+/// * `worktree_path` - Path of the worktree.
+/// * `base` - Base ref, e.g. the main branch.
+/// * `options` - Diff options.
+///
+/// # Errors
+///
+/// Returns an error if git fails.
+#[instrument(skip(options))]
+pub fn diff_worktree(worktree_path: &Path, base: &str, options: &DiffOptions) -> Result<Diff> {
+    let merge_base = git(worktree_path, &["merge-base", base, "HEAD"])?;
+    generate(worktree_path, &[merge_base.trim()], options)
+}
+
+/// Run `git diff` with the given revision arguments and options.
+fn generate(repo_path: &Path, revisions: &[&str], options: &DiffOptions) -> Result<Diff> {
+    let unified = format!("--unified={}", options.context_lines);
+    let mut pathspecs = options.pathspecs();
+
+    let files = git(repo_path, &diff_args("--name-only", revisions, &pathspecs))?
+        .lines()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let binary_files = binary_files(&git(
+        repo_path,
+        &diff_args("--numstat", revisions, &pathspecs),
+    )?);
+    if !options.include_binary && !binary_files.is_empty() {
+        if options.include.is_empty() && options.exclude.is_empty() {
+            pathspecs.push(".".to_string());
+        }
+        pathspecs.extend(
+            binary_files
+                .iter()
+                .map(|path| format!(":(exclude,literal){}", path.display())),
+        );
+    }
+
+    let content = git(repo_path, &diff_args(&unified, revisions, &pathspecs))?;
+    let (mut content, truncated) = truncate(content, options.max_bytes);
+    if !options.include_binary {
+        for path in &binary_files {
+            content.push_str(&format!("... binary file omitted: {}\n", path.display()));
+        }
+    }
+
+    debug!(
+        "Generated diff of {} files ({} bytes, truncated: {}, binary: {})",
+        files.len(),
+        content.len(),
+        truncated,
+        binary_files.len()
+    );
+
+    Ok(Diff {
+        content,
+        files,
+        truncated,
+        binary_files,
+    })
+}
+
+/// Build the argument list for `git diff`.
+fn diff_args<'a>(mode: &'a str, revisions: &[&'a str], pathspecs: &'a [String]) -> Vec<&'a str> {
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff", mode];
+    args.extend_from_slice(revisions);
+    args.push("--");
+    args.extend(pathspecs.iter().map(String::as_str));
+    args
+}
+
+/// Get the binary files of `git diff --numstat` output, which counts their
+/// lines as `-`.
+///
+/// Renamed files are reported under their new path.
+fn binary_files(numstat: &str) -> Vec<PathBuf> {
+    numstat
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let (added, deleted, path) = (fields.next()?, fields.next()?, fields.next()?);
+            (added == "-" && deleted == "-").then(|| PathBuf::from(renamed_path(path)))
+        })
+        .collect()
+}
+
+/// Get the new path of a `--numstat` path, written `old => new` or
+/// `dir/{old => new}/file` for renames.
+fn renamed_path(path: &str) -> String {
+    let Some((before, after)) = path.split_once(" => ") else {
+        return path.to_string();
+    };
+    match (before.rfind('{'), after.find('}')) {
+        (Some(open), Some(close)) => {
+            let path = format!(
+                "{}{}{}",
+                &before[..open],
+                &after[..close],
+                &after[close + 1..]
+            );
+            path.replace("//", "/").trim_start_matches('/').to_string()
+        }
+        _ => after.to_string(),
+    }
+}
+
+/// Truncate diff content to at most `max_bytes`, cutting at a line boundary.
+fn truncate(content: String, max_bytes: usize) -> (String, bool) {
+    if max_bytes == 0 || content.len() <= max_bytes {
+        return (content, false);
+    }
+
+    let mut cut = max_bytes;
+    while !content.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let cut = content[..cut].rfind('\n').map_or(cut, |i| i + 1);
+
+    let omitted = content.len() - cut;
+    let mut truncated = content[..cut].to_string();
+    truncated.push_str(&format!("... diff truncated ({omitted} bytes omitted)\n"));
+    (truncated, true)
+}
+
+/// Run a git command and return its stdout.
+fn git(repo_path: &Path, args: &[&str]) -> Result<String> {
+    Ok(crate::git::git(repo_path, args)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        let content = "line one\nline two\nline three\n".to_string();
+
+        let (same, truncated) = truncate(content.clone(), 0);
+        assert_eq!(same, content);
+        assert!(!truncated);
+
+        let (cut, truncated) = truncate(content, 14);
+        assert!(truncated);
+        assert!(cut.starts_with("line one\n... diff truncated"));
+    }
+
+    #[test]
+    fn test_binary_files() {
+        let numstat = "3\t1\tsrc/lib.rs\n-\t-\tassets/logo.png\n\
+                       -\t-\tassets/{old.png => new.png}\n-\t-\t{a => }/b.bin\n";
+        assert_eq!(
+            binary_files(numstat),
+            vec![
+                PathBuf::from("assets/logo.png"),
+                PathBuf::from("assets/new.png"),
+                PathBuf::from("b.bin"),
+            ]
+        );
+        assert_eq!(renamed_path("old.bin => new.bin"), "new.bin");
+    }
+
+    #[test]
+    fn test_pathspecs() {
+        let options = DiffOptions::new().with_exclude(vec!["Cargo.lock".to_string()]);
+        assert_eq!(options.pathspecs(), vec![".", ":(exclude)Cargo.lock"]);
+
+        let options = DiffOptions::new().with_include(vec!["src/".to_string()]);
+        assert_eq!(options.pathspecs(), vec!["src/"]);
+    }
+}
@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::task::Response;
+
 /// Result type alias for GBA Core.
 pub type Result<T> = std::result::Result<T, CoreError>;
 
@@ -12,10 +14,55 @@ pub enum CoreError {
     #[error("Claude Agent SDK error: {0}")]
     ClaudeAgent(String),
 
+    /// The task's cost reached its budget before it finished.
+    #[error("Budget exceeded: ${spent:.4} spent of ${limit:.4}")]
+    BudgetExceeded {
+        /// Cost of the task in USD.
+        spent: f64,
+        /// Budget of the task in USD.
+        limit: f64,
+        /// What the task produced until it stopped, with its actual usage.
+        partial: Box<Response>,
+    },
+
+    /// The agent used its maximum number of turns before finishing.
+    #[error("Turn limit reached after {turns} turns")]
+    TurnLimitReached {
+        /// Turns taken by the agent.
+        turns: u32,
+        /// What the task produced until it stopped, with its actual usage.
+        partial: Box<Response>,
+    },
+
+    /// The response still broke the task's output constraints after it was
+    /// sent back to be fixed.
+    #[error("Response doesn't meet the output requirements: {}", problems.join("; "))]
+    OutputConstraints {
+        /// Problems found in the fixed response.
+        problems: Vec<String>,
+        /// The fixed response, with the usage of both attempts.
+        partial: Box<Response>,
+    },
+
+    /// The task did not finish within the agent's timeout.
+    #[error("Timed out after {seconds}s")]
+    Timeout {
+        /// Timeout in seconds.
+        seconds: u64,
+    },
+
+    /// The task was canceled before it finished.
+    #[error("Canceled")]
+    Canceled,
+
     /// Configuration error.
     #[error("Configuration error: {0}")]
     Config(String),
 
+    /// The full prompt could not be laid out.
+    #[error("Prompt layout error: {0}")]
+    Layout(String),
+
     /// IO error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -23,4 +70,84 @@ pub enum CoreError {
     /// Serialization/deserialization error.
     #[error("Serialization error: {0}")]
     Serde(#[from] serde_json::Error),
+
+    /// Audit log error.
+    #[error("Audit error: {0}")]
+    Audit(#[from] crate::audit::AuditError),
+
+    /// Cargo workspace error.
+    #[error("Cargo error: {0}")]
+    Cargo(#[from] crate::cargo::CargoError),
+
+    /// Storage backend error.
+    #[error("Storage error: {0}")]
+    Store(#[from] crate::store::StoreError),
+
+    /// Diff generation error.
+    #[error("Diff error: {0}")]
+    Diff(#[from] crate::diff::DiffError),
+
+    /// Git error.
+    #[error("Git error: {0}")]
+    Git(#[from] crate::git::GitError),
+
+    /// Repository index error.
+    #[error("Index error: {0}")]
+    Index(#[from] crate::index::IndexError),
+
+    /// Cost ledger error.
+    #[error("Ledger error: {0}")]
+    Ledger(#[from] crate::ledger::LedgerError),
+
+    /// Feature lock error.
+    #[error("Lock error: {0}")]
+    Lock(#[from] crate::lock::LockError),
+
+    /// Project quota error.
+    #[error("Quota error: {0}")]
+    Quota(#[from] crate::quota::QuotaError),
+
+    /// Session error.
+    #[error("Session error: {0}")]
+    Session(#[from] crate::session::SessionError),
+
+    /// Feature state error.
+    #[error("State error: {0}")]
+    State(#[from] crate::state::StateError),
+
+    /// Version control error.
+    #[error("Version control error: {0}")]
+    Vcs(#[from] crate::vcs::VcsError),
+
+    /// Transcript error.
+    #[error("Transcript error: {0}")]
+    Transcript(#[from] crate::transcript::TranscriptError),
+
+    /// Worktree error.
+    #[error("Worktree error: {0}")]
+    Worktree(#[from] crate::worktree::WorktreeError),
+}
+
+impl CoreError {
+    /// Get the partial response of a task stopped on a limit, or whose
+    /// response broke its output constraints.
+    #[must_use]
+    pub fn partial_response(&self) -> Option<&Response> {
+        match self {
+            Self::BudgetExceeded { partial, .. }
+            | Self::TurnLimitReached { partial, .. }
+            | Self::OutputConstraints { partial, .. } => Some(partial),
+            _ => None,
+        }
+    }
+
+    /// Check whether running the task again may succeed: the connection to
+    /// the agent failed, or the task timed out.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::ClaudeAgent(_) | Self::Timeout { .. } | Self::Io(_)
+        )
+    }
 }
@@ -0,0 +1,345 @@
+//! Task events published while runs execute.
+//!
+//! An [`EventBus`] forwards each [`TaskEvent`] to its [`EventSink`]s, e.g. a
+//! dashboard webhook. A run publishes when it starts, when the feature moves
+//! to another phase, for every tool call of the agent, and when it finishes
+//! with its usage. Sinks are called synchronously and must not block: a sink
+//! doing IO should queue the event and deliver it in the background.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//!
+//! use gba_core::events::{EventBus, EventKind, EventSink, RunEvents, TaskEvent};
+//!
+//! #[derive(Debug, Default)]
+//! struct Recorder(Mutex<Vec<String>>);
+//!
+//! impl EventSink for Recorder {
+//!     fn send(&self, event: &TaskEvent) {
+//!         self.0.lock().unwrap().push(event.kind.name().to_string());
+//!     }
+//! }
+//!
+//! let recorder = Arc::new(Recorder::default());
+//! let bus = EventBus::new().with_sink(recorder.clone());
+//! let events = RunEvents::new(bus, "add-auth", "0003", "20260224T103000Z-1a2b");
+//! events.emit(EventKind::Started { kind: "planning".into() });
+//!
+//! assert_eq!(*recorder.0.lock().unwrap(), ["started"]);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use claude_agent_sdk_rs::{
+    HookCallback, HookEvent, HookInput, HookJsonOutput, HookMatcher, SyncHookJsonOutput,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::sandbox::PATH_FIELDS;
+use crate::task::Usage;
+
+/// Maximum number of characters of a tool call summary.
+const MAX_SUMMARY_CHARS: usize = 200;
+
+/// An event of a run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskEvent {
+    /// When the event happened.
+    pub timestamp: DateTime<Utc>,
+
+    /// Feature name.
+    pub feature: String,
+
+    /// Feature identifier, e.g. `"0003"`.
+    pub feature_id: String,
+
+    /// Run identifier.
+    pub run_id: String,
+
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// What happened in a run, serialized with a `type` field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum EventKind {
+    /// The run started.
+    Started {
+        /// Task kind, e.g. `"implementation"`.
+        kind: String,
+    },
+
+    /// The feature moved to another phase.
+    PhaseChanged {
+        /// Previous phase, if any.
+        from: Option<String>,
+        /// New phase.
+        to: String,
+    },
+
+    /// The agent called a tool.
+    ToolCall {
+        /// Tool name, e.g. `"Bash"`.
+        tool: String,
+        /// Short description of the call: the command or the file.
+        summary: String,
+    },
+
+    /// The run finished.
+    Finished {
+        /// Task kind.
+        kind: String,
+        /// Whether the run succeeded.
+        success: bool,
+        /// Error message of a failed run.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        /// Usage of the run.
+        usage: Usage,
+    },
+}
+
+impl EventKind {
+    /// Get the name of the event type, e.g. `"toolCall"`, as serialized.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Started { .. } => "started",
+            Self::PhaseChanged { .. } => "phaseChanged",
+            Self::ToolCall { .. } => "toolCall",
+            Self::Finished { .. } => "finished",
+        }
+    }
+}
+
+/// Receives the events of runs.
+pub trait EventSink: Send + Sync {
+    /// Receive an event. Must not block.
+    fn send(&self, event: &TaskEvent);
+}
+
+/// Forwards events to sinks.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    /// Sinks, in registration order.
+    sinks: Vec<Arc<dyn EventSink>>,
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("sinks", &self.sinks.len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    /// Create a bus without sinks.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink.
+    #[must_use]
+    pub fn with_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Check whether the bus has no sinks.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Forward an event to every sink.
+    pub fn publish(&self, event: &TaskEvent) {
+        for sink in &self.sinks {
+            sink.send(event);
+        }
+    }
+}
+
+/// Publishes the events of one run.
+#[derive(Debug, Clone)]
+pub struct RunEvents {
+    /// Bus events are published on.
+    bus: EventBus,
+    /// Feature name.
+    feature: String,
+    /// Feature identifier.
+    feature_id: String,
+    /// Run identifier.
+    run_id: String,
+}
+
+impl RunEvents {
+    /// Create a publisher for a run.
+    #[must_use]
+    pub fn new(
+        bus: EventBus,
+        feature: impl Into<String>,
+        feature_id: impl Into<String>,
+        run_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            bus,
+            feature: feature.into(),
+            feature_id: feature_id.into(),
+            run_id: run_id.into(),
+        }
+    }
+
+    /// Publish the events of the run to a sink too, e.g. one showing its
+    /// progress.
+    #[must_use]
+    pub fn with_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.bus = self.bus.with_sink(sink);
+        self
+    }
+
+    /// Publish an event of the run, timestamped now.
+    pub fn emit(&self, kind: EventKind) {
+        if self.bus.is_empty() {
+            return;
+        }
+        self.bus.publish(&TaskEvent {
+            timestamp: Utc::now(),
+            feature: self.feature.clone(),
+            feature_id: self.feature_id.clone(),
+            run_id: self.run_id.clone(),
+            kind,
+        });
+    }
+
+    /// Build the hooks publishing a [`EventKind::ToolCall`] for every tool
+    /// call of the agent.
+    #[must_use]
+    pub fn hooks(&self) -> HashMap<HookEvent, Vec<HookMatcher>> {
+        let events = self.clone();
+        let callback: HookCallback = Arc::new(move |input, _tool_use_id, _context| {
+            let events = events.clone();
+            Box::pin(async move {
+                if let HookInput::PreToolUse(input) = input {
+                    events.emit(EventKind::ToolCall {
+                        summary: tool_summary(&input.tool_name, &input.tool_input),
+                        tool: input.tool_name,
+                    });
+                }
+                HookJsonOutput::Sync(SyncHookJsonOutput::default())
+            })
+        });
+
+        HashMap::from([(
+            HookEvent::PreToolUse,
+            vec![HookMatcher::builder().hooks(vec![callback]).build()],
+        )])
+    }
+}
+
+/// Summarize a tool call: the command of a Bash call, the file of a file
+/// tool, or the pattern or URL of a search.
+#[must_use]
+pub fn tool_summary(tool: &str, input: &Value) -> String {
+    let field = |name: &str| input.get(name).and_then(Value::as_str);
+    let summary = if tool == "Bash" {
+        field("command")
+    } else {
+        PATH_FIELDS
+            .iter()
+            .chain(&["pattern", "url", "query"])
+            .find_map(|name| field(name))
+    }
+    .unwrap_or_default();
+
+    let summary = summary.split_whitespace().collect::<Vec<_>>().join(" ");
+    match summary.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((cut, _)) => format!("{}…", &summary[..cut]),
+        None => summary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_event_serialization() {
+        let event = TaskEvent {
+            timestamp: Utc::now(),
+            feature: "add-auth".to_string(),
+            feature_id: "0003".to_string(),
+            run_id: "run-1".to_string(),
+            kind: EventKind::Finished {
+                kind: "planning".to_string(),
+                success: true,
+                error: None,
+                usage: Usage {
+                    input_tokens: 10,
+                    output_tokens: 5,
+                    total_cost_usd: 0.25,
+                },
+            },
+        };
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "finished");
+        assert_eq!(value["featureId"], "0003");
+        assert_eq!(value["usage"]["totalCostUsd"], 0.25);
+        assert!(value.get("error").is_none());
+        assert_eq!(event.kind.name(), "finished");
+
+        let value = serde_json::to_value(EventKind::PhaseChanged {
+            from: None,
+            to: "planning".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({ "type": "phaseChanged", "from": null, "to": "planning" })
+        );
+    }
+
+    #[test]
+    fn test_tool_summary() {
+        assert_eq!(
+            tool_summary("Bash", &json!({ "command": "cargo   test\n--workspace" })),
+            "cargo test --workspace"
+        );
+        assert_eq!(
+            tool_summary(
+                "Edit",
+                &json!({ "file_path": "src/lib.rs", "old_string": "a" })
+            ),
+            "src/lib.rs"
+        );
+        assert_eq!(
+            tool_summary("Grep", &json!({ "pattern": "fn main" })),
+            "fn main"
+        );
+        assert_eq!(tool_summary("TodoWrite", &json!({})), "");
+
+        let long = "x".repeat(300);
+        assert_eq!(
+            tool_summary("Bash", &json!({ "command": long }))
+                .chars()
+                .count(),
+            MAX_SUMMARY_CHARS + 1
+        );
+    }
+}
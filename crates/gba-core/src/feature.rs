@@ -0,0 +1,96 @@
+//! Feature naming.
+//!
+//! Features are identified by a four-digit id derived from the SHA-256 of
+//! their name, so it is the same on every machine and toolchain. The id keys
+//! the feature's files under `.gba/features/<id>/`, and worktrees and
+//! branches are named `<id>-<slug>`. Two names can share an id; the state
+//! file records the name, see [`crate::FeatureState::load_or_new`].
+
+use sha2::{Digest, Sha256};
+
+/// Get the identifier of a feature, e.g. `"0042"`.
+///
+/// # Arguments
+///
+/// * `name` - Feature name.
+#[must_use]
+pub fn feature_id(name: &str) -> String {
+    let digest = Sha256::digest(name.as_bytes());
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    format!("{:04}", u64::from_be_bytes(prefix) % 10000)
+}
+
+/// Convert a feature name into a slug usable in paths and branch names.
+///
+/// # Arguments
+///
+/// * `name` - Feature name.
+#[must_use]
+pub fn feature_slug(name: &str) -> String {
+    let slug = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        "feature".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Get the worktree directory name (`<id>-<slug>`) of a feature.
+///
+/// # Arguments
+///
+/// * `name` - Feature name.
+#[must_use]
+pub fn worktree_name(name: &str) -> String {
+    format!("{}-{}", feature_id(name), feature_slug(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_id() {
+        let id1 = feature_id("test-feature");
+        let id2 = feature_id("test-feature");
+        assert_eq!(id1, id2);
+        assert_eq!(id1.len(), 4);
+
+        let id3 = feature_id("different-feature");
+        assert_ne!(id1, id3);
+
+        // Ids are stable across toolchains and machines
+        assert_eq!(feature_id("test-feature"), "5907");
+        assert_eq!(feature_id("add-auth"), "7140");
+    }
+
+    #[test]
+    fn test_feature_slug() {
+        assert_eq!(feature_slug("add-auth"), "add-auth");
+        assert_eq!(feature_slug("Add PR in status.yml"), "add-pr-in-status-yml");
+        assert_eq!(feature_slug("--"), "feature");
+    }
+
+    #[test]
+    fn test_worktree_name() {
+        let name = worktree_name("Add Auth");
+        assert_eq!(name, format!("{}-add-auth", feature_id("Add Auth")));
+    }
+}
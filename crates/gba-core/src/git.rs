@@ -0,0 +1,696 @@
+//! Repository metadata, status, stash, commit, and integration helpers.
+//!
+//! Repository metadata (remotes, current branch, HEAD) and status are read
+//! with libgit2 when the `git2` feature is enabled, falling back to the `git`
+//! CLI when it is disabled or libgit2 can't open the repository. Operations
+//! that change the repository (stash, commit, rebase, merge) always use the
+//! CLI so that the user's hooks and configuration apply.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+/// Result type alias for git operations.
+pub type Result<T> = std::result::Result<T, GitError>;
+
+/// Error types for git operations.
+#[derive(Debug, Error)]
+pub enum GitError {
+    /// A git command failed.
+    #[error("git {command} failed: {stderr}")]
+    Git {
+        /// The git subcommand that failed.
+        command: String,
+        /// Standard error output from git.
+        stderr: String,
+    },
+
+    /// IO error, e.g. git is not installed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A changed path reported by `git status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    /// Two-letter porcelain status code, e.g. `" M"` or `"??"`.
+    pub code: String,
+
+    /// Path relative to the repository root.
+    pub path: PathBuf,
+}
+
+impl StatusEntry {
+    /// Check whether the path is untracked.
+    #[must_use]
+    pub fn is_untracked(&self) -> bool {
+        self.code == "??"
+    }
+}
+
+/// Repository metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoInfo {
+    /// URL of the `origin` remote.
+    pub remote_url: Option<String>,
+
+    /// Checked out branch, `None` when HEAD is detached.
+    pub branch: Option<String>,
+
+    /// SHA of the HEAD commit, `None` in a repository without commits.
+    pub head: Option<String>,
+}
+
+/// How to integrate a base branch into a feature branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integration {
+    /// Rebase the feature branch onto the base branch.
+    Rebase,
+    /// Merge the base branch into the feature branch.
+    Merge,
+}
+
+/// Outcome of integrating a base branch into a feature branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrationOutcome {
+    /// The feature branch already contains the base branch.
+    UpToDate,
+    /// The integration completed without conflicts.
+    Completed,
+    /// The integration stopped on conflicts in these paths.
+    Conflicts(Vec<PathBuf>),
+}
+
+/// Read the metadata of a repository or worktree.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when the path is not a repository.
+#[instrument]
+pub fn repo_info(repo_path: &Path) -> Result<RepoInfo> {
+    Ok(RepoInfo {
+        remote_url: remote_url(repo_path, "origin")?,
+        branch: current_branch(repo_path)?,
+        head: head_sha(repo_path)?,
+    })
+}
+
+/// Get the URL of a remote.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository or worktree.
+/// * `remote` - Remote name, e.g. `origin`.
+///
+/// # Returns
+///
+/// The URL, or `None` if the remote doesn't exist.
+///
+/// # Errors
+///
+/// Returns an error if git fails.
+pub fn remote_url(repo_path: &Path, remote: &str) -> Result<Option<String>> {
+    #[cfg(feature = "git2")]
+    match libgit2::remote_url(repo_path, remote) {
+        Ok(url) => return Ok(url),
+        Err(e) => debug!("Falling back to the git CLI: {}", e),
+    }
+
+    let key = format!("remote.{remote}.url");
+    optional(git(repo_path, &["config", "--get", &key]))
+}
+
+/// Get the branch checked out in a repository or worktree.
+///
+/// # Returns
+///
+/// The short branch name, or `None` if HEAD is detached.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when the path is not a repository.
+pub fn current_branch(repo_path: &Path) -> Result<Option<String>> {
+    #[cfg(feature = "git2")]
+    match libgit2::current_branch(repo_path) {
+        Ok(branch) => return Ok(branch),
+        Err(e) => debug!("Falling back to the git CLI: {}", e),
+    }
+
+    optional(git(
+        repo_path,
+        &["symbolic-ref", "--quiet", "--short", "HEAD"],
+    ))
+}
+
+/// Get the SHA of the HEAD commit.
+///
+/// # Returns
+///
+/// The full SHA, or `None` if the repository has no commits yet.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when the path is not a repository.
+pub fn head_sha(repo_path: &Path) -> Result<Option<String>> {
+    #[cfg(feature = "git2")]
+    match libgit2::head_sha(repo_path) {
+        Ok(sha) => return Ok(sha),
+        Err(e) => debug!("Falling back to the git CLI: {}", e),
+    }
+
+    optional(git(
+        repo_path,
+        &["rev-parse", "--verify", "--quiet", "HEAD"],
+    ))
+}
+
+/// Get the uncommitted changes in a repository or worktree.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository or worktree.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when the path is not a repository.
+#[instrument]
+pub fn status(repo_path: &Path) -> Result<Vec<StatusEntry>> {
+    #[cfg(feature = "git2")]
+    match libgit2::status(repo_path) {
+        Ok(entries) => return Ok(entries),
+        Err(e) => debug!("Falling back to the git CLI: {}", e),
+    }
+
+    let output = git(
+        repo_path,
+        &["status", "--porcelain", "--untracked-files=all"],
+    )?;
+    Ok(parse_status(&output))
+}
+
+/// Stash all uncommitted changes, including untracked files.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository or worktree.
+/// * `message` - Stash message.
+/// * `exclude` - Paths to leave untouched, e.g. `.gba`.
+///
+/// # Returns
+///
+/// The commit SHA of the stash entry, or `None` if there was nothing to
+/// stash. The SHA identifies the entry even after other stashes are pushed.
+///
+/// # Errors
+///
+/// Returns an error if git fails.
+#[instrument]
+pub fn stash_push(repo_path: &Path, message: &str, exclude: &[&str]) -> Result<Option<String>> {
+    let stashable = status(repo_path)?
+        .iter()
+        .any(|e| !exclude.iter().any(|x| e.path.starts_with(x)));
+    if !stashable {
+        return Ok(None);
+    }
+
+    let pathspecs = exclude
+        .iter()
+        .map(|x| format!(":(exclude){x}"))
+        .collect::<Vec<_>>();
+    let mut args = vec![
+        "stash",
+        "push",
+        "--include-untracked",
+        "--message",
+        message,
+        "--",
+        ".",
+    ];
+    args.extend(pathspecs.iter().map(String::as_str));
+    git(repo_path, &args)?;
+
+    let stash = git(repo_path, &["rev-parse", "refs/stash"])?
+        .trim()
+        .to_string();
+    debug!("Stashed changes in {} as {}", repo_path.display(), stash);
+    Ok(Some(stash))
+}
+
+/// Restore and drop a stash entry.
+///
+/// Git refuses to restore over local changes to the same files; the entry is
+/// kept in that case, and on conflicts.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository or worktree.
+/// * `stash` - Commit SHA of the stash entry, as returned by [`stash_push`].
+///
+/// # Errors
+///
+/// Returns an error if the entry doesn't exist or can't be restored.
+#[instrument]
+pub fn stash_pop(repo_path: &Path, stash: &str) -> Result<()> {
+    let entries = git(repo_path, &["stash", "list", "--format=%H"])?;
+    let index = entries
+        .lines()
+        .position(|sha| sha == stash)
+        .ok_or_else(|| GitError::Git {
+            command: "stash".to_string(),
+            stderr: format!("stash entry {stash} not found"),
+        })?;
+
+    git(repo_path, &["stash", "pop", &format!("stash@{{{index}}}")])?;
+    debug!("Restored stashed changes in {}", repo_path.display());
+    Ok(())
+}
+
+/// Commit all changes, including untracked files.
+///
+/// Trailers are appended to the message as a `Key: value` block, so they can
+/// be read back with `git log --format=%(trailers)`.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository or worktree.
+/// * `message` - Commit message.
+/// * `trailers` - Trailer keys and values.
+///
+/// # Returns
+///
+/// The SHA of the new commit, or `None` if there was nothing to commit.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when a pre-commit hook rejects the
+/// commit.
+#[instrument(skip(message))]
+pub fn commit_all(
+    repo_path: &Path,
+    message: &str,
+    trailers: &[(&str, &str)],
+) -> Result<Option<String>> {
+    git(repo_path, &["add", "--all"])?;
+    if git(repo_path, &["diff", "--cached", "--name-only"])?
+        .trim()
+        .is_empty()
+    {
+        return Ok(None);
+    }
+
+    let message = with_trailers(message, trailers);
+    git(repo_path, &["commit", "--quiet", "--message", &message])?;
+    let sha = git(repo_path, &["rev-parse", "HEAD"])?.trim().to_string();
+    debug!("Created commit {} in {}", sha, repo_path.display());
+    Ok(Some(sha))
+}
+
+/// Push a branch to a remote and set it as the branch's upstream.
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the repository or worktree.
+/// * `remote` - Remote name, e.g. `origin`.
+/// * `branch` - Branch to push.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when the remote rejects the push.
+#[instrument]
+pub fn push(repo_path: &Path, remote: &str, branch: &str) -> Result<()> {
+    git(
+        repo_path,
+        &["push", "--quiet", "--set-upstream", remote, branch],
+    )?;
+    debug!("Pushed {} to {}", branch, remote);
+    Ok(())
+}
+
+/// Integrate a base branch into the branch checked out in a worktree.
+///
+/// On conflicts the rebase or merge is left in progress so the conflicts can
+/// be resolved, then finished with [`continue_integration`] or undone with
+/// [`abort_integration`].
+///
+/// # Arguments
+///
+/// * `repo_path` - Path of the worktree.
+/// * `base` - Base ref, e.g. the main branch.
+/// * `integration` - Whether to rebase or merge.
+///
+/// # Errors
+///
+/// Returns an error if git fails for a reason other than conflicts.
+#[instrument]
+pub fn integrate(
+    repo_path: &Path,
+    base: &str,
+    integration: Integration,
+) -> Result<IntegrationOutcome> {
+    if is_ancestor(repo_path, base, "HEAD")? {
+        return Ok(IntegrationOutcome::UpToDate);
+    }
+
+    let result = match integration {
+        Integration::Rebase => git(repo_path, &["rebase", base]),
+        Integration::Merge => git(repo_path, &["merge", "--no-edit", base]),
+    };
+    finish(repo_path, result)
+}
+
+/// Continue an integration stopped on conflicts, after they were resolved.
+///
+/// Conflicted files without conflict markers are considered resolved and
+/// staged automatically.
+///
+/// # Errors
+///
+/// Returns an error if no rebase or merge is in progress, or git fails.
+#[instrument]
+pub fn continue_integration(repo_path: &Path) -> Result<IntegrationOutcome> {
+    let conflicts = conflicted_paths(repo_path)?
+        .into_iter()
+        .filter(|path| has_conflict_markers(&repo_path.join(path)))
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() {
+        return Ok(IntegrationOutcome::Conflicts(conflicts));
+    }
+
+    git(repo_path, &["add", "--all"])?;
+    let result = if is_rebasing(repo_path)? {
+        git(repo_path, &["rebase", "--continue"])
+    } else if is_merging(repo_path)? {
+        git(repo_path, &["commit", "--no-edit"])
+    } else {
+        return Err(GitError::Git {
+            command: "continue".to_string(),
+            stderr: "no rebase or merge in progress".to_string(),
+        });
+    };
+    finish(repo_path, result)
+}
+
+/// Abort an integration stopped on conflicts.
+///
+/// # Errors
+///
+/// Returns an error if no rebase or merge is in progress, or git fails.
+#[instrument]
+pub fn abort_integration(repo_path: &Path) -> Result<()> {
+    if is_rebasing(repo_path)? {
+        git(repo_path, &["rebase", "--abort"])?;
+    } else {
+        git(repo_path, &["merge", "--abort"])?;
+    }
+    Ok(())
+}
+
+/// Count the commits of `head` not in `base`, and of `base` not in `head`.
+///
+/// # Returns
+///
+/// The `(ahead, behind)` commit counts.
+///
+/// # Errors
+///
+/// Returns an error if git fails, e.g. when a ref doesn't exist.
+pub fn ahead_behind(repo_path: &Path, base: &str, head: &str) -> Result<(usize, usize)> {
+    let range = format!("{base}...{head}");
+    let output = git(repo_path, &["rev-list", "--left-right", "--count", &range])?;
+    let mut counts = output
+        .split_whitespace()
+        .map(|n| n.parse::<usize>().unwrap_or_default());
+    let behind = counts.next().unwrap_or_default();
+    let ahead = counts.next().unwrap_or_default();
+    Ok((ahead, behind))
+}
+
+/// Map the result of a rebase or merge step to its outcome.
+fn finish(repo_path: &Path, result: Result<String>) -> Result<IntegrationOutcome> {
+    match result {
+        Ok(_) => Ok(IntegrationOutcome::Completed),
+        Err(e) => {
+            let conflicts = conflicted_paths(repo_path)?;
+            if conflicts.is_empty() {
+                Err(e)
+            } else {
+                debug!("Integration stopped on {} conflicts", conflicts.len());
+                Ok(IntegrationOutcome::Conflicts(conflicts))
+            }
+        }
+    }
+}
+
+/// Get the paths with unresolved conflicts.
+fn conflicted_paths(repo_path: &Path) -> Result<Vec<PathBuf>> {
+    let output = git(repo_path, &["diff", "--name-only", "--diff-filter=U"])?;
+    Ok(output.lines().map(PathBuf::from).collect())
+}
+
+/// Check whether a file still contains conflict markers.
+fn has_conflict_markers(path: &Path) -> bool {
+    // Deleted or binary files can't carry markers
+    std::fs::read_to_string(path).is_ok_and(|content| {
+        content
+            .lines()
+            .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> "))
+    })
+}
+
+/// Check whether `ancestor` is an ancestor of `descendant`.
+fn is_ancestor(repo_path: &Path, ancestor: &str, descendant: &str) -> Result<bool> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(repo_path)
+        .args(["merge-base", "--is-ancestor", ancestor, descendant])
+        .status()?;
+    Ok(status.success())
+}
+
+/// Check whether a rebase is in progress.
+fn is_rebasing(repo_path: &Path) -> Result<bool> {
+    Ok(git_path(repo_path, "rebase-merge")?.exists()
+        || git_path(repo_path, "rebase-apply")?.exists())
+}
+
+/// Check whether a merge is in progress.
+fn is_merging(repo_path: &Path) -> Result<bool> {
+    Ok(git_path(repo_path, "MERGE_HEAD")?.exists())
+}
+
+/// Resolve a path inside the git directory of a worktree.
+fn git_path(repo_path: &Path, name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(git(repo_path, &["rev-parse", "--git-path", name])?.trim());
+    Ok(if path.is_relative() {
+        repo_path.join(path)
+    } else {
+        path
+    })
+}
+
+/// Append a trailer block to a commit message.
+fn with_trailers(message: &str, trailers: &[(&str, &str)]) -> String {
+    let mut message = message.trim_end().to_string();
+    if !trailers.is_empty() {
+        message.push_str("\n\n");
+        for (key, value) in trailers {
+            message.push_str(&format!("{key}: {value}\n"));
+        }
+    }
+    message
+}
+
+/// Parse the output of `git status --porcelain`.
+fn parse_status(output: &str) -> Vec<StatusEntry> {
+    output
+        .lines()
+        .filter(|line| line.len() > 3)
+        .map(|line| {
+            let path = &line[3..];
+            // Renames are reported as "old -> new"
+            let path = path.rsplit_once(" -> ").map_or(path, |(_, new)| new);
+            StatusEntry {
+                code: line[..2].to_string(),
+                path: PathBuf::from(path.trim_matches('"')),
+            }
+        })
+        .collect()
+}
+
+/// Treat a git command that failed without an error message as "no value".
+///
+/// Commands like `git config --get` and `git symbolic-ref --quiet` exit with
+/// a non-zero status but no output when the value is simply absent.
+fn optional(result: Result<String>) -> Result<Option<String>> {
+    match result {
+        Ok(output) => Ok(Some(output.trim().to_string())),
+        Err(GitError::Git { stderr, .. }) if stderr.is_empty() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Run a git command and return its stdout.
+///
+/// Shared by the modules shelling out to git, so every command runs with the
+/// same environment.
+pub(crate) fn git(repo_path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_path)
+        .args(args)
+        // Never wait on an interactive editor, e.g. in `rebase --continue`
+        .env("GIT_EDITOR", "true")
+        .output()?;
+
+    if !output.status.success() {
+        return Err(GitError::Git {
+            command: args.first().copied().unwrap_or_default().to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// libgit2-backed implementations of the read-only operations.
+#[cfg(feature = "git2")]
+mod libgit2 {
+    use std::path::{Path, PathBuf};
+
+    use git2::{ErrorCode, Repository, Status, StatusOptions};
+
+    use super::StatusEntry;
+
+    type Result<T> = std::result::Result<T, git2::Error>;
+
+    /// Map "not found" style errors to `None`.
+    fn optional<T>(result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::UnbornBranch) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(super) fn remote_url(repo_path: &Path, remote: &str) -> Result<Option<String>> {
+        let repo = Repository::discover(repo_path)?;
+        let remote = optional(repo.find_remote(remote))?;
+        Ok(remote.and_then(|r| r.url().map(str::to_string)))
+    }
+
+    pub(super) fn current_branch(repo_path: &Path) -> Result<Option<String>> {
+        let repo = Repository::discover(repo_path)?;
+        if repo.head_detached()? {
+            return Ok(None);
+        }
+        // HEAD of a repository without commits points at an unborn branch
+        let head = repo.find_reference("HEAD")?;
+        Ok(head
+            .symbolic_target()
+            .map(|target| target.trim_start_matches("refs/heads/").to_string()))
+    }
+
+    pub(super) fn head_sha(repo_path: &Path) -> Result<Option<String>> {
+        let repo = Repository::discover(repo_path)?;
+        let head = optional(repo.head())?;
+        head.map(|h| h.peel_to_commit().map(|c| c.id().to_string()))
+            .transpose()
+    }
+
+    pub(super) fn status(repo_path: &Path) -> Result<Vec<StatusEntry>> {
+        let repo = Repository::discover(repo_path)?;
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+
+        let statuses = repo.statuses(Some(&mut options))?;
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| {
+                Some(StatusEntry {
+                    code: status_code(entry.status()),
+                    path: PathBuf::from(entry.path()?),
+                })
+            })
+            .collect())
+    }
+
+    /// Convert a libgit2 status to a two-letter porcelain status code.
+    fn status_code(status: Status) -> String {
+        if status.is_conflicted() {
+            return "UU".to_string();
+        }
+        if status == Status::WT_NEW {
+            return "??".to_string();
+        }
+
+        let index = if status.is_index_new() {
+            'A'
+        } else if status.is_index_modified() {
+            'M'
+        } else if status.is_index_deleted() {
+            'D'
+        } else if status.is_index_renamed() {
+            'R'
+        } else if status.is_index_typechange() {
+            'T'
+        } else {
+            ' '
+        };
+        let worktree = if status.is_wt_modified() {
+            'M'
+        } else if status.is_wt_deleted() {
+            'D'
+        } else if status.is_wt_renamed() {
+            'R'
+        } else if status.is_wt_typechange() {
+            'T'
+        } else {
+            ' '
+        };
+        format!("{index}{worktree}")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_status_code() {
+            assert_eq!(status_code(Status::WT_NEW), "??");
+            assert_eq!(status_code(Status::WT_MODIFIED), " M");
+            assert_eq!(status_code(Status::INDEX_NEW | Status::WT_MODIFIED), "AM");
+            assert_eq!(status_code(Status::CONFLICTED), "UU");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let output = " M src/lib.rs\n?? notes.txt\nR  old.rs -> new.rs\n";
+        let entries = parse_status(output);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].code, " M");
+        assert_eq!(entries[0].path, PathBuf::from("src/lib.rs"));
+        assert!(entries[1].is_untracked());
+        assert_eq!(entries[2].path, PathBuf::from("new.rs"));
+    }
+
+    #[test]
+    fn test_with_trailers() {
+        let message = with_trailers("gba: implement add-auth\n", &[("Gba-Run-Id", "42")]);
+        assert_eq!(message, "gba: implement add-auth\n\nGba-Run-Id: 42\n");
+        assert_eq!(with_trailers("subject", &[]), "subject");
+    }
+}
@@ -0,0 +1,550 @@
+//! Local embedding index of past plans, review findings and key files.
+//!
+//! The index gives the agent a long-term memory of the repository: when a
+//! feature is planned or reviewed, the plan, the review findings and the
+//! files the run looked at are embedded and stored under `.gba/index`. Later
+//! runs query the index with their description, and the closest entries are
+//! added to the task context, so prior decisions aren't forgotten between
+//! features.
+//!
+//! Embeddings are computed locally by an [`Embedder`]. The default
+//! [`HashingEmbedder`] hashes words and word pairs into a fixed number of
+//! dimensions: it needs no model or network access and is deterministic
+//! across runs and platforms.
+//!
+//! Entries are stored one JSON object per line in `entries.jsonl` and keyed
+//! by [`IndexEntry::id`], so re-indexing a plan or file replaces its entry.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::index::{EntryKind, HashingEmbedder, IndexEntry, RepoIndex};
+//!
+//! let embedder = HashingEmbedder::default();
+//! let mut index = RepoIndex::open(".gba/index", &embedder)?;
+//! index.upsert(IndexEntry::new(
+//!     EntryKind::Plan,
+//!     "0003",
+//!     "Plan for add-auth",
+//!     "Store sessions in Redis with a 24h expiry",
+//! ));
+//! index.save()?;
+//!
+//! for hit in index.query("session storage", 3) {
+//!     println!("{:.2} {}", hit.score, hit.entry.title);
+//! }
+//! # Ok::<(), gba_core::index::IndexError>(())
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::review::Finding;
+use crate::task::{Context, File};
+
+/// Result type alias for index operations.
+pub type Result<T> = std::result::Result<T, IndexError>;
+
+/// Error types for index operations.
+#[derive(Debug, Error)]
+pub enum IndexError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// An index line could not be serialized or parsed.
+    #[error("Invalid index entry at line {line}: {source}")]
+    Entry {
+        /// Line number, starting at 1 (0 when serializing).
+        line: usize,
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+}
+
+/// Maximum number of characters of text stored per entry.
+const MAX_TEXT_CHARS: usize = 4_000;
+
+/// Maximum number of characters of an entry added to a task context.
+const MAX_EXCERPT_CHARS: usize = 600;
+
+/// Context metadata key recalled entries are added under.
+pub const MEMORY_KEY: &str = "memory";
+
+/// Kind of an indexed entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryKind {
+    /// An implementation plan.
+    Plan,
+    /// A review finding.
+    Finding,
+    /// A repository file.
+    File,
+}
+
+impl fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plan => write!(f, "plan"),
+            Self::Finding => write!(f, "finding"),
+            Self::File => write!(f, "file"),
+        }
+    }
+}
+
+/// An entry of the index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntry {
+    /// Unique key, e.g. `"plan:0003"` or `"file:src/auth.rs"`.
+    pub id: String,
+
+    /// Kind of the entry.
+    pub kind: EntryKind,
+
+    /// Where the entry comes from: a feature identifier or a file path.
+    pub source: String,
+
+    /// Short title shown when the entry is recalled.
+    pub title: String,
+
+    /// Indexed text, truncated.
+    pub text: String,
+
+    /// Embedding of the title and text, filled in by the index.
+    #[serde(default)]
+    pub vector: Vec<f32>,
+
+    /// When the entry was indexed.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl IndexEntry {
+    /// Create an entry. Its id is `<kind>:<source>`.
+    #[must_use]
+    pub fn new(
+        kind: EntryKind,
+        source: impl Into<String>,
+        title: impl Into<String>,
+        text: &str,
+    ) -> Self {
+        let source = source.into();
+        Self {
+            id: format!("{kind}:{source}"),
+            kind,
+            source,
+            title: title.into(),
+            text: truncate(text, MAX_TEXT_CHARS),
+            vector: Vec::new(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Set the id, for several entries from the same source.
+    #[must_use]
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Create an entry for the plan of a feature.
+    #[must_use]
+    pub fn plan(feature_id: &str, feature: &str, plan: &str) -> Self {
+        Self::new(
+            EntryKind::Plan,
+            feature_id,
+            format!("Plan for {feature}"),
+            plan,
+        )
+    }
+
+    /// Create an entry for a review finding of a feature.
+    #[must_use]
+    pub fn finding(feature_id: &str, feature: &str, finding: &Finding) -> Self {
+        let location = finding.location.as_deref().unwrap_or_default();
+        let mut text = format!("{} {location}: {}", finding.severity, finding.description);
+        if let Some(fix) = &finding.suggested_fix {
+            text.push_str("\nSuggested fix: ");
+            text.push_str(fix);
+        }
+        Self::new(
+            EntryKind::Finding,
+            feature_id,
+            format!("Review of {feature}: {}", finding.description),
+            &text,
+        )
+        .with_id(format!(
+            "finding:{feature_id}:{location}:{}",
+            finding.description
+        ))
+    }
+
+    /// Create an entry for a repository file.
+    #[must_use]
+    pub fn file(file: &File) -> Self {
+        let path = file.path.to_string_lossy().replace('\\', "/");
+        Self::new(EntryKind::File, path.clone(), path, &file.content)
+    }
+}
+
+/// An entry recalled by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit<'a> {
+    /// Cosine similarity with the query, from -1 to 1.
+    pub score: f32,
+
+    /// The recalled entry.
+    pub entry: &'a IndexEntry,
+}
+
+/// Computes embeddings of text.
+pub trait Embedder: Send + Sync {
+    /// Embed a text as an L2-normalized vector.
+    ///
+    /// Vectors of the same embedder must have the same dimensions.
+    fn embed(&self, text: &str) -> Vec<f32>;
+}
+
+/// Embedder hashing words and word pairs into a fixed number of dimensions.
+///
+/// Words are lowercased alphanumeric runs, so `parse_config` matches
+/// "parse config". The hash is FNV-1a, stable across Rust versions and
+/// platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashingEmbedder {
+    /// Number of dimensions.
+    dimensions: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl HashingEmbedder {
+    /// Create an embedder with the given number of dimensions, at least 1.
+    #[must_use]
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    /// Get the number of dimensions.
+    #[must_use]
+    pub const fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Add a feature to a vector, with a sign from the hash to reduce the
+    /// bias of collisions.
+    fn add(&self, vector: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let bucket = (hash % self.dimensions as u64) as usize;
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.len() > 1)
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+
+        for word in &words {
+            self.add(&mut vector, word, 1.0);
+        }
+        for pair in words.windows(2) {
+            self.add(&mut vector, &format!("{} {}", pair[0], pair[1]), 0.5);
+        }
+
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for x in &mut vector {
+                *x /= norm;
+            }
+        }
+        vector
+    }
+}
+
+/// Index stored in a directory, e.g. `.gba/index`.
+pub struct RepoIndex<'a> {
+    /// Directory of the index files.
+    dir: PathBuf,
+    /// Embedder for entries and queries.
+    embedder: &'a dyn Embedder,
+    /// Entries, in insertion order.
+    entries: Vec<IndexEntry>,
+}
+
+impl fmt::Debug for RepoIndex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RepoIndex")
+            .field("dir", &self.dir)
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl<'a> RepoIndex<'a> {
+    /// Open the index in a directory.
+    ///
+    /// A missing index is empty. Entries embedded with different dimensions,
+    /// e.g. by another embedder, are re-embedded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index file cannot be read or a line is
+    /// invalid.
+    pub fn open(dir: impl Into<PathBuf>, embedder: &'a dyn Embedder) -> Result<Self> {
+        let dir = dir.into();
+        let content = match std::fs::read_to_string(dir.join("entries.jsonl")) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str::<IndexEntry>(line).map_err(|source| IndexError::Entry {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let dimensions = embedder.embed("").len();
+        for entry in &mut entries {
+            if entry.vector.len() != dimensions {
+                entry.vector = embedder.embed(&entry_text(entry));
+            }
+        }
+
+        Ok(Self {
+            dir,
+            embedder,
+            entries,
+        })
+    }
+
+    /// Get the directory of the index.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Get the entries.
+    #[must_use]
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Add an entry, replacing the entry with the same id.
+    pub fn upsert(&mut self, mut entry: IndexEntry) {
+        entry.vector = self.embedder.embed(&entry_text(&entry));
+        match self.entries.iter_mut().find(|e| e.id == entry.id) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Remove the entries of a source, e.g. a deleted file.
+    ///
+    /// Returns the number of removed entries.
+    pub fn remove_source(&mut self, kind: EntryKind, source: &str) -> usize {
+        let before = self.entries.len();
+        self.entries
+            .retain(|entry| !(entry.kind == kind && entry.source == source));
+        before - self.entries.len()
+    }
+
+    /// Find the entries closest to a query, best first.
+    ///
+    /// Only entries with a positive similarity are returned, so an empty
+    /// query recalls nothing.
+    #[must_use]
+    pub fn query(&self, text: &str, limit: usize) -> Vec<Hit<'_>> {
+        let query = self.embedder.embed(text);
+        let mut hits = self
+            .entries
+            .iter()
+            .map(|entry| Hit {
+                score: dot(&query, &entry.vector),
+                entry,
+            })
+            .filter(|hit| hit.score > 0.0)
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+
+    /// Add the entries closest to a query to a task context.
+    ///
+    /// The entries are added to the context metadata under `memory`, as a
+    /// list of objects with `kind`, `source`, `title`, `excerpt` and `score`,
+    /// and so are also available to templates as `memory`.
+    ///
+    /// # Returns
+    ///
+    /// The number of entries added.
+    pub fn recall_into(&self, context: &mut Context, query: &str, limit: usize) -> usize {
+        let memories = self
+            .query(query, limit)
+            .into_iter()
+            .map(|hit| {
+                serde_json::json!({
+                    "kind": hit.entry.kind,
+                    "source": hit.entry.source,
+                    "title": hit.entry.title,
+                    "excerpt": truncate(&hit.entry.text, MAX_EXCERPT_CHARS),
+                    "score": (hit.score * 100.0).round() / 100.0,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let count = memories.len();
+        if count > 0 {
+            context
+                .metadata
+                .insert(MEMORY_KEY.to_string(), serde_json::Value::Array(memories));
+        }
+        count
+    }
+
+    /// Write the index to `entries.jsonl`.
+    ///
+    /// The file is written atomically, so a crash never leaves a partial
+    /// index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    #[tracing::instrument(skip(self), fields(dir = %self.dir.display()))]
+    pub fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join("entries.jsonl");
+
+        let mut content = Vec::new();
+        for entry in &self.entries {
+            serde_json::to_writer(&mut content, entry)
+                .map_err(|source| IndexError::Entry { line: 0, source })?;
+            content.push(b'\n');
+        }
+        crate::atomic::write(&path, content)?;
+
+        tracing::debug!("Saved {} index entries", self.entries.len());
+        Ok(())
+    }
+}
+
+/// Get the text an entry is embedded from.
+fn entry_text(entry: &IndexEntry) -> String {
+    format!("{}\n{}", entry.title, entry.text)
+}
+
+/// Dot product of two vectors; the cosine similarity of normalized ones.
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// 64-bit FNV-1a hash.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Truncate text to at most `max_chars` characters.
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => text[..cut].to_string(),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashing_embedder_is_normalized_and_deterministic() {
+        let embedder = HashingEmbedder::new(64);
+        let vector = embedder.embed("Store sessions in Redis");
+
+        assert_eq!(vector.len(), 64);
+        let norm = dot(&vector, &vector);
+        assert!((norm - 1.0).abs() < 1e-5);
+        assert_eq!(vector, embedder.embed("store SESSIONS in redis"));
+        assert!(embedder.embed("").iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn test_index_query_ranks_related_entries_first() {
+        let embedder = HashingEmbedder::default();
+        let dir = std::env::temp_dir().join(format!("gba-test-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut index = RepoIndex::open(&dir, &embedder).unwrap();
+        index.upsert(IndexEntry::plan(
+            "0001",
+            "add-auth",
+            "Store user sessions in Redis with a 24 hour expiry",
+        ));
+        index.upsert(IndexEntry::plan(
+            "0002",
+            "dark-mode",
+            "Add a dark color theme toggle to the settings page",
+        ));
+
+        let hits = index.query("where are user sessions stored", 5);
+        assert_eq!(hits[0].entry.id, "plan:0001");
+        assert!(index.query("", 5).is_empty());
+
+        index.upsert(IndexEntry::plan("0001", "add-auth", "Use JWT tokens"));
+        assert_eq!(index.entries().len(), 2);
+        index.save().unwrap();
+
+        let reopened = RepoIndex::open(&dir, &embedder).unwrap();
+        assert_eq!(reopened.entries(), index.entries());
+        assert_eq!(reopened.query("jwt tokens", 1)[0].entry.id, "plan:0001");
+
+        let mut context = Context::default();
+        assert_eq!(reopened.recall_into(&mut context, "dark theme", 1), 1);
+        assert_eq!(context.metadata[MEMORY_KEY][0]["source"], "0002");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_index_reembeds_other_dimensions() {
+        let dir = std::env::temp_dir().join(format!("gba-test-index-dims-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let small = HashingEmbedder::new(16);
+        let mut index = RepoIndex::open(&dir, &small).unwrap();
+        index.upsert(IndexEntry::plan("0001", "add-auth", "sessions"));
+        index.save().unwrap();
+
+        let large = HashingEmbedder::new(128);
+        let mut index = RepoIndex::open(&dir, &large).unwrap();
+        assert_eq!(index.entries()[0].vector.len(), 128);
+        assert_eq!(index.remove_source(EntryKind::Plan, "0001"), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
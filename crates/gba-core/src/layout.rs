@@ -0,0 +1,340 @@
+//! Layout of the full prompt sent to the agent.
+//!
+//! The agent wraps each task prompt in a scaffold: the repository context
+//! with its files, an overview of the files left out, the metadata and
+//! finally the task. A [`PromptLayout`] holds these sections, and
+//! [`PromptLayout::render`] lays them out in the built-in format. A
+//! [`LayoutRenderer`] set with [`crate::Agent::with_layout_renderer`]
+//! replaces the built-in format, e.g. with the overridable `layout` template
+//! of the prompt manager.
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_core::Context;
+//! use gba_core::layout::PromptLayout;
+//!
+//! let context = Context {
+//!     repository_path: "/repo".into(),
+//!     branch: "main".to_string(),
+//!     ..Context::default()
+//! };
+//! let prompt = PromptLayout::new("Add a login page", &context).render();
+//! assert!(prompt.contains("Branch: main"));
+//! assert!(prompt.ends_with("## Task\n\nAdd a login page"));
+//! ```
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::context_budget::{self, ContextStage};
+use crate::error::Result;
+use crate::task::{Context, File};
+
+/// Sections of the full prompt of a task.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptLayout {
+    /// Repository path.
+    pub repository_path: String,
+
+    /// Branch the agent works on.
+    pub branch: String,
+
+    /// Files of the context.
+    pub files: Vec<LayoutFile>,
+
+    /// Whether the files are outlines rather than their full content.
+    pub outlines: bool,
+
+    /// Whether file contents are prefixed with their line numbers.
+    pub line_numbers: bool,
+
+    /// Overview of the repository replacing file contents left out to fit
+    /// the context window.
+    pub overview: Option<String>,
+
+    /// Metadata entries as key and JSON value, sorted by key.
+    pub metadata: Vec<(String, String)>,
+
+    /// Directory for the agent's intermediate files, if the run has one.
+    pub scratch_dir: Option<String>,
+
+    /// Task prompt.
+    pub task: String,
+}
+
+/// A file of the prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LayoutFile {
+    /// Path relative to the repository.
+    pub path: String,
+
+    /// Language tag of the fenced block, e.g. `rust`; empty if unknown.
+    pub language: String,
+
+    /// Fence of the block: three backticks, or more if the content holds a
+    /// run of backticks as long.
+    pub fence: String,
+
+    /// File content.
+    pub content: String,
+}
+
+impl LayoutFile {
+    /// Gather a file of the prompt.
+    fn new(file: &File) -> Self {
+        let language = if file.language == "unknown" {
+            String::new()
+        } else {
+            file.language.clone()
+        };
+        Self {
+            path: file.path.display().to_string(),
+            language,
+            fence: fence(&file.content),
+            content: file.content.clone(),
+        }
+    }
+}
+
+impl PromptLayout {
+    /// Gather the sections of a task's prompt.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - Task prompt.
+    /// * `context` - Task context.
+    #[must_use]
+    pub fn new(prompt: &str, context: &Context) -> Self {
+        let outlines = context.metadata.get(context_budget::STAGE_KEY)
+            == Some(&ContextStage::Outlines.to_string().into());
+        let overview = context
+            .metadata
+            .get(context_budget::OVERVIEW_KEY)
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string);
+        let mut metadata = context
+            .metadata
+            .iter()
+            .filter(|(key, _)| *key != context_budget::OVERVIEW_KEY)
+            .map(|(key, value)| (key.clone(), value.to_string()))
+            .collect::<Vec<_>>();
+        metadata.sort();
+
+        Self {
+            repository_path: context.repository_path.display().to_string(),
+            branch: context.branch.clone(),
+            files: context.files.iter().map(LayoutFile::new).collect(),
+            outlines,
+            line_numbers: false,
+            overview,
+            metadata,
+            scratch_dir: None,
+            task: prompt.to_string(),
+        }
+    }
+
+    /// Prefix each line of the file contents with its line number, so
+    /// findings can reference lines.
+    ///
+    /// Outlines keep their content, their lines not being the file's.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to number lines.
+    #[must_use]
+    pub fn with_line_numbers(mut self, enabled: bool) -> Self {
+        if !enabled || self.outlines || self.line_numbers {
+            return self;
+        }
+        for file in &mut self.files {
+            file.content = number_lines(&file.content);
+        }
+        self.line_numbers = true;
+        self
+    }
+
+    /// Point the agent to the scratch directory of its run.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Scratch directory, see [`crate::scratch::ScratchDir`].
+    #[must_use]
+    pub fn with_scratch_dir(mut self, path: &std::path::Path) -> Self {
+        self.scratch_dir = Some(path.display().to_string());
+        self
+    }
+
+    /// Lay out the prompt in the built-in format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut prompt = String::new();
+
+        prompt.push_str("\n## Repository Context\n\n");
+        prompt.push_str(&format!("Repository path: {}\n", self.repository_path));
+        prompt.push_str(&format!("Branch: {}\n", self.branch));
+        if self.files.is_empty() {
+            prompt.push('\n');
+        } else {
+            prompt.push_str(&format!("Files: {}", self.files.len()));
+            if self.outlines {
+                prompt.push_str(" (outlines only; read a file for its full content)");
+            }
+            prompt.push_str("\n\n");
+            for file in &self.files {
+                prompt.push_str(&format!(
+                    "### {}\n\n{}{}\n{}\n{}\n\n",
+                    file.path, file.fence, file.language, file.content, file.fence
+                ));
+            }
+        }
+
+        // A degraded context summarizes the files instead
+        if let Some(overview) = &self.overview {
+            prompt.push_str("\n## Repository Overview\n\n");
+            prompt.push_str("File contents were left out to fit the context window; ");
+            prompt.push_str("read the files you need.\n\n");
+            prompt.push_str(&format!("```\n{overview}\n```\n\n"));
+        }
+
+        if !self.metadata.is_empty() {
+            prompt.push_str("\n## Metadata\n\n");
+            for (key, value) in &self.metadata {
+                prompt.push_str(&format!("{key}: {value}\n"));
+            }
+            prompt.push('\n');
+        }
+
+        if let Some(scratch_dir) = &self.scratch_dir {
+            prompt.push_str("\n## Scratch Directory\n\n");
+            prompt.push_str(&format!(
+                "Write intermediate files, such as experiments, notes and downloads, \
+                 to {scratch_dir} instead of the repository.\n\n"
+            ));
+        }
+
+        prompt.push_str("\n## Task\n\n");
+        prompt.push_str(&self.task);
+        prompt
+    }
+}
+
+/// Get the fence for a content: one backtick longer than its longest run
+/// of backticks, and at least three.
+fn fence(content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat((longest + 1).max(3))
+}
+
+/// Prefix each line with its number, right-aligned, e.g. `" 9 | fn main()"`.
+fn number_lines(content: &str) -> String {
+    let width = content.lines().count().to_string().len();
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let number = index + 1;
+            if line.is_empty() {
+                format!("{number:>width$} |")
+            } else {
+                format!("{number:>width$} | {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lays out the full prompt of a task in place of the built-in format.
+pub trait LayoutRenderer: Send + Sync + fmt::Debug {
+    /// Lay out a prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt cannot be laid out, e.g. its template
+    /// fails to render.
+    fn render(&self, layout: &PromptLayout) -> Result<String>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_layout_sorts_metadata_and_extracts_overview() {
+        let mut context = Context {
+            repository_path: PathBuf::from("/repo"),
+            branch: "main".to_string(),
+            files: vec![File {
+                path: PathBuf::from("src/lib.rs"),
+                content: "pub fn login() {}".to_string(),
+                language: "rust".to_string(),
+            }],
+            ..Context::default()
+        };
+        context
+            .metadata
+            .insert("phase".to_string(), "review".into());
+        context.metadata.insert("attempt".to_string(), 2.into());
+        context.metadata.insert(
+            context_budget::OVERVIEW_KEY.to_string(),
+            "src/ (1 file)".into(),
+        );
+
+        let layout = PromptLayout::new("Review", &context);
+        assert_eq!(
+            layout.metadata,
+            vec![
+                ("attempt".to_string(), "2".to_string()),
+                ("phase".to_string(), "\"review\"".to_string()),
+            ]
+        );
+        assert_eq!(layout.overview.as_deref(), Some("src/ (1 file)"));
+
+        let prompt = layout.render();
+        assert!(prompt.contains("Files: 1\n\n### src/lib.rs\n\n```rust\npub fn login() {}\n```"));
+        assert!(prompt.contains("## Repository Overview"));
+        assert!(prompt.contains("attempt: 2\nphase: \"review\"\n"));
+    }
+
+    #[test]
+    fn test_layout_fences_and_line_numbers() {
+        let file = |path: &str, content: &str, language: &str| File {
+            path: PathBuf::from(path),
+            content: content.to_string(),
+            language: language.to_string(),
+        };
+        let context = Context {
+            files: vec![
+                file("README.md", "# Demo\n\n```sh\nmake\n```", "markdown"),
+                file("LICENSE", "MIT", "unknown"),
+                file("src/main.rs", &"fn main() {}\n".repeat(10), "rust"),
+            ],
+            ..Context::default()
+        };
+
+        let layout = PromptLayout::new("Review", &context).with_line_numbers(true);
+        assert!(layout.line_numbers);
+        assert_eq!(layout.files[0].fence, "````");
+        assert_eq!(layout.files[1].language, "");
+        assert_eq!(layout.files[1].content, "1 | MIT");
+
+        let prompt = layout.render();
+        assert!(prompt.contains("### README.md\n\n````markdown\n1 | # Demo\n2 |\n3 | ```sh\n"));
+        assert!(prompt.contains("### LICENSE\n\n```\n1 | MIT\n```"));
+        assert!(prompt.contains("```rust\n 1 | fn main() {}\n"));
+        assert!(prompt.contains("\n10 | fn main() {}\n```"));
+
+        // Outlines are not the file's lines
+        let mut context = context;
+        context.metadata.insert(
+            context_budget::STAGE_KEY.to_string(),
+            ContextStage::Outlines.to_string().into(),
+        );
+        let layout = PromptLayout::new("Review", &context).with_line_numbers(true);
+        assert!(!layout.line_numbers);
+        assert_eq!(layout.files[1].content, "MIT");
+    }
+}
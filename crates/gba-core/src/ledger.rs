@@ -0,0 +1,424 @@
+//! Append-only cost ledger in `.gba/ledger.jsonl`.
+//!
+//! Every run appends one JSON line with its usage. The ledger is kept apart
+//! from the feature state files, so the record survives when features are
+//! cleaned up. Entries are only ever appended, never rewritten.
+//!
+//! Entries carry the cost attribution tags of their run, e.g. `sprint-42` or
+//! `team:payments`, so [`breakdown`] can split the spend by team or
+//! initiative for chargeback.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::task::Usage;
+
+/// Result type alias for ledger operations.
+pub type Result<T> = std::result::Result<T, LedgerError>;
+
+/// Error types for ledger operations.
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A ledger line could not be serialized or parsed.
+    #[error("Invalid ledger entry at line {line}: {source}")]
+    Entry {
+        /// Line number, starting at 1 (0 when serializing).
+        line: usize,
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+}
+
+/// Usage of a single run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    /// When the run finished.
+    pub timestamp: DateTime<Utc>,
+
+    /// Run identifier, e.g. `"20260224T103000Z-1a2b"`.
+    #[serde(default)]
+    pub run_id: Option<String>,
+
+    /// Feature identifier, e.g. `"0003"`.
+    pub feature_id: String,
+
+    /// Feature name.
+    pub feature: String,
+
+    /// Task kind, e.g. `"implementation"`.
+    pub kind: String,
+
+    /// Model used for the run.
+    pub model: String,
+
+    /// Input tokens used.
+    pub input_tokens: u64,
+
+    /// Output tokens used.
+    pub output_tokens: u64,
+
+    /// Total cost in USD.
+    pub total_cost_usd: f64,
+
+    /// Cost attribution tags of the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl LedgerEntry {
+    /// Create an entry for a run that finished now.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature_id` - Feature identifier.
+    /// * `feature` - Feature name.
+    /// * `kind` - Task kind.
+    /// * `model` - Model used for the run.
+    /// * `usage` - Usage of the run.
+    #[must_use]
+    pub fn new(
+        feature_id: impl Into<String>,
+        feature: impl Into<String>,
+        kind: impl Into<String>,
+        model: impl Into<String>,
+        usage: &Usage,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            run_id: None,
+            feature_id: feature_id.into(),
+            feature: feature.into(),
+            kind: kind.into(),
+            model: model.into(),
+            input_tokens: u64::from(usage.input_tokens),
+            output_tokens: u64::from(usage.output_tokens),
+            total_cost_usd: usage.total_cost_usd,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Set the run identifier.
+    #[must_use]
+    pub fn with_run_id(mut self, run_id: Option<String>) -> Self {
+        self.run_id = run_id;
+        self
+    }
+
+    /// Set the cost attribution tags.
+    #[must_use]
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+}
+
+/// Combine the project's tags with the tags of a run.
+///
+/// Blank tags are dropped and duplicates kept once, project tags first.
+#[must_use]
+pub fn merge_tags(project: &[String], run: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in project.iter().chain(run) {
+        let tag = tag.trim();
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Key the spend of a ledger is grouped by, see [`breakdown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CostGroup {
+    /// Feature name.
+    Feature,
+    /// Task kind.
+    Kind,
+    /// Model.
+    Model,
+    /// Cost attribution tag.
+    Tag,
+    /// UTC day the run finished, e.g. `2026-02-24`.
+    Day,
+}
+
+impl fmt::Display for CostGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Feature => write!(f, "feature"),
+            Self::Kind => write!(f, "kind"),
+            Self::Model => write!(f, "model"),
+            Self::Tag => write!(f, "tag"),
+            Self::Day => write!(f, "day"),
+        }
+    }
+}
+
+/// Label of the group of entries without tags.
+pub const UNTAGGED: &str = "(untagged)";
+
+/// Spend of a group of ledger entries.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostBreakdown {
+    /// Group key, e.g. the feature name or tag.
+    pub key: String,
+
+    /// Number of runs.
+    pub runs: usize,
+
+    /// Input tokens used.
+    pub input_tokens: u64,
+
+    /// Output tokens used.
+    pub output_tokens: u64,
+
+    /// Total cost in USD.
+    pub total_cost_usd: f64,
+}
+
+/// Break the spend of ledger entries down by a key, most expensive first,
+/// or by day, in order.
+///
+/// Grouped by tag, an entry with several tags counts towards each of them,
+/// so the groups can add up to more than the total; entries without tags
+/// are grouped under [`UNTAGGED`].
+#[must_use]
+pub fn breakdown(entries: &[LedgerEntry], group: CostGroup) -> Vec<CostBreakdown> {
+    let mut groups: BTreeMap<String, CostBreakdown> = BTreeMap::new();
+    for entry in entries {
+        let keys = match group {
+            CostGroup::Feature => vec![entry.feature.clone()],
+            CostGroup::Kind => vec![entry.kind.clone()],
+            CostGroup::Model => vec![entry.model.clone()],
+            CostGroup::Tag if entry.tags.is_empty() => vec![UNTAGGED.to_string()],
+            CostGroup::Tag => entry.tags.clone(),
+            CostGroup::Day => vec![entry.timestamp.format("%Y-%m-%d").to_string()],
+        };
+        for key in keys {
+            let row = groups.entry(key).or_insert_with_key(|key| CostBreakdown {
+                key: key.clone(),
+                ..CostBreakdown::default()
+            });
+            row.runs += 1;
+            row.input_tokens += entry.input_tokens;
+            row.output_tokens += entry.output_tokens;
+            row.total_cost_usd += entry.total_cost_usd;
+        }
+    }
+
+    let mut rows: Vec<CostBreakdown> = groups.into_values().collect();
+    // Stable, so groups of the same cost stay sorted by key
+    if group != CostGroup::Day {
+        rows.sort_by(|a, b| b.total_cost_usd.total_cmp(&a.total_cost_usd));
+    }
+    rows
+}
+
+/// Append-only ledger file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba_core::ledger::{Ledger, LedgerEntry};
+/// use gba_core::task::Usage;
+///
+/// let ledger = Ledger::new(".gba/ledger.jsonl");
+/// let entry = LedgerEntry::new("0003", "add-auth", "implementation", "sonnet", &Usage::default());
+/// ledger.append(&entry)?;
+///
+/// let total: f64 = ledger.entries()?.iter().map(|e| e.total_cost_usd).sum();
+/// println!("Total cost: ${total:.2}");
+/// # Ok::<(), gba_core::ledger::LedgerError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    /// Path of the ledger file.
+    path: PathBuf,
+}
+
+impl Ledger {
+    /// Create a ledger backed by the given file.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Get the path of the ledger file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry, creating the file if needed.
+    ///
+    /// The line is written with a single append-mode write and synced to
+    /// disk, so concurrent runs never interleave or overwrite entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be written.
+    #[tracing::instrument(skip(self, entry))]
+    pub fn append(&self, entry: &LedgerEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(entry)
+            .map_err(|source| LedgerError::Entry { line: 0, source })?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        tracing::debug!("Appended ledger entry to {}", self.path.display());
+        Ok(())
+    }
+
+    /// Read all entries, oldest first.
+    ///
+    /// A missing ledger file has no entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or a line is invalid.
+    pub fn entries(&self) -> Result<Vec<LedgerEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| LedgerError::Entry {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_append_and_read() {
+        let dir = std::env::temp_dir().join(format!("gba-test-ledger-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let ledger = Ledger::new(dir.join(".gba").join("ledger.jsonl"));
+        assert!(ledger.entries().unwrap().is_empty());
+
+        let usage = Usage {
+            input_tokens: 1200,
+            output_tokens: 300,
+            total_cost_usd: 0.42,
+        };
+        let first = LedgerEntry::new("0003", "add-auth", "planning", "sonnet", &usage)
+            .with_run_id(Some("20260224T103000Z-1a2b".to_string()));
+        let second = LedgerEntry::new("0003", "add-auth", "implementation", "sonnet", &usage);
+        ledger.append(&first).unwrap();
+        ledger.append(&second).unwrap();
+
+        let entries = ledger.entries().unwrap();
+        assert_eq!(entries, vec![first, second]);
+
+        std::fs::write(ledger.path(), "{\"broken\": true}\n").unwrap();
+        assert!(matches!(
+            ledger.entries(),
+            Err(LedgerError::Entry { line: 1, .. })
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_merge_tags() {
+        let project = vec!["team:payments".to_string(), " ".to_string()];
+        let run = vec!["sprint-42".to_string(), "team:payments".to_string()];
+        assert_eq!(merge_tags(&project, &run), ["team:payments", "sprint-42"]);
+    }
+
+    #[test]
+    fn test_breakdown_by_tag() {
+        let usage = |cost| Usage {
+            input_tokens: 100,
+            output_tokens: 10,
+            total_cost_usd: cost,
+        };
+        let entries = vec![
+            LedgerEntry::new("0001", "add-auth", "planning", "sonnet", &usage(1.0))
+                .with_tags(vec!["team:payments".to_string(), "sprint-42".to_string()]),
+            LedgerEntry::new("0001", "add-auth", "implementation", "sonnet", &usage(2.0))
+                .with_tags(vec!["team:payments".to_string()]),
+            LedgerEntry::new("0002", "fix-login", "planning", "opus", &usage(0.5)),
+        ];
+
+        let rows = breakdown(&entries, CostGroup::Tag);
+        let summary = rows
+            .iter()
+            .map(|row| (row.key.as_str(), row.runs, row.total_cost_usd))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("team:payments", 2, 3.0),
+                ("sprint-42", 1, 1.0),
+                (UNTAGGED, 1, 0.5)
+            ]
+        );
+        assert_eq!(rows[0].input_tokens, 200);
+
+        let rows = breakdown(&entries, CostGroup::Feature);
+        assert_eq!(rows[0].key, "add-auth");
+        assert_eq!(rows[1].key, "fix-login");
+    }
+
+    #[test]
+    fn test_breakdown_by_day() {
+        let usage = |cost| Usage {
+            total_cost_usd: cost,
+            ..Usage::default()
+        };
+        let at = |day: &str, cost| {
+            let mut entry =
+                LedgerEntry::new("0001", "add-auth", "planning", "sonnet", &usage(cost));
+            entry.timestamp = format!("{day}T12:00:00Z").parse().unwrap();
+            entry
+        };
+        let entries = vec![
+            at("2026-02-25", 0.5),
+            at("2026-02-24", 1.0),
+            at("2026-02-25", 2.0),
+        ];
+
+        let rows = breakdown(&entries, CostGroup::Day);
+        let summary = rows
+            .iter()
+            .map(|row| (row.key.as_str(), row.runs, row.total_cost_usd))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, [("2026-02-24", 1, 1.0), ("2026-02-25", 2, 2.5)]);
+    }
+}
@@ -6,20 +6,65 @@
 #![warn(rust_2024_compatibility, missing_docs, missing_debug_implementations)]
 
 pub mod agent;
+pub mod atomic;
+pub mod audit;
+pub mod cargo;
+pub mod compare;
 pub mod config;
+pub mod context_budget;
 pub mod context_builder;
+pub mod diff;
 pub mod error;
+pub mod events;
+pub mod feature;
+pub mod git;
+pub mod index;
+pub mod injection;
+pub mod layout;
+pub mod ledger;
+pub mod lock;
+pub mod metrics;
+pub mod models;
+pub mod plan;
+pub mod pool;
+pub mod postprocess;
+pub mod protect;
+pub mod pull_request;
+pub mod quota;
+pub mod review;
+pub mod sandbox;
+pub mod scratch;
+pub mod session;
+pub mod state;
+pub mod state_check;
+pub mod store;
+pub mod stream;
 pub mod task;
+pub mod task_kind;
+pub mod transcript;
+pub mod vcs;
+pub mod vendored;
+pub mod verify;
+pub mod worktree;
 
 pub use agent::Agent;
 pub use config::{
-    AgentConfig, ConfigError, LimitsConfig, LoggingConfig, ProjectConfig, ProjectMetadata,
-    PromptsConfig, RepositoryConfig, RepositoryMetadata, WorktreeConfig,
+    AgentConfig, ConfigError, ConfigProblem, ConstraintsConfig, ContextConfig, FixLoopConfig,
+    GitHubConfig, IndexConfig, LimitsConfig, LoggingConfig, OutputConstraint, PermissionMode,
+    PostProcessConfig, PreCommitConfig, ProjectConfig, ProjectMetadata, PromptsConfig, PrunePolicy,
+    QuotaConfig, RepositoryConfig, RepositoryMetadata, ReviewConfig, SandboxConfig, ScratchConfig,
+    ScratchRetention, SparseCheckoutConfig, TuiConfig, TuiKeyBindings, VerificationCommand,
+    VerificationConfig, WebhookConfig, WorktreeConfig,
 };
 pub use error::{CoreError, Result};
+pub use metrics::Metrics;
+pub use state::{FeatureState, StateError};
 pub use task::{Context, Response, Task};
+pub use worktree::{StaleReason, StaleWorktree, Worktree, WorktreeError, WorktreeManager};
 
 /// Re-export common types for convenience.
 pub mod prelude {
-    pub use crate::{Agent, AgentConfig, Context, CoreError, ProjectConfig, Response, Result, Task};
+    pub use crate::{
+        Agent, AgentConfig, Context, CoreError, ProjectConfig, Response, Result, Task,
+    };
 }
@@ -0,0 +1,319 @@
+//! Per-feature run lock in `.gba/features/<id>/lock`.
+//!
+//! Two runs of the same feature, e.g. started from two terminals, would
+//! overwrite each other's state and edit the same worktree. A run therefore
+//! holds the feature's lock file while it executes. The file records the
+//! process and time the lock was acquired, and is removed when the
+//! [`FeatureLock`] is dropped.
+//!
+//! A lock left behind by a process that no longer runs, e.g. after a crash,
+//! is stale: it is replaced on the next acquisition with a warning. The
+//! lock file is published with its content, see [`crate::atomic::create_new`],
+//! so a lock file that cannot be parsed was not written by a run; it is only
+//! considered stale once it is [`UNREADABLE_GRACE_SECS`] old.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::lock::FeatureLock;
+//!
+//! let lock = FeatureLock::acquire(".gba/features/0003/lock", "implementation")?;
+//! // ... run the task ...
+//! drop(lock);
+//! # Ok::<(), gba_core::lock::LockError>(())
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// File name of the lock in a feature directory.
+pub const LOCK_FILE: &str = "lock";
+
+/// Age in seconds after which a lock file that cannot be parsed is stale.
+pub const UNREADABLE_GRACE_SECS: i64 = 60;
+
+/// Result type alias for lock operations.
+pub type Result<T> = std::result::Result<T, LockError>;
+
+/// Error types for lock operations.
+#[derive(Debug, Error)]
+pub enum LockError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The lock is held by a running process.
+    #[error(
+        "Feature is locked by process {} ({}) since {}; remove {} if that run is gone",
+        .holder.pid, .holder.purpose, .holder.acquired_at.to_rfc3339(), .path.display()
+    )]
+    Held {
+        /// Lock file.
+        path: PathBuf,
+        /// The holder of the lock.
+        holder: LockInfo,
+    },
+}
+
+/// Contents of a lock file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockInfo {
+    /// Process holding the lock.
+    pub pid: u32,
+
+    /// What the lock is held for, e.g. `"implementation"`.
+    pub purpose: String,
+
+    /// When the lock was acquired.
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl LockInfo {
+    /// Read the lock file at a path.
+    ///
+    /// Returns `None` if there is no lock. A lock file that cannot be parsed,
+    /// e.g. one written by hand, is reported with pid 0 and the time it was
+    /// last modified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read.
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if let Ok(info) = serde_json::from_str(&content) {
+            return Ok(Some(info));
+        }
+        let modified = match std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified.into(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(Self {
+            pid: 0,
+            purpose: "unknown".to_string(),
+            acquired_at: modified,
+        }))
+    }
+
+    /// Check whether the lock is stale: the holding process no longer runs,
+    /// or the lock file cannot be parsed and is older than
+    /// [`UNREADABLE_GRACE_SECS`].
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        if self.pid == 0 {
+            return (Utc::now() - self.acquired_at).num_seconds() >= UNREADABLE_GRACE_SECS;
+        }
+        !process_running(self.pid)
+    }
+}
+
+/// A held feature lock, released when dropped.
+#[derive(Debug)]
+pub struct FeatureLock {
+    /// Lock file.
+    path: PathBuf,
+    /// Contents of the lock file.
+    info: LockInfo,
+}
+
+impl FeatureLock {
+    /// Acquire the lock file at a path, replacing a stale lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Lock file, e.g. `.gba/features/0003/lock`.
+    /// * `purpose` - What the lock is held for, shown to other runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Held`] if a running process holds the lock, or an
+    /// IO error if the lock file cannot be written.
+    pub fn acquire(path: impl Into<PathBuf>, purpose: &str) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let info = LockInfo {
+            pid: std::process::id(),
+            purpose: purpose.to_string(),
+            acquired_at: Utc::now(),
+        };
+        let content = serde_json::to_string(&info).map_err(std::io::Error::other)?;
+
+        // A second attempt after removing a stale lock; losing that race to
+        // another process is reported as held
+        for _ in 0..2 {
+            match crate::atomic::create_new(&path, &content) {
+                Ok(()) => {
+                    debug!("Acquired lock {}", path.display());
+                    return Ok(Self { path, info });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let Some(holder) = LockInfo::read(&path)? else {
+                        continue;
+                    };
+                    if !holder.is_stale() {
+                        return Err(LockError::Held { path, holder });
+                    }
+                    warn!(
+                        "Replacing stale lock {} of process {} ({})",
+                        path.display(),
+                        holder.pid,
+                        holder.purpose
+                    );
+                    match std::fs::remove_file(&path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let holder = LockInfo::read(&path)?.unwrap_or_else(|| info.clone());
+        Err(LockError::Held { path, holder })
+    }
+
+    /// Get the lock file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the contents of the lock file.
+    #[must_use]
+    pub fn info(&self) -> &LockInfo {
+        &self.info
+    }
+}
+
+impl Drop for FeatureLock {
+    fn drop(&mut self) {
+        // Only remove the file if it is still ours
+        if LockInfo::read(&self.path).ok().flatten().as_ref() != Some(&self.info) {
+            return;
+        }
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => debug!("Released lock {}", self.path.display()),
+            Err(e) => warn!("Failed to release lock {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// Check whether a process runs on this machine.
+#[cfg(target_os = "linux")]
+fn process_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Check whether a process runs on this machine.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_running(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Check whether a process runs on this machine.
+///
+/// Without a way to check, the process is assumed to run, so locks are only
+/// released by their holder or by hand.
+#[cfg(not(unix))]
+fn process_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_and_released_on_drop() {
+        let dir = std::env::temp_dir().join(format!("gba-test-lock-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("0001").join(LOCK_FILE);
+
+        let lock = FeatureLock::acquire(&path, "implementation").unwrap();
+        assert_eq!(lock.info().pid, std::process::id());
+        assert_eq!(LockInfo::read(&path).unwrap().as_ref(), Some(lock.info()));
+
+        let err = FeatureLock::acquire(&path, "review").unwrap_err();
+        assert!(matches!(
+            err,
+            LockError::Held { ref holder, .. } if holder.purpose == "implementation"
+        ));
+
+        drop(lock);
+        assert!(!path.exists());
+        assert!(LockInfo::read(&path).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stale_lock_is_replaced() {
+        let dir = std::env::temp_dir().join(format!("gba-test-lock-stale-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LOCK_FILE);
+
+        // A process that no longer runs
+        let holder = LockInfo {
+            pid: u32::MAX,
+            purpose: "implementation".to_string(),
+            acquired_at: Utc::now(),
+        };
+        std::fs::write(&path, serde_json::to_string(&holder).unwrap()).unwrap();
+        assert!(LockInfo::read(&path).unwrap().unwrap().is_stale());
+
+        let lock = FeatureLock::acquire(&path, "planning").unwrap();
+        assert_eq!(lock.info().purpose, "planning");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_should_not_reclaim_fresh_unparsable_lock() {
+        let dir = std::env::temp_dir().join(format!("gba-test-lock-empty-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LOCK_FILE);
+
+        std::fs::write(&path, "").unwrap();
+        let holder = LockInfo::read(&path).unwrap().unwrap();
+        assert_eq!(holder.pid, 0);
+        assert!(!holder.is_stale());
+        assert!(matches!(
+            FeatureLock::acquire(&path, "planning"),
+            Err(LockError::Held { .. })
+        ));
+        assert!(path.exists());
+
+        // Past the grace period, it is reclaimed
+        let old = std::time::SystemTime::now()
+            - std::time::Duration::from_secs(UNREADABLE_GRACE_SECS as u64 + 1);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert!(LockInfo::read(&path).unwrap().unwrap().is_stale());
+        let lock = FeatureLock::acquire(&path, "planning").unwrap();
+        assert_eq!(lock.info().purpose, "planning");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,283 @@
+//! In-process task metrics.
+//!
+//! [`Metrics`] is a cheaply cloneable handle that collects counters and
+//! duration histograms per task phase. Embedders can read a [`snapshot`] or
+//! render the metrics in the Prometheus text exposition format to serve
+//! them from their own `/metrics` endpoint.
+//!
+//! [`snapshot`]: Metrics::snapshot
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::task::Usage;
+
+/// Upper bounds of the task duration histogram buckets, in seconds.
+pub const DURATION_BUCKETS: [f64; 10] = [
+    1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+
+/// Metrics of a single task phase.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhaseMetrics {
+    /// Tasks started.
+    pub started: u64,
+    /// Tasks that succeeded.
+    pub succeeded: u64,
+    /// Tasks that failed.
+    pub failed: u64,
+    /// Retried attempts.
+    pub retries: u64,
+    /// Input tokens used.
+    pub input_tokens: u64,
+    /// Output tokens used.
+    pub output_tokens: u64,
+    /// Total cost in USD.
+    pub cost_usd: f64,
+    /// Task durations.
+    pub duration: Histogram,
+}
+
+/// A histogram with the fixed task duration buckets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Cumulative count of observations per bucket in [`DURATION_BUCKETS`].
+    pub buckets: [u64; DURATION_BUCKETS.len()],
+    /// Number of observations.
+    pub count: u64,
+    /// Sum of observed values.
+    pub sum: f64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; DURATION_BUCKETS.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Record an observation.
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// Handle to in-process task metrics.
+///
+/// Clones share the same underlying metrics.
+///
+/// # Examples
+///
+/// ```
+/// use gba_core::metrics::Metrics;
+/// use gba_core::task::Usage;
+/// use std::time::Duration;
+///
+/// let metrics = Metrics::new();
+/// metrics.task_started("implementation");
+/// metrics.task_succeeded("implementation", &Usage::default(), Duration::from_secs(42));
+///
+/// assert_eq!(metrics.snapshot()["implementation"].succeeded, 1);
+/// println!("{}", metrics.render_prometheus());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// Metrics per phase.
+    phases: Arc<Mutex<BTreeMap<String, PhaseMetrics>>>,
+}
+
+impl Metrics {
+    /// Create an empty metrics handle.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that a task started.
+    pub fn task_started(&self, phase: &str) {
+        self.update(phase, |m| m.started += 1);
+    }
+
+    /// Record that a task succeeded, with its usage and duration.
+    pub fn task_succeeded(&self, phase: &str, usage: &Usage, duration: Duration) {
+        self.update(phase, |m| {
+            m.succeeded += 1;
+            m.input_tokens += u64::from(usage.input_tokens);
+            m.output_tokens += u64::from(usage.output_tokens);
+            m.cost_usd += usage.total_cost_usd;
+            m.duration.observe(duration.as_secs_f64());
+        });
+    }
+
+    /// Record that a task failed after the given duration.
+    pub fn task_failed(&self, phase: &str, duration: Duration) {
+        self.update(phase, |m| {
+            m.failed += 1;
+            m.duration.observe(duration.as_secs_f64());
+        });
+    }
+
+    /// Record a retried attempt.
+    pub fn retry(&self, phase: &str) {
+        self.update(phase, |m| m.retries += 1);
+    }
+
+    /// Get a copy of the current metrics, keyed by phase.
+    #[must_use]
+    pub fn snapshot(&self) -> BTreeMap<String, PhaseMetrics> {
+        self.phases
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    #[must_use]
+    pub fn render_prometheus(&self) -> String {
+        let phases = self.snapshot();
+        let mut out = String::new();
+
+        let counters: [Counter; 5] = [
+            ("gba_tasks_started_total", "Tasks started.", |m| {
+                m.started.to_string()
+            }),
+            ("gba_tasks_succeeded_total", "Tasks that succeeded.", |m| {
+                m.succeeded.to_string()
+            }),
+            ("gba_tasks_failed_total", "Tasks that failed.", |m| {
+                m.failed.to_string()
+            }),
+            ("gba_task_retries_total", "Retried task attempts.", |m| {
+                m.retries.to_string()
+            }),
+            ("gba_cost_usd_total", "Total cost in USD.", |m| {
+                m.cost_usd.to_string()
+            }),
+        ];
+        for (name, help, value) in counters {
+            write_header(&mut out, name, help, "counter");
+            for (phase, m) in &phases {
+                let _ = writeln!(out, "{name}{{phase=\"{phase}\"}} {}", value(m));
+            }
+        }
+
+        let name = "gba_tokens_total";
+        write_header(&mut out, name, "Tokens used.", "counter");
+        for (phase, m) in &phases {
+            for (direction, tokens) in [("input", m.input_tokens), ("output", m.output_tokens)] {
+                let _ = writeln!(
+                    out,
+                    "{name}{{phase=\"{phase}\",direction=\"{direction}\"}} {tokens}"
+                );
+            }
+        }
+
+        let name = "gba_task_duration_seconds";
+        write_header(&mut out, name, "Task duration in seconds.", "histogram");
+        for (phase, m) in &phases {
+            for (count, bound) in m.duration.buckets.iter().zip(DURATION_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "{name}_bucket{{phase=\"{phase}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let count = m.duration.count;
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{phase=\"{phase}\",le=\"+Inf\"}} {count}"
+            );
+            let _ = writeln!(out, "{name}_sum{{phase=\"{phase}\"}} {}", m.duration.sum);
+            let _ = writeln!(out, "{name}_count{{phase=\"{phase}\"}} {count}");
+        }
+
+        out
+    }
+
+    /// Apply an update to the metrics of a phase.
+    fn update(&self, phase: &str, f: impl FnOnce(&mut PhaseMetrics)) {
+        let mut phases = self.phases.lock().unwrap_or_else(PoisonError::into_inner);
+        f(phases.entry(phase.to_string()).or_default());
+    }
+}
+
+/// A per-phase counter: name, help text and value accessor.
+type Counter = (&'static str, &'static str, fn(&PhaseMetrics) -> String);
+
+/// Write the `HELP` and `TYPE` lines of a metric.
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_record() {
+        let metrics = Metrics::new();
+        let shared = metrics.clone();
+        let usage = Usage {
+            input_tokens: 100,
+            output_tokens: 20,
+            total_cost_usd: 0.5,
+        };
+
+        metrics.task_started("planning");
+        shared.task_succeeded("planning", &usage, Duration::from_secs(10));
+        metrics.task_started("planning");
+        metrics.retry("planning");
+        metrics.task_failed("planning", Duration::from_secs(700));
+
+        let planning = &metrics.snapshot()["planning"];
+        assert_eq!(planning.started, 2);
+        assert_eq!((planning.succeeded, planning.failed), (1, 1));
+        assert_eq!(planning.retries, 1);
+        assert_eq!(planning.input_tokens, 100);
+        assert_eq!(planning.duration.count, 2);
+        // 10s falls in the 15s bucket, 700s only in the 1800s bucket
+        assert_eq!(planning.duration.buckets[1], 0);
+        assert_eq!(planning.duration.buckets[2], 1);
+        assert_eq!(planning.duration.buckets[8], 2);
+    }
+
+    #[test]
+    fn test_metrics_render_prometheus() {
+        let metrics = Metrics::new();
+        metrics.task_started("verification");
+        metrics.task_succeeded(
+            "verification",
+            &Usage {
+                input_tokens: 7,
+                output_tokens: 3,
+                total_cost_usd: 0.25,
+            },
+            Duration::from_secs(2),
+        );
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE gba_tasks_started_total counter"));
+        assert!(text.contains("gba_tasks_succeeded_total{phase=\"verification\"} 1"));
+        assert!(text.contains("gba_tokens_total{phase=\"verification\",direction=\"output\"} 3"));
+        assert!(text.contains("gba_cost_usd_total{phase=\"verification\"} 0.25"));
+        assert!(
+            text.contains("gba_task_duration_seconds_bucket{phase=\"verification\",le=\"1\"} 0")
+        );
+        assert!(
+            text.contains("gba_task_duration_seconds_bucket{phase=\"verification\",le=\"5\"} 1")
+        );
+        assert!(text.contains("gba_task_duration_seconds_count{phase=\"verification\"} 1"));
+    }
+}
@@ -0,0 +1,252 @@
+//! Model capabilities: context windows, output limits and pricing.
+//!
+//! [`ModelRegistry::builtin`] knows the current Claude models. Projects
+//! override or extend it under `models` in `.gba/config.yml`, e.g. to add a
+//! newly released model or negotiated pricing:
+//!
+//! ```yaml
+//! models:
+//!   - id: "claude-sonnet-4-20250514"
+//!     contextWindow: 1000000
+//!     maxOutputTokens: 64000
+//!     inputCostPerMtok: 6.0
+//!     outputCostPerMtok: 22.5
+//! ```
+//!
+//! The registry is the single source for token budgeting, cost estimation
+//! and validation of `agent.model`.
+
+use serde::{Deserialize, Serialize};
+
+/// Capabilities and pricing of a model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    /// Model identifier, e.g. `"claude-sonnet-4-20250514"`.
+    pub id: String,
+
+    /// Other names the model is known by, e.g. `"sonnet"`.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+
+    /// Context window in tokens.
+    pub context_window: u32,
+
+    /// Maximum output tokens per response.
+    pub max_output_tokens: u32,
+
+    /// Price of one million input tokens in USD.
+    pub input_cost_per_mtok: f64,
+
+    /// Price of one million output tokens in USD.
+    pub output_cost_per_mtok: f64,
+}
+
+impl ModelInfo {
+    /// Check whether the model is known by the given name.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        self.id == name || self.aliases.iter().any(|alias| alias == name)
+    }
+
+    /// Estimate the cost of a request in USD.
+    #[must_use]
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let (input, output) = (input_tokens as f64, output_tokens as f64);
+        (input * self.input_cost_per_mtok + output * self.output_cost_per_mtok) / 1_000_000.0
+    }
+
+    /// Get the tokens left for input once `max_tokens` are reserved for the
+    /// response.
+    #[must_use]
+    pub const fn input_budget(&self, max_tokens: u32) -> u32 {
+        self.context_window.saturating_sub(max_tokens)
+    }
+}
+
+/// Table of known models.
+///
+/// # Examples
+///
+/// ```
+/// use gba_core::models::ModelRegistry;
+///
+/// let registry = ModelRegistry::builtin();
+/// let model = registry.get("claude-sonnet-4-20250514").unwrap();
+/// assert_eq!(model.context_window, 200_000);
+///
+/// let cost = registry.estimate_cost("sonnet", 1_000_000, 0).unwrap();
+/// assert!(cost > 0.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelRegistry {
+    /// Known models.
+    models: Vec<ModelInfo>,
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ModelRegistry {
+    /// Create a registry with the built-in models.
+    #[must_use]
+    pub fn builtin() -> Self {
+        let model = |id: &str, aliases: &[&str], max_output, input, output| ModelInfo {
+            id: id.to_string(),
+            aliases: aliases.iter().map(ToString::to_string).collect(),
+            context_window: 200_000,
+            max_output_tokens: max_output,
+            input_cost_per_mtok: input,
+            output_cost_per_mtok: output,
+        };
+
+        Self {
+            models: vec![
+                model(
+                    "claude-opus-4-1-20250805",
+                    &["opus", "claude-opus-4-1"],
+                    32_000,
+                    15.0,
+                    75.0,
+                ),
+                model(
+                    "claude-opus-4-20250514",
+                    &["claude-opus-4-0"],
+                    32_000,
+                    15.0,
+                    75.0,
+                ),
+                model(
+                    "claude-sonnet-4-5-20250929",
+                    &["sonnet", "claude-sonnet-4-5"],
+                    64_000,
+                    3.0,
+                    15.0,
+                ),
+                model(
+                    "claude-sonnet-4-20250514",
+                    &["claude-sonnet-4-0"],
+                    64_000,
+                    3.0,
+                    15.0,
+                ),
+                model(
+                    "claude-3-7-sonnet-20250219",
+                    &["claude-3-7-sonnet-latest"],
+                    64_000,
+                    3.0,
+                    15.0,
+                ),
+                model(
+                    "claude-haiku-4-5-20251001",
+                    &["haiku", "claude-haiku-4-5"],
+                    64_000,
+                    1.0,
+                    5.0,
+                ),
+                model(
+                    "claude-3-5-haiku-20241022",
+                    &["claude-3-5-haiku-latest"],
+                    8_192,
+                    0.8,
+                    4.0,
+                ),
+            ],
+        }
+    }
+
+    /// Override or add models.
+    ///
+    /// A model replaces the built-in model with the same id; other models
+    /// are added.
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: impl IntoIterator<Item = ModelInfo>) -> Self {
+        for model in overrides {
+            self.models.retain(|m| m.id != model.id);
+            self.models.push(model);
+        }
+        self
+    }
+
+    /// Get a model by id or alias.
+    ///
+    /// Overrides take precedence, so an overriding model can claim an alias
+    /// of a built-in one.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ModelInfo> {
+        self.models
+            .iter()
+            .find(|m| m.id == name)
+            .or_else(|| self.models.iter().rev().find(|m| m.matches(name)))
+    }
+
+    /// Get all known models.
+    #[must_use]
+    pub fn models(&self) -> &[ModelInfo] {
+        &self.models
+    }
+
+    /// Estimate the cost of a request in USD, if the model is known.
+    #[must_use]
+    pub fn estimate_cost(&self, model: &str, input_tokens: u64, output_tokens: u64) -> Option<f64> {
+        self.get(model)
+            .map(|info| info.cost(input_tokens, output_tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup_and_cost() {
+        let registry = ModelRegistry::builtin();
+        let sonnet = registry.get("sonnet").unwrap();
+        assert_eq!(sonnet.id, "claude-sonnet-4-5-20250929");
+        assert!((sonnet.cost(1_000_000, 1_000_000) - 18.0).abs() < 1e-9);
+        assert_eq!(sonnet.input_budget(4096), 200_000 - 4096);
+        assert!(registry.get("gpt-4").is_none());
+        assert!(registry.estimate_cost("gpt-4", 1, 1).is_none());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let registry = ModelRegistry::builtin().with_overrides([
+            ModelInfo {
+                id: "claude-sonnet-4-20250514".to_string(),
+                aliases: vec!["sonnet".to_string()],
+                context_window: 1_000_000,
+                max_output_tokens: 64_000,
+                input_cost_per_mtok: 6.0,
+                output_cost_per_mtok: 22.5,
+            },
+            ModelInfo {
+                id: "claude-next".to_string(),
+                aliases: vec![],
+                context_window: 500_000,
+                max_output_tokens: 128_000,
+                input_cost_per_mtok: 2.0,
+                output_cost_per_mtok: 10.0,
+            },
+        ]);
+
+        assert_eq!(
+            registry.models().len(),
+            ModelRegistry::builtin().models().len() + 1
+        );
+        let sonnet = registry.get("claude-sonnet-4-20250514").unwrap();
+        assert_eq!(sonnet.context_window, 1_000_000);
+        assert_eq!(
+            registry.get("sonnet").unwrap().id,
+            "claude-sonnet-4-20250514"
+        );
+        assert_eq!(
+            registry.get("claude-next").unwrap().max_output_tokens,
+            128_000
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Implementation plan helpers.
+//!
+//! Plans are markdown documents produced by the planning task. The plan of
+//! a feature is stored in its `.gba/features/<id>/plan.md`, see [`save`] and
+//! [`load`], for the implementation to pick it up. This module also extracts
+//! structured information from plans, such as the files to pre-load into the
+//! context of the implementation, see [`context_files`].
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use tracing::debug;
+
+use crate::context_builder::detect_language;
+
+/// Name of the plan file in a feature's directory.
+pub const PLAN_FILE: &str = "plan.md";
+
+/// Heading of the plan section listing affected files.
+const AFFECTED_FILES_HEADING: &str = "affected files";
+
+/// Stems of the files declaring a directory's module, e.g. `mod.rs`.
+const ENTRY_STEMS: &[&str] = &["mod", "lib", "main", "index", "__init__"];
+
+/// Maximum size of a file searched for references to an affected file.
+const MAX_SEARCHED_BYTES: u64 = 256 * 1024;
+
+/// Get the path of the plan stored in a feature's directory.
+///
+/// # Arguments
+///
+/// * `feature_dir` - Feature directory, e.g. `.gba/features/<id>`.
+#[must_use]
+pub fn plan_path(feature_dir: &Path) -> PathBuf {
+    feature_dir.join(PLAN_FILE)
+}
+
+/// Store the plan of a feature, replacing the stored one.
+///
+/// # Arguments
+///
+/// * `feature_dir` - Feature directory, created if missing.
+/// * `plan` - Plan markdown.
+///
+/// # Returns
+///
+/// The path of the plan file.
+///
+/// # Errors
+///
+/// Returns an error if the plan cannot be written.
+pub fn save(feature_dir: &Path, plan: &str) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(feature_dir)?;
+    let path = plan_path(feature_dir);
+    crate::atomic::write_with_backup(&path, plan)?;
+    debug!("Saved plan to {}", path.display());
+    Ok(path)
+}
+
+/// Load the plan stored in a feature's directory.
+///
+/// # Arguments
+///
+/// * `feature_dir` - Feature directory, e.g. `.gba/features/<id>`.
+///
+/// # Returns
+///
+/// The plan, or `None` if the feature has no plan yet.
+///
+/// # Errors
+///
+/// Returns an error if the plan file exists but cannot be read.
+pub fn load(feature_dir: &Path) -> io::Result<Option<String>> {
+    match std::fs::read_to_string(plan_path(feature_dir)) {
+        Ok(plan) => Ok(Some(plan)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Extract the affected files listed in a plan.
+///
+/// Reads the list items of the `## Affected Files` section. Items may wrap
+/// the path in backticks and follow it with a description, e.g.
+/// ``- `src/auth/login.rs` - add the login handler``.
+///
+/// # Arguments
+///
+/// * `plan` - Plan markdown.
+///
+/// # Returns
+///
+/// The listed paths, in order, or an empty list if the section is missing.
+#[must_use]
+pub fn affected_files(plan: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut in_section = false;
+
+    for line in plan.lines() {
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix('#') {
+            in_section = heading
+                .trim_start_matches('#')
+                .trim()
+                .eq_ignore_ascii_case(AFFECTED_FILES_HEADING);
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+
+        let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) else {
+            continue;
+        };
+        let path = match item.strip_prefix('`') {
+            Some(quoted) => quoted.split('`').next().unwrap_or_default(),
+            None => item.split_whitespace().next().unwrap_or_default(),
+        };
+        if !path.is_empty() {
+            files.push(path.to_string());
+        }
+    }
+
+    files
+}
+
+/// Get the files to pre-load into the context of a plan's implementation:
+/// the affected files it lists, then their immediate dependents.
+///
+/// A dependent is a source file next to an affected file, or in the parent
+/// directory, that mentions the affected file's module name, e.g. the
+/// `mod.rs` declaring `mod login;`. For a file the plan creates, the files
+/// declaring its directory's module are included instead. Listed paths
+/// outside the repository are ignored.
+///
+/// # Arguments
+///
+/// * `repo_path` - Repository the plan is implemented in.
+/// * `plan` - Plan markdown.
+///
+/// # Returns
+///
+/// Paths relative to the repository, affected files first, without
+/// duplicates; empty if the plan lists no files.
+#[must_use]
+pub fn context_files(repo_path: &Path, plan: &str) -> Vec<PathBuf> {
+    let affected = affected_files(plan)
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| {
+            path.components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        })
+        .collect::<Vec<_>>();
+
+    let mut files = affected.clone();
+    for path in &affected {
+        for dependent in dependents(repo_path, path) {
+            if !files.contains(&dependent) {
+                files.push(dependent);
+            }
+        }
+    }
+    files
+}
+
+/// Find the immediate dependents of a file, see [`context_files`].
+fn dependents(repo_path: &Path, path: &Path) -> Vec<PathBuf> {
+    let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        return Vec::new();
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    // A directory's module is named after the directory
+    let (name, dir) = if ENTRY_STEMS.contains(&stem) {
+        match dir.file_name().and_then(|name| name.to_str()) {
+            Some(name) => (name, dir.parent().unwrap_or(Path::new(""))),
+            None => return Vec::new(),
+        }
+    } else {
+        (stem, dir)
+    };
+    let parent = dir.parent();
+
+    if !repo_path.join(path).exists() {
+        // Nothing mentions a new file yet; its module gets declared
+        let mut files = source_files(repo_path, dir)
+            .into_iter()
+            .filter(|file| {
+                file.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| ENTRY_STEMS.contains(&stem))
+            })
+            .collect::<Vec<_>>();
+        if let (Some(parent), Some(dir_name)) = (parent, dir.file_name()) {
+            files.extend(
+                source_files(repo_path, parent)
+                    .into_iter()
+                    .filter(|file| file.file_stem() == Some(dir_name)),
+            );
+        }
+        return files;
+    }
+
+    let mut files = source_files(repo_path, dir);
+    if let Some(parent) = parent {
+        files.extend(source_files(repo_path, parent));
+    }
+    files
+        .into_iter()
+        .filter(|file| file != path)
+        .filter(|file| {
+            std::fs::read_to_string(repo_path.join(file))
+                .is_ok_and(|content| mentions(&content, name))
+        })
+        .collect()
+}
+
+/// List the source files of a directory, not recursing, sorted.
+fn source_files(repo_path: &Path, dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(repo_path.join(dir)) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() <= MAX_SEARCHED_BYTES)
+        })
+        .map(|entry| dir.join(entry.file_name()))
+        .filter(|file| {
+            !matches!(
+                detect_language(file).as_str(),
+                "text" | "markdown" | "unknown"
+            )
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Check whether a text mentions a name as a whole word.
+fn mentions(content: &str, name: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    content.match_indices(name).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let after = content[start + name.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_plan() {
+        let dir = std::env::temp_dir().join("gba-test-plan-store");
+        let _ = std::fs::remove_dir_all(&dir);
+        let feature_dir = dir.join("features").join("0001");
+
+        assert_eq!(load(&feature_dir).unwrap(), None);
+        let path = save(&feature_dir, "# Plan\n1. Add login\n").unwrap();
+        assert_eq!(path, feature_dir.join("plan.md"));
+        assert_eq!(
+            load(&feature_dir).unwrap().as_deref(),
+            Some("# Plan\n1. Add login\n")
+        );
+        save(&feature_dir, "# Plan v2\n").unwrap();
+        assert_eq!(load(&feature_dir).unwrap().as_deref(), Some("# Plan v2\n"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_affected_files() {
+        let plan = "\
+# Implementation Plan: add-auth
+
+## Overview
+- not a file
+
+## Affected Files
+- `src/auth/login.rs` - add the login handler
+- src/auth/mod.rs
+* `docs/`
+
+## Testing Requirements
+- [ ] Unit tests
+";
+        assert_eq!(
+            affected_files(plan),
+            vec!["src/auth/login.rs", "src/auth/mod.rs", "docs/"]
+        );
+        assert!(affected_files("# Plan\n- src/lib.rs\n").is_empty());
+    }
+
+    #[test]
+    fn test_context_files_adds_dependents() {
+        let repo = std::env::temp_dir().join("gba-test-plan-context");
+        let _ = std::fs::remove_dir_all(&repo);
+        std::fs::create_dir_all(repo.join("src/auth")).unwrap();
+        for (path, content) in [
+            ("src/lib.rs", "pub mod auth;\n"),
+            ("src/auth/mod.rs", "mod login;\nmod logout;\n"),
+            ("src/auth/login.rs", "pub fn login() {}\n"),
+            ("src/auth/logout.rs", "pub fn logout() {}\n"),
+            ("src/auth/session.rs", "use super::login_form;\n"),
+            ("src/auth/NOTES.md", "login\n"),
+        ] {
+            std::fs::write(repo.join(path), content).unwrap();
+        }
+
+        let plan = "## Affected Files\n\
+                    - `src/auth/login.rs` - add the handler\n\
+                    - `src/auth/token.rs` - new\n\
+                    - `../outside.rs`\n";
+        assert_eq!(
+            context_files(&repo, plan),
+            ["src/auth/login.rs", "src/auth/token.rs", "src/auth/mod.rs",].map(PathBuf::from)
+        );
+        assert_eq!(
+            context_files(&repo, "## Affected Files\n- src/auth/mod.rs\n"),
+            ["src/auth/mod.rs", "src/lib.rs"].map(PathBuf::from)
+        );
+        assert!(context_files(&repo, "# Plan\n").is_empty());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+}
@@ -0,0 +1,113 @@
+//! Concurrent execution of several agent tasks.
+//!
+//! An [`AgentPool`] runs a batch of independent tasks, e.g. the reviewer
+//! personas of a fan-out review, with a bounded number of queries in flight.
+//! The tasks run on the caller's task: they are I/O bound, so no threads are
+//! spawned.
+
+use futures::StreamExt;
+use futures::stream;
+
+use crate::agent::Agent;
+use crate::error::Result;
+use crate::task::{Response, Task};
+
+/// A task for the pool: an agent with the task it runs.
+#[derive(Debug)]
+pub struct PoolTask {
+    /// Agent executing the task, configured with its own transcript and
+    /// audit log.
+    pub agent: Agent,
+
+    /// Task, with its prompt, context and template settings.
+    pub task: Task,
+}
+
+impl PoolTask {
+    /// Create a pool task.
+    #[must_use]
+    pub const fn new(agent: Agent, task: Task) -> Self {
+        Self { agent, task }
+    }
+}
+
+/// Pool running agent tasks concurrently.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba_core::pool::{AgentPool, PoolTask};
+/// use gba_core::{Agent, AgentConfig, Context, Task};
+///
+/// # async fn example() {
+/// let tasks = ["Review for security", "Review for performance"]
+///     .into_iter()
+///     .map(|prompt| {
+///         let task = Task::with_defaults(prompt, Context::default());
+///         PoolTask::new(Agent::new(AgentConfig::default()), task)
+///     })
+///     .collect();
+///
+/// for result in AgentPool::new(2).run(tasks).await {
+///     match result {
+///         Ok(response) => println!("{}", response.content),
+///         Err(e) => eprintln!("Task failed: {e}"),
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgentPool {
+    /// Maximum number of tasks running at once.
+    max_concurrency: usize,
+}
+
+impl AgentPool {
+    /// Create a pool running at most `max_concurrency` tasks at once.
+    ///
+    /// A limit of 0 is treated as 1.
+    #[must_use]
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            max_concurrency: max_concurrency.max(1),
+        }
+    }
+
+    /// Get the maximum number of tasks running at once.
+    #[must_use]
+    pub const fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// Run tasks concurrently and wait for all of them.
+    ///
+    /// A failing task doesn't cancel the others.
+    ///
+    /// # Returns
+    ///
+    /// The result of each task, in the order the tasks were given.
+    #[tracing::instrument(skip(self, tasks), fields(tasks = tasks.len()))]
+    pub async fn run(&self, tasks: Vec<PoolTask>) -> Vec<Result<Response>> {
+        stream::iter(tasks)
+            .map(|task| async move { task.agent.execute_task(&task.task).await })
+            .buffered(self.max_concurrency)
+            .collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_concurrency_is_at_least_one() {
+        assert_eq!(AgentPool::new(0).max_concurrency(), 1);
+        assert_eq!(AgentPool::new(4).max_concurrency(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_pool_runs_no_tasks() {
+        assert!(AgentPool::new(2).run(Vec::new()).await.is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+//! Post-processing of agent responses.
+//!
+//! Models wrap their answers in prose: "Sure, here is the plan:" before the
+//! first heading, Windows line endings, a closing summary nobody asked for.
+//! A [`PostProcessPipeline`] applies a chain of [`PostProcessor`]s to
+//! [`Response::content`], so each phase receives predictable text. Pipelines
+//! are configured per task kind in the project configuration, see
+//! [`crate::config::PostProcessConfig`].
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_core::postprocess::{PostProcessPipeline, PostProcessor};
+//!
+//! let pipeline = PostProcessPipeline::new(vec![
+//!     PostProcessor::NormalizeLineEndings,
+//!     PostProcessor::StripPreamble,
+//! ]);
+//! let content = pipeline.apply("Sure! Here is the plan:\r\n\r\n# Plan\r\n\r\n1. Add login\r\n");
+//! assert_eq!(content, "# Plan\n\n1. Add login\n");
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::task::Response;
+
+/// Marker appended to content truncated by [`PostProcessor::MaxLength`].
+pub const TRUNCATION_MARKER: &str = "\n\n[Truncated]\n";
+
+/// A transformation of response content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PostProcessor {
+    /// Convert `\r\n` and `\r` line endings to `\n`, and end the content
+    /// with a single newline.
+    NormalizeLineEndings,
+
+    /// Remove the prose before the first markdown heading.
+    ///
+    /// Content without a heading is left unchanged.
+    StripPreamble,
+
+    /// Keep only the sections under the given headings, with their
+    /// subsections, in document order.
+    ///
+    /// Headings are compared case-insensitively, without the leading `#`s.
+    /// Content without any of the headings is left unchanged, so nothing is
+    /// lost when the model doesn't follow the expected format.
+    ExtractSections {
+        /// Headings of the sections to keep, e.g. `"Affected Files"`.
+        headings: Vec<String>,
+    },
+
+    /// Truncate the content to a number of characters, at a line break if
+    /// possible, and append [`TRUNCATION_MARKER`].
+    MaxLength {
+        /// Maximum number of characters kept.
+        max_chars: usize,
+    },
+}
+
+impl PostProcessor {
+    /// Apply the post-processor to content.
+    #[must_use]
+    pub fn apply(&self, content: &str) -> String {
+        match self {
+            Self::NormalizeLineEndings => normalize_line_endings(content),
+            Self::StripPreamble => strip_preamble(content),
+            Self::ExtractSections { headings } => extract_sections(content, headings),
+            Self::MaxLength { max_chars } => max_length(content, *max_chars),
+        }
+    }
+}
+
+/// Chain of post-processors applied in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PostProcessPipeline {
+    /// Post-processors, in application order.
+    processors: Vec<PostProcessor>,
+}
+
+impl PostProcessPipeline {
+    /// Create a pipeline applying the given post-processors in order.
+    #[must_use]
+    pub const fn new(processors: Vec<PostProcessor>) -> Self {
+        Self { processors }
+    }
+
+    /// Append a post-processor to the chain.
+    #[must_use]
+    pub fn with(mut self, processor: PostProcessor) -> Self {
+        self.processors.push(processor);
+        self
+    }
+
+    /// Get the post-processors, in application order.
+    #[must_use]
+    pub fn processors(&self) -> &[PostProcessor] {
+        &self.processors
+    }
+
+    /// Check whether the pipeline leaves content unchanged.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    /// Apply the chain to content.
+    #[must_use]
+    pub fn apply(&self, content: &str) -> String {
+        self.processors
+            .iter()
+            .fold(content.to_string(), |content, processor| {
+                processor.apply(&content)
+            })
+    }
+
+    /// Apply the chain to the content of a response.
+    #[must_use]
+    pub fn process(&self, mut response: Response) -> Response {
+        if !self.is_empty() {
+            response.content = self.apply(&response.content);
+        }
+        response
+    }
+}
+
+/// Get the text of a markdown heading line and its level, if it is one.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let text = &trimmed[level..];
+    (text.is_empty() || text.starts_with(' ')).then(|| (level, text.trim()))
+}
+
+/// Split content into lines with their line endings, and the heading of each
+/// line. Lines in fenced code blocks are never headings.
+fn lines_with_headings(content: &str) -> Vec<(&str, Option<(usize, &str)>)> {
+    let mut in_fence = false;
+    content
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                return (line, None);
+            }
+            (line, if in_fence { None } else { heading(line) })
+        })
+        .collect()
+}
+
+fn normalize_line_endings(content: &str) -> String {
+    let normalized = content.replace("\r\n", "\n").replace('\r', "\n");
+    let trimmed = normalized.trim_end_matches('\n');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("{trimmed}\n")
+    }
+}
+
+fn strip_preamble(content: &str) -> String {
+    let mut offset = 0;
+    for (line, heading) in lines_with_headings(content) {
+        if heading.is_some() {
+            return content[offset..].to_string();
+        }
+        offset += line.len();
+    }
+    content.to_string()
+}
+
+fn extract_sections(content: &str, headings: &[String]) -> String {
+    let mut out = String::new();
+    // Level of the kept section the current line is in
+    let mut keeping: Option<usize> = None;
+
+    for (line, heading) in lines_with_headings(content) {
+        if let Some((level, text)) = heading {
+            if keeping.is_some_and(|kept| level <= kept) {
+                keeping = None;
+            }
+            if keeping.is_none() && headings.iter().any(|h| h.eq_ignore_ascii_case(text)) {
+                keeping = Some(level);
+            }
+        }
+        if keeping.is_some() {
+            out.push_str(line);
+        }
+    }
+
+    if out.is_empty() {
+        content.to_string()
+    } else {
+        out
+    }
+}
+
+fn max_length(content: &str, max_chars: usize) -> String {
+    let Some((end, _)) = content.char_indices().nth(max_chars) else {
+        return content.to_string();
+    };
+    let kept = &content[..end];
+    let kept = match kept.rfind('\n') {
+        Some(newline) if newline > 0 => &kept[..newline],
+        _ => kept,
+    };
+    format!("{}{TRUNCATION_MARKER}", kept.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "Sure, here is the plan.\n\n\
+        # Plan\n\nOverview.\n\n\
+        ## Affected Files\n\n- `src/auth.rs`\n\n\
+        ```markdown\n## Not A Heading\n```\n\n\
+        ### Tests\n\n- `tests/auth.rs`\n\n\
+        ## Steps\n\n1. Add login\n";
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let processor = PostProcessor::NormalizeLineEndings;
+        assert_eq!(processor.apply("a\r\nb\rc\n\n\n"), "a\nb\nc\n");
+        assert_eq!(processor.apply("a"), "a\n");
+        assert_eq!(processor.apply("\r\n"), "");
+    }
+
+    #[test]
+    fn test_strip_preamble() {
+        let processor = PostProcessor::StripPreamble;
+        assert!(processor.apply(PLAN).starts_with("# Plan\n"));
+        assert_eq!(processor.apply("No headings here."), "No headings here.");
+        assert_eq!(processor.apply("#hashtag\n# Title\n"), "# Title\n");
+    }
+
+    #[test]
+    fn test_extract_sections() {
+        let processor = PostProcessor::ExtractSections {
+            headings: vec!["affected files".to_string()],
+        };
+        let extracted = processor.apply(PLAN);
+        assert!(extracted.starts_with("## Affected Files\n"));
+        assert!(extracted.contains("## Not A Heading"));
+        assert!(extracted.contains("### Tests\n\n- `tests/auth.rs`"));
+        assert!(!extracted.contains("## Steps"));
+
+        let missing = PostProcessor::ExtractSections {
+            headings: vec!["Risks".to_string()],
+        };
+        assert_eq!(missing.apply(PLAN), PLAN);
+    }
+
+    #[test]
+    fn test_max_length() {
+        let processor = PostProcessor::MaxLength { max_chars: 12 };
+        assert_eq!(processor.apply("short"), "short");
+        assert_eq!(
+            processor.apply("first line\nsecond line"),
+            format!("first line{TRUNCATION_MARKER}")
+        );
+        assert_eq!(
+            processor.apply("ééééééééééééééé"),
+            format!("éééééééééééé{TRUNCATION_MARKER}")
+        );
+    }
+
+    #[test]
+    fn test_pipeline_and_config_format() {
+        let processors: Vec<PostProcessor> = serde_yaml::from_str(
+            "- type: normalizeLineEndings\n\
+             - type: extractSections\n  headings: [Steps]\n\
+             - type: maxLength\n  maxChars: 100\n",
+        )
+        .unwrap();
+        let pipeline = PostProcessPipeline::new(processors).with(PostProcessor::StripPreamble);
+        assert_eq!(pipeline.processors().len(), 4);
+
+        let response = Response {
+            content: PLAN.replace('\n', "\r\n"),
+            ..Response::default()
+        };
+        assert_eq!(
+            pipeline.process(response).content,
+            "## Steps\n\n1. Add login\n"
+        );
+        assert!(PostProcessPipeline::default().is_empty());
+    }
+}
@@ -0,0 +1,415 @@
+//! Write protection of project paths.
+//!
+//! Some files must never be changed by automation, whatever the task:
+//! CI workflows, lock files, database migrations. A [`PathGuard`] checks each
+//! tool call against the project's protected path patterns before it runs,
+//! and denies calls that would modify a protected file: file edits, and Bash
+//! commands that write to, move or delete one. Reading protected files stays
+//! allowed.
+//!
+//! Patterns are relative to the working directory and use `/` as separator:
+//!
+//! - `*` matches any characters within a path component, `?` a single one.
+//! - `**` matches any number of components.
+//! - A pattern without `/` matches at any depth, e.g. `Cargo.lock`.
+//! - A pattern matching a directory protects everything below it.
+//!
+//! Denied calls are recorded as [`Violation`]s, reported in the
+//! [`Response`](crate::task::Response) of the task.
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_core::protect::PathGuard;
+//! use serde_json::json;
+//!
+//! let guard = PathGuard::new(&[".github/workflows/**".to_string()], "/work/repo");
+//!
+//! let input = json!({ "file_path": "/work/repo/.github/workflows/ci.yml" });
+//! assert!(guard.check("Read", &input).is_none());
+//! assert!(guard.check("Edit", &input).is_some());
+//! assert_eq!(guard.violations().len(), 1);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use claude_agent_sdk_rs::{
+    HookCallback, HookEvent, HookInput, HookJsonOutput, HookMatcher, SyncHookJsonOutput,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::sandbox::{self, PATH_FIELDS};
+
+/// Tools that modify the file given in their input.
+const WRITE_TOOLS: &[&str] = &["Write", "Edit", "MultiEdit", "NotebookEdit"];
+
+/// Commands modifying every file given as argument.
+const WRITE_COMMANDS: &[&str] = &[
+    "rm", "mv", "touch", "truncate", "chmod", "chown", "tee", "unlink", "shred",
+];
+
+/// Commands modifying only the file given as last argument.
+const TARGET_COMMANDS: &[&str] = &["cp", "ln", "install"];
+
+/// Commands modifying the files given as argument when run with `-i`.
+const IN_PLACE_COMMANDS: &[&str] = &["sed", "perl"];
+
+/// A tool call denied for modifying a protected path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Tool name, e.g. `"Edit"`.
+    pub tool: String,
+
+    /// Protected path, relative to the working directory.
+    pub path: String,
+
+    /// Pattern the path matches.
+    pub pattern: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} may not modify {}: it matches protected path `{}`",
+            self.tool, self.path, self.pattern
+        )
+    }
+}
+
+/// Compiled protected path patterns.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProtectedPaths {
+    /// Patterns with their components, e.g. `["**", "Cargo.lock"]`.
+    patterns: Vec<(String, Vec<String>)>,
+}
+
+impl ProtectedPaths {
+    /// Compile protected path patterns.
+    #[must_use]
+    pub fn new(patterns: &[String]) -> Self {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| {
+                let mut components = pattern
+                    .split('/')
+                    .filter(|c| !c.is_empty() && *c != ".")
+                    .map(String::from)
+                    .collect::<Vec<_>>();
+                if components.is_empty() {
+                    return None;
+                }
+                if !pattern.trim_end_matches('/').contains('/') {
+                    components.insert(0, "**".to_string());
+                }
+                Some((pattern.clone(), components))
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// Check whether there are no patterns.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Get the pattern protecting a path, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path relative to the working directory, with `/` as
+    ///   separator.
+    #[must_use]
+    pub fn matching(&self, path: &str) -> Option<&str> {
+        let components = path
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect::<Vec<_>>();
+        // The path itself or any directory containing it
+        self.patterns
+            .iter()
+            .find(|(_, pattern)| {
+                (1..=components.len()).any(|len| matches_path(pattern, &components[..len]))
+            })
+            .map(|(pattern, _)| pattern.as_str())
+    }
+}
+
+/// Guard denying modifications of protected paths, rooted at the agent's
+/// working directory.
+///
+/// Clones share the recorded violations, so the hooks built by
+/// [`PathGuard::hooks`] and the guard they came from see the same ones.
+#[derive(Debug, Clone)]
+pub struct PathGuard {
+    /// Protected path patterns.
+    paths: ProtectedPaths,
+    /// Directory the agent works in.
+    root: PathBuf,
+    /// Violations recorded so far.
+    violations: Arc<Mutex<Vec<Violation>>>,
+}
+
+impl PathGuard {
+    /// Create a guard for an agent working in `root`.
+    #[must_use]
+    pub fn new(patterns: &[String], root: impl Into<PathBuf>) -> Self {
+        Self {
+            paths: ProtectedPaths::new(patterns),
+            root: sandbox::normalize(&root.into()),
+            violations: Arc::default(),
+        }
+    }
+
+    /// Check a tool call, recording it as a violation if it modifies a
+    /// protected path.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool_name` - Name of the tool, e.g. `"Edit"`.
+    /// * `input` - Input of the tool call.
+    pub fn check(&self, tool_name: &str, input: &Value) -> Option<Violation> {
+        let paths = if tool_name == "Bash" {
+            let command = input
+                .get("command")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            written_paths(command)
+        } else if WRITE_TOOLS.contains(&tool_name) {
+            PATH_FIELDS
+                .iter()
+                .filter_map(|field| input.get(*field).and_then(Value::as_str))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let violation = paths.into_iter().find_map(|path| {
+            let relative = self.relative(path)?;
+            let pattern = self.paths.matching(&relative)?;
+            Some(Violation {
+                tool: tool_name.to_string(),
+                path: relative,
+                pattern: pattern.to_string(),
+            })
+        })?;
+        self.lock().push(violation.clone());
+        Some(violation)
+    }
+
+    /// Get the violations recorded so far.
+    #[must_use]
+    pub fn violations(&self) -> Vec<Violation> {
+        self.lock().clone()
+    }
+
+    /// Build the hooks that enforce the protection.
+    #[must_use]
+    pub fn hooks(&self) -> HashMap<HookEvent, Vec<HookMatcher>> {
+        let guard = self.clone();
+        let callback: HookCallback = Arc::new(move |input, _tool_use_id, _context| {
+            let guard = guard.clone();
+            Box::pin(async move {
+                let HookInput::PreToolUse(input) = input else {
+                    return HookJsonOutput::Sync(SyncHookJsonOutput::default());
+                };
+                match guard.check(&input.tool_name, &input.tool_input) {
+                    None => HookJsonOutput::Sync(SyncHookJsonOutput::default()),
+                    Some(violation) => {
+                        warn!("Protected path: {}", violation);
+                        HookJsonOutput::Sync(sandbox::deny(format!(
+                            "{violation}; protected files may be read but not modified"
+                        )))
+                    }
+                }
+            })
+        });
+
+        HashMap::from([(
+            HookEvent::PreToolUse,
+            vec![HookMatcher::builder().hooks(vec![callback]).build()],
+        )])
+    }
+
+    /// Get a path relative to the working directory, if it is within it.
+    fn relative(&self, path: &str) -> Option<String> {
+        let path = sandbox::normalize(&self.root.join(path));
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+
+    /// Lock the recorded violations, recovering from a poisoned lock.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Violation>> {
+        self.violations
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Get the paths a Bash command writes to, moves or deletes.
+///
+/// Recognizes output redirections and common file commands; commands
+/// writing files in other ways, such as build tools, aren't detected.
+fn written_paths(command: &str) -> Vec<&str> {
+    let mut paths = Vec::new();
+    for segment in sandbox::command_segments(command) {
+        let mut words = segment.iter().copied().peekable();
+        let mut args = Vec::new();
+        while let Some(word) = words.next() {
+            // `> file`, `>> file`, `>file`, `2>file`
+            let redirect = word.trim_start_matches(|c: char| c.is_ascii_digit());
+            if let Some(target) = redirect.strip_prefix('>') {
+                let target = target.trim_start_matches('>');
+                if target.is_empty() {
+                    paths.extend(words.next());
+                } else {
+                    paths.push(target);
+                }
+            } else {
+                args.push(word);
+            }
+        }
+
+        let Some((program, args)) = sandbox::unwrap_command(&args).split_first() else {
+            continue;
+        };
+        let mut files = args.iter().copied().filter(|arg| !arg.starts_with('-'));
+        if WRITE_COMMANDS.contains(program) {
+            paths.extend(files);
+        } else if TARGET_COMMANDS.contains(program) {
+            paths.extend(files.next_back());
+        } else if IN_PLACE_COMMANDS.contains(program)
+            && args.iter().any(|arg| arg.starts_with("-i"))
+        {
+            paths.extend(files);
+        } else if *program == "git"
+            && args
+                .first()
+                .is_some_and(|sub| ["rm", "mv", "checkout", "restore"].contains(sub))
+        {
+            paths.extend(files.skip(1));
+        }
+    }
+    paths
+}
+
+/// Match path components against pattern components.
+fn matches_path(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| matches_path(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(component, path)| {
+            matches_component(first.as_bytes(), component.as_bytes()) && matches_path(rest, path)
+        }),
+    }
+}
+
+/// Match a path component against a pattern with `*` and `?` wildcards.
+fn matches_component(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| matches_component(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && matches_component(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches_component(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn guard() -> PathGuard {
+        PathGuard::new(
+            &[
+                ".github/workflows/**".to_string(),
+                "Cargo.lock".to_string(),
+                "migrations".to_string(),
+                "config/*.prod.yml".to_string(),
+            ],
+            "/work/repo",
+        )
+    }
+
+    #[test]
+    fn test_protected_paths_matching() {
+        let paths = guard().paths;
+        assert_eq!(
+            paths.matching(".github/workflows/ci.yml"),
+            Some(".github/workflows/**")
+        );
+        assert_eq!(paths.matching("crates/gba/Cargo.lock"), Some("Cargo.lock"));
+        assert_eq!(
+            paths.matching("migrations/0001_init.sql"),
+            Some("migrations")
+        );
+        assert_eq!(
+            paths.matching("config/app.prod.yml"),
+            Some("config/*.prod.yml")
+        );
+        assert_eq!(paths.matching("config/app.dev.yml"), None);
+        assert_eq!(paths.matching("src/Cargo.lock.bak"), None);
+        assert!(ProtectedPaths::new(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_guard_file_tools() {
+        let guard = guard();
+        let lock = json!({ "file_path": "/work/repo/Cargo.lock" });
+        assert!(guard.check("Read", &lock).is_none());
+        assert!(
+            guard
+                .check("Grep", &json!({ "path": "migrations" }))
+                .is_none()
+        );
+
+        let violation = guard.check("Write", &lock).unwrap();
+        assert_eq!(violation.path, "Cargo.lock");
+        assert_eq!(
+            violation.to_string(),
+            "Write may not modify Cargo.lock: it matches protected path `Cargo.lock`"
+        );
+        assert!(
+            guard
+                .check("Edit", &json!({ "file_path": "src/../Cargo.lock" }))
+                .is_some()
+        );
+        assert!(
+            guard
+                .check("Edit", &json!({ "file_path": "/elsewhere/Cargo.lock" }))
+                .is_none()
+        );
+        assert_eq!(guard.clone().violations().len(), 2);
+    }
+
+    #[test]
+    fn test_guard_bash_commands() {
+        let guard = guard();
+        let bash = |command: &str| guard.check("Bash", &json!({ "command": command }));
+
+        assert!(bash("cat Cargo.lock && cp Cargo.lock /tmp/lock").is_none());
+        assert!(bash("cargo test 2>&1 | tee test.log").is_none());
+        assert!(bash("echo x >> Cargo.lock").is_some());
+        assert!(bash("echo x >.github/workflows/ci.yml").is_some());
+        assert!(bash("rm -rf migrations").is_some());
+        assert!(bash("cp /tmp/lock Cargo.lock").is_some());
+        assert!(bash("sed -i 's/a/b/' config/app.prod.yml").is_some());
+        assert!(bash("sed 's/a/b/' config/app.prod.yml").is_none());
+        assert!(bash("git checkout -- Cargo.lock").is_some());
+        assert!(bash("sudo rm -rf migrations").is_some());
+    }
+}
@@ -0,0 +1,362 @@
+//! Pull request reviews built from review findings.
+//!
+//! Maps the findings of a [`MergedReview`] to the lines of a pull request's
+//! unified diff, so each one can be posted as a review comment on the line it
+//! cites. A comment can only be placed on a line of the diff: an added or
+//! context line on the new side of a hunk. Findings without a location, or
+//! citing a line outside the diff, are listed in the review body instead.
+//!
+//! [`PullRequestDescription`] describes the pull request opened for an
+//! implemented feature, from its plan and a summary of the implementation.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::review::{Finding, MergedReview};
+
+/// A review comment on a line of a pull request's diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewComment {
+    /// Path of the file, relative to the repository root.
+    pub path: String,
+
+    /// Line in the new version of the file.
+    pub line: u32,
+
+    /// Comment text, in markdown.
+    pub body: String,
+}
+
+/// A pull request review: a body and comments on lines of the diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestReview {
+    /// Review body, in markdown.
+    pub body: String,
+
+    /// Comments on lines of the diff.
+    pub comments: Vec<ReviewComment>,
+
+    /// Findings that could not be placed on a line of the diff.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unplaced: Vec<Finding>,
+}
+
+impl PullRequestReview {
+    /// Build a pull request review from a merged review and the diff of the
+    /// pull request.
+    ///
+    /// A finding citing a range of lines, e.g. `src/auth.rs:42-50`, is placed
+    /// on the first line of the range in the diff.
+    ///
+    /// # Arguments
+    ///
+    /// * `review` - Merged review of the changes.
+    /// * `diff` - Unified diff of the pull request.
+    #[must_use]
+    pub fn from_review(review: &MergedReview, diff: &str) -> Self {
+        let lines = DiffLines::parse(diff);
+        let mut comments = Vec::new();
+        let mut unplaced = Vec::new();
+        for finding in &review.findings {
+            let placed = finding
+                .location
+                .as_deref()
+                .and_then(parse_location)
+                .and_then(|(path, start, end)| {
+                    (start..=end)
+                        .find(|&line| lines.contains(&path, line))
+                        .map(|line| (path, line))
+                });
+            match placed {
+                Some((path, line)) => comments.push(ReviewComment {
+                    path,
+                    line,
+                    body: comment_body(finding),
+                }),
+                None => unplaced.push(finding.clone()),
+            }
+        }
+
+        let mut body = String::from("## Code Review\n\n");
+        for (persona, summary) in &review.summaries {
+            let _ = writeln!(body, "**{persona}**: {summary}\n");
+        }
+        if !unplaced.is_empty() {
+            body.push_str("### Findings outside the diff\n\n");
+            for finding in &unplaced {
+                let location = finding
+                    .location
+                    .as_deref()
+                    .map(|location| format!("[{location}] "))
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    body,
+                    "- **{}** {location}{}",
+                    finding.severity, finding.description
+                );
+            }
+            body.push('\n');
+        }
+        let status = if review.approved {
+            "APPROVED"
+        } else {
+            "REQUEST CHANGES"
+        };
+        let _ = write!(body, "**Status**: {status}");
+
+        Self {
+            body,
+            comments,
+            unplaced,
+        }
+    }
+}
+
+/// Title and body of the pull request opened for an implemented feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestDescription {
+    /// Title, e.g. `"gba: implement add-auth"`.
+    pub title: String,
+
+    /// Body, in markdown.
+    pub body: String,
+}
+
+impl PullRequestDescription {
+    /// Describe the pull request of a feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name.
+    /// * `plan` - Implementation plan of the feature, if it was planned.
+    /// * `summary` - Summary of the implementation, e.g. the final response
+    ///   of the implementation task.
+    #[must_use]
+    pub fn new(feature: &str, plan: Option<&str>, summary: &str) -> Self {
+        let mut body = String::new();
+        let summary = summary.trim();
+        if !summary.is_empty() {
+            let _ = writeln!(body, "## Summary\n\n{summary}\n");
+        }
+        if let Some(plan) = plan.map(str::trim).filter(|plan| !plan.is_empty()) {
+            let _ = writeln!(
+                body,
+                "<details>\n<summary>Implementation plan</summary>\n\n{plan}\n\n</details>\n"
+            );
+        }
+        let _ = write!(
+            body,
+            "Implemented by gba for feature `{feature}`; verification passed."
+        );
+
+        Self {
+            title: format!("gba: implement {feature}"),
+            body,
+        }
+    }
+}
+
+/// Lines of the new side of a diff, by file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffLines {
+    files: BTreeMap<String, BTreeSet<u32>>,
+}
+
+impl DiffLines {
+    /// Collect the added and context lines of a unified diff.
+    #[must_use]
+    pub fn parse(diff: &str) -> Self {
+        let mut files: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+        let mut path: Option<String> = None;
+        let mut line = 0;
+        let mut previous = "";
+
+        for text in diff.lines() {
+            // `+++` starts a file header, unless it's an added line in a hunk
+            let header = (line == 0 || previous.starts_with("--- "))
+                .then(|| text.strip_prefix("+++ "))
+                .flatten();
+            previous = text;
+            if let Some(new) = header {
+                path = new.strip_prefix("b/").map(|p| p.trim_end().to_string());
+                line = 0;
+            } else if text.starts_with("diff --git ") {
+                path = None;
+                line = 0;
+            } else if let Some(hunk) = text.strip_prefix("@@ ") {
+                line = hunk_start(hunk).unwrap_or(0);
+            } else if let Some(path) = path.as_ref().filter(|_| line > 0) {
+                match text.chars().next() {
+                    Some('+' | ' ') => {
+                        files.entry(path.clone()).or_default().insert(line);
+                        line += 1;
+                    }
+                    // Removed lines and `\ No newline` markers don't advance
+                    Some('-' | '\\') => {}
+                    _ => line = 0,
+                }
+            }
+        }
+
+        Self { files }
+    }
+
+    /// Check whether a line of a file is in the diff.
+    #[must_use]
+    pub fn contains(&self, path: &str, line: u32) -> bool {
+        self.files
+            .get(path)
+            .is_some_and(|lines| lines.contains(&line))
+    }
+}
+
+/// Get the first new-side line of a hunk from its header, e.g. `-1,3 +4,5 @@`.
+fn hunk_start(hunk: &str) -> Option<u32> {
+    let new = hunk.split_whitespace().find(|part| part.starts_with('+'))?;
+    new[1..].split(',').next()?.parse().ok()
+}
+
+/// Split a finding location, e.g. `src/auth.rs:42` or `src/auth.rs:42-50`,
+/// into its path and line range.
+fn parse_location(location: &str) -> Option<(String, u32, u32)> {
+    let (path, lines) = location.trim().rsplit_once(':')?;
+    let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+    let start: u32 = start.trim().parse().ok()?;
+    let end: u32 = end.trim().parse().ok()?;
+    let path = path.trim().trim_start_matches("./");
+    (!path.is_empty() && start <= end).then(|| (path.to_string(), start, end))
+}
+
+/// Render the comment posted for a finding.
+fn comment_body(finding: &Finding) -> String {
+    let mut body = format!("**{}**: {}", finding.severity, finding.description);
+    if let Some(fix) = &finding.suggested_fix {
+        let _ = write!(body, "\n\nSuggested fix: {fix}");
+    }
+    if !finding.personas.is_empty() {
+        let _ = write!(body, "\n\n_Raised by: {}_", finding.personas.join(", "));
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::review::Severity;
+
+    const DIFF: &str = "\
+diff --git a/src/auth.rs b/src/auth.rs
+index 1111111..2222222 100644
+--- a/src/auth.rs
++++ b/src/auth.rs
+@@ -10,4 +10,5 @@ fn login() {
+     let user = find(name);
+-    check(user);
++    let ok = check(user);
++    log(ok);
+     done();
+diff --git a/old.rs b/old.rs
+deleted file mode 100644
+--- a/old.rs
++++ /dev/null
+@@ -1,1 +0,0 @@
+-gone
+";
+
+    fn finding(location: Option<&str>, severity: Severity) -> Finding {
+        Finding {
+            location: location.map(str::to_string),
+            description: "Token is not validated".to_string(),
+            severity,
+            suggested_fix: Some("Validate the token".to_string()),
+            personas: vec!["security".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_diff_lines_new_side() {
+        let lines = DiffLines::parse(DIFF);
+        for line in 10..=13 {
+            assert!(lines.contains("src/auth.rs", line), "line {line}");
+        }
+        assert!(!lines.contains("src/auth.rs", 14));
+        assert!(!lines.contains("src/auth.rs", 9));
+        assert!(!lines.contains("old.rs", 1));
+    }
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            parse_location("src/auth.rs:42"),
+            Some(("src/auth.rs".to_string(), 42, 42))
+        );
+        assert_eq!(
+            parse_location("./src/auth.rs:42-50"),
+            Some(("src/auth.rs".to_string(), 42, 50))
+        );
+        assert_eq!(parse_location("src/auth.rs"), None);
+        assert_eq!(parse_location("src/auth.rs:9-3"), None);
+    }
+
+    #[test]
+    fn test_from_review_places_findings() {
+        let review = MergedReview {
+            findings: vec![
+                finding(Some("src/auth.rs:11"), Severity::Critical),
+                finding(Some("src/auth.rs:1-10"), Severity::Important),
+                finding(Some("src/auth.rs:99"), Severity::Minor),
+                finding(None, Severity::Minor),
+            ],
+            summaries: vec![("security".to_string(), "Looks risky".to_string())],
+            ..MergedReview::default()
+        };
+
+        let pr_review = PullRequestReview::from_review(&review, DIFF);
+        let placed = pr_review
+            .comments
+            .iter()
+            .map(|comment| (comment.path.as_str(), comment.line))
+            .collect::<Vec<_>>();
+        assert_eq!(placed, [("src/auth.rs", 11), ("src/auth.rs", 10)]);
+        assert!(pr_review.comments[0].body.starts_with("**Critical**"));
+        assert!(pr_review.comments[0].body.contains("Validate the token"));
+        assert_eq!(pr_review.unplaced.len(), 2);
+        assert!(pr_review.body.contains("[src/auth.rs:99]"));
+        assert!(pr_review.body.contains("**security**: Looks risky"));
+        assert!(pr_review.body.ends_with("REQUEST CHANGES"));
+    }
+
+    #[test]
+    fn test_pull_request_description() {
+        let description = PullRequestDescription::new(
+            "add-auth",
+            Some("## Phase 1\n\nAdd a login route\n"),
+            "Added a login route.",
+        );
+        assert_eq!(description.title, "gba: implement add-auth");
+        assert!(
+            description
+                .body
+                .starts_with("## Summary\n\nAdded a login route.")
+        );
+        assert!(
+            description
+                .body
+                .contains("## Phase 1\n\nAdd a login route\n\n</details>")
+        );
+        assert!(
+            description
+                .body
+                .ends_with("feature `add-auth`; verification passed.")
+        );
+
+        let description = PullRequestDescription::new("add-auth", None, " ");
+        assert!(!description.body.contains("Summary"));
+        assert!(!description.body.contains("plan"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Project-level usage quotas enforced from the cost ledger.
+//!
+//! Quotas cap the spending and the number of runs of a whole project, across
+//! features and users, in rolling windows: the last 24 hours for the daily
+//! limits and the last 7 days for the weekly one. They are checked before a
+//! run starts against the usage recorded in the [`Ledger`](crate::ledger::Ledger), so a run that is
+//! already in progress is never interrupted.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::config::QuotaConfig;
+//! use gba_core::ledger::Ledger;
+//! use gba_core::quota;
+//!
+//! let quota = QuotaConfig {
+//!     daily_max_usd: Some(20.0),
+//!     ..QuotaConfig::default()
+//! };
+//! let ledger = Ledger::new(".gba/ledger.jsonl");
+//! quota::check(&quota, &ledger.entries()?, chrono::Utc::now())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+use crate::config::QuotaConfig;
+use crate::ledger::LedgerEntry;
+
+/// Result type alias for quota checks.
+pub type Result<T> = std::result::Result<T, QuotaError>;
+
+/// Error types for quota checks.
+#[derive(Debug, Error)]
+pub enum QuotaError {
+    /// A quota has been reached.
+    #[error("{limit} quota reached: {used} of {max} used")]
+    Exceeded {
+        /// The quota that has been reached.
+        limit: QuotaLimit,
+        /// Usage in the quota's window, formatted.
+        used: String,
+        /// Configured maximum, formatted.
+        max: String,
+    },
+}
+
+/// A project quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaLimit {
+    /// Maximum cost in the last 24 hours.
+    DailyCost,
+    /// Maximum cost in the last 7 days.
+    WeeklyCost,
+    /// Maximum number of runs in the last 24 hours.
+    DailyRuns,
+}
+
+impl fmt::Display for QuotaLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DailyCost => write!(f, "Daily cost"),
+            Self::WeeklyCost => write!(f, "Weekly cost"),
+            Self::DailyRuns => write!(f, "Daily runs"),
+        }
+    }
+}
+
+/// Usage of a project in the quota windows.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct QuotaUsage {
+    /// Cost in USD in the last 24 hours.
+    pub daily_cost_usd: f64,
+    /// Cost in USD in the last 7 days.
+    pub weekly_cost_usd: f64,
+    /// Number of runs in the last 24 hours.
+    pub daily_runs: u32,
+}
+
+impl QuotaUsage {
+    /// Sum the usage of ledger entries in the windows ending at `now`.
+    #[must_use]
+    pub fn from_entries(entries: &[LedgerEntry], now: DateTime<Utc>) -> Self {
+        let day = now - Duration::days(1);
+        let week = now - Duration::days(7);
+
+        let mut usage = Self::default();
+        for entry in entries.iter().filter(|entry| entry.timestamp <= now) {
+            if entry.timestamp > week {
+                usage.weekly_cost_usd += entry.total_cost_usd;
+            }
+            if entry.timestamp > day {
+                usage.daily_cost_usd += entry.total_cost_usd;
+                usage.daily_runs += 1;
+            }
+        }
+        usage
+    }
+}
+
+/// Check that a new run can start within the project's quotas.
+///
+/// # Arguments
+///
+/// * `quota` - Configured quotas; unset quotas are not enforced.
+/// * `entries` - Entries of the cost ledger.
+/// * `now` - End of the quota windows, usually the current time.
+///
+/// # Errors
+///
+/// Returns [`QuotaError::Exceeded`] for the first quota that has been
+/// reached.
+pub fn check(quota: &QuotaConfig, entries: &[LedgerEntry], now: DateTime<Utc>) -> Result<()> {
+    let usage = QuotaUsage::from_entries(entries, now);
+
+    let costs = [
+        (
+            QuotaLimit::DailyCost,
+            usage.daily_cost_usd,
+            quota.daily_max_usd,
+        ),
+        (
+            QuotaLimit::WeeklyCost,
+            usage.weekly_cost_usd,
+            quota.weekly_max_usd,
+        ),
+    ];
+    for (limit, used, max) in costs {
+        if let Some(max) = max
+            && used >= max
+        {
+            return Err(QuotaError::Exceeded {
+                limit,
+                used: format!("${used:.2}"),
+                max: format!("${max:.2}"),
+            });
+        }
+    }
+
+    if let Some(max) = quota.max_runs_per_day
+        && usage.daily_runs >= max
+    {
+        return Err(QuotaError::Exceeded {
+            limit: QuotaLimit::DailyRuns,
+            used: usage.daily_runs.to_string(),
+            max: max.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Usage;
+
+    fn entry(hours_ago: i64, cost: f64, now: DateTime<Utc>) -> LedgerEntry {
+        let usage = Usage {
+            input_tokens: 1000,
+            output_tokens: 100,
+            total_cost_usd: cost,
+        };
+        let mut entry = LedgerEntry::new("0001", "add-auth", "planning", "sonnet", &usage);
+        entry.timestamp = now - Duration::hours(hours_ago);
+        entry
+    }
+
+    #[test]
+    fn test_quota_usage_windows() {
+        let now = Utc::now();
+        let entries = vec![
+            entry(200, 5.0, now),
+            entry(30, 2.0, now),
+            entry(1, 1.5, now),
+        ];
+
+        let usage = QuotaUsage::from_entries(&entries, now);
+        assert_eq!(usage.daily_runs, 1);
+        assert!((usage.daily_cost_usd - 1.5).abs() < f64::EPSILON);
+        assert!((usage.weekly_cost_usd - 3.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_quota_check() {
+        let now = Utc::now();
+        let entries = vec![entry(30, 6.0, now), entry(2, 1.0, now), entry(1, 1.0, now)];
+
+        assert!(check(&QuotaConfig::default(), &entries, now).is_ok());
+
+        let daily = QuotaConfig {
+            daily_max_usd: Some(2.0),
+            ..QuotaConfig::default()
+        };
+        let err = check(&daily, &entries, now).unwrap_err();
+        assert!(matches!(
+            err,
+            QuotaError::Exceeded {
+                limit: QuotaLimit::DailyCost,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Daily cost quota reached: $2.00 of $2.00 used"
+        );
+
+        let weekly = QuotaConfig {
+            daily_max_usd: Some(5.0),
+            weekly_max_usd: Some(10.0),
+            ..QuotaConfig::default()
+        };
+        assert!(check(&weekly, &entries, now).is_ok());
+
+        let runs = QuotaConfig {
+            max_runs_per_day: Some(2),
+            ..QuotaConfig::default()
+        };
+        assert!(matches!(
+            check(&runs, &entries, now),
+            Err(QuotaError::Exceeded {
+                limit: QuotaLimit::DailyRuns,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,530 @@
+//! Merging the reviews of several reviewer personas.
+//!
+//! A fan-out review runs one review template per persona, e.g. security,
+//! performance and style, over the same diff. Each review lists its findings
+//! in the bundled review format:
+//!
+//! ```markdown
+//! ## Critical Issues
+//!
+//! - [src/auth.rs:42] Password compared with `==`
+//!   - Severity: Critical
+//!   - Suggested fix: Use a constant-time comparison
+//! ```
+//!
+//! [`MergedReview::merge`] parses the findings of every review and merges
+//! them, so an issue raised by several personas is reported once, at the
+//! highest severity it was given.
+
+use std::fmt::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::protect::Violation;
+use crate::task::{Response, Usage};
+
+/// Severity of a review finding, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// A suggestion for improvement.
+    Minor,
+    /// An issue that should be addressed.
+    Important,
+    /// An issue that must be fixed before merging.
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Minor => write!(f, "Minor"),
+            Self::Important => write!(f, "Important"),
+            Self::Critical => write!(f, "Critical"),
+        }
+    }
+}
+
+impl Severity {
+    /// Parse a severity from a label such as `"Critical"` or
+    /// `"important issues"`.
+    #[must_use]
+    pub fn parse(label: &str) -> Option<Self> {
+        let label = label.to_ascii_lowercase();
+        [Self::Critical, Self::Important, Self::Minor]
+            .into_iter()
+            .find(|severity| label.contains(&severity.to_string().to_ascii_lowercase()))
+    }
+
+    /// Get the heading of the section listing findings of this severity.
+    const fn heading(self) -> &'static str {
+        match self {
+            Self::Minor => "Minor Issues",
+            Self::Important => "Important Issues",
+            Self::Critical => "Critical Issues",
+        }
+    }
+}
+
+/// A single issue raised in a review.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    /// Location of the issue, e.g. `"src/auth.rs:42"`, if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+
+    /// Description of the issue.
+    pub description: String,
+
+    /// Severity of the issue.
+    pub severity: Severity,
+
+    /// Suggested fix, if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_fix: Option<String>,
+
+    /// Personas that raised the issue.
+    #[serde(default)]
+    pub personas: Vec<String>,
+}
+
+impl Finding {
+    /// Key identifying the same issue across reviews.
+    ///
+    /// Case, whitespace and trailing punctuation are ignored.
+    fn key(&self) -> (String, String) {
+        (
+            normalize(self.location.as_deref().unwrap_or_default()),
+            normalize(&self.description),
+        )
+    }
+}
+
+/// Parse the findings of a review.
+///
+/// Findings are the top-level list items of the `Critical Issues`,
+/// `Important Issues` and `Minor Issues` sections. A `Severity:` sub-item
+/// overrides the severity of the section.
+///
+/// # Arguments
+///
+/// * `persona` - Persona that wrote the review, recorded on each finding.
+/// * `content` - Review in the bundled review format.
+#[must_use]
+pub fn parse_findings(persona: &str, content: &str) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    let mut section = None;
+
+    for line in content.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            section = heading
+                .to_ascii_lowercase()
+                .contains("issues")
+                .then(|| Severity::parse(heading))
+                .flatten();
+            continue;
+        }
+        let Some(severity) = section else {
+            continue;
+        };
+
+        let indented = line.starts_with([' ', '\t']);
+        let Some(item) = line.trim_start().strip_prefix("- ") else {
+            continue;
+        };
+        let item = item.trim();
+
+        if !indented {
+            let (location, description) = split_location(item);
+            if description.is_empty() {
+                continue;
+            }
+            findings.push(Finding {
+                location,
+                description,
+                severity,
+                suggested_fix: None,
+                personas: vec![persona.to_string()],
+            });
+        } else if let Some(finding) = findings.last_mut() {
+            if let Some(label) = strip_label(item, "severity:") {
+                finding.severity = Severity::parse(label).unwrap_or(finding.severity);
+            } else if let Some(fix) = strip_label(item, "suggested fix:") {
+                finding.suggested_fix = Some(fix.to_string());
+            }
+        }
+    }
+
+    findings
+}
+
+/// The review of a single persona.
+#[derive(Debug)]
+pub struct PersonaReview {
+    /// Persona name, e.g. `"security"`.
+    pub persona: String,
+
+    /// The agent's response, or the error the review failed with.
+    pub result: Result<Response>,
+}
+
+/// The merged reviews of several personas.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedReview {
+    /// De-duplicated findings, most severe first.
+    pub findings: Vec<Finding>,
+
+    /// Summary of each persona's review, by persona.
+    #[serde(default)]
+    pub summaries: Vec<(String, String)>,
+
+    /// Personas whose review failed, with the error message.
+    #[serde(default)]
+    pub failures: Vec<(String, String)>,
+
+    /// Whether no persona found critical issues or requested changes.
+    pub approved: bool,
+
+    /// Combined usage of all reviews.
+    #[serde(default)]
+    pub usage: Usage,
+
+    /// Tool calls of all reviews denied for modifying protected paths.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
+
+impl MergedReview {
+    /// Merge the reviews of several personas.
+    ///
+    /// Findings with the same location and description are merged: the
+    /// highest severity wins and every persona raising it is recorded.
+    #[must_use]
+    pub fn merge(reviews: Vec<PersonaReview>) -> Self {
+        let mut merged = Self {
+            approved: true,
+            ..Self::default()
+        };
+
+        for review in reviews {
+            let response = match review.result {
+                Ok(response) => response,
+                Err(e) => {
+                    merged.failures.push((review.persona, e.to_string()));
+                    continue;
+                }
+            };
+
+            merged.usage.input_tokens += response.usage.input_tokens;
+            merged.usage.output_tokens += response.usage.output_tokens;
+            merged.usage.total_cost_usd += response.usage.total_cost_usd;
+            merged.violations.extend(response.violations);
+            if requests_changes(&response.content) {
+                merged.approved = false;
+            }
+            if let Some(summary) = section(&response.content, "summary") {
+                merged.summaries.push((review.persona.clone(), summary));
+            }
+            for finding in parse_findings(&review.persona, &response.content) {
+                merged.add(finding);
+            }
+        }
+
+        // Stable, so findings of the same severity keep their order
+        merged.findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+        if merged.count(Severity::Critical) > 0 {
+            merged.approved = false;
+        }
+        merged
+    }
+
+    /// Get the number of findings of a severity.
+    #[must_use]
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    /// Get the number of findings of a severity or a more severe one.
+    #[must_use]
+    pub fn count_at_least(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity >= severity)
+            .count()
+    }
+
+    /// Render the merged review as markdown, in the bundled review format.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Code Review\n\n## Summary\n\n");
+        for (persona, summary) in &self.summaries {
+            let _ = writeln!(out, "**{persona}**: {summary}\n");
+        }
+        for (persona, message) in &self.failures {
+            let _ = writeln!(out, "**{persona}**: review failed: {message}\n");
+        }
+
+        for severity in [Severity::Critical, Severity::Important, Severity::Minor] {
+            let _ = writeln!(out, "## {}\n", severity.heading());
+            let findings = self.findings.iter().filter(|f| f.severity == severity);
+            let mut empty = true;
+            for finding in findings {
+                empty = false;
+                match &finding.location {
+                    Some(location) => {
+                        let _ = writeln!(out, "- [{location}] {}", finding.description);
+                    }
+                    None => {
+                        let _ = writeln!(out, "- {}", finding.description);
+                    }
+                }
+                let _ = writeln!(out, "  - Severity: {severity}");
+                let _ = writeln!(out, "  - Raised by: {}", finding.personas.join(", "));
+                if let Some(fix) = &finding.suggested_fix {
+                    let _ = writeln!(out, "  - Suggested fix: {fix}");
+                }
+            }
+            if empty {
+                out.push_str("None.\n");
+            }
+            out.push('\n');
+        }
+
+        let status = if self.approved {
+            "APPROVED"
+        } else {
+            "REQUEST CHANGES"
+        };
+        let _ = writeln!(out, "## Review Status\n\n{status}");
+        out
+    }
+
+    /// Convert the merged review into a response with the combined usage.
+    #[must_use]
+    pub fn to_response(&self) -> Response {
+        Response {
+            content: self.to_markdown(),
+            usage: self.usage.clone(),
+            violations: self.violations.clone(),
+            ..Response::default()
+        }
+    }
+
+    /// Add a finding, merging it into an earlier one for the same issue.
+    fn add(&mut self, finding: Finding) {
+        let key = finding.key();
+        let Some(existing) = self.findings.iter_mut().find(|f| f.key() == key) else {
+            self.findings.push(finding);
+            return;
+        };
+
+        existing.severity = existing.severity.max(finding.severity);
+        if existing.suggested_fix.is_none() {
+            existing.suggested_fix = finding.suggested_fix;
+        }
+        for persona in finding.personas {
+            if !existing.personas.contains(&persona) {
+                existing.personas.push(persona);
+            }
+        }
+    }
+}
+
+/// Split a finding into its `[location]` prefix and description.
+fn split_location(item: &str) -> (Option<String>, String) {
+    if let Some(rest) = item.strip_prefix('[')
+        && let Some((location, description)) = rest.split_once(']')
+    {
+        let location = location.trim();
+        return (
+            (!location.is_empty()).then(|| location.to_string()),
+            description.trim().to_string(),
+        );
+    }
+    (None, item.to_string())
+}
+
+/// Strip a case-insensitive `label` prefix from a list item.
+fn strip_label<'a>(item: &'a str, label: &str) -> Option<&'a str> {
+    let prefix = item.get(..label.len())?;
+    prefix
+        .eq_ignore_ascii_case(label)
+        .then(|| item[label.len()..].trim())
+}
+
+/// Get the text of a `## ` section, trimmed, if it has any.
+fn section(content: &str, name: &str) -> Option<String> {
+    let mut lines = content.lines().skip_while(|line| {
+        !line
+            .strip_prefix("## ")
+            .is_some_and(|heading| heading.trim().eq_ignore_ascii_case(name))
+    });
+    lines.next()?;
+    let text = lines
+        .take_while(|line| !line.starts_with("## ") && !line.starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Check whether a review requests changes in its status section.
+fn requests_changes(content: &str) -> bool {
+    section(content, "review status")
+        .is_some_and(|status| status.to_ascii_uppercase().contains("REQUEST CHANGES"))
+}
+
+/// Normalize text for comparing findings.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['.', '!', ';', ':'])
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CoreError;
+
+    const SECURITY: &str = "\
+# Code Review: add-auth
+
+## Summary
+
+Token handling needs work.
+
+## Critical Issues
+
+- [src/auth.rs:42] Password compared with `==`
+  - Severity: Critical
+  - Suggested fix: Use a constant-time comparison
+
+## Important Issues
+
+- [src/auth.rs:10] Missing input validation.
+  - Suggested fix: Validate the username
+
+## Minor Issues
+
+## Review Status
+
+REQUEST CHANGES
+";
+
+    const PERFORMANCE: &str = "\
+## Summary
+
+Mostly fine.
+
+## Important Issues
+
+- [src/auth.rs:42] password compared with `==`
+  - Severity: Important
+- [src/db.rs:7] Query inside a loop
+  - Severity: Minor
+
+## Review Status
+
+APPROVED
+";
+
+    fn review(persona: &str, content: &str) -> PersonaReview {
+        PersonaReview {
+            persona: persona.to_string(),
+            result: Ok(Response {
+                content: content.to_string(),
+                usage: Usage {
+                    input_tokens: 100,
+                    output_tokens: 10,
+                    total_cost_usd: 0.5,
+                },
+                ..Response::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_parse_findings() {
+        let findings = parse_findings("security", SECURITY);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].location.as_deref(), Some("src/auth.rs:42"));
+        assert_eq!(findings[0].description, "Password compared with `==`");
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(
+            findings[0].suggested_fix.as_deref(),
+            Some("Use a constant-time comparison")
+        );
+        assert_eq!(findings[1].severity, Severity::Important);
+        assert_eq!(findings[1].personas, vec!["security"]);
+    }
+
+    #[test]
+    fn test_parse_findings_severity_overrides_section() {
+        let findings = parse_findings("performance", PERFORMANCE);
+        assert_eq!(findings[1].severity, Severity::Minor);
+    }
+
+    #[test]
+    fn test_merge_deduplicates_findings() {
+        let merged = MergedReview::merge(vec![
+            review("security", SECURITY),
+            review("performance", PERFORMANCE),
+        ]);
+
+        assert_eq!(merged.findings.len(), 3);
+        let first = &merged.findings[0];
+        assert_eq!(first.severity, Severity::Critical);
+        assert_eq!(first.personas, vec!["security", "performance"]);
+        assert_eq!(merged.findings[2].severity, Severity::Minor);
+        assert_eq!(merged.count_at_least(Severity::Critical), 1);
+        assert_eq!(merged.count_at_least(Severity::Important), 2);
+        assert_eq!(merged.count_at_least(Severity::Minor), 3);
+        assert!(!merged.approved);
+        assert_eq!(merged.usage.input_tokens, 200);
+        assert_eq!(merged.summaries.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_records_failures() {
+        let merged = MergedReview::merge(vec![
+            review("performance", PERFORMANCE),
+            PersonaReview {
+                persona: "style".to_string(),
+                result: Err(CoreError::ClaudeAgent("timed out".to_string())),
+            },
+        ]);
+
+        assert!(merged.approved);
+        assert_eq!(
+            merged.failures,
+            vec![(
+                "style".to_string(),
+                "Claude Agent SDK error: timed out".to_string()
+            )]
+        );
+        assert!(merged.to_markdown().contains("**style**: review failed"));
+    }
+
+    #[test]
+    fn test_merged_review_markdown_round_trips() {
+        let merged = MergedReview::merge(vec![
+            review("security", SECURITY),
+            review("performance", PERFORMANCE),
+        ]);
+        let markdown = merged.to_markdown();
+
+        assert!(markdown.contains("- [src/auth.rs:42] Password compared with `==`"));
+        assert!(markdown.contains("  - Raised by: security, performance"));
+        assert!(markdown.ends_with("REQUEST CHANGES\n"));
+        assert_eq!(parse_findings("merged", &markdown).len(), 3);
+    }
+}
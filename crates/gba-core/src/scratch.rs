@@ -0,0 +1,187 @@
+//! Per-run scratch directories in `.gba/tmp/<run-id>`.
+//!
+//! Agents write intermediate files while they work: experiments, notes,
+//! downloaded logs. Left in the working directory, they litter the
+//! repository and end up in commits. Each run therefore gets its own
+//! [`ScratchDir`], which the agent is told to use for such files and may
+//! access even when the sandbox restricts paths.
+//!
+//! When the run finishes, [`ScratchDir::finish`] keeps or removes the
+//! directory as `scratch.retention` says, and [`prune`] removes the
+//! directories of earlier runs older than `scratch.maxAgeDays`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::config::ScratchConfig;
+//! use gba_core::scratch::{self, ScratchDir};
+//!
+//! let config = ScratchConfig::default();
+//! scratch::prune(".gba/tmp".as_ref(), config.max_age_days)?;
+//! let scratch = ScratchDir::create(".gba/tmp".as_ref(), "20250101-120000")?;
+//! // ... run the task with `scratch.path()` ...
+//! scratch.finish(true, config.retention)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tracing::debug;
+
+use crate::config::ScratchRetention;
+
+/// Directory of the scratch directories in a project's `.gba` directory.
+pub const SCRATCH_DIR: &str = "tmp";
+
+/// Scratch directory of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScratchDir {
+    /// Path of the directory.
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Create the scratch directory of a run, or reuse it if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Directory of the scratch directories, e.g. `.gba/tmp`.
+    /// * `run_id` - Run identifier, naming the directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn create(root: &Path, run_id: &str) -> io::Result<Self> {
+        let path = root.join(run_id);
+        std::fs::create_dir_all(&path)?;
+        // Tools resolve the directory from other working directories
+        let path = path.canonicalize().unwrap_or(path);
+        debug!("Created scratch directory {}", path.display());
+        Ok(Self { path })
+    }
+
+    /// Get the path of the directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keep or remove the directory at the end of its run.
+    ///
+    /// # Arguments
+    ///
+    /// * `success` - Whether the run succeeded.
+    /// * `retention` - What to keep.
+    ///
+    /// # Returns
+    ///
+    /// Whether the directory was removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be removed.
+    pub fn finish(&self, success: bool, retention: ScratchRetention) -> io::Result<bool> {
+        let remove = match retention {
+            ScratchRetention::Delete => true,
+            ScratchRetention::KeepFailed => success,
+            ScratchRetention::Keep => false,
+        };
+        if !remove {
+            return Ok(false);
+        }
+        match std::fs::remove_dir_all(&self.path) {
+            Ok(()) => {
+                debug!("Removed scratch directory {}", self.path.display());
+                Ok(true)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Remove the scratch directories last modified more than `max_age_days`
+/// ago.
+///
+/// # Arguments
+///
+/// * `root` - Directory of the scratch directories, e.g. `.gba/tmp`.
+/// * `max_age_days` - Age in days after which a directory is removed; 0
+///   keeps every directory.
+///
+/// # Returns
+///
+/// The removed directories.
+///
+/// # Errors
+///
+/// Returns an error if `root` cannot be read or a directory cannot be
+/// removed.
+pub fn prune(root: &Path, max_age_days: u32) -> io::Result<Vec<PathBuf>> {
+    if max_age_days == 0 || !root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let max_age = Duration::from_secs(u64::from(max_age_days) * 24 * 60 * 60);
+    let now = SystemTime::now();
+
+    let mut removed = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if metadata.is_dir() && age > max_age {
+            std::fs::remove_dir_all(entry.path())?;
+            removed.push(entry.path());
+        }
+    }
+    removed.sort();
+    debug!("Pruned {} scratch directories", removed.len());
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_dir_retention() {
+        let root = std::env::temp_dir().join("gba-test-scratch");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let scratch = ScratchDir::create(&root, "run-1").unwrap();
+        assert!(scratch.path().is_dir());
+        std::fs::write(scratch.path().join("notes.md"), "notes").unwrap();
+
+        assert!(!scratch.finish(false, ScratchRetention::KeepFailed).unwrap());
+        assert!(scratch.path().is_dir());
+        assert!(!scratch.finish(true, ScratchRetention::Keep).unwrap());
+        assert!(scratch.finish(true, ScratchRetention::KeepFailed).unwrap());
+        assert!(!scratch.path().exists());
+
+        let scratch = ScratchDir::create(&root, "run-2").unwrap();
+        assert!(scratch.finish(false, ScratchRetention::Delete).unwrap());
+        assert!(!scratch.path().exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_prune_keeps_recent_directories() {
+        let root = std::env::temp_dir().join("gba-test-scratch-prune");
+        let _ = std::fs::remove_dir_all(&root);
+        ScratchDir::create(&root, "run-1").unwrap();
+
+        assert!(prune(&root, 0).unwrap().is_empty());
+        assert!(prune(&root, 1).unwrap().is_empty());
+        assert!(root.join("run-1").is_dir());
+        assert!(prune(&root.join("missing"), 1).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
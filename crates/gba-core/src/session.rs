@@ -0,0 +1,388 @@
+//! Persistent sessions of agent tasks.
+//!
+//! A session records the full message history of a task as the messages
+//! stream in, one JSON entry per line, to `.gba/sessions/<id>.jsonl`, where
+//! the id is the SDK's session id reported in [`crate::Response::session_id`].
+//! Unlike a transcript, written once the task finishes, a session survives a
+//! crash or reboot: re-open it with [`Session::open`] and continue the
+//! conversation with [`crate::Agent::resume_session`].
+//!
+//! # Format
+//!
+//! Every line is an object with a `type` field:
+//!
+//! | `type`    | Fields                                                |
+//! |-----------|-------------------------------------------------------|
+//! | `start`   | `version`, `id`, `model`, `startedAt`, `prompt`       |
+//! | `message` | `message`, an SDK message as received                 |
+//! | `resume`  | `resumedAt`, `prompt`                                 |
+//!
+//! The start entry is always the first line. A last line cut short by a
+//! crash is ignored when reading.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use claude_agent_sdk_rs::Message;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Current session schema version.
+pub const SESSION_VERSION: u32 = 1;
+
+/// Result type alias for session operations.
+pub type Result<T> = std::result::Result<T, SessionError>;
+
+/// Error types for session operations.
+#[derive(Debug, Error)]
+pub enum SessionError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// No session with the id was recorded.
+    #[error("Session not found: {0}")]
+    NotFound(String),
+
+    /// A session line could not be serialized or parsed.
+    #[error("Invalid session entry at line {line}: {source}")]
+    Entry {
+        /// Line number, starting at 1 (0 when serializing).
+        line: usize,
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+
+    /// The session doesn't start with a start entry.
+    #[error("Session has no start entry")]
+    MissingStart,
+
+    /// The session was written with a newer schema.
+    #[error("Unsupported session version {0} (supported up to {SESSION_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
+/// A single session entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SessionEntry {
+    /// Session information, always the first entry.
+    Start {
+        /// Schema version.
+        version: u32,
+        /// Session id.
+        id: String,
+        /// Model used for the task.
+        model: String,
+        /// When the task started.
+        started_at: DateTime<Utc>,
+        /// Full prompt sent to the agent.
+        prompt: String,
+    },
+
+    /// Message received from the Claude Agent SDK.
+    Message {
+        /// The message.
+        message: Message,
+    },
+
+    /// The session was resumed.
+    Resume {
+        /// When the session was resumed.
+        resumed_at: DateTime<Utc>,
+        /// Prompt continuing the conversation.
+        prompt: String,
+    },
+}
+
+/// A session recorded to a JSONL file.
+///
+/// Entries are appended to the file as they are recorded, so the session
+/// holds everything received up to a crash.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba_core::session::Session;
+/// use std::path::Path;
+///
+/// let session = Session::open(Path::new(".gba/sessions"), "session-1")?;
+/// println!("{} messages", session.messages()?.len());
+/// # Ok::<(), gba_core::session::SessionError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// Session id.
+    id: String,
+    /// Session file.
+    path: PathBuf,
+}
+
+impl Session {
+    /// Start recording a session, replacing any session with the same id.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory of the sessions, e.g. `.gba/sessions`.
+    /// * `id` - Session id reported by the SDK.
+    /// * `model` - Model used for the task.
+    /// * `prompt` - Full prompt sent to the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session file cannot be written.
+    pub fn create(dir: &Path, id: &str, model: &str, prompt: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let session = Self {
+            id: id.to_string(),
+            path: Self::path_in(dir, id),
+        };
+        std::fs::write(&session.path, "")?;
+        session.append(&SessionEntry::Start {
+            version: SESSION_VERSION,
+            id: id.to_string(),
+            model: model.to_string(),
+            started_at: Utc::now(),
+            prompt: prompt.to_string(),
+        })?;
+        tracing::debug!("Recording session to {}", session.path.display());
+        Ok(session)
+    }
+
+    /// Open a recorded session.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory of the sessions.
+    /// * `id` - Session id.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SessionError::NotFound`] if no session with the id was
+    /// recorded, or an error if the session cannot be read.
+    pub fn open(dir: &Path, id: &str) -> Result<Self> {
+        let path = Self::path_in(dir, id);
+        if !path.is_file() {
+            return Err(SessionError::NotFound(id.to_string()));
+        }
+        let session = Self {
+            id: id.to_string(),
+            path,
+        };
+        session.entries()?;
+        Ok(session)
+    }
+
+    /// Get the path of a session file.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory of the sessions.
+    /// * `id` - Session id.
+    #[must_use]
+    pub fn path_in(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{id}.jsonl"))
+    }
+
+    /// Get the session id.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get the session file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a message received from the Claude Agent SDK.
+    ///
+    /// Stream events and control requests are not recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be written.
+    pub fn record(&self, message: &Message) -> Result<()> {
+        if matches!(
+            message,
+            Message::StreamEvent(_) | Message::ControlCancelRequest(_)
+        ) {
+            return Ok(());
+        }
+        self.append(&SessionEntry::Message {
+            message: message.clone(),
+        })
+    }
+
+    /// Record that the session was resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - Prompt continuing the conversation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be written.
+    pub fn record_resume(&self, prompt: &str) -> Result<()> {
+        self.append(&SessionEntry::Resume {
+            resumed_at: Utc::now(),
+            prompt: prompt.to_string(),
+        })
+    }
+
+    /// Read the entries, starting with the start entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, a line before the last
+    /// is invalid, the start entry is missing, or the session was written
+    /// with a newer schema version.
+    pub fn entries(&self) -> Result<Vec<SessionEntry>> {
+        let content = std::fs::read_to_string(&self.path)?;
+        let lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+        let mut entries = Vec::with_capacity(lines.len());
+        for (index, (i, line)) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                // The last line may have been cut short by a crash
+                Err(_) if index + 1 == lines.len() && !content.ends_with('\n') => {
+                    tracing::warn!("Ignoring truncated last line of {}", self.path.display());
+                }
+                Err(source) => {
+                    return Err(SessionError::Entry {
+                        line: i + 1,
+                        source,
+                    });
+                }
+            }
+        }
+
+        match entries.first() {
+            Some(SessionEntry::Start { version, .. }) if *version > SESSION_VERSION => {
+                Err(SessionError::UnsupportedVersion(*version))
+            }
+            Some(SessionEntry::Start { .. }) => Ok(entries),
+            _ => Err(SessionError::MissingStart),
+        }
+    }
+
+    /// Read the recorded messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entries cannot be read, see [`Self::entries`].
+    pub fn messages(&self) -> Result<Vec<Message>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter_map(|entry| match entry {
+                SessionEntry::Message { message } => Some(message),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Get the latest SDK session id reported in the messages, to resume
+    /// the conversation from; resuming may start a new SDK session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entries cannot be read, see [`Self::entries`].
+    pub fn latest_session_id(&self) -> Result<String> {
+        let latest = self
+            .messages()?
+            .iter()
+            .rev()
+            .find_map(message_session_id)
+            .map(str::to_string);
+        Ok(latest.unwrap_or_else(|| self.id.clone()))
+    }
+
+    /// Append an entry to the session file.
+    fn append(&self, entry: &SessionEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|source| SessionError::Entry { line: 0, source })?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Get the SDK session id a message reports, if any.
+#[must_use]
+pub fn message_session_id(message: &Message) -> Option<&str> {
+    match message {
+        Message::Assistant(msg) => msg.session_id.as_deref(),
+        Message::Result(result) => Some(&result.session_id),
+        Message::System(system) => system.session_id.as_deref(),
+        Message::User(_) | Message::StreamEvent(_) | Message::ControlCancelRequest(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(value: serde_json::Value) -> Message {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_session_roundtrip_and_resume_id() {
+        let dir = std::env::temp_dir().join(format!("gba-test-session-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let session = Session::create(&dir, "session-1", "sonnet", "Add a login page").unwrap();
+        session
+            .record(&message(serde_json::json!({
+                "type": "assistant",
+                "message": {"content": [{"type": "text", "text": "Reading the router."}]},
+                "session_id": "session-1",
+            })))
+            .unwrap();
+        session.record_resume("Continue").unwrap();
+        session
+            .record(&message(serde_json::json!({
+                "type": "assistant",
+                "message": {"content": [{"type": "text", "text": "Done."}]},
+                "session_id": "session-2",
+            })))
+            .unwrap();
+
+        let session = Session::open(&dir, "session-1").unwrap();
+        let entries = session.entries().unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(
+            matches!(&entries[0], SessionEntry::Start { prompt, .. } if prompt == "Add a login page")
+        );
+        assert!(matches!(&entries[2], SessionEntry::Resume { .. }));
+        assert_eq!(session.messages().unwrap().len(), 2);
+        assert_eq!(session.latest_session_id().unwrap(), "session-2");
+
+        // A line cut short by a crash is ignored
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(session.path())
+            .unwrap();
+        file.write_all(b"{\"type\":\"message\",\"mess").unwrap();
+        assert_eq!(session.entries().unwrap().len(), 4);
+
+        assert!(matches!(
+            Session::open(&dir, "missing"),
+            Err(SessionError::NotFound(id)) if id == "missing"
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,747 @@
+//! Feature state persisted in `.gba/features/<id>/state.yml`.
+//!
+//! The state file tracks the progress of a feature across runs so that
+//! interrupted tasks can be resumed. Its layout follows the schema in
+//! `specs/design.md`. A [`StateTracker`] saves the progress of a running
+//! agent after each turn, so an interrupted run leaves its turns, last step
+//! and session behind for `gba run --resume`.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use gba_pm::ResumeContext;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::protect::Violation;
+use crate::task::GenerationConfig;
+
+/// Result type alias for state operations.
+pub type Result<T> = std::result::Result<T, StateError>;
+
+/// Error types for state operations.
+#[derive(Debug, Error)]
+pub enum StateError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Serialization/deserialization error.
+    #[error("Serialization error: {0}")]
+    Serde(#[from] serde_yaml::Error),
+
+    /// Another feature has the same identifier.
+    #[error("Feature '{name}' has the same id {id} as feature '{existing}'; choose another name")]
+    IdCollision {
+        /// Shared feature identifier.
+        id: String,
+        /// Name of the feature the state belongs to.
+        existing: String,
+        /// Name of the feature being loaded.
+        name: String,
+    },
+}
+
+/// Execution status of a feature task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// Task has not started.
+    #[default]
+    Pending,
+    /// Task is running or was interrupted.
+    InProgress,
+    /// Task completed successfully.
+    Completed,
+    /// Task failed.
+    Failed,
+}
+
+/// Persistent state of a feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureState {
+    /// Feature identification.
+    pub feature: FeatureInfo,
+
+    /// Task configuration.
+    #[serde(default)]
+    pub task: TaskInfo,
+
+    /// Current status.
+    #[serde(default)]
+    pub status: StatusInfo,
+
+    /// Execution statistics.
+    #[serde(default)]
+    pub execution: ExecutionInfo,
+
+    /// Final results, populated when completed.
+    #[serde(default)]
+    pub result: Option<ResultInfo>,
+
+    /// Context for resumption.
+    #[serde(default)]
+    pub context: StateContext,
+
+    /// Iterations of the last fix loop between verification and
+    /// implementation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fix_loop: Vec<FixIteration>,
+
+    /// Phases run by the last pipeline, from planning to verification.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pipeline: Vec<PipelineStep>,
+
+    /// Timestamps.
+    pub timestamps: Timestamps,
+}
+
+/// Feature identification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureInfo {
+    /// Feature name.
+    pub name: String,
+
+    /// Feature identifier, e.g. `"0003"`.
+    pub id: String,
+
+    /// Feature description.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Task configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TaskInfo {
+    /// Task kind, e.g. `"implementation"`.
+    #[serde(default)]
+    pub kind: String,
+
+    /// Task description.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Template used for the task.
+    #[serde(default)]
+    pub template: String,
+
+    /// Cost attribution tags of the run, see [`crate::ledger::merge_tags`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Current status.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StatusInfo {
+    /// Execution status.
+    #[serde(default)]
+    pub state: TaskStatus,
+
+    /// Current phase.
+    #[serde(default)]
+    pub current_phase: Option<String>,
+
+    /// Current step within the phase.
+    #[serde(default)]
+    pub current_step: Option<String>,
+
+    /// Human-readable status message.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Execution statistics.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ExecutionInfo {
+    /// Identifier of the most recent run, e.g. `"20260224T103000Z-1a2b"`.
+    #[serde(default)]
+    pub run_id: Option<String>,
+
+    /// Number of agent turns.
+    #[serde(default)]
+    pub turns: u32,
+
+    /// Agent session of the most recent run, resumed by `gba run --resume`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    /// Cost breakdown.
+    #[serde(default)]
+    pub cost: CostInfo,
+
+    /// Tool calls of the most recent run denied for modifying protected
+    /// paths.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+
+    /// Generation settings of the most recent run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<GenerationConfig>,
+}
+
+/// Cost breakdown.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CostInfo {
+    /// Input tokens used.
+    #[serde(default)]
+    pub input_tokens: u64,
+
+    /// Output tokens used.
+    #[serde(default)]
+    pub output_tokens: u64,
+
+    /// Total cost in USD.
+    #[serde(default)]
+    pub total_cost_usd: f64,
+}
+
+/// Final results of a completed task.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ResultInfo {
+    /// Link to the pull request.
+    #[serde(default)]
+    pub pr_link: Option<String>,
+
+    /// Summary of the work done.
+    #[serde(default)]
+    pub summary: Option<String>,
+
+    /// Number of files changed.
+    #[serde(default)]
+    pub files_changed: u32,
+
+    /// Number of commits created.
+    #[serde(default)]
+    pub commits_created: u32,
+}
+
+/// Context for resumption.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StateContext {
+    /// Worktree the feature is developed in.
+    #[serde(default)]
+    pub worktree: Option<WorktreeInfo>,
+
+    /// Commit checked out in the working directory when the last run started.
+    #[serde(default)]
+    pub head_commit: Option<String>,
+}
+
+/// Worktree information.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorktreeInfo {
+    /// Worktree path.
+    pub path: PathBuf,
+
+    /// Worktree branch.
+    pub branch: String,
+}
+
+/// An iteration of a fix loop: a verification, followed by an
+/// implementation fixing its failures unless it passed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixIteration {
+    /// Iteration number, starting at 1.
+    pub iteration: u32,
+
+    /// Run identifier of the verification.
+    #[serde(default)]
+    pub verification_run_id: Option<String>,
+
+    /// Whether the verification passed.
+    pub passed: bool,
+
+    /// Verdict of the agent, e.g. `"NEEDS WORK"`.
+    #[serde(default)]
+    pub verdict: Option<String>,
+
+    /// Names of the verification commands that failed.
+    #[serde(default)]
+    pub failed_commands: Vec<String>,
+
+    /// Run identifier of the implementation fixing the failures.
+    #[serde(default)]
+    pub fix_run_id: Option<String>,
+
+    /// Cost of the loop so far in USD, including this iteration.
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+/// A phase run by a pipeline chaining planning, implementation and
+/// verification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineStep {
+    /// Phase, e.g. `"planning"`.
+    pub phase: String,
+
+    /// Run identifier of the phase.
+    #[serde(default)]
+    pub run_id: Option<String>,
+
+    /// Whether the phase succeeded; a failed phase ends the pipeline.
+    pub success: bool,
+
+    /// Cost of the phase in USD.
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+/// Timestamps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timestamps {
+    /// When the state was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the state was last updated.
+    pub updated_at: DateTime<Utc>,
+
+    /// When the task completed.
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl FeatureState {
+    /// Create a new pending state for a feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Feature name.
+    /// * `id` - Feature identifier.
+    #[must_use]
+    pub fn new(name: impl Into<String>, id: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            feature: FeatureInfo {
+                name: name.into(),
+                id: id.into(),
+                description: None,
+            },
+            task: TaskInfo::default(),
+            status: StatusInfo::default(),
+            execution: ExecutionInfo::default(),
+            result: None,
+            context: StateContext::default(),
+            fix_loop: Vec::new(),
+            pipeline: Vec::new(),
+            timestamps: Timestamps {
+                created_at: now,
+                updated_at: now,
+                completed_at: None,
+            },
+        }
+    }
+
+    /// Start a new run, assigning it a fresh run identifier.
+    ///
+    /// The identifier combines the start time with the process id, so runs
+    /// of the same feature sort chronologically. Violations of the previous
+    /// run are cleared.
+    ///
+    /// # Returns
+    ///
+    /// The new run identifier.
+    pub fn start_run(&mut self) -> String {
+        let run_id = format!(
+            "{}-{:04x}",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            std::process::id() & 0xffff
+        );
+        self.execution.run_id = Some(run_id.clone());
+        self.execution.violations.clear();
+        run_id
+    }
+
+    /// Record a turn of the agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - Session of the agent, if known.
+    /// * `step` - What the agent did in the turn, e.g. its last tool call.
+    pub fn record_turn(&mut self, session_id: Option<&str>, step: Option<&str>) {
+        self.execution.turns += 1;
+        if let Some(session_id) = session_id {
+            self.execution.session_id = Some(session_id.to_string());
+        }
+        if let Some(step) = step {
+            self.status.current_step = Some(step.to_string());
+        }
+    }
+
+    /// Check whether the feature has a task to resume: one that is in
+    /// progress or failed.
+    #[must_use]
+    pub fn is_resumable(&self) -> bool {
+        matches!(
+            self.status.state,
+            TaskStatus::InProgress | TaskStatus::Failed
+        )
+    }
+
+    /// Record a phase run by a pipeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - Phase, e.g. `"planning"`.
+    /// * `before` - State of the feature before the phase ran.
+    /// * `success` - Whether the phase succeeded.
+    pub fn record_pipeline_step(&mut self, phase: &str, before: &Self, success: bool) {
+        // A phase failing before it started leaves the previous run behind
+        let run_id = self
+            .execution
+            .run_id
+            .clone()
+            .filter(|run_id| before.execution.run_id.as_ref() != Some(run_id));
+        self.pipeline.push(PipelineStep {
+            phase: phase.to_string(),
+            run_id,
+            success,
+            cost_usd: self.execution.cost.total_cost_usd - before.execution.cost.total_cost_usd,
+        });
+    }
+
+    /// Load state from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    #[tracing::instrument(skip(path))]
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let state = serde_yaml::from_str(&content)?;
+        tracing::debug!("Loaded feature state from {}", path.display());
+        Ok(state)
+    }
+
+    /// Load state from a file, or create a new one if the file doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the state file.
+    /// * `name` - Feature name, checked against existing state.
+    /// * `id` - Feature identifier, used for new state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing file cannot be read or parsed, or
+    /// belongs to another feature with the same identifier.
+    pub fn load_or_new(path: &Path, name: &str, id: &str) -> Result<Self> {
+        if path.exists() {
+            let state = Self::load(path)?;
+            if state.feature.name != name {
+                return Err(StateError::IdCollision {
+                    id: id.to_string(),
+                    existing: state.feature.name,
+                    name: name.to_string(),
+                });
+            }
+            Ok(state)
+        } else {
+            Ok(Self::new(name, id))
+        }
+    }
+
+    /// Save state to a file, updating the `updated_at` timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state cannot be written.
+    #[tracing::instrument(skip(self, path))]
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        self.timestamps.updated_at = Utc::now();
+        let content = serde_yaml::to_string(self)?;
+        crate::atomic::write_with_backup(path, content)?;
+
+        tracing::debug!("Saved feature state to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Where the feature's task stopped, for the `resume` template.
+///
+/// Without a worktree, the worktree path and branch are left empty; the
+/// implementation plan and tools are left to the caller.
+impl From<&FeatureState> for ResumeContext {
+    fn from(state: &FeatureState) -> Self {
+        let (worktree_path, worktree_branch) = state
+            .context
+            .worktree
+            .as_ref()
+            .map(|worktree| (worktree.path.display().to_string(), worktree.branch.clone()))
+            .unwrap_or_default();
+        Self {
+            feature_name: state.feature.name.clone(),
+            feature_id: state.feature.id.clone(),
+            feature_description: state.feature.description.clone().unwrap_or_default(),
+            task_kind: state.task.kind.clone(),
+            current_phase: state.status.current_phase.clone().unwrap_or_default(),
+            current_step: state.status.current_step.clone().unwrap_or_default(),
+            turns_so_far: state.execution.turns,
+            cost_so_far: state.execution.cost.total_cost_usd,
+            worktree_path,
+            worktree_branch,
+            ..Self::default()
+        }
+    }
+}
+
+/// Saves the progress of a running agent to a state file after each turn.
+///
+/// The file is read and written on every turn, so the state held by the
+/// caller is not affected; failures are logged, since they must not
+/// interrupt the agent.
+#[derive(Debug, Clone)]
+pub struct StateTracker {
+    /// Path of the state file.
+    path: PathBuf,
+}
+
+impl StateTracker {
+    /// Create a tracker for a state file.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Get the path of the state file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a turn of the agent in the state file.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - Session of the agent, if known.
+    /// * `step` - What the agent did in the turn.
+    pub fn record_turn(&self, session_id: Option<&str>, step: Option<&str>) {
+        self.update(|state| state.record_turn(session_id, step));
+    }
+
+    /// Record the current step of the agent within a turn.
+    pub fn record_step(&self, step: &str) {
+        self.update(|state| state.status.current_step = Some(step.to_string()));
+    }
+
+    /// Copy the progress recorded in the state file into a state, so saving
+    /// the state does not undo it.
+    ///
+    /// Failures are logged, leaving the state unchanged.
+    pub fn sync(&self, state: &mut FeatureState) {
+        match FeatureState::load(&self.path) {
+            Ok(saved) => {
+                state.execution.turns = saved.execution.turns;
+                state.execution.session_id = saved.execution.session_id;
+                state.status.current_step = saved.status.current_step;
+            }
+            Err(e) => tracing::warn!("Failed to read {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// Load, update and save the state file.
+    fn update(&self, update: impl FnOnce(&mut FeatureState)) {
+        let result = FeatureState::load(&self.path).and_then(|mut state| {
+            update(&mut state);
+            state.save(&self.path)
+        });
+        if let Err(e) = result {
+            tracing::warn!("Failed to update {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_state_design_schema() {
+        let yaml = r#"
+feature:
+  name: "add-pr-in-status-yml"
+  id: "0003"
+  description: "Add PR link to status.yml"
+task:
+  kind: "implementation"
+  template: "implement"
+status:
+  state: "in_progress"
+  current_phase: "phase_3"
+execution:
+  turns: 15
+  cost:
+    input_tokens: 125000
+    output_tokens: 45000
+    total_cost_usd: 1.70
+context:
+  worktree:
+    path: "./.trees/0003-add-pr-in-status-yml"
+    branch: "gba/0003-add-pr-in-status-yml"
+timestamps:
+  created_at: "2026-02-24T10:30:00Z"
+  updated_at: "2026-02-24T11:45:00Z"
+  completed_at: null
+"#;
+        let state: FeatureState = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(state.feature.id, "0003");
+        assert_eq!(state.status.state, TaskStatus::InProgress);
+        assert_eq!(state.execution.cost.input_tokens, 125_000);
+        assert_eq!(
+            state.context.worktree.unwrap().branch,
+            "gba/0003-add-pr-in-status-yml"
+        );
+        assert!(state.result.is_none());
+    }
+
+    #[test]
+    fn test_feature_state_new() {
+        let state = FeatureState::new("add-auth", "0042");
+        assert_eq!(state.feature.name, "add-auth");
+        assert_eq!(state.status.state, TaskStatus::Pending);
+        assert!(state.context.worktree.is_none());
+        assert!(state.execution.run_id.is_none());
+    }
+
+    #[test]
+    fn test_feature_state_load_or_new_detects_id_collision() {
+        let dir =
+            std::env::temp_dir().join(format!("gba-test-state-collision-{}", std::process::id()));
+        let path = dir.join("state.yml");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut state = FeatureState::load_or_new(&path, "add-auth", "0042").unwrap();
+        state.save(&path).unwrap();
+        assert!(FeatureState::load_or_new(&path, "add-auth", "0042").is_ok());
+        assert!(matches!(
+            FeatureState::load_or_new(&path, "add-oauth", "0042"),
+            Err(StateError::IdCollision { existing, name, .. })
+                if existing == "add-auth" && name == "add-oauth"
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_feature_state_fix_loop_round_trip() {
+        let mut state = FeatureState::new("add-auth", "0042");
+        assert!(!serde_yaml::to_string(&state).unwrap().contains("fix_loop"));
+
+        state.fix_loop.push(FixIteration {
+            iteration: 1,
+            verification_run_id: Some("20260301T120000Z-00aa".to_string()),
+            passed: false,
+            verdict: Some("NEEDS WORK".to_string()),
+            failed_commands: vec!["test".to_string()],
+            fix_run_id: Some("20260301T121500Z-00aa".to_string()),
+            cost_usd: 0.75,
+        });
+        let yaml = serde_yaml::to_string(&state).unwrap();
+        let loaded: FeatureState = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(loaded.fix_loop, state.fix_loop);
+    }
+
+    #[test]
+    fn test_feature_state_pipeline_round_trip() {
+        let mut state = FeatureState::new("add-auth", "0042");
+        assert!(!serde_yaml::to_string(&state).unwrap().contains("pipeline"));
+
+        let before = state.clone();
+        state.start_run();
+        state.execution.cost.total_cost_usd = 0.25;
+        state.record_pipeline_step("planning", &before, true);
+        // A phase failing before it started has no run of its own
+        let before = state.clone();
+        state.record_pipeline_step("implementation", &before, false);
+        assert_eq!(
+            state.pipeline[0],
+            PipelineStep {
+                phase: "planning".to_string(),
+                run_id: state.execution.run_id.clone(),
+                success: true,
+                cost_usd: 0.25,
+            }
+        );
+        assert_eq!(state.pipeline[1].run_id, None);
+
+        let yaml = serde_yaml::to_string(&state).unwrap();
+        let loaded: FeatureState = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(loaded.pipeline, state.pipeline);
+    }
+
+    #[test]
+    fn test_feature_state_start_run() {
+        let mut state = FeatureState::new("add-auth", "0042");
+        state.execution.violations.push(Violation {
+            tool: "Edit".to_string(),
+            path: "Cargo.lock".to_string(),
+            pattern: "Cargo.lock".to_string(),
+        });
+        let yaml = serde_yaml::to_string(&state).unwrap();
+        assert!(yaml.contains("pattern: Cargo.lock"));
+
+        let run_id = state.start_run();
+        assert_eq!(state.execution.run_id.as_deref(), Some(run_id.as_str()));
+        assert!(run_id.ends_with(&format!("-{:04x}", std::process::id() & 0xffff)));
+        assert!(state.execution.violations.is_empty());
+    }
+
+    #[test]
+    fn test_resume_context_from_state() {
+        let mut state = FeatureState::new("add-auth", "0042");
+        state.task.kind = "implementation".to_string();
+        state.status.current_phase = Some("implement".to_string());
+        state.status.current_step = Some("Edit src/lib.rs".to_string());
+        state.execution.turns = 7;
+        state.execution.cost.total_cost_usd = 0.42;
+
+        let resume = ResumeContext::from(&state);
+        assert_eq!(resume.feature_id, "0042");
+        assert_eq!(resume.task_kind, "implementation");
+        assert_eq!(resume.current_phase, "implement");
+        assert_eq!(resume.current_step, "Edit src/lib.rs");
+        assert_eq!(resume.turns_so_far, 7);
+        assert_eq!(resume.cost_so_far, 0.42);
+        assert_eq!(resume.worktree_path, "");
+        assert!(resume.use_preset);
+
+        state.context.worktree = Some(WorktreeInfo {
+            path: PathBuf::from("/trees/0042"),
+            branch: "gba/0042-add-auth".to_string(),
+        });
+        let resume = ResumeContext::from(&state);
+        assert_eq!(resume.worktree_path, "/trees/0042");
+        assert_eq!(resume.worktree_branch, "gba/0042-add-auth");
+    }
+
+    #[test]
+    fn test_state_tracker_records_turns() {
+        let dir = std::env::temp_dir().join(format!("gba-test-state-{}", std::process::id()));
+        let path = dir.join("state.yml");
+        let mut state = FeatureState::new("add-auth", "0042");
+        state.execution.turns = 3;
+        state.status.state = TaskStatus::InProgress;
+        state.save(&path).unwrap();
+
+        let tracker = StateTracker::new(&path);
+        tracker.record_turn(None, Some("Edit src/lib.rs"));
+        tracker.record_turn(Some("session-1"), None);
+        tracker.record_step("Bash cargo test");
+
+        let state = FeatureState::load(&path).unwrap();
+        assert_eq!(state.execution.turns, 5);
+        assert_eq!(state.execution.session_id.as_deref(), Some("session-1"));
+        assert_eq!(
+            state.status.current_step.as_deref(),
+            Some("Bash cargo test")
+        );
+        assert!(state.is_resumable());
+        assert!(!FeatureState::new("add-auth", "0042").is_resumable());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
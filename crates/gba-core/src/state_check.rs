@@ -0,0 +1,469 @@
+//! Validation and repair of feature state files.
+//!
+//! A state file outlives the gba version that wrote it, and a crashed run
+//! can leave it behind inconsistent. [`check_features`] validates the state
+//! of every feature in `.gba/features` against the current schema and the
+//! feature's artifacts, and [`FeatureCheck::fix`] repairs the issues that
+//! can be recovered from: see [`StateIssue::is_fixable`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::ledger::Ledger;
+//! use gba_core::state_check;
+//! use std::path::Path;
+//!
+//! let ledger = Ledger::new(".gba/ledger.jsonl").entries().unwrap_or_default();
+//! for mut check in state_check::check_features(Path::new(".gba/features"), &ledger)? {
+//!     for fixed in check.fix()? {
+//!         println!("{}: fixed {fixed}", check.id);
+//!     }
+//! }
+//! # Ok::<(), gba_core::StateError>(())
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::ledger::LedgerEntry;
+use crate::lock::{LOCK_FILE, LockInfo};
+use crate::state::{CostInfo, FeatureState, Result, TaskStatus};
+
+/// File name of the state in a feature directory.
+pub const STATE_FILE: &str = "state.yml";
+
+/// Directory of the run transcripts in a feature directory.
+const TRANSCRIPTS_DIR: &str = "transcripts";
+
+/// Tolerance when comparing costs in USD, for rounding in the sums.
+const COST_TOLERANCE: f64 = 1e-6;
+
+/// An issue found in a feature state.
+#[derive(Debug, Clone)]
+pub enum StateIssue {
+    /// The state file cannot be read or doesn't match the schema.
+    Unreadable(String),
+
+    /// Fields unknown to the schema, e.g. written by a newer gba; they are
+    /// dropped on the next save.
+    UnknownFields(Vec<String>),
+
+    /// The feature ID differs from the feature directory.
+    IdMismatch {
+        /// The feature ID in the state.
+        found: String,
+    },
+
+    /// The task is in progress, but no run holds the feature's lock.
+    Interrupted,
+
+    /// The recorded worktree no longer exists.
+    MissingWorktree(PathBuf),
+
+    /// The transcript of the last run is missing.
+    MissingTranscript(String),
+
+    /// The recorded usage differs from the runs of the feature in the cost
+    /// ledger.
+    CostMismatch {
+        /// Usage recorded in the state.
+        recorded: CostInfo,
+        /// Usage summed from the ledger.
+        ledger: CostInfo,
+    },
+}
+
+impl StateIssue {
+    /// Check whether [`FeatureCheck::fix`] can repair the issue.
+    #[must_use]
+    pub fn is_fixable(&self) -> bool {
+        matches!(
+            self,
+            Self::IdMismatch { .. }
+                | Self::Interrupted
+                | Self::MissingWorktree(_)
+                | Self::CostMismatch { .. }
+        )
+    }
+}
+
+impl fmt::Display for StateIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "unreadable state: {e}"),
+            Self::UnknownFields(fields) => {
+                write!(f, "fields unknown to the schema: {}", fields.join(", "))
+            }
+            Self::IdMismatch { found } => {
+                write!(f, "feature ID {found} differs from its directory")
+            }
+            Self::Interrupted => write!(f, "in progress, but no run is active"),
+            Self::MissingWorktree(path) => write!(f, "worktree {} is missing", path.display()),
+            Self::MissingTranscript(run_id) => {
+                write!(f, "transcript of run {run_id} is missing")
+            }
+            Self::CostMismatch { recorded, ledger } => write!(
+                f,
+                "usage {} / {} tokens, ${:.4} differs from the ledger's {} / {} tokens, ${:.4}",
+                recorded.input_tokens,
+                recorded.output_tokens,
+                recorded.total_cost_usd,
+                ledger.input_tokens,
+                ledger.output_tokens,
+                ledger.total_cost_usd
+            ),
+        }
+    }
+}
+
+/// The result of checking the state of a feature.
+#[derive(Debug, Clone)]
+pub struct FeatureCheck {
+    /// Feature ID, from the feature directory.
+    pub id: String,
+
+    /// Path of the state file.
+    pub path: PathBuf,
+
+    /// Issues found.
+    pub issues: Vec<StateIssue>,
+
+    /// The state, if readable.
+    state: Option<FeatureState>,
+}
+
+impl FeatureCheck {
+    /// Check whether no issue was found.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Repair the fixable issues and save the state.
+    ///
+    /// Issues that can't be fixed are kept in [`Self::issues`].
+    ///
+    /// # Returns
+    ///
+    /// The issues fixed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state cannot be saved.
+    pub fn fix(&mut self) -> Result<Vec<StateIssue>> {
+        let Some(state) = &mut self.state else {
+            return Ok(Vec::new());
+        };
+        let (fixed, kept) = std::mem::take(&mut self.issues)
+            .into_iter()
+            .partition::<Vec<_>, _>(StateIssue::is_fixable);
+        self.issues = kept;
+        if fixed.is_empty() {
+            return Ok(fixed);
+        }
+
+        for issue in &fixed {
+            match issue {
+                StateIssue::IdMismatch { .. } => state.feature.id.clone_from(&self.id),
+                StateIssue::Interrupted => {
+                    state.status.state = TaskStatus::Failed;
+                    state.status.message =
+                        Some("Interrupted; resume with gba run --resume".to_string());
+                }
+                // The next implementation run creates a new worktree
+                StateIssue::MissingWorktree(_) => state.context.worktree = None,
+                StateIssue::CostMismatch { ledger, .. } => {
+                    state.execution.cost = ledger.clone();
+                }
+                _ => {}
+            }
+        }
+        state.save(&self.path)?;
+        Ok(fixed)
+    }
+}
+
+/// Check the state of every feature in a features directory.
+///
+/// Feature directories without a state file are skipped.
+///
+/// # Arguments
+///
+/// * `features_dir` - Features directory, e.g. `.gba/features`.
+/// * `ledger` - Entries of the cost ledger.
+///
+/// # Errors
+///
+/// Returns an error if the features directory cannot be read.
+pub fn check_features(features_dir: &Path, ledger: &[LedgerEntry]) -> Result<Vec<FeatureCheck>> {
+    if !features_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut dirs = std::fs::read_dir(features_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join(STATE_FILE).is_file())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    Ok(dirs.iter().map(|dir| check_feature(dir, ledger)).collect())
+}
+
+/// Check the state of a feature.
+///
+/// # Arguments
+///
+/// * `dir` - Feature directory, e.g. `.gba/features/0003`.
+/// * `ledger` - Entries of the cost ledger.
+#[must_use]
+pub fn check_feature(dir: &Path, ledger: &[LedgerEntry]) -> FeatureCheck {
+    let id = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let path = dir.join(STATE_FILE);
+    let mut check = FeatureCheck {
+        id,
+        path,
+        issues: Vec::new(),
+        state: None,
+    };
+
+    let state = match read_state(&check.path) {
+        Ok((state, unknown)) => {
+            if !unknown.is_empty() {
+                check.issues.push(StateIssue::UnknownFields(unknown));
+            }
+            state
+        }
+        Err(e) => {
+            check.issues.push(StateIssue::Unreadable(e));
+            return check;
+        }
+    };
+
+    if state.feature.id != check.id {
+        check.issues.push(StateIssue::IdMismatch {
+            found: state.feature.id.clone(),
+        });
+    }
+
+    if state.status.state == TaskStatus::InProgress {
+        let lock = LockInfo::read(&dir.join(LOCK_FILE)).ok().flatten();
+        if lock.is_none_or(|lock| lock.is_stale()) {
+            check.issues.push(StateIssue::Interrupted);
+        }
+    }
+
+    if let Some(worktree) = &state.context.worktree
+        && !worktree.path.is_dir()
+    {
+        check
+            .issues
+            .push(StateIssue::MissingWorktree(worktree.path.clone()));
+    }
+
+    // Only finished runs have written their transcript
+    if let Some(run_id) = &state.execution.run_id
+        && matches!(
+            state.status.state,
+            TaskStatus::Completed | TaskStatus::Failed
+        )
+        && !dir
+            .join(TRANSCRIPTS_DIR)
+            .join(format!("{run_id}.jsonl"))
+            .is_file()
+    {
+        check
+            .issues
+            .push(StateIssue::MissingTranscript(run_id.clone()));
+    }
+
+    // Features run before the ledger existed have no entries to compare
+    let runs = ledger
+        .iter()
+        .filter(|entry| entry.feature_id == check.id)
+        .collect::<Vec<_>>();
+    if !runs.is_empty() {
+        let total = CostInfo {
+            input_tokens: runs.iter().map(|entry| entry.input_tokens).sum(),
+            output_tokens: runs.iter().map(|entry| entry.output_tokens).sum(),
+            total_cost_usd: runs.iter().map(|entry| entry.total_cost_usd).sum(),
+        };
+        let recorded = &state.execution.cost;
+        if recorded.input_tokens != total.input_tokens
+            || recorded.output_tokens != total.output_tokens
+            || (recorded.total_cost_usd - total.total_cost_usd).abs() > COST_TOLERANCE
+        {
+            check.issues.push(StateIssue::CostMismatch {
+                recorded: recorded.clone(),
+                ledger: total,
+            });
+        }
+    }
+
+    check.state = Some(state);
+    check
+}
+
+/// Read a state file and the dotted paths of its fields unknown to the
+/// schema.
+fn read_state(path: &Path) -> std::result::Result<(FeatureState, Vec<String>), String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let state = serde_yaml::from_str::<FeatureState>(&content).map_err(|e| e.to_string())?;
+    let original = serde_yaml::from_str(&content).map_err(|e| e.to_string())?;
+    let known = serde_yaml::to_value(&state).map_err(|e| e.to_string())?;
+
+    let mut unknown = Vec::new();
+    unknown_fields(&original, &known, "", &mut unknown);
+    Ok((state, unknown))
+}
+
+/// Collect the fields of a value missing from its known counterpart.
+///
+/// Empty fields are ignored: the schema leaves them out when saving.
+fn unknown_fields(
+    value: &serde_yaml::Value,
+    known: &serde_yaml::Value,
+    prefix: &str,
+    unknown: &mut Vec<String>,
+) {
+    use serde_yaml::Value;
+
+    match (value, known) {
+        (Value::Mapping(fields), Value::Mapping(known_fields)) => {
+            for (key, field) in fields {
+                let name = match key.as_str() {
+                    Some(key) if prefix.is_empty() => key.to_string(),
+                    Some(key) => format!("{prefix}.{key}"),
+                    None => continue,
+                };
+                match known_fields.get(key) {
+                    Some(known_field) => unknown_fields(field, known_field, &name, unknown),
+                    None if is_empty(field) => {}
+                    None => unknown.push(name),
+                }
+            }
+        }
+        (Value::Sequence(items), Value::Sequence(known_items)) => {
+            for (index, (item, known_item)) in items.iter().zip(known_items).enumerate() {
+                unknown_fields(item, known_item, &format!("{prefix}[{index}]"), unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check whether a YAML value is null or empty.
+fn is_empty(value: &serde_yaml::Value) -> bool {
+    match value {
+        serde_yaml::Value::Null => true,
+        serde_yaml::Value::Sequence(items) => items.is_empty(),
+        serde_yaml::Value::Mapping(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::WorktreeInfo;
+    use crate::task::Usage;
+
+    fn ledger_entry(feature_id: &str, output_tokens: u32) -> LedgerEntry {
+        let usage = Usage {
+            input_tokens: 100,
+            output_tokens,
+            total_cost_usd: 0.25,
+        };
+        LedgerEntry::new(feature_id, "add-auth", "implementation", "sonnet", &usage)
+    }
+
+    #[test]
+    fn test_check_and_fix_feature() {
+        let dir = std::env::temp_dir().join(format!("gba-test-state-check-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let feature_dir = dir.join("0042");
+
+        let mut state = FeatureState::new("add-auth", "0007");
+        state.status.state = TaskStatus::InProgress;
+        state.context.worktree = Some(WorktreeInfo {
+            path: dir.join("missing-worktree"),
+            branch: "gba/0042-add-auth".to_string(),
+        });
+        state.execution.cost.input_tokens = 100;
+        state.save(&feature_dir.join(STATE_FILE)).unwrap();
+        let ledger = vec![
+            ledger_entry("0042", 10),
+            ledger_entry("0042", 20),
+            ledger_entry("0001", 30),
+        ];
+
+        let mut checks = check_features(&dir, &ledger).unwrap();
+        assert_eq!(checks.len(), 1);
+        let check = &mut checks[0];
+        assert_eq!(check.id, "0042");
+        assert_eq!(check.issues.len(), 4, "{:?}", check.issues);
+        assert!(check.issues.iter().all(StateIssue::is_fixable));
+
+        let fixed = check.fix().unwrap();
+        assert_eq!(fixed.len(), 4);
+        assert!(check.is_ok());
+
+        let state = FeatureState::load(&check.path).unwrap();
+        assert_eq!(state.feature.id, "0042");
+        assert_eq!(state.status.state, TaskStatus::Failed);
+        assert!(state.context.worktree.is_none());
+        assert_eq!(state.execution.cost.input_tokens, 200);
+        assert_eq!(state.execution.cost.output_tokens, 30);
+        assert!((state.execution.cost.total_cost_usd - 0.5).abs() < COST_TOLERANCE);
+        assert!(check_feature(&feature_dir, &ledger).is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_check_reports_unrecoverable_issues() {
+        let dir = std::env::temp_dir().join(format!(
+            "gba-test-state-check-unfixable-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Unknown fields, and a finished run without its transcript
+        std::fs::write(
+            dir.join(STATE_FILE),
+            r#"
+feature:
+  name: "add-auth"
+  id: "TEST"
+  priority: high
+status:
+  state: completed
+execution:
+  run_id: "20260224T103000Z-1a2b"
+  session_id: null
+timestamps:
+  created_at: "2026-02-24T10:30:00Z"
+  updated_at: "2026-02-24T10:30:00Z"
+"#
+            .replace("TEST", &dir.file_name().unwrap().to_string_lossy()),
+        )
+        .unwrap();
+        let mut check = check_feature(&dir, &[]);
+        assert!(matches!(
+            &check.issues[..],
+            [StateIssue::UnknownFields(fields), StateIssue::MissingTranscript(run_id)]
+                if fields == &["feature.priority"] && run_id == "20260224T103000Z-1a2b"
+        ));
+        assert!(check.fix().unwrap().is_empty());
+        assert_eq!(check.issues.len(), 2);
+
+        std::fs::write(dir.join(STATE_FILE), "feature: [").unwrap();
+        let check = check_feature(&dir, &[]);
+        assert!(matches!(&check.issues[..], [StateIssue::Unreadable(_)]));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
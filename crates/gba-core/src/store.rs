@@ -0,0 +1,685 @@
+//! Storage backends for feature state and artifacts.
+//!
+//! A feature's state, plan, transcripts and reports live in its
+//! `.gba/features/<id>` directory. On an ephemeral CI runner that directory
+//! is gone with the machine, and a run cannot be resumed elsewhere. A
+//! [`StateStore`] persists the files of features beyond the project
+//! directory. Two backends exist:
+//!
+//! - [`FileStore`], a directory holding one subdirectory per feature; the
+//!   project's own `.gba/features` is one, and so is a shared mount
+//! - [`S3Store`], a prefix of an S3 bucket or S3-compatible service, accessed
+//!   through the `aws` CLI
+//!
+//! With `storage` configured in `.gba/config.yml`, [`remote`] opens the
+//! store; a run restores a feature from it with [`StateStore::download`]
+//! when the project has no state for the feature yet, and saves the feature
+//! back with [`StateStore::upload`] when it finishes.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::store::{FileStore, StateStore};
+//! use std::path::Path;
+//!
+//! let shared = FileStore::new("/mnt/shared/gba");
+//! shared.upload("0001", Path::new(".gba/features/0001"))?;
+//! # Ok::<(), gba_core::store::StoreError>(())
+//! ```
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+use crate::config::StorageConfig;
+use crate::lock::LOCK_FILE;
+
+/// Result type alias for storage operations.
+pub type Result<T> = std::result::Result<T, StoreError>;
+
+/// Error types for storage operations.
+#[derive(Debug, Error)]
+pub enum StoreError {
+    /// IO error, e.g. the `aws` CLI is not installed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A storage command failed.
+    #[error("{command} failed: {message}")]
+    Command {
+        /// The command, e.g. `"aws s3 cp"`.
+        command: String,
+        /// Error output of the command.
+        message: String,
+    },
+
+    /// An artifact name is not a relative path inside the feature.
+    #[error("Invalid artifact name: {0}")]
+    InvalidName(String),
+
+    /// The S3 backend is configured without a bucket.
+    #[error("storage.bucket is required for the s3 backend")]
+    MissingBucket,
+}
+
+/// Kind of storage backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    /// A directory, see [`FileStore`].
+    #[default]
+    Filesystem,
+
+    /// An S3 bucket, see [`S3Store`].
+    S3,
+}
+
+impl fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Filesystem => write!(f, "filesystem"),
+            Self::S3 => write!(f, "s3"),
+        }
+    }
+}
+
+/// A store of feature state and artifacts.
+///
+/// Artifacts are named by their path relative to the feature's directory,
+/// e.g. `"state.yml"` or `"context/20250101-120000.json"`.
+pub trait StateStore: fmt::Debug + Send + Sync {
+    /// Get the kind of the backend.
+    fn kind(&self) -> StoreKind;
+
+    /// List the identifiers of the stored features, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be listed.
+    fn features(&self) -> Result<Vec<String>>;
+
+    /// List the artifacts of a feature, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be listed.
+    fn artifacts(&self, feature_id: &str) -> Result<Vec<String>>;
+
+    /// Read an artifact of a feature.
+    ///
+    /// # Returns
+    ///
+    /// The content, or `None` if the artifact doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the artifact cannot be read.
+    fn read(&self, feature_id: &str, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Write an artifact of a feature, replacing it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the artifact cannot be written.
+    fn write(&self, feature_id: &str, name: &str, content: &[u8]) -> Result<()>;
+
+    /// Remove a feature and its artifacts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature cannot be removed.
+    fn remove(&self, feature_id: &str) -> Result<()>;
+
+    /// Store the files of a local feature directory, except its lock and
+    /// temporary files.
+    ///
+    /// # Returns
+    ///
+    /// The number of stored files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or stored.
+    fn upload(&self, feature_id: &str, dir: &Path) -> Result<usize> {
+        let names = local_artifacts(dir)?;
+        for name in &names {
+            self.write(feature_id, name, &std::fs::read(dir.join(name))?)?;
+        }
+        Ok(names.len())
+    }
+
+    /// Write the stored artifacts of a feature into a local feature
+    /// directory, replacing the files there.
+    ///
+    /// # Returns
+    ///
+    /// The number of written files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an artifact cannot be read or written.
+    fn download(&self, feature_id: &str, dir: &Path) -> Result<usize> {
+        let mut count = 0;
+        for name in self.artifacts(feature_id)? {
+            if is_transient(&name) {
+                continue;
+            }
+            if let Some(content) = self.read(feature_id, &name)? {
+                let path = dir.join(check_name(&name)?);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                crate::atomic::write(&path, content)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// A directory holding one subdirectory per feature.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    /// The directory, e.g. `.gba/features`.
+    root: PathBuf,
+}
+
+impl FileStore {
+    /// Open a store in a directory, created on the first write.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Get the directory.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the directory of a feature.
+    fn feature_dir(&self, feature_id: &str) -> Result<PathBuf> {
+        Ok(self.root.join(check_name(feature_id)?))
+    }
+}
+
+impl StateStore for FileStore {
+    fn kind(&self) -> StoreKind {
+        StoreKind::Filesystem
+    }
+
+    fn features(&self) -> Result<Vec<String>> {
+        if !self.root.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut features = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                features.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        features.sort();
+        Ok(features)
+    }
+
+    fn artifacts(&self, feature_id: &str) -> Result<Vec<String>> {
+        local_artifacts(&self.feature_dir(feature_id)?)
+    }
+
+    fn read(&self, feature_id: &str, name: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.feature_dir(feature_id)?.join(check_name(name)?);
+        match std::fs::read(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, feature_id: &str, name: &str, content: &[u8]) -> Result<()> {
+        let path = self.feature_dir(feature_id)?.join(check_name(name)?);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::atomic::write(&path, content)?;
+        Ok(())
+    }
+
+    fn remove(&self, feature_id: &str) -> Result<()> {
+        match std::fs::remove_dir_all(self.feature_dir(feature_id)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A prefix of an S3 bucket, accessed through the `aws` CLI.
+///
+/// Credentials and region come from the CLI's usual configuration, e.g.
+/// `AWS_PROFILE` or the runner's instance role. An endpoint URL selects an
+/// S3-compatible service such as MinIO or R2.
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    /// Bucket name.
+    bucket: String,
+    /// Key prefix of the features, without surrounding slashes.
+    prefix: String,
+    /// Endpoint of an S3-compatible service.
+    endpoint: Option<String>,
+}
+
+impl S3Store {
+    /// Open a store in a bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `bucket` - Bucket name.
+    /// * `prefix` - Key prefix of the features, e.g. `"gba/my-repo"`.
+    #[must_use]
+    pub fn new(bucket: impl Into<String>, prefix: &str) -> Self {
+        Self {
+            bucket: bucket.into(),
+            prefix: prefix.trim_matches('/').to_string(),
+            endpoint: None,
+        }
+    }
+
+    /// Use an S3-compatible service instead of AWS.
+    #[must_use]
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Get the URL of a key below the prefix, e.g. `s3://bucket/prefix/0001/`.
+    fn url(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            format!("s3://{}/{key}", self.bucket)
+        } else {
+            format!("s3://{}/{}/{key}", self.bucket, self.prefix)
+        }
+    }
+
+    /// Run `aws s3 <args>`, optionally feeding it input.
+    fn aws(&self, args: &[&str], input: Option<&[u8]>) -> Result<std::process::Output> {
+        let mut command = Command::new("aws");
+        command.arg("s3").args(args);
+        if let Some(endpoint) = &self.endpoint {
+            command.args(["--endpoint-url", endpoint]);
+        }
+        command
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        debug!("Running aws s3 {}", args.join(" "));
+
+        let mut child = command.spawn()?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input)?;
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Run `aws s3 <args>`, failing on a non-zero exit.
+    fn aws_ok(&self, args: &[&str], input: Option<&[u8]>) -> Result<std::process::Output> {
+        let output = self.aws(args, input)?;
+        if !output.status.success() {
+            return Err(command_error(args, &output));
+        }
+        Ok(output)
+    }
+
+    /// List a prefix, treating a missing prefix as empty.
+    fn ls(&self, url: &str, recursive: bool) -> Result<String> {
+        let mut args = vec!["ls", url];
+        if recursive {
+            args.push("--recursive");
+        }
+        let output = self.aws(&args, None)?;
+        // `aws s3 ls` exits with 1 when nothing matches
+        let empty = output.status.code() == Some(1) && output.stderr.is_empty();
+        if !output.status.success() && !empty {
+            return Err(command_error(&args, &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// `aws s3 sync` arguments leaving lock and temporary files out.
+    fn sync_args<'a>(from: &'a str, to: &'a str) -> Vec<&'a str> {
+        vec![
+            "sync",
+            from,
+            to,
+            "--exclude",
+            LOCK_FILE,
+            "--exclude",
+            "*.tmp",
+            "--exclude",
+            "*.bak",
+        ]
+    }
+}
+
+impl StateStore for S3Store {
+    fn kind(&self) -> StoreKind {
+        StoreKind::S3
+    }
+
+    #[instrument(skip(self), fields(bucket = %self.bucket))]
+    fn features(&self) -> Result<Vec<String>> {
+        Ok(parse_prefixes(&self.ls(&self.url(""), false)?))
+    }
+
+    #[instrument(skip(self), fields(bucket = %self.bucket))]
+    fn artifacts(&self, feature_id: &str) -> Result<Vec<String>> {
+        let url = self.url(&format!("{}/", check_name(feature_id)?));
+        let listing = self.ls(&url, true)?;
+        let key_prefix = url
+            .trim_start_matches("s3://")
+            .split_once('/')
+            .map_or(String::new(), |(_, key)| key.to_string());
+        Ok(parse_objects(&listing, &key_prefix))
+    }
+
+    fn read(&self, feature_id: &str, name: &str) -> Result<Option<Vec<u8>>> {
+        let url = self.url(&format!(
+            "{}/{}",
+            check_name(feature_id)?,
+            check_name(name)?
+        ));
+        let args = ["cp", url.as_str(), "-"];
+        let output = self.aws(&args, None)?;
+        if output.status.success() {
+            return Ok(Some(output.stdout));
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("404") || stderr.contains("Not Found") || stderr.contains("NoSuchKey") {
+            return Ok(None);
+        }
+        Err(command_error(&args, &output))
+    }
+
+    fn write(&self, feature_id: &str, name: &str, content: &[u8]) -> Result<()> {
+        let url = self.url(&format!(
+            "{}/{}",
+            check_name(feature_id)?,
+            check_name(name)?
+        ));
+        self.aws_ok(&["cp", "-", &url], Some(content))?;
+        Ok(())
+    }
+
+    fn remove(&self, feature_id: &str) -> Result<()> {
+        let url = self.url(&format!("{}/", check_name(feature_id)?));
+        self.aws_ok(&["rm", &url, "--recursive"], None)?;
+        Ok(())
+    }
+
+    /// Sync the directory up, transferring only the files that changed.
+    fn upload(&self, feature_id: &str, dir: &Path) -> Result<usize> {
+        let url = self.url(&format!("{}/", check_name(feature_id)?));
+        let local = dir.to_string_lossy();
+        let output = self.aws_ok(&Self::sync_args(&local, &url), None)?;
+        Ok(count_transfers(&output.stdout))
+    }
+
+    /// Sync the directory down, transferring only the files that changed.
+    fn download(&self, feature_id: &str, dir: &Path) -> Result<usize> {
+        std::fs::create_dir_all(dir)?;
+        let url = self.url(&format!("{}/", check_name(feature_id)?));
+        let local = dir.to_string_lossy();
+        let output = self.aws_ok(&Self::sync_args(&url, &local), None)?;
+        Ok(count_transfers(&output.stdout))
+    }
+}
+
+/// Open the store features are persisted to beyond the project, if any.
+///
+/// # Arguments
+///
+/// * `config` - Storage configuration.
+/// * `project_path` - Project directory a relative `storage.path` is
+///   resolved against.
+///
+/// # Returns
+///
+/// The store, or `None` for the filesystem backend without a `path`, which
+/// keeps features in the project only.
+///
+/// # Errors
+///
+/// Returns [`StoreError::MissingBucket`] for the S3 backend without a
+/// bucket.
+pub fn remote(config: &StorageConfig, project_path: &Path) -> Result<Option<Box<dyn StateStore>>> {
+    let store: Box<dyn StateStore> = match config.backend {
+        StoreKind::Filesystem => match &config.path {
+            Some(path) => Box::new(FileStore::new(project_path.join(path))),
+            None => return Ok(None),
+        },
+        StoreKind::S3 => {
+            let bucket = config.bucket.as_deref().ok_or(StoreError::MissingBucket)?;
+            let store = S3Store::new(bucket, &config.prefix);
+            match &config.endpoint {
+                Some(endpoint) => Box::new(store.with_endpoint(endpoint)),
+                None => Box::new(store),
+            }
+        }
+    };
+    debug!("Persisting features to the {} store", store.kind());
+    Ok(Some(store))
+}
+
+/// Restore a feature from a store unless the project has its state already.
+///
+/// # Arguments
+///
+/// * `store` - Store to restore from.
+/// * `feature_id` - Feature identifier.
+/// * `dir` - The feature's local directory, e.g. `.gba/features/0001`.
+///
+/// # Returns
+///
+/// The number of restored files; 0 if the feature was present or isn't
+/// stored.
+///
+/// # Errors
+///
+/// Returns an error if the feature cannot be downloaded.
+pub fn restore(store: &dyn StateStore, feature_id: &str, dir: &Path) -> Result<usize> {
+    if dir.join(crate::state_check::STATE_FILE).is_file() {
+        return Ok(0);
+    }
+    let count = store.download(feature_id, dir)?;
+    if count > 0 {
+        debug!("Restored {} files of feature {}", count, feature_id);
+    }
+    Ok(count)
+}
+
+/// List the artifacts of a local feature directory, sorted.
+fn local_artifacts(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    if dir.is_dir() {
+        collect_files(dir, dir, &mut names)?;
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Collect the non-transient files below a directory as relative names.
+fn collect_files(root: &Path, dir: &Path, names: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, names)?;
+            continue;
+        }
+        let name = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if !is_transient(&name) {
+            names.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Check whether an artifact is local to a machine: the feature's lock, and
+/// temporary and backup files of atomic writes.
+fn is_transient(name: &str) -> bool {
+    name == LOCK_FILE
+        || name.ends_with(".tmp")
+        || name.ends_with(&format!(".{}", crate::atomic::BACKUP_EXTENSION))
+}
+
+/// Check that a name is a relative path that stays inside its directory.
+fn check_name(name: &str) -> Result<&str> {
+    let path = Path::new(name);
+    if name.is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(StoreError::InvalidName(name.to_string()));
+    }
+    Ok(name)
+}
+
+/// Build the error of a failed `aws s3` command.
+fn command_error(args: &[&str], output: &std::process::Output) -> StoreError {
+    StoreError::Command {
+        command: format!("aws s3 {}", args.first().copied().unwrap_or_default()),
+        message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    }
+}
+
+/// Parse the directories of an `aws s3 ls` listing, e.g. `PRE 0001/`.
+fn parse_prefixes(listing: &str) -> Vec<String> {
+    let mut prefixes = listing
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("PRE "))
+        .map(|prefix| prefix.trim().trim_end_matches('/').to_string())
+        .filter(|prefix| !prefix.is_empty())
+        .collect::<Vec<_>>();
+    prefixes.sort();
+    prefixes
+}
+
+/// Parse the object keys of a recursive `aws s3 ls` listing, relative to a
+/// key prefix.
+///
+/// Lines read `<date> <time> <size> <key>`; keys may contain spaces.
+fn parse_objects(listing: &str, key_prefix: &str) -> Vec<String> {
+    let mut names = listing
+        .lines()
+        .filter_map(|line| {
+            let mut rest = line.trim_start();
+            for _ in 0..3 {
+                rest = rest.split_once(char::is_whitespace)?.1.trim_start();
+            }
+            Some(rest)
+        })
+        .filter_map(|key| key.strip_prefix(key_prefix))
+        .filter(|name| !name.is_empty() && !name.ends_with('/'))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// Count the files an `aws s3 sync` transferred.
+fn count_transfers(stdout: &[u8]) -> usize {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .filter(|line| line.starts_with("upload:") || line.starts_with("download:"))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let dir = std::env::temp_dir().join("gba-test-store");
+        let _ = std::fs::remove_dir_all(&dir);
+        let local = dir.join("project").join("0001");
+        std::fs::create_dir_all(local.join("context")).unwrap();
+        std::fs::write(local.join("state.yml"), "feature: {}\n").unwrap();
+        std::fs::write(local.join("plan.md"), "# Plan\n").unwrap();
+        std::fs::write(local.join("context").join("run.json"), "{}").unwrap();
+        std::fs::write(local.join(LOCK_FILE), "{}").unwrap();
+        std::fs::write(local.join("state.yml.bak"), "old").unwrap();
+
+        let store = FileStore::new(dir.join("shared"));
+        assert!(store.features().unwrap().is_empty());
+        assert_eq!(store.upload("0001", &local).unwrap(), 3);
+        assert_eq!(store.features().unwrap(), ["0001"]);
+        assert_eq!(
+            store.artifacts("0001").unwrap(),
+            ["context/run.json", "plan.md", "state.yml"]
+        );
+        assert_eq!(store.read("0001", "plan.md").unwrap().unwrap(), b"# Plan\n");
+        assert!(store.read("0001", "missing.md").unwrap().is_none());
+        assert!(matches!(
+            store.read("0001", "../escape"),
+            Err(StoreError::InvalidName(_))
+        ));
+
+        // Restoring skips a feature the project has, and fills in a missing one
+        let restored = dir.join("runner").join("0001");
+        assert_eq!(restore(&store, "0001", &local).unwrap(), 0);
+        assert_eq!(restore(&store, "0001", &restored).unwrap(), 3);
+        assert_eq!(
+            std::fs::read_to_string(restored.join("context").join("run.json")).unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            restore(&store, "0002", &dir.join("runner/0002")).unwrap(),
+            0
+        );
+
+        store.remove("0001").unwrap();
+        store.remove("0001").unwrap();
+        assert!(store.features().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_s3_listings() {
+        let prefixes =
+            "                           PRE 0002/\n                           PRE 0001/\n";
+        assert_eq!(parse_prefixes(prefixes), ["0001", "0002"]);
+
+        let objects = "\
+2025-01-01 12:00:00        120 gba/repo/0001/state.yml
+2025-01-01 12:00:01         10 gba/repo/0001/context/run 1.json
+2025-01-01 12:00:02          0 gba/repo/0001/context/
+";
+        assert_eq!(
+            parse_objects(objects, "gba/repo/0001/"),
+            ["context/run 1.json", "state.yml"]
+        );
+        assert_eq!(
+            count_transfers(b"upload: ./state.yml to s3://b/0001/state.yml\nCompleted 1 of 1\n"),
+            1
+        );
+
+        let store = S3Store::new("bucket", "/gba/repo/");
+        assert_eq!(store.url("0001/"), "s3://bucket/gba/repo/0001/");
+        assert_eq!(S3Store::new("bucket", "").url("0001/"), "s3://bucket/0001/");
+    }
+}
@@ -0,0 +1,219 @@
+//! Output of a task streamed in chunks while it runs.
+//!
+//! An agent set with [`crate::Agent::with_chunks`] sends the output of each
+//! task to a channel as its messages stream in, e.g. for the TUI to render.
+//!
+//! # Semantics
+//!
+//! For every task the agent runs, its consumer receives:
+//!
+//! 1. [`Chunk::Text`] and [`Chunk::ToolUse`] chunks, in the order the agent
+//!    produced them, and a [`Chunk::Usage`] with the usage spent so far
+//!    after the first message of each turn;
+//! 2. if the task failed, mid-stream or before it started, exactly one
+//!    [`Chunk::Error`], flagged retryable when running the task again may
+//!    succeed;
+//! 3. always exactly one final [`Chunk::Done`] with the task's usage: the
+//!    usage spent until it stopped, marked partial, if it failed.
+//!
+//! No chunk of the task follows its [`Chunk::Done`]. Chunks the consumer is
+//! no longer there to receive are dropped.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::stream::Chunk;
+//! use gba_core::{Agent, AgentConfig, Context};
+//!
+//! # async fn run() -> Result<(), gba_core::CoreError> {
+//! let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+//! let agent = Agent::new(AgentConfig::default()).with_chunks(tx);
+//! let task = tokio::spawn(async move { agent.execute("Hello", &Context::default()).await });
+//!
+//! while let Some(chunk) = rx.recv().await {
+//!     match chunk {
+//!         Chunk::Text(text) => print!("{text}"),
+//!         Chunk::ToolUse { name } => println!("[{name}]"),
+//!         Chunk::Usage(usage) => eprintln!("${:.4} so far", usage.total_cost_usd),
+//!         Chunk::Error { message, retryable } => eprintln!("{message} (retryable: {retryable})"),
+//!         Chunk::Done { usage, .. } => {
+//!             println!("{} output tokens", usage.output_tokens);
+//!             break;
+//!         }
+//!     }
+//! }
+//! # let _ = task.await;
+//! # Ok(())
+//! # }
+//! ```
+
+use claude_agent_sdk_rs::{ContentBlock, Message};
+
+use crate::error::CoreError;
+use crate::task::{Response, Usage};
+
+/// A chunk of a task's output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    /// Text of the agent.
+    Text(String),
+
+    /// The agent called a tool.
+    ToolUse {
+        /// Tool name, e.g. `"Bash"`.
+        name: String,
+    },
+
+    /// Usage the task has spent so far, its cost estimated from the model's
+    /// pricing.
+    Usage(Usage),
+
+    /// The task failed; always followed by [`Chunk::Done`].
+    Error {
+        /// Error message.
+        message: String,
+        /// Whether running the task again may succeed.
+        retryable: bool,
+    },
+
+    /// The task finished; always the last chunk of a task.
+    Done {
+        /// Usage of the task.
+        usage: Usage,
+        /// Whether the task failed, its usage then being what it spent until
+        /// it stopped.
+        partial: bool,
+    },
+}
+
+/// Get the chunks of a message of the agent.
+///
+/// Only assistant messages carry output: other messages have none.
+#[must_use]
+pub fn message_chunks(message: &Message) -> Vec<Chunk> {
+    let Message::Assistant(msg) = message else {
+        return Vec::new();
+    };
+    msg.message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(Chunk::Text(text.text.clone())),
+            ContentBlock::ToolUse(tool) => Some(Chunk::ToolUse {
+                name: tool.name.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the final chunks of a task: an error if it failed, then done.
+///
+/// # Arguments
+///
+/// * `result` - Result of the task.
+/// * `spent` - Usage spent until the task stopped, for failures without a
+///   partial response.
+#[must_use]
+pub fn final_chunks(result: &Result<Response, CoreError>, spent: Usage) -> Vec<Chunk> {
+    match result {
+        Ok(response) => vec![Chunk::Done {
+            usage: response.usage.clone(),
+            partial: false,
+        }],
+        Err(e) => vec![
+            Chunk::Error {
+                message: e.to_string(),
+                retryable: e.is_retryable(),
+            },
+            Chunk::Done {
+                usage: e
+                    .partial_response()
+                    .map_or(spent, |partial| partial.usage.clone()),
+                partial: true,
+            },
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_chunks() {
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "text", "text": "Reading the router."},
+                {"type": "tool_use", "id": "tool-1", "name": "Read", "input": {"file_path": "src/main.rs"}},
+            ]},
+        }))
+        .unwrap();
+        assert_eq!(
+            message_chunks(&message),
+            vec![
+                Chunk::Text("Reading the router.".to_string()),
+                Chunk::ToolUse {
+                    name: "Read".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_final_chunks_end_with_done() {
+        let usage = |output_tokens| Usage {
+            output_tokens,
+            ..Usage::default()
+        };
+        let response = Response {
+            usage: usage(50),
+            ..Response::default()
+        };
+        assert_eq!(
+            final_chunks(&Ok(response.clone()), usage(0)),
+            vec![Chunk::Done {
+                usage: usage(50),
+                partial: false
+            }]
+        );
+
+        // A task stopped on a limit reports its partial usage
+        let limited = CoreError::TurnLimitReached {
+            turns: 3,
+            partial: Box::new(response),
+        };
+        let chunks = final_chunks(&Err(limited), usage(10));
+        assert!(matches!(
+            &chunks[0],
+            Chunk::Error {
+                retryable: false,
+                ..
+            }
+        ));
+        assert_eq!(
+            chunks[1],
+            Chunk::Done {
+                usage: usage(50),
+                partial: true
+            }
+        );
+
+        // Otherwise the usage spent until it failed
+        let chunks = final_chunks(&Err(CoreError::Timeout { seconds: 300 }), usage(10));
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::Error {
+                    message: "Timed out after 300s".to_string(),
+                    retryable: true
+                },
+                Chunk::Done {
+                    usage: usage(10),
+                    partial: true
+                },
+            ]
+        );
+    }
+}
@@ -1,9 +1,13 @@
 //! Task execution logic for GBA Core.
 
+use gba_pm::TemplateConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::config::PermissionMode;
+use crate::protect::Violation;
+
 /// Task execution context.
 ///
 /// This context provides information about the repository, files, and metadata
@@ -58,21 +62,80 @@ pub struct Response {
     /// Usage statistics.
     #[serde(default)]
     pub usage: Usage,
+
+    /// Tool calls denied for modifying protected paths.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+
+    /// Number of agent turns.
+    #[serde(default)]
+    pub turns: u32,
+
+    /// Session of the agent, to resume it later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    /// Generation settings the task ran with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<GenerationConfig>,
+}
+
+/// Effective generation settings of a task, resolved from the agent
+/// configuration, its limits and the task's overrides.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    /// Model used.
+    pub model: String,
+
+    /// Maximum output tokens of each response.
+    pub max_tokens: u32,
+
+    /// Maximum number of agent turns, if limited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+
+    /// Budget in USD, if limited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_budget_usd: Option<f64>,
+
+    /// Sampling temperature applied, `None` when the model samples at its
+    /// default: the Claude Code CLI the SDK runs takes no temperature, so
+    /// `agent.temperature` is not applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
 }
 
 /// Tool call made during execution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolCall {
+    /// Identifier of the tool use.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+
     /// Tool name.
     pub name: String,
 
     /// Tool arguments.
     pub arguments: serde_json::Value,
+
+    /// Result of the call, `None` if none was received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+
+    /// Whether the tool failed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+
+    /// Time from the call to its result in milliseconds, if measured while
+    /// the task streamed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
 }
 
 /// Usage statistics for the response.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Usage {
     /// Input tokens used.
@@ -104,6 +167,22 @@ pub struct Task {
 
     /// Maximum turns for this task.
     pub max_turns: u32,
+
+    /// Maximum output tokens of each response, overriding the agent's
+    /// `max_tokens`.
+    pub max_tokens: Option<u32>,
+
+    /// Permission mode of the task, overriding the agent's
+    /// `permissionMode`.
+    pub permission_mode: Option<PermissionMode>,
+
+    /// Timeout of the task in seconds, overriding the agent's `timeout`; 0
+    /// disables it.
+    pub timeout: Option<u64>,
+
+    /// Tools the task may use, overriding the agent's template tools; empty
+    /// keeps them.
+    pub tools: Vec<String>,
 }
 
 impl Task {
@@ -145,9 +224,60 @@ impl Task {
             context,
             system_prompt,
             max_turns,
+            max_tokens: None,
+            permission_mode: None,
+            timeout: None,
+            tools: Vec::new(),
         }
     }
 
+    /// Set the maximum output tokens of each response, overriding the
+    /// agent's `max_tokens`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_tokens` - Output token ceiling.
+    #[must_use]
+    pub const fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set the permission mode of the task, overriding the agent's
+    /// `permissionMode`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Permission mode, e.g. [`PermissionMode::Plan`].
+    #[must_use]
+    pub const fn with_permission_mode(mut self, mode: PermissionMode) -> Self {
+        self.permission_mode = Some(mode);
+        self
+    }
+
+    /// Set the timeout of the task, overriding the agent's `timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - Timeout in seconds; 0 disables it.
+    #[must_use]
+    pub const fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+
+    /// Set the tools the task may use, overriding the agent's template
+    /// tools. The project's `allowedTools` and `deniedTools` still apply.
+    ///
+    /// # Arguments
+    ///
+    /// * `tools` - Tool names, e.g. `["Read", "Grep"]`.
+    #[must_use]
+    pub fn with_tools(mut self, tools: Vec<String>) -> Self {
+        self.tools = tools;
+        self
+    }
+
     /// Create a new task with default system prompt and max turns.
     ///
     /// # Arguments
@@ -178,6 +308,34 @@ impl Task {
             100,
         )
     }
+
+    /// Create a task with the settings of the template its prompt was
+    /// rendered from: system prompt, turns, output tokens, permission mode
+    /// and tools.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The rendered prompt.
+    /// * `context` - The task context.
+    /// * `template` - Configuration of the template.
+    #[must_use]
+    pub fn from_template(
+        prompt: impl Into<String>,
+        context: Context,
+        template: &TemplateConfig,
+    ) -> Self {
+        Self {
+            max_tokens: template.max_tokens,
+            permission_mode: template.permission_mode,
+            tools: template.tools.clone(),
+            ..Self::new(
+                prompt.into(),
+                context,
+                template.system_prompt.clone(),
+                template.max_turns,
+            )
+        }
+    }
 }
 
 impl Default for Context {
@@ -213,6 +371,15 @@ mod tests {
 
         assert_eq!(task.prompt, "Implement feature");
         assert_eq!(task.max_turns, 50);
+        assert_eq!(task.max_tokens, None);
+        assert_eq!(task.permission_mode, None);
+        assert_eq!(
+            task.clone()
+                .with_permission_mode(PermissionMode::Plan)
+                .permission_mode,
+            Some(PermissionMode::Plan)
+        );
+        assert_eq!(task.with_max_tokens(1024).max_tokens, Some(1024));
     }
 
     #[test]
@@ -228,6 +395,26 @@ mod tests {
         assert_eq!(task.max_turns, 100);
     }
 
+    #[test]
+    fn test_task_from_template() {
+        let template = TemplateConfig {
+            system_prompt: "You review code.".to_string(),
+            tools: vec!["Read".to_string()],
+            max_turns: 5,
+            max_tokens: Some(2048),
+            permission_mode: Some(PermissionMode::Plan),
+            ..TemplateConfig::default()
+        };
+        let task = Task::from_template("Review the diff", Context::default(), &template);
+
+        assert_eq!(task.prompt, "Review the diff");
+        assert_eq!(task.system_prompt, "You review code.");
+        assert_eq!(task.max_turns, 5);
+        assert_eq!(task.max_tokens, Some(2048));
+        assert_eq!(task.permission_mode, Some(PermissionMode::Plan));
+        assert_eq!(task.tools, ["Read"]);
+    }
+
     #[test]
     fn test_context_default() {
         let context = Context::default();
@@ -241,14 +428,27 @@ mod tests {
         let response = Response {
             content: "Test response".to_string(),
             tool_calls: vec![ToolCall {
+                id: "toolu_1".to_string(),
                 name: "Read".to_string(),
                 arguments: serde_json::json!({"path": "test.rs"}),
+                result: Some("fn main() {}".to_string()),
+                is_error: false,
+                duration_ms: Some(12),
             }],
             usage: Usage {
                 input_tokens: 100,
                 output_tokens: 50,
                 total_cost_usd: 0.01,
             },
+            violations: Vec::new(),
+            turns: 3,
+            session_id: Some("session-1".to_string()),
+            generation: Some(GenerationConfig {
+                model: "sonnet".to_string(),
+                max_tokens: 4096,
+                max_turns: Some(10),
+                ..GenerationConfig::default()
+            }),
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -257,5 +457,10 @@ mod tests {
         assert_eq!(response.content, deserialized.content);
         assert_eq!(response.tool_calls.len(), deserialized.tool_calls.len());
         assert_eq!(response.usage.input_tokens, deserialized.usage.input_tokens);
+        assert_eq!(deserialized.turns, 3);
+        assert_eq!(deserialized.session_id.as_deref(), Some("session-1"));
+        assert_eq!(deserialized.generation, response.generation);
+        assert!(json.contains("\"maxTokens\":4096"));
+        assert!(!json.contains("temperature"));
     }
 }
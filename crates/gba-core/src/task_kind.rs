@@ -0,0 +1,235 @@
+//! Pluggable task kinds.
+//!
+//! A task kind decides which prompt template a task renders, how its context
+//! is prepared, how the agent's response is post-processed and which tools
+//! the agent may use. The built-in kinds (`planning`, `implementation`,
+//! `verification` and `review`) are registered in every
+//! [`TaskKindRegistry`]; embedders register their own, e.g. a
+//! `security-audit` kind with a read-only tool set.
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_core::task_kind::{TaskKindPlugin, TaskKindRegistry};
+//! use gba_core::{Context, Response};
+//!
+//! struct SecurityAudit;
+//!
+//! impl TaskKindPlugin for SecurityAudit {
+//!     fn name(&self) -> &str {
+//!         "security-audit"
+//!     }
+//!
+//!     fn template_name(&self) -> &str {
+//!         "security-audit"
+//!     }
+//!
+//!     fn prepare_context(&self, context: &mut Context) -> gba_core::Result<()> {
+//!         context.metadata.insert("focus".to_string(), "owasp-top-10".into());
+//!         Ok(())
+//!     }
+//!
+//!     fn post_process(&self, mut response: Response) -> Response {
+//!         response.content = response.content.trim().to_string();
+//!         response
+//!     }
+//!
+//!     fn default_tools(&self) -> Vec<String> {
+//!         vec!["Read".to_string(), "Glob".to_string(), "Grep".to_string()]
+//!     }
+//! }
+//!
+//! let mut registry = TaskKindRegistry::new();
+//! registry.register(SecurityAudit);
+//! assert_eq!(registry.get("security-audit").unwrap().template_name(), "security-audit");
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::task::{Context, Response};
+
+/// Extension point for a kind of task.
+pub trait TaskKindPlugin: Send + Sync {
+    /// Name of the kind, e.g. `"security-audit"`.
+    fn name(&self) -> &str;
+
+    /// Name of the prompt template the kind renders.
+    fn template_name(&self) -> &str;
+
+    /// Prepare the context of a task before its prompt is rendered.
+    ///
+    /// Entries added to [`Context::metadata`] are available as template
+    /// variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the context cannot be prepared; the task is not
+    /// run.
+    fn prepare_context(&self, context: &mut Context) -> Result<()> {
+        let _ = context;
+        Ok(())
+    }
+
+    /// Post-process the agent's response.
+    fn post_process(&self, response: Response) -> Response {
+        response
+    }
+
+    /// Tools the agent may use for this kind. Empty allows all tools.
+    fn default_tools(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Task kind defined by its name, template and tools.
+///
+/// Used for the built-in kinds and for simple custom kinds that need no
+/// context preparation or post-processing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StandardTaskKind {
+    /// Name of the kind.
+    name: String,
+    /// Name of the prompt template.
+    template_name: String,
+    /// Tools the agent may use.
+    tools: Vec<String>,
+}
+
+impl StandardTaskKind {
+    /// Create a task kind rendering the given template.
+    #[must_use]
+    pub fn new(name: impl Into<String>, template_name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            template_name: template_name.into(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Restrict the tools the agent may use.
+    #[must_use]
+    pub fn with_tools(mut self, tools: Vec<String>) -> Self {
+        self.tools = tools;
+        self
+    }
+}
+
+impl TaskKindPlugin for StandardTaskKind {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn template_name(&self) -> &str {
+        &self.template_name
+    }
+
+    fn default_tools(&self) -> Vec<String> {
+        self.tools.clone()
+    }
+}
+
+/// Registry of task kinds by name.
+#[derive(Clone)]
+pub struct TaskKindRegistry {
+    /// Registered kinds, in registration order.
+    kinds: Vec<Arc<dyn TaskKindPlugin>>,
+}
+
+impl fmt::Debug for TaskKindRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskKindRegistry")
+            .field("kinds", &self.names())
+            .finish()
+    }
+}
+
+impl Default for TaskKindRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskKindRegistry {
+    /// Create a registry with the built-in kinds.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        for (name, template) in [
+            ("planning", "plan"),
+            ("implementation", "implement"),
+            ("verification", "verify"),
+            ("review", "review"),
+        ] {
+            registry.register(StandardTaskKind::new(name, template));
+        }
+        registry
+    }
+
+    /// Create a registry without any kinds.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self { kinds: Vec::new() }
+    }
+
+    /// Register a kind, replacing any kind with the same name.
+    ///
+    /// Returns the replaced kind, if any.
+    pub fn register(
+        &mut self,
+        kind: impl TaskKindPlugin + 'static,
+    ) -> Option<Arc<dyn TaskKindPlugin>> {
+        let kind: Arc<dyn TaskKindPlugin> = Arc::new(kind);
+        match self.kinds.iter_mut().find(|k| k.name() == kind.name()) {
+            Some(existing) => Some(std::mem::replace(existing, kind)),
+            None => {
+                self.kinds.push(kind);
+                None
+            }
+        }
+    }
+
+    /// Get a kind by name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Arc<dyn TaskKindPlugin>> {
+        self.kinds.iter().find(|k| k.name() == name).cloned()
+    }
+
+    /// Get the names of all registered kinds.
+    #[must_use]
+    pub fn names(&self) -> Vec<&str> {
+        self.kinds.iter().map(|k| k.name()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_builtins_and_override() {
+        let mut registry = TaskKindRegistry::new();
+        assert_eq!(
+            registry.names(),
+            vec!["planning", "implementation", "verification", "review"]
+        );
+        assert_eq!(
+            registry.get("verification").unwrap().template_name(),
+            "verify"
+        );
+        assert!(registry.get("i18n-extraction").is_none());
+
+        let readonly = StandardTaskKind::new("review", "strict-review")
+            .with_tools(vec!["Read".to_string(), "Grep".to_string()]);
+        let replaced = registry.register(readonly).unwrap();
+        assert_eq!(replaced.template_name(), "review");
+
+        let review = registry.get("review").unwrap();
+        assert_eq!(review.template_name(), "strict-review");
+        assert_eq!(review.default_tools(), vec!["Read", "Grep"]);
+        assert_eq!(registry.names().len(), 4);
+
+        assert!(TaskKindRegistry::empty().names().is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+//! Structured JSONL transcripts of agent runs.
+//!
+//! A transcript records a run as one JSON event per line: a header with the
+//! schema version and prompt, followed by the assistant's text, tool calls,
+//! tool results and the final usage. Transcripts can be replayed later
+//! without calling the API again.
+//!
+//! # Schema
+//!
+//! Every line is an object with a `type` field:
+//!
+//! | `type`       | Fields                                                       |
+//! |--------------|--------------------------------------------------------------|
+//! | `header`     | `version`, `model`, `startedAt`, `prompt`                    |
+//! | `text`       | `text`                                                       |
+//! | `toolCall`   | `id`, `name`, `input`                                        |
+//! | `toolResult` | `toolUseId`, `content`, `isError`                            |
+//! | `usage`      | `inputTokens`, `outputTokens`, `totalCostUsd`, `turns`, `durationMs` |
+//!
+//! The header is always the first line. Readers reject transcripts with a
+//! newer version than [`TRANSCRIPT_VERSION`].
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use claude_agent_sdk_rs::{ContentBlock, Message, ToolResultBlock, ToolResultContent};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Current transcript schema version.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// Result type alias for transcript operations.
+pub type Result<T> = std::result::Result<T, TranscriptError>;
+
+/// Error types for transcript operations.
+#[derive(Debug, Error)]
+pub enum TranscriptError {
+    /// IO error.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A transcript line could not be serialized or parsed.
+    #[error("Invalid transcript event at line {line}: {source}")]
+    Event {
+        /// Line number, starting at 1 (0 when serializing).
+        line: usize,
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+
+    /// The transcript doesn't start with a header.
+    #[error("Transcript has no header")]
+    MissingHeader,
+
+    /// The transcript was written with a newer schema.
+    #[error("Unsupported transcript version {0} (supported up to {TRANSCRIPT_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
+/// A single transcript event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum TranscriptEvent {
+    /// Run information, always the first event.
+    Header {
+        /// Schema version.
+        version: u32,
+        /// Model used for the run.
+        model: String,
+        /// When the run started.
+        started_at: DateTime<Utc>,
+        /// Full prompt sent to the agent.
+        prompt: String,
+    },
+
+    /// Text produced by the assistant.
+    Text {
+        /// Text content.
+        text: String,
+    },
+
+    /// Tool invoked by the assistant.
+    ToolCall {
+        /// Tool use identifier.
+        id: String,
+        /// Tool name.
+        name: String,
+        /// Tool input.
+        input: serde_json::Value,
+    },
+
+    /// Result of a tool call.
+    ToolResult {
+        /// Identifier of the tool use this result belongs to.
+        tool_use_id: String,
+        /// Result content.
+        content: String,
+        /// Whether the tool failed.
+        is_error: bool,
+    },
+
+    /// Final usage of the run.
+    Usage {
+        /// Input tokens used.
+        input_tokens: u64,
+        /// Output tokens used.
+        output_tokens: u64,
+        /// Total cost in USD.
+        total_cost_usd: f64,
+        /// Number of agent turns.
+        turns: u32,
+        /// Duration of the run in milliseconds.
+        duration_ms: u64,
+    },
+}
+
+/// Transcript of an agent run.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba_core::transcript::{Transcript, TranscriptEvent};
+/// use std::path::Path;
+///
+/// let transcript = Transcript::load(Path::new(".gba/transcripts/run.jsonl"))?;
+/// for event in transcript.events() {
+///     if let TranscriptEvent::Text { text } = event {
+///         print!("{text}");
+///     }
+/// }
+/// # Ok::<(), gba_core::transcript::TranscriptError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    /// Events, starting with the header.
+    events: Vec<TranscriptEvent>,
+}
+
+impl Transcript {
+    /// Start a transcript for a run.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Model used for the run.
+    /// * `prompt` - Full prompt sent to the agent.
+    #[must_use]
+    pub fn new(model: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            events: vec![TranscriptEvent::Header {
+                version: TRANSCRIPT_VERSION,
+                model: model.into(),
+                started_at: Utc::now(),
+                prompt: prompt.into(),
+            }],
+        }
+    }
+
+    /// Get the events, starting with the header.
+    #[must_use]
+    pub fn events(&self) -> &[TranscriptEvent] {
+        &self.events
+    }
+
+    /// Append an event.
+    pub fn push(&mut self, event: TranscriptEvent) {
+        self.events.push(event);
+    }
+
+    /// Record a message received from the Claude Agent SDK.
+    ///
+    /// System messages and stream events are not recorded.
+    pub fn push_message(&mut self, message: &Message) {
+        match message {
+            Message::Assistant(msg) => {
+                for block in &msg.message.content {
+                    self.push_block(block);
+                }
+            }
+            Message::User(msg) => {
+                for block in msg.content.iter().flatten() {
+                    if let ContentBlock::ToolResult(_) = block {
+                        self.push_block(block);
+                    }
+                }
+            }
+            Message::Result(result) => {
+                let tokens = |key: &str| {
+                    result
+                        .usage
+                        .as_ref()
+                        .and_then(|usage| usage.get(key))
+                        .and_then(serde_json::Value::as_u64)
+                        .unwrap_or_default()
+                };
+                self.push(TranscriptEvent::Usage {
+                    input_tokens: tokens("input_tokens"),
+                    output_tokens: tokens("output_tokens"),
+                    total_cost_usd: result.total_cost_usd.unwrap_or_default(),
+                    turns: result.num_turns,
+                    duration_ms: result.duration_ms,
+                });
+            }
+            Message::System(_) | Message::StreamEvent(_) | Message::ControlCancelRequest(_) => {}
+        }
+    }
+
+    /// Record a content block.
+    fn push_block(&mut self, block: &ContentBlock) {
+        match block {
+            ContentBlock::Text(text) => self.push(TranscriptEvent::Text {
+                text: text.text.clone(),
+            }),
+            ContentBlock::ToolUse(tool) => self.push(TranscriptEvent::ToolCall {
+                id: tool.id.clone(),
+                name: tool.name.clone(),
+                input: tool.input.clone(),
+            }),
+            ContentBlock::ToolResult(result) => self.push(TranscriptEvent::ToolResult {
+                tool_use_id: result.tool_use_id.clone(),
+                content: tool_result_text(result),
+                is_error: result.is_error.unwrap_or(false),
+            }),
+            _ => {}
+        }
+    }
+
+    /// Render the transcript as JSON lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an event cannot be serialized.
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut out = String::new();
+        for event in &self.events {
+            let line = serde_json::to_string(event)
+                .map_err(|source| TranscriptError::Event { line: 0, source })?;
+            out.push_str(&line);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Parse a transcript from JSON lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a line is invalid, the header is missing, or the
+    /// transcript was written with a newer schema version.
+    pub fn from_jsonl(content: &str) -> Result<Self> {
+        let events = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| TranscriptError::Event {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect::<Result<Vec<TranscriptEvent>>>()?;
+
+        match events.first() {
+            Some(TranscriptEvent::Header { version, .. }) if *version > TRANSCRIPT_VERSION => {
+                Err(TranscriptError::UnsupportedVersion(*version))
+            }
+            Some(TranscriptEvent::Header { .. }) => Ok(Self { events }),
+            _ => Err(TranscriptError::MissingHeader),
+        }
+    }
+
+    /// Load a transcript from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_jsonl(&std::fs::read_to_string(path)?)
+    }
+
+    /// Save the transcript to a file, creating parent directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transcript cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::atomic::write(path, self.to_jsonl()?)?;
+        tracing::debug!("Saved transcript to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Get the content of a tool result as text, joining the text of its
+/// blocks.
+pub(crate) fn tool_result_text(result: &ToolResultBlock) -> String {
+    match &result.content {
+        Some(ToolResultContent::Text(text)) => text.clone(),
+        Some(ToolResultContent::Blocks(blocks)) => blocks
+            .iter()
+            .map(|block| match block.get("text").and_then(|t| t.as_str()) {
+                Some(text) => text.to_string(),
+                None => block.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_roundtrip() {
+        let mut transcript = Transcript::new("sonnet", "Add a login endpoint");
+        transcript.push(TranscriptEvent::Text {
+            text: "Reading the router.".to_string(),
+        });
+        transcript.push(TranscriptEvent::ToolCall {
+            id: "toolu_1".to_string(),
+            name: "Read".to_string(),
+            input: serde_json::json!({ "file_path": "src/router.rs" }),
+        });
+        transcript.push(TranscriptEvent::ToolResult {
+            tool_use_id: "toolu_1".to_string(),
+            content: "fn router() {}".to_string(),
+            is_error: false,
+        });
+
+        let jsonl = transcript.to_jsonl().unwrap();
+        assert!(jsonl.starts_with("{\"type\":\"header\",\"version\":1,"));
+        assert!(jsonl.contains("\"type\":\"toolResult\",\"toolUseId\":\"toolu_1\""));
+        assert_eq!(Transcript::from_jsonl(&jsonl).unwrap(), transcript);
+    }
+
+    #[test]
+    fn test_transcript_rejects_invalid() {
+        assert!(matches!(
+            Transcript::from_jsonl("{\"type\":\"text\",\"text\":\"hi\"}\n"),
+            Err(TranscriptError::MissingHeader)
+        ));
+
+        let newer = "{\"type\":\"header\",\"version\":99,\"model\":\"m\",\
+                     \"startedAt\":\"2026-02-24T10:30:00Z\",\"prompt\":\"p\"}";
+        assert!(matches!(
+            Transcript::from_jsonl(newer),
+            Err(TranscriptError::UnsupportedVersion(99))
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! Version control backends.
+//!
+//! GBA scans, prompts and runs against any directory. A [`Vcs`] backend
+//! provides what version control adds on top: repository metadata, the
+//! status of the working tree and feature worktrees. Two backends exist:
+//!
+//! - [`GitRepo`] for git repositories, see [`crate::git`]
+//! - [`PlainDirectory`] for folders under no version control: no metadata,
+//!   no changes to protect, and tasks run in the directory itself instead of
+//!   a worktree
+//!
+//! The backend is detected from the directory, or set with
+//! `repository.vcs` in `.gba/config.yml`. Other systems such as Jujutsu or
+//! Mercurial fit in as further [`VcsKind`]s implementing [`Vcs`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use gba_core::vcs;
+//!
+//! let repo = vcs::open(Path::new("/path/to/project"), None);
+//! if !repo.supports_worktrees() {
+//!     println!("{} is not a git repository", repo.path().display());
+//! }
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::debug;
+
+use crate::git::{self, GitError, RepoInfo, StatusEntry};
+
+/// Result type alias for version control operations.
+pub type Result<T> = std::result::Result<T, VcsError>;
+
+/// Error types for version control operations.
+#[derive(Debug, Error)]
+pub enum VcsError {
+    /// Git error.
+    #[error("Git error: {0}")]
+    Git(#[from] GitError),
+
+    /// The backend doesn't support the operation.
+    #[error("{operation} requires version control, but {} is a {kind} directory", .path.display())]
+    Unsupported {
+        /// Backend of the directory.
+        kind: VcsKind,
+        /// The operation, e.g. `"Creating a worktree"`.
+        operation: &'static str,
+        /// The directory.
+        path: PathBuf,
+    },
+}
+
+/// Kind of version control of a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VcsKind {
+    /// A git repository.
+    Git,
+
+    /// A directory under no version control.
+    #[serde(alias = "none")]
+    Plain,
+}
+
+impl fmt::Display for VcsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Git => write!(f, "git"),
+            Self::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+/// A version control backend for a directory.
+pub trait Vcs: fmt::Debug + Send + Sync {
+    /// Get the kind of the backend.
+    fn kind(&self) -> VcsKind;
+
+    /// Get the directory.
+    fn path(&self) -> &Path;
+
+    /// Read the repository metadata: remote, branch and revision.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metadata cannot be read.
+    fn info(&self) -> Result<RepoInfo>;
+
+    /// Get the uncommitted changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the status cannot be read.
+    fn status(&self) -> Result<Vec<StatusEntry>>;
+
+    /// Check whether features can be implemented in worktrees of their own.
+    fn supports_worktrees(&self) -> bool;
+
+    /// Check that features can be implemented in worktrees.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VcsError::Unsupported`] otherwise.
+    fn require_worktrees(&self) -> Result<()> {
+        if self.supports_worktrees() {
+            return Ok(());
+        }
+        Err(VcsError::Unsupported {
+            kind: self.kind(),
+            operation: "Creating a worktree",
+            path: self.path().to_path_buf(),
+        })
+    }
+}
+
+/// A git repository or worktree.
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    /// Directory in the repository.
+    path: PathBuf,
+}
+
+impl GitRepo {
+    /// Open a directory in a git repository.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Vcs for GitRepo {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Git
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn info(&self) -> Result<RepoInfo> {
+        Ok(git::repo_info(&self.path)?)
+    }
+
+    fn status(&self) -> Result<Vec<StatusEntry>> {
+        Ok(git::status(&self.path)?)
+    }
+
+    fn supports_worktrees(&self) -> bool {
+        true
+    }
+}
+
+/// A directory under no version control.
+#[derive(Debug, Clone)]
+pub struct PlainDirectory {
+    /// The directory.
+    path: PathBuf,
+}
+
+impl PlainDirectory {
+    /// Open a directory.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Vcs for PlainDirectory {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Plain
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn info(&self) -> Result<RepoInfo> {
+        Ok(RepoInfo::default())
+    }
+
+    fn status(&self) -> Result<Vec<StatusEntry>> {
+        Ok(Vec::new())
+    }
+
+    fn supports_worktrees(&self) -> bool {
+        false
+    }
+}
+
+/// Detect the version control of a directory.
+///
+/// A directory is in a git repository if it or one of its ancestors
+/// contains `.git`, a directory in a repository or a file in a worktree.
+#[must_use]
+pub fn detect(path: &Path) -> VcsKind {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    if absolute.ancestors().any(|dir| dir.join(".git").exists()) {
+        VcsKind::Git
+    } else {
+        VcsKind::Plain
+    }
+}
+
+/// Open the version control backend of a directory.
+///
+/// # Arguments
+///
+/// * `path` - The directory.
+/// * `kind` - Backend to use, e.g. from `repository.vcs`; detected if
+///   `None`.
+#[must_use]
+pub fn open(path: &Path, kind: Option<VcsKind>) -> Box<dyn Vcs> {
+    let kind = kind.unwrap_or_else(|| detect(path));
+    debug!("Using {} backend for {}", kind, path.display());
+    match kind {
+        VcsKind::Git => Box::new(GitRepo::new(path)),
+        VcsKind::Plain => Box::new(PlainDirectory::new(path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_open() {
+        let dir = std::env::temp_dir().join(format!("gba-test-vcs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let nested = dir.join("repo").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        // The temp directory itself may be inside a repository
+        if detect(&dir) == VcsKind::Plain {
+            assert_eq!(detect(&nested), VcsKind::Plain);
+            let plain = open(&nested, None);
+            assert_eq!(plain.info().unwrap(), RepoInfo::default());
+            assert!(plain.status().unwrap().is_empty());
+            assert!(matches!(
+                plain.require_worktrees(),
+                Err(VcsError::Unsupported {
+                    kind: VcsKind::Plain,
+                    ..
+                })
+            ));
+        }
+
+        std::fs::create_dir(dir.join("repo").join(".git")).unwrap();
+        assert_eq!(detect(&nested), VcsKind::Git);
+        assert!(open(&nested, None).supports_worktrees());
+        assert_eq!(open(&nested, Some(VcsKind::Plain)).kind(), VcsKind::Plain);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_vcs_kind_serialization() {
+        assert_eq!(serde_json::to_string(&VcsKind::Plain).unwrap(), "\"plain\"");
+        assert_eq!(
+            serde_json::from_str::<VcsKind>("\"none\"").unwrap(),
+            VcsKind::Plain
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Heuristic detection of lockfiles, vendored code and build output.
+//!
+//! Lockfiles, vendored dependencies and generated bundles are rarely worth
+//! the agent's attention, yet a single `package-lock.json` or `vendor/`
+//! directory can take most of a context budget. [`detect`] recognizes them
+//! by name:
+//!
+//! - lockfiles: `*.lock` (`Cargo.lock`, `yarn.lock`, `poetry.lock`, ...),
+//!   `package-lock.json`, `npm-shrinkwrap.json`, `pnpm-lock.yaml` and
+//!   `go.sum`;
+//! - directories of vendored code and build output: `vendor/`,
+//!   `third_party/`, `third-party/`, `bower_components/`, `dist/` and
+//!   `coverage/`, at any depth;
+//! - minified bundles and source maps: `*.min.js`, `*.min.css` and
+//!   `*.js.map`.
+//!
+//! The context builder leaves matching paths out unless they match one of
+//! the patterns kept with
+//! [`crate::context_builder::ContextBuilderConfig::with_keep_vendored`].
+
+use std::path::{Component, Path};
+
+/// Directories holding vendored code or build output, with the pattern
+/// reported for them.
+const VENDORED_DIRS: &[(&str, &str)] = &[
+    ("vendor", "vendor/"),
+    ("third_party", "third_party/"),
+    ("third-party", "third-party/"),
+    ("bower_components", "bower_components/"),
+    ("dist", "dist/"),
+    ("coverage", "coverage/"),
+];
+
+/// Lockfiles not named `*.lock`.
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "go.sum",
+];
+
+/// Suffixes of lockfiles and generated files, with the pattern reported for
+/// them.
+const GENERATED_SUFFIXES: &[(&str, &str)] = &[
+    (".lock", "*.lock"),
+    (".min.js", "*.min.js"),
+    (".min.css", "*.min.css"),
+    (".js.map", "*.js.map"),
+];
+
+/// Check whether a path looks like a lockfile, vendored code or build
+/// output.
+///
+/// # Arguments
+///
+/// * `path` - Path relative to the repository root.
+/// * `is_dir` - Whether the path is a directory.
+///
+/// # Returns
+///
+/// The heuristic that matched, e.g. `"vendor/"` or `"*.lock"`.
+///
+/// # Examples
+///
+/// ```
+/// use gba_core::vendored::detect;
+/// use std::path::Path;
+///
+/// assert_eq!(detect(Path::new("Cargo.lock"), false), Some("*.lock"));
+/// assert_eq!(detect(Path::new("web/vendor"), true), Some("vendor/"));
+/// assert_eq!(detect(Path::new("src/vendor.rs"), false), None);
+/// ```
+#[must_use]
+pub fn detect(path: &Path, is_dir: bool) -> Option<&'static str> {
+    let names = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let (&name, parents) = names.split_last()?;
+
+    let dirs = if is_dir { &names[..] } else { parents };
+    let vendored_dir = dirs.iter().find_map(|dir| {
+        VENDORED_DIRS
+            .iter()
+            .find(|(name, _)| name == dir)
+            .map(|(_, pattern)| *pattern)
+    });
+    if vendored_dir.is_some() || is_dir {
+        return vendored_dir;
+    }
+
+    if let Some(lockfile) = LOCKFILES.iter().find(|lockfile| **lockfile == name) {
+        return Some(lockfile);
+    }
+    GENERATED_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| name.len() > suffix.len() && name.ends_with(suffix))
+        .map(|(_, pattern)| *pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_lockfiles() {
+        for (path, pattern) in [
+            ("Cargo.lock", "*.lock"),
+            ("web/yarn.lock", "*.lock"),
+            ("web/package-lock.json", "package-lock.json"),
+            ("pnpm-lock.yaml", "pnpm-lock.yaml"),
+            ("go.sum", "go.sum"),
+            ("static/app.min.js", "*.min.js"),
+            ("static/app.js.map", "*.js.map"),
+        ] {
+            assert_eq!(detect(Path::new(path), false), Some(pattern), "{path}");
+        }
+        assert_eq!(detect(Path::new(".lock"), false), None);
+        assert_eq!(detect(Path::new("src/lock.rs"), false), None);
+        assert_eq!(detect(Path::new("package.json"), false), None);
+    }
+
+    #[test]
+    fn test_detect_vendored_dirs() {
+        assert_eq!(detect(Path::new("vendor"), true), Some("vendor/"));
+        assert_eq!(
+            detect(Path::new("crates/ffi/third_party"), true),
+            Some("third_party/")
+        );
+        assert_eq!(detect(Path::new("web/dist/app.js"), false), Some("dist/"));
+        assert_eq!(
+            detect(Path::new("coverage/lcov.info"), false),
+            Some("coverage/")
+        );
+        // A file named like a directory is not vendored
+        assert_eq!(detect(Path::new("docs/dist"), false), None);
+        assert_eq!(detect(Path::new("src/vendoring"), true), None);
+    }
+}
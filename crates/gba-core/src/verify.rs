@@ -0,0 +1,576 @@
+//! Verification commands and reports.
+//!
+//! The verification phase runs the project's configured commands, e.g.
+//! `cargo test` and `cargo clippy`, in the feature's worktree before asking
+//! the agent to verify the feature. The results are passed to the verify
+//! template so the agent triages real failures instead of guessing, and the
+//! agent's verdict is parsed from the `## Verification Status` section of its
+//! report.
+//!
+//! A project without configured commands gets the test commands of the
+//! tooling [`detect_commands`] finds in the repository: `cargo test`,
+//! `pytest`, `jest` or `npm test`, and `go test`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba_core::config::{VerificationCommand, VerificationConfig};
+//! use gba_core::verify::{self, VerificationReport};
+//!
+//! # async fn example() {
+//! let config = VerificationConfig {
+//!     commands: vec![VerificationCommand::new("test", "cargo test")],
+//!     ..VerificationConfig::default()
+//! };
+//! let report = VerificationReport::new(verify::run_commands(".trees/add-auth", &config).await);
+//! for failure in report.failures() {
+//!     eprintln!("{} failed:\n{}", failure.name, failure.output);
+//! }
+//! # }
+//! ```
+
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::config::{VerificationCommand, VerificationConfig};
+use crate::postprocess::PostProcessor;
+use crate::task::Response;
+
+/// Context metadata key command results are added under.
+pub const COMMANDS_KEY: &str = "commands";
+
+/// Heading of the report section holding the verdict.
+const STATUS_HEADING: &str = "verification status";
+
+/// Heading of the report section listing the issues found.
+const ISSUES_HEADING: &str = "issues found";
+
+/// Result of a verification command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandResult {
+    /// Name of the command, e.g. `"test"`.
+    pub name: String,
+
+    /// Command line that was run.
+    pub command: String,
+
+    /// Whether the command exited successfully.
+    pub success: bool,
+
+    /// Exit code, if the command exited normally.
+    pub exit_code: Option<i32>,
+
+    /// Whether the command was killed after its timeout.
+    pub timed_out: bool,
+
+    /// How long the command ran, in milliseconds.
+    pub duration_ms: u64,
+
+    /// Combined stdout and stderr, keeping the end if truncated.
+    pub output: String,
+}
+
+/// Verdict of the agent on a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    /// All checks pass.
+    Verified,
+    /// Issues remain that need more work.
+    NeedsWork,
+    /// The feature doesn't work.
+    Failed,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Verified => write!(f, "VERIFIED"),
+            Self::NeedsWork => write!(f, "NEEDS WORK"),
+            Self::Failed => write!(f, "FAILED"),
+        }
+    }
+}
+
+impl Verdict {
+    /// Parse the verdict of a verification report.
+    ///
+    /// Reads the first verdict in the `## Verification Status` section:
+    /// `VERIFIED`, `NEEDS WORK` or `FAILED`, and also `PASS` or `FAIL`.
+    ///
+    /// # Returns
+    ///
+    /// The verdict, or `None` if the section is missing or holds none.
+    #[must_use]
+    pub fn parse(report: &str) -> Option<Self> {
+        let mut in_section = false;
+        for line in report.lines() {
+            let line = line.trim();
+            if let Some(heading) = line.strip_prefix('#') {
+                in_section = heading
+                    .trim_start_matches('#')
+                    .trim()
+                    .eq_ignore_ascii_case(STATUS_HEADING);
+                continue;
+            }
+            if in_section && let Some(verdict) = Self::parse_line(line) {
+                return Some(verdict);
+            }
+        }
+        None
+    }
+
+    /// Parse a verdict line, ignoring markdown emphasis and brackets.
+    fn parse_line(line: &str) -> Option<Self> {
+        let words = line
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|word| !word.is_empty())
+            .map(str::to_ascii_uppercase)
+            .collect::<Vec<_>>();
+        // A line listing the choices, as in the template, is no verdict
+        if words.len() > 3 {
+            return None;
+        }
+        match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["VERIFIED"] | ["PASS"] | ["PASSED"] => Some(Self::Verified),
+            ["NEEDS", "WORK"] => Some(Self::NeedsWork),
+            ["FAILED"] | ["FAIL"] => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Report of a verification: the command results and the agent's verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    /// Results of the verification commands, in configuration order.
+    pub commands: Vec<CommandResult>,
+
+    /// Verdict parsed from the agent's report.
+    #[serde(default)]
+    pub verdict: Option<Verdict>,
+
+    /// The agent's report.
+    #[serde(default)]
+    pub report: String,
+}
+
+impl VerificationReport {
+    /// Create a report of command results, before the agent has run.
+    #[must_use]
+    pub const fn new(commands: Vec<CommandResult>) -> Self {
+        Self {
+            commands,
+            verdict: None,
+            report: String::new(),
+        }
+    }
+
+    /// Add the agent's report and parse its verdict.
+    #[must_use]
+    pub fn with_response(mut self, response: &Response) -> Self {
+        self.verdict = Verdict::parse(&response.content);
+        self.report.clone_from(&response.content);
+        self
+    }
+
+    /// Get the commands that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &CommandResult> {
+        self.commands.iter().filter(|command| !command.success)
+    }
+
+    /// Check whether the feature passed verification.
+    ///
+    /// Every command must succeed, and the agent, if it has run, must have
+    /// given the `VERIFIED` verdict. A report without a parsable verdict
+    /// doesn't pass.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        let commands_passed = self.failures().next().is_none();
+        let verdict_passed = self.report.is_empty() || self.verdict == Some(Verdict::Verified);
+        commands_passed && verdict_passed
+    }
+
+    /// Describe the failures as markdown, for an implementation task fixing
+    /// them: the output of each failed command, then the issues the agent
+    /// found.
+    #[must_use]
+    pub fn failure_context(&self) -> String {
+        let mut out = String::new();
+        for failure in self.failures() {
+            let status = match (failure.timed_out, failure.exit_code) {
+                (true, _) => "timed out".to_string(),
+                (false, Some(code)) => format!("exit code {code}"),
+                (false, None) => "failed".to_string(),
+            };
+            let _ = write!(
+                out,
+                "### `{}` ({status})\n\n```text\n{}\n```\n\n",
+                failure.command,
+                failure.output.trim_end()
+            );
+        }
+
+        if !self.report.is_empty() && self.verdict != Some(Verdict::Verified) {
+            let issues = PostProcessor::ExtractSections {
+                headings: vec![ISSUES_HEADING.to_string()],
+            }
+            .apply(&self.report);
+            out.push_str("### Verification report\n\n");
+            out.push_str(issues.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Get the command results as template metadata, under
+    /// [`COMMANDS_KEY`].
+    #[must_use]
+    pub fn commands_metadata(&self) -> serde_json::Value {
+        serde_json::to_value(&self.commands).unwrap_or_default()
+    }
+}
+
+/// Detect the test commands of a repository from the files marking its
+/// tooling.
+///
+/// Detects `cargo test` from `Cargo.toml`, `pytest` from its configuration
+/// or a `conftest.py`, `jest` from a dependency or configuration file, else
+/// `npm test` from a `test` script in `package.json`, and `go test` from
+/// `go.mod`. Only the top-level directory is looked at.
+///
+/// # Returns
+///
+/// The detected commands, empty if no tooling was recognized.
+#[must_use]
+pub fn detect_commands(dir: impl AsRef<Path>) -> Vec<VerificationCommand> {
+    let dir = dir.as_ref();
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap_or_default();
+
+    let mut commands = Vec::new();
+    if dir.join("Cargo.toml").is_file() {
+        commands.push(VerificationCommand::new("cargo", "cargo test"));
+    }
+    let uses_pytest = ["pytest.ini", "conftest.py"]
+        .iter()
+        .any(|name| dir.join(name).is_file())
+        || read("pyproject.toml").contains("[tool.pytest")
+        || read("setup.cfg").contains("[tool:pytest]")
+        || read("tox.ini").contains("[pytest]");
+    if uses_pytest {
+        commands.push(VerificationCommand::new("pytest", "python -m pytest"));
+    }
+    if let Some(command) = detect_node_command(dir) {
+        commands.push(command);
+    }
+    if dir.join("go.mod").is_file() {
+        commands.push(VerificationCommand::new("go", "go test ./..."));
+    }
+    commands
+}
+
+/// Detect the test command of a Node.js project.
+fn detect_node_command(dir: &Path) -> Option<VerificationCommand> {
+    let package = std::fs::read_to_string(dir.join("package.json")).ok()?;
+    let package = serde_json::from_str::<serde_json::Value>(&package).unwrap_or_default();
+
+    let has_jest_config = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .any(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with("jest.config."))
+        });
+    let has_jest_dependency = ["dependencies", "devDependencies"]
+        .iter()
+        .any(|key| package[key].get("jest").is_some());
+    if has_jest_config || has_jest_dependency || package.get("jest").is_some() {
+        return Some(VerificationCommand::new("jest", "npx jest"));
+    }
+
+    // `npm init` writes a test script that always fails
+    let script = package["scripts"]["test"].as_str()?;
+    (!script.contains("no test specified")).then(|| VerificationCommand::new("npm", "npm test"))
+}
+
+/// Get the commands to verify a directory with: the configured ones, or the
+/// detected ones if none are configured.
+#[must_use]
+pub fn resolve_commands(
+    dir: impl AsRef<Path>,
+    config: &VerificationConfig,
+) -> Vec<VerificationCommand> {
+    if !config.commands.is_empty() {
+        return config.commands.clone();
+    }
+    let commands = detect_commands(dir);
+    if !commands.is_empty() {
+        tracing::debug!(
+            "Detected verification commands: {}",
+            commands
+                .iter()
+                .map(|command| command.command.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    commands
+}
+
+/// Run the verification commands of a directory, one at a time.
+///
+/// Runs the configured commands or, if none are configured, the ones
+/// [`detect_commands`] finds. Commands run through the platform shell (`sh -c`, or `cmd /C` on
+/// Windows). A command that cannot be started or times out is reported as
+/// failed; the remaining commands still run.
+///
+/// # Arguments
+///
+/// * `dir` - Working directory, usually the feature's worktree.
+/// * `config` - Commands, timeout and output limit.
+pub async fn run_commands(
+    dir: impl AsRef<Path>,
+    config: &VerificationConfig,
+) -> Vec<CommandResult> {
+    let commands = resolve_commands(dir.as_ref(), config);
+    let mut results = Vec::with_capacity(commands.len());
+    for command in &commands {
+        results.push(run_command(dir.as_ref(), command, config).await);
+    }
+    results
+}
+
+/// Run a verification command.
+#[tracing::instrument(skip(config), fields(command = %command.command))]
+async fn run_command(
+    dir: &Path,
+    command: &VerificationCommand,
+    config: &VerificationConfig,
+) -> CommandResult {
+    let timeout = Duration::from_secs(command.timeout_secs.unwrap_or(config.timeout_secs));
+    let started = Instant::now();
+
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C");
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c");
+        process
+    };
+    let child = process
+        .arg(&command.command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut result = CommandResult {
+        name: command.name.clone(),
+        command: command.command.clone(),
+        success: false,
+        exit_code: None,
+        timed_out: false,
+        duration_ms: 0,
+        output: String::new(),
+    };
+    match child {
+        Ok(child) => match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(Ok(output)) => {
+                result.success = output.status.success();
+                result.exit_code = output.status.code();
+                let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+                combined.push_str(&String::from_utf8_lossy(&output.stderr));
+                result.output = tail(&combined, config.max_output_bytes);
+            }
+            Ok(Err(e)) => result.output = format!("Failed to wait for the command: {e}"),
+            Err(_) => {
+                result.timed_out = true;
+                result.output = format!("Timed out after {}s", timeout.as_secs());
+            }
+        },
+        Err(e) => result.output = format!("Failed to start the command: {e}"),
+    }
+    result.duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    if result.success {
+        tracing::debug!("Verification command {} passed", command.name);
+    } else {
+        tracing::info!("Verification command {} failed", command.name);
+    }
+    result
+}
+
+/// Keep the end of an output, where test failures and errors are reported.
+fn tail(output: &str, max_bytes: usize) -> String {
+    if output.len() <= max_bytes {
+        return output.to_string();
+    }
+    let mut start = output.len() - max_bytes;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[... truncated ...]\n{}", &output[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_parse() {
+        let report = "# Verification Report\n\n## Verification Status\n\n**NEEDS WORK**\n";
+        assert_eq!(Verdict::parse(report), Some(Verdict::NeedsWork));
+        assert_eq!(
+            Verdict::parse("## Verification Status\n\n[VERIFIED / NEEDS WORK / FAILED]\n\nFAIL\n"),
+            Some(Verdict::Failed)
+        );
+        assert_eq!(
+            Verdict::parse("## Verification Status\n\nVerified\n"),
+            Some(Verdict::Verified)
+        );
+        assert_eq!(Verdict::parse("VERIFIED\n"), None);
+    }
+
+    #[test]
+    fn test_detect_commands() {
+        let dir = std::env::temp_dir().join("gba-test-verify-detect");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(detect_commands(&dir).is_empty());
+
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{"scripts": {"test": "echo \"Error: no test specified\" && exit 1"}}"#,
+        )
+        .unwrap();
+        assert!(detect_commands(&dir).is_empty());
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{"scripts": {"test": "vitest"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            detect_commands(&dir),
+            vec![VerificationCommand::new("npm", "npm test")]
+        );
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{"devDependencies": {"jest": "^29"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            detect_commands(&dir),
+            vec![VerificationCommand::new("jest", "npx jest")]
+        );
+
+        std::fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(dir.join("pyproject.toml"), "[tool.pytest.ini_options]\n").unwrap();
+        std::fs::write(dir.join("go.mod"), "module example.com/app\n").unwrap();
+        let names = detect_commands(&dir)
+            .into_iter()
+            .map(|command| command.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["cargo", "pytest", "jest", "go"]);
+
+        let config = VerificationConfig {
+            commands: vec![VerificationCommand::new("check", "make check")],
+            ..VerificationConfig::default()
+        };
+        assert_eq!(resolve_commands(&dir, &config), config.commands);
+        assert_eq!(
+            resolve_commands(&dir, &VerificationConfig::default()).len(),
+            4
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_report_passed() {
+        let result = |name: &str, success: bool| CommandResult {
+            name: name.to_string(),
+            command: format!("cargo {name}"),
+            success,
+            exit_code: Some(i32::from(!success)),
+            timed_out: false,
+            duration_ms: 10,
+            output: String::new(),
+        };
+
+        let report = VerificationReport::new(vec![result("test", true), result("clippy", true)]);
+        assert!(report.passed());
+
+        let response = Response {
+            content: "## Verification Status\n\nVERIFIED\n".to_string(),
+            ..Response::default()
+        };
+        assert!(report.clone().with_response(&response).passed());
+        let unparsable = Response {
+            content: "Looks good to me".to_string(),
+            ..Response::default()
+        };
+        assert!(!report.clone().with_response(&unparsable).passed());
+
+        let failing = VerificationReport::new(vec![result("test", false)]).with_response(&response);
+        assert!(!failing.passed());
+        assert_eq!(failing.failures().count(), 1);
+        assert_eq!(failing.commands_metadata()[0]["exitCode"], 1);
+        assert_eq!(
+            failing.failure_context(),
+            "### `cargo test` (exit code 1)\n\n```text\n\n```\n\n"
+        );
+
+        let needs_work = Response {
+            content: "# Report\n\n## Issues Found\n\n- **Critical**: Login panics\n\n\
+                      ## Verification Status\n\nNEEDS WORK\n"
+                .to_string(),
+            ..Response::default()
+        };
+        let context = report.with_response(&needs_work).failure_context();
+        assert_eq!(
+            context,
+            "### Verification report\n\n## Issues Found\n\n- **Critical**: Login panics\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_commands() {
+        let config = VerificationConfig {
+            commands: vec![
+                VerificationCommand::new("ok", "echo passing"),
+                VerificationCommand::new("fail", "echo broken >&2; exit 3"),
+                VerificationCommand::new("slow", "sleep 5").with_timeout_secs(1),
+            ],
+            ..VerificationConfig::default()
+        };
+
+        let results = run_commands(std::env::temp_dir(), &config).await;
+        assert!(results[0].success);
+        assert_eq!(results[0].output, "passing\n");
+        assert!(!results[1].success);
+        assert_eq!(results[1].exit_code, Some(3));
+        assert_eq!(results[1].output, "broken\n");
+        assert!(results[2].timed_out);
+    }
+
+    #[test]
+    fn test_tail() {
+        assert_eq!(tail("short", 10), "short");
+        assert_eq!(tail("0123456789", 4), "[... truncated ...]\n6789");
+        assert_eq!(tail("aé", 1), "[... truncated ...]\n");
+    }
+}
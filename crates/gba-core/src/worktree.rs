@@ -0,0 +1,710 @@
+//! Git worktree management for GBA features.
+//!
+//! Each feature is developed in its own git worktree under the configured
+//! worktree directory, on a branch named with the configured prefix. This
+//! module shells out to the `git` CLI for all worktree operations.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, instrument, warn};
+
+use crate::config::{PrunePolicy, WorktreeConfig};
+use crate::git::GitError;
+
+/// Result type alias for worktree operations.
+pub type Result<T> = std::result::Result<T, WorktreeError>;
+
+/// Error types for worktree operations.
+#[derive(Debug, Error)]
+pub enum WorktreeError {
+    /// Feature name cannot be used as a worktree or branch name.
+    #[error("Invalid feature name: '{0}'")]
+    InvalidFeature(String),
+
+    /// A worktree already exists for the feature.
+    #[error("Worktree already exists: {0}")]
+    AlreadyExists(PathBuf),
+
+    /// No worktree exists for the feature.
+    #[error("Worktree not found for feature '{0}'")]
+    NotFound(String),
+
+    /// A git command failed.
+    #[error("git {command} failed: {stderr}")]
+    Git {
+        /// The git subcommand that failed.
+        command: String,
+        /// Standard error output from git.
+        stderr: String,
+    },
+
+    /// IO error, e.g. git is not installed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A git worktree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Worktree {
+    /// Absolute path of the worktree.
+    pub path: PathBuf,
+
+    /// Branch checked out in the worktree, without the `refs/heads/` prefix.
+    pub branch: Option<String>,
+
+    /// Commit checked out in the worktree.
+    pub head: String,
+}
+
+/// Why a worktree is considered stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StaleReason {
+    /// The branch has been merged into the base.
+    Merged,
+    /// The worktree has had no activity for the given number of days.
+    Abandoned {
+        /// Days since the last activity.
+        idle_days: u64,
+    },
+}
+
+impl std::fmt::Display for StaleReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Merged => write!(f, "merged"),
+            Self::Abandoned { idle_days } => write!(f, "abandoned ({idle_days} days idle)"),
+        }
+    }
+}
+
+/// A feature worktree selected for cleanup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleWorktree {
+    /// Feature name (the worktree directory name).
+    pub feature: String,
+    /// The worktree.
+    pub worktree: Worktree,
+    /// Why the worktree is stale.
+    pub reason: StaleReason,
+}
+
+/// Manager for feature worktrees of a repository.
+#[derive(Debug, Clone)]
+pub struct WorktreeManager {
+    /// Path of the main repository.
+    repo_path: PathBuf,
+    /// Worktree configuration.
+    config: WorktreeConfig,
+    /// Base ref for new feature branches (defaults to `HEAD`).
+    base: Option<String>,
+}
+
+impl WorktreeManager {
+    /// Create a new worktree manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_path` - Path of the main repository.
+    /// * `config` - Worktree configuration.
+    #[must_use]
+    pub fn new(repo_path: impl Into<PathBuf>, config: WorktreeConfig) -> Self {
+        Self {
+            repo_path: repo_path.into(),
+            config,
+            base: None,
+        }
+    }
+
+    /// Set the base ref that new feature branches are created from.
+    #[must_use]
+    pub fn with_base(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Get the directory that holds feature worktrees.
+    #[must_use]
+    pub fn worktrees_dir(&self) -> PathBuf {
+        self.repo_path.join(&self.config.directory)
+    }
+
+    /// Get the worktree path for a feature.
+    #[must_use]
+    pub fn worktree_path(&self, feature: &str) -> PathBuf {
+        self.worktrees_dir().join(feature)
+    }
+
+    /// Get the branch name for a feature.
+    #[must_use]
+    pub fn branch_name(&self, feature: &str) -> String {
+        format!("{}{}", self.config.branch_prefix, feature)
+    }
+
+    /// Create a worktree and branch for a feature.
+    ///
+    /// Reuses the feature branch if it already exists, otherwise creates it
+    /// from the base ref.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name, used for the worktree directory and branch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature name is invalid, the worktree already
+    /// exists, or git fails.
+    pub fn create(&self, feature: &str) -> Result<Worktree> {
+        self.create_sparse(feature, &[])
+    }
+
+    /// Create a worktree for a feature that checks out only some directories.
+    ///
+    /// Files are only written once the sparse checkout is configured, so
+    /// creating a worktree of a large repository stays fast. Top-level files
+    /// are always checked out.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name, used for the worktree directory and branch.
+    /// * `directories` - Directories to check out; empty checks out everything.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature name is invalid, the worktree already
+    /// exists, or git fails.
+    #[instrument(skip(self))]
+    pub fn create_sparse(&self, feature: &str, directories: &[String]) -> Result<Worktree> {
+        validate_feature(feature)?;
+
+        let path = self.worktree_path(feature);
+        if path.exists() {
+            return Err(WorktreeError::AlreadyExists(path));
+        }
+
+        let branch = self.branch_name(feature);
+        let path_arg = path.to_string_lossy().into_owned();
+        let mut args = vec!["worktree", "add"];
+        if !directories.is_empty() {
+            args.push("--no-checkout");
+        }
+        if self.branch_exists(&branch)? {
+            debug!("Checking out existing branch {} into {}", branch, path_arg);
+            args.extend([path_arg.as_str(), branch.as_str()]);
+        } else {
+            let base = self.base.as_deref().unwrap_or("HEAD");
+            debug!("Creating branch {} from {} in {}", branch, base, path_arg);
+            args.extend(["-b", branch.as_str(), path_arg.as_str(), base]);
+        }
+        self.git(&args)?;
+
+        let worktree = self
+            .find(feature)?
+            .ok_or_else(|| WorktreeError::NotFound(feature.to_string()))?;
+        if !directories.is_empty() {
+            self.set_sparse_checkout(&worktree, directories)?;
+            self.git_in(&worktree.path, &["read-tree", "-mu", "HEAD"])?;
+        }
+        Ok(worktree)
+    }
+
+    /// Get the worktree for a feature, creating it if it doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name, used for the worktree directory and branch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature name is invalid or git fails.
+    pub fn ensure(&self, feature: &str) -> Result<Worktree> {
+        self.ensure_sparse(feature, &[])
+    }
+
+    /// Get the worktree for a feature, creating it if it doesn't exist, and
+    /// restrict it to some directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name, used for the worktree directory and branch.
+    /// * `directories` - Directories to check out; empty leaves an existing
+    ///   worktree as it is and checks out everything in a new one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature name is invalid or git fails.
+    pub fn ensure_sparse(&self, feature: &str, directories: &[String]) -> Result<Worktree> {
+        validate_feature(feature)?;
+
+        match self.find(feature)? {
+            Some(worktree) => {
+                if !directories.is_empty() {
+                    self.set_sparse_checkout(&worktree, directories)?;
+                }
+                Ok(worktree)
+            }
+            None => self.create_sparse(feature, directories),
+        }
+    }
+
+    /// Restrict a worktree to some directories using a cone-mode sparse
+    /// checkout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails, e.g. when the worktree has uncommitted
+    /// changes outside the directories.
+    pub fn set_sparse_checkout(&self, worktree: &Worktree, directories: &[String]) -> Result<()> {
+        let mut args = vec!["sparse-checkout", "set", "--cone"];
+        args.extend(directories.iter().map(String::as_str));
+        self.git_in(&worktree.path, &args)?;
+        debug!(
+            "Sparse checkout of {} in {}",
+            directories.join(", "),
+            worktree.path.display()
+        );
+        Ok(())
+    }
+
+    /// Convert paths to the directories a cone-mode sparse checkout needs.
+    ///
+    /// Paths of existing directories in the repository are kept; any other
+    /// path, such as a file that may not exist yet, is replaced by its parent
+    /// directory. Top-level files need no directory and are dropped.
+    #[must_use]
+    pub fn sparse_directories(&self, paths: &[String]) -> Vec<String> {
+        let mut directories = paths
+            .iter()
+            .filter_map(|path| {
+                let path = path.trim_start_matches("./").trim_end_matches('/');
+                if path.is_empty() || self.repo_path.join(path).is_dir() {
+                    return Some(path.to_string());
+                }
+                Path::new(path)
+                    .parent()
+                    .map(|parent| parent.to_string_lossy().into_owned())
+            })
+            .filter(|dir| !dir.is_empty())
+            .collect::<Vec<_>>();
+        directories.sort();
+        directories.dedup();
+        directories
+    }
+
+    /// List feature worktrees located in the worktree directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails.
+    #[instrument(skip(self))]
+    pub fn list(&self) -> Result<Vec<Worktree>> {
+        let output = self.git(&["worktree", "list", "--porcelain"])?;
+        let dir = canonicalize_lossy(&self.worktrees_dir());
+
+        Ok(parse_porcelain(&output)
+            .into_iter()
+            .filter(|w| canonicalize_lossy(&w.path).starts_with(&dir))
+            .collect())
+    }
+
+    /// Find the worktree for a feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails.
+    pub fn find(&self, feature: &str) -> Result<Option<Worktree>> {
+        let path = canonicalize_lossy(&self.worktree_path(feature));
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|w| canonicalize_lossy(&w.path) == path))
+    }
+
+    /// Remove the worktree for a feature.
+    ///
+    /// The feature branch is kept so that work is never lost.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no worktree exists for the feature, it has
+    /// uncommitted changes, or git fails.
+    #[instrument(skip(self))]
+    pub fn remove(&self, feature: &str) -> Result<()> {
+        validate_feature(feature)?;
+
+        let worktree = self
+            .find(feature)?
+            .ok_or_else(|| WorktreeError::NotFound(feature.to_string()))?;
+        self.git(&["worktree", "remove", &worktree.path.to_string_lossy()])?;
+        debug!("Removed worktree {}", worktree.path.display());
+        Ok(())
+    }
+
+    /// Prune administrative data for worktrees whose directories are gone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails.
+    #[instrument(skip(self))]
+    pub fn prune(&self) -> Result<()> {
+        self.git(&["worktree", "prune"])?;
+        Ok(())
+    }
+
+    /// Find feature worktrees that are stale according to a prune policy.
+    ///
+    /// A branch counts as merged when it has commits of its own and its tip
+    /// is an ancestor of the base, so branches without any work yet are never
+    /// selected. A
+    /// worktree counts as abandoned when neither its branch tip nor its
+    /// directory changed within the configured number of days.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails.
+    #[instrument(skip(self))]
+    pub fn find_stale(&self, policy: &PrunePolicy) -> Result<Vec<StaleWorktree>> {
+        let base = self.base.as_deref().unwrap_or("HEAD");
+        let base_sha = self.git(&["rev-parse", base])?.trim().to_string();
+        let now = SystemTime::now();
+
+        let mut stale = Vec::new();
+        for worktree in self.list()? {
+            let Some(feature) = worktree
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+
+            let merged = policy.merged
+                && self.has_own_commits(&worktree, &base_sha)
+                && self.is_ancestor(&worktree.head, base)?;
+
+            let reason = if merged {
+                Some(StaleReason::Merged)
+            } else if policy.abandoned_after_days > 0 {
+                let idle_days = now
+                    .duration_since(self.last_activity(&worktree)?)
+                    .unwrap_or_default()
+                    .as_secs()
+                    / 86_400;
+                (idle_days >= u64::from(policy.abandoned_after_days))
+                    .then_some(StaleReason::Abandoned { idle_days })
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                stale.push(StaleWorktree {
+                    feature,
+                    worktree,
+                    reason,
+                });
+            }
+        }
+
+        Ok(stale)
+    }
+
+    /// Remove stale feature worktrees according to a prune policy.
+    ///
+    /// Worktrees with uncommitted changes are skipped with a warning rather
+    /// than failing the whole cleanup. Local branches are deleted when the
+    /// policy asks for it: merged branches with `git branch -d`, which keeps
+    /// a branch git doesn't consider merged, and the unmerged branches of
+    /// abandoned worktrees only with [`PrunePolicy::delete_unmerged`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git fails while detecting stale worktrees.
+    #[instrument(skip(self))]
+    pub fn cleanup(&self, policy: &PrunePolicy) -> Result<Vec<StaleWorktree>> {
+        let mut removed = Vec::new();
+
+        for stale in self.find_stale(policy)? {
+            let path = stale.worktree.path.to_string_lossy().into_owned();
+            if let Err(e) = self.git(&["worktree", "remove", &path]) {
+                warn!("Skipping worktree {}: {}", path, e);
+                continue;
+            }
+
+            if policy.delete_branches
+                && let Some(branch) = &stale.worktree.branch
+            {
+                let flag = match stale.reason {
+                    StaleReason::Merged => Some("-d"),
+                    StaleReason::Abandoned { .. } => policy.delete_unmerged.then_some("-D"),
+                };
+                match flag {
+                    Some(flag) => {
+                        if let Err(e) = self.git(&["branch", flag, branch]) {
+                            warn!("Failed to delete branch {}: {}", branch, e);
+                        }
+                    }
+                    None => debug!("Keeping unmerged branch {}", branch),
+                }
+            }
+
+            debug!("Removed {} worktree {}", stale.reason, path);
+            removed.push(stale);
+        }
+
+        self.prune()?;
+        Ok(removed)
+    }
+
+    /// Check whether a worktree's branch moved past the commit it was created at.
+    ///
+    /// The creation commit comes from the branch reflog; without a reflog the
+    /// branch is compared against the base instead.
+    fn has_own_commits(&self, worktree: &Worktree, base_sha: &str) -> bool {
+        let created = worktree.branch.as_ref().and_then(|branch| {
+            let reflog = self
+                .git(&[
+                    "reflog",
+                    "show",
+                    "--format=%H",
+                    &format!("refs/heads/{branch}"),
+                ])
+                .ok()?;
+            reflog.lines().last().map(str::to_string)
+        });
+
+        worktree.head != created.as_deref().unwrap_or(base_sha)
+    }
+
+    /// Check whether a commit is an ancestor of another.
+    fn is_ancestor(&self, commit: &str, of: &str) -> Result<bool> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo_path)
+            .args(["merge-base", "--is-ancestor", commit, of])
+            .status()?;
+        Ok(status.success())
+    }
+
+    /// Get the time of the last activity in a worktree.
+    ///
+    /// This is the later of the branch tip's commit time and the worktree
+    /// directory's modification time.
+    fn last_activity(&self, worktree: &Worktree) -> Result<SystemTime> {
+        let committed = self
+            .git(&["log", "-1", "--format=%ct", &worktree.head])?
+            .trim()
+            .parse::<u64>()
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap_or(UNIX_EPOCH);
+        let modified = std::fs::metadata(&worktree.path)
+            .and_then(|m| m.modified())
+            .unwrap_or(UNIX_EPOCH);
+        Ok(committed.max(modified))
+    }
+
+    /// Install the configured pre-commit hook in a feature worktree.
+    ///
+    /// The hook is scoped to the worktree through a per-worktree
+    /// `core.hooksPath`, so commits in the main repository are unaffected.
+    /// After the configured commands pass, it runs the repository's own
+    /// pre-commit hook, if any. Reinstalling overwrites the previous hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `worktree` - Worktree to install the hook in.
+    ///
+    /// # Returns
+    ///
+    /// The path of the installed hook, or `None` if the hook is disabled or
+    /// has no commands.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hook cannot be written or git fails.
+    #[instrument(skip(self, worktree), fields(worktree = %worktree.path.display()))]
+    pub fn install_pre_commit_hook(&self, worktree: &Worktree) -> Result<Option<PathBuf>> {
+        let config = &self.config.pre_commit;
+        if !config.enabled || config.commands.is_empty() {
+            return Ok(None);
+        }
+
+        let git_dir = self.git_in(&worktree.path, &["rev-parse", "--absolute-git-dir"])?;
+        let hooks_dir = PathBuf::from(git_dir.trim()).join("hooks");
+        std::fs::create_dir_all(&hooks_dir)?;
+
+        let hook = hooks_dir.join("pre-commit");
+        crate::atomic::write(&hook, pre_commit_script(&config.commands))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        self.git(&["config", "extensions.worktreeConfig", "true"])?;
+        self.git_in(
+            &worktree.path,
+            &[
+                "config",
+                "--worktree",
+                "core.hooksPath",
+                &hooks_dir.to_string_lossy(),
+            ],
+        )?;
+
+        debug!("Installed pre-commit hook {}", hook.display());
+        Ok(Some(hook))
+    }
+
+    /// Check whether a local branch exists.
+    fn branch_exists(&self, branch: &str) -> Result<bool> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo_path)
+            .args(["show-ref", "--verify", "--quiet"])
+            .arg(format!("refs/heads/{branch}"))
+            .status()?;
+        Ok(status.success())
+    }
+
+    /// Run a git command in the repository and return its stdout.
+    fn git(&self, args: &[&str]) -> Result<String> {
+        self.git_in(&self.repo_path, args)
+    }
+
+    /// Run a git command in a directory and return its stdout.
+    fn git_in(&self, dir: &Path, args: &[&str]) -> Result<String> {
+        crate::git::git(dir, args).map_err(|e| match e {
+            GitError::Git { stderr, .. } => WorktreeError::Git {
+                command: args.iter().take(2).copied().collect::<Vec<_>>().join(" "),
+                stderr,
+            },
+            GitError::Io(e) => WorktreeError::Io(e),
+        })
+    }
+}
+
+/// Header of the pre-commit hook script.
+const PRE_COMMIT_HEADER: &str = r"#!/bin/sh
+# Installed by gba for this feature worktree; regenerated on each run.
+set -e
+";
+
+/// Footer of the pre-commit hook script, chaining to the repository's hook.
+const PRE_COMMIT_FOOTER: &str = r#"
+# Chain to the repository's own pre-commit hook
+hook="$(git rev-parse --git-common-dir)/hooks/pre-commit"
+if [ -x "$hook" ]; then
+    exec "$hook" "$@"
+fi
+"#;
+
+/// Render the pre-commit hook script for a list of commands.
+fn pre_commit_script(commands: &[String]) -> String {
+    let mut script = format!("{PRE_COMMIT_HEADER}\n");
+    for command in commands {
+        script.push_str(command);
+        script.push('\n');
+    }
+    script.push_str(PRE_COMMIT_FOOTER);
+    script
+}
+
+/// Check that a feature name is usable as a directory and branch name.
+fn validate_feature(feature: &str) -> Result<()> {
+    let valid = !feature.is_empty()
+        && !feature.starts_with(['.', '-'])
+        && feature
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(WorktreeError::InvalidFeature(feature.to_string()))
+    }
+}
+
+/// Canonicalize a path, falling back to the path itself if it doesn't exist.
+fn canonicalize_lossy(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Parse the output of `git worktree list --porcelain`.
+fn parse_porcelain(output: &str) -> Vec<Worktree> {
+    let mut worktrees = Vec::new();
+    let mut current: Option<Worktree> = None;
+
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            worktrees.extend(current.take());
+            current = Some(Worktree {
+                path: PathBuf::from(path),
+                branch: None,
+                head: String::new(),
+            });
+        } else if let Some(worktree) = current.as_mut() {
+            if let Some(head) = line.strip_prefix("HEAD ") {
+                worktree.head = head.to_string();
+            } else if let Some(branch) = line.strip_prefix("branch ") {
+                let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+                worktree.branch = Some(branch.to_string());
+            }
+        }
+    }
+
+    worktrees.extend(current);
+    worktrees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = "worktree /repo\nHEAD abc123\nbranch refs/heads/main\n\n\
+                      worktree /repo/.trees/add-auth\nHEAD def456\nbranch refs/heads/gba/add-auth\n\n\
+                      worktree /repo/.trees/detached\nHEAD 789abc\ndetached\n";
+        let worktrees = parse_porcelain(output);
+
+        assert_eq!(worktrees.len(), 3);
+        assert_eq!(worktrees[0].branch.as_deref(), Some("main"));
+        assert_eq!(worktrees[1].path, PathBuf::from("/repo/.trees/add-auth"));
+        assert_eq!(worktrees[1].branch.as_deref(), Some("gba/add-auth"));
+        assert_eq!(worktrees[1].head, "def456");
+        assert!(worktrees[2].branch.is_none());
+    }
+
+    #[test]
+    fn test_pre_commit_script() {
+        let script = pre_commit_script(&["cargo fmt --check".to_string()]);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("set -e\n\ncargo fmt --check\n"));
+        assert!(script.contains("exec \"$hook\" \"$@\""));
+    }
+
+    #[test]
+    fn test_validate_feature() {
+        assert!(validate_feature("add-auth").is_ok());
+        assert!(validate_feature("0001-add_auth.v2").is_ok());
+        assert!(validate_feature("").is_err());
+        assert!(validate_feature("../escape").is_err());
+        assert!(validate_feature("-rf").is_err());
+        assert!(validate_feature("a b").is_err());
+    }
+
+    #[test]
+    fn test_branch_and_path_names() {
+        let manager = WorktreeManager::new("/repo", WorktreeConfig::default());
+        assert_eq!(manager.branch_name("add-auth"), "gba/add-auth");
+        assert_eq!(
+            manager.worktree_path("add-auth"),
+            PathBuf::from("/repo/./.trees/add-auth")
+        );
+    }
+}
@@ -106,6 +106,7 @@ fn test_should_integration_response_with_usage() {
             output_tokens: 50,
             total_cost_usd: 0.01,
         },
+        ..Default::default()
     };
 
     assert_eq!(response.content, "Test response");
@@ -165,3 +166,577 @@ fn test_should_integration_context_builder_config_chainable() {
     assert_eq!(config.exclude_patterns.len(), 2);
     assert_eq!(config.include_extensions.len(), 2);
 }
+
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .status()
+        .expect("Failed to run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn test_should_integration_worktree_lifecycle() {
+    use gba_core::config::WorktreeConfig;
+    use gba_core::worktree::{WorktreeError, WorktreeManager};
+
+    let repo = std::env::temp_dir().join("gba-test-worktree-lifecycle");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    git(
+        &repo,
+        &[
+            "-c",
+            "user.name=gba",
+            "-c",
+            "user.email=gba@example.com",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ],
+    );
+
+    let manager = WorktreeManager::new(&repo, WorktreeConfig::default()).with_base("main");
+    assert!(manager.list().unwrap().is_empty());
+
+    let worktree = manager
+        .create("add-auth")
+        .expect("Failed to create worktree");
+    assert_eq!(worktree.branch.as_deref(), Some("gba/add-auth"));
+    assert!(manager.worktree_path("add-auth").is_dir());
+    assert_eq!(manager.list().unwrap().len(), 1);
+
+    assert!(matches!(
+        manager.create("add-auth"),
+        Err(WorktreeError::AlreadyExists(_))
+    ));
+
+    manager
+        .remove("add-auth")
+        .expect("Failed to remove worktree");
+    assert!(manager.list().unwrap().is_empty());
+    assert!(matches!(
+        manager.remove("add-auth"),
+        Err(WorktreeError::NotFound(_))
+    ));
+
+    // Re-creating reuses the existing branch
+    let worktree = manager
+        .create("add-auth")
+        .expect("Failed to recreate worktree");
+    assert_eq!(worktree.branch.as_deref(), Some("gba/add-auth"));
+
+    std::fs::remove_dir_all(manager.worktree_path("add-auth")).unwrap();
+    manager.prune().expect("Failed to prune worktrees");
+    assert!(manager.list().unwrap().is_empty());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_integration_worktree_cleanup_merged() {
+    use gba_core::config::{PrunePolicy, WorktreeConfig};
+    use gba_core::worktree::{StaleReason, WorktreeManager};
+
+    let commit = |dir: &std::path::Path, message: &str| {
+        git(
+            dir,
+            &[
+                "-c",
+                "user.name=gba",
+                "-c",
+                "user.email=gba@example.com",
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                message,
+            ],
+        );
+    };
+
+    let repo = std::env::temp_dir().join("gba-test-worktree-cleanup");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    commit(&repo, "init");
+
+    let manager = WorktreeManager::new(&repo, WorktreeConfig::default()).with_base("main");
+    let merged = manager.create("merged").unwrap();
+    manager.create("fresh").unwrap();
+
+    commit(&merged.path, "feature work");
+    git(&repo, &["merge", "-q", "--ff-only", "gba/merged"]);
+
+    let policy = PrunePolicy::default();
+    let stale = manager.find_stale(&policy).unwrap();
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].feature, "merged");
+    assert_eq!(stale[0].reason, StaleReason::Merged);
+
+    let removed = manager.cleanup(&policy).unwrap();
+    assert_eq!(removed.len(), 1);
+    let remaining = manager.list().unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].branch.as_deref(), Some("gba/fresh"));
+
+    let branches = std::process::Command::new("git")
+        .arg("-C")
+        .arg(&repo)
+        .args(["branch", "--list", "gba/merged"])
+        .output()
+        .unwrap();
+    assert!(branches.stdout.is_empty());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_keep_unmerged_branch_of_abandoned_worktree() {
+    use gba_core::config::{PrunePolicy, WorktreeConfig};
+    use gba_core::worktree::{StaleReason, WorktreeManager};
+
+    let repo = std::env::temp_dir().join("gba-test-worktree-abandoned");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    let commit = |dir: &std::path::Path, message: &str| {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=gba", "-c", "user.email=gba@example.com"])
+            .args(["commit", "-q", "--allow-empty", "-m", message])
+            .env("GIT_COMMITTER_DATE", "2001-01-01T00:00:00Z")
+            .env("GIT_AUTHOR_DATE", "2001-01-01T00:00:00Z")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    commit(&repo, "init");
+
+    let manager = WorktreeManager::new(&repo, WorktreeConfig::default()).with_base("main");
+    let abandoned = manager.create("abandoned").unwrap();
+    commit(&abandoned.path, "unmerged work");
+    std::fs::File::open(&abandoned.path)
+        .unwrap()
+        .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(978_307_200))
+        .unwrap();
+
+    let branch_exists = || {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["branch", "--list", "gba/abandoned"])
+            .output()
+            .unwrap();
+        !output.stdout.is_empty()
+    };
+
+    let policy = PrunePolicy::default();
+    let removed = manager.cleanup(&policy).unwrap();
+    assert_eq!(removed.len(), 1);
+    assert!(matches!(removed[0].reason, StaleReason::Abandoned { .. }));
+    assert!(manager.list().unwrap().is_empty());
+    assert!(branch_exists());
+
+    // Recreating the worktree checks out the kept branch again
+    let abandoned = manager.create("abandoned").unwrap();
+    std::fs::File::open(&abandoned.path)
+        .unwrap()
+        .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(978_307_200))
+        .unwrap();
+    let policy = PrunePolicy {
+        delete_unmerged: true,
+        ..PrunePolicy::default()
+    };
+    assert_eq!(manager.cleanup(&policy).unwrap().len(), 1);
+    assert!(!branch_exists());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_integration_diff_worktree_and_refs() {
+    use gba_core::diff::{self, DiffOptions};
+
+    let repo = std::env::temp_dir().join("gba-test-diff");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    let commit = |message: &str| {
+        git(&repo, &["add", "-A"]);
+        git(
+            &repo,
+            &[
+                "-c",
+                "user.name=gba",
+                "-c",
+                "user.email=gba@example.com",
+                "commit",
+                "-q",
+                "-m",
+                message,
+            ],
+        );
+    };
+
+    git(&repo, &["init", "-q", "-b", "main"]);
+    std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+    commit("init");
+
+    git(&repo, &["checkout", "-q", "-b", "feature"]);
+    std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+    std::fs::write(repo.join("Cargo.lock"), "lock\n").unwrap();
+    commit("feature");
+    std::fs::write(repo.join("notes.txt"), "tracked later\n").unwrap();
+    std::fs::write(repo.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0, 1]).unwrap();
+    git(&repo, &["add", "notes.txt", "logo.png"]);
+
+    let options = DiffOptions::new().with_exclude(vec!["Cargo.lock".to_string()]);
+    let refs = diff::diff_refs(&repo, "main", "feature", &options).unwrap();
+    assert_eq!(refs.files, vec![PathBuf::from("README.md")]);
+    assert!(refs.content.contains("+world"));
+    assert!(!refs.truncated);
+
+    let worktree = diff::diff_worktree(&repo, "main", &DiffOptions::new()).unwrap();
+    assert_eq!(worktree.files.len(), 4);
+    assert!(worktree.content.contains("+tracked later"));
+    assert_eq!(worktree.binary_files, vec![PathBuf::from("logo.png")]);
+    assert!(!worktree.content.contains("Binary files"));
+    assert!(
+        worktree
+            .content
+            .ends_with("... binary file omitted: logo.png\n")
+    );
+
+    let binary = diff::diff_worktree(&repo, "main", &DiffOptions::new().with_binary(true)).unwrap();
+    assert!(binary.content.contains("Binary files"));
+
+    let truncated =
+        diff::diff_worktree(&repo, "main", &DiffOptions::new().with_max_bytes(64)).unwrap();
+    assert!(truncated.truncated);
+    assert!(truncated.content.len() < worktree.content.len());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_integration_git_rebase_with_conflicts() {
+    use gba_core::git::{self, Integration, IntegrationOutcome};
+
+    let repo = std::env::temp_dir().join("gba-test-git-rebase");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    git(&repo, &["config", "user.name", "gba"]);
+    git(&repo, &["config", "user.email", "gba@example.com"]);
+    let commit = |message: &str| {
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", message]);
+    };
+
+    std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+    commit("init");
+    git(&repo, &["checkout", "-q", "-b", "feature"]);
+    std::fs::write(repo.join("README.md"), "hello feature\n").unwrap();
+    commit("feature");
+
+    assert_eq!(
+        git::integrate(&repo, "main", Integration::Rebase).unwrap(),
+        IntegrationOutcome::UpToDate
+    );
+
+    git(&repo, &["checkout", "-q", "main"]);
+    std::fs::write(repo.join("README.md"), "hello main\n").unwrap();
+    commit("main");
+    git(&repo, &["checkout", "-q", "feature"]);
+    assert_eq!(git::ahead_behind(&repo, "main", "HEAD").unwrap(), (1, 1));
+
+    let outcome = git::integrate(&repo, "main", Integration::Rebase).unwrap();
+    assert_eq!(
+        outcome,
+        IntegrationOutcome::Conflicts(vec![PathBuf::from("README.md")])
+    );
+    assert!(matches!(
+        git::continue_integration(&repo).unwrap(),
+        IntegrationOutcome::Conflicts(_)
+    ));
+
+    std::fs::write(repo.join("README.md"), "hello main and feature\n").unwrap();
+    assert_eq!(
+        git::continue_integration(&repo).unwrap(),
+        IntegrationOutcome::Completed
+    );
+    assert_eq!(git::ahead_behind(&repo, "main", "HEAD").unwrap(), (1, 0));
+    assert!(git::status(&repo).unwrap().is_empty());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_integration_git_push() {
+    use gba_core::git;
+
+    let root = std::env::temp_dir().join("gba-test-git-push");
+    std::fs::remove_dir_all(&root).ok();
+    let (repo, remote) = (root.join("repo"), root.join("remote.git"));
+    std::fs::create_dir_all(&repo).unwrap();
+    std::fs::create_dir_all(&remote).unwrap();
+    git(&remote, &["init", "-q", "--bare"]);
+    git(&repo, &["init", "-q", "-b", "gba/add-auth"]);
+    git(&repo, &["config", "user.name", "gba"]);
+    git(&repo, &["config", "user.email", "gba@example.com"]);
+    git(
+        &repo,
+        &["remote", "add", "origin", remote.to_str().unwrap()],
+    );
+    std::fs::write(repo.join("auth.rs"), "fn login() {}\n").unwrap();
+    git::commit_all(&repo, "gba: implement add-auth", &[]).unwrap();
+
+    git::push(&repo, "origin", "gba/add-auth").unwrap();
+    assert_eq!(
+        git::ahead_behind(&repo, "origin/gba/add-auth", "HEAD").unwrap(),
+        (0, 0)
+    );
+    assert!(git::push(&repo, "upstream", "gba/add-auth").is_err());
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn test_should_integration_git_repo_info() {
+    use gba_core::git::{self, RepoInfo};
+
+    let repo = std::env::temp_dir().join("gba-test-git-repo-info");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+
+    // A repository without commits or remotes
+    assert_eq!(
+        git::repo_info(&repo).unwrap(),
+        RepoInfo {
+            remote_url: None,
+            branch: Some("main".to_string()),
+            head: None,
+        }
+    );
+
+    git(
+        &repo,
+        &["remote", "add", "origin", "https://example.com/gba.git"],
+    );
+    git(
+        &repo,
+        &[
+            "-c",
+            "user.name=gba",
+            "-c",
+            "user.email=gba@example.com",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ],
+    );
+    std::fs::write(repo.join("notes.txt"), "untracked\n").unwrap();
+
+    let info = git::repo_info(&repo).unwrap();
+    assert_eq!(
+        info.remote_url.as_deref(),
+        Some("https://example.com/gba.git")
+    );
+    assert_eq!(info.head.as_ref().map(String::len), Some(40));
+    let status = git::status(&repo).unwrap();
+    assert_eq!(status.len(), 1);
+    assert!(status[0].is_untracked());
+
+    git(&repo, &["checkout", "-q", "--detach"]);
+    assert_eq!(git::current_branch(&repo).unwrap(), None);
+    assert_eq!(git::head_sha(&repo).unwrap(), info.head);
+
+    assert!(git::repo_info(&std::env::temp_dir()).is_err());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_integration_worktree_pre_commit_hook() {
+    use gba_core::config::{PreCommitConfig, WorktreeConfig};
+    use gba_core::worktree::WorktreeManager;
+
+    let commit = |dir: &std::path::Path| {
+        std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=gba", "-c", "user.email=gba@example.com"])
+            .args(["commit", "-q", "--allow-empty", "-m", "change"])
+            .status()
+            .unwrap()
+            .success()
+    };
+
+    let repo = std::env::temp_dir().join("gba-test-worktree-hook");
+    std::fs::remove_dir_all(&repo).ok();
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    assert!(commit(&repo));
+
+    let disabled = WorktreeManager::new(&repo, WorktreeConfig::default());
+    let worktree = disabled.create("hooked").unwrap();
+    assert!(
+        disabled
+            .install_pre_commit_hook(&worktree)
+            .unwrap()
+            .is_none()
+    );
+
+    let config = WorktreeConfig {
+        pre_commit: PreCommitConfig {
+            enabled: true,
+            commands: vec!["test -f formatted.txt".to_string()],
+        },
+        ..WorktreeConfig::default()
+    };
+    let manager = WorktreeManager::new(&repo, config);
+    let hook = manager.install_pre_commit_hook(&worktree).unwrap().unwrap();
+    assert!(hook.is_file());
+
+    // The hook rejects commits in the worktree until its command passes
+    assert!(!commit(&worktree.path));
+    std::fs::write(worktree.path.join("formatted.txt"), "ok\n").unwrap();
+    assert!(commit(&worktree.path));
+
+    // Commits in the main repository are unaffected
+    assert!(commit(&repo));
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[test]
+fn test_should_integration_worktree_sparse_checkout() {
+    use gba_core::config::WorktreeConfig;
+    use gba_core::worktree::WorktreeManager;
+
+    let repo = std::env::temp_dir().join("gba-test-worktree-sparse");
+    std::fs::remove_dir_all(&repo).ok();
+    for dir in ["crates/auth/src", "crates/billing", "docs"] {
+        std::fs::create_dir_all(repo.join(dir)).unwrap();
+    }
+    std::fs::write(repo.join("Cargo.toml"), "[workspace]\n").unwrap();
+    std::fs::write(repo.join("crates/auth/src/lib.rs"), "\n").unwrap();
+    std::fs::write(repo.join("crates/billing/lib.rs"), "\n").unwrap();
+    std::fs::write(repo.join("docs/index.md"), "\n").unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    git(&repo, &["add", "-A"]);
+    git(
+        &repo,
+        &[
+            "-c",
+            "user.name=gba",
+            "-c",
+            "user.email=gba@example.com",
+            "commit",
+            "-q",
+            "-m",
+            "init",
+        ],
+    );
+
+    let manager = WorktreeManager::new(&repo, WorktreeConfig::default());
+    let directories = manager.sparse_directories(&[
+        "crates/auth/src/login.rs".to_string(),
+        "crates/auth/src/".to_string(),
+        "Cargo.toml".to_string(),
+    ]);
+    assert_eq!(directories, vec!["crates/auth/src"]);
+
+    let worktree = manager.create_sparse("sparse", &directories).unwrap();
+    assert!(worktree.path.join("Cargo.toml").is_file());
+    assert!(worktree.path.join("crates/auth/src/lib.rs").is_file());
+    assert!(!worktree.path.join("crates/billing").exists());
+    assert!(!worktree.path.join("docs").exists());
+
+    // Widening an existing worktree checks out the new directories
+    manager
+        .ensure_sparse(
+            "sparse",
+            &["crates/auth/src".to_string(), "docs".to_string()],
+        )
+        .unwrap();
+    assert!(worktree.path.join("docs/index.md").is_file());
+    assert!(!worktree.path.join("crates/billing").exists());
+
+    // The main repository keeps its full checkout
+    assert!(repo.join("crates/billing/lib.rs").is_file());
+
+    std::fs::remove_dir_all(&repo).ok();
+}
+
+#[tokio::test]
+async fn test_should_integration_context_scoped_to_crates() {
+    let repo = std::env::temp_dir().join(format!("gba-test-crates-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&repo);
+    let write = |path: &str, content: &str| {
+        let path = repo.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    write(
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"crates/*\"]\nresolver = \"2\"\n",
+    );
+    for (name, dependency) in [("api", "core"), ("core", ""), ("cli", "api")] {
+        let dependencies = if dependency.is_empty() {
+            String::new()
+        } else {
+            format!("{dependency} = {{ path = \"../{dependency}\" }}\n")
+        };
+        write(
+            &format!("crates/{name}/Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\n{dependencies}"
+            ),
+        );
+        write(&format!("crates/{name}/src/lib.rs"), "pub fn run() {}\n");
+    }
+
+    let map = gba_core::cargo::CrateMap::load(&repo).unwrap().unwrap();
+    assert_eq!(map.get("api").unwrap().dependencies, vec!["core"]);
+    let touched = map.touched(&[PathBuf::from("crates/api/src/lib.rs")]);
+    assert_eq!(touched, vec!["api"]);
+
+    let config = ContextBuilderConfig::default().with_crates(touched);
+    let context = gba_core::context_builder::build_context(&repo, "main", &config)
+        .await
+        .unwrap();
+    let mut paths = context
+        .files
+        .iter()
+        .map(|file| file.path.to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            "Cargo.toml",
+            "crates/api/Cargo.toml",
+            "crates/api/src/lib.rs",
+            "crates/core/Cargo.toml",
+            "crates/core/src/lib.rs",
+        ]
+    );
+    assert_eq!(
+        context.metadata["crateScope"],
+        serde_json::json!(["api", "core"])
+    );
+    assert_eq!(context.metadata["crates"].as_array().unwrap().len(), 3);
+
+    std::fs::remove_dir_all(&repo).ok();
+}
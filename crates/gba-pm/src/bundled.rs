@@ -0,0 +1,179 @@
+//! Templates bundled with the crate.
+//!
+//! Every bundled template is a [`BundledTemplate`], with its name, its raw
+//! source including the front matter, and its parsed configuration, so
+//! embedders can list, export or compare them without going through a
+//! [`crate::TemplateEngine`].
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_pm::BundledTemplate;
+//!
+//! for template in BundledTemplate::iter() {
+//!     println!("{}: {} bytes", template.name(), template.source().len());
+//! }
+//!
+//! let plan = BundledTemplate::from_name("plan").unwrap();
+//! assert_eq!(plan.file_name(), "plan.jinja2");
+//! assert!(plan.config().unwrap().max_turns > 0);
+//! ```
+
+use std::fmt;
+
+use crate::config::{PromptTemplate, TemplateConfig};
+use crate::error::Result;
+
+/// A template bundled with the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BundledTemplate {
+    /// Initializes a GBA project.
+    Init,
+    /// Creates the implementation plan of a feature.
+    Plan,
+    /// Implements a feature from its plan.
+    Implement,
+    /// Verifies an implementation.
+    Verify,
+    /// Reviews the changes of a feature.
+    Review,
+    /// Reviews the changes of a feature for security issues.
+    ReviewSecurity,
+    /// Reviews the changes of a feature for performance issues.
+    ReviewPerformance,
+    /// Reviews the changes of a feature for style and maintainability.
+    ReviewStyle,
+    /// Resumes an interrupted task.
+    Resume,
+    /// Writes the `CLAUDE.md` / `AGENTS.md` guidance file.
+    AgentDocs,
+    /// Lays out every prompt sent to the agent, see [`crate::LAYOUT_TEMPLATE`].
+    Layout,
+}
+
+impl BundledTemplate {
+    /// Every bundled template, in a stable order.
+    pub const ALL: [Self; 11] = [
+        Self::Init,
+        Self::Plan,
+        Self::Implement,
+        Self::Verify,
+        Self::Review,
+        Self::ReviewSecurity,
+        Self::ReviewPerformance,
+        Self::ReviewStyle,
+        Self::Resume,
+        Self::AgentDocs,
+        Self::Layout,
+    ];
+
+    /// Iterate over every bundled template, in the order of [`Self::ALL`].
+    pub fn iter() -> impl Iterator<Item = Self> {
+        Self::ALL.into_iter()
+    }
+
+    /// Find a bundled template by name, with or without its `.jinja2`
+    /// extension, e.g. `"plan"` or `"plan.jinja2"`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.strip_suffix(".jinja2").unwrap_or(name);
+        Self::iter().find(|template| template.name() == name)
+    }
+
+    /// Get the name the template is registered under, e.g. `"plan"`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Init => "init",
+            Self::Plan => "plan",
+            Self::Implement => "implement",
+            Self::Verify => "verify",
+            Self::Review => "review",
+            Self::ReviewSecurity => "review-security",
+            Self::ReviewPerformance => "review-performance",
+            Self::ReviewStyle => "review-style",
+            Self::Resume => "resume",
+            Self::AgentDocs => "agent-docs",
+            Self::Layout => "layout",
+        }
+    }
+
+    /// Get the file name of the template, e.g. `"plan.jinja2"`, as a local
+    /// template overriding it is named.
+    #[must_use]
+    pub fn file_name(self) -> String {
+        format!("{}.jinja2", self.name())
+    }
+
+    /// Get the raw source of the template, including its front matter.
+    #[must_use]
+    pub const fn source(self) -> &'static str {
+        match self {
+            Self::Init => include_str!("../templates/init.jinja2"),
+            Self::Plan => include_str!("../templates/plan.jinja2"),
+            Self::Implement => include_str!("../templates/implement.jinja2"),
+            Self::Verify => include_str!("../templates/verify.jinja2"),
+            Self::Review => include_str!("../templates/review.jinja2"),
+            Self::ReviewSecurity => include_str!("../templates/review-security.jinja2"),
+            Self::ReviewPerformance => include_str!("../templates/review-performance.jinja2"),
+            Self::ReviewStyle => include_str!("../templates/review-style.jinja2"),
+            Self::Resume => include_str!("../templates/resume.jinja2"),
+            Self::AgentDocs => include_str!("../templates/agent-docs.jinja2"),
+            Self::Layout => include_str!("../templates/layout.jinja2"),
+        }
+    }
+
+    /// Parse the template into its configuration and body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the front matter doesn't parse, as with the
+    /// `resume` template, whose front matter is itself templated.
+    pub fn parse(self) -> Result<PromptTemplate> {
+        PromptTemplate::parse(self.source())
+    }
+
+    /// Get the configuration of the template's front matter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the front matter doesn't parse, see
+    /// [`Self::parse`].
+    pub fn config(self) -> Result<TemplateConfig> {
+        self.parse().map(|template| template.config)
+    }
+}
+
+impl fmt::Display for BundledTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_templates() {
+        for template in BundledTemplate::iter() {
+            assert_eq!(BundledTemplate::from_name(template.name()), Some(template));
+            assert_eq!(
+                BundledTemplate::from_name(&template.file_name()),
+                Some(template)
+            );
+            assert!(!template.source().is_empty());
+        }
+        assert_eq!(BundledTemplate::from_name("custom"), None);
+        assert_eq!(
+            BundledTemplate::ReviewSecurity.to_string(),
+            "review-security"
+        );
+
+        let plan = BundledTemplate::Plan.parse().unwrap();
+        assert!(!plan.template.starts_with("---"));
+        assert!(BundledTemplate::Plan.config().is_ok());
+        // Its front matter is rendered with the task's settings
+        assert!(BundledTemplate::Resume.config().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Render cache and timing of rendered templates.
+//!
+//! Batch runs render the same template with the same context many times.
+//! The prompt manager keeps each rendered prompt keyed by the template name
+//! and a hash of its context, and returns it again instead of re-rendering.
+//! Every render is timed, and [`RenderStats`] reports the renders, cache hits
+//! and time spent per template.
+//!
+//! Registering or reloading templates clears the cache.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Maximum number of rendered prompts kept; renders past it are not cached.
+pub const RENDER_CACHE_CAPACITY: usize = 256;
+
+/// Render statistics of a template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TemplateStats {
+    /// Number of times the template was rendered.
+    pub renders: u64,
+
+    /// Number of renders answered from the cache.
+    pub cache_hits: u64,
+
+    /// Time spent rendering, cache hits excluded.
+    pub render_time: Duration,
+}
+
+/// Render statistics of a prompt manager.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Statistics per template name.
+    pub templates: BTreeMap<String, TemplateStats>,
+}
+
+impl RenderStats {
+    /// Get the number of renders of all templates.
+    #[must_use]
+    pub fn renders(&self) -> u64 {
+        self.templates.values().map(|stats| stats.renders).sum()
+    }
+
+    /// Get the number of renders of all templates answered from the cache.
+    #[must_use]
+    pub fn cache_hits(&self) -> u64 {
+        self.templates.values().map(|stats| stats.cache_hits).sum()
+    }
+
+    /// Get the time spent rendering all templates.
+    #[must_use]
+    pub fn render_time(&self) -> Duration {
+        self.templates.values().map(|stats| stats.render_time).sum()
+    }
+}
+
+/// Rendered prompts keyed by template name and context hash.
+#[derive(Debug, Default)]
+pub(crate) struct RenderCache {
+    inner: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    prompts: HashMap<(String, u64), String>,
+    stats: RenderStats,
+}
+
+impl RenderCache {
+    /// Get a cached prompt, counting a render of the template.
+    pub(crate) fn get(&self, name: &str, key: u64) -> Option<String> {
+        let mut state = self.lock();
+        let prompt = state.prompts.get(&(name.to_string(), key)).cloned();
+        let stats = state.stats.templates.entry(name.to_string()).or_default();
+        stats.renders += 1;
+        if prompt.is_some() {
+            stats.cache_hits += 1;
+        }
+        prompt
+    }
+
+    /// Record a rendered prompt and the time it took.
+    ///
+    /// `key` is `None` when the context could not be hashed: the render is
+    /// timed but not cached.
+    pub(crate) fn insert(&self, name: &str, key: Option<u64>, prompt: &str, elapsed: Duration) {
+        let mut state = self.lock();
+        state
+            .stats
+            .templates
+            .entry(name.to_string())
+            .or_default()
+            .render_time += elapsed;
+        if let Some(key) = key
+            && state.prompts.len() < RENDER_CACHE_CAPACITY
+        {
+            state
+                .prompts
+                .insert((name.to_string(), key), prompt.to_string());
+        }
+    }
+
+    /// Count a render that can't be cached.
+    pub(crate) fn count(&self, name: &str) {
+        self.lock()
+            .stats
+            .templates
+            .entry(name.to_string())
+            .or_default()
+            .renders += 1;
+    }
+
+    /// Drop the cached prompts, keeping the statistics.
+    pub(crate) fn clear(&self) {
+        self.lock().prompts.clear();
+    }
+
+    /// Get a snapshot of the statistics.
+    pub(crate) fn stats(&self) -> RenderStats {
+        self.lock().stats.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        // The state stays consistent even if a holder panicked
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Hash a rendering context, if it serializes.
+///
+/// The context is hashed through its JSON value, whose object keys are
+/// sorted, so maps hash the same whatever their iteration order.
+pub(crate) fn context_hash(context: &impl Serialize) -> Option<u64> {
+    let value = serde_json::to_value(context).ok()?;
+    let mut hasher = DefaultHasher::new();
+    value.to_string().hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_context_hash_ignores_key_order() {
+        let mut first = HashMap::new();
+        first.insert("a", 1);
+        first.insert("b", 2);
+        let mut second = HashMap::new();
+        second.insert("b", 2);
+        second.insert("a", 1);
+        assert_eq!(context_hash(&first), context_hash(&second));
+        assert_ne!(
+            context_hash(&json!({"a": 1})),
+            context_hash(&json!({"a": 2}))
+        );
+    }
+
+    #[test]
+    fn test_render_cache_counts_hits() {
+        let cache = RenderCache::default();
+        assert_eq!(cache.get("plan", 1), None);
+        cache.insert("plan", Some(1), "Plan", Duration::from_millis(5));
+        assert_eq!(cache.get("plan", 1).as_deref(), Some("Plan"));
+        cache.count("verify");
+
+        let stats = cache.stats();
+        assert_eq!(
+            stats.templates["plan"],
+            TemplateStats {
+                renders: 2,
+                cache_hits: 1,
+                render_time: Duration::from_millis(5),
+            }
+        );
+        assert_eq!(stats.renders(), 3);
+        assert_eq!(stats.cache_hits(), 1);
+
+        cache.clear();
+        assert_eq!(cache.get("plan", 1), None);
+        assert_eq!(cache.stats().templates["plan"].renders, 3);
+    }
+}
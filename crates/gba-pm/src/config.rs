@@ -3,7 +3,9 @@
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
+use crate::constraints::OutputConstraint;
 use crate::error::{PromptError, Result};
+use crate::vars::DeclaredVariable;
 
 /// Template configuration extracted from front matter.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +26,50 @@ pub struct TemplateConfig {
     /// Maximum number of turns allowed.
     #[serde(default = "default_max_turns")]
     pub max_turns: u32,
+
+    /// Maximum output tokens of each response, overriding the agent's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// Permission mode of the agent, overriding the agent's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<PermissionMode>,
+
+    /// Variables the caller supplies, beyond the standard context, see
+    /// [`DeclaredVariable`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<DeclaredVariable>,
+
+    /// Requirements on the response, see [`crate::constraints`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<OutputConstraint>,
+}
+
+/// How the agent asks for permission to use tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    /// Ask for permission as the agent's settings say; tools needing it are
+    /// refused in unattended runs.
+    Default,
+    /// Accept file edits without asking.
+    AcceptEdits,
+    /// Plan only: read and analyze, never modify anything.
+    Plan,
+    /// Never ask: allowed tools run without confirmation.
+    #[default]
+    BypassPermissions,
+}
+
+impl std::fmt::Display for PermissionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::AcceptEdits => "acceptEdits",
+            Self::Plan => "plan",
+            Self::BypassPermissions => "bypassPermissions",
+        })
+    }
 }
 
 fn default_use_preset() -> bool {
@@ -41,6 +87,10 @@ impl Default for TemplateConfig {
             use_preset: true,
             tools: Vec::new(),
             max_turns: 100,
+            max_tokens: None,
+            permission_mode: None,
+            variables: Vec::new(),
+            constraints: Vec::new(),
         }
     }
 }
@@ -77,12 +127,38 @@ impl PromptTemplate {
 ///
 /// Returns an error if the front matter cannot be parsed.
 fn extract_front_matter(source: &str) -> Result<(TemplateConfig, String)> {
+    let Some(parts) = split_front_matter(source) else {
+        // No front matter, use default config and entire source as template
+        return Ok((TemplateConfig::default(), source.to_string()));
+    };
+
+    // Parse YAML
+    let config: TemplateConfig = serde_yaml::from_str(&parts.front_matter)
+        .map_err(|e| PromptError::Template(format!("Failed to parse front matter: {e}")))?;
+
+    Ok((config, parts.template))
+}
+
+/// A template source split at the end of its front matter.
+pub(crate) struct FrontMatterParts {
+    /// Front matter, without its delimiters.
+    pub front_matter: String,
+    /// Template after the front matter.
+    pub template: String,
+    /// Number of lines before the template, delimiters included.
+    pub template_offset: usize,
+}
+
+/// Split the front matter off a template source.
+///
+/// Returns `None` if the source doesn't start with a front matter delimited
+/// by `---` lines.
+pub(crate) fn split_front_matter(source: &str) -> Option<FrontMatterParts> {
     let lines: Vec<&str> = source.lines().collect();
 
     // Check for front matter delimiter at start
     if lines.first().is_none_or(|l| l.trim() != "---") {
-        // No front matter, use default config and entire source as template
-        return Ok((TemplateConfig::default(), source.to_string()));
+        return None;
     }
 
     // Find the end delimiter
@@ -90,24 +166,13 @@ fn extract_front_matter(source: &str) -> Result<(TemplateConfig, String)> {
         .iter()
         .skip(1)
         .position(|l| l.trim() == "---")
-        .map(|i| i + 1);
-
-    let Some(end_idx) = end_idx else {
-        // No end delimiter found, use default config and entire source as template
-        return Ok((TemplateConfig::default(), source.to_string()));
-    };
-
-    // Extract front matter (lines 1 to end_idx)
-    let front_matter = lines[1..end_idx].join("\n");
+        .map(|i| i + 1)?;
 
-    // Parse YAML
-    let config: TemplateConfig = serde_yaml::from_str(&front_matter)
-        .map_err(|e| PromptError::Template(format!("Failed to parse front matter: {e}")))?;
-
-    // Extract template (lines after end_idx)
-    let template = lines[end_idx + 1..].join("\n");
-
-    Ok((config, template))
+    Some(FrontMatterParts {
+        front_matter: lines[1..end_idx].join("\n"),
+        template: lines[end_idx + 1..].join("\n"),
+        template_offset: end_idx + 1,
+    })
 }
 
 /// Template context for rendering.
@@ -223,6 +288,79 @@ pub struct FileContext {
     pub language: String,
 }
 
+/// Where an interrupted task stopped, to resume it with
+/// [`Context::for_resume`].
+///
+/// `gba_core` converts a feature's persisted state into a resume context;
+/// the implementation plan and tools are left to the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResumeContext {
+    /// Feature name.
+    pub feature_name: String,
+
+    /// Feature ID.
+    pub feature_id: String,
+
+    /// Feature description.
+    pub feature_description: String,
+
+    /// Task kind, e.g. `"implementation"`.
+    pub task_kind: String,
+
+    /// Phase the task stopped in.
+    pub current_phase: String,
+
+    /// Step the task stopped at.
+    pub current_step: String,
+
+    /// Turns completed so far.
+    pub turns_so_far: u32,
+
+    /// Cost incurred so far in USD.
+    pub cost_so_far: f64,
+
+    /// Worktree path; empty if the feature has no worktree.
+    pub worktree_path: String,
+
+    /// Worktree branch name; empty if the feature has no worktree.
+    pub worktree_branch: String,
+
+    /// Implementation plan.
+    pub implementation_plan: String,
+
+    /// Whether to use preset.
+    pub use_preset: bool,
+
+    /// Tools of the task.
+    pub tools: Vec<String>,
+}
+
+impl Default for ResumeContext {
+    fn default() -> Self {
+        Self {
+            feature_name: String::new(),
+            feature_id: String::new(),
+            feature_description: String::new(),
+            task_kind: String::new(),
+            current_phase: String::new(),
+            current_step: String::new(),
+            turns_so_far: 0,
+            cost_so_far: 0.0,
+            worktree_path: String::new(),
+            worktree_branch: String::new(),
+            implementation_plan: String::new(),
+            use_preset: true,
+            tools: Vec::new(),
+        }
+    }
+}
+
+impl From<ResumeContext> for Context {
+    fn from(resume: ResumeContext) -> Self {
+        Self::for_resume(resume)
+    }
+}
+
 impl Context {
     /// Create a new context with the given values.
     ///
@@ -402,37 +540,27 @@ impl Context {
     }
 
     /// Create a context for resuming a task.
+    ///
+    /// # Arguments
+    ///
+    /// * `resume` - Where the task stopped, e.g. converted from the feature's
+    ///   persisted state.
     #[must_use]
-    #[allow(clippy::too_many_arguments)]
-    pub fn for_resume(
-        feature_name: impl Into<String>,
-        feature_id: impl Into<String>,
-        feature_description: impl Into<String>,
-        task_kind: impl Into<String>,
-        current_phase: impl Into<String>,
-        current_step: impl Into<String>,
-        turns_so_far: u32,
-        cost_so_far: f64,
-        worktree_path: impl Into<String>,
-        worktree_branch: impl Into<String>,
-        implementation_plan: impl Into<String>,
-        use_preset: bool,
-        tools: Vec<String>,
-    ) -> Self {
+    pub fn for_resume(resume: ResumeContext) -> Self {
         Self {
-            feature_name: feature_name.into(),
-            feature_id: feature_id.into(),
-            feature_description: feature_description.into(),
-            task_kind: task_kind.into(),
-            current_phase: current_phase.into(),
-            current_step: current_step.into(),
-            turns_so_far,
-            cost_so_far,
-            worktree_path: worktree_path.into(),
-            worktree_branch: worktree_branch.into(),
-            implementation_plan: implementation_plan.into(),
-            use_preset,
-            tools,
+            feature_name: resume.feature_name,
+            feature_id: resume.feature_id,
+            feature_description: resume.feature_description,
+            task_kind: resume.task_kind,
+            current_phase: resume.current_phase,
+            current_step: resume.current_step,
+            turns_so_far: resume.turns_so_far,
+            cost_so_far: resume.cost_so_far,
+            worktree_path: resume.worktree_path,
+            worktree_branch: resume.worktree_branch,
+            implementation_plan: resume.implementation_plan,
+            use_preset: resume.use_preset,
+            tools: resume.tools,
             user_message: String::new(),
             files: Vec::new(),
             extra: serde_json::Value::Object(Default::default()),
@@ -538,6 +666,15 @@ Template content here"#;
         assert_eq!(template.trim(), "Template content here");
     }
 
+    #[test]
+    fn test_extract_permission_mode() {
+        let source = "---\npermissionMode: acceptEdits\n---\nEdit";
+        let (config, _) = extract_front_matter(source).unwrap();
+        assert_eq!(config.permission_mode, Some(PermissionMode::AcceptEdits));
+        assert_eq!(PermissionMode::AcceptEdits.to_string(), "acceptEdits");
+        assert_eq!(PermissionMode::default(), PermissionMode::BypassPermissions);
+    }
+
     #[test]
     fn test_extract_no_front_matter() {
         let source = "Just template content";
@@ -621,22 +758,20 @@ You are helping with {{ task }}"#;
 
     #[test]
     fn test_context_for_resume() {
-        let context = Context::for_resume(
-            "add-auth",
-            "0001",
-            "Add authentication",
-            "implementation",
-            "phase_1",
-            "step_2",
-            5,
-            0.50,
-            "/trees/0001",
-            "gba/0001-add-auth",
-            "Plan content",
-            true,
-            vec!["Write".to_string()],
-        );
+        let context = Context::for_resume(ResumeContext {
+            feature_name: "add-auth".to_string(),
+            feature_id: "0001".to_string(),
+            task_kind: "implementation".to_string(),
+            current_phase: "phase_1".to_string(),
+            current_step: "step_2".to_string(),
+            turns_so_far: 5,
+            cost_so_far: 0.50,
+            tools: vec!["Write".to_string()],
+            ..ResumeContext::default()
+        });
         assert_eq!(context.task_kind, "implementation");
+        assert!(context.use_preset);
+        assert_eq!(context.tools, vec!["Write"]);
         assert_eq!(context.current_phase, "phase_1");
         assert_eq!(context.turns_so_far, 5);
         assert_eq!(context.cost_so_far, 0.50);
@@ -0,0 +1,470 @@
+//! Constraints on the output of a prompt.
+//!
+//! A template can require its response to follow a format, e.g. a plan with
+//! numbered phases or a review answering in JSON. Each [`OutputConstraint`]
+//! both instructs the model, with a line appended to the prompt by
+//! [`with_instructions`], and checks the response, so a caller can ask the
+//! model to fix a response that breaks one with [`repair_prompt`].
+//!
+//! Constraints are declared in the front matter of a template:
+//!
+//! ```yaml
+//! ---
+//! constraints:
+//!   - type: requiredSections
+//!     headings: [Overview, Phases]
+//!   - type: numberedHeadings
+//!     prefix: Phase
+//!     min: 1
+//! ---
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use gba_pm::constraints::{self, OutputConstraint};
+//!
+//! let constraints = vec![OutputConstraint::NumberedHeadings {
+//!     prefix: "Phase".to_string(),
+//!     min: 2,
+//! }];
+//! let problems = constraints::violations(&constraints, "### Phase 1: Models\n");
+//! assert_eq!(
+//!     problems,
+//!     ["expected at least 2 headings numbered like 'Phase 1', found 1"]
+//! );
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A requirement on the response to a prompt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum OutputConstraint {
+    /// Include a markdown heading for each section, at any level.
+    ///
+    /// Headings are compared case-insensitively, without the leading `#`s.
+    RequiredSections {
+        /// Headings of the sections, e.g. `"Affected Files"`.
+        headings: Vec<String>,
+    },
+
+    /// Include markdown headings numbered from 1 without gaps, e.g.
+    /// `### Phase 1: Models`, `### Phase 2: API`.
+    NumberedHeadings {
+        /// Word before the number, e.g. `"Phase"`; empty for headings
+        /// starting with the number, e.g. `## 1. Models`.
+        #[serde(default)]
+        prefix: String,
+
+        /// Minimum number of numbered headings.
+        #[serde(default = "default_min_headings")]
+        min: usize,
+    },
+
+    /// Answer with a JSON value matching a schema, alone or in a ```` ```json ````
+    /// code block.
+    ///
+    /// The schema is checked for its `type`, `enum`, `required`,
+    /// `properties`, `additionalProperties: false`, `items` and `minItems`
+    /// keywords; others are ignored.
+    JsonSchema {
+        /// JSON schema of the answer.
+        schema: Value,
+    },
+}
+
+fn default_min_headings() -> usize {
+    1
+}
+
+impl OutputConstraint {
+    /// Get the instruction telling the model to meet the constraint.
+    #[must_use]
+    pub fn instruction(&self) -> String {
+        match self {
+            Self::RequiredSections { headings } => format!(
+                "Include these sections, each under a markdown heading: {}.",
+                headings.join(", ")
+            ),
+            Self::NumberedHeadings { prefix, min } => format!(
+                "Number the {} headings from 1 without gaps, e.g. `### {}: ...`, with at least {min}.",
+                if prefix.is_empty() { "section" } else { prefix },
+                numbered(prefix, 1)
+            ),
+            Self::JsonSchema { schema } => format!(
+                "Answer with a single JSON value in a ```json code block, matching this JSON schema:\n\n```json\n{}\n```",
+                serde_json::to_string_pretty(schema).unwrap_or_default()
+            ),
+        }
+    }
+
+    /// Check a response against the constraint.
+    ///
+    /// # Returns
+    ///
+    /// A description of each problem found; empty when the response meets
+    /// the constraint.
+    #[must_use]
+    pub fn check(&self, content: &str) -> Vec<String> {
+        match self {
+            Self::RequiredSections { headings } => {
+                let found = headings_of(content).collect::<Vec<_>>();
+                headings
+                    .iter()
+                    .filter(|heading| !found.iter().any(|text| text.eq_ignore_ascii_case(heading)))
+                    .map(|heading| format!("missing the '{heading}' section"))
+                    .collect()
+            }
+            Self::NumberedHeadings { prefix, min } => check_numbered(content, prefix, *min),
+            Self::JsonSchema { schema } => match serde_json::from_str(json_block(content)) {
+                Ok(value) => {
+                    let mut problems = Vec::new();
+                    check_schema(schema, &value, "$", &mut problems);
+                    problems
+                }
+                Err(e) => vec![format!("the answer is not valid JSON: {e}")],
+            },
+        }
+    }
+}
+
+/// Append the instructions of constraints to a prompt.
+///
+/// A prompt without constraints is returned unchanged.
+///
+/// # Arguments
+///
+/// * `prompt` - Rendered prompt.
+/// * `constraints` - Constraints on its response.
+#[must_use]
+pub fn with_instructions(prompt: &str, constraints: &[OutputConstraint]) -> String {
+    if constraints.is_empty() {
+        return prompt.to_string();
+    }
+    format!(
+        "{}\n\n## Output Requirements\n\n{}\n",
+        prompt.trim_end(),
+        bullets(constraints.iter().map(OutputConstraint::instruction))
+    )
+}
+
+/// Check a response against constraints.
+///
+/// # Returns
+///
+/// A description of each problem found, in the order of the constraints.
+#[must_use]
+pub fn violations(constraints: &[OutputConstraint], content: &str) -> Vec<String> {
+    constraints
+        .iter()
+        .flat_map(|constraint| constraint.check(content))
+        .collect()
+}
+
+/// Build the prompt asking the model to fix a response that broke
+/// constraints.
+///
+/// # Arguments
+///
+/// * `constraints` - Constraints on the response.
+/// * `problems` - Problems found by [`violations`].
+#[must_use]
+pub fn repair_prompt(constraints: &[OutputConstraint], problems: &[String]) -> String {
+    format!(
+        "Your response doesn't meet the output requirements:\n\n{}\n\nRespond again with the complete response, meeting every requirement:\n\n{}\n",
+        bullets(problems.iter().cloned()),
+        bullets(constraints.iter().map(OutputConstraint::instruction))
+    )
+}
+
+/// Format lines as a markdown list.
+fn bullets(lines: impl Iterator<Item = String>) -> String {
+    lines
+        .map(|line| format!("- {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Get the text of the markdown headings of content, outside code blocks.
+fn headings_of(content: &str) -> impl Iterator<Item = &str> {
+    let mut in_code = false;
+    content.lines().filter_map(move |line| {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            return None;
+        }
+        if in_code {
+            return None;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let text = &trimmed[level..];
+        ((1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')))
+            .then(|| text.trim())
+    })
+}
+
+/// Format a numbered heading, e.g. `Phase 1`.
+fn numbered(prefix: &str, number: usize) -> String {
+    if prefix.is_empty() {
+        format!("{number}.")
+    } else {
+        format!("{prefix} {number}")
+    }
+}
+
+/// Get the number of a heading starting with a prefix and a number, e.g.
+/// 2 for `Phase 2: API` with the prefix `Phase`.
+fn heading_number(text: &str, prefix: &str) -> Option<usize> {
+    let rest = if prefix.is_empty() {
+        text
+    } else {
+        let head = text.get(..prefix.len())?;
+        if !head.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        text[prefix.len()..].strip_prefix(' ')?
+    };
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let after = rest[digits..].chars().next();
+    if digits == 0 || after.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    rest[..digits].parse().ok()
+}
+
+/// Check that content has at least `min` headings numbered from 1 without
+/// gaps.
+fn check_numbered(content: &str, prefix: &str, min: usize) -> Vec<String> {
+    let numbers = headings_of(content)
+        .filter_map(|text| heading_number(text, prefix))
+        .collect::<Vec<_>>();
+    let mut problems = Vec::new();
+    if numbers.len() < min {
+        problems.push(format!(
+            "expected at least {min} headings numbered like '{}', found {}",
+            numbered(prefix, 1),
+            numbers.len()
+        ));
+    }
+    if let Some((index, number)) = numbers
+        .iter()
+        .enumerate()
+        .find(|(index, number)| **number != index + 1)
+    {
+        problems.push(format!(
+            "'{}' should be '{}': number the headings from 1 without gaps",
+            numbered(prefix, *number),
+            numbered(prefix, index + 1)
+        ));
+    }
+    problems
+}
+
+/// Get the JSON of an answer: its first ```` ```json ```` code block, or the
+/// whole answer.
+fn json_block(content: &str) -> &str {
+    let Some(start) = content.find("```json") else {
+        return content.trim();
+    };
+    let block = &content[start + "```json".len()..];
+    block.find("```").map_or(block, |end| &block[..end]).trim()
+}
+
+/// Check a JSON value against a schema, collecting each problem with the
+/// path of the value, e.g. `$.findings[0].severity`.
+fn check_schema(schema: &Value, value: &Value, path: &str, problems: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect::<Vec<_>>(),
+        };
+        if !types.is_empty() && !types.iter().any(|kind| has_type(value, kind)) {
+            problems.push(format!("{path} should be of type {}", types.join(" or ")));
+            return;
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum")
+        && !allowed.contains(value)
+    {
+        let allowed = allowed.iter().map(Value::to_string).collect::<Vec<_>>();
+        problems.push(format!("{path} should be one of {}", allowed.join(", ")));
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        problems.push(format!("{path} is missing the '{name}' field"));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in object {
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(field_schema) => {
+                        check_schema(field_schema, field, &format!("{path}.{name}"), problems);
+                    }
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        problems.push(format!("{path} has an unexpected '{name}' field"));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+                && (items.len() as u64) < min
+            {
+                problems.push(format!("{path} should have at least {min} items"));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check_schema(item_schema, item, &format!("{path}[{index}]"), problems);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check whether a JSON value is of a JSON schema type.
+fn has_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_sections() {
+        let constraint = OutputConstraint::RequiredSections {
+            headings: vec!["Overview".to_string(), "Affected Files".to_string()],
+        };
+        assert!(
+            constraint
+                .check("# Plan\n\n## overview\n\n## Affected Files\n")
+                .is_empty()
+        );
+        assert_eq!(
+            constraint.check("## Overview\n\n```md\n## Affected Files\n```\n"),
+            ["missing the 'Affected Files' section"]
+        );
+    }
+
+    #[test]
+    fn test_numbered_headings() {
+        let constraint = OutputConstraint::NumberedHeadings {
+            prefix: "Phase".to_string(),
+            min: 2,
+        };
+        let plan = "## Phases\n\n### Phase 1: Models\n\n### Phase 2: API\n";
+        assert!(constraint.check(plan).is_empty());
+        assert_eq!(
+            constraint.check("### Phase 1: Models\n\n### Phase 3: API\n"),
+            ["'Phase 3' should be 'Phase 2': number the headings from 1 without gaps"]
+        );
+        assert_eq!(
+            constraint.check("### Phases\n\n### Phase one\n"),
+            ["expected at least 2 headings numbered like 'Phase 1', found 0"]
+        );
+
+        let constraint = OutputConstraint::NumberedHeadings {
+            prefix: String::new(),
+            min: 1,
+        };
+        assert!(constraint.check("## 1. Models\n## 2) API\n").is_empty());
+        assert_eq!(constraint.check("## 2024 roadmap\n").len(), 1);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let constraint = OutputConstraint::JsonSchema {
+            schema: serde_json::json!({
+                "type": "object",
+                "required": ["findings"],
+                "additionalProperties": false,
+                "properties": {
+                    "findings": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["file", "severity"],
+                            "properties": {
+                                "file": {"type": "string"},
+                                "line": {"type": "integer"},
+                                "severity": {"enum": ["critical", "important", "minor"]}
+                            }
+                        }
+                    }
+                }
+            }),
+        };
+        let answer = "Here is the review:\n\n```json\n{\"findings\": [{\"file\": \"src/auth.rs\", \"line\": 42, \"severity\": \"critical\"}]}\n```\n";
+        assert!(constraint.check(answer).is_empty());
+        assert!(constraint.check("{\"findings\": []}").is_empty());
+
+        assert_eq!(
+            constraint.check(
+                "{\"findings\": [{\"file\": \"a.rs\", \"line\": \"42\", \"severity\": \"high\"}], \"score\": 3}"
+            ),
+            [
+                "$.findings[0].line should be of type integer",
+                "$.findings[0].severity should be one of \"critical\", \"important\", \"minor\"",
+                "$ has an unexpected 'score' field",
+            ]
+        );
+        assert_eq!(
+            constraint.check("{}"),
+            ["$ is missing the 'findings' field"]
+        );
+        assert!(constraint.check("No findings.")[0].starts_with("the answer is not valid JSON"));
+    }
+
+    #[test]
+    fn test_instructions_and_repair_prompt() {
+        let constraints = vec![OutputConstraint::RequiredSections {
+            headings: vec!["Overview".to_string()],
+        }];
+        assert_eq!(with_instructions("Plan it.", &[]), "Plan it.");
+        assert_eq!(
+            with_instructions("Plan it.\n", &constraints),
+            "Plan it.\n\n## Output Requirements\n\n- Include these sections, each under a markdown heading: Overview.\n"
+        );
+
+        let problems = violations(&constraints, "No headings");
+        let prompt = repair_prompt(&constraints, &problems);
+        assert!(prompt.contains("- missing the 'Overview' section"));
+        assert!(prompt.contains("- Include these sections"));
+    }
+
+    #[test]
+    fn test_constraints_from_yaml() {
+        let yaml = "- type: requiredSections\n  headings: [Overview]\n- type: numberedHeadings\n  prefix: Phase\n";
+        let constraints: Vec<OutputConstraint> = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            constraints[1],
+            OutputConstraint::NumberedHeadings {
+                prefix: "Phase".to_string(),
+                min: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Default filters and functions for prompt authoring.
+//!
+//! Every [`crate::TemplateEngine`] starts with these, in addition to
+//! Minijinja's built-in filters:
+//!
+//! | Name | Kind | Example |
+//! |------|------|---------|
+//! | `truncate_tokens` | filter | `{{ diff \| truncate_tokens(2000) }}` |
+//! | `code_fence` | filter | `{{ content \| code_fence("rust") }}` |
+//! | `relpath` | filter | `{{ file.path \| relpath(repo_path) }}` |
+//! | `now` | function | `{{ now() }}`, `{{ now("%Y-%m-%d") }}` |
+//!
+//! More can be registered with [`crate::TemplateEngine::add_filter`] and
+//! [`crate::TemplateEngine::add_function`].
+
+use std::fmt::Write;
+use std::path::Path;
+
+use chrono::Utc;
+use minijinja::{Environment, Error, ErrorKind};
+
+/// Characters per token assumed by `truncate_tokens`.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Marker appended to text cut by `truncate_tokens`.
+const TRUNCATION_MARKER: &str = "…";
+
+/// Register the default filters and functions in an environment.
+pub(crate) fn register_defaults(env: &mut Environment<'static>) {
+    env.add_filter("truncate_tokens", truncate_tokens);
+    env.add_filter("code_fence", code_fence);
+    env.add_filter("relpath", relpath);
+    env.add_function("now", now);
+}
+
+/// Cut a text to about `max_tokens` tokens, at four characters per token.
+///
+/// A cut text ends with `…`.
+fn truncate_tokens(value: &str, max_tokens: usize) -> String {
+    let max_chars = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+    match value.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}{TRUNCATION_MARKER}", &value[..end]),
+        None => value.to_string(),
+    }
+}
+
+/// Wrap a text in a markdown code block, optionally tagged with a language.
+///
+/// The fence is longer than any run of backticks in the text, so code
+/// containing fences of its own stays in one block.
+fn code_fence(value: &str, language: Option<&str>) -> String {
+    let longest = value
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    let newline = if value.ends_with('\n') { "" } else { "\n" };
+    format!(
+        "{fence}{}\n{value}{newline}{fence}",
+        language.unwrap_or_default()
+    )
+}
+
+/// Make a path relative to a base directory.
+///
+/// Paths outside the base are returned unchanged.
+fn relpath(path: &str, base: &str) -> String {
+    Path::new(path)
+        .strip_prefix(base)
+        .map_or_else(|_| path.to_string(), |p| p.display().to_string())
+}
+
+/// Get the current UTC time, in RFC 3339 or the given `strftime` format.
+fn now(format: Option<&str>) -> Result<String, Error> {
+    let now = Utc::now();
+    let Some(format) = format else {
+        return Ok(now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+    };
+    let mut out = String::new();
+    write!(out, "{}", now.format(format)).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("invalid time format: {format}"),
+        )
+    })?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> Result<String, Error> {
+        let mut env = Environment::new();
+        register_defaults(&mut env);
+        env.render_str(source, minijinja::context! { repo => "/repo" })
+    }
+
+    #[test]
+    fn test_truncate_tokens() {
+        assert_eq!(truncate_tokens("abcdefghij", 2), "abcdefgh…");
+        assert_eq!(truncate_tokens("abcd", 1), "abcd");
+        assert_eq!(truncate_tokens("éééééé", 1), "éééé…");
+        assert_eq!(
+            render("{{ 'abcdefghij' | truncate_tokens(1) }}").unwrap(),
+            "abcd…"
+        );
+    }
+
+    #[test]
+    fn test_code_fence() {
+        assert_eq!(
+            code_fence("let x = 1;", Some("rust")),
+            "```rust\nlet x = 1;\n```"
+        );
+        assert_eq!(code_fence("a\n", None), "```\na\n```");
+        assert_eq!(code_fence("```\nx\n```", None), "````\n```\nx\n```\n````");
+        assert_eq!(render("{{ 'x' | code_fence }}").unwrap(), "```\nx\n```");
+    }
+
+    #[test]
+    fn test_relpath() {
+        assert_eq!(
+            render("{{ '/repo/src/lib.rs' | relpath(repo) }}").unwrap(),
+            "src/lib.rs"
+        );
+        assert_eq!(relpath("/other/lib.rs", "/repo"), "/other/lib.rs");
+    }
+
+    #[test]
+    fn test_now() {
+        let year = render("{{ now('%Y') }}").unwrap();
+        assert_eq!(year.len(), 4);
+        assert!(render("{{ now() }}").unwrap().ends_with('Z'));
+        assert!(render("{{ now('%Q') }}").is_err());
+    }
+}
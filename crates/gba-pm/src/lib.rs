@@ -1,24 +1,41 @@
 //! GBA Prompt Manager - Template-based prompt management using Minijinja.
 //!
 //! This crate provides functionality for managing and rendering prompts using
-//! the Minijinja templating engine.
+//! the Minijinja templating engine. Besides Minijinja's built-in filters,
+//! templates can use the prompt authoring helpers of [`filters`], and
+//! embedders can register their own with [`TemplateEngine::add_filter`] and
+//! [`PromptManager::add_function`].
 
 #![warn(rust_2024_compatibility, missing_docs, missing_debug_implementations)]
 
+pub mod bundled;
+pub mod cache;
 pub mod config;
+pub mod constraints;
 pub mod error;
+pub mod filters;
+pub mod lint;
 pub mod prompt;
 pub mod template;
+pub mod vars;
 
-pub use config::{Context, FileContext, PromptTemplate, TemplateConfig};
+pub use bundled::BundledTemplate;
+pub use cache::{RenderStats, TemplateStats};
+pub use config::{
+    Context, FileContext, PermissionMode, PromptTemplate, ResumeContext, TemplateConfig,
+};
+pub use constraints::OutputConstraint;
 pub use error::{PromptError, Result};
-pub use prompt::PromptManager;
+pub use lint::{TemplateProblem, check_template};
+pub use minijinja;
+pub use prompt::{LAYOUT_TEMPLATE, PromptManager};
 pub use template::TemplateEngine;
+pub use vars::{DeclaredVariable, TemplateVariable, VariableKind};
 
 /// Re-export common types for convenience.
 pub mod prelude {
     pub use crate::{
-        Context, FileContext, PromptError, PromptManager, PromptTemplate, Result, TemplateConfig,
-        TemplateEngine,
+        Context, FileContext, PromptError, PromptManager, PromptTemplate, Result, ResumeContext,
+        TemplateConfig, TemplateEngine,
     };
 }
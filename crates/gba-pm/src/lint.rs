@@ -0,0 +1,152 @@
+//! Checks of template sources.
+//!
+//! [`check_template`] reports every problem of a template without rendering
+//! it, each on its line of the source when known:
+//!
+//! - front matter that isn't valid YAML or has fields of the wrong type,
+//! - Jinja syntax errors,
+//! - variables that no context provides: neither a field of the standard
+//!   [`Context`], nor a variable of the bundled template of the same name,
+//!   which an override receives too, nor one of the caller's extras.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::config::{Context, TemplateConfig, split_front_matter};
+use crate::template::{BUNDLED_PREFIX, TemplateEngine};
+use crate::vars::referenced_variables;
+
+/// A problem found in a template by [`check_template`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateProblem {
+    /// Line of the problem in the template source, starting at 1, if known.
+    pub line: Option<usize>,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for TemplateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Check a template source.
+///
+/// # Arguments
+///
+/// * `engine` - Engine the template is rendered with, for its globals and
+///   bundled templates.
+/// * `name` - Template name, e.g. `"plan"`.
+/// * `source` - Template source, with its front matter.
+/// * `extra` - Extra variables the caller adds to the context, e.g.
+///   `"feature_name"`.
+#[must_use]
+pub fn check_template(
+    engine: &TemplateEngine,
+    name: &str,
+    source: &str,
+    extra: &BTreeSet<String>,
+) -> Vec<TemplateProblem> {
+    let mut problems = Vec::new();
+    let (template, offset) = match split_front_matter(source) {
+        Some(parts) => {
+            if let Err(e) = serde_yaml::from_str::<TemplateConfig>(&parts.front_matter) {
+                problems.push(TemplateProblem {
+                    // The front matter starts on the second line
+                    line: e.location().map(|location| location.line() + 1),
+                    message: format!("invalid front matter: {e}"),
+                });
+            }
+            (parts.template, parts.template_offset)
+        }
+        None => (source.to_string(), 0),
+    };
+
+    let env = engine.env();
+    let compiled = match env.template_from_str(&template) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            problems.push(TemplateProblem {
+                line: e.line().map(|line| line + offset),
+                message: match e.detail() {
+                    Some(detail) => format!("{}: {detail}", e.kind()),
+                    None => e.kind().to_string(),
+                },
+            });
+            return problems;
+        }
+    };
+
+    let mut known = Context::variable_names();
+    known.extend(extra.iter().cloned());
+    if let Ok(bundled) = env.get_template(&format!("{BUNDLED_PREFIX}{name}")) {
+        known.extend(referenced_variables(env, &bundled));
+    }
+    for variable in referenced_variables(env, &compiled) {
+        if !known.contains(&variable) {
+            problems.push(TemplateProblem {
+                line: variable_line(&template, &variable).map(|line| line + offset),
+                message: format!("unknown variable '{variable}'"),
+            });
+        }
+    }
+    problems
+}
+
+/// Find the first line, starting at 1, using a variable inside a tag.
+fn variable_line(template: &str, variable: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    template
+        .lines()
+        .position(|line| {
+            line.match_indices(variable).any(|(start, _)| {
+                let end = start + variable.len();
+                !line[..start].ends_with(is_ident)
+                    && !line[..start].ends_with('.')
+                    && !line[end..].starts_with(is_ident)
+                    && (line[..start].contains("{{") || line[..start].contains("{%"))
+            })
+        })
+        .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, source: &str) -> Vec<TemplateProblem> {
+        let extra = BTreeSet::from(["feature_name".to_string()]);
+        check_template(&TemplateEngine::new().unwrap(), name, source, &extra)
+    }
+
+    #[test]
+    fn test_check_valid_template() {
+        let problems = check(
+            "custom",
+            "---\ntools: []\n---\n{{ featureName }} {{ feature_name | upper }}\n{{ now() }}",
+        );
+        assert_eq!(problems, []);
+
+        // An override gets the variables of the bundled template
+        assert_eq!(check("plan", "{{ main_branch }}"), []);
+    }
+
+    #[test]
+    fn test_check_reports_lines() {
+        let problems = check("custom", "---\ntools: 3\n---\nHello\n{{ user.name }}");
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].line, Some(2));
+        assert!(problems[0].message.starts_with("invalid front matter"));
+        assert_eq!(problems[1].to_string(), "line 5: unknown variable 'user'");
+
+        let problems = check("custom", "---\n---\nok\n{% if x %}\nunclosed");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(4));
+        assert!(problems[0].message.starts_with("syntax error"));
+    }
+}
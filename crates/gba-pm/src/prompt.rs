@@ -1,13 +1,22 @@
 //! Prompt manager for loading and managing prompt templates.
 
+use crate::cache::{self, RenderCache, RenderStats};
 use crate::config::{Context, PromptTemplate, TemplateConfig};
 use crate::error::{PromptError, Result};
 use crate::template::TemplateEngine;
-use minijinja::value::Value;
+use crate::vars::{self, TemplateVariable};
+use minijinja::functions::Function;
+use minijinja::value::{FunctionArgs, FunctionResult, Value};
+use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Instant;
 use tracing::{debug, instrument, warn};
 
+/// Template laying out the full prompt sent to the agent.
+pub const LAYOUT_TEMPLATE: &str = "layout";
+
 /// Prompt manager for loading and managing prompt templates.
 #[derive(Debug)]
 pub struct PromptManager {
@@ -17,6 +26,8 @@ pub struct PromptManager {
     registry: HashMap<String, TemplateConfig>,
     /// Local templates directory path.
     local_templates_dir: Option<PathBuf>,
+    /// Rendered prompts and render statistics.
+    cache: RenderCache,
 }
 
 impl PromptManager {
@@ -32,6 +43,7 @@ impl PromptManager {
             engine,
             registry: HashMap::new(),
             local_templates_dir: None,
+            cache: RenderCache::default(),
         })
     }
 
@@ -49,7 +61,13 @@ impl PromptManager {
     pub fn with_local_dir(local_dir: PathBuf, use_bundled: bool) -> Result<Self> {
         let mut engine = TemplateEngine::new()?;
 
-        // Load local templates if directory exists
+        // Load bundled templates as fallback
+        if use_bundled || !local_dir.exists() {
+            debug!("Loading bundled templates");
+            engine.load_all_bundled_templates()?;
+        }
+
+        // Load local templates if directory exists, overriding bundled ones
         if local_dir.exists() {
             debug!(
                 "Loading templates from local directory: {}",
@@ -58,16 +76,11 @@ impl PromptManager {
             engine.load_templates_from_dir(&local_dir)?;
         }
 
-        // Load bundled templates as fallback
-        if use_bundled || !local_dir.exists() {
-            debug!("Loading bundled templates");
-            engine.load_all_bundled_templates()?;
-        }
-
         Ok(Self {
             engine,
             registry: HashMap::new(),
             local_templates_dir: Some(local_dir),
+            cache: RenderCache::default(),
         })
     }
 
@@ -92,13 +105,46 @@ impl PromptManager {
 
         // Add the template to the engine
         self.engine.add_template(&name, prompt_template.template)?;
+        self.cache.clear();
 
         debug!("Registered prompt template: {}", name);
         Ok(())
     }
 
+    /// Register a filter available to all templates, see
+    /// [`TemplateEngine::add_filter`].
+    ///
+    /// Clears the render cache.
+    pub fn add_filter<F, Rv, Args>(&mut self, name: impl Into<Cow<'static, str>>, filter: F)
+    where
+        F: Function<Rv, Args>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        self.engine.add_filter(name, filter);
+        self.cache.clear();
+    }
+
+    /// Register a function available to all templates, see
+    /// [`TemplateEngine::add_function`].
+    ///
+    /// Clears the render cache.
+    pub fn add_function<F, Rv, Args>(&mut self, name: impl Into<Cow<'static, str>>, function: F)
+    where
+        F: Function<Rv, Args>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        self.engine.add_function(name, function);
+        self.cache.clear();
+    }
+
     /// Get a rendered prompt by name.
     ///
+    /// A prompt already rendered with the same context is returned from the
+    /// render cache, so a template calling `now()` keeps the time of its first
+    /// render.
+    ///
     /// # Arguments
     ///
     /// * `name` - Name of the template to render.
@@ -109,8 +155,63 @@ impl PromptManager {
     /// Returns an error if the template is not found or rendering fails.
     #[instrument(skip(context))]
     pub fn get_prompt(&self, name: &str, context: &Context) -> Result<String> {
-        // Convert Context to minijinja Value
-        self.engine.render(name, Value::from_serialize(context))
+        self.render(name, context)
+    }
+
+    /// Lay out the full prompt sent to the agent with the `layout` template.
+    ///
+    /// The bundled layout reproduces the agent's built-in format; a local
+    /// `layout.jinja2` overrides it.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Sections of the prompt, e.g. a `gba_core::layout::PromptLayout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the layout template is not found or rendering
+    /// fails.
+    pub fn render_layout(&self, layout: &impl Serialize) -> Result<String> {
+        self.render(LAYOUT_TEMPLATE, layout)
+    }
+
+    /// Get the render statistics: renders, cache hits and time spent
+    /// rendering, per template.
+    #[must_use]
+    pub fn render_stats(&self) -> RenderStats {
+        self.cache.stats()
+    }
+
+    /// Render a template, from the cache if already rendered with the same
+    /// context.
+    fn render(&self, name: &str, context: &impl Serialize) -> Result<String> {
+        let key = cache::context_hash(context);
+        match key {
+            Some(key) => {
+                if let Some(prompt) = self.cache.get(name, key) {
+                    debug!("Render cache hit for template: {}", name);
+                    return Ok(prompt);
+                }
+            }
+            None => self.cache.count(name),
+        }
+
+        let started = Instant::now();
+        let prompt = self.engine.render(name, Value::from_serialize(context))?;
+        self.cache.insert(name, key, &prompt, started.elapsed());
+        Ok(prompt)
+    }
+
+    /// Get the variables a template references, sorted by name, and whether
+    /// the standard [`Context`] provides each.
+    ///
+    /// The template is analyzed without being rendered, see [`crate::vars`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template is not found.
+    pub fn template_variables(&self, name: &str) -> Result<Vec<TemplateVariable>> {
+        vars::template_variables(self.engine.env(), name)
     }
 
     /// Get the configuration for a registered template.
@@ -126,14 +227,19 @@ impl PromptManager {
     pub fn get_config(&self, name: &str) -> Result<TemplateConfig> {
         self.registry
             .get(name)
+            .or_else(|| self.engine.config(name))
             .cloned()
             .ok_or_else(|| PromptError::NotFound(name.to_string()))
     }
 
-    /// List all registered prompt names.
+    /// List the names of the registered and loaded prompts, sorted.
     #[must_use]
     pub fn list_prompts(&self) -> Vec<String> {
-        self.registry.keys().cloned().collect()
+        let mut names: Vec<String> = self.registry.keys().cloned().collect();
+        names.extend(self.engine.template_names().into_iter().map(String::from));
+        names.sort_unstable();
+        names.dedup();
+        names
     }
 
     /// Check if a template exists.
@@ -149,8 +255,9 @@ impl PromptManager {
     /// Returns an error if templates cannot be reloaded.
     #[instrument]
     pub fn reload(&mut self) -> Result<()> {
-        // Create new engine
-        let mut engine = TemplateEngine::new()?;
+        // Start over from the current engine to keep registered filters
+        let mut engine = self.engine.clone();
+        engine.clear_templates();
 
         // Reload local templates
         if let Some(ref local_dir) = self.local_templates_dir
@@ -168,6 +275,7 @@ impl PromptManager {
         engine.load_all_bundled_templates()?;
 
         self.engine = engine;
+        self.cache.clear();
 
         // Re-register parsed templates
         for _config in self.registry.values() {
@@ -296,6 +404,58 @@ Hello, {{ main_branch }}!"#;
         assert_eq!(result, "Hello, develop!");
     }
 
+    #[test]
+    fn test_prompt_manager_render_cache() {
+        let mut pm = PromptManager::new().unwrap();
+        pm.register("greeting", "---\n---\nHello, {{ branch }}!")
+            .unwrap();
+
+        let main = Context::new("/repo", "main", "Help");
+        let develop = Context::new("/repo", "develop", "Help");
+        assert_eq!(pm.get_prompt("greeting", &main).unwrap(), "Hello, main!");
+        assert_eq!(pm.get_prompt("greeting", &main).unwrap(), "Hello, main!");
+        assert_eq!(
+            pm.get_prompt("greeting", &develop).unwrap(),
+            "Hello, develop!"
+        );
+
+        let stats = pm.render_stats();
+        assert_eq!(stats.templates["greeting"].renders, 3);
+        assert_eq!(stats.cache_hits(), 1);
+
+        // Registering the template again drops its cached prompts
+        pm.register("greeting", "---\n---\nHi, {{ branch }}!")
+            .unwrap();
+        assert_eq!(pm.get_prompt("greeting", &main).unwrap(), "Hi, main!");
+        assert_eq!(pm.render_stats().cache_hits(), 1);
+    }
+
+    #[test]
+    fn test_prompt_manager_custom_filter_survives_reload() {
+        let mut manager = PromptManager::new().unwrap();
+        manager.add_filter("shout", |value: &str| value.to_uppercase());
+        manager.add_function("ticket", || "GBA-42");
+        manager
+            .register(
+                "greet",
+                "---\n---\nHello, {{ name | shout }} ({{ ticket() }})",
+            )
+            .unwrap();
+
+        let mut context = Context::new("/repo", "main", "Help");
+        context.add_extra("name", json!("gba"));
+        assert_eq!(
+            manager.get_prompt("greet", &context).unwrap(),
+            "Hello, GBA (GBA-42)"
+        );
+
+        manager.reload().unwrap();
+        manager
+            .register("greet", "---\n---\n{{ name | shout }}")
+            .unwrap();
+        assert_eq!(manager.get_prompt("greet", &context).unwrap(), "GBA");
+    }
+
     #[test]
     fn test_prompt_manager_list_prompts() {
         let mut pm = PromptManager::new().unwrap();
@@ -316,6 +476,10 @@ Hello, {{ main_branch }}!"#;
             use_preset: true,
             tools: vec![],
             max_turns: 50,
+            max_tokens: None,
+            permission_mode: None,
+            variables: vec![],
+            constraints: vec![],
         };
         let template = PromptTemplate {
             config: config.clone(),
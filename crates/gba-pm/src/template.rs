@@ -1,15 +1,43 @@
 //! Template engine implementation using Minijinja.
+//!
+//! Besides the templates added to it, an engine loads on demand the templates
+//! that others `{% include %}`, `{% import %}` or `{% extends %}`:
+//!
+//! - `bundled/<name>`, e.g. `bundled/plan`: a bundled template, even when a
+//!   local template overrides it, so the override can extend it.
+//! - any other name: a file of the local templates directory, relative to it
+//!   and with or without its `.jinja2` extension, e.g. `partials/rules`.
+//!
+//! The front matter of a template loaded this way is dropped, as it only
+//! configures the template rendered first. Templates loaded from the bundle
+//! or a directory keep theirs, see [`TemplateEngine::config`].
 
+use crate::bundled::BundledTemplate;
+use crate::config::{PromptTemplate, TemplateConfig};
 use crate::error::{PromptError, Result};
-use minijinja::{Environment, value::Value};
-use std::path::Path;
-use tracing::instrument;
+use crate::filters;
+use minijinja::functions::Function;
+use minijinja::value::{FunctionArgs, FunctionResult, Value};
+use minijinja::{Environment, Error, ErrorKind};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, instrument};
+
+/// Prefix of the names loading a bundled template.
+pub const BUNDLED_PREFIX: &str = "bundled/";
 
 /// Template engine for rendering prompts.
-#[derive(Debug)]
+///
+/// Templates can use the default filters and functions of
+/// [`crate::filters`], and any registered with [`Self::add_filter`] and
+/// [`Self::add_function`].
+#[derive(Debug, Clone)]
 pub struct TemplateEngine {
     /// Minijinja environment.
     env: Environment<'static>,
+    /// Front matter of the templates loaded from the bundle or a directory.
+    configs: HashMap<String, TemplateConfig>,
 }
 
 impl TemplateEngine {
@@ -19,7 +47,12 @@ impl TemplateEngine {
         let mut env = Environment::new();
         // Set up default configuration
         env.set_auto_escape_callback(|_| minijinja::AutoEscape::None);
-        Ok(Self { env })
+        filters::register_defaults(&mut env);
+        env.set_loader(|name| load_template(name, None));
+        Ok(Self {
+            env,
+            configs: HashMap::new(),
+        })
     }
 
     /// Create a new template engine with the given path loader.
@@ -36,7 +69,82 @@ impl TemplateEngine {
         let mut env = Environment::new();
         env.set_loader(minijinja::path_loader(path));
         env.set_auto_escape_callback(|_| minijinja::AutoEscape::None);
-        Ok(Self { env })
+        filters::register_defaults(&mut env);
+        Ok(Self {
+            env,
+            configs: HashMap::new(),
+        })
+    }
+
+    /// Register a filter, replacing any filter with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Filter name, e.g. `"shout"` for `{{ name | shout }}`.
+    /// * `filter` - Function taking the filtered value and the filter's
+    ///   arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use gba_pm::TemplateEngine;
+    ///
+    /// let mut engine = TemplateEngine::new()?;
+    /// engine.add_filter("shout", |value: &str| value.to_uppercase());
+    /// engine.add_template("greeting", "Hello, {{ name | shout }}!")?;
+    /// let prompt = engine.render("greeting", minijinja::context! { name => "world" })?;
+    /// assert_eq!(prompt, "Hello, WORLD!");
+    /// # Ok::<(), gba_pm::PromptError>(())
+    /// ```
+    pub fn add_filter<F, Rv, Args>(&mut self, name: impl Into<Cow<'static, str>>, filter: F)
+    where
+        F: Function<Rv, Args>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        self.env.add_filter(name, filter);
+    }
+
+    /// Register a function callable from templates, replacing any function or
+    /// global with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Function name, e.g. `"ticket"` for `{{ ticket() }}`.
+    /// * `function` - Function taking the call's arguments.
+    pub fn add_function<F, Rv, Args>(&mut self, name: impl Into<Cow<'static, str>>, function: F)
+    where
+        F: Function<Rv, Args>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        self.env.add_function(name, function);
+    }
+
+    /// Remove all templates, keeping the filters and functions.
+    pub fn clear_templates(&mut self) {
+        self.env.clear_templates();
+        self.configs.clear();
+    }
+
+    /// Get the front matter of a template loaded from the bundle or a
+    /// directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Template name.
+    #[must_use]
+    pub fn config(&self, name: &str) -> Option<&TemplateConfig> {
+        self.configs.get(name)
+    }
+
+    /// Get the names of the templates loaded from the bundle or a
+    /// directory, sorted.
+    #[must_use]
+    pub fn template_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.configs.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
     }
 
     /// Render a template with the given context.
@@ -90,7 +198,9 @@ impl TemplateEngine {
 
     /// Load templates from a directory.
     ///
-    /// This scans the directory and loads all `.jinja2` files.
+    /// This scans the directory and loads all `.jinja2` files. Files in
+    /// subdirectories, e.g. partials, are loaded when a template includes or
+    /// extends them.
     ///
     /// # Arguments
     ///
@@ -106,6 +216,9 @@ impl TemplateEngine {
         }
 
         let entries = std::fs::read_dir(path).map_err(PromptError::Io)?;
+        let dir = path.to_path_buf();
+        self.env
+            .set_loader(move |name| load_template(name, Some(&dir)));
 
         for entry in entries {
             let entry = entry.map_err(PromptError::Io)?;
@@ -118,7 +231,7 @@ impl TemplateEngine {
             {
                 let name = name.to_string_lossy().to_string();
                 let content = std::fs::read_to_string(&file_path).map_err(PromptError::Io)?;
-                self.add_template(&name, content)?;
+                self.add_prompt_template(name, &content)?;
             }
         }
 
@@ -136,11 +249,29 @@ impl TemplateEngine {
     /// Returns an error if the template is not found or cannot be loaded.
     #[instrument]
     pub fn load_bundled_template(&mut self, name: &str) -> Result<()> {
-        let template_name = format!("{name}.jinja2");
-        let content = get_bundled_template(&template_name).ok_or_else(|| {
-            PromptError::NotFound(format!("Bundled template '{template_name}' not found"))
+        let template = BundledTemplate::from_name(name).ok_or_else(|| {
+            PromptError::NotFound(format!("Bundled template '{name}.jinja2' not found"))
         })?;
-        self.add_template(name, content)
+        self.add_prompt_template(template.name().to_string(), template.source())
+    }
+
+    /// Add a template with front matter, keeping its configuration.
+    ///
+    /// A front matter that is itself templated, e.g. listing
+    /// `{{ tools }}`, doesn't parse before rendering: the template is then
+    /// added whole, without a configuration.
+    fn add_prompt_template(&mut self, name: String, source: &str) -> Result<()> {
+        match PromptTemplate::parse(source) {
+            Ok(PromptTemplate { config, template }) => {
+                self.add_template(name.clone(), template)?;
+                self.configs.insert(name, config);
+                Ok(())
+            }
+            Err(e) => {
+                debug!("Adding template {} without its configuration: {}", name, e);
+                self.add_template(name, source)
+            }
+        }
     }
 
     /// Load all bundled templates.
@@ -150,10 +281,8 @@ impl TemplateEngine {
     /// Returns an error if any bundled template cannot be loaded.
     #[instrument]
     pub fn load_all_bundled_templates(&mut self) -> Result<()> {
-        const TEMPLATES: &[&str] = &["init", "plan", "implement", "verify", "review", "resume"];
-
-        for name in TEMPLATES {
-            self.load_bundled_template(name)?;
+        for template in BundledTemplate::iter() {
+            self.load_bundled_template(template.name())?;
         }
 
         Ok(())
@@ -166,19 +295,56 @@ impl Default for TemplateEngine {
     }
 }
 
-/// Get a bundled template by name.
+/// Load a template included or extended by another, without its front
+/// matter.
 ///
 /// Returns `None` if the template does not exist.
-fn get_bundled_template(name: &str) -> Option<String> {
-    match name {
-        "init.jinja2" => Some(include_str!("../templates/init.jinja2").to_string()),
-        "plan.jinja2" => Some(include_str!("../templates/plan.jinja2").to_string()),
-        "implement.jinja2" => Some(include_str!("../templates/implement.jinja2").to_string()),
-        "verify.jinja2" => Some(include_str!("../templates/verify.jinja2").to_string()),
-        "review.jinja2" => Some(include_str!("../templates/review.jinja2").to_string()),
-        "resume.jinja2" => Some(include_str!("../templates/resume.jinja2").to_string()),
-        _ => None,
+fn load_template(
+    name: &str,
+    local_dir: Option<&Path>,
+) -> std::result::Result<Option<String>, Error> {
+    let source = if let Some(bundled) = name.strip_prefix(BUNDLED_PREFIX) {
+        BundledTemplate::from_name(bundled).map(|template| template.source().to_string())
+    } else if let Some(path) = local_dir.and_then(|dir| local_template_path(dir, name)) {
+        let source = std::fs::read_to_string(&path).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                format!("could not read template {}", path.display()),
+            )
+            .with_source(e)
+        })?;
+        Some(source)
+    } else {
+        None
+    };
+
+    source
+        .map(|source| {
+            PromptTemplate::parse(&source)
+                .map(|template| template.template)
+                .map_err(|e| Error::new(ErrorKind::SyntaxError, e.to_string()))
+        })
+        .transpose()
+}
+
+/// Get the file of a local template, with or without its `.jinja2`
+/// extension.
+///
+/// Names leaving the directory, e.g. `../secrets`, are never found.
+fn local_template_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let path = dir.join(relative);
+    if path.is_file() {
+        return Some(path);
     }
+    let path = dir.join(format!("{name}.jinja2"));
+    path.is_file().then_some(path)
 }
 
 #[cfg(test)]
@@ -234,6 +400,30 @@ mod tests {
         assert!(matches!(result, Err(PromptError::NotFound(_))));
     }
 
+    #[test]
+    fn test_load_template_outside_dir() {
+        let dir = std::env::temp_dir().join("gba-test-pm-template-path");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.jinja2"), "A").unwrap();
+
+        assert_eq!(local_template_path(&dir, "a"), Some(dir.join("a.jinja2")));
+        assert_eq!(
+            local_template_path(&dir, "a.jinja2"),
+            Some(dir.join("a.jinja2"))
+        );
+        assert!(local_template_path(&dir, "../a").is_none());
+        assert!(local_template_path(&dir, "/etc/passwd").is_none());
+        assert!(
+            load_template("bundled/missing", Some(&dir))
+                .unwrap()
+                .is_none()
+        );
+        assert!(load_template("bundled/plan", None).unwrap().is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_load_templates_from_nonexistent_dir() {
         let mut engine = TemplateEngine::new().unwrap();
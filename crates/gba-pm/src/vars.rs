@@ -0,0 +1,301 @@
+//! Variables referenced by templates.
+//!
+//! A template's variables are found statically from its syntax tree, without
+//! rendering it: every name the template reads but doesn't define itself,
+//! e.g. with `{% set %}` or a `{% for %}` loop. Globals of the environment,
+//! like `range`, are left out. Each variable is checked against the fields of
+//! the standard [`Context`]; the others must be supplied with
+//! [`Context::add_extra`].
+//!
+//! A template can also declare the variables its caller supplies in its
+//! front matter, with a description, a default and the values accepted, so
+//! a caller missing one can ask the user for it:
+//!
+//! ```yaml
+//! ---
+//! variables:
+//!   - name: ticket
+//!     description: Ticket of the feature
+//!     required: true
+//!   - name: risk
+//!     default: low
+//!     choices: [low, medium, high]
+//!   - name: maxFiles
+//!     type: integer
+//!     default: "20"
+//! ---
+//! ```
+
+use std::collections::BTreeSet;
+
+use minijinja::{Environment, Template};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Context;
+use crate::error::{PromptError, Result};
+
+/// A variable referenced by a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariable {
+    /// Variable name, e.g. `"featureName"`.
+    pub name: String,
+
+    /// Whether the standard [`Context`] provides the variable.
+    pub provided: bool,
+}
+
+/// A variable a template declares in its front matter, for the caller to
+/// supply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclaredVariable {
+    /// Variable name, e.g. `"ticket"`.
+    pub name: String,
+
+    /// What the variable holds, shown when asking for it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+
+    /// Value used when none is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+
+    /// Whether a value must be given when there is no default.
+    #[serde(default)]
+    pub required: bool,
+
+    /// Values accepted; empty accepts any value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+
+    /// Type of the value.
+    #[serde(default, rename = "type")]
+    pub kind: VariableKind,
+}
+
+/// Type of a declared variable's value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VariableKind {
+    /// Any text.
+    #[default]
+    String,
+    /// A whole number.
+    Integer,
+    /// A number.
+    Number,
+    /// `true` or `false`.
+    Boolean,
+}
+
+impl DeclaredVariable {
+    /// Get the value of the variable from what the user entered: the
+    /// default for blank input, or the input checked against the variable's
+    /// type and choices.
+    ///
+    /// An optional variable without a default left blank is an empty
+    /// string.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text entered, surrounding whitespace ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PromptError::MissingVariable`] for a blank required
+    /// variable, and [`PromptError::InvalidVariable`] for a value of the
+    /// wrong type or not among the choices.
+    pub fn parse(&self, input: &str) -> Result<serde_json::Value> {
+        let input = input.trim();
+        let input = match (input.is_empty(), &self.default) {
+            (false, _) => input,
+            (true, Some(default)) => default.as_str(),
+            (true, None) if self.required => {
+                return Err(PromptError::MissingVariable(self.name.clone()));
+            }
+            (true, None) => return Ok(serde_json::Value::String(String::new())),
+        };
+
+        if !self.choices.is_empty() && !self.choices.iter().any(|choice| choice == input) {
+            return Err(PromptError::InvalidVariable(format!(
+                "{}: '{input}' is not one of {}",
+                self.name,
+                self.choices.join(", ")
+            )));
+        }
+        let invalid = |kind: &str| {
+            PromptError::InvalidVariable(format!("{}: '{input}' is not {kind}", self.name))
+        };
+        Ok(match self.kind {
+            VariableKind::String => serde_json::Value::String(input.to_string()),
+            VariableKind::Integer => input
+                .parse::<i64>()
+                .map(serde_json::Value::from)
+                .map_err(|_| invalid("an integer"))?,
+            VariableKind::Number => input
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(serde_json::Value::Number)
+                .ok_or_else(|| invalid("a number"))?,
+            VariableKind::Boolean => input
+                .parse::<bool>()
+                .map(serde_json::Value::Bool)
+                .map_err(|_| invalid("true or false"))?,
+        })
+    }
+}
+
+impl Context {
+    /// Get the declared variables the context lacks: neither a field of the
+    /// standard context nor an extra variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `declared` - Variables a template declares.
+    #[must_use]
+    pub fn missing_variables<'a>(
+        &self,
+        declared: &'a [DeclaredVariable],
+    ) -> Vec<&'a DeclaredVariable> {
+        let provided = Self::variable_names();
+        declared
+            .iter()
+            .filter(|variable| {
+                !provided.contains(&variable.name) && self.extra.get(&variable.name).is_none()
+            })
+            .collect()
+    }
+
+    /// Get the names of the variables the standard context provides, as
+    /// templates see them, e.g. `"featureName"`.
+    #[must_use]
+    pub fn variable_names() -> BTreeSet<String> {
+        match serde_json::to_value(Self::default()) {
+            Ok(serde_json::Value::Object(fields)) => fields.into_iter().map(|(k, _)| k).collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+}
+
+/// Get the variables a template references, sorted by name.
+///
+/// # Errors
+///
+/// Returns an error if the template is not found.
+pub(crate) fn template_variables(
+    env: &Environment<'_>,
+    name: &str,
+) -> Result<Vec<TemplateVariable>> {
+    let template = env
+        .get_template(name)
+        .map_err(|e| PromptError::NotFound(format!("{name}: {e}")))?;
+    let provided = Context::variable_names();
+
+    Ok(referenced_variables(env, &template)
+        .into_iter()
+        .map(|name| TemplateVariable {
+            provided: provided.contains(&name),
+            name,
+        })
+        .collect())
+}
+
+/// Get the names a template reads but doesn't define, leaving out the
+/// globals of the environment.
+pub(crate) fn referenced_variables(
+    env: &Environment<'_>,
+    template: &Template<'_, '_>,
+) -> BTreeSet<String> {
+    let globals = env.globals().map(|(name, _)| name).collect::<BTreeSet<_>>();
+    template
+        .undeclared_variables(false)
+        .into_iter()
+        .filter(|name| !globals.contains(name.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_variables() {
+        let mut env = Environment::new();
+        env.add_template(
+            "plan",
+            "{% set title = featureName | upper %}{{ title }}\n\
+             {% for file in files %}{{ file.path }}{% endfor %}\n\
+             {% for i in range(3) %}{{ verificationFailures }}{% endfor %}",
+        )
+        .unwrap();
+
+        let variables = template_variables(&env, "plan").unwrap();
+        assert_eq!(
+            variables,
+            vec![
+                TemplateVariable {
+                    name: "featureName".to_string(),
+                    provided: true,
+                },
+                TemplateVariable {
+                    name: "files".to_string(),
+                    provided: true,
+                },
+                TemplateVariable {
+                    name: "verificationFailures".to_string(),
+                    provided: false,
+                },
+            ]
+        );
+        assert!(matches!(
+            template_variables(&env, "missing"),
+            Err(PromptError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_declared_variables() {
+        let declared: Vec<DeclaredVariable> = serde_yaml::from_str(
+            "- name: ticket\n  required: true\n\
+             - name: risk\n  default: low\n  choices: [low, high]\n\
+             - name: maxFiles\n  type: integer\n\
+             - name: notes\n",
+        )
+        .unwrap();
+        let [ticket, risk, max_files, notes] = &declared[..] else {
+            panic!("expected four variables");
+        };
+
+        assert!(matches!(
+            ticket.parse("  "),
+            Err(PromptError::MissingVariable(_))
+        ));
+        assert_eq!(ticket.parse(" GBA-12 ").unwrap(), "GBA-12");
+        assert_eq!(risk.parse("").unwrap(), "low");
+        assert!(matches!(
+            risk.parse("extreme"),
+            Err(PromptError::InvalidVariable(_))
+        ));
+        assert_eq!(max_files.parse("20").unwrap(), 20);
+        assert!(max_files.parse("twenty").is_err());
+        assert_eq!(notes.parse("").unwrap(), "");
+
+        let mut context = Context::default();
+        context.add_extra("risk", serde_json::json!("high"));
+        let missing: Vec<&str> = context
+            .missing_variables(&declared)
+            .iter()
+            .map(|variable| variable.name.as_str())
+            .collect();
+        assert_eq!(missing, ["ticket", "maxFiles", "notes"]);
+    }
+
+    #[test]
+    fn test_context_variable_names() {
+        let names = Context::variable_names();
+        assert!(names.contains("featureName"));
+        assert!(names.contains("implementationPlan"));
+        assert!(!names.contains("extra"));
+    }
+}
@@ -2,7 +2,7 @@
 //
 // These tests verify the integration between different components.
 
-use gba_pm::{Context, FileContext, PromptManager, PromptTemplate, TemplateConfig};
+use gba_pm::{Context, FileContext, PromptManager, PromptTemplate, ResumeContext, TemplateConfig};
 
 #[test]
 fn test_should_integration_prompt_manager_with_complex_template() {
@@ -130,28 +130,28 @@ fn test_should_integration_context_creation_methods() {
         "Implementation summary...",
     );
     assert_eq!(verification_context.task_kind, "verification");
-    assert!(verification_context.tools.len() > 0);
+    assert!(!verification_context.tools.is_empty());
 
     let review_context =
         Context::for_review("add-auth", "0001", "Add authentication", "diff content...");
     assert_eq!(review_context.task_kind, "review");
     assert_eq!(review_context.diff_content, "diff content...");
 
-    let resume_context = Context::for_resume(
-        "add-auth",
-        "0001",
-        "Add authentication",
-        "implementation",
-        "phase_2",
-        "step_1",
-        10,
-        0.75,
-        "/trees/0001",
-        "gba/0001-add-auth",
-        "The plan...",
-        true,
-        vec!["Write".to_string()],
-    );
+    let resume_context = Context::for_resume(ResumeContext {
+        feature_name: "add-auth".to_string(),
+        feature_id: "0001".to_string(),
+        feature_description: "Add authentication".to_string(),
+        task_kind: "implementation".to_string(),
+        current_phase: "phase_2".to_string(),
+        current_step: "step_1".to_string(),
+        turns_so_far: 10,
+        cost_so_far: 0.75,
+        worktree_path: "/trees/0001".to_string(),
+        worktree_branch: "gba/0001-add-auth".to_string(),
+        implementation_plan: "The plan...".to_string(),
+        use_preset: true,
+        tools: vec!["Write".to_string()],
+    });
     assert_eq!(resume_context.current_phase, "phase_2");
     assert_eq!(resume_context.turns_so_far, 10);
     assert_eq!(resume_context.cost_so_far, 0.75);
@@ -190,6 +190,10 @@ fn test_should_integration_template_config_serialization() {
         use_preset: false,
         tools: vec!["Read".to_string(), "Write".to_string()],
         max_turns: 150,
+        max_tokens: Some(2048),
+        permission_mode: None,
+        variables: vec![],
+        constraints: vec![],
     };
 
     let yaml = serde_yaml::to_string(&config).expect("Failed to serialize");
@@ -198,6 +202,7 @@ fn test_should_integration_template_config_serialization() {
     assert_eq!(config.system_prompt, deserialized.system_prompt);
     assert_eq!(config.use_preset, deserialized.use_preset);
     assert_eq!(config.tools, deserialized.tools);
+    assert_eq!(config.max_tokens, deserialized.max_tokens);
     assert_eq!(config.max_turns, deserialized.max_turns);
 }
 
@@ -256,6 +261,10 @@ fn test_should_integration_template_registry() {
         use_preset: true,
         tools: vec![],
         max_turns: 100,
+        max_tokens: None,
+        permission_mode: None,
+        variables: vec![],
+        constraints: vec![],
     };
 
     let template1 = PromptTemplate {
@@ -268,6 +277,10 @@ fn test_should_integration_template_registry() {
         use_preset: false,
         tools: vec!["Read".to_string()],
         max_turns: 50,
+        max_tokens: None,
+        permission_mode: None,
+        variables: vec![],
+        constraints: vec![],
     };
 
     let template2 = PromptTemplate {
@@ -293,3 +306,125 @@ fn test_should_integration_template_registry() {
     assert!(list.contains(&"template1"));
     assert!(list.contains(&"template2"));
 }
+
+#[test]
+fn test_should_integration_verify_template_with_command_results() {
+    let prompt_manager =
+        PromptManager::with_local_dir(std::path::PathBuf::from("/nonexistent/templates"), true)
+            .expect("Failed to create prompt manager");
+
+    let mut context =
+        Context::for_verification("add-auth", "0001", "Add authentication", "Summary");
+    context.main_branch = "main".to_string();
+    let prompt = prompt_manager
+        .get_prompt("verify", &context)
+        .expect("Failed to render verify template");
+    assert!(!prompt.contains("## Command Results"));
+
+    context.add_extra(
+        "commands",
+        serde_json::json!([
+            {"name": "test", "command": "cargo test", "success": false, "exitCode": 101,
+             "timedOut": false, "output": "test auth::login ... FAILED"},
+            {"name": "clippy", "command": "cargo clippy", "success": true, "exitCode": 0,
+             "timedOut": false, "output": ""}
+        ]),
+    );
+    let prompt = prompt_manager
+        .get_prompt("verify", &context)
+        .expect("Failed to render verify template");
+    assert!(prompt.contains("## Command Results"));
+    assert!(prompt.contains("### test: FAILED (exit code 101)"));
+    assert!(prompt.contains("test auth::login ... FAILED"));
+    assert!(prompt.contains("### clippy: PASSED"));
+}
+
+#[test]
+fn test_should_integration_local_layout_overrides_bundled() {
+    let dir = std::env::temp_dir().join("gba-test-pm-local-layout");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("layout.jinja2"),
+        "# Task\n\n{{ task }}\n\n# Context\n\n{{ branch }}",
+    )
+    .unwrap();
+
+    let layout = serde_json::json!({
+        "repository_path": "/repo",
+        "branch": "main",
+        "files": [],
+        "outlines": false,
+        "overview": null,
+        "metadata": [],
+        "task": "Add a login page",
+    });
+    let prompt_manager =
+        PromptManager::with_local_dir(dir.clone(), true).expect("Failed to create prompt manager");
+    assert_eq!(
+        prompt_manager.render_layout(&layout).unwrap(),
+        "# Task\n\nAdd a login page\n\n# Context\n\nmain"
+    );
+
+    let bundled = PromptManager::with_local_dir(dir.join("missing"), true)
+        .expect("Failed to create prompt manager");
+    assert!(
+        bundled
+            .render_layout(&layout)
+            .unwrap()
+            .starts_with("\n## Repository Context\n")
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_local_templates_build_on_bundled() {
+    let dir = std::env::temp_dir().join("gba-test-pm-include-bundled");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("partials")).unwrap();
+    std::fs::write(
+        dir.join("partials/rules.jinja2"),
+        "---\ntools: []\n---\nNever push to {{ main_branch }}.",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("plan.jinja2"),
+        "{% include \"bundled/plan\" %}\n{% include \"partials/rules\" %}",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("layout.jinja2"),
+        "{% extends \"bundled/layout\" %}",
+    )
+    .unwrap();
+
+    let prompt_manager =
+        PromptManager::with_local_dir(dir.clone(), false).expect("Failed to create prompt manager");
+    let mut context = Context::new("/repo", "main", "Plan it");
+    context.add_extra("feature_name", serde_json::json!("add-auth"));
+    context.add_extra("main_branch", serde_json::json!("main"));
+    let prompt = prompt_manager.get_prompt("plan", &context).unwrap();
+    assert!(
+        prompt
+            .trim_start()
+            .starts_with("You are creating an implementation plan for the feature: add-auth")
+    );
+    assert!(prompt.ends_with("Never push to main."));
+    assert!(!prompt.contains("systemPrompt"));
+
+    let layout = serde_json::json!({
+        "repository_path": "/repo",
+        "branch": "main",
+        "files": [],
+        "outlines": false,
+        "overview": null,
+        "metadata": [],
+        "task": "Add a login page",
+    });
+    let rendered = prompt_manager.render_layout(&layout).unwrap();
+    assert!(rendered.contains("Branch: main"));
+    assert!(rendered.contains("Add a login page"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
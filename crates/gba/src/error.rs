@@ -0,0 +1,82 @@
+//! Error types for the GBA facade.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Result type alias for the GBA facade.
+pub type Result<T> = std::result::Result<T, GbaError>;
+
+/// Facade error types.
+#[derive(Debug, Error)]
+pub enum GbaError {
+    /// The directory has no `.gba/config.yml`.
+    #[error("Not a GBA project: {0}")]
+    NotGbaProject(PathBuf),
+
+    /// The project configuration is invalid.
+    #[error("Configuration error: {0}")]
+    Config(#[from] gba_core::ConfigError),
+
+    /// No task kind with this name is registered.
+    #[error("Unknown task kind: {0}")]
+    UnknownTaskKind(String),
+
+    /// The feature has no task in progress or failed to resume.
+    #[error("Nothing to resume for feature: {0}")]
+    NothingToResume(String),
+
+    /// The feature has no worktree, e.g. before it is implemented.
+    #[error("Feature has no worktree: {0}")]
+    NoWorktree(String),
+
+    /// The working tree of a run has uncommitted changes.
+    #[error("{count} uncommitted change(s) in {path}; commit or stash them first")]
+    DirtyWorkingTree {
+        /// Path of the working tree.
+        path: PathBuf,
+        /// Number of changed paths.
+        count: usize,
+    },
+
+    /// The working tree of a run could not be checked for uncommitted
+    /// changes.
+    #[error("Could not check {path} for uncommitted changes: {reason}")]
+    WorkingTreeCheckFailed {
+        /// Path of the working tree.
+        path: PathBuf,
+        /// Why the check failed.
+        reason: String,
+    },
+
+    /// Changes stashed before a run could not be restored after it.
+    #[error(
+        "Could not restore stashed changes in {path}: {reason}; they are kept in the stash, restore them with `git stash apply {stash}`"
+    )]
+    StashRestoreFailed {
+        /// Path of the working tree.
+        path: PathBuf,
+        /// Commit SHA of the stash entry.
+        stash: String,
+        /// Why restoring failed.
+        reason: String,
+    },
+
+    /// Error from the prompt manager.
+    #[error("Prompt manager error: {0}")]
+    Prompt(#[from] gba_pm::PromptError),
+
+    /// Error from the core engine.
+    #[error("Core error: {0}")]
+    Core(#[from] gba_core::CoreError),
+
+    /// A GitHub request failed.
+    #[cfg(feature = "github")]
+    #[error("GitHub error: {0}")]
+    GitHub(#[from] crate::github::GitHubError),
+
+    /// The configured webhooks could not be set up.
+    #[cfg(feature = "webhook")]
+    #[error("Webhook error: {0}")]
+    Webhook(#[from] crate::webhook::WebhookError),
+}
@@ -0,0 +1,381 @@
+//! GitHub client posting reviews to pull requests and opening them.
+//!
+//! [`GitHubClient`] fetches the diff of a pull request and creates a review
+//! from a [`PullRequestReview`]: its body and a comment on the line of the
+//! diff each finding cites. Reviews are posted with the `COMMENT` event, so
+//! they never approve or block a pull request on their own.
+//!
+//! [`GitHubClient::create_pull_request`] opens a pull request, as
+//! [`crate::Workspace::open_pull_request`] does for a verified feature.
+//!
+//! Enabled by the `github` feature. The token is read from `GITHUB_TOKEN`;
+//! fetching the diff of a public repository works without one.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba::Workspace;
+//! use gba::core::pull_request::PullRequestReview;
+//! use gba::github::GitHubClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let workspace = Workspace::open("/path/to/project")?;
+//! let review = workspace.review_fanout("add-auth").await?;
+//!
+//! let client = GitHubClient::from_env();
+//! let diff = client.pull_request_diff("acme/app", 42).await?;
+//! let pr_review = PullRequestReview::from_review(&review, &diff);
+//! let url = client.create_review("acme/app", 42, &pr_review).await?;
+//! println!("Posted {url}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use gba_core::pull_request::PullRequestReview;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Environment variable holding the GitHub token.
+pub const TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// User agent sent with every request, required by the GitHub API.
+const USER_AGENT: &str = concat!("gba/", env!("CARGO_PKG_VERSION"));
+
+/// Result type alias for GitHub operations.
+pub type Result<T> = std::result::Result<T, GitHubError>;
+
+/// Error types for GitHub operations.
+#[derive(Debug, Error)]
+pub enum GitHubError {
+    /// A repository is not of the form `owner/name`.
+    #[error("Invalid repository: {0}, expected owner/name")]
+    InvalidRepo(String),
+
+    /// The repository of a remote could not be told from its URL.
+    #[error("Cannot tell the GitHub repository of remote {0}, set github.repo")]
+    UnknownRepo(String),
+
+    /// Creating a review or a pull request requires a token.
+    #[error("Environment variable {TOKEN_ENV} is not set")]
+    MissingToken,
+
+    /// The GitHub API returned an error.
+    #[error("GitHub API error ({status}): {message}")]
+    Api {
+        /// HTTP status of the response.
+        status: u16,
+        /// Error message of the response.
+        message: String,
+    },
+
+    /// The GitHub API could not be reached.
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// Client for the GitHub REST API.
+#[derive(Clone)]
+pub struct GitHubClient {
+    /// HTTP client.
+    http: reqwest::Client,
+    /// Token authenticating requests, if any.
+    token: Option<String>,
+    /// Base URL of the REST API.
+    api_url: String,
+}
+
+impl fmt::Debug for GitHubClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHubClient")
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("api_url", &self.api_url)
+            .finish()
+    }
+}
+
+/// Response of creating a review.
+#[derive(Debug, Deserialize)]
+struct ReviewResponse {
+    html_url: String,
+}
+
+/// A pull request to open, see [`GitHubClient::create_pull_request`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewPullRequest {
+    /// Title of the pull request.
+    pub title: String,
+
+    /// Body of the pull request, in markdown.
+    pub body: String,
+
+    /// Branch with the changes.
+    pub head: String,
+
+    /// Branch the changes are pulled into.
+    pub base: String,
+
+    /// Open the pull request as a draft.
+    pub draft: bool,
+}
+
+/// Response of creating a pull request.
+#[derive(Debug, Deserialize)]
+struct PullRequestResponse {
+    html_url: String,
+}
+
+/// Error response of the API.
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl GitHubClient {
+    /// Create a client, authenticated with a token if given.
+    #[must_use]
+    pub fn new(token: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            token,
+            api_url: "https://api.github.com".to_string(),
+        }
+    }
+
+    /// Create a client authenticated with `GITHUB_TOKEN`, if it is set.
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self::new(std::env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty()))
+    }
+
+    /// Set the base URL of the REST API, e.g. for GitHub Enterprise.
+    #[must_use]
+    pub fn with_api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Fetch the unified diff of a pull request.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo` - Repository, e.g. `"acme/app"`.
+    /// * `number` - Pull request number.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository is invalid or the API cannot be
+    /// reached or rejects the request.
+    pub async fn pull_request_diff(&self, repo: &str, number: u64) -> Result<String> {
+        let request = self
+            .request(reqwest::Method::GET, &pull_url(repo, number)?)
+            .header(reqwest::header::ACCEPT, "application/vnd.github.diff");
+        let response = check(request.send().await?).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Create a review on a pull request.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo` - Repository, e.g. `"acme/app"`.
+    /// * `number` - Pull request number.
+    /// * `review` - Review to post.
+    ///
+    /// # Returns
+    ///
+    /// The URL of the posted review.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no token is set, the repository is invalid, or the
+    /// API cannot be reached or rejects the review, e.g. when a comment is
+    /// outside the diff.
+    pub async fn create_review(
+        &self,
+        repo: &str,
+        number: u64,
+        review: &PullRequestReview,
+    ) -> Result<String> {
+        if self.token.is_none() {
+            return Err(GitHubError::MissingToken);
+        }
+        let comments = review
+            .comments
+            .iter()
+            .map(|comment| {
+                serde_json::json!({
+                    "path": comment.path,
+                    "line": comment.line,
+                    "side": "RIGHT",
+                    "body": comment.body,
+                })
+            })
+            .collect::<Vec<_>>();
+        let body = serde_json::json!({
+            "event": "COMMENT",
+            "body": review.body,
+            "comments": comments,
+        });
+
+        let url = format!("{}/reviews", pull_url(repo, number)?);
+        let request = self
+            .request(reqwest::Method::POST, &url)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .json(&body);
+        let response: ReviewResponse = check(request.send().await?).await?.json().await?;
+        Ok(response.html_url)
+    }
+
+    /// Open a pull request.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo` - Repository, e.g. `"acme/app"`.
+    /// * `pull_request` - Pull request to open.
+    ///
+    /// # Returns
+    ///
+    /// The URL of the pull request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no token is set, the repository is invalid, or the
+    /// API cannot be reached or rejects the pull request, e.g. when one is
+    /// already open for the branch.
+    pub async fn create_pull_request(
+        &self,
+        repo: &str,
+        pull_request: &NewPullRequest,
+    ) -> Result<String> {
+        if self.token.is_none() {
+            return Err(GitHubError::MissingToken);
+        }
+        let body = serde_json::json!({
+            "title": pull_request.title,
+            "body": pull_request.body,
+            "head": pull_request.head,
+            "base": pull_request.base,
+            "draft": pull_request.draft,
+        });
+
+        let request = self
+            .request(reqwest::Method::POST, &pulls_url(repo)?)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .json(&body);
+        let response: PullRequestResponse = check(request.send().await?).await?.json().await?;
+        Ok(response.html_url)
+    }
+
+    /// Start a request to a path of the API.
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{path}", self.api_url))
+            .header(reqwest::header::USER_AGENT, USER_AGENT);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+/// Get the repository, `owner/name`, of a GitHub remote URL.
+///
+/// Accepts HTTPS (`https://github.com/acme/app.git`) and SSH
+/// (`git@github.com:acme/app.git`) URLs.
+#[must_use]
+pub fn repo_from_url(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let path = url
+        .split_once("github.com")
+        .map(|(_, path)| path.trim_start_matches([':', '/']))?;
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, name) = path.split_once('/')?;
+    (!owner.is_empty() && !name.is_empty() && !name.contains('/'))
+        .then(|| format!("{owner}/{name}"))
+}
+
+/// Get the API path of a pull request.
+fn pull_url(repo: &str, number: u64) -> Result<String> {
+    Ok(format!("{}/{number}", pulls_url(repo)?))
+}
+
+/// Get the API path of the pull requests of a repository.
+fn pulls_url(repo: &str) -> Result<String> {
+    let valid = repo
+        .split_once('/')
+        .is_some_and(|(owner, name)| !owner.is_empty() && !name.is_empty() && !name.contains('/'));
+    if !valid {
+        return Err(GitHubError::InvalidRepo(repo.to_string()));
+    }
+    Ok(format!("/repos/{repo}/pulls"))
+}
+
+/// Turn an error response into an error.
+async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<ErrorResponse>(&text)
+        .map(|error| error.message)
+        .unwrap_or(text);
+    Err(GitHubError::Api {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_from_url() {
+        for url in [
+            "https://github.com/acme/app.git",
+            "https://github.com/acme/app",
+            "git@github.com:acme/app.git",
+            "ssh://git@github.com/acme/app/",
+        ] {
+            assert_eq!(repo_from_url(url).as_deref(), Some("acme/app"), "{url}");
+        }
+        assert_eq!(repo_from_url("https://gitlab.com/acme/app.git"), None);
+        assert_eq!(repo_from_url("https://github.com/acme"), None);
+    }
+
+    #[test]
+    fn test_pull_url() {
+        assert_eq!(
+            pull_url("acme/app", 42).unwrap(),
+            "/repos/acme/app/pulls/42"
+        );
+        assert!(matches!(
+            pull_url("acme", 42),
+            Err(GitHubError::InvalidRepo(_))
+        ));
+    }
+
+    #[test]
+    fn test_writes_require_token() {
+        let client = GitHubClient::new(None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let result =
+            runtime.block_on(client.create_review("acme/app", 42, &PullRequestReview::default()));
+        assert!(matches!(result, Err(GitHubError::MissingToken)));
+        let result =
+            runtime.block_on(client.create_pull_request("acme/app", &NewPullRequest::default()));
+        assert!(matches!(result, Err(GitHubError::MissingToken)));
+    }
+
+    #[test]
+    fn test_debug_redacts_token() {
+        let client = GitHubClient::new(Some("secret".to_string()));
+        assert!(!format!("{client:?}").contains("secret"));
+    }
+}
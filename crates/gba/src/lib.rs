@@ -0,0 +1,53 @@
+//! GBA - High-level facade for embedding the GBA workflow.
+//!
+//! [`Workspace`] wires the project configuration, prompt manager, context
+//! builder, agent and feature state together, so a feature can be planned,
+//! implemented and reviewed in a few calls:
+//!
+//! ```no_run
+//! use gba::Workspace;
+//!
+//! # async fn example() -> gba::Result<()> {
+//! let workspace = Workspace::open("/path/to/project")?;
+//! workspace.plan("add-auth", "Add an authentication system").await?;
+//! workspace.implement("add-auth").await?;
+//! let review = workspace.review("add-auth").await?;
+//! println!("{}", review.content);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Pipeline`] chains planning, implementation and verification to run a
+//! feature through its whole lifecycle.
+//!
+//! With the `slack` feature, [`slack::SlackBot`] serves a Slack app that
+//! starts runs from slash commands and mentions and reports them in a thread.
+//!
+//! With the `github` feature, [`github::GitHubClient`] posts the findings of
+//! a review as comments on a GitHub pull request.
+//!
+//! Runs publish [`core::events`] as they start, change phase, call tools and
+//! finish; [`Workspace::with_event_sink`] subscribes to them. With the
+//! `webhook` feature, the events are posted to the project's configured
+//! `webhooks`, see [`webhook::WebhookSink`].
+//!
+//! The underlying crates are re-exported as [`core`] and [`pm`] for finer
+//! control.
+
+#![warn(rust_2024_compatibility, missing_docs, missing_debug_implementations)]
+
+pub mod error;
+#[cfg(feature = "github")]
+pub mod github;
+pub mod pipeline;
+#[cfg(feature = "slack")]
+pub mod slack;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+pub mod workspace;
+
+pub use error::{GbaError, Result};
+pub use gba_core as core;
+pub use gba_pm as pm;
+pub use pipeline::{Pipeline, PipelineOutcome};
+pub use workspace::{FixLoopOutcome, FixLoopStatus, Phase, Run, RunPreview, Workspace};
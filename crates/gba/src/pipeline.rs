@@ -0,0 +1,207 @@
+//! Pipeline running a feature through its whole lifecycle.
+//!
+//! A [`Pipeline`] plans a feature, implements the plan in the feature's
+//! worktree and verifies the result, each phase starting only if the
+//! previous one succeeded. The phases run like [`Workspace::plan`],
+//! [`Workspace::implement`] and [`Workspace::verify`], so the plan is saved
+//! to `plan.md` and the worktree is created before implementing; each phase
+//! is also recorded in the feature's `state.yml` under `pipeline`.
+//!
+//! With `github.createPullRequest` and the `github` feature, a pipeline whose
+//! verification passed then opens a pull request for the feature's worktree
+//! branch, see [`Workspace::open_pull_request`].
+
+use std::future::Future;
+use std::path::Path;
+
+use gba_core::verify::VerificationReport;
+use gba_core::{CoreError, FeatureState, Response, feature};
+use tracing::info;
+
+use crate::error::Result;
+use crate::workspace::{Phase, Workspace};
+
+/// Name of the verification phase in the feature state.
+const VERIFICATION: &str = "verification";
+
+/// Name of the pull request step in the feature state.
+#[cfg(feature = "github")]
+const PULL_REQUEST: &str = "pullRequest";
+
+/// Outcome of a pipeline, see [`Pipeline::run`].
+#[derive(Debug, Clone)]
+pub struct PipelineOutcome {
+    /// Response of the planning phase.
+    pub plan: Response,
+
+    /// Response of the implementation phase.
+    pub implementation: Response,
+
+    /// Report of the verification phase.
+    pub verification: VerificationReport,
+
+    /// URL of the pull request opened for the feature, if any.
+    pub pull_request_url: Option<String>,
+
+    /// Cost of the pipeline in USD.
+    pub cost_usd: f64,
+}
+
+/// Runs the planning, implementation and verification phases of a feature
+/// one after the other.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gba::{Pipeline, Workspace};
+///
+/// # async fn example() -> gba::Result<()> {
+/// let workspace = Workspace::open("/path/to/project")?;
+/// let outcome = Pipeline::new(&workspace)
+///     .with_fix_loop(true)
+///     .run("add-auth", "Add an authentication system")
+///     .await?;
+/// println!("Passed: {}", outcome.verification.passed());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Pipeline<'a> {
+    workspace: &'a Workspace,
+    fix_loop: bool,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Create a pipeline running in a workspace.
+    #[must_use]
+    pub const fn new(workspace: &'a Workspace) -> Self {
+        Self {
+            workspace,
+            fix_loop: false,
+        }
+    }
+
+    /// Verify with a fix loop instead of a single verification, see
+    /// [`Workspace::fix_loop`].
+    #[must_use]
+    pub const fn with_fix_loop(mut self, fix_loop: bool) -> Self {
+        self.fix_loop = fix_loop;
+        self
+    }
+
+    /// Plan, implement and verify a feature.
+    ///
+    /// The steps of a previous pipeline of the feature are cleared first.
+    /// Failing verification commands don't fail the pipeline: see
+    /// [`VerificationReport::passed`]. A pull request is only opened once
+    /// verification passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first phase that failed, after recording it,
+    /// or an error if the feature state cannot be saved.
+    pub async fn run(&self, feature: &str, description: &str) -> Result<PipelineOutcome> {
+        let workspace = self.workspace;
+        let feature_id = feature::feature_id(feature);
+        let state_path = workspace.state_path(&feature_id);
+        let mut state = FeatureState::load_or_new(&state_path, feature, &feature_id)
+            .map_err(CoreError::from)?;
+        let start_cost = state.execution.cost.total_cost_usd;
+        state.pipeline.clear();
+        state.save(&state_path).map_err(CoreError::from)?;
+
+        let plan = step(
+            &state_path,
+            &Phase::Planning.to_string(),
+            workspace.plan(feature, description),
+        )
+        .await?;
+        let implementation = step(
+            &state_path,
+            &Phase::Implementation.to_string(),
+            workspace.implement(feature),
+        )
+        .await?;
+        let verification = if self.fix_loop {
+            step(&state_path, VERIFICATION, async {
+                Ok(workspace.fix_loop(feature).await?.report)
+            })
+            .await?
+        } else {
+            step(&state_path, VERIFICATION, workspace.verify(feature)).await?
+        };
+        let pull_request_url = if verification.passed() {
+            self.open_pull_request(&state_path, feature, &implementation)
+                .await?
+        } else {
+            None
+        };
+
+        let state = FeatureState::load(&state_path).map_err(CoreError::from)?;
+        let cost_usd = state.execution.cost.total_cost_usd - start_cost;
+        info!(
+            "Pipeline of {} finished, verification passed: {}",
+            feature,
+            verification.passed()
+        );
+        Ok(PipelineOutcome {
+            plan,
+            implementation,
+            verification,
+            pull_request_url,
+            cost_usd,
+        })
+    }
+
+    /// Open a pull request for a verified feature if the project asks for
+    /// one.
+    #[cfg(feature = "github")]
+    async fn open_pull_request(
+        &self,
+        state_path: &Path,
+        feature: &str,
+        implementation: &Response,
+    ) -> Result<Option<String>> {
+        if !self.workspace.config().github.create_pull_request {
+            return Ok(None);
+        }
+        let url = step(
+            state_path,
+            PULL_REQUEST,
+            self.workspace
+                .open_pull_request(feature, &implementation.content),
+        )
+        .await?;
+        Ok(Some(url))
+    }
+
+    /// Open a pull request for a verified feature if the project asks for
+    /// one.
+    #[cfg(not(feature = "github"))]
+    async fn open_pull_request(
+        &self,
+        _state_path: &Path,
+        _feature: &str,
+        _implementation: &Response,
+    ) -> Result<Option<String>> {
+        if self.workspace.config().github.create_pull_request {
+            tracing::warn!("Not opening a pull request: gba is built without the github feature");
+        }
+        Ok(None)
+    }
+}
+
+/// Run a phase of a pipeline and record it in the feature state.
+async fn step<T>(
+    state_path: &Path,
+    phase: &str,
+    run: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let before = FeatureState::load(state_path).map_err(CoreError::from)?;
+    let result = run.await;
+
+    let mut state = FeatureState::load(state_path).map_err(CoreError::from)?;
+    state.record_pipeline_step(phase, &before, result.is_ok());
+    state.save(state_path).map_err(CoreError::from)?;
+    result
+}
@@ -0,0 +1,785 @@
+//! Slack bot for triggering and reporting runs.
+//!
+//! [`SlackBot`] serves two endpoints for a Slack app:
+//!
+//! - `POST /slack/commands` for slash commands, e.g.
+//!   `/gba plan add-auth Add an authentication system`
+//! - `POST /slack/events` for the Events API, answering the URL verification
+//!   challenge and `app_mention` events such as
+//!   `@gba plan add-auth Add an authentication system`
+//!
+//! Every request is verified against the app's signing secret. A command is
+//! acknowledged right away and runs in the background: the bot starts a
+//! thread in the channel, posts progress updates to it while the agent works,
+//! and finishes with the outcome, a summary of the plan and the run's cost.
+//!
+//! Enabled by the `slack` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use gba::Workspace;
+//! use gba::slack::{SlackBot, SlackConfig};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let workspace = Workspace::open("/path/to/project")?;
+//! let bot = SlackBot::new(workspace, SlackConfig::from_env()?);
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+//! axum::serve(listener, bot.router()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response as HttpResponse};
+use axum::routing::{get, post};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::workspace::Workspace;
+
+/// Environment variable holding the app's signing secret.
+pub const SIGNING_SECRET_ENV: &str = "SLACK_SIGNING_SECRET";
+
+/// Environment variable holding the bot's OAuth token.
+pub const BOT_TOKEN_ENV: &str = "SLACK_BOT_TOKEN";
+
+/// Requests older than this are rejected, to prevent replays.
+const MAX_REQUEST_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Maximum length of the plan summary posted when a run completes.
+const SUMMARY_CHARS: usize = 1_500;
+
+/// Result type alias for Slack operations.
+pub type Result<T> = std::result::Result<T, SlackError>;
+
+/// Error types for Slack operations.
+#[derive(Debug, Error)]
+pub enum SlackError {
+    /// A required environment variable is not set.
+    #[error("Environment variable {0} is not set")]
+    MissingEnv(&'static str),
+
+    /// A request is missing a signature header.
+    #[error("Missing header: {0}")]
+    MissingHeader(&'static str),
+
+    /// A request's signature doesn't match its body.
+    #[error("Invalid request signature")]
+    InvalidSignature,
+
+    /// A request's timestamp is too old or malformed.
+    #[error("Stale or invalid request timestamp")]
+    StaleRequest,
+
+    /// A request body could not be parsed.
+    #[error("Invalid payload: {0}")]
+    InvalidPayload(String),
+
+    /// The Slack API returned an error.
+    #[error("Slack API error: {0}")]
+    Api(String),
+
+    /// The Slack API could not be reached.
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// Slack app credentials and bot settings.
+#[derive(Clone)]
+pub struct SlackConfig {
+    /// Signing secret used to verify requests from Slack.
+    signing_secret: String,
+    /// Bot token used to post messages.
+    bot_token: String,
+    /// Interval between progress updates while a run is going.
+    progress_interval: Duration,
+    /// Base URL of the Slack Web API.
+    api_url: String,
+}
+
+impl fmt::Debug for SlackConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlackConfig")
+            .field("signing_secret", &"<redacted>")
+            .field("bot_token", &"<redacted>")
+            .field("progress_interval", &self.progress_interval)
+            .field("api_url", &self.api_url)
+            .finish()
+    }
+}
+
+impl SlackConfig {
+    /// Create a configuration from the app's credentials.
+    #[must_use]
+    pub fn new(signing_secret: impl Into<String>, bot_token: impl Into<String>) -> Self {
+        Self {
+            signing_secret: signing_secret.into(),
+            bot_token: bot_token.into(),
+            progress_interval: Duration::from_secs(60),
+            api_url: "https://slack.com/api".to_string(),
+        }
+    }
+
+    /// Read the credentials from `SLACK_SIGNING_SECRET` and `SLACK_BOT_TOKEN`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either variable is not set.
+    pub fn from_env() -> Result<Self> {
+        let signing_secret = std::env::var(SIGNING_SECRET_ENV)
+            .map_err(|_| SlackError::MissingEnv(SIGNING_SECRET_ENV))?;
+        let bot_token =
+            std::env::var(BOT_TOKEN_ENV).map_err(|_| SlackError::MissingEnv(BOT_TOKEN_ENV))?;
+        Ok(Self::new(signing_secret, bot_token))
+    }
+
+    /// Set the interval between progress updates. Defaults to a minute.
+    #[must_use]
+    pub const fn with_progress_interval(mut self, interval: Duration) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Set the base URL of the Slack Web API, e.g. for a proxy.
+    #[must_use]
+    pub fn with_api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = url.into();
+        self
+    }
+}
+
+/// Verify the signature of a request from Slack.
+///
+/// Slack signs `v0:<timestamp>:<body>` with the app's signing secret using
+/// HMAC-SHA256 and sends it as `v0=<hex digest>`.
+///
+/// # Arguments
+///
+/// * `signing_secret` - The app's signing secret.
+/// * `timestamp` - The `X-Slack-Request-Timestamp` header.
+/// * `body` - The raw request body.
+/// * `signature` - The `X-Slack-Signature` header.
+/// * `now` - Current time, to reject requests older than five minutes.
+///
+/// # Errors
+///
+/// Returns an error if the request is stale or the signature doesn't match.
+pub fn verify_signature(
+    signing_secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    now: SystemTime,
+) -> Result<()> {
+    let sent = timestamp
+        .parse::<u64>()
+        .map_err(|_| SlackError::StaleRequest)?;
+    let now = now
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| SlackError::StaleRequest)?
+        .as_secs();
+    if now.abs_diff(sent) > MAX_REQUEST_AGE.as_secs() {
+        return Err(SlackError::StaleRequest);
+    }
+
+    let expected = signature
+        .strip_prefix("v0=")
+        .and_then(|digest| hex::decode(digest).ok())
+        .ok_or(SlackError::InvalidSignature)?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes())
+        .map_err(|_| SlackError::InvalidSignature)?;
+    mac.update(format!("v0:{timestamp}:").as_bytes());
+    mac.update(body);
+    let actual = mac.finalize().into_bytes();
+
+    if bool::from(actual.as_slice().ct_eq(&expected)) {
+        Ok(())
+    } else {
+        Err(SlackError::InvalidSignature)
+    }
+}
+
+/// A command for the bot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlackCommand {
+    /// Plan a feature.
+    Plan {
+        /// Feature name.
+        feature: String,
+        /// Feature description.
+        description: String,
+    },
+
+    /// Show usage.
+    Help,
+}
+
+impl SlackCommand {
+    /// Parse the text of a slash command or mention.
+    ///
+    /// User mentions such as `<@U123>` are ignored, so
+    /// `@gba plan add-auth Add auth` parses like `plan add-auth Add auth`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the expected usage if the text is not a
+    /// valid command.
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let mut words = text
+            .split_whitespace()
+            .filter(|word| !(word.starts_with("<@") && word.ends_with('>')));
+        match words.next() {
+            None | Some("help") => Ok(Self::Help),
+            Some("plan") => {
+                let feature = words
+                    .next()
+                    .ok_or_else(|| "Usage: plan <feature> [description]".to_string())?;
+                Ok(Self::Plan {
+                    feature: feature.to_string(),
+                    description: words.collect::<Vec<_>>().join(" "),
+                })
+            }
+            Some(other) => Err(format!("Unknown command `{other}`. {USAGE}")),
+        }
+    }
+}
+
+/// Usage shown for `help` and unknown commands.
+const USAGE: &str = "Usage: `plan <feature> [description]` to plan a feature.";
+
+/// A slash command request, as sent by Slack.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlashCommandRequest {
+    /// The command, e.g. `/gba`.
+    pub command: String,
+    /// Text after the command.
+    #[serde(default)]
+    pub text: String,
+    /// Channel the command was sent in.
+    pub channel_id: String,
+    /// User who sent the command.
+    pub user_id: String,
+}
+
+/// An Events API request, as sent by Slack.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventRequest {
+    /// Sent once when the events URL is configured.
+    UrlVerification {
+        /// Challenge to echo back.
+        challenge: String,
+    },
+
+    /// An event the app subscribed to.
+    EventCallback {
+        /// The event.
+        event: Event,
+    },
+}
+
+/// An event from the Events API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Event {
+    /// Event type, e.g. `app_mention`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Message text.
+    #[serde(default)]
+    pub text: String,
+    /// Channel of the message.
+    #[serde(default)]
+    pub channel: String,
+    /// User who sent the message.
+    #[serde(default)]
+    pub user: String,
+    /// Timestamp of the message.
+    #[serde(default)]
+    pub ts: String,
+    /// Thread the message was sent in, if any.
+    #[serde(default)]
+    pub thread_ts: Option<String>,
+}
+
+/// Client for the Slack Web API.
+#[derive(Debug, Clone)]
+pub struct SlackClient {
+    /// HTTP client.
+    http: reqwest::Client,
+    /// Bot credentials and API URL.
+    config: SlackConfig,
+}
+
+/// Response of `chat.postMessage`.
+#[derive(Debug, Deserialize)]
+struct PostMessageResponse {
+    ok: bool,
+    #[serde(default)]
+    ts: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl SlackClient {
+    /// Create a client posting as the configured bot.
+    #[must_use]
+    pub fn new(config: SlackConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// Post a message to a channel, optionally in a thread.
+    ///
+    /// # Returns
+    ///
+    /// The timestamp of the posted message, which identifies its thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API cannot be reached or rejects the message.
+    pub async fn post_message(
+        &self,
+        channel: &str,
+        thread_ts: Option<&str>,
+        text: &str,
+    ) -> Result<String> {
+        let mut body = serde_json::json!({ "channel": channel, "text": text });
+        if let Some(thread_ts) = thread_ts {
+            body["thread_ts"] = thread_ts.into();
+        }
+
+        let response: PostMessageResponse = self
+            .http
+            .post(format!("{}/chat.postMessage", self.config.api_url))
+            .bearer_auth(&self.config.bot_token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !response.ok {
+            return Err(SlackError::Api(
+                response
+                    .error
+                    .unwrap_or_else(|| "unknown error".to_string()),
+            ));
+        }
+        response
+            .ts
+            .ok_or_else(|| SlackError::Api("missing message timestamp".to_string()))
+    }
+}
+
+/// Slack bot running the workflow of a workspace.
+#[derive(Debug, Clone)]
+pub struct SlackBot {
+    /// Shared bot state.
+    inner: Arc<BotInner>,
+}
+
+/// State shared by the bot's handlers and runs.
+#[derive(Debug)]
+struct BotInner {
+    /// Workspace runs are started in.
+    workspace: Workspace,
+    /// Client posting replies.
+    client: SlackClient,
+    /// Bot settings.
+    config: SlackConfig,
+}
+
+impl SlackBot {
+    /// Create a bot for a workspace.
+    #[must_use]
+    pub fn new(workspace: Workspace, config: SlackConfig) -> Self {
+        Self {
+            inner: Arc::new(BotInner {
+                workspace,
+                client: SlackClient::new(config.clone()),
+                config,
+            }),
+        }
+    }
+
+    /// Build the router serving `/slack/commands` and `/slack/events`, and
+    /// the workspace's task metrics at `/metrics`, see
+    /// [`Workspace::metrics`].
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/slack/commands", post(handle_command))
+            .route("/slack/events", post(handle_event))
+            .route("/metrics", get(handle_metrics))
+            .with_state(self.clone())
+    }
+
+    /// Verify that a request comes from Slack.
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<()> {
+        let header = |name: &'static str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(SlackError::MissingHeader(name))
+        };
+        verify_signature(
+            &self.inner.config.signing_secret,
+            header("x-slack-request-timestamp")?,
+            body,
+            header("x-slack-signature")?,
+            SystemTime::now(),
+        )
+    }
+
+    /// Start a command in the background.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ts` - Thread to reply in; a new thread is started otherwise.
+    fn spawn(
+        &self,
+        command: SlackCommand,
+        channel: String,
+        user: String,
+        thread_ts: Option<String>,
+    ) {
+        let SlackCommand::Plan {
+            feature,
+            description,
+        } = command
+        else {
+            return;
+        };
+
+        let bot = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bot
+                .run_plan(&feature, &description, &channel, &user, thread_ts)
+                .await
+            {
+                warn!("Failed to report planning of {} to Slack: {}", feature, e);
+            }
+        });
+    }
+
+    /// Plan a feature, reporting progress and the outcome in a thread.
+    async fn run_plan(
+        &self,
+        feature: &str,
+        description: &str,
+        channel: &str,
+        user: &str,
+        thread_ts: Option<String>,
+    ) -> Result<()> {
+        let client = &self.inner.client;
+        let started = format!("Planning `{feature}` for <@{user}>...");
+        let thread = match thread_ts {
+            Some(thread) => {
+                client
+                    .post_message(channel, Some(&thread), &started)
+                    .await?;
+                thread
+            }
+            None => client.post_message(channel, None, &started).await?,
+        };
+        info!("Planning {} from Slack (thread {})", feature, thread);
+
+        let workspace = &self.inner.workspace;
+        let plan = workspace.plan(feature, description);
+        tokio::pin!(plan);
+        let start = Instant::now();
+        let mut progress = tokio::time::interval(self.inner.config.progress_interval);
+        progress.tick().await;
+        let result = loop {
+            tokio::select! {
+                result = &mut plan => break result,
+                _ = progress.tick() => {
+                    let text = format!(
+                        "Still planning `{feature}` ({} elapsed)",
+                        format_elapsed(start.elapsed())
+                    );
+                    if let Err(e) = client.post_message(channel, Some(&thread), &text).await {
+                        debug!("Failed to post progress to Slack: {}", e);
+                    }
+                }
+            }
+        };
+
+        let text = match result {
+            Ok(response) => {
+                let run_id = workspace
+                    .feature_state(feature)
+                    .ok()
+                    .flatten()
+                    .and_then(|state| state.execution.run_id)
+                    .unwrap_or_default();
+                let blocked = response
+                    .violations
+                    .iter()
+                    .map(|violation| format!("\n:no_entry: {violation}"))
+                    .collect::<String>();
+                format!(
+                    ":white_check_mark: Planned `{feature}` in {} (run `{run_id}`)\n\
+                     Cost: ${:.4} ({} input / {} output tokens){blocked}\n\n{}",
+                    format_elapsed(start.elapsed()),
+                    response.usage.total_cost_usd,
+                    response.usage.input_tokens,
+                    response.usage.output_tokens,
+                    summarize(&response.content, SUMMARY_CHARS),
+                )
+            }
+            Err(e) => format!(
+                ":x: Planning `{feature}` failed after {}: {e}",
+                format_elapsed(start.elapsed())
+            ),
+        };
+        client.post_message(channel, Some(&thread), &text).await?;
+        Ok(())
+    }
+}
+
+/// Handle a slash command.
+async fn handle_command(
+    State(bot): State<SlackBot>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> HttpResponse {
+    if let Err(e) = bot.verify(&headers, &body) {
+        return reject(&e);
+    }
+    let request: SlashCommandRequest = match serde_urlencoded::from_bytes(&body) {
+        Ok(request) => request,
+        Err(e) => return reject(&SlackError::InvalidPayload(e.to_string())),
+    };
+    debug!("Slack command {} {}", request.command, request.text);
+
+    // The reply is only visible to the user; progress goes to the channel
+    match SlackCommand::parse(&request.text) {
+        Ok(SlackCommand::Help) => USAGE.into_response(),
+        Ok(command @ SlackCommand::Plan { .. }) => {
+            let reply = acknowledgement(&command);
+            bot.spawn(command, request.channel_id, request.user_id, None);
+            reply.into_response()
+        }
+        Err(usage) => usage.into_response(),
+    }
+}
+
+/// Handle an Events API request.
+async fn handle_event(
+    State(bot): State<SlackBot>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> HttpResponse {
+    if let Err(e) = bot.verify(&headers, &body) {
+        return reject(&e);
+    }
+    let request: EventRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return reject(&SlackError::InvalidPayload(e.to_string())),
+    };
+
+    match request {
+        EventRequest::UrlVerification { challenge } => challenge.into_response(),
+        EventRequest::EventCallback { event } if event.kind == "app_mention" => {
+            // Replies go to the mention's thread, or start one under it
+            let thread = event.thread_ts.clone().unwrap_or(event.ts.clone());
+            let reply = match SlackCommand::parse(&event.text) {
+                Ok(command @ SlackCommand::Plan { .. }) => {
+                    bot.spawn(command, event.channel, event.user, Some(thread));
+                    return StatusCode::OK.into_response();
+                }
+                Ok(SlackCommand::Help) => USAGE.to_string(),
+                Err(usage) => usage,
+            };
+            tokio::spawn(async move {
+                let client = &bot.inner.client;
+                if let Err(e) = client
+                    .post_message(&event.channel, Some(&thread), &reply)
+                    .await
+                {
+                    warn!("Failed to reply on Slack: {}", e);
+                }
+            });
+            StatusCode::OK.into_response()
+        }
+        EventRequest::EventCallback { event } => {
+            debug!("Ignoring Slack event {}", event.kind);
+            StatusCode::OK.into_response()
+        }
+    }
+}
+
+/// Serve the workspace's task metrics in the Prometheus text format.
+async fn handle_metrics(State(bot): State<SlackBot>) -> HttpResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        bot.inner.workspace.metrics().render_prometheus(),
+    )
+        .into_response()
+}
+
+/// Reject a request that failed verification or parsing.
+fn reject(error: &SlackError) -> HttpResponse {
+    warn!("Rejected Slack request: {}", error);
+    let status = match error {
+        SlackError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::UNAUTHORIZED,
+    };
+    (status, error.to_string()).into_response()
+}
+
+/// Reply acknowledging a command that was started.
+fn acknowledgement(command: &SlackCommand) -> String {
+    match command {
+        SlackCommand::Plan { feature, .. } => {
+            format!("Planning `{feature}`; progress will be posted in this channel.")
+        }
+        SlackCommand::Help => USAGE.to_string(),
+    }
+}
+
+/// Format an elapsed duration as `1m 05s`.
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Shorten text to at most `max_chars` characters, cutting at a line break
+/// where possible.
+fn summarize(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else {
+        return text.to_string();
+    };
+    let head = &text[..cut];
+    let head = head.rfind('\n').map_or(head, |line| &head[..line]);
+    format!("{}\n...", head.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:{timestamp}:").as_bytes());
+        mac.update(body);
+        format!("v0={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let body = b"command=%2Fgba&text=plan+add-auth";
+        let signature = sign("secret", "1700000000", body);
+
+        assert!(verify_signature("secret", "1700000000", body, &signature, now).is_ok());
+        assert!(matches!(
+            verify_signature("other", "1700000000", body, &signature, now),
+            Err(SlackError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify_signature("secret", "1700000000", b"tampered", &signature, now),
+            Err(SlackError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify_signature("secret", "1700000000", body, "v0=zz", now),
+            Err(SlackError::InvalidSignature)
+        ));
+
+        let later = now + Duration::from_secs(301);
+        assert!(matches!(
+            verify_signature("secret", "1700000000", body, &signature, later),
+            Err(SlackError::StaleRequest)
+        ));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            SlackCommand::parse("plan add-auth Add an authentication system"),
+            Ok(SlackCommand::Plan {
+                feature: "add-auth".to_string(),
+                description: "Add an authentication system".to_string(),
+            })
+        );
+        assert_eq!(
+            SlackCommand::parse("<@U123> plan add-auth"),
+            Ok(SlackCommand::Plan {
+                feature: "add-auth".to_string(),
+                description: String::new(),
+            })
+        );
+        assert_eq!(SlackCommand::parse(""), Ok(SlackCommand::Help));
+        assert_eq!(SlackCommand::parse("<@U123> help"), Ok(SlackCommand::Help));
+        assert!(SlackCommand::parse("plan").is_err());
+        assert!(SlackCommand::parse("deploy").is_err());
+    }
+
+    #[test]
+    fn test_parse_event_request() {
+        let verification: EventRequest =
+            serde_json::from_str(r#"{"type":"url_verification","challenge":"abc"}"#).unwrap();
+        assert!(matches!(
+            verification,
+            EventRequest::UrlVerification { challenge } if challenge == "abc"
+        ));
+
+        let mention: EventRequest = serde_json::from_str(
+            r#"{"type":"event_callback","event":{"type":"app_mention","text":"<@U1> plan x",
+                "channel":"C1","user":"U2","ts":"1.2"}}"#,
+        )
+        .unwrap();
+        let EventRequest::EventCallback { event } = mention else {
+            panic!("expected an event callback");
+        };
+        assert_eq!(event.kind, "app_mention");
+        assert_eq!(event.channel, "C1");
+        assert!(event.thread_ts.is_none());
+    }
+
+    #[test]
+    fn test_parse_slash_command_request() {
+        let request: SlashCommandRequest = serde_urlencoded::from_bytes(
+            b"command=%2Fgba&text=plan+add-auth&channel_id=C1&user_id=U1&team_id=T1",
+        )
+        .unwrap();
+        assert_eq!(request.command, "/gba");
+        assert_eq!(request.text, "plan add-auth");
+        assert_eq!(request.channel_id, "C1");
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize("  short  ", 100), "short");
+        assert_eq!(summarize("line one\nline two", 12), "line one\n...");
+        assert_eq!(format_elapsed(Duration::from_secs(65)), "1m 05s");
+        assert_eq!(format_elapsed(Duration::from_secs(9)), "9s");
+    }
+
+    #[test]
+    fn test_config_debug_redacts_secrets() {
+        let config = SlackConfig::new("signing-secret", "xoxb-token");
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("signing-secret"));
+        assert!(!debug.contains("xoxb-token"));
+    }
+}
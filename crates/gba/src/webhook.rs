@@ -0,0 +1,478 @@
+//! Webhook sink posting task events to HTTP endpoints.
+//!
+//! [`WebhookSink`] posts each [`TaskEvent`] as JSON to the endpoints listed
+//! under `webhooks` in `.gba/config.yml`, so a dashboard can follow runs
+//! without the Slack bot. A request carries the event type in `X-GBA-Event`
+//! and, when the endpoint has a secret, the HMAC-SHA256 of its body in
+//! `X-GBA-Signature-256` as `sha256=<hex>`.
+//!
+//! Events are delivered in order by a background task on the current tokio
+//! runtime. Failed deliveries, i.e. network errors, `429` and `5xx`
+//! responses, are retried with exponential backoff; an event that still
+//! fails is logged and dropped, so an unreachable dashboard never fails a
+//! run.
+//!
+//! Enabled by the `webhook` feature. [`crate::Workspace::open`] installs the
+//! sink when the project configures webhooks.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use gba::Workspace;
+//! use gba::core::WebhookConfig;
+//! use gba::webhook::WebhookSink;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let sink = Arc::new(WebhookSink::new(&[WebhookConfig {
+//!     url: "https://dashboard.example.com/gba".to_string(),
+//!     secret_env: Some("GBA_WEBHOOK_SECRET".to_string()),
+//!     events: vec!["finished".to_string()],
+//!     max_retries: 3,
+//! }])?);
+//! let workspace = Workspace::open("/path/to/project")?.with_event_sink(sink.clone());
+//! workspace.plan("add-auth", "Add an authentication system").await?;
+//! sink.flush().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use gba_core::WebhookConfig;
+use gba_core::events::{EventSink, TaskEvent};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+/// Header carrying the event type.
+pub const EVENT_HEADER: &str = "X-GBA-Event";
+
+/// Header carrying the signature of the body.
+pub const SIGNATURE_HEADER: &str = "X-GBA-Signature-256";
+
+/// Timeout of a delivery attempt.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry, doubled for each further retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Result type alias for webhook operations.
+pub type Result<T> = std::result::Result<T, WebhookError>;
+
+/// Error types for webhook operations.
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    /// The environment variable holding an endpoint's secret is not set.
+    #[error("Environment variable {0} is not set")]
+    MissingEnv(String),
+
+    /// The HTTP client could not be created.
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// An endpoint events are posted to.
+struct Endpoint {
+    /// Endpoint configuration.
+    config: WebhookConfig,
+    /// Secret bodies are signed with, if any.
+    secret: Option<String>,
+}
+
+impl fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("url", &self.config.url)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("events", &self.config.events)
+            .field("max_retries", &self.config.max_retries)
+            .finish()
+    }
+}
+
+/// A message to the delivery task.
+#[derive(Debug)]
+enum Message {
+    /// Deliver an event.
+    Event(Box<TaskEvent>),
+    /// Signal once the events before are delivered.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Event sink posting events to webhook endpoints.
+#[derive(Debug)]
+pub struct WebhookSink {
+    /// Endpoints events are posted to.
+    endpoints: Arc<[Endpoint]>,
+    /// HTTP client.
+    http: reqwest::Client,
+    /// Queue of the delivery task, started with the first event.
+    queue: OnceLock<mpsc::UnboundedSender<Message>>,
+}
+
+impl WebhookSink {
+    /// Create a sink posting to endpoints.
+    ///
+    /// Secrets are read from the endpoints' `secretEnv` variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a secret variable is not set, or the HTTP client
+    /// cannot be created.
+    pub fn new(configs: &[WebhookConfig]) -> Result<Self> {
+        Self::with_secrets(configs, |name| std::env::var(name).ok())
+    }
+
+    /// Create a sink posting to endpoints, resolving secrets with a lookup.
+    ///
+    /// # Arguments
+    ///
+    /// * `configs` - Endpoints to post to
+    /// * `lookup` - Resolves a `secretEnv` name to the secret, if set
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a secret cannot be resolved, or the HTTP client
+    /// cannot be created.
+    pub fn with_secrets(
+        configs: &[WebhookConfig],
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let endpoints = configs
+            .iter()
+            .map(|config| {
+                let secret = config
+                    .secret_env
+                    .as_ref()
+                    .map(|name| lookup(name).ok_or_else(|| WebhookError::MissingEnv(name.clone())))
+                    .transpose()?;
+                Ok(Endpoint {
+                    config: config.clone(),
+                    secret,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            endpoints: endpoints.into(),
+            http,
+            queue: OnceLock::new(),
+        })
+    }
+
+    /// Wait until the events sent so far are delivered or dropped.
+    ///
+    /// Call before exiting, since pending deliveries are abandoned when the
+    /// runtime shuts down.
+    pub async fn flush(&self) {
+        let Some(queue) = self.queue.get() else {
+            return;
+        };
+        let (done, delivered) = oneshot::channel();
+        if queue.send(Message::Flush(done)).is_ok() {
+            let _ = delivered.await;
+        }
+    }
+
+    /// Get the queue of the delivery task, starting it on the current
+    /// runtime if needed.
+    fn queue(&self) -> Option<&mpsc::UnboundedSender<Message>> {
+        if let Some(queue) = self.queue.get() {
+            return Some(queue);
+        }
+        let handle = Handle::try_current().ok()?;
+        Some(self.queue.get_or_init(|| {
+            let (queue, messages) = mpsc::unbounded_channel();
+            handle.spawn(deliver(self.http.clone(), self.endpoints.clone(), messages));
+            queue
+        }))
+    }
+}
+
+impl EventSink for WebhookSink {
+    fn send(&self, event: &TaskEvent) {
+        let name = event.kind.name();
+        if !self.endpoints.iter().any(|e| e.config.accepts(name)) {
+            return;
+        }
+        let Some(queue) = self.queue() else {
+            warn!("Dropping {} event: webhooks need a tokio runtime", name);
+            return;
+        };
+        if queue.send(Message::Event(Box::new(event.clone()))).is_err() {
+            warn!("Dropping {} event: webhook delivery has stopped", name);
+        }
+    }
+}
+
+/// Deliver queued events in order.
+async fn deliver(
+    http: reqwest::Client,
+    endpoints: Arc<[Endpoint]>,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+) {
+    while let Some(message) = messages.recv().await {
+        let event = match message {
+            Message::Event(event) => event,
+            Message::Flush(done) => {
+                let _ = done.send(());
+                continue;
+            }
+        };
+        let name = event.kind.name();
+        let body = match serde_json::to_vec(&event) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize {} event: {}", name, e);
+                continue;
+            }
+        };
+        for endpoint in endpoints.iter().filter(|e| e.config.accepts(name)) {
+            post(&http, endpoint, name, &body).await;
+        }
+    }
+}
+
+/// Post an event to an endpoint, retrying failed deliveries.
+async fn post(http: &reqwest::Client, endpoint: &Endpoint, name: &str, body: &[u8]) {
+    let mut backoff = INITIAL_BACKOFF;
+    for attempt in 0..=endpoint.config.max_retries {
+        if attempt > 0 {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+
+        let mut request = http
+            .post(&endpoint.config.url)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, name)
+            .body(body.to_vec());
+        if let Some(secret) = &endpoint.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body));
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!("Posted {} event to {}", name, endpoint.config.url);
+                return;
+            }
+            Ok(response)
+                if response.status().is_server_error()
+                    || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                format!("status {}", response.status())
+            }
+            Ok(response) => {
+                warn!(
+                    "Webhook {} rejected {} event with status {}",
+                    endpoint.config.url,
+                    name,
+                    response.status()
+                );
+                return;
+            }
+            Err(e) => e.to_string(),
+        };
+        debug!(
+            "Delivery of {} event to {} failed (attempt {}): {}",
+            name,
+            endpoint.config.url,
+            attempt + 1,
+            error
+        );
+    }
+    warn!(
+        "Giving up posting {} event to {} after {} attempts",
+        name,
+        endpoint.config.url,
+        endpoint.config.max_retries + 1
+    );
+}
+
+/// Sign a body with a secret, as sent in [`SIGNATURE_HEADER`].
+///
+/// Receivers verify a request by computing the signature of its raw body
+/// and comparing it to the header in constant time.
+#[must_use]
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use chrono::Utc;
+    use gba_core::events::EventKind;
+
+    use super::*;
+
+    /// Serve one request per status, returning the raw requests.
+    fn serve(statuses: &'static [u16]) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            statuses
+                .iter()
+                .map(|status| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = Vec::new();
+                    let mut buf = [0; 4096];
+                    loop {
+                        let n = stream.read(&mut buf).unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request);
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head
+                                .lines()
+                                .find_map(|l| l.strip_prefix("content-length: "))
+                                .and_then(|l| l.parse::<usize>().ok())
+                                .unwrap_or_default();
+                            if body.len() >= length {
+                                break;
+                            }
+                        }
+                    }
+                    stream
+                        .write_all(
+                            format!("HTTP/1.1 {status} X\r\ncontent-length: 0\r\n\r\n").as_bytes(),
+                        )
+                        .unwrap();
+                    String::from_utf8(request).unwrap()
+                })
+                .collect()
+        });
+        (url, server)
+    }
+
+    fn event(kind: EventKind) -> TaskEvent {
+        TaskEvent {
+            timestamp: Utc::now(),
+            feature: "add-auth".to_string(),
+            feature_id: "0001".to_string(),
+            run_id: "run-1".to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", b"{}"),
+            "sha256=77325902caca812dc259733aacd046b73817372c777b8d95b402647474516e13"
+        );
+        assert_ne!(sign("other", b"{}"), sign("secret", b"{}"));
+    }
+
+    #[test]
+    fn test_events_are_posted_signed_with_retries() {
+        let (url, server) = serve(&[503, 200]);
+        let sink = WebhookSink::with_secrets(
+            &[WebhookConfig {
+                url,
+                secret_env: Some("GBA_WEBHOOK_SECRET".to_string()),
+                events: vec!["finished".to_string()],
+                max_retries: 1,
+            }],
+            |name| (name == "GBA_WEBHOOK_SECRET").then(|| "secret".to_string()),
+        )
+        .unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            sink.send(&event(EventKind::Started {
+                kind: "planning".to_string(),
+            }));
+            sink.send(&event(EventKind::Finished {
+                kind: "planning".to_string(),
+                success: true,
+                error: None,
+                usage: Default::default(),
+            }));
+            sink.flush().await;
+        });
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        let request = &requests[1];
+        assert!(request.starts_with("POST /events"));
+        assert!(request.contains("x-gba-event: finished"));
+        let body = request.split_once("\r\n\r\n").unwrap().1;
+        assert!(body.contains("\"type\":\"finished\""));
+        assert!(request.contains(&format!(
+            "x-gba-signature-256: {}",
+            sign("secret", body.as_bytes())
+        )));
+    }
+
+    #[test]
+    fn test_workspace_runs_post_to_configured_webhooks() {
+        let (url, server) = serve(&[200]);
+        let dir = std::env::temp_dir().join("gba-test-webhook-workspace");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".gba")).unwrap();
+        let mut config = gba_core::ProjectConfig::default();
+        config.prompts.use_bundled = true;
+        config.webhooks = vec![WebhookConfig {
+            url,
+            secret_env: None,
+            events: vec!["finished".to_string()],
+            max_retries: 0,
+        }];
+        config
+            .save_to_file(&dir.join(".gba").join("config.yml"))
+            .unwrap();
+        let workspace = crate::Workspace::open(&dir).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut run = workspace
+                .start_run("add-auth", "planning", None)
+                .await
+                .unwrap();
+            workspace
+                .finish_run::<crate::GbaError>(&mut run, Ok(&Default::default()))
+                .unwrap();
+            workspace.flush_events().await;
+        });
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("x-gba-event: finished"));
+        assert!(requests[0].contains("\"feature\":\"add-auth\""));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_missing_secret() {
+        let config = WebhookConfig {
+            url: "http://localhost/events".to_string(),
+            secret_env: Some("GBA_TEST_WEBHOOK_SECRET_UNSET".to_string()),
+            events: Vec::new(),
+            max_retries: 0,
+        };
+        assert!(matches!(
+            WebhookSink::new(&[config]),
+            Err(WebhookError::MissingEnv(name)) if name == "GBA_TEST_WEBHOOK_SECRET_UNSET"
+        ));
+    }
+}
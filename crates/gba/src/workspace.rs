@@ -0,0 +1,2186 @@
+//! The workspace of a GBA project.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures::{StreamExt, stream};
+use gba_core::agent::TaskPreview;
+use gba_core::atomic;
+use gba_core::audit::AuditLog;
+use gba_core::cargo::CrateMap;
+use gba_core::context_budget;
+use gba_core::context_builder::{
+    ContextBuilderConfig, ContextReport, build_context_with_report, estimate_tokens,
+};
+use gba_core::diff::{self, DiffOptions};
+use gba_core::events::{EventBus, EventKind, EventSink, RunEvents};
+use gba_core::git;
+use gba_core::index::{EntryKind, HashingEmbedder, IndexEntry, RepoIndex};
+use gba_core::layout::{LayoutRenderer, PromptLayout};
+use gba_core::ledger::{self, Ledger, LedgerEntry};
+use gba_core::lock::{self, FeatureLock};
+use gba_core::plan;
+use gba_core::pool::{AgentPool, PoolTask};
+use gba_core::quota;
+use gba_core::review::{self, MergedReview, PersonaReview};
+use gba_core::scratch::{self, ScratchDir};
+use gba_core::state::{FixIteration, StateTracker, TaskStatus, WorktreeInfo};
+use gba_core::store::{self, StateStore};
+use gba_core::task::Usage;
+use gba_core::task_kind::{TaskKindPlugin, TaskKindRegistry};
+use gba_core::vcs::{self, VcsKind};
+use gba_core::verify::{self, VerificationReport};
+use gba_core::worktree::WorktreeManager;
+use gba_core::{
+    Agent, ConfigError, Context, CoreError, FeatureState, Metrics, OutputConstraint, ProjectConfig,
+    Response, Task, feature,
+};
+use gba_pm::{Context as PromptContext, PromptManager, ResumeContext};
+use tracing::{debug, info, warn};
+
+use crate::error::{GbaError, Result};
+
+/// Commit trailer naming the feature of the commits gba creates.
+const FEATURE_ID_TRAILER: &str = "Gba-Feature-Id";
+
+/// Commit trailer naming the run of the commits gba creates.
+const RUN_ID_TRAILER: &str = "Gba-Run-Id";
+
+/// Task kind verifying a feature, see [`Workspace::verify`].
+const VERIFICATION_KIND: &str = "verification";
+
+/// How a fix loop ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixLoopStatus {
+    /// Verification passed.
+    Passed,
+
+    /// Verification still failed after the maximum number of iterations.
+    IterationCap,
+
+    /// The loop's budget was spent before verification passed.
+    BudgetExhausted,
+}
+
+/// Outcome of a fix loop, see [`Workspace::fix_loop`].
+#[derive(Debug, Clone)]
+pub struct FixLoopOutcome {
+    /// How the loop ended.
+    pub status: FixLoopStatus,
+
+    /// Number of verifications run.
+    pub iterations: u32,
+
+    /// Cost of the loop in USD.
+    pub cost_usd: f64,
+
+    /// Report of the last verification.
+    pub report: VerificationReport,
+}
+
+/// Phase of the feature workflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Create an implementation plan.
+    Planning,
+
+    /// Implement the plan in the feature's worktree.
+    Implementation,
+
+    /// Review the feature's changes.
+    Review,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Planning => write!(f, "planning"),
+            Self::Implementation => write!(f, "implementation"),
+            Self::Review => write!(f, "review"),
+        }
+    }
+}
+
+impl Phase {
+    /// Get the template name for this phase.
+    #[must_use]
+    pub const fn template_name(&self) -> &str {
+        match self {
+            Self::Planning => "plan",
+            Self::Implementation => "implement",
+            Self::Review => "review",
+        }
+    }
+
+    /// Get the phase a task kind runs as, if it is one of the workflow phases.
+    fn from_kind(kind: &str) -> Option<Self> {
+        [Self::Planning, Self::Implementation, Self::Review]
+            .into_iter()
+            .find(|phase| phase.to_string() == kind)
+    }
+}
+
+/// Lays out the prompts of agents with the project's `layout` template.
+#[derive(Debug)]
+struct TemplateLayout(Arc<PromptManager>);
+
+impl LayoutRenderer for TemplateLayout {
+    fn render(&self, layout: &PromptLayout) -> gba_core::Result<String> {
+        self.0
+            .render_layout(layout)
+            .map_err(|e| CoreError::Layout(e.to_string()))
+    }
+}
+
+/// A run of a task kind on a feature, between its start and finish, see
+/// [`Workspace::start_run`].
+///
+/// The feature stays locked until the run is dropped.
+pub struct Run {
+    /// Task kind of the run.
+    kind: Arc<dyn TaskKindPlugin>,
+    /// Workflow phase the kind runs as, if any.
+    phase: Option<Phase>,
+    /// Feature state, saved when the run finishes.
+    state: FeatureState,
+    /// Path of the feature state file.
+    state_path: PathBuf,
+    /// Tracker saving the agent's turns to the feature state file.
+    tracker: StateTracker,
+    /// Run identifier.
+    run_id: String,
+    /// Directory the agent runs in.
+    working_dir: PathBuf,
+    /// Task context.
+    context: Context,
+    /// Provenance of the context.
+    report: ContextReport,
+    /// Path the context report is saved to.
+    report_path: PathBuf,
+    /// Publisher of the run's events.
+    events: RunEvents,
+    /// Scratch directory of the agent.
+    scratch: ScratchDir,
+    /// Stash entry of the changes set aside for the run, until restored.
+    stash: Option<String>,
+    /// Commit of the run's changes, once committed.
+    commit: Option<String>,
+    /// Lock of the feature, released when the run is dropped.
+    _lock: FeatureLock,
+}
+
+impl fmt::Debug for Run {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Run")
+            .field("kind", &self.kind.name())
+            .field("phase", &self.phase)
+            .field("feature", &self.state.feature.name)
+            .field("run_id", &self.run_id)
+            .field("working_dir", &self.working_dir)
+            .field("stash", &self.stash)
+            .field("commit", &self.commit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Run {
+    /// Get the task kind of the run.
+    #[must_use]
+    pub fn kind(&self) -> &dyn TaskKindPlugin {
+        self.kind.as_ref()
+    }
+
+    /// Get the feature state of the run.
+    #[must_use]
+    pub const fn state(&self) -> &FeatureState {
+        &self.state
+    }
+
+    /// Get the run identifier.
+    #[must_use]
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Get the directory the agent runs in: the feature's worktree, or the
+    /// project directory without one.
+    #[must_use]
+    pub fn working_dir(&self) -> &Path {
+        &self.working_dir
+    }
+
+    /// Get the task context of the run.
+    #[must_use]
+    pub const fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Get the task context of the run to add metadata to, e.g. the results
+    /// of verification commands.
+    pub const fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// Get the provenance of the run's context.
+    #[must_use]
+    pub const fn report(&self) -> &ContextReport {
+        &self.report
+    }
+
+    /// Get the publisher of the run's events.
+    #[must_use]
+    pub const fn events(&self) -> &RunEvents {
+        &self.events
+    }
+
+    /// Get the stash entry of the uncommitted changes set aside for the
+    /// run, until they are restored.
+    #[must_use]
+    pub fn stash(&self) -> Option<&str> {
+        self.stash.as_deref()
+    }
+
+    /// Get the commit of the run's changes, once committed.
+    #[must_use]
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+}
+
+/// What a run would send to the agent, see [`Workspace::preview`].
+#[derive(Debug, Clone)]
+pub struct RunPreview {
+    /// Directory the agent would run in.
+    pub working_dir: PathBuf,
+
+    /// Task the agent would execute, with its context fitted to the input
+    /// budget.
+    pub task: Task,
+
+    /// Full prompt and settings of the task.
+    pub preview: TaskPreview,
+
+    /// Provenance of the context, with the stage it was degraded to.
+    pub report: ContextReport,
+}
+
+/// A GBA project opened for embedding.
+///
+/// Each phase records its progress in the feature's `state.yml`, appends its
+/// usage to the cost ledger, audits its tool calls and writes a transcript.
+/// `gba run` drives its runs through the same [`Self::start_run`],
+/// [`Self::task`], [`Self::agent`] and [`Self::finish_run`].
+pub struct Workspace {
+    /// Project directory.
+    project_path: PathBuf,
+    /// Project configuration.
+    config: ProjectConfig,
+    /// Prompt manager with the project's templates.
+    prompts: Arc<PromptManager>,
+    /// Metrics of the workspace's tasks, shared with its agents.
+    metrics: Metrics,
+    /// Task kinds the workspace can run.
+    kinds: TaskKindRegistry,
+    /// Start runs even if a project quota has been reached.
+    override_quota: bool,
+    /// Cost attribution tags of runs, in addition to the project's.
+    tags: Vec<String>,
+    /// Start runs on a working tree with uncommitted changes.
+    force: bool,
+    /// Stash the uncommitted changes of a working tree during runs.
+    auto_stash: bool,
+    /// Commit the changes of implementation runs in the feature's worktree.
+    commit: bool,
+    /// Bus the events of runs are published on.
+    events: EventBus,
+    /// Sink posting the events to the configured webhooks, if any.
+    #[cfg(feature = "webhook")]
+    webhooks: Option<Arc<crate::webhook::WebhookSink>>,
+    /// Store features are persisted to beyond the project, if configured.
+    store: Option<Box<dyn StateStore>>,
+}
+
+impl fmt::Debug for Workspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Workspace")
+            .field("project_path", &self.project_path)
+            .field("config", &self.config)
+            .field("metrics", &self.metrics)
+            .field("kinds", &self.kinds)
+            .field("override_quota", &self.override_quota)
+            .field("tags", &self.tags)
+            .field("force", &self.force)
+            .field("auto_stash", &self.auto_stash)
+            .field("commit", &self.commit)
+            .field("events", &self.events)
+            .field("store", &self.store)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Workspace {
+    /// Open a GBA project.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_path` - Project directory containing `.gba/config.yml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory is not a GBA project, or its
+    /// configuration or templates cannot be loaded, or the configured storage
+    /// is incomplete. With the `webhook` feature, also if the configured
+    /// webhooks cannot be set up.
+    pub fn open(project_path: impl Into<PathBuf>) -> Result<Self> {
+        let project_path = project_path.into();
+        let config_path = project_path.join(".gba").join("config.yml");
+        if !config_path.is_file() {
+            return Err(GbaError::NotGbaProject(project_path));
+        }
+
+        let config = ProjectConfig::load_from_file(&config_path)?;
+        let prompts = PromptManager::with_local_dir(
+            project_path.join(&config.prompts.directory),
+            config.prompts.use_bundled,
+        )?;
+        #[cfg(feature = "webhook")]
+        let webhooks = if config.webhooks.is_empty() {
+            None
+        } else {
+            Some(Arc::new(crate::webhook::WebhookSink::new(
+                &config.webhooks,
+            )?))
+        };
+        #[cfg(feature = "webhook")]
+        let events = webhooks
+            .iter()
+            .fold(EventBus::new(), |bus, sink| bus.with_sink(sink.clone()));
+        #[cfg(not(feature = "webhook"))]
+        let events = {
+            if !config.webhooks.is_empty() {
+                warn!("Ignoring the configured webhooks: gba is built without the webhook feature");
+            }
+            EventBus::new()
+        };
+        let store = store::remote(&config.storage, &project_path).map_err(CoreError::from)?;
+        debug!("Opened GBA project {}", project_path.display());
+
+        Ok(Self {
+            project_path,
+            config,
+            prompts: Arc::new(prompts),
+            metrics: Metrics::new(),
+            kinds: TaskKindRegistry::new(),
+            override_quota: false,
+            tags: Vec::new(),
+            force: false,
+            auto_stash: false,
+            commit: false,
+            events,
+            #[cfg(feature = "webhook")]
+            webhooks,
+            store,
+        })
+    }
+
+    /// Record the tasks of all phases in a metrics handle shared with the
+    /// embedder, instead of the workspace's own.
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Get the metrics of the workspace's tasks, e.g. to serve them in the
+    /// Prometheus text format.
+    #[must_use]
+    pub const fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Register a task kind, replacing any kind with the same name.
+    ///
+    /// Registering a kind named after a phase, e.g. `"review"`, changes how
+    /// that phase is run.
+    #[must_use]
+    pub fn with_task_kind(mut self, kind: impl TaskKindPlugin + 'static) -> Self {
+        self.kinds.register(kind);
+        self
+    }
+
+    /// Publish the events of runs to a sink, in addition to the configured
+    /// webhooks.
+    ///
+    /// See [`gba_core::events`] for the events.
+    #[must_use]
+    pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.events = self.events.with_sink(sink);
+        self
+    }
+
+    /// Wait until the events of runs are delivered to the configured
+    /// webhooks, or dropped.
+    ///
+    /// Call before exiting, since pending deliveries are abandoned when the
+    /// runtime shuts down. Without the `webhook` feature, returns right away.
+    pub async fn flush_events(&self) {
+        #[cfg(feature = "webhook")]
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.flush().await;
+        }
+    }
+
+    /// Start runs even if a project quota has been reached.
+    ///
+    /// Reached quotas are still logged as warnings.
+    #[must_use]
+    pub const fn with_quota_override(mut self, override_quota: bool) -> Self {
+        self.override_quota = override_quota;
+        self
+    }
+
+    /// Tag runs for cost attribution, e.g. `sprint-42`, in addition to the
+    /// project's `project.tags`.
+    ///
+    /// The tags are recorded in the feature state and the cost ledger.
+    #[must_use]
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Start runs on a working tree with uncommitted changes, which are
+    /// refused otherwise.
+    ///
+    /// The changes are logged as a warning. Stashing them, see
+    /// [`Self::with_auto_stash`], takes precedence.
+    #[must_use]
+    pub const fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Stash the uncommitted changes of a working tree before a run and
+    /// restore them when it finishes, instead of refusing it.
+    ///
+    /// GBA's own files are left in place. Verification in the project
+    /// directory always stashes, since its commands may have side effects.
+    #[must_use]
+    pub const fn with_auto_stash(mut self, auto_stash: bool) -> Self {
+        self.auto_stash = auto_stash;
+        self
+    }
+
+    /// Commit the changes of implementation runs in the feature's worktree,
+    /// as `worktree.autoCommit` does.
+    #[must_use]
+    pub const fn with_commit(mut self, commit: bool) -> Self {
+        self.commit = commit;
+        self
+    }
+
+    /// Get the task kinds the workspace can run.
+    #[must_use]
+    pub const fn task_kinds(&self) -> &TaskKindRegistry {
+        &self.kinds
+    }
+
+    /// Get the project directory.
+    #[must_use]
+    pub fn project_path(&self) -> &Path {
+        &self.project_path
+    }
+
+    /// Get the project configuration.
+    #[must_use]
+    pub const fn config(&self) -> &ProjectConfig {
+        &self.config
+    }
+
+    /// Get the prompt manager.
+    #[must_use]
+    pub fn prompts(&self) -> &PromptManager {
+        &self.prompts
+    }
+
+    /// Get the state of a feature, if it has been started.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file cannot be read.
+    pub fn feature_state(&self, feature: &str) -> Result<Option<FeatureState>> {
+        let path = self.state_path(&feature::feature_id(feature));
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(FeatureState::load(&path).map_err(CoreError::from)?))
+    }
+
+    /// Create an implementation plan for a feature.
+    ///
+    /// The plan is saved to `.gba/features/<id>/plan.md`, where the
+    /// implementation phase picks it up.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt cannot be rendered or the agent fails.
+    pub async fn plan(&self, feature: &str, description: &str) -> Result<Response> {
+        self.run_phase(feature, Phase::Planning, Some(description))
+            .await
+    }
+
+    /// Implement a feature in its worktree, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the worktree cannot be created, the prompt cannot
+    /// be rendered or the agent fails.
+    pub async fn implement(&self, feature: &str) -> Result<Response> {
+        self.run_phase(feature, Phase::Implementation, None).await
+    }
+
+    /// Review the changes of a feature against the main branch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the diff cannot be generated, the prompt cannot
+    /// be rendered or the agent fails.
+    pub async fn review(&self, feature: &str) -> Result<Response> {
+        self.run_phase(feature, Phase::Review, None).await
+    }
+
+    /// Review the changes of a feature with several reviewer personas at once.
+    ///
+    /// Runs the review templates configured under `review.personas`
+    /// concurrently over the same diff, see [`Self::review_with_personas`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no personas are configured, the diff cannot be
+    /// generated, a prompt cannot be rendered or every review fails.
+    pub async fn review_fanout(&self, feature: &str) -> Result<MergedReview> {
+        self.review_with_personas(feature, &self.config.review.personas)
+            .await
+    }
+
+    /// Review the changes of a feature with the given reviewer personas.
+    ///
+    /// Each persona's review template runs as the `review` task kind, with
+    /// its tools and post-processing, at most `review.maxConcurrency` at
+    /// once. The findings are merged with duplicates removed, see
+    /// [`MergedReview`]. Each persona gets its own transcript,
+    /// `<run-id>-<persona>.jsonl`; the run is recorded once in the feature
+    /// state and the cost ledger.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name.
+    /// * `personas` - Review template names, e.g. `"review-security"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `personas` is empty, the diff cannot be
+    /// generated, a prompt cannot be rendered or every review fails. A review
+    /// failing while others succeed is reported in [`MergedReview::failures`].
+    pub async fn review_with_personas(
+        &self,
+        feature: &str,
+        personas: &[String],
+    ) -> Result<MergedReview> {
+        if personas.is_empty() {
+            return Err(
+                ConfigError::ValidationError("No review personas configured".to_string()).into(),
+            );
+        }
+        let mut run = self
+            .start_run(feature, &Phase::Review.to_string(), None)
+            .await?;
+        let kind = run.kind.clone();
+        let prompt_context =
+            self.prompt_context(kind.as_ref(), run.phase, &run.state, &run.context.metadata)?;
+        let tasks = self.persona_tasks(&mut run, personas, &prompt_context)?;
+
+        info!("Reviewing {} with {} personas", feature, personas.len());
+        let pool = AgentPool::new(self.config.review.max_concurrency);
+        let reviews = personas
+            .iter()
+            .zip(pool.run(tasks).await)
+            .map(|(template, result)| PersonaReview {
+                persona: persona_name(template).to_string(),
+                result: result.map(|response| kind.post_process(response)),
+            })
+            .collect::<Vec<_>>();
+
+        let merged = if reviews.iter().all(|review| review.result.is_err()) {
+            Err(reviews
+                .into_iter()
+                .find_map(|review| review.result.err())
+                .unwrap_or_else(|| CoreError::ClaudeAgent("Review failed".to_string())))
+        } else {
+            let merged = MergedReview::merge(reviews);
+            for (persona, message) in &merged.failures {
+                warn!("Review by {} failed: {}", persona, message);
+            }
+            Ok(merged)
+        };
+        let response = merged.as_ref().map(MergedReview::to_response);
+        self.finish_run(&mut run, response.as_ref().map_err(|e| *e))?;
+
+        Ok(merged?)
+    }
+
+    /// Build the tasks of a review's personas, each with the settings of its
+    /// template and its own transcript.
+    ///
+    /// The run's context is fitted to the longest of their prompts.
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - Run of the review.
+    /// * `personas` - Review template names.
+    /// * `prompt_context` - Template context the prompts are rendered with.
+    fn persona_tasks(
+        &self,
+        run: &mut Run,
+        personas: &[String],
+        prompt_context: &PromptContext,
+    ) -> Result<Vec<PoolTask>> {
+        let prompts = personas
+            .iter()
+            .map(|template| self.render_prompt(template, prompt_context))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(longest) = prompts.iter().max_by_key(|prompt| prompt.len()) {
+            self.fit_run(run, longest);
+        }
+        personas
+            .iter()
+            .zip(prompts)
+            .map(|(template, prompt)| {
+                let transcript = format!("{}-{}", run.run_id, persona_name(template));
+                let agent = self
+                    .run_agent(run, &transcript)
+                    .with_constraints(self.constraints(run.kind.name(), template));
+                let task = self.template_task(template, prompt, run.context.clone())?;
+                Ok(PoolTask::new(agent, task))
+            })
+            .collect()
+    }
+
+    /// Verify a feature: run the configured verification commands in its
+    /// worktree, then let the agent triage their results.
+    ///
+    /// The commands under `verification.commands` run one at a time in the
+    /// feature's worktree, or in the project directory without one. Their
+    /// results are passed to the `verification` task kind's template as
+    /// `commands`, and the agent's verdict is parsed from its report. The
+    /// report is saved to the feature's `verification.json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the context cannot be prepared, the prompt cannot
+    /// be rendered, the agent fails or the report cannot be saved. Failing
+    /// commands are not errors: see [`VerificationReport::passed`].
+    pub async fn verify(&self, feature: &str) -> Result<VerificationReport> {
+        let mut run = self.start_run(feature, VERIFICATION_KIND, None).await?;
+        let kind = run.kind.clone();
+        let report = VerificationReport::new(
+            verify::run_commands(&run.working_dir, &self.config.verification).await,
+        );
+        for failure in report.failures() {
+            info!("Verification command {} failed", failure.name);
+        }
+        run.context
+            .metadata
+            .insert(verify::COMMANDS_KEY.to_string(), report.commands_metadata());
+
+        let prompt = self.render_prompt(
+            kind.template_name(),
+            &self.prompt_context(kind.as_ref(), run.phase, &run.state, &run.context.metadata)?,
+        )?;
+        let task = self.task(&mut run, kind.template_name(), prompt)?;
+        let result = self
+            .agent(&run)
+            .execute_task(&task)
+            .await
+            .map(|response| kind.post_process(response));
+        self.finish_run(&mut run, result.as_ref())?;
+
+        let report = report.with_response(&result?);
+        self.save_verification(&run, &report)?;
+
+        Ok(report)
+    }
+
+    /// Verify a feature and fix its failures until verification passes.
+    ///
+    /// Each iteration verifies the feature, see [`Self::verify`]. While
+    /// verification fails, an implementation task runs with the failures
+    /// attached as `verification_failures`, then the feature is verified
+    /// again, up to `fixLoop.maxIterations` verifications and
+    /// `fixLoop.maxCostUsd`. Each iteration is recorded in the feature state
+    /// under `fix_loop`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a verification or implementation task fails to
+    /// run, or the feature state cannot be saved. Verification failing is
+    /// not an error: see [`FixLoopOutcome::status`].
+    pub async fn fix_loop(&self, feature: &str) -> Result<FixLoopOutcome> {
+        let config = &self.config.fix_loop;
+        let implementation = self
+            .kinds
+            .get(&Phase::Implementation.to_string())
+            .ok_or_else(|| GbaError::UnknownTaskKind(Phase::Implementation.to_string()))?;
+        let feature_id = feature::feature_id(feature);
+        let state_path = self.state_path(&feature_id);
+
+        self.restore_feature(feature)?;
+        let mut state = FeatureState::load_or_new(&state_path, feature, &feature_id)
+            .map_err(CoreError::from)?;
+        let start_cost = state.execution.cost.total_cost_usd;
+        state.fix_loop.clear();
+        state.save(&state_path).map_err(CoreError::from)?;
+        let over_budget = |cost: f64| config.max_cost_usd.is_some_and(|max| cost >= max);
+
+        let mut iteration = 0;
+        loop {
+            iteration += 1;
+            let report = self.verify(feature).await?;
+
+            let mut state = FeatureState::load(&state_path).map_err(CoreError::from)?;
+            let cost_usd = state.execution.cost.total_cost_usd - start_cost;
+            state.fix_loop.push(FixIteration {
+                iteration,
+                verification_run_id: state.execution.run_id.clone(),
+                passed: report.passed(),
+                verdict: report.verdict.map(|verdict| verdict.to_string()),
+                failed_commands: report.failures().map(|f| f.name.clone()).collect(),
+                fix_run_id: None,
+                cost_usd,
+            });
+            state.save(&state_path).map_err(CoreError::from)?;
+
+            let status = if report.passed() {
+                Some(FixLoopStatus::Passed)
+            } else if iteration >= config.max_iterations {
+                Some(FixLoopStatus::IterationCap)
+            } else if over_budget(cost_usd) {
+                Some(FixLoopStatus::BudgetExhausted)
+            } else {
+                None
+            };
+            if let Some(status) = status {
+                self.persist_feature(feature);
+                info!(
+                    "Fix loop of {} finished after {} iteration(s): {:?}",
+                    feature, iteration, status
+                );
+                return Ok(FixLoopOutcome {
+                    status,
+                    iterations: iteration,
+                    cost_usd,
+                    report,
+                });
+            }
+
+            info!(
+                "Verification of {} failed, fixing (iteration {} of {})",
+                feature, iteration, config.max_iterations
+            );
+            self.metrics.retry(implementation.name());
+            let metadata = HashMap::from([
+                (
+                    "verification_failures".to_string(),
+                    serde_json::Value::from(report.failure_context()),
+                ),
+                (
+                    "fix_iteration".to_string(),
+                    serde_json::Value::from(iteration),
+                ),
+            ]);
+            // A fix spending the whole task budget exhausts the loop's too
+            let budget_exceeded = match self
+                .run(feature, implementation.name(), None, metadata)
+                .await
+            {
+                Ok(_) => false,
+                Err(GbaError::Core(CoreError::BudgetExceeded { .. })) => true,
+                Err(e) => return Err(e),
+            };
+
+            let mut state = FeatureState::load(&state_path).map_err(CoreError::from)?;
+            let cost_usd = state.execution.cost.total_cost_usd - start_cost;
+            let fix_run_id = state.execution.run_id.clone();
+            if let Some(last) = state.fix_loop.last_mut() {
+                last.fix_run_id = fix_run_id;
+                last.cost_usd = cost_usd;
+            }
+            state.save(&state_path).map_err(CoreError::from)?;
+
+            if budget_exceeded || over_budget(cost_usd) {
+                self.persist_feature(feature);
+                info!("Fix loop of {} stopped: budget exhausted", feature);
+                return Ok(FixLoopOutcome {
+                    status: FixLoopStatus::BudgetExhausted,
+                    iterations: iteration,
+                    cost_usd,
+                    report,
+                });
+            }
+        }
+    }
+
+    /// Open a pull request for a feature's worktree branch.
+    ///
+    /// Uncommitted changes in the worktree are committed first, then the
+    /// branch is pushed to `github.remote` and a pull request is opened
+    /// against `github.base`, or the main branch, with the feature's plan
+    /// and the summary in its body. The token is read from `GITHUB_TOKEN`
+    /// and the repository from `github.repo` or the URL of the remote. The
+    /// link is recorded in the feature state as `result.pr_link`; once it is
+    /// set, later calls only commit and push to the existing pull request.
+    /// The feature is locked meanwhile, like a run.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name.
+    /// * `summary` - Summary of the implementation, e.g. the final response
+    ///   of the implementation task.
+    ///
+    /// # Returns
+    ///
+    /// The URL of the pull request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature is locked by another run, has no
+    /// worktree, committing or pushing fails, the repository cannot be told,
+    /// or GitHub rejects the pull request.
+    #[cfg(feature = "github")]
+    pub async fn open_pull_request(&self, feature: &str, summary: &str) -> Result<String> {
+        use gba_core::pull_request::PullRequestDescription;
+
+        use crate::github::{GitHubClient, GitHubError, NewPullRequest, repo_from_url};
+
+        let config = &self.config.github;
+        let feature_id = feature::feature_id(feature);
+        let _lock = FeatureLock::acquire(
+            self.feature_dir(&feature_id).join(lock::LOCK_FILE),
+            "pull-request",
+        )
+        .map_err(CoreError::from)?;
+        let state_path = self.state_path(&feature_id);
+        let mut state = FeatureState::load(&state_path).map_err(CoreError::from)?;
+        let worktree = state
+            .context
+            .worktree
+            .clone()
+            .ok_or_else(|| GbaError::NoWorktree(feature.to_string()))?;
+
+        let trailers = [(FEATURE_ID_TRAILER, feature_id.as_str())];
+        let message = format!("gba: implement {}", state.feature.name);
+        let commit =
+            git::commit_all(&worktree.path, &message, &trailers).map_err(CoreError::from)?;
+        if let Some(url) = state
+            .result
+            .as_ref()
+            .and_then(|result| result.pr_link.clone())
+        {
+            git::push(&worktree.path, &config.remote, &worktree.branch).map_err(CoreError::from)?;
+            info!("Pushed {} to pull request {}", feature, url);
+            if commit.is_some() {
+                if let Some(result) = state.result.as_mut() {
+                    result.commits_created += 1;
+                }
+                state.save(&state_path).map_err(CoreError::from)?;
+                self.persist_feature(feature);
+            }
+            return Ok(url);
+        }
+        let repo = match &config.repo {
+            Some(repo) => repo.clone(),
+            None => git::remote_url(&worktree.path, &config.remote)
+                .map_err(CoreError::from)?
+                .as_deref()
+                .and_then(repo_from_url)
+                .ok_or_else(|| GitHubError::UnknownRepo(config.remote.clone()))?,
+        };
+        git::push(&worktree.path, &config.remote, &worktree.branch).map_err(CoreError::from)?;
+
+        let plan = plan::load(&self.feature_dir(&feature_id)).map_err(CoreError::from)?;
+        let description =
+            PullRequestDescription::new(&state.feature.name, plan.as_deref(), summary);
+        let mut client = GitHubClient::from_env();
+        if let Some(api_url) = &config.api_url {
+            client = client.with_api_url(api_url);
+        }
+        let url = client
+            .create_pull_request(
+                &repo,
+                &NewPullRequest {
+                    title: description.title,
+                    body: description.body,
+                    head: worktree.branch.clone(),
+                    base: config.base.clone().unwrap_or_else(|| self.main_branch()),
+                    draft: config.draft,
+                },
+            )
+            .await?;
+        info!("Opened pull request {} for {}", url, feature);
+
+        let result = state.result.get_or_insert_with(Default::default);
+        if commit.is_some() {
+            result.commits_created += 1;
+        }
+        result.pr_link = Some(url.clone());
+        state.save(&state_path).map_err(CoreError::from)?;
+        self.persist_feature(feature);
+        Ok(url)
+    }
+
+    /// Run a task of a registered kind on a feature.
+    ///
+    /// Kinds other than the workflow phases run in the feature's worktree if
+    /// it exists, and in the project directory otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name.
+    /// * `kind` - Name of a registered task kind, e.g. `"security-audit"`.
+    /// * `description` - Feature description, saved in the feature state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kind is not registered, its context cannot be
+    /// prepared, the prompt cannot be rendered or the agent fails.
+    pub async fn run_kind(
+        &self,
+        feature: &str,
+        kind: &str,
+        description: Option<&str>,
+    ) -> Result<Response> {
+        self.run(feature, kind, description, HashMap::new()).await
+    }
+
+    /// Run a task of a registered kind on several features concurrently.
+    ///
+    /// Each feature runs as with [`Self::run_kind`], in its own worktree
+    /// and agent session, with at most `max_concurrency` running at once (0
+    /// is treated as 1). The events of every run are published on the
+    /// workspace's event bus, tagged with their feature, so one sink reports
+    /// the progress of them all.
+    ///
+    /// # Arguments
+    ///
+    /// * `features` - Feature names.
+    /// * `kind` - Name of a registered task kind, e.g. `"implementation"`.
+    /// * `max_concurrency` - Maximum number of features running at once.
+    ///
+    /// # Returns
+    ///
+    /// The result of each feature, in the order of `features`. A failing
+    /// feature doesn't stop the others.
+    pub async fn run_many(
+        &self,
+        features: &[String],
+        kind: &str,
+        max_concurrency: usize,
+    ) -> Vec<(String, Result<Response>)> {
+        info!(
+            "Running {} on {} features, {} at a time",
+            kind,
+            features.len(),
+            max_concurrency.max(1)
+        );
+        stream::iter(features)
+            .map(|feature| async move {
+                let result = self.run_kind(feature, kind, None).await;
+                (feature.clone(), result)
+            })
+            .buffered(max_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Resume the interrupted or failed task of a feature.
+    ///
+    /// The task runs again with the `resume` template, rendered from the
+    /// progress recorded in the feature state: its phase, last step, turns,
+    /// cost and worktree. The agent session of the interrupted run is
+    /// resumed when the state recorded one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature has no task in progress or failed,
+    /// its kind is not registered, the prompt cannot be rendered or the
+    /// agent fails.
+    pub async fn resume(&self, feature: &str) -> Result<Response> {
+        let state = self
+            .feature_state(feature)?
+            .filter(FeatureState::is_resumable)
+            .ok_or_else(|| GbaError::NothingToResume(feature.to_string()))?;
+        let mut run = self.start_run(feature, &state.task.kind, None).await?;
+        let kind = run.kind.clone();
+        info!(
+            "Resuming {} of {} after {} turns",
+            kind.name(),
+            feature,
+            run.state.execution.turns
+        );
+        let prompt =
+            self.render_prompt("resume", &self.resume_context(kind.as_ref(), &run.state))?;
+        let task = self.task(&mut run, "resume", prompt)?;
+
+        let mut agent = self.agent(&run);
+        if let Some(session_id) = &state.execution.session_id {
+            agent = agent.with_resume(session_id);
+        }
+        let result = agent
+            .execute_task(&task)
+            .await
+            .map(|response| kind.post_process(response));
+        self.finish_run(&mut run, result.as_ref())?;
+
+        Ok(result?)
+    }
+
+    /// Run a phase of a feature.
+    async fn run_phase(
+        &self,
+        feature: &str,
+        phase: Phase,
+        description: Option<&str>,
+    ) -> Result<Response> {
+        self.run_kind(feature, &phase.to_string(), description)
+            .await
+    }
+
+    /// Run a task of a kind on a feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - Template variables added to those the kind prepares.
+    async fn run(
+        &self,
+        feature: &str,
+        kind: &str,
+        description: Option<&str>,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<Response> {
+        let mut run = self.start_run(feature, kind, description).await?;
+        let kind = run.kind.clone();
+        run.context.metadata.extend(metadata);
+        let prompt = self.render_prompt(
+            kind.template_name(),
+            &self.prompt_context(kind.as_ref(), run.phase, &run.state, &run.context.metadata)?,
+        )?;
+        let task = self.task(&mut run, kind.template_name(), prompt)?;
+
+        let result = self
+            .agent(&run)
+            .execute_task(&task)
+            .await
+            .map(|response| kind.post_process(response));
+        self.finish_run(&mut run, result.as_ref())?;
+
+        Ok(result?)
+    }
+
+    /// Start a run of a task kind on a feature.
+    ///
+    /// Checks the project's quotas, locks the feature and records the run in
+    /// its state. The implementation phase creates the feature's worktree if
+    /// needed, sparsely checked out if configured, with the project's
+    /// pre-commit hook. A working tree with uncommitted changes refuses the
+    /// run, see [`Self::with_force`] and [`Self::with_auto_stash`];
+    /// verification in a worktree leaves the project's changes alone. The
+    /// context is then built from the working tree, and its provenance saved
+    /// to the feature's `context/<run-id>.json`.
+    ///
+    /// Render the prompt of the run, then build its task with [`Self::task`]
+    /// and its agent with [`Self::agent`], and record its outcome with
+    /// [`Self::finish_run`].
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name.
+    /// * `kind` - Name of a registered task kind, e.g. `"implementation"`.
+    /// * `description` - Feature description, saved in the feature state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kind is not registered, a quota has been
+    /// reached, another run holds the feature, the working tree has
+    /// uncommitted changes, or the worktree or the context cannot be
+    /// prepared.
+    pub async fn start_run(
+        &self,
+        feature: &str,
+        kind: &str,
+        description: Option<&str>,
+    ) -> Result<Run> {
+        let kind = self
+            .kinds
+            .get(kind)
+            .ok_or_else(|| GbaError::UnknownTaskKind(kind.to_string()))?;
+        self.check_quota()?;
+        let phase = Phase::from_kind(kind.name());
+        let feature_id = feature::feature_id(feature);
+        let lock = FeatureLock::acquire(
+            self.feature_dir(&feature_id).join(lock::LOCK_FILE),
+            kind.name(),
+        )
+        .map_err(CoreError::from)?;
+        self.restore_feature(feature)?;
+        let state_path = self.state_path(&feature_id);
+        let mut state = FeatureState::load_or_new(&state_path, feature, &feature_id)
+            .map_err(CoreError::from)?;
+
+        if phase == Some(Phase::Implementation) {
+            self.ensure_worktree(&mut state)?;
+        }
+        let working_dir = self.working_dir(&state);
+        // Verification in the project directory always stashes, since its
+        // commands may have side effects on the working tree
+        let verifying = kind.name() == VERIFICATION_KIND;
+        let verifying_in_place = verifying && state.context.worktree.is_none();
+        let stash = if !verifying || verifying_in_place {
+            self.guard_working_tree(&working_dir, kind.name(), feature, verifying_in_place)?
+        } else {
+            None
+        };
+
+        let run_id = state.start_run();
+        info!("Starting {} of {} (run {})", kind.name(), feature, run_id);
+        state.task.kind = kind.name().to_string();
+        state.task.template = kind.template_name().to_string();
+        state.task.tags = ledger::merge_tags(&self.config.project.tags, &self.tags);
+        state.status.state = TaskStatus::InProgress;
+        let previous = state.status.current_phase.replace(kind.name().to_string());
+        if let Some(description) = description {
+            state.feature.description = Some(description.to_string());
+        }
+        state.context.head_commit = vcs::open(&working_dir, self.config.repository.vcs)
+            .info()
+            .inspect_err(|e| debug!("Failed to read HEAD of {}: {}", working_dir.display(), e))
+            .ok()
+            .and_then(|info| info.head);
+
+        let prepared = async {
+            let scratch_root = self.scratch_dir();
+            if let Err(e) = scratch::prune(&scratch_root, self.config.scratch.max_age_days) {
+                warn!("Failed to prune scratch directories: {}", e);
+            }
+            let scratch = ScratchDir::create(&scratch_root, &run_id).map_err(CoreError::from)?;
+            state.save(&state_path).map_err(CoreError::from)?;
+            let (context, report) = self
+                .build_context(kind.as_ref(), phase, &state, &working_dir)
+                .await?;
+            Ok::<_, GbaError>((scratch, context, report))
+        }
+        .await;
+        let (scratch, context, report) = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                if let Some(stash) = &stash
+                    && let Err(restore) = pop_stash(&working_dir, stash)
+                {
+                    warn!("{}", restore);
+                }
+                return Err(e);
+            }
+        };
+        let report_path = self
+            .feature_dir(&feature_id)
+            .join("context")
+            .join(format!("{run_id}.json"));
+        save_report(&report, &report_path);
+
+        let events = RunEvents::new(self.events.clone(), feature, &feature_id, &run_id);
+        events.emit(EventKind::Started {
+            kind: kind.name().to_string(),
+        });
+        if previous.as_deref() != Some(kind.name()) {
+            events.emit(EventKind::PhaseChanged {
+                from: previous,
+                to: kind.name().to_string(),
+            });
+        }
+
+        Ok(Run {
+            kind,
+            phase,
+            state,
+            tracker: StateTracker::new(&state_path),
+            state_path,
+            run_id,
+            working_dir,
+            context,
+            report,
+            report_path,
+            events,
+            scratch,
+            stash,
+            commit: None,
+            _lock: lock,
+        })
+    }
+
+    /// Preview a run of a task kind on a feature: what its agent would be
+    /// sent, without contacting it, changing the feature state or creating
+    /// its worktree.
+    ///
+    /// The context is built and fitted to the input budget like a run's.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - Feature name.
+    /// * `kind` - Name of a registered task kind.
+    /// * `template` - Name of the template the prompt was rendered from.
+    /// * `prompt` - Rendered prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kind is not registered, the feature state
+    /// cannot be read, or the context or the prompt cannot be built.
+    pub async fn preview(
+        &self,
+        feature: &str,
+        kind: &str,
+        template: &str,
+        prompt: String,
+    ) -> Result<RunPreview> {
+        let kind = self
+            .kinds
+            .get(kind)
+            .ok_or_else(|| GbaError::UnknownTaskKind(kind.to_string()))?;
+        let feature_id = feature::feature_id(feature);
+        let state = FeatureState::load_or_new(&self.state_path(&feature_id), feature, &feature_id)
+            .map_err(CoreError::from)?;
+        let working_dir = self.working_dir(&state);
+
+        let phase = Phase::from_kind(kind.name());
+        let (mut context, mut report) = self
+            .build_context(kind.as_ref(), phase, &state, &working_dir)
+            .await?;
+        self.fit_context(&mut context, &mut report, &prompt);
+        let task = self.template_task(template, prompt, context)?;
+        let preview = self
+            .base_agent(kind.as_ref(), &working_dir)
+            .preview(&task)?;
+
+        Ok(RunPreview {
+            working_dir,
+            task,
+            preview,
+            report,
+        })
+    }
+
+    /// Build the task of a run from a prompt rendered from a template, with
+    /// the template's system prompt, turns, output tokens, permission mode
+    /// and tools.
+    ///
+    /// The run's context is degraded to fit the model's input budget with
+    /// the prompt. The stage used, and the tokens each file takes in the
+    /// prompt, are saved in the run's context report.
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - Run of the task.
+    /// * `template` - Name of the template the prompt was rendered from.
+    /// * `prompt` - Rendered prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template is unknown.
+    pub fn task(&self, run: &mut Run, template: &str, prompt: String) -> Result<Task> {
+        self.fit_run(run, &prompt);
+        self.template_task(template, prompt, run.context.clone())
+    }
+
+    /// Create the agent of a run, saving its turns to the feature state as
+    /// it goes.
+    ///
+    /// Its tool calls are audited in the feature's `audit.jsonl`, its
+    /// transcript written to `transcripts/<run-id>.jsonl` and its events
+    /// published on the workspace's event bus.
+    #[must_use]
+    pub fn agent(&self, run: &Run) -> Agent {
+        self.run_agent(run, &run.run_id)
+            .with_state_tracker(run.tracker.clone())
+    }
+
+    /// Get the crates of a Cargo workspace a feature's worktree changes,
+    /// empty if it's no Cargo workspace or nothing changed yet.
+    fn touched_crates(&self, working_dir: &Path) -> Vec<String> {
+        let changed =
+            match diff::diff_worktree(working_dir, &self.main_branch(), &DiffOptions::new()) {
+                Ok(diff) => diff.files,
+                Err(e) => {
+                    warn!(
+                        "Failed to list the changes of {}: {}",
+                        working_dir.display(),
+                        e
+                    );
+                    return Vec::new();
+                }
+            };
+        match CrateMap::load(working_dir) {
+            Ok(Some(map)) => map.touched(&changed),
+            Ok(None) => Vec::new(),
+            Err(e) => {
+                warn!(
+                    "Failed to map the crates of {}: {}",
+                    working_dir.display(),
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Degrade the context of a run to fit the model's input budget with a
+    /// prompt, and save its report.
+    fn fit_run(&self, run: &mut Run, prompt: &str) {
+        self.fit_context(&mut run.context, &mut run.report, prompt);
+        save_report(&run.report, &run.report_path);
+    }
+
+    /// Degrade a context to fit the model's input budget with a prompt,
+    /// recording the stage used and the tokens each file takes in the prompt
+    /// in the context's report.
+    fn fit_context(&self, context: &mut Context, report: &mut ContextReport, prompt: &str) {
+        let config = &self.config.context;
+        if let Some(budget) = config.input_budget(&self.config.agent, &self.config.model_registry())
+        {
+            let metadata = serde_json::to_string(&context.metadata).unwrap_or_default();
+            let reserved = estimate_tokens(prompt) + estimate_tokens(&metadata);
+            report.stage =
+                context_budget::fit_context(context, reserved, budget as usize, &config.stages);
+        }
+        report.record_prompt(&context.files);
+    }
+
+    /// Create the agent of a run.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - Name of the transcript file, without extension.
+    fn run_agent(&self, run: &Run, transcript: &str) -> Agent {
+        let feature_dir = self.feature_dir(&run.state.feature.id);
+        let audit =
+            AuditLog::new(feature_dir.join("audit.jsonl")).with_run_id(Some(run.run_id.clone()));
+        self.base_agent(run.kind.as_ref(), &run.working_dir)
+            .with_transcript(
+                feature_dir
+                    .join("transcripts")
+                    .join(format!("{transcript}.jsonl")),
+            )
+            .with_audit_log(audit)
+            .with_sessions_dir(self.sessions_dir())
+            .with_scratch_dir(run.scratch.path())
+            .with_events(run.events.clone())
+            .with_metrics(self.metrics.clone())
+    }
+
+    /// Create an agent for a kind working in a directory, with the project's
+    /// settings.
+    fn base_agent(&self, kind: &dyn TaskKindPlugin, working_dir: &Path) -> Agent {
+        Agent::new(self.config.agent.clone())
+            .with_working_dir(working_dir)
+            .with_task_kind(kind)
+            .with_post_processing(self.config.post_process.pipeline(kind.name()))
+            .with_constraints(self.constraints(kind.name(), kind.template_name()))
+            .with_model_registry(self.config.model_registry())
+            .with_limits(self.config.limits.clone())
+            .with_layout_renderer(Arc::new(TemplateLayout(self.prompts.clone())))
+            .with_line_numbers(self.config.context.line_numbers)
+    }
+
+    /// Get the output constraints on the responses of a kind: those
+    /// configured for the kind, then those its template declares.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Name of the task kind.
+    /// * `template` - Name of the template rendering the prompt.
+    fn constraints(&self, kind: &str, template: &str) -> Vec<OutputConstraint> {
+        let mut constraints = self.config.constraints.for_kind(kind).to_vec();
+        if let Ok(config) = self.prompts.get_config(template) {
+            constraints.extend(config.constraints);
+        }
+        constraints
+    }
+
+    /// Finish a run: record its outcome in the feature state, its usage in
+    /// the cost ledger and its results in the repository index.
+    ///
+    /// A task stopped on a limit, or failing its output constraints, still
+    /// records the usage it spent, found among the sources of `result`'s
+    /// error. The planning phase saves the plan to the feature's `plan.md`,
+    /// and the implementation phase its response as the summary of the
+    /// feature. A completed implementation is committed in the feature's
+    /// worktree with `Gba-Feature-Id` and `Gba-Run-Id` trailers, see
+    /// [`Self::with_commit`]. The changes stashed for the run are then
+    /// restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - Run to finish.
+    /// * `result` - Response of the agent, or the error the run failed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature state or the plan cannot be saved,
+    /// committing fails, or the stashed changes of a completed run cannot
+    /// be restored; they are kept in the stash then. A failed run only logs
+    /// the latter.
+    pub fn finish_run<E>(
+        &self,
+        run: &mut Run,
+        result: std::result::Result<&Response, &E>,
+    ) -> Result<()>
+    where
+        E: std::error::Error + 'static,
+    {
+        let kind = run.kind.clone();
+        let state = &mut run.state;
+        run.tracker.sync(state);
+        match result {
+            Ok(response) => {
+                if response.session_id.is_some() {
+                    state.execution.session_id.clone_from(&response.session_id);
+                }
+                state.status.state = TaskStatus::Completed;
+                state.status.message = None;
+                state.timestamps.completed_at = Some(Utc::now());
+                let cost = &mut state.execution.cost;
+                cost.input_tokens += u64::from(response.usage.input_tokens);
+                cost.output_tokens += u64::from(response.usage.output_tokens);
+                cost.total_cost_usd += response.usage.total_cost_usd;
+                state.execution.violations = response.violations.clone();
+                state.execution.generation.clone_from(&response.generation);
+                for violation in &response.violations {
+                    warn!("Denied during run: {}", violation);
+                }
+
+                match run.phase {
+                    Some(Phase::Planning) => {
+                        plan::save(&self.feature_dir(&state.feature.id), &response.content)
+                            .map_err(CoreError::from)?;
+                    }
+                    // The pull request of the feature describes the
+                    // implementation with it
+                    Some(Phase::Implementation) => {
+                        let summary = response.content.trim().to_string();
+                        state.result.get_or_insert_with(Default::default).summary = Some(summary);
+                    }
+                    Some(Phase::Review) | None => {}
+                }
+                self.record_usage(state, kind.name(), &response.usage);
+            }
+            Err(e) => {
+                // The usage of a task stopped on a limit, or failing its output
+                // constraints, has been spent
+                if let Some(partial) = partial_response(e) {
+                    if partial.session_id.is_some() {
+                        state.execution.session_id.clone_from(&partial.session_id);
+                    }
+                    let cost = &mut state.execution.cost;
+                    cost.input_tokens += u64::from(partial.usage.input_tokens);
+                    cost.output_tokens += u64::from(partial.usage.output_tokens);
+                    cost.total_cost_usd += partial.usage.total_cost_usd;
+                    state.execution.violations = partial.violations.clone();
+                    state.execution.generation.clone_from(&partial.generation);
+                    self.record_usage(state, kind.name(), &partial.usage);
+                }
+                state.status.state = TaskStatus::Failed;
+                state.status.message = Some(e.to_string());
+            }
+        }
+        run.events.emit(EventKind::Finished {
+            kind: kind.name().to_string(),
+            success: result.is_ok(),
+            error: state.status.message.clone(),
+            usage: result.map(|r| r.usage.clone()).unwrap_or_default(),
+        });
+        state.save(&run.state_path).map_err(CoreError::from)?;
+        if let Err(e) = run
+            .scratch
+            .finish(result.is_ok(), self.config.scratch.retention)
+        {
+            warn!("Failed to remove scratch directory: {}", e);
+        }
+        if let Ok(response) = result {
+            self.remember(run, response);
+        }
+
+        // Commit before restoring the stash so the user's work stays out of it
+        let committed = if result.is_ok() && self.should_commit(run) {
+            self.commit_changes(run)
+        } else {
+            Ok(())
+        };
+        let restored = match run.stash.take() {
+            Some(stash) => pop_stash(&run.working_dir, &stash).inspect_err(|_| {
+                run.stash = Some(stash.clone());
+            }),
+            None => Ok(()),
+        };
+        self.persist_feature(&run.state.feature.name);
+
+        committed?;
+        match restored {
+            Err(e) if result.is_err() => {
+                warn!("{}", e);
+                Ok(())
+            }
+            restored => restored,
+        }
+    }
+
+    /// Save the report of a verification run to the feature's
+    /// `verification.json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the report cannot be saved.
+    pub fn save_verification(&self, run: &Run, report: &VerificationReport) -> Result<()> {
+        let path = self
+            .feature_dir(&run.state.feature.id)
+            .join("verification.json");
+        let json = serde_json::to_string_pretty(report).map_err(CoreError::from)?;
+        atomic::write(&path, json).map_err(CoreError::from)?;
+        debug!("Saved verification report to {}", path.display());
+        self.persist_feature(&run.state.feature.name);
+        Ok(())
+    }
+
+    /// Build the context of a run of a kind in a working directory.
+    ///
+    /// With `context.scopeToCrates`, the context of a feature's worktree is
+    /// scoped to the crates it changes. Implementation starts from the files
+    /// the feature's plan lists, with `context.preloadPlanFiles`. The kind
+    /// prepares the context, and the closest index entries are recalled into
+    /// it.
+    async fn build_context(
+        &self,
+        kind: &dyn TaskKindPlugin,
+        phase: Option<Phase>,
+        state: &FeatureState,
+        working_dir: &Path,
+    ) -> Result<(Context, ContextReport)> {
+        let branch = state
+            .context
+            .worktree
+            .as_ref()
+            .map_or_else(|| self.main_branch(), |worktree| worktree.branch.clone());
+        let mut builder = ContextBuilderConfig::default()
+            .with_vcs(self.config.repository.vcs)
+            .with_injection_policy(self.config.repository.prompt_injection)
+            .with_binary_placeholders(self.config.repository.binary_placeholders)
+            .with_exclude_vendored(self.config.repository.exclude_vendored)
+            .with_keep_vendored(self.config.repository.keep_vendored.clone());
+        if self.config.context.scope_to_crates && state.context.worktree.is_some() {
+            builder = builder.with_crates(self.touched_crates(working_dir));
+        }
+        if phase == Some(Phase::Implementation)
+            && self.config.context.preload_plan_files
+            && let Ok(Some(plan)) = plan::load(&self.feature_dir(&state.feature.id))
+        {
+            builder = builder.with_files(plan::context_files(working_dir, &plan));
+        }
+        let (mut context, report) =
+            build_context_with_report(working_dir, &branch, &builder).await?;
+        kind.prepare_context(&mut context)?;
+        self.recall(state, &mut context);
+        Ok((context, report))
+    }
+
+    /// Create the worktree of a feature if needed, and record it in its
+    /// state.
+    ///
+    /// Outside of a git repository, the feature is implemented in place.
+    fn ensure_worktree(&self, state: &mut FeatureState) -> Result<()> {
+        let vcs = vcs::open(&self.project_path, self.config.repository.vcs);
+        if !vcs.supports_worktrees() {
+            warn!(
+                "{} is not a git repository; implementing in place instead of a worktree",
+                self.project_path.display()
+            );
+            return Ok(());
+        }
+
+        let manager = self.worktree_manager();
+        let name = feature::worktree_name(&state.feature.name);
+        let directories = self.sparse_directories(&manager, state);
+        let worktree = manager
+            .ensure_sparse(&name, &directories)
+            .map_err(CoreError::from)?;
+        if let Some(hook) = manager
+            .install_pre_commit_hook(&worktree)
+            .map_err(CoreError::from)?
+        {
+            debug!("Installed pre-commit hook {}", hook.display());
+        }
+        let branch = worktree
+            .branch
+            .unwrap_or_else(|| manager.branch_name(&name));
+        info!(
+            "Using worktree {} on branch {}",
+            worktree.path.display(),
+            branch
+        );
+        state.context.worktree = Some(WorktreeInfo {
+            path: worktree.path,
+            branch,
+        });
+        Ok(())
+    }
+
+    /// Get the directories to sparsely check out in a feature's worktree.
+    ///
+    /// Combines the configured directories with those of the affected files
+    /// listed in the feature's plan. Returns an empty list, meaning a full
+    /// checkout, when sparse checkout is disabled.
+    fn sparse_directories(&self, manager: &WorktreeManager, state: &FeatureState) -> Vec<String> {
+        let sparse = &self.config.worktree.sparse_checkout;
+        if !sparse.enabled {
+            return Vec::new();
+        }
+
+        let feature = &state.feature.name;
+        let mut paths = sparse.directories_for(feature);
+        if sparse.from_plan
+            && let Ok(Some(plan)) = plan::load(&self.feature_dir(&state.feature.id))
+        {
+            paths.extend(plan::affected_files(&plan));
+        }
+
+        let directories = manager.sparse_directories(&paths);
+        if directories.is_empty() {
+            warn!("Sparse checkout enabled but no directories configured for {feature}");
+        }
+        directories
+    }
+
+    /// Check a working tree for uncommitted changes before a run.
+    ///
+    /// GBA's own files are ignored. A dirty tree, or one whose status can't
+    /// be read, refuses the run unless stashing or forced; stashing wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Working tree of the run.
+    /// * `kind` - Name of the task kind of the run.
+    /// * `feature` - Feature name.
+    /// * `stash` - Stash the changes even if auto-stashing is off.
+    ///
+    /// # Returns
+    ///
+    /// The stash entry to restore after the run, if changes were stashed.
+    fn guard_working_tree(
+        &self,
+        path: &Path,
+        kind: &str,
+        feature: &str,
+        stash: bool,
+    ) -> Result<Option<String>> {
+        let worktree_dir = self.config.worktree.directory.trim_start_matches("./");
+        let ignored = [".gba", worktree_dir];
+
+        let vcs = vcs::open(path, self.config.repository.vcs);
+        if vcs.kind() == VcsKind::Plain {
+            // Nothing to protect outside of version control
+            debug!("Skipping working tree check of {}", path.display());
+            return Ok(None);
+        }
+        let entries = match vcs.status() {
+            Ok(entries) => entries,
+            Err(e) if self.force => {
+                warn!("Skipping working tree check: {}", e);
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(GbaError::WorkingTreeCheckFailed {
+                    path: path.to_path_buf(),
+                    reason: e.to_string(),
+                });
+            }
+        };
+        let changes = entries
+            .iter()
+            .filter(|e| !ignored.iter().any(|x| e.path.starts_with(x)))
+            .count();
+        if changes == 0 {
+            return Ok(None);
+        }
+
+        if stash || self.auto_stash {
+            let message = format!("gba: auto-stash before {kind} of {feature}");
+            let stash = git::stash_push(path, &message, &ignored).map_err(CoreError::from)?;
+            if let Some(stash) = &stash {
+                info!(
+                    "Stashed {} uncommitted change(s) in {} as {}",
+                    changes,
+                    path.display(),
+                    stash
+                );
+            }
+            return Ok(stash);
+        }
+        if self.force {
+            warn!(
+                "Proceeding with {} uncommitted change(s) in {}",
+                changes,
+                path.display()
+            );
+            return Ok(None);
+        }
+        Err(GbaError::DirtyWorkingTree {
+            path: path.to_path_buf(),
+            count: changes,
+        })
+    }
+
+    /// Check whether the changes of a run should be committed.
+    fn should_commit(&self, run: &Run) -> bool {
+        run.phase == Some(Phase::Implementation)
+            && run.state.context.worktree.is_some()
+            && (self.commit || self.config.worktree.auto_commit)
+    }
+
+    /// Commit the changes in the worktree of a run's feature, with
+    /// `Gba-Feature-Id` and `Gba-Run-Id` trailers tracing them back to the
+    /// run.
+    fn commit_changes(&self, run: &mut Run) -> Result<()> {
+        let state = &mut run.state;
+        let Some(worktree) = &state.context.worktree else {
+            return Ok(());
+        };
+
+        let message = format!("gba: implement {}", state.feature.name);
+        let trailers = [
+            (FEATURE_ID_TRAILER, state.feature.id.as_str()),
+            (RUN_ID_TRAILER, run.run_id.as_str()),
+        ];
+        let commit =
+            git::commit_all(&worktree.path, &message, &trailers).map_err(CoreError::from)?;
+        let Some(sha) = commit else {
+            info!("No changes to commit");
+            return Ok(());
+        };
+        info!("Committed {} on {}", sha, worktree.branch);
+
+        state
+            .result
+            .get_or_insert_with(Default::default)
+            .commits_created += 1;
+        state.save(&run.state_path).map_err(CoreError::from)?;
+        run.commit = Some(sha);
+        Ok(())
+    }
+
+    /// Build the template context of a task.
+    ///
+    /// The metadata prepared by the task kind is available as template
+    /// variables.
+    fn prompt_context(
+        &self,
+        kind: &dyn TaskKindPlugin,
+        phase: Option<Phase>,
+        state: &FeatureState,
+        metadata: &HashMap<String, serde_json::Value>,
+    ) -> Result<PromptContext> {
+        let description = state.feature.description.clone().unwrap_or_default();
+        let user_message = if description.is_empty() {
+            format!("{} for feature: {}", kind.name(), state.feature.name)
+        } else {
+            description.clone()
+        };
+
+        let mut context = PromptContext::new(
+            self.project_path.display().to_string(),
+            self.main_branch(),
+            user_message,
+        );
+        context.feature_name = state.feature.name.clone();
+        context.feature_id = state.feature.id.clone();
+        context.feature_description = description;
+        context.task_kind = kind.name().to_string();
+        context.extra = serde_json::Value::Object(metadata.clone().into_iter().collect());
+        if let Some(worktree) = &state.context.worktree {
+            context.worktree_path = worktree.path.display().to_string();
+            context.worktree_branch = worktree.branch.clone();
+        }
+
+        match phase {
+            Some(Phase::Planning) | None => {}
+            Some(Phase::Implementation) => {
+                match plan::load(&self.feature_dir(&state.feature.id)).map_err(CoreError::from)? {
+                    Some(plan) => context.implementation_plan = plan,
+                    None => warn!("Implementing {} without a stored plan", state.feature.name),
+                }
+            }
+            Some(Phase::Review) => {
+                let options = DiffOptions::new().with_max_bytes(self.config.review.max_diff_bytes);
+                let diff = match &state.context.worktree {
+                    Some(worktree) => {
+                        diff::diff_worktree(&worktree.path, &self.main_branch(), &options)
+                    }
+                    // Without a worktree, review the feature branch
+                    None => {
+                        let name = feature::worktree_name(&state.feature.name);
+                        let branch = self.worktree_manager().branch_name(&name);
+                        diff::diff_refs(&self.project_path, &self.main_branch(), &branch, &options)
+                    }
+                };
+                context.diff_content = diff.map_err(CoreError::from)?.content;
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// Build the template context resuming the task of a feature from its
+    /// state.
+    fn resume_context(&self, kind: &dyn TaskKindPlugin, state: &FeatureState) -> PromptContext {
+        let mut context = PromptContext::for_resume(ResumeContext {
+            task_kind: kind.name().to_string(),
+            implementation_plan: plan::load(&self.feature_dir(&state.feature.id))
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            tools: kind.default_tools(),
+            ..ResumeContext::from(state)
+        });
+        // Without a worktree the feature is developed in the project itself
+        if state.context.worktree.is_none() {
+            context.worktree_path = self.project_path.display().to_string();
+            context.worktree_branch = self.main_branch();
+        }
+        context.repo_path = self.project_path.display().to_string();
+        context.main_branch = self.main_branch();
+        context
+    }
+
+    /// Render a prompt template.
+    fn render_prompt(&self, template: &str, context: &PromptContext) -> Result<String> {
+        Ok(self.prompts.get_prompt(template, context)?)
+    }
+
+    /// Build the task of a prompt rendered from a template, with the
+    /// template's system prompt, turns, output tokens, permission mode and
+    /// tools.
+    fn template_task(&self, template: &str, prompt: String, context: Context) -> Result<Task> {
+        let config = self.prompts.get_config(template)?;
+        Ok(Task::from_template(prompt, context, &config))
+    }
+
+    /// Append the usage of a run to the cost ledger.
+    ///
+    /// Failures are logged, since the run itself has completed.
+    fn record_usage(&self, state: &FeatureState, kind: &str, usage: &Usage) {
+        let entry = LedgerEntry::new(
+            &state.feature.id,
+            &state.feature.name,
+            kind,
+            &self.config.agent.model,
+            usage,
+        )
+        .with_run_id(state.execution.run_id.clone())
+        .with_tags(state.task.tags.clone());
+
+        if let Err(e) = self.ledger().append(&entry) {
+            warn!("Failed to record usage in the cost ledger: {}", e);
+        }
+    }
+
+    /// Check that a run can start within the project's quotas.
+    ///
+    /// Runs check their quotas when they start. With
+    /// [`Self::with_quota_override`], a reached quota is logged as a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a quota has been reached or the cost ledger
+    /// cannot be read.
+    pub fn check_quota(&self) -> Result<()> {
+        let entries = self.ledger().entries().map_err(CoreError::from)?;
+        match quota::check(&self.config.quota, &entries, Utc::now()) {
+            Ok(()) => Ok(()),
+            Err(e) if self.override_quota => {
+                warn!("Overriding project quota: {}", e);
+                Ok(())
+            }
+            Err(e) => Err(CoreError::from(e).into()),
+        }
+    }
+
+    /// Add the index entries closest to a run's description to its context.
+    ///
+    /// Does nothing unless the index is enabled. Failures are logged, since
+    /// the run can go on without them.
+    fn recall(&self, state: &FeatureState, context: &mut Context) {
+        if !self.config.index.enabled {
+            return;
+        }
+
+        let query = match &state.feature.description {
+            Some(description) => format!("{}\n{}", state.feature.name, description),
+            None => state.feature.name.clone(),
+        };
+        let embedder = HashingEmbedder::new(self.config.index.dimensions);
+        match RepoIndex::open(self.index_dir(), &embedder) {
+            Ok(index) => {
+                let count = index.recall_into(context, &query, self.config.index.max_results);
+                debug!("Recalled {} index entries", count);
+            }
+            Err(e) => warn!("Failed to open the repository index: {}", e),
+        }
+    }
+
+    /// Index the plan or review findings of a completed run, and the files
+    /// it looked at.
+    ///
+    /// Does nothing unless the index is enabled. Failures are logged, since
+    /// the run itself has completed.
+    fn remember(&self, run: &Run, response: &Response) {
+        if !self.config.index.enabled {
+            return;
+        }
+
+        let feature = &run.state.feature;
+        let mut entries = match run.phase {
+            Some(Phase::Planning) => {
+                vec![IndexEntry::plan(
+                    &feature.id,
+                    &feature.name,
+                    &response.content,
+                )]
+            }
+            Some(Phase::Review) => review::parse_findings("review", &response.content)
+                .iter()
+                .map(|finding| IndexEntry::finding(&feature.id, &feature.name, finding))
+                .collect(),
+            Some(Phase::Implementation) | None => Vec::new(),
+        };
+        if self.config.index.index_files {
+            entries.extend(run.context.files.iter().map(IndexEntry::file));
+        }
+        if entries.is_empty() {
+            return;
+        }
+
+        let embedder = HashingEmbedder::new(self.config.index.dimensions);
+        let result = RepoIndex::open(self.index_dir(), &embedder).and_then(|mut index| {
+            if run.phase == Some(Phase::Review) {
+                // Findings fixed since the last review are forgotten
+                index.remove_source(EntryKind::Finding, &feature.id);
+            }
+            for entry in entries {
+                index.upsert(entry);
+            }
+            index.save()
+        });
+        if let Err(e) = result {
+            warn!("Failed to update the repository index: {}", e);
+        }
+    }
+
+    /// Get the project's cost ledger.
+    fn ledger(&self) -> Ledger {
+        Ledger::new(self.project_path.join(".gba").join("ledger.jsonl"))
+    }
+
+    /// Get the directory of the runs' scratch directories.
+    fn scratch_dir(&self) -> PathBuf {
+        self.project_path.join(".gba").join(scratch::SCRATCH_DIR)
+    }
+
+    /// Get the directory the agents' sessions are recorded in.
+    fn sessions_dir(&self) -> PathBuf {
+        self.project_path.join(".gba").join("sessions")
+    }
+
+    /// Get the directory of the repository index.
+    fn index_dir(&self) -> PathBuf {
+        self.project_path.join(".gba").join("index")
+    }
+
+    /// Create a worktree manager for the project.
+    fn worktree_manager(&self) -> WorktreeManager {
+        WorktreeManager::new(&self.project_path, self.config.worktree.clone())
+            .with_base(self.main_branch())
+    }
+
+    /// Get the directory a feature is developed in: its worktree, or the
+    /// project directory without one.
+    fn working_dir(&self, state: &FeatureState) -> PathBuf {
+        state.context.worktree.as_ref().map_or_else(
+            || self.project_path.clone(),
+            |worktree| worktree.path.clone(),
+        )
+    }
+
+    /// Get the main branch of the repository.
+    fn main_branch(&self) -> String {
+        self.config.project.repository.main_branch.clone()
+    }
+
+    /// Restore a feature from the configured store if the project has no
+    /// state for it, e.g. on a fresh CI runner.
+    ///
+    /// Runs restore their feature when they start.
+    ///
+    /// Returns the number of files restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature cannot be downloaded.
+    pub fn restore_feature(&self, feature: &str) -> Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let feature_id = feature::feature_id(feature);
+        let count = store::restore(store.as_ref(), &feature_id, &self.feature_dir(&feature_id))
+            .map_err(CoreError::from)?;
+        if count > 0 {
+            info!(
+                "Restored feature {} from the {} store",
+                feature_id,
+                store.kind()
+            );
+        }
+        Ok(count)
+    }
+
+    /// Save a feature to the configured store. A failure doesn't fail the
+    /// run, whose state is saved in the project regardless.
+    ///
+    /// Runs save their feature when they finish.
+    pub fn persist_feature(&self, feature: &str) {
+        let feature_id = feature::feature_id(feature);
+        if let Some(store) = &self.store
+            && let Err(e) = store.upload(&feature_id, &self.feature_dir(&feature_id))
+        {
+            warn!(
+                "Failed to save feature {} to the {} store: {}",
+                feature_id,
+                store.kind(),
+                e
+            );
+        }
+    }
+
+    /// Get the directory of a feature's files.
+    fn feature_dir(&self, feature_id: &str) -> PathBuf {
+        self.project_path
+            .join(".gba")
+            .join("features")
+            .join(feature_id)
+    }
+
+    /// Get the state file path of a feature.
+    pub(crate) fn state_path(&self, feature_id: &str) -> PathBuf {
+        self.feature_dir(feature_id).join("state.yml")
+    }
+}
+
+/// Get the persona of a review template, e.g. `"security"` for
+/// `"review-security"`.
+fn persona_name(template: &str) -> &str {
+    template.strip_prefix("review-").unwrap_or(template)
+}
+
+/// Get the partial response of a task stopped on a limit, or failing its
+/// output constraints, from the core error among the sources of an error.
+fn partial_response<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a Response> {
+    std::iter::successors(Some(error), |error| error.source())
+        .find_map(|error| error.downcast_ref::<CoreError>()?.partial_response())
+}
+
+/// Restore changes stashed before a run.
+///
+/// Git refuses to restore changes over files the run modified as well; the
+/// stash entry is kept in that case so nothing is lost.
+fn pop_stash(path: &Path, stash: &str) -> Result<()> {
+    git::stash_pop(path, stash).map_err(|e| GbaError::StashRestoreFailed {
+        path: path.to_path_buf(),
+        stash: stash.to_string(),
+        reason: e.to_string(),
+    })?;
+    info!("Restored stashed changes in {}", path.display());
+    Ok(())
+}
+
+/// Save the context report of a run.
+///
+/// Failures are logged, since the report only documents the run.
+fn save_report(report: &ContextReport, path: &Path) {
+    match report.save(path) {
+        Ok(()) => debug!("Saved context report to {}", path.display()),
+        Err(e) => warn!("Failed to save context report: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gba_core::PermissionMode;
+
+    #[test]
+    fn test_persona_tasks_use_their_template_settings() {
+        let dir = std::env::temp_dir().join("gba-test-workspace-persona-tasks");
+        let _ = std::fs::remove_dir_all(&dir);
+        let templates = dir.join(".gba").join("templates");
+        std::fs::create_dir_all(&templates).unwrap();
+        let mut config = ProjectConfig::default();
+        config.prompts.directory = ".gba/templates".to_string();
+        config.prompts.use_bundled = true;
+        config
+            .save_to_file(&dir.join(".gba").join("config.yml"))
+            .unwrap();
+        std::fs::write(
+            templates.join("review-security.jinja2"),
+            "---\nsystemPrompt: \"You review for vulnerabilities.\"\npermissionMode: plan\n\
+             tools:\n  - Read\n---\nReview the change for vulnerabilities.\n",
+        )
+        .unwrap();
+
+        let workspace = Workspace::open(&dir).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut run = runtime
+            .block_on(workspace.start_run("add-auth", "review", None))
+            .unwrap();
+        let prompt_context =
+            PromptContext::new(dir.display().to_string(), "main", "Review add-auth");
+        let personas = ["review-security".to_string(), "review-style".to_string()];
+        let tasks = workspace
+            .persona_tasks(&mut run, &personas, &prompt_context)
+            .unwrap();
+
+        let preview = tasks[0].agent.preview(&tasks[0].task).unwrap();
+        assert_eq!(preview.permission_mode, PermissionMode::Plan);
+        assert_eq!(preview.system_prompt, "You review for vulnerabilities.");
+        assert!(
+            preview
+                .prompt
+                .contains("Review the change for vulnerabilities.")
+        );
+        // A persona whose template sets none keeps the agent's
+        let preview = tasks[1].agent.preview(&tasks[1].task).unwrap();
+        assert_eq!(
+            preview.permission_mode,
+            workspace.config().agent.permission_mode
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,815 @@
+// Integration tests for the GBA facade
+//
+// These tests verify that a workspace wires the project together.
+
+use gba::core::task_kind::StandardTaskKind;
+use gba::{GbaError, Phase, Workspace};
+use gba_core::ProjectConfig;
+use gba_core::context_budget::ContextStage;
+use gba_core::context_builder::ContextReport;
+use gba_core::index::MEMORY_KEY;
+use gba_core::state::{StateTracker, TaskStatus};
+use gba_core::task::{Response, Usage};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn project(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gba-test-facade-{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join(".gba")).unwrap();
+    let mut config = ProjectConfig::default();
+    config.prompts.use_bundled = true;
+    config
+        .save_to_file(&dir.join(".gba").join("config.yml"))
+        .unwrap();
+    dir
+}
+
+/// Make a project a git repository with an initial commit.
+fn git_init(dir: &Path) -> impl Fn(&[&str]) -> String + '_ {
+    let git = move |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    git(&["init", "-q", "-b", "main"]);
+    git(&["config", "user.name", "gba"]);
+    git(&["config", "user.email", "gba@example.com"]);
+    std::fs::write(dir.join("README.md"), "hello\n").unwrap();
+    git(&["add", "README.md"]);
+    git(&["commit", "-q", "-m", "init"]);
+    git
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_should_integration_workspace_open() {
+    let dir = project("open");
+
+    let workspace = Workspace::open(&dir).unwrap();
+    assert_eq!(workspace.project_path(), dir);
+    assert_eq!(workspace.config().project.repository.main_branch, "main");
+    for phase in [Phase::Planning, Phase::Implementation, Phase::Review] {
+        assert!(workspace.prompts().has_prompt(phase.template_name()));
+    }
+    assert!(workspace.feature_state("add-auth").unwrap().is_none());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_workspace_open_not_gba_project() {
+    let dir = std::env::temp_dir().join("gba-test-facade-not-a-project");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    assert!(matches!(
+        Workspace::open(&dir),
+        Err(GbaError::NotGbaProject(path)) if path == dir
+    ));
+}
+
+#[test]
+fn test_should_integration_workspace_task_kinds() {
+    let dir = project("task-kinds");
+
+    let workspace = Workspace::open(&dir)
+        .unwrap()
+        .with_task_kind(StandardTaskKind::new("security-audit", "review"));
+    let kinds = workspace.task_kinds();
+    assert!(kinds.get("planning").is_some());
+    assert_eq!(
+        kinds.get("security-audit").unwrap().template_name(),
+        "review"
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let result = runtime.block_on(workspace.run_kind("add-auth", "i18n-extraction", None));
+    assert!(matches!(result, Err(GbaError::UnknownTaskKind(kind)) if kind == "i18n-extraction"));
+    assert!(workspace.feature_state("add-auth").unwrap().is_none());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_workspace_review_personas() {
+    let dir = project("review-personas");
+
+    let workspace = Workspace::open(&dir).unwrap();
+    for persona in &workspace.config().review.personas {
+        assert!(workspace.prompts().has_prompt(persona), "{persona}");
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let result = runtime.block_on(workspace.review_with_personas("add-auth", &[]));
+    assert!(matches!(result, Err(GbaError::Config(_))));
+    assert!(workspace.feature_state("add-auth").unwrap().is_none());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_bundled_layout_matches_builtin() {
+    use gba_core::context_budget::{OVERVIEW_KEY, STAGE_KEY};
+    use gba_core::layout::PromptLayout;
+    use gba_core::task::File;
+
+    let dir = project("layout");
+    let workspace = Workspace::open(&dir).unwrap();
+
+    let mut context = gba_core::Context {
+        repository_path: dir.clone(),
+        branch: "main".to_string(),
+        ..gba_core::Context::default()
+    };
+    let mut contexts = vec![context.clone()];
+    context.files.push(File {
+        path: PathBuf::from("src/lib.rs"),
+        content: "pub fn login() {}".to_string(),
+        language: "rust".to_string(),
+    });
+    contexts.push(context.clone());
+    context
+        .metadata
+        .insert(STAGE_KEY.to_string(), "outlines".into());
+    context
+        .metadata
+        .insert(OVERVIEW_KEY.to_string(), "src/ (1 file)".into());
+    context.metadata.insert("attempt".to_string(), 2.into());
+    contexts.push(context);
+
+    for context in &contexts {
+        let layout = PromptLayout::new("Add a login page", context);
+        assert_eq!(
+            workspace.prompts().render_layout(&layout).unwrap(),
+            layout.render()
+        );
+        let layout = layout.with_scratch_dir(&dir.join(".gba/tmp/run-1"));
+        assert_eq!(
+            workspace.prompts().render_layout(&layout).unwrap(),
+            layout.render()
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_start_run_creates_worktree() {
+    let dir = project("start-run");
+    let _git = git_init(&dir);
+    let mut config = ProjectConfig::load_from_file(&dir.join(".gba").join("config.yml")).unwrap();
+    config.project.tags = vec!["team:payments".to_string()];
+    config
+        .save_to_file(&dir.join(".gba").join("config.yml"))
+        .unwrap();
+    let workspace = Workspace::open(&dir)
+        .unwrap()
+        .with_tags(vec!["sprint-42".to_string()]);
+    let runtime = runtime();
+
+    let run = runtime
+        .block_on(workspace.start_run("Add Auth", "planning", Some("Add login")))
+        .unwrap();
+    assert!(run.state().context.worktree.is_none());
+    assert!(run.state().context.head_commit.is_some());
+    assert_eq!(run.state().task.tags, ["team:payments", "sprint-42"]);
+    assert_eq!(run.working_dir(), dir);
+    // Another run of the feature waits for this one
+    assert!(
+        runtime
+            .block_on(workspace.start_run("Add Auth", "planning", None))
+            .is_err()
+    );
+    drop(run);
+
+    let run = runtime
+        .block_on(workspace.start_run("Add Auth", "implementation", None))
+        .unwrap();
+    let worktree = run.state().context.worktree.clone().unwrap();
+    let id = gba_core::feature::feature_id("Add Auth");
+    assert_eq!(worktree.branch, format!("gba/{id}-add-auth"));
+    assert_eq!(run.working_dir(), worktree.path);
+    assert!(worktree.path.is_dir());
+    let saved = workspace.feature_state("Add Auth").unwrap().unwrap();
+    assert_eq!(saved.task.kind, "implementation");
+    assert_eq!(saved.feature.description.as_deref(), Some("Add login"));
+    drop(run);
+
+    // Running again reuses the recorded worktree
+    let run = runtime
+        .block_on(workspace.start_run("Add Auth", "implementation", None))
+        .unwrap();
+    assert_eq!(run.working_dir(), worktree.path);
+    drop(run);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_finish_run_records_usage() {
+    let dir = project("finish-run");
+    let workspace = Workspace::open(&dir).unwrap();
+    let runtime = runtime();
+
+    let mut run = runtime
+        .block_on(workspace.start_run("Add Auth", "planning", None))
+        .unwrap();
+    let state_path = dir
+        .join(".gba")
+        .join("features")
+        .join(&run.state().feature.id)
+        .join("state.yml");
+    // Turns saved by the agent during the run are kept
+    StateTracker::new(&state_path).record_turn(Some("session-1"), Some("Read src/lib.rs"));
+    let response = Response {
+        content: "1. Add login".to_string(),
+        usage: Usage {
+            input_tokens: 100,
+            output_tokens: 20,
+            total_cost_usd: 0.5,
+        },
+        ..Response::default()
+    };
+    workspace
+        .finish_run::<GbaError>(&mut run, Ok(&response))
+        .unwrap();
+    drop(run);
+    let saved = workspace.feature_state("Add Auth").unwrap().unwrap();
+    assert_eq!(saved.status.state, TaskStatus::Completed);
+    assert_eq!(saved.execution.turns, 1);
+    assert_eq!(saved.execution.session_id.as_deref(), Some("session-1"));
+    assert_eq!(saved.execution.cost.input_tokens, 100);
+    assert!(saved.timestamps.completed_at.is_some());
+    let plan = dir
+        .join(".gba")
+        .join("features")
+        .join(&saved.feature.id)
+        .join("plan.md");
+    assert_eq!(
+        std::fs::read_to_string(plan).unwrap().trim(),
+        "1. Add login"
+    );
+
+    // A task stopped on its budget still adds the cost it spent
+    let mut run = runtime
+        .block_on(workspace.start_run("Add Auth", "planning", None))
+        .unwrap();
+    let error = GbaError::Core(gba_core::CoreError::BudgetExceeded {
+        spent: 2.0,
+        limit: 2.0,
+        partial: Box::new(Response {
+            usage: Usage {
+                input_tokens: 50,
+                output_tokens: 10,
+                total_cost_usd: 2.0,
+            },
+            ..Response::default()
+        }),
+    });
+    workspace.finish_run(&mut run, Err(&error)).unwrap();
+    drop(run);
+    let saved = workspace.feature_state("Add Auth").unwrap().unwrap();
+    assert_eq!(saved.status.state, TaskStatus::Failed);
+    assert!((saved.execution.cost.total_cost_usd - 2.5).abs() < f64::EPSILON);
+    assert!(saved.status.message.unwrap().contains("Budget exceeded"));
+    let ledger = std::fs::read_to_string(dir.join(".gba").join("ledger.jsonl")).unwrap();
+    assert_eq!(ledger.lines().count(), 2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_finish_run_commits_implementation() {
+    let dir = project("commit");
+    let git = git_init(&dir);
+    let runtime = runtime();
+    let response = Response {
+        content: "Added login\n".to_string(),
+        ..Response::default()
+    };
+
+    // Without committing, the changes are left in the worktree
+    let workspace = Workspace::open(&dir).unwrap();
+    let mut run = runtime
+        .block_on(workspace.start_run("add-auth", "implementation", None))
+        .unwrap();
+    std::fs::write(run.working_dir().join("auth.rs"), "fn login() {}\n").unwrap();
+    workspace
+        .finish_run::<GbaError>(&mut run, Ok(&response))
+        .unwrap();
+    assert!(run.commit().is_none());
+    drop(run);
+
+    // The leftover changes need forcing past the working tree guard
+    let workspace = Workspace::open(&dir)
+        .unwrap()
+        .with_commit(true)
+        .with_force(true);
+    let mut run = runtime
+        .block_on(workspace.start_run("add-auth", "implementation", None))
+        .unwrap();
+    workspace
+        .finish_run::<GbaError>(&mut run, Ok(&response))
+        .unwrap();
+    assert!(run.commit().is_some());
+    let state = run.state().clone();
+    let branch = &state.context.worktree.as_ref().unwrap().branch;
+    let trailers = git(&["log", "-1", "--format=%(trailers)", branch]);
+    assert!(trailers.contains(&format!("Gba-Feature-Id: {}", state.feature.id)));
+    assert!(trailers.contains(&format!("Gba-Run-Id: {}", run.run_id())));
+    let result = state.result.unwrap();
+    assert_eq!(result.commits_created, 1);
+    assert_eq!(result.summary.as_deref(), Some("Added login"));
+    drop(run);
+
+    // Nothing left to commit
+    let mut run = runtime
+        .block_on(workspace.start_run("add-auth", "implementation", None))
+        .unwrap();
+    workspace
+        .finish_run::<GbaError>(&mut run, Ok(&response))
+        .unwrap();
+    assert!(run.commit().is_none());
+    assert_eq!(run.state().result.as_ref().unwrap().commits_created, 1);
+    drop(run);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_start_run_guards_working_tree() {
+    let dir = project("guard");
+    let git = git_init(&dir);
+    let runtime = runtime();
+    let response = Response::default();
+    let start =
+        |workspace: &Workspace| runtime.block_on(workspace.start_run("add-auth", "planning", None));
+
+    // Untracked GBA files don't count as changes
+    let workspace = Workspace::open(&dir).unwrap();
+    drop(start(&workspace).unwrap());
+
+    std::fs::write(dir.join("README.md"), "work in progress\n").unwrap();
+    assert!(matches!(
+        start(&workspace),
+        Err(GbaError::DirtyWorkingTree { count: 1, .. })
+    ));
+    let run = start(&Workspace::open(&dir).unwrap().with_force(true)).unwrap();
+    assert!(run.stash().is_none());
+    drop(run);
+
+    // Auto-stashing wins over forcing
+    let workspace = Workspace::open(&dir)
+        .unwrap()
+        .with_force(true)
+        .with_auto_stash(true);
+    let mut run = start(&workspace).unwrap();
+    assert!(run.stash().is_some());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("README.md")).unwrap(),
+        "hello\n"
+    );
+    assert!(dir.join(".gba").join("config.yml").exists());
+    workspace
+        .finish_run::<GbaError>(&mut run, Ok(&response))
+        .unwrap();
+    assert!(run.stash().is_none());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("README.md")).unwrap(),
+        "work in progress\n"
+    );
+    drop(run);
+
+    // A run that touched the same file leaves the stash in place
+    let mut run = start(&workspace).unwrap();
+    std::fs::write(dir.join("README.md"), "test side effect\n").unwrap();
+    assert!(matches!(
+        workspace.finish_run::<GbaError>(&mut run, Ok(&response)),
+        Err(GbaError::StashRestoreFailed { .. })
+    ));
+    assert!(run.stash().is_some());
+    drop(run);
+    git(&["checkout", "--", "README.md"]);
+    git(&["stash", "pop", "-q"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("README.md")).unwrap(),
+        "work in progress\n"
+    );
+
+    // A status that can't be read refuses the run unless forced
+    std::fs::write(dir.join(".git").join("index"), "garbage").unwrap();
+    drop(start(&Workspace::open(&dir).unwrap().with_force(true)).unwrap());
+    assert!(matches!(
+        start(&Workspace::open(&dir).unwrap()),
+        Err(GbaError::WorkingTreeCheckFailed { .. })
+    ));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_run_fits_context_budget() {
+    let dir = project("context-budget");
+    let git = git_init(&dir);
+    let mut config = ProjectConfig::load_from_file(&dir.join(".gba").join("config.yml")).unwrap();
+    config.context.max_input_tokens = Some(2_000);
+    config
+        .save_to_file(&dir.join(".gba").join("config.yml"))
+        .unwrap();
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let body = format!("pub fn run() {{\n{}}}\n", "    let x = 1;\n".repeat(20));
+    std::fs::write(dir.join("src").join("lib.rs"), body.repeat(100)).unwrap();
+    git(&["add", "src"]);
+    git(&["commit", "-q", "-m", "Add lib"]);
+    let workspace = Workspace::open(&dir).unwrap();
+    let runtime = runtime();
+
+    let preview = runtime
+        .block_on(workspace.preview(
+            "add-auth",
+            "planning",
+            Phase::Planning.template_name(),
+            "Plan the feature.".to_string(),
+        ))
+        .unwrap();
+    assert_eq!(preview.report.stage, ContextStage::Outlines);
+
+    let mut run = runtime
+        .block_on(workspace.start_run("add-auth", "planning", None))
+        .unwrap();
+    assert_eq!(run.report().stage, ContextStage::Full);
+    let task = workspace
+        .task(
+            &mut run,
+            Phase::Planning.template_name(),
+            "Plan the feature.".to_string(),
+        )
+        .unwrap();
+    assert_eq!(run.report().stage, ContextStage::Outlines);
+    let file = task
+        .context
+        .files
+        .iter()
+        .find(|file| file.path.ends_with("lib.rs"))
+        .unwrap();
+    assert!(!file.content.contains("let x = 1;"));
+    assert!(file.content.contains("pub fn run() {"));
+    drop(run);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_run_saves_context_report() {
+    let dir = project("context-report");
+    let git = git_init(&dir);
+    let mut config = ProjectConfig::load_from_file(&dir.join(".gba").join("config.yml")).unwrap();
+    config.context.max_input_tokens = Some(2_000);
+    config
+        .save_to_file(&dir.join(".gba").join("config.yml"))
+        .unwrap();
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let body = format!("pub fn run() {{\n{}}}\n", "    let x = 1;\n".repeat(20));
+    std::fs::write(dir.join("src").join("lib.rs"), body.repeat(100)).unwrap();
+    git(&["add", "src"]);
+    git(&["commit", "-q", "-m", "Add lib"]);
+    let workspace = Workspace::open(&dir).unwrap();
+    let runtime = runtime();
+
+    let mut run = runtime
+        .block_on(workspace.start_run("add-auth", "implementation", None))
+        .unwrap();
+    let path = dir
+        .join(".gba")
+        .join("features")
+        .join(&run.state().feature.id)
+        .join("context")
+        .join(format!("{}.json", run.run_id()));
+    let load = || -> ContextReport {
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap()
+    };
+    let report = load();
+    assert_eq!(report.stage, ContextStage::Full);
+    assert!(
+        report
+            .included
+            .iter()
+            .any(|file| file.path.ends_with("lib.rs"))
+    );
+
+    // The stage the prompt was fitted at is saved with it
+    workspace
+        .task(
+            &mut run,
+            Phase::Implementation.template_name(),
+            "Implement the feature.".to_string(),
+        )
+        .unwrap();
+    assert_eq!(load().stage, ContextStage::Outlines);
+    drop(run);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_run_records_file_tokens() {
+    let dir = project("file-tokens");
+    let git = git_init(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let body = format!("pub fn run() {{\n{}}}\n", "    let x = 1;\n".repeat(20));
+    std::fs::write(dir.join("src").join("lib.rs"), body.repeat(100)).unwrap();
+    std::fs::write(dir.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+    git(&["add", "src"]);
+    git(&["commit", "-q", "-m", "Add lib"]);
+    let runtime = runtime();
+    let tokens = |max_input_tokens: Option<u32>| {
+        let mut config =
+            ProjectConfig::load_from_file(&dir.join(".gba").join("config.yml")).unwrap();
+        config.context.max_input_tokens = max_input_tokens;
+        config
+            .save_to_file(&dir.join(".gba").join("config.yml"))
+            .unwrap();
+        let workspace = Workspace::open(&dir).unwrap();
+        let mut run = runtime
+            .block_on(workspace.start_run("add-auth", "planning", None))
+            .unwrap();
+        workspace
+            .task(
+                &mut run,
+                Phase::Planning.template_name(),
+                "Plan the feature.".to_string(),
+            )
+            .unwrap();
+        let file = |name: &str| {
+            run.report()
+                .included
+                .iter()
+                .find(|file| file.path.ends_with(name))
+                .unwrap()
+                .clone()
+        };
+        (file("lib.rs"), file("main.rs"))
+    };
+
+    // In full, a file takes its content and path
+    let (lib, main) = tokens(None);
+    assert!(lib.prompt_tokens.unwrap() >= lib.tokens);
+    assert!(main.prompt_tokens.unwrap() < lib.prompt_tokens.unwrap());
+
+    // As outlines, only its declarations
+    let (lib, _) = tokens(Some(2_000));
+    assert!(lib.prompt_tokens.unwrap() < lib.tokens / 4);
+
+    // In an overview, nothing
+    let (lib, main) = tokens(Some(200));
+    assert_eq!(lib.prompt_tokens, Some(0));
+    assert_eq!(main.prompt_tokens, Some(0));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_run_recalls_indexed_plans() {
+    let dir = project("recall");
+    let mut config = ProjectConfig::load_from_file(&dir.join(".gba").join("config.yml")).unwrap();
+    config.index.enabled = true;
+    config.index.index_files = false;
+    config
+        .save_to_file(&dir.join(".gba").join("config.yml"))
+        .unwrap();
+    let workspace = Workspace::open(&dir).unwrap();
+    let runtime = runtime();
+
+    let mut run = runtime
+        .block_on(workspace.start_run("add-auth", "planning", Some("Log in with passwords")))
+        .unwrap();
+    assert!(!run.context().metadata.contains_key(MEMORY_KEY));
+    let response = Response {
+        content: "Hash the passwords with argon2".to_string(),
+        ..Response::default()
+    };
+    workspace
+        .finish_run::<GbaError>(&mut run, Ok(&response))
+        .unwrap();
+    drop(run);
+
+    // The plan of an earlier feature is recalled into a related one
+    let run = runtime
+        .block_on(workspace.start_run(
+            "reset-password",
+            "planning",
+            Some("Reset forgotten passwords"),
+        ))
+        .unwrap();
+    let memory = run.context().metadata[MEMORY_KEY].to_string();
+    assert!(memory.contains("Hash the passwords with argon2"));
+    drop(run);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_should_integration_run_scopes_context_to_crates() {
+    let dir = project("scope-crates");
+    let git = git_init(&dir);
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/auth\", \"crates/billing\"]\nresolver = \"2\"\n",
+    )
+    .unwrap();
+    for name in ["auth", "billing"] {
+        let crate_dir = dir.join("crates").join(name);
+        std::fs::create_dir_all(crate_dir.join("src")).unwrap();
+        std::fs::write(
+            crate_dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+        )
+        .unwrap();
+        std::fs::write(crate_dir.join("src").join("lib.rs"), "pub fn run() {}\n").unwrap();
+    }
+    git(&["add", "Cargo.toml", "crates"]);
+    git(&["commit", "-q", "-m", "Add crates"]);
+    let mut config = ProjectConfig::load_from_file(&dir.join(".gba").join("config.yml")).unwrap();
+    config.context.scope_to_crates = true;
+    config
+        .save_to_file(&dir.join(".gba").join("config.yml"))
+        .unwrap();
+    let workspace = Workspace::open(&dir).unwrap();
+    let runtime = runtime();
+    let files = |run: &gba::Run| {
+        run.context()
+            .files
+            .iter()
+            .map(|file| file.path.display().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Before the feature changes anything, the whole workspace is in context
+    let run = runtime
+        .block_on(workspace.start_run("add-auth", "implementation", None))
+        .unwrap();
+    let worktree = run.working_dir().to_path_buf();
+    assert!(files(&run).contains(&"crates/billing/src/lib.rs".to_string()));
+    drop(run);
+
+    std::fs::write(
+        worktree
+            .join("crates")
+            .join("auth")
+            .join("src")
+            .join("lib.rs"),
+        "pub fn login() {}\n",
+    )
+    .unwrap();
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&worktree)
+        .args(["commit", "-q", "-am", "Add login"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let run = runtime
+        .block_on(workspace.start_run("add-auth", "review", None))
+        .unwrap();
+    let files = files(&run);
+    assert!(files.contains(&"crates/auth/src/lib.rs".to_string()));
+    assert!(!files.contains(&"crates/billing/src/lib.rs".to_string()));
+    drop(run);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "slack")]
+#[test]
+fn test_should_integration_slack_bot_serve_metrics() {
+    use gba::slack::{SlackBot, SlackConfig};
+    use std::io::{Read, Write};
+
+    let dir = project("metrics");
+    let workspace = Workspace::open(&dir).unwrap();
+    workspace.metrics().retry("implementation");
+    let bot = SlackBot::new(workspace, SlackConfig::new("secret", "xoxb-token"));
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let listener = runtime
+        .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            let _ = axum::serve(listener, bot.router()).await;
+        });
+    });
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains("text/plain; version=0.0.4"));
+    assert!(response.contains("gba_task_retries_total{phase=\"implementation\"} 1"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "github")]
+#[test]
+fn test_should_integration_open_pull_request_pushes_to_existing() {
+    use gba_core::feature::feature_id;
+    use gba_core::lock::{FeatureLock, LOCK_FILE};
+    use gba_core::state::{FeatureState, ResultInfo, WorktreeInfo};
+    use std::process::Command;
+
+    let dir = project("existing-pr");
+    let remote = dir.with_extension("remote");
+    let _ = std::fs::remove_dir_all(&remote);
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q", "-b", "main"]);
+    git(&["config", "user.name", "gba"]);
+    git(&["config", "user.email", "gba@example.com"]);
+    git(&["config", "user.name", "gba"]);
+    git(&["config", "user.email", "gba@example.com"]);
+    git(&["init", "-q", "--bare", remote.to_str().unwrap()]);
+    git(&["remote", "add", "origin", remote.to_str().unwrap()]);
+    std::fs::write(dir.join("README.md"), "hello\n").unwrap();
+    git(&["add", "README.md"]);
+    git(&["commit", "-q", "-m", "init"]);
+    git(&["checkout", "-q", "-b", "gba/add-auth"]);
+
+    let id = feature_id("add-auth");
+    let feature_dir = dir.join(".gba").join("features").join(&id);
+    let mut state = FeatureState::new("add-auth", &id);
+    state.context.worktree = Some(WorktreeInfo {
+        path: dir.clone(),
+        branch: "gba/add-auth".to_string(),
+    });
+    state.result = Some(ResultInfo {
+        pr_link: Some("https://github.com/acme/app/pull/7".to_string()),
+        ..ResultInfo::default()
+    });
+    state.save(&feature_dir.join("state.yml")).unwrap();
+    std::fs::write(dir.join("README.md"), "hello again\n").unwrap();
+
+    let workspace = Workspace::open(&dir).unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    // The feature is locked while another run holds it
+    let lock = FeatureLock::acquire(feature_dir.join(LOCK_FILE), "implementation").unwrap();
+    assert!(
+        runtime
+            .block_on(workspace.open_pull_request("add-auth", "Done"))
+            .is_err()
+    );
+    drop(lock);
+
+    let url = runtime
+        .block_on(workspace.open_pull_request("add-auth", "Done"))
+        .unwrap();
+    assert_eq!(url, "https://github.com/acme/app/pull/7");
+    let pushed = Command::new("git")
+        .arg("-C")
+        .arg(&remote)
+        .args(["log", "-1", "--format=%s", "gba/add-auth"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&pushed.stdout).trim(),
+        "gba: implement add-auth"
+    );
+    let state = workspace.feature_state("add-auth").unwrap().unwrap();
+    assert_eq!(state.result.unwrap().commits_created, 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_dir_all(&remote);
+}